@@ -2,7 +2,10 @@ use std::{collections::HashMap, env, time::Duration};
 
 use ab_glyph::{FontRef, PxScale};
 use artbounty::{
-    api::{Api, ApiNative, ServerReqImg},
+    api::{
+        Api, ApiNative, ServerReqImg,
+        shared::{rating::PostRating, visibility::PostVisibility},
+    },
     path::{
         PATH_API_LOGIN, PATH_API_POST_ADD, PATH_API_REGISTER, PATH_API_SEND_EMAIL_INVITE,
         PATH_API_USER,
@@ -35,7 +38,10 @@ async fn main() {
         .subcommand(
             Command::new("user")
                 .about("manage users")
-                .arg(arg!(--"token" <TOKEN>).required(true))
+                .arg(
+                    arg!(--"token" <TOKEN> "personal access token with the post:write scope")
+                        .required(true),
+                )
                 .arg(arg!(--"count" <COUNT>).required(true))
                 .arg_required_else_help(true),
         );
@@ -83,6 +89,9 @@ async fn main() {
                         "title1",
                         "wow",
                         "",
+                        PostRating::default(),
+                        PostVisibility::Public,
+                        0,
                         // Vec::from([ServerReqImg {
                         //     path: path.to_string(),
                         //     data: img.clone(),
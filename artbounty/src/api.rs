@@ -18,7 +18,26 @@ use thiserror::Error;
 use tracing::{debug, error, trace};
 use wasm_bindgen_futures::spawn_local;
 
+use crate::api::shared::account::{
+    AccountDeletionErr, AccountDeletionMode, DataExportErr, UserAccountDeletion, UserDataExport,
+};
+use crate::api::shared::admin::{AdminErr, AdminUser, UserRole};
+use crate::api::shared::api_token::{API_TOKEN_PREFIX, ApiTokenErr, ApiTokenScope, UserApiToken};
+use crate::api::shared::audit::{AuditKind, UserAuditEvent};
+use crate::api::shared::collection::{CollectionErr, UserCollection};
+use crate::api::shared::conversation::{ConversationErr, UserConversation, UserMessage};
+use crate::api::shared::cursor::{Cursor, CursorErr, CursorRange};
+use crate::api::shared::notification::UserNotification;
 use crate::api::shared::post_comment::UserPostComment;
+use crate::api::shared::rating::{
+    PostRating, PostRatingErr, RatingPreference, create_rating_cookie,
+};
+use crate::api::shared::report::{ReportAction, ReportErr, ReportReason, ReportTarget, UserReport};
+use crate::api::shared::revision::{RevisionErr, RevisionTarget, UserRevision};
+use crate::api::shared::trash::{TrashErr, UserTrash};
+use crate::api::shared::user_block::{UserBlock, UserBlockErr, UserBlockKind};
+use crate::api::shared::visibility::{PostVisibility, PostVisibilityErr};
+use crate::api::shared::webhook::{UserWebhook, UserWebhookDelivery, WebhookErr, WebhookEvent};
 use crate::path::{
     link_settings_form_email_completed, link_settings_form_email_current_click,
     link_settings_form_email_current_send, link_settings_form_email_final_confirm,
@@ -38,16 +57,22 @@ pub mod app_state {
     use rand::distr::{Alphanumeric, SampleString};
     use surrealdb::types::{RecordId, ToSql};
     use tokio::sync::{Mutex, RwLock};
-    use tracing::trace;
+    use tracing::{info, trace};
 
     use crate::{
         api::{
             EmailChangeNewErr, EmailChangeStage, PasswordChangeStage, ServerErr, ServerTokenErr,
-            clock::Clock, settings::Settings,
+            backend::activitypub::ApRemoteActor,
+            cache::{AP_ACTOR_CACHE_TTL_NS, RELATED_POSTS_CACHE_TTL_NS, TimedCache},
+            clock::Clock,
+            live::{LIVE_EVENTS_CAPACITY, LiveHub},
+            settings::Settings,
+            shared::{admin::UserRole, report::ReportAction},
         },
         db::{self, DB404Err, DBSentEmailReason, DBUser, DbEngine},
         path::{
-            link_login_form_password_confirm, link_settings_form_email_current_confirm,
+            link_login_form_password_confirm, link_settings_account_deletion_confirm,
+            link_settings_data_exports, link_settings_form_email_current_confirm,
             link_settings_form_email_new_confirm, link_settings_form_password,
             link_settings_form_password_confirm,
         },
@@ -65,18 +90,33 @@ pub mod app_state {
         pub db: DbEngine,
         pub settings: Settings,
         pub clock: Clock,
+        pub related_posts_cache: TimedCache<String, Vec<db::DBUserPost>>,
+        pub ap_actor_cache: TimedCache<String, ApRemoteActor>,
+        pub live: LiveHub,
     }
 
     impl AppState {
         pub async fn new(time: u128) -> Self {
             let settings = Settings::new_from_file();
-            {
-                let path = std::path::Path::new(&settings.site.files_path);
+            for path in [&settings.site.files_path, &settings.site.exports_path] {
+                let path = std::path::Path::new(path);
                 if !path.exists() {
                     tokio::fs::create_dir_all(path).await.unwrap();
                 }
             }
             let db = db::new_local(time, &settings.db.path).await;
+            let admins = db
+                .update_user_role_by_email(
+                    time,
+                    settings.auth.admin_emails.clone(),
+                    UserRole::Admin.to_string(),
+                    crate::db::audit::DBAudit::default(),
+                )
+                .await
+                .unwrap();
+            for admin in admins {
+                info!("promoted {} to admin", admin.username);
+            }
             let f = move || async move { time_now_ns() };
             let clock = Clock::new(f);
 
@@ -84,14 +124,17 @@ pub mod app_state {
                 db,
                 settings,
                 clock,
+                related_posts_cache: TimedCache::new(RELATED_POSTS_CACHE_TTL_NS),
+                ap_actor_cache: TimedCache::new(AP_ACTOR_CACHE_TTL_NS),
+                live: LiveHub::new(LIVE_EVENTS_CAPACITY),
             }
         }
 
         pub async fn new_testng_with_settings(time: Arc<Mutex<u128>>, settings: Settings) -> Self {
             let db = db::new_mem(*time.lock().await).await;
 
-            {
-                let path = std::path::Path::new(&settings.site.files_path);
+            for path in [&settings.site.files_path, &settings.site.exports_path] {
+                let path = std::path::Path::new(path);
                 if !path.exists() {
                     tokio::fs::create_dir_all(path).await.unwrap();
                 }
@@ -110,6 +153,9 @@ pub mod app_state {
                 db,
                 settings,
                 clock,
+                related_posts_cache: TimedCache::new(RELATED_POSTS_CACHE_TTL_NS),
+                ap_actor_cache: TimedCache::new(AP_ACTOR_CACHE_TTL_NS),
+                live: LiveHub::new(LIVE_EVENTS_CAPACITY),
             }
         }
 
@@ -135,6 +181,10 @@ pub mod app_state {
             self.settings.site.files_path.clone()
         }
 
+        pub async fn get_exports_path(&self) -> String {
+            self.settings.site.exports_path.clone()
+        }
+
         pub async fn time(&self) -> u128 {
             self.clock.now().await
         }
@@ -275,6 +325,104 @@ pub mod app_state {
 
             Ok(())
         }
+
+        /// the moderator reason is internal, reporters only learn the outcome
+        pub async fn send_email_report_resolved(
+            &self,
+            time: u128,
+            to_email: impl Into<String>,
+            action: &ReportAction,
+        ) -> Result<(), ServerErr> {
+            let outcome = match action {
+                ReportAction::Dismiss => "no rules were broken",
+                ReportAction::HideContent => "the content was removed",
+                ReportAction::WarnUser => "the user was warned",
+                ReportAction::SuspendUser { .. } => "the user was suspended",
+            };
+            let body = format!("Your report was reviewed by a moderator, {outcome}.");
+            self.db
+                .add_sent_email(
+                    time,
+                    body.clone(),
+                    to_email,
+                    DBSentEmailReason::ReportResolved,
+                )
+                .await
+                .map_err(|_| ServerErr::DbErr)?;
+            trace!("{body}");
+
+            Ok(())
+        }
+
+        pub async fn send_email_account_deletion(
+            &self,
+            time: u128,
+            to_email: impl Into<String>,
+            confim_key: impl Into<String>,
+        ) -> Result<(), ServerErr> {
+            let link = link_settings_account_deletion_confirm(confim_key);
+            let link = format!("{}{}", &self.get_address().await, link);
+
+            self.db
+                .add_sent_email(
+                    time,
+                    link.clone(),
+                    to_email,
+                    DBSentEmailReason::ConfirmAccountDeletion,
+                )
+                .await
+                .map_err(|_| ServerErr::DbErr)?;
+            trace!("{link}");
+
+            Ok(())
+        }
+
+        pub async fn send_email_data_export_ready(
+            &self,
+            time: u128,
+            to_email: impl Into<String>,
+        ) -> Result<(), ServerErr> {
+            let link = link_settings_data_exports();
+            let link = format!("{}{}", &self.get_address().await, link);
+            let body = format!("Your data export is ready, download it from {link}");
+
+            self.db
+                .add_sent_email(
+                    time,
+                    body.clone(),
+                    to_email,
+                    DBSentEmailReason::DataExportReady,
+                )
+                .await
+                .map_err(|_| ServerErr::DbErr)?;
+            trace!("{body}");
+
+            Ok(())
+        }
+
+        pub async fn send_email_moderation_warning(
+            &self,
+            time: u128,
+            to_email: impl Into<String>,
+            reason: impl AsRef<str>,
+        ) -> Result<(), ServerErr> {
+            let body = format!(
+                "You received a warning from a moderator: {}",
+                reason.as_ref()
+            );
+            self.db
+                .add_sent_email(
+                    time,
+                    body.clone(),
+                    to_email,
+                    DBSentEmailReason::ModerationWarning,
+                )
+                .await
+                .map_err(|_| ServerErr::DbErr)?;
+            trace!("{body}");
+
+            Ok(())
+        }
     }
 }
 
@@ -288,12 +436,30 @@ pub mod settings {
         pub site: Site,
         pub auth: Auth,
         pub db: Db,
+        #[serde(default)]
+        pub robots: Robots,
+    }
+
+    /// what /robots.txt tells crawlers, on top of the private pages that are always disallowed
+    #[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+    pub struct Robots {
+        /// keeps every crawler out, for staging sites
+        #[serde(default)]
+        pub disallow_all: bool,
+        #[serde(default)]
+        pub disallow: Vec<String>,
+        /// appended as is, for rules aimed at specific crawlers
+        #[serde(default)]
+        pub extra: String,
     }
 
     #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
     pub struct Auth {
         pub secret: String,
         pub invite_exp_ns: u64,
+        /// accounts registered with these emails are promoted to admin
+        #[serde(default)]
+        pub admin_emails: Vec<String>,
     }
 
     #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
@@ -311,6 +477,14 @@ pub mod settings {
         pub address: String,
         pub address_host: String,
         pub files_path: String,
+        /// finished data exports, kept apart from files_path because that one is served publicly
+        pub exports_path: String,
+        /// lets webhooks and federation reach loopback and private addresses, only for local setups
+        #[serde(default)]
+        pub allow_private_outbound: bool,
+        /// reverse proxies whose x-forwarded-for is believed, everyone else is logged by their own address
+        #[serde(default)]
+        pub trusted_proxies: Vec<std::net::IpAddr>,
     }
 
     impl Settings {
@@ -331,10 +505,14 @@ pub mod settings {
                     address_host: "0.0.0.0:3000".to_string(),
                     // files_path: "/tmp".to_string(),
                     files_path: "../files".to_string(),
+                    exports_path: "../exports".to_string(),
+                    allow_private_outbound: true,
+                    trusted_proxies: Vec::new(),
                 },
                 auth: Auth {
                     secret: "secret".to_string(),
                     invite_exp_ns: invite_exp_ns as u64,
+                    admin_emails: Vec::new(),
                 },
                 db: Db {
                     path: "memory".to_string(),
@@ -343,6 +521,7 @@ pub mod settings {
                     tailwind_input_file: "style/tailwind.css".to_string(),
                     assets_dir: "assets".to_string(),
                 },
+                robots: Robots::default(),
             }
         }
     }
@@ -389,6 +568,82 @@ pub mod clock {
     }
 }
 
+#[cfg(feature = "ssr")]
+pub mod cache {
+
+    use std::{collections::HashMap, hash::Hash, sync::Arc};
+
+    use tokio::sync::RwLock;
+
+    pub const RELATED_POSTS_CACHE_TTL_NS: u128 = 60 * 1_000_000_000;
+    /// ranked related posts kept per post, the viewer's rating and blocks are filtered on top
+    pub const RELATED_POSTS_CACHE_LIMIT: usize = 200;
+    pub const AP_ACTOR_CACHE_TTL_NS: u128 = 60 * 60 * 1_000_000_000;
+
+    #[derive(Clone)]
+    pub struct TimedCache<K, V> {
+        ttl_ns: u128,
+        items: Arc<RwLock<HashMap<K, (u128, V)>>>,
+    }
+
+    impl<K: Eq + Hash, V: Clone> TimedCache<K, V> {
+        pub fn new(ttl_ns: u128) -> Self {
+            Self {
+                ttl_ns,
+                items: Arc::new(RwLock::new(HashMap::new())),
+            }
+        }
+
+        pub async fn get(&self, time: u128, key: &K) -> Option<V> {
+            let items = self.items.read().await;
+            items
+                .get(key)
+                .filter(|(created_at, _)| time.saturating_sub(*created_at) < self.ttl_ns)
+                .map(|(_, value)| value.clone())
+        }
+
+        pub async fn set(&self, time: u128, key: K, value: V) {
+            let mut items = self.items.write().await;
+            items.retain(|_, (created_at, _)| time.saturating_sub(*created_at) < self.ttl_ns);
+            items.insert(key, (time, value));
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub mod live {
+
+    use tokio::sync::broadcast;
+    use tracing::trace;
+
+    use crate::api::shared::live::LiveEvent;
+
+    pub const LIVE_EVENTS_CAPACITY: usize = 256;
+
+    #[derive(Clone)]
+    pub struct LiveHub {
+        sender: broadcast::Sender<LiveEvent>,
+    }
+
+    impl LiveHub {
+        pub fn new(capacity: usize) -> Self {
+            let (sender, _) = broadcast::channel(capacity);
+            Self { sender }
+        }
+
+        pub fn publish(&self, event: LiveEvent) {
+            // sending only fails when nobody is listening
+            if let Err(err) = self.sender.send(event) {
+                trace!("live event dropped, no subscribers: {:?}", err.0);
+            }
+        }
+
+        pub fn subscribe(&self) -> broadcast::Receiver<LiveEvent> {
+            self.sender.subscribe()
+        }
+    }
+}
+
 #[derive(
     Debug,
     Clone,
@@ -419,6 +674,24 @@ pub enum ServerReq {
     GetUser {
         username: String,
     },
+    Username {
+        username: String,
+    },
+    GetUserFollows {
+        username: String,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    },
+    AddUserBlock {
+        username: String,
+        kind: UserBlockKind,
+    },
+    GetUserBlocks {
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    },
     ConfirmToken {
         token: String,
     },
@@ -453,7 +726,7 @@ pub enum ServerReq {
         post_key: String,
         comment_key: Option<String>,
         limit: usize,
-        time_range: TimeRange,
+        cursor: CursorRange,
         order: Order,
         flatten: bool,
     },
@@ -469,24 +742,185 @@ pub enum ServerReq {
         post_key: String,
         new_title: String,
     },
+    EditPostRating {
+        post_key: String,
+        rating: PostRating,
+    },
+    EditPostVisibility {
+        post_key: String,
+        visibility: PostVisibility,
+        publish_at: u128,
+    },
+    EditPostEmbeddable {
+        post_key: String,
+        embeddable: bool,
+    },
+    UpdateRatingPreference {
+        max_rating: PostRating,
+        blur: bool,
+    },
     PostId {
         post_key: String,
     },
     GetPosts {
-        time: u128,
-        limit: u32,
+        cursor: CursorRange,
+        order: Order,
+        limit: usize,
+        tags: String,
+        username: String,
+    },
+    GetRelatedPosts {
+        post_key: String,
+        limit: usize,
+    },
+    GetFollowingPosts {
+        limit: usize,
+        cursor: CursorRange,
+        order: Order,
+    },
+    GetNotifications {
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    },
+    NotificationId {
+        notification_key: String,
+    },
+    AddCollection {
+        title: String,
+        private: bool,
+    },
+    UpdateCollection {
+        collection_key: String,
+        title: String,
+        private: bool,
+    },
+    CollectionId {
+        collection_key: String,
     },
-    GetUserPosts {
-        time: u128,
-        limit: u32,
+    GetUserCollections {
         username: String,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    },
+    CollectionItem {
+        collection_key: String,
+        post_key: String,
+    },
+    OrderCollectionItem {
+        collection_key: String,
+        post_key: String,
+        new_pos: usize,
+    },
+    GetCollectionPosts {
+        collection_key: String,
+        limit: usize,
+        offset: usize,
     },
-    GetPosts2 {
-        time: TimeRange,
+    GetConversations {
+        limit: usize,
+        time_range: TimeRange,
         order: Order,
+    },
+    AdminGetUsers {
+        query: String,
         limit: usize,
-        tags: String,
+        time_range: TimeRange,
+        order: Order,
+    },
+    AdminGetUserPosts {
         username: String,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    },
+    AdminUpdateUserStorage {
+        user_key: String,
+        max_storage_bytes: u64,
+        max_storage_per_file_bytes: u64,
+    },
+    AdminUpdateUserRole {
+        user_key: String,
+        role: UserRole,
+    },
+    AdminUpdateUserBan {
+        user_key: String,
+        banned: bool,
+    },
+    AdminUpdatePostShow {
+        post_key: String,
+        show: bool,
+    },
+    AddReport {
+        target: ReportTarget,
+        reason: ReportReason,
+        text: String,
+    },
+    AdminGetReports {
+        limit: usize,
+        offset: usize,
+    },
+    AdminResolveReport {
+        report_key: String,
+        action: ReportAction,
+        reason: String,
+    },
+    GetAuditEvents {
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    },
+    GetRevisions {
+        target: RevisionTarget,
+        limit: usize,
+    },
+    AdminGetAuditEvents {
+        username: Option<String>,
+        kind: Option<AuditKind>,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    },
+    ConversationId {
+        conversation_key: String,
+    },
+    AddMessage {
+        conversation_key: String,
+        text: String,
+    },
+    GetMessages {
+        conversation_key: String,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    },
+    RequestAccountDeletion {
+        password: String,
+        mode: AccountDeletionMode,
+    },
+    AddApiToken {
+        name: String,
+        scopes: Vec<ApiTokenScope>,
+        /// 0 for a token that never expires
+        expires: u128,
+    },
+    DeleteApiToken {
+        token_key: String,
+    },
+    AddWebhook {
+        url: String,
+        events: Vec<WebhookEvent>,
+        /// staff only, gets events from the whole site
+        admin: bool,
+    },
+    DeleteWebhook {
+        webhook_key: String,
+    },
+    GetWebhookDeliveries {
+        webhook_key: String,
+        limit: usize,
+        time_range: TimeRange,
     },
     // AddPostFile {
     //     title: String,
@@ -499,6 +933,9 @@ pub enum ServerReq {
         title: String,
         description: String,
         tags: String,
+        rating: PostRating,
+        visibility: PostVisibility,
+        publish_at: u128,
         // files: Vec<ServerReqImg>,
     },
     None,
@@ -560,10 +997,20 @@ where
 
     async fn from_request(req: axum::extract::Request, state: &S) -> Result<Self, Self::Rejection> {
         let headers = format!("{:#?}", req.headers());
-        let multipart = axum::extract::Multipart::from_request(req, state)
-            .await
-            .map_err(|err| ServerDesErr::ServerDesGettingMultipartErr(err.to_string()))?;
-        recv(headers, multipart).await
+        match WireFormat::from_content_type(req.headers()) {
+            WireFormat::Json => {
+                let bytes = axum::body::Bytes::from_request(req, state)
+                    .await
+                    .map_err(|err| ServerDesErr::ServerDesGettingBodyErr(err.to_string()))?;
+                recv_json(headers, bytes)
+            }
+            WireFormat::Rkyv => {
+                let multipart = axum::extract::Multipart::from_request(req, state)
+                    .await
+                    .map_err(|err| ServerDesErr::ServerDesGettingMultipartErr(err.to_string()))?;
+                recv(headers, multipart).await
+            }
+        }
     }
 }
 
@@ -598,6 +1045,9 @@ pub enum ServerRes {
         token: String,
     },
     DeleteAuthCookie,
+    SetRatingCookie {
+        max_rating: PostRating,
+    },
     Condition(bool),
     User {
         username: String,
@@ -606,16 +1056,63 @@ pub enum ServerRes {
         key: String,
         username: String,
         email: String,
+        role: UserRole,
     },
     InviteToken {
         email: String,
         created_at: u128,
         exp: u128,
     },
-    Comments(Vec<UserPostComment>),
+    /// next and prev are only valid with the same filters and order
+    CommentPage {
+        comments: Vec<UserPostComment>,
+        next: Option<Cursor>,
+        prev: Option<Cursor>,
+    },
     Comment(UserPostComment),
     Posts(Vec<UserPost>),
+    /// next and prev are only valid with the same filters and order
+    PostPage {
+        posts: Vec<UserPost>,
+        next: Option<Cursor>,
+        prev: Option<Cursor>,
+    },
     Post(UserPost),
+    UserFollows(Vec<UserFollow>),
+    UserFollowStats {
+        followers: u64,
+        following: u64,
+    },
+    UserBlocks(Vec<UserBlock>),
+    UserBlockKind(Option<UserBlockKind>),
+    RatingPreference(RatingPreference),
+    Notifications(Vec<UserNotification>),
+    Collection(UserCollection),
+    Collections(Vec<UserCollection>),
+    CollectionKeys(Vec<String>),
+    AdminUser(AdminUser),
+    AdminUsers(Vec<AdminUser>),
+    Reports(Vec<UserReport>),
+    AuditEvents(Vec<UserAuditEvent>),
+    Revisions(Vec<UserRevision>),
+    Trash(UserTrash),
+    AccountDeletion(Option<UserAccountDeletion>),
+    DataExport(UserDataExport),
+    DataExports(Vec<UserDataExport>),
+    ApiTokens(Vec<UserApiToken>),
+    /// the secret is only ever returned here
+    ApiToken {
+        token: UserApiToken,
+        secret: String,
+    },
+    Webhook(UserWebhook),
+    Webhooks(Vec<UserWebhook>),
+    WebhookDeliveries(Vec<UserWebhookDelivery>),
+    Conversation(UserConversation),
+    Conversations(Vec<UserConversation>),
+    Message(UserMessage),
+    Messages(Vec<UserMessage>),
+    Count(u64),
     EmailChangeStage(EmailChangeStage),
     PasswordChangeStage(PasswordChangeStage),
     Ok,
@@ -654,6 +1151,51 @@ pub enum ServerErr {
     #[error("add post err {0}")]
     PostLikeErr(#[from] PostLikeErr),
 
+    #[error("user follow err {0}")]
+    UserFollowErr(#[from] UserFollowErr),
+
+    #[error("user block err {0}")]
+    UserBlockErr(#[from] UserBlockErr),
+
+    #[error("post rating err {0}")]
+    PostRatingErr(#[from] PostRatingErr),
+
+    #[error("post visibility err {0}")]
+    PostVisibilityErr(#[from] PostVisibilityErr),
+
+    #[error("collection err {0}")]
+    CollectionErr(#[from] CollectionErr),
+
+    #[error("conversation err {0}")]
+    ConversationErr(#[from] ConversationErr),
+
+    #[error("admin err {0}")]
+    AdminErr(#[from] AdminErr),
+
+    #[error("report err {0}")]
+    ReportErr(#[from] ReportErr),
+
+    #[error("revision err {0}")]
+    RevisionErr(#[from] RevisionErr),
+
+    #[error("trash err {0}")]
+    TrashErr(#[from] TrashErr),
+
+    #[error("account deletion err {0}")]
+    AccountDeletionErr(#[from] AccountDeletionErr),
+
+    #[error("data export err {0}")]
+    DataExportErr(#[from] DataExportErr),
+
+    #[error("api token err {0}")]
+    ApiTokenErr(#[from] ApiTokenErr),
+
+    #[error("webhook err {0}")]
+    WebhookErr(#[from] WebhookErr),
+
+    #[error("cursor err {0}")]
+    CursorErr(#[from] CursorErr),
+
     #[error("add post err {0}")]
     AddPostErr(#[from] ServerAddPostErr),
 
@@ -754,6 +1296,12 @@ pub enum ServerDesErr {
 
     #[error("failed to get multipart from request {0}")]
     ServerDesGettingMultipartErr(String),
+
+    #[error("failed to get body from request {0}")]
+    ServerDesGettingBodyErr(String),
+
+    #[error("failed to run json deserialization {0}")]
+    ServerDesJsonErr(String),
 }
 
 #[derive(
@@ -868,6 +1416,9 @@ pub enum AddPostCommentErr {
 
     #[error("invalid text {0}")]
     InvalidText(String),
+
+    #[error("you can't comment here")]
+    Blocked,
 }
 
 #[derive(
@@ -903,6 +1454,24 @@ pub enum ServerAuthErr {
 
     #[error("unauthorized invalid cookie")]
     ServerUnauthorizedInvalidCookie,
+
+    #[error("account is banned")]
+    ServerBanned,
+
+    #[error("account is suspended until {0}")]
+    ServerSuspended(u128),
+
+    #[error("invalid api token")]
+    ServerApiTokenInvalid,
+
+    #[error("api token expired")]
+    ServerApiTokenExpired,
+
+    #[error("api token is missing the \"{0}\" scope")]
+    ServerApiTokenScope(String),
+
+    #[error("this endpoint needs a login session, api tokens are not accepted")]
+    ServerApiTokenNotAccepted,
 }
 
 #[derive(
@@ -1106,6 +1675,9 @@ pub enum PostLikeErr {
 
     #[error("post \"{0}\" not found")]
     PostNotFound(String),
+
+    #[error("you can't like this post")]
+    Blocked,
 }
 
 #[derive(
@@ -1119,12 +1691,34 @@ pub enum PostLikeErr {
     rkyv::Serialize,
     rkyv::Deserialize,
 )]
-pub enum ChangePasswordErr {
-    #[error("invalid password {0}")]
-    InvalidPassword(String),
+pub enum UserFollowErr {
+    #[error("user \"{0}\" was already followed")]
+    UserAlreadyFollowed(String),
 
-    #[error("confirm key is invalid/expired")]
-    NotFound,
+    #[error("user \"{0}\" not found")]
+    UserNotFound(String),
+
+    #[error("cant follow yourself")]
+    FollowSelf,
+}
+
+#[derive(
+    Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum ChangePasswordErr {
+    #[error("invalid password {0}")]
+    InvalidPassword(String),
+
+    #[error("confirm key is invalid/expired")]
+    NotFound,
 }
 
 #[derive(
@@ -1241,6 +1835,31 @@ impl From<crate::db::DBUser> for User {
     }
 }
 
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserFollow {
+    pub user: User,
+    pub created_at: u128,
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::user_follow::DBUserFollowUser> for UserFollow {
+    fn from(value: crate::db::user_follow::DBUserFollowUser) -> Self {
+        Self {
+            user: value.user.into(),
+            created_at: value.created_at,
+        }
+    }
+}
+
 #[derive(
     Debug,
     Clone,
@@ -1258,8 +1877,20 @@ pub struct UserPost {
     pub title: String,
     pub description: String,
     pub tags: String,
+    pub rating: PostRating,
+    /// set once a moderator overrides the rating, the author can't change it anymore
+    pub rating_locked: bool,
+    pub visibility: PostVisibility,
+    /// when a scheduled post goes public
+    pub publish_at: u128,
+    /// whether other sites may show the post through /embed and /oembed
+    pub embeddable: bool,
     pub favorites: u64,
     pub file: Vec<UserPostFile>,
+    /// last time the title, description or tags changed, 0 if never
+    pub edited_at: u128,
+    /// 0 unless the post is in its authors trash
+    pub deleted_at: u128,
     pub modified_at: u128,
     pub created_at: u128,
 }
@@ -1473,7 +2104,14 @@ impl From<crate::db::DBUserPost> for UserPost {
             show: value.show,
             description: value.description,
             tags: value.tags,
+            rating: PostRating::from_db(&value.rating),
+            rating_locked: value.rating_locked,
+            visibility: PostVisibility::from_db(&value.visibility),
+            publish_at: value.publish_at,
+            embeddable: value.embeddable,
             favorites: value.favorites,
+            edited_at: value.edited_at,
+            deleted_at: value.deleted_at,
             modified_at: value.modified_at,
             created_at: value.created_at,
         }
@@ -1585,6 +2223,17 @@ pub fn auth_token_get(
         .inspect(|v| trace!("extract auth value cut {v:?}"))
 }
 
+/// personal access tokens come in the authorization header, sessions keep using the cookie
+pub fn api_token_get(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix(COOKIE_PREFIX))
+        .map(|v| v.trim())
+        .filter(|v| v.starts_with(API_TOKEN_PREFIX))
+        .map(|v| v.to_string())
+}
+
 fn extract_auth_token_plain(input: impl AsRef<str>) -> Option<String> {
     let input = input.as_ref();
     let input_len = input.len();
@@ -1725,6 +2374,14 @@ pub fn verify_password<T: AsRef<[u8]>, S2: AsRef<str>>(
     PasswordHash::new(hash).and_then(|hash| Argon2::default().verify_password(password, &hash))
 }
 
+/// tokens are long and random so a plain digest is enough, unlike passwords
+#[cfg(feature = "ssr")]
+pub fn hash_api_token(secret: impl AsRef<str>) -> String {
+    use sha2::{Digest, Sha256};
+
+    format!("{:x}", Sha256::digest(secret.as_ref().as_bytes()))
+}
+
 #[cfg(feature = "ssr")]
 pub fn hash_password<S: Into<String>>(password: S) -> Result<String, argon2::password_hash::Error> {
     use argon2::{
@@ -1819,7 +2476,7 @@ pub trait Api {
         post_key: impl Into<String>,
         comment_key: Option<String>,
         limit: usize,
-        time_range: TimeRange,
+        cursor: CursorRange,
         order: Order,
         flatten: bool,
     ) -> ApiReq {
@@ -1829,7 +2486,7 @@ pub trait Api {
                 post_key: post_key.into(),
                 comment_key,
                 limit,
-                time_range,
+                cursor,
                 order,
                 flatten,
             },
@@ -1877,280 +2534,946 @@ pub trait Api {
 
     //
 
-    fn login(&self, email: impl Into<String>, password: impl Into<String>) -> ApiReq {
-        let email = email.into();
-        let password = password.into();
-        let builder = self.provide_builder(crate::path::PATH_API_LOGIN);
-        let server_req = ServerReq::Login { email, password };
-        let result_signal = self.provide_signal_result();
-        let busy_signal = self.provide_signal_busy();
-        ApiReq {
-            builder,
-            server_req,
-            result: result_signal,
-            busy: busy_signal,
-        }
+    // notification
+    fn get_notifications(&self, limit: usize, time_range: TimeRange, order: Order) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_NOTIFICATIONS_GET,
+            ServerReq::GetNotifications {
+                limit,
+                time_range,
+                order,
+            },
+        )
     }
 
-    fn logout(&self) -> ApiReq {
-        let builder = self.provide_builder(crate::path::PATH_API_LOGOUT);
-        let server_req = ServerReq::None;
-        let result_signal = self.provide_signal_result();
-        let busy_signal = self.provide_signal_busy();
-        ApiReq {
-            builder,
-            server_req,
-            result: result_signal,
-            busy: busy_signal,
-        }
+    fn get_notification_unread_count(&self) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_NOTIFICATION_UNREAD_COUNT,
+            ServerReq::None,
+        )
     }
 
-    fn get_user(&self, username: impl Into<String>) -> ApiReq {
-        let username = username.into();
-        let builder = self.provide_builder(crate::path::PATH_API_USER);
-        let server_req = ServerReq::GetUser { username };
-        let result_signal = self.provide_signal_result();
-        let busy_signal = self.provide_signal_busy();
-        ApiReq {
-            builder,
-            server_req,
-            result: result_signal,
-            busy: busy_signal,
-        }
+    fn read_notification(&self, notification_key: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_NOTIFICATION_READ,
+            ServerReq::NotificationId {
+                notification_key: notification_key.into(),
+            },
+        )
     }
 
-    fn get_my_acc(&self) -> ApiReq {
-        let builder = self.provide_builder(crate::path::PATH_API_ACC);
-        let server_req = ServerReq::None;
-        let result_signal = self.provide_signal_result();
-        let busy_signal = self.provide_signal_busy();
-        ApiReq {
-            builder,
-            server_req,
-            result: result_signal,
-            busy: busy_signal,
-        }
+    fn read_notification_all(&self) -> ApiReq {
+        self.into_req(crate::path::PATH_API_NOTIFICATION_READ_ALL, ServerReq::None)
     }
 
-    fn decode_invite(&self, token: impl Into<String>) -> ApiReq {
-        let token = token.into();
-        let builder = self.provide_builder(crate::path::PATH_API_INVITE_DECODE);
-        let server_req = ServerReq::ConfirmToken { token };
-        let result_signal = self.provide_signal_result();
-        let busy_signal = self.provide_signal_busy();
-        ApiReq {
-            builder,
-            server_req,
-            result: result_signal,
-            busy: busy_signal,
-        }
+    // collection
+    fn add_collection(&self, title: impl Into<String>, private: bool) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_COLLECTION_ADD,
+            ServerReq::AddCollection {
+                title: title.into(),
+                private,
+            },
+        )
     }
 
-    fn get_post(&self, post_id: impl Into<String>) -> ApiReq {
-        let builder = self.provide_builder(crate::path::PATH_API_POST_GET);
-        let server_req = ServerReq::PostId {
-            post_key: post_id.into(),
-        };
-        let result_signal = self.provide_signal_result();
-        let busy_signal = self.provide_signal_busy();
-        ApiReq {
-            builder,
-            server_req,
-            result: result_signal,
-            busy: busy_signal,
-        }
+    fn get_collection(&self, collection_key: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_COLLECTION_GET,
+            ServerReq::CollectionId {
+                collection_key: collection_key.into(),
+            },
+        )
     }
 
-    fn change_username(
+    fn update_collection(
         &self,
-        password: impl Into<String>,
-        new_username: impl Into<String>,
+        collection_key: impl Into<String>,
+        title: impl Into<String>,
+        private: bool,
     ) -> ApiReq {
         self.into_req(
-            crate::path::PATH_API_CHANGE_USERNAME,
-            ServerReq::ChangeUsername {
-                username: new_username.into(),
-                password: password.into(),
+            crate::path::PATH_API_COLLECTION_UPDATE,
+            ServerReq::UpdateCollection {
+                collection_key: collection_key.into(),
+                title: title.into(),
+                private,
             },
         )
     }
 
-    fn get_user_posts_newer(&self, time: u128, limit: u32, username: impl Into<String>) -> ApiReq {
+    fn delete_collection(&self, collection_key: impl Into<String>) -> ApiReq {
         self.into_req(
-            crate::path::PATH_API_USER_POST_GET_NEWER,
-            ServerReq::GetUserPosts {
-                time,
-                limit,
-                username: username.into(),
+            crate::path::PATH_API_COLLECTION_DELETE,
+            ServerReq::CollectionId {
+                collection_key: collection_key.into(),
             },
         )
     }
 
-    fn get_user_posts_older(&self, time: u128, limit: u32, username: impl Into<String>) -> ApiReq {
+    fn get_user_collections(
+        &self,
+        username: impl Into<String>,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> ApiReq {
         self.into_req(
-            crate::path::PATH_API_USER_POST_GET_OLDER,
-            ServerReq::GetUserPosts {
-                time,
-                limit,
+            crate::path::PATH_API_COLLECTIONS_GET,
+            ServerReq::GetUserCollections {
                 username: username.into(),
+                limit,
+                time_range,
+                order,
             },
         )
     }
 
-    fn get_user_posts_older_or_equal(
+    fn add_collection_item(
         &self,
-        time: u128,
-        limit: u32,
-        username: impl Into<String>,
+        collection_key: impl Into<String>,
+        post_key: impl Into<String>,
     ) -> ApiReq {
         self.into_req(
-            crate::path::PATH_API_USER_POST_GET_OLDER_OR_EQUAL,
-            ServerReq::GetUserPosts {
-                time,
-                limit,
-                username: username.into(),
+            crate::path::PATH_API_COLLECTION_ITEM_ADD,
+            ServerReq::CollectionItem {
+                collection_key: collection_key.into(),
+                post_key: post_key.into(),
             },
         )
     }
 
-    fn get_user_posts_newer_or_equal(
+    fn delete_collection_item(
         &self,
-        time: u128,
-        limit: u32,
-        username: impl Into<String>,
+        collection_key: impl Into<String>,
+        post_key: impl Into<String>,
     ) -> ApiReq {
         self.into_req(
-            crate::path::PATH_API_USER_POST_GET_NEWER_OR_EQUAL,
-            ServerReq::GetUserPosts {
-                time,
-                limit,
-                username: username.into(),
+            crate::path::PATH_API_COLLECTION_ITEM_DELETE,
+            ServerReq::CollectionItem {
+                collection_key: collection_key.into(),
+                post_key: post_key.into(),
             },
         )
     }
 
-    fn get_posts_newer_or_equal(&self, time: u128, limit: u32) -> ApiReq {
+    fn order_collection_item(
+        &self,
+        collection_key: impl Into<String>,
+        post_key: impl Into<String>,
+        new_pos: usize,
+    ) -> ApiReq {
         self.into_req(
-            crate::path::PATH_API_POST_GET_NEWER_OR_EQUAL,
-            ServerReq::GetPosts { time, limit },
+            crate::path::PATH_API_COLLECTION_ITEM_ORDER,
+            ServerReq::OrderCollectionItem {
+                collection_key: collection_key.into(),
+                post_key: post_key.into(),
+                new_pos,
+            },
         )
     }
 
-    fn get_posts_older_or_equal(&self, time: u128, limit: u32) -> ApiReq {
-        let builder = self.provide_builder(crate::path::PATH_API_POST_GET_OLDER_OR_EQUAL);
-        let server_req = ServerReq::GetPosts { time, limit };
-        let result_signal = self.provide_signal_result();
-        let busy_signal = self.provide_signal_busy();
-        ApiReq {
-            builder,
-            server_req,
-            result: result_signal,
-            busy: busy_signal,
-        }
-    }
-
-    fn get_posts_newer(&self, time: u128, limit: u32) -> ApiReq {
-        let builder = self.provide_builder(crate::path::PATH_API_POST_GET_NEWER);
-        let server_req = ServerReq::GetPosts { time, limit };
-        let result_signal = self.provide_signal_result();
-        let busy_signal = self.provide_signal_busy();
-        ApiReq {
-            builder,
-            server_req,
-            result: result_signal,
-            busy: busy_signal,
-        }
-    }
-
-    fn get_posts_older(&self, time: u128, limit: u32) -> ApiReq {
-        let builder = self.provide_builder(crate::path::PATH_API_POST_GET_OLDER);
-        let server_req = ServerReq::GetPosts { time, limit };
-        let result_signal = self.provide_signal_result();
-        let busy_signal = self.provide_signal_busy();
-        ApiReq {
-            builder,
-            server_req,
-            result: result_signal,
-            busy: busy_signal,
-        }
-    }
-
-    fn get_posts(
+    fn get_collection_posts(
         &self,
+        collection_key: impl Into<String>,
         limit: usize,
-        time_range: TimeRange,
-        order: Order,
-        tags: impl Into<String>,
-        username: impl Into<String>,
+        offset: usize,
     ) -> ApiReq {
-        // let tags = tags.into();
-        // let tags = tags.into();
-        let builder = self.provide_builder(crate::path::PATH_API_POSTS_GET);
-        let server_req = ServerReq::GetPosts2 {
-            time: time_range,
-            order,
-            limit,
-            username: username.into(),
-            tags: tags.into(),
-        };
-        let result_signal = self.provide_signal_result();
-        let busy_signal = self.provide_signal_busy();
-        ApiReq {
-            builder,
-            server_req,
-            result: result_signal,
-            busy: busy_signal,
-        }
-    }
-
-    fn update_post_title(&self, post_key: impl Into<String>, title: impl Into<String>) -> ApiReq {
         self.into_req(
-            crate::path::PATH_API_POST_UPDATE_TITLE,
-            ServerReq::EditPostTitle {
-                post_key: post_key.into(),
-                new_title: title.into(),
+            crate::path::PATH_API_COLLECTION_POSTS_GET,
+            ServerReq::GetCollectionPosts {
+                collection_key: collection_key.into(),
+                limit,
+                offset,
             },
         )
     }
 
-    fn update_post_tags(&self, post_key: impl Into<String>, tags: impl Into<String>) -> ApiReq {
+    fn get_post_collections(&self, post_key: impl Into<String>) -> ApiReq {
         self.into_req(
-            crate::path::PATH_API_POST_UPDATE_TAGS,
-            ServerReq::EditPostTags {
+            crate::path::PATH_API_POST_COLLECTIONS_GET,
+            ServerReq::PostId {
                 post_key: post_key.into(),
-                new_tags: tags.into(),
             },
         )
     }
 
-    fn update_post_description(
-        &self,
-        post_key: impl Into<String>,
-        description: impl Into<String>,
-    ) -> ApiReq {
+    // conversation
+    fn add_conversation(&self, username: impl Into<String>) -> ApiReq {
         self.into_req(
-            crate::path::PATH_API_POST_UPDATE_DESCRIPTION,
-            ServerReq::EditPostDescription {
-                post_key: post_key.into(),
-                new_description: description.into(),
+            crate::path::PATH_API_CONVERSATION_ADD,
+            ServerReq::Username {
+                username: username.into(),
             },
         )
     }
 
-    fn delete_post(&self, post_key: impl Into<String>) -> ApiReq {
+    fn get_conversation(&self, conversation_key: impl Into<String>) -> ApiReq {
         self.into_req(
-            crate::path::PATH_API_POST_DELETE,
-            ServerReq::PostId {
-                post_key: post_key.into(),
+            crate::path::PATH_API_CONVERSATION_GET,
+            ServerReq::ConversationId {
+                conversation_key: conversation_key.into(),
             },
         )
     }
 
-    fn add_post(
-        &self,
-        title: impl Into<String>,
+    fn get_conversations(&self, limit: usize, time_range: TimeRange, order: Order) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_CONVERSATIONS_GET,
+            ServerReq::GetConversations {
+                limit,
+                time_range,
+                order,
+            },
+        )
+    }
+
+    fn get_conversation_unread_count(&self) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_CONVERSATION_UNREAD_COUNT,
+            ServerReq::None,
+        )
+    }
+
+    fn read_conversation(&self, conversation_key: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_CONVERSATION_READ,
+            ServerReq::ConversationId {
+                conversation_key: conversation_key.into(),
+            },
+        )
+    }
+
+    fn block_conversation(&self, conversation_key: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_CONVERSATION_BLOCK,
+            ServerReq::ConversationId {
+                conversation_key: conversation_key.into(),
+            },
+        )
+    }
+
+    fn unblock_conversation(&self, conversation_key: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_CONVERSATION_UNBLOCK,
+            ServerReq::ConversationId {
+                conversation_key: conversation_key.into(),
+            },
+        )
+    }
+
+    fn add_message(&self, conversation_key: impl Into<String>, text: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_MESSAGE_ADD,
+            ServerReq::AddMessage {
+                conversation_key: conversation_key.into(),
+                text: text.into(),
+            },
+        )
+    }
+
+    fn get_messages(
+        &self,
+        conversation_key: impl Into<String>,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_MESSAGES_GET,
+            ServerReq::GetMessages {
+                conversation_key: conversation_key.into(),
+                limit,
+                time_range,
+                order,
+            },
+        )
+    }
+
+    // admin
+    fn admin_get_users(
+        &self,
+        query: impl Into<String>,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ADMIN_USERS_GET,
+            ServerReq::AdminGetUsers {
+                query: query.into(),
+                limit,
+                time_range,
+                order,
+            },
+        )
+    }
+
+    fn admin_get_user_posts(
+        &self,
+        username: impl Into<String>,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ADMIN_USER_POSTS_GET,
+            ServerReq::AdminGetUserPosts {
+                username: username.into(),
+                limit,
+                time_range,
+                order,
+            },
+        )
+    }
+
+    fn admin_update_user_storage(
+        &self,
+        user_key: impl Into<String>,
+        max_storage_bytes: u64,
+        max_storage_per_file_bytes: u64,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ADMIN_USER_STORAGE_UPDATE,
+            ServerReq::AdminUpdateUserStorage {
+                user_key: user_key.into(),
+                max_storage_bytes,
+                max_storage_per_file_bytes,
+            },
+        )
+    }
+
+    fn admin_update_user_role(&self, user_key: impl Into<String>, role: UserRole) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ADMIN_USER_ROLE_UPDATE,
+            ServerReq::AdminUpdateUserRole {
+                user_key: user_key.into(),
+                role,
+            },
+        )
+    }
+
+    fn admin_update_user_ban(&self, user_key: impl Into<String>, banned: bool) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ADMIN_USER_BAN_UPDATE,
+            ServerReq::AdminUpdateUserBan {
+                user_key: user_key.into(),
+                banned,
+            },
+        )
+    }
+
+    fn admin_update_post_show(&self, post_key: impl Into<String>, show: bool) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ADMIN_POST_SHOW_UPDATE,
+            ServerReq::AdminUpdatePostShow {
+                post_key: post_key.into(),
+                show,
+            },
+        )
+    }
+
+    fn admin_update_post_rating(&self, post_key: impl Into<String>, rating: PostRating) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ADMIN_POST_RATING_UPDATE,
+            ServerReq::EditPostRating {
+                post_key: post_key.into(),
+                rating,
+            },
+        )
+    }
+
+    fn admin_delete_post_comment(&self, comment_key: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ADMIN_POST_COMMENT_DELETE,
+            ServerReq::CommentId {
+                comment_id: comment_key.into(),
+            },
+        )
+    }
+
+    // report
+    fn add_report(
+        &self,
+        target: ReportTarget,
+        reason: ReportReason,
+        text: impl Into<String>,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_REPORT_ADD,
+            ServerReq::AddReport {
+                target,
+                reason,
+                text: text.into(),
+            },
+        )
+    }
+
+    fn admin_get_reports(&self, limit: usize, offset: usize) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ADMIN_REPORTS_GET,
+            ServerReq::AdminGetReports { limit, offset },
+        )
+    }
+
+    fn admin_resolve_report(
+        &self,
+        report_key: impl Into<String>,
+        action: ReportAction,
+        reason: impl Into<String>,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ADMIN_REPORT_RESOLVE,
+            ServerReq::AdminResolveReport {
+                report_key: report_key.into(),
+                action,
+                reason: reason.into(),
+            },
+        )
+    }
+
+    fn admin_get_audit_events(
+        &self,
+        username: Option<String>,
+        kind: Option<AuditKind>,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ADMIN_AUDIT_EVENTS_GET,
+            ServerReq::AdminGetAuditEvents {
+                username,
+                kind,
+                limit,
+                time_range,
+                order,
+            },
+        )
+    }
+
+    // audit
+    fn get_audit_events(&self, limit: usize, time_range: TimeRange, order: Order) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_AUDIT_EVENTS_GET,
+            ServerReq::GetAuditEvents {
+                limit,
+                time_range,
+                order,
+            },
+        )
+    }
+
+    // revision
+    fn get_revisions(&self, target: RevisionTarget, limit: usize) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_REVISIONS_GET,
+            ServerReq::GetRevisions { target, limit },
+        )
+    }
+
+    // trash
+    fn get_trash(&self) -> ApiReq {
+        self.into_req(crate::path::PATH_API_TRASH_GET, ServerReq::None)
+    }
+
+    fn restore_post(&self, post_key: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_TRASH_POST_RESTORE,
+            ServerReq::PostId {
+                post_key: post_key.into(),
+            },
+        )
+    }
+
+    fn restore_post_comment(&self, comment_key: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_TRASH_POST_COMMENT_RESTORE,
+            ServerReq::CommentId {
+                comment_id: comment_key.into(),
+            },
+        )
+    }
+
+    // account
+    fn request_account_deletion(
+        &self,
+        password: impl Into<String>,
+        mode: AccountDeletionMode,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ACCOUNT_DELETION_REQUEST,
+            ServerReq::RequestAccountDeletion {
+                password: password.into(),
+                mode,
+            },
+        )
+    }
+
+    fn confirm_account_deletion(&self, confirm_key: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ACCOUNT_DELETION_CONFIRM,
+            ServerReq::ConfirmToken {
+                token: confirm_key.into(),
+            },
+        )
+    }
+
+    fn cancel_account_deletion(&self) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_ACCOUNT_DELETION_CANCEL,
+            ServerReq::None,
+        )
+    }
+
+    fn get_account_deletion(&self) -> ApiReq {
+        self.into_req(crate::path::PATH_API_ACCOUNT_DELETION_GET, ServerReq::None)
+    }
+
+    fn add_data_export(&self) -> ApiReq {
+        self.into_req(crate::path::PATH_API_DATA_EXPORT_ADD, ServerReq::None)
+    }
+
+    fn get_data_exports(&self) -> ApiReq {
+        self.into_req(crate::path::PATH_API_DATA_EXPORTS_GET, ServerReq::None)
+    }
+
+    // api token
+    fn add_api_token(
+        &self,
+        name: impl Into<String>,
+        scopes: Vec<ApiTokenScope>,
+        expires: u128,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_API_TOKEN_ADD,
+            ServerReq::AddApiToken {
+                name: name.into(),
+                scopes,
+                expires,
+            },
+        )
+    }
+
+    fn get_api_tokens(&self) -> ApiReq {
+        self.into_req(crate::path::PATH_API_API_TOKENS_GET, ServerReq::None)
+    }
+
+    fn delete_api_token(&self, token_key: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_API_TOKEN_DELETE,
+            ServerReq::DeleteApiToken {
+                token_key: token_key.into(),
+            },
+        )
+    }
+
+    // webhook
+    fn add_webhook(
+        &self,
+        url: impl Into<String>,
+        events: Vec<WebhookEvent>,
+        admin: bool,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_WEBHOOK_ADD,
+            ServerReq::AddWebhook {
+                url: url.into(),
+                events,
+                admin,
+            },
+        )
+    }
+
+    fn get_webhooks(&self) -> ApiReq {
+        self.into_req(crate::path::PATH_API_WEBHOOKS_GET, ServerReq::None)
+    }
+
+    fn delete_webhook(&self, webhook_key: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_WEBHOOK_DELETE,
+            ServerReq::DeleteWebhook {
+                webhook_key: webhook_key.into(),
+            },
+        )
+    }
+
+    fn get_webhook_deliveries(
+        &self,
+        webhook_key: impl Into<String>,
+        limit: usize,
+        time_range: TimeRange,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_WEBHOOK_DELIVERIES_GET,
+            ServerReq::GetWebhookDeliveries {
+                webhook_key: webhook_key.into(),
+                limit,
+                time_range,
+            },
+        )
+    }
+
+    // user block
+    fn add_user_block(&self, username: impl Into<String>, kind: UserBlockKind) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_USER_BLOCK_ADD,
+            ServerReq::AddUserBlock {
+                username: username.into(),
+                kind,
+            },
+        )
+    }
+
+    fn check_user_block(&self, username: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_USER_BLOCK_CHECK,
+            ServerReq::Username {
+                username: username.into(),
+            },
+        )
+    }
+
+    fn delete_user_block(&self, username: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_USER_BLOCK_DELETE,
+            ServerReq::Username {
+                username: username.into(),
+            },
+        )
+    }
+
+    fn get_user_blocks(&self, limit: usize, time_range: TimeRange, order: Order) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_USER_BLOCKS_GET,
+            ServerReq::GetUserBlocks {
+                limit,
+                time_range,
+                order,
+            },
+        )
+    }
+
+    // user follow
+    fn add_user_follow(&self, username: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_USER_FOLLOW_ADD,
+            ServerReq::Username {
+                username: username.into(),
+            },
+        )
+    }
+
+    fn check_user_follow(&self, username: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_USER_FOLLOW_CHECK,
+            ServerReq::Username {
+                username: username.into(),
+            },
+        )
+    }
+
+    fn delete_user_follow(&self, username: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_USER_FOLLOW_DELETE,
+            ServerReq::Username {
+                username: username.into(),
+            },
+        )
+    }
+
+    fn get_user_follow_stats(&self, username: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_USER_FOLLOW_STATS,
+            ServerReq::Username {
+                username: username.into(),
+            },
+        )
+    }
+
+    fn get_user_followers(
+        &self,
+        username: impl Into<String>,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_USER_FOLLOWERS_GET,
+            ServerReq::GetUserFollows {
+                username: username.into(),
+                limit,
+                time_range,
+                order,
+            },
+        )
+    }
+
+    fn get_user_following(
+        &self,
+        username: impl Into<String>,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_USER_FOLLOWING_GET,
+            ServerReq::GetUserFollows {
+                username: username.into(),
+                limit,
+                time_range,
+                order,
+            },
+        )
+    }
+
+    //
+
+    fn login(&self, email: impl Into<String>, password: impl Into<String>) -> ApiReq {
+        let email = email.into();
+        let password = password.into();
+        let builder = self.provide_builder(crate::path::PATH_API_LOGIN);
+        let server_req = ServerReq::Login { email, password };
+        let result_signal = self.provide_signal_result();
+        let busy_signal = self.provide_signal_busy();
+        ApiReq {
+            builder,
+            server_req,
+            result: result_signal,
+            busy: busy_signal,
+        }
+    }
+
+    fn logout(&self) -> ApiReq {
+        let builder = self.provide_builder(crate::path::PATH_API_LOGOUT);
+        let server_req = ServerReq::None;
+        let result_signal = self.provide_signal_result();
+        let busy_signal = self.provide_signal_busy();
+        ApiReq {
+            builder,
+            server_req,
+            result: result_signal,
+            busy: busy_signal,
+        }
+    }
+
+    fn get_user(&self, username: impl Into<String>) -> ApiReq {
+        let username = username.into();
+        let builder = self.provide_builder(crate::path::PATH_API_USER);
+        let server_req = ServerReq::GetUser { username };
+        let result_signal = self.provide_signal_result();
+        let busy_signal = self.provide_signal_busy();
+        ApiReq {
+            builder,
+            server_req,
+            result: result_signal,
+            busy: busy_signal,
+        }
+    }
+
+    fn get_my_acc(&self) -> ApiReq {
+        let builder = self.provide_builder(crate::path::PATH_API_ACC);
+        let server_req = ServerReq::None;
+        let result_signal = self.provide_signal_result();
+        let busy_signal = self.provide_signal_busy();
+        ApiReq {
+            builder,
+            server_req,
+            result: result_signal,
+            busy: busy_signal,
+        }
+    }
+
+    fn decode_invite(&self, token: impl Into<String>) -> ApiReq {
+        let token = token.into();
+        let builder = self.provide_builder(crate::path::PATH_API_INVITE_DECODE);
+        let server_req = ServerReq::ConfirmToken { token };
+        let result_signal = self.provide_signal_result();
+        let busy_signal = self.provide_signal_busy();
+        ApiReq {
+            builder,
+            server_req,
+            result: result_signal,
+            busy: busy_signal,
+        }
+    }
+
+    fn get_post(&self, post_id: impl Into<String>) -> ApiReq {
+        let builder = self.provide_builder(crate::path::PATH_API_POST_GET);
+        let server_req = ServerReq::PostId {
+            post_key: post_id.into(),
+        };
+        let result_signal = self.provide_signal_result();
+        let busy_signal = self.provide_signal_busy();
+        ApiReq {
+            builder,
+            server_req,
+            result: result_signal,
+            busy: busy_signal,
+        }
+    }
+
+    fn change_username(
+        &self,
+        password: impl Into<String>,
+        new_username: impl Into<String>,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_CHANGE_USERNAME,
+            ServerReq::ChangeUsername {
+                username: new_username.into(),
+                password: password.into(),
+            },
+        )
+    }
+
+    fn get_posts(
+        &self,
+        limit: usize,
+        cursor: CursorRange,
+        order: Order,
+        tags: impl Into<String>,
+        username: impl Into<String>,
+    ) -> ApiReq {
+        // let tags = tags.into();
+        // let tags = tags.into();
+        let builder = self.provide_builder(crate::path::PATH_API_POSTS_GET);
+        let server_req = ServerReq::GetPosts {
+            cursor,
+            order,
+            limit,
+            username: username.into(),
+            tags: tags.into(),
+        };
+        let result_signal = self.provide_signal_result();
+        let busy_signal = self.provide_signal_busy();
+        ApiReq {
+            builder,
+            server_req,
+            result: result_signal,
+            busy: busy_signal,
+        }
+    }
+
+    fn get_posts_related(&self, post_key: impl Into<String>, limit: usize) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_POSTS_GET_RELATED,
+            ServerReq::GetRelatedPosts {
+                post_key: post_key.into(),
+                limit,
+            },
+        )
+    }
+
+    fn get_posts_following(&self, limit: usize, cursor: CursorRange, order: Order) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_POSTS_GET_FOLLOWING,
+            ServerReq::GetFollowingPosts {
+                limit,
+                cursor,
+                order,
+            },
+        )
+    }
+
+    fn update_post_title(&self, post_key: impl Into<String>, title: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_POST_UPDATE_TITLE,
+            ServerReq::EditPostTitle {
+                post_key: post_key.into(),
+                new_title: title.into(),
+            },
+        )
+    }
+
+    fn update_post_rating(&self, post_key: impl Into<String>, rating: PostRating) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_POST_UPDATE_RATING,
+            ServerReq::EditPostRating {
+                post_key: post_key.into(),
+                rating,
+            },
+        )
+    }
+
+    fn update_post_visibility(
+        &self,
+        post_key: impl Into<String>,
+        visibility: PostVisibility,
+        publish_at: u128,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_POST_UPDATE_VISIBILITY,
+            ServerReq::EditPostVisibility {
+                post_key: post_key.into(),
+                visibility,
+                publish_at,
+            },
+        )
+    }
+
+    fn update_post_embeddable(&self, post_key: impl Into<String>, embeddable: bool) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_POST_UPDATE_EMBEDDABLE,
+            ServerReq::EditPostEmbeddable {
+                post_key: post_key.into(),
+                embeddable,
+            },
+        )
+    }
+
+    fn get_rating_preference(&self) -> ApiReq {
+        self.into_req(crate::path::PATH_API_RATING_PREFERENCE_GET, ServerReq::None)
+    }
+
+    fn update_rating_preference(&self, max_rating: PostRating, blur: bool) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_RATING_PREFERENCE_UPDATE,
+            ServerReq::UpdateRatingPreference { max_rating, blur },
+        )
+    }
+
+    fn update_post_tags(&self, post_key: impl Into<String>, tags: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_POST_UPDATE_TAGS,
+            ServerReq::EditPostTags {
+                post_key: post_key.into(),
+                new_tags: tags.into(),
+            },
+        )
+    }
+
+    fn update_post_description(
+        &self,
+        post_key: impl Into<String>,
+        description: impl Into<String>,
+    ) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_POST_UPDATE_DESCRIPTION,
+            ServerReq::EditPostDescription {
+                post_key: post_key.into(),
+                new_description: description.into(),
+            },
+        )
+    }
+
+    fn delete_post(&self, post_key: impl Into<String>) -> ApiReq {
+        self.into_req(
+            crate::path::PATH_API_POST_DELETE,
+            ServerReq::PostId {
+                post_key: post_key.into(),
+            },
+        )
+    }
+
+    fn add_post(
+        &self,
+        title: impl Into<String>,
         description: impl Into<String>,
         tags: impl Into<String>,
+        rating: PostRating,
+        visibility: PostVisibility,
+        publish_at: u128,
         // files: Vec<ServerReqImg>,
     ) -> ApiReq {
         let title = title.into();
@@ -2161,6 +3484,9 @@ pub trait Api {
             title,
             description,
             tags,
+            rating,
+            visibility,
+            publish_at,
             // files,
         };
         let result_signal = self.provide_signal_result();
@@ -2491,6 +3817,88 @@ impl Api for ApiWeb {
     }
 }
 
+/// how requests and responses are encoded on the wire, the rust client uses rkyv and
+/// third party clients opt into json with the content-type and accept headers
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum WireFormat {
+    #[default]
+    Rkyv,
+    Json,
+}
+
+impl WireFormat {
+    pub const JSON_MIME: &'static str = "application/json";
+    pub const RKYV_MIME: &'static str = "application/octet-stream";
+
+    fn from_header(headers: &HeaderMap, name: http::header::HeaderName) -> Self {
+        let is_json = headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|mime| {
+                mime.split(';')
+                    .next()
+                    .map(|mime| mime.trim().eq_ignore_ascii_case(Self::JSON_MIME))
+                    .unwrap_or_default()
+            });
+        if is_json { Self::Json } else { Self::Rkyv }
+    }
+
+    /// format of the request body
+    pub fn from_content_type(headers: &HeaderMap) -> Self {
+        Self::from_header(headers, http::header::CONTENT_TYPE)
+    }
+
+    /// format the client wants the response in
+    pub fn from_accept(headers: &HeaderMap) -> Self {
+        Self::from_header(headers, http::header::ACCEPT)
+    }
+
+    pub fn mime(&self) -> &'static str {
+        match self {
+            WireFormat::Rkyv => Self::RKYV_MIME,
+            WireFormat::Json => Self::JSON_MIME,
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+tokio::task_local! {
+    /// response format of the request being handled, set by wire_format_middleware
+    pub static WIRE_FORMAT: WireFormat;
+}
+
+/// encodes a response in the format picked for the current request, rkyv outside of a request
+#[cfg(feature = "ssr")]
+pub fn encode_result(
+    result: &Result<ServerRes, ServerErr>,
+) -> ([(http::header::HeaderName, &'static str); 1], Vec<u8>) {
+    let format = WIRE_FORMAT.try_with(|format| *format).unwrap_or_default();
+    let bytes = match format {
+        WireFormat::Rkyv => rkyv::to_bytes::<rkyv::rancor::Error>(result)
+            .unwrap()
+            .to_vec(),
+        WireFormat::Json => serde_json::to_vec(result).unwrap(),
+    };
+
+    ([(http::header::CONTENT_TYPE, format.mime())], bytes)
+}
+
+#[cfg(feature = "ssr")]
+pub fn recv_json(
+    headers: impl AsRef<str>,
+    bytes: axum::body::Bytes,
+) -> Result<ServerReq, ServerErr> {
+    let client_input = serde_json::from_slice::<ServerReq>(&bytes)
+        .inspect_err(|err| error!("{err} SERVER RECV:\n{}", String::from_utf8_lossy(&bytes)))
+        .map_err(|err| ServerDesErr::ServerDesJsonErr(err.to_string()))?;
+
+    debug!("SERVER RECV:\n{}\n {client_input:?}", headers.as_ref());
+
+    Ok(client_input)
+}
+
 #[cfg(feature = "ssr")]
 pub async fn recv(
     headers: impl AsRef<str>,
@@ -2535,16 +3943,14 @@ impl axum::response::IntoResponse for ServerErr {
         match self {
             ServerErr::AuthErr(ServerAuthErr::ServerUnauthorizedInvalidCookie) => {
                 let result: Result<ServerRes, ServerErr> = Err(self);
-                let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&result).unwrap();
-                let bytes = bytes.to_vec();
+                let body = encode_result(&result);
                 let headers = create_deleted_cookie();
-                (status, headers, bytes).into_response()
+                (status, headers, body).into_response()
             }
             server_err => {
                 let result: Result<ServerRes, ServerErr> = Err(server_err);
-                let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&result).unwrap();
-                let bytes = bytes.to_vec();
-                (status, bytes).into_response()
+                let body = encode_result(&result);
+                (status, body).into_response()
             }
         }
     }
@@ -2556,28 +3962,31 @@ impl axum::response::IntoResponse for ServerRes {
         match self {
             ServerRes::DeleteAuthCookie => {
                 let result: Result<ServerRes, ServerErr> = Ok(ServerRes::Ok);
-                let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&result).unwrap();
-                let bytes = bytes.to_vec();
+                let body = encode_result(&result);
                 let headers = create_deleted_cookie();
-                (headers, bytes).into_response()
+                (headers, body).into_response()
+            }
+            ServerRes::SetRatingCookie { max_rating } => {
+                let result: Result<ServerRes, ServerErr> = Ok(ServerRes::Ok);
+                let body = encode_result(&result);
+                let headers = create_rating_cookie(max_rating);
+                (headers, body).into_response()
             }
             ServerRes::SetAuthCookie { token } => {
                 let result: Result<ServerRes, ServerErr> = Ok(ServerRes::Ok);
-                let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&result).unwrap();
-                let bytes = bytes.to_vec();
+                let body = encode_result(&result);
                 let headers = create_auth_cookie(token);
 
-                debug!("SERVER SEND:\n{result:?} - {bytes:?}");
+                debug!("SERVER SEND:\n{result:?} - {:?}", body.1);
 
-                (headers, bytes).into_response()
+                (headers, body).into_response()
             }
             res => {
                 let result: Result<ServerRes, ServerErr> = Ok(res);
-                let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&result).unwrap();
-                let bytes = bytes.to_vec();
-                debug!("SERVER SEND:\n{result:?} - {bytes:?}");
+                let body = encode_result(&result);
+                debug!("SERVER SEND:\n{result:?} - {:?}", body.1);
 
-                bytes.into_response()
+                body.into_response()
             }
         }
     }
@@ -2593,8 +4002,13 @@ pub async fn send(
     let part = reqwest::multipart::Part::bytes(bytes.to_vec());
     let form = reqwest::multipart::Form::new().part("data", part);
     if let Some(token) = token {
-        let cookie = create_auth_header(token);
-        req_builder = req_builder.header(http::header::COOKIE, cookie);
+        let token = token.as_ref();
+        if token.starts_with(API_TOKEN_PREFIX) {
+            req_builder = req_builder.header(AUTHORIZATION, format!("{COOKIE_PREFIX}{token}"));
+        } else {
+            let cookie = create_auth_header(token);
+            req_builder = req_builder.header(http::header::COOKIE, cookie);
+        }
     }
     let res = req_builder
         .multipart(form)
@@ -2660,12 +4074,16 @@ pub mod tests {
 
     use crate::api::app_state::AppState;
     use crate::api::settings::Settings;
+    use crate::api::shared::cursor::{Cursor, CursorRange, PAGE_LIMIT_MAX};
+    use crate::api::shared::notification::UserNotification;
     use crate::api::shared::post_comment::UserPostComment;
+    use crate::api::shared::rating::PostRating;
+    use crate::api::shared::visibility::PostVisibility;
     use crate::api::{
         Api, ApiTest, EmailChangeErr, EmailChangeNewErr, EmailChangeStage, EmailChangeTokenErr,
-        Order, PostLikeErr, Server404Err, ServerAddPostFileErr, ServerAuthErr, ServerErr,
-        ServerLoginErr, ServerRegistrationErr, ServerReqImg, ServerRes, ServerSendInviteErr,
-        ServerUpdatePostTitleErr, TimeRange, UserPost,
+        Order, PostLikeErr, Server404Err, ServerAddPostFileErr, ServerAuthErr, ServerDesErr,
+        ServerErr, ServerLoginErr, ServerRegistrationErr, ServerReq, ServerReqImg, ServerRes,
+        ServerSendInviteErr, ServerUpdatePostTitleErr, TimeRange, UserFollow, UserPost, WireFormat,
     };
     use crate::db::DB404Err;
     use crate::db::email_change::create_email_change_id;
@@ -2896,7 +4314,14 @@ pub mod tests {
             // let img = tokio::fs::read(path).await.unwrap();
             let result = self
                 .api
-                .add_post(title, description, tags)
+                .add_post(
+                    title,
+                    description,
+                    tags,
+                    PostRating::General,
+                    PostVisibility::Public,
+                    0,
+                )
                 .send_native_with_token(auth_token.clone())
                 .await;
             trace!("{result:#?}");
@@ -2912,7 +4337,7 @@ pub mod tests {
             time: u128,
             auth_token: impl Into<String>,
             limit: usize,
-            time_range: TimeRange,
+            cursor: CursorRange,
             order: Order,
             tags: impl Into<String>,
             username: impl Into<String>,
@@ -2922,17 +4347,39 @@ pub mod tests {
 
             let result = self
                 .api
-                .get_posts(limit, time_range, order, tags, username)
+                .get_posts(limit, cursor, order, tags, username)
                 .send_native_with_token(auth_token.clone())
                 .await;
             trace!("{result:#?}");
 
             match result {
-                Ok(crate::api::ServerRes::Posts(posts)) => Some(posts),
+                Ok(crate::api::ServerRes::PostPage { posts, .. }) => Some(posts),
                 _ => None,
             }
         }
 
+        pub async fn get_posts_related(
+            &self,
+            time: u128,
+            post_key: impl Into<String>,
+            limit: usize,
+        ) -> Result<Vec<UserPost>, ServerErr> {
+            self.set_time(time).await;
+
+            let result = self
+                .api
+                .get_posts_related(post_key, limit)
+                .send_native()
+                .await;
+            trace!("{result:#?}");
+
+            match result {
+                Ok(crate::api::ServerRes::Posts(posts)) => Ok(posts),
+                Ok(res) => panic!("expected Posts, received {res:?}"),
+                Err(err) => Err(err),
+            }
+        }
+
         pub async fn expect_posts(
             &self,
             server_time: u128,
@@ -2941,84 +4388,45 @@ pub mod tests {
             post_count_older: usize,
             post_count_older_or_equal: usize,
         ) -> Option<()> {
-            let (matched_newer, len_newer) = match self
-                .api
-                .get_posts_newer(server_time, 1000)
-                .send_native()
-                .await
-            {
-                Ok(crate::api::ServerRes::Posts(posts)) => {
-                    let len = posts.len();
-                    let result = len == post_count_newer;
-                    (result, len)
-                }
-                wrong => (false, 0),
-            };
-
-            let (matched_newer_or_equal, len_newer_or_equal) = match self
-                .api
-                .get_posts_newer_or_equal(server_time, 1000)
-                .send_native()
-                .await
-            {
-                Ok(crate::api::ServerRes::Posts(posts)) => {
-                    let len = posts.len();
-                    let result = len == post_count_newer_or_equal;
-                    (result, len)
-                }
-                wrong => (false, 0),
-            };
-
-            let (matched_older, len_older) = match self
-                .api
-                .get_posts_older(server_time, 1000)
-                .send_native()
-                .await
-            {
-                Ok(crate::api::ServerRes::Posts(posts)) => {
-                    let len = posts.len();
-                    let result = len == post_count_older;
-                    (result, len)
-                }
-                wrong => (false, 0),
-            };
-
-            let (matched_older_or_equal, len_older_or_equal) = match self
-                .api
-                .get_posts_older_or_equal(server_time, 1000)
-                .send_native()
-                .await
-            {
-                Ok(crate::api::ServerRes::Posts(posts)) => {
-                    let len = posts.len();
-                    let result = len == post_count_older_or_equal;
-                    (result, len)
+            let mut matched = true;
+            for (name, cursor, expected) in [
+                (
+                    "newer",
+                    CursorRange::After(Cursor::at(server_time + 1)),
+                    post_count_newer,
+                ),
+                (
+                    "newer_or_equal",
+                    CursorRange::After(Cursor::at(server_time)),
+                    post_count_newer_or_equal,
+                ),
+                (
+                    "older",
+                    CursorRange::Before(Cursor::at(server_time)),
+                    post_count_older,
+                ),
+                (
+                    "older_or_equal",
+                    CursorRange::Before(Cursor::at(server_time + 1)),
+                    post_count_older_or_equal,
+                ),
+            ] {
+                let len = match self
+                    .api
+                    .get_posts(PAGE_LIMIT_MAX, cursor, Order::ThreeTwoOne, "", "")
+                    .send_native()
+                    .await
+                {
+                    Ok(crate::api::ServerRes::PostPage { posts, .. }) => posts.len(),
+                    _ => 0,
+                };
+                if len != expected {
+                    error!("expected {name} len to be {expected}, got {len}.");
+                    matched = false;
                 }
-                wrong => (false, 0),
-            };
-
-            if !matched_newer {
-                error!("expected newer len to be {post_count_newer}, got {len_newer}.");
-            }
-            if !matched_newer_or_equal {
-                error!(
-                    "expected newer_or_equal len to be {post_count_newer_or_equal}, got {len_newer_or_equal}."
-                );
-            }
-            if !matched_older {
-                error!("expected older len to be {post_count_older}, got {len_older}.");
-            }
-            if !matched_older_or_equal {
-                error!(
-                    "expected older_or_equal len to be {post_count_older_or_equal}, got {len_older_or_equal}."
-                );
             }
 
-            if matched_newer && matched_newer_or_equal && matched_older && matched_older_or_equal {
-                Some(())
-            } else {
-                None
-            }
+            matched.then_some(())
         }
         pub async fn delete_post_like(
             &self,
@@ -3127,6 +4535,235 @@ pub mod tests {
             }
         }
 
+        pub async fn add_user_follow(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            username: impl Into<String>,
+        ) -> Option<()> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .add_user_follow(username)
+                .send_native_with_token(auth_token)
+                .await;
+
+            if result == Ok(ServerRes::Ok) {
+                Some(())
+            } else {
+                None
+            }
+        }
+
+        pub async fn add_user_follow_err(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            username: impl Into<String>,
+        ) -> ServerErr {
+            self.set_time(server_time).await;
+            self.api
+                .add_user_follow(username)
+                .send_native_with_token(auth_token)
+                .await
+                .unwrap_err()
+        }
+
+        pub async fn delete_user_follow(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            username: impl Into<String>,
+        ) -> Option<()> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .delete_user_follow(username)
+                .send_native_with_token(auth_token)
+                .await;
+
+            if result == Ok(ServerRes::Ok) {
+                Some(())
+            } else {
+                None
+            }
+        }
+
+        pub async fn check_user_follow(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            username: impl Into<String>,
+            condition: bool,
+        ) -> Option<()> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .check_user_follow(username)
+                .send_native_with_token(auth_token)
+                .await
+                .unwrap();
+            if result == ServerRes::Condition(condition) {
+                Some(())
+            } else {
+                None
+            }
+        }
+
+        pub async fn get_user_follow_stats(
+            &self,
+            server_time: u128,
+            username: impl Into<String>,
+        ) -> Option<(u64, u64)> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_user_follow_stats(username)
+                .send_native()
+                .await;
+            match result {
+                Ok(ServerRes::UserFollowStats {
+                    followers,
+                    following,
+                }) => Some((followers, following)),
+                _ => None,
+            }
+        }
+
+        pub async fn get_user_followers(
+            &self,
+            server_time: u128,
+            username: impl Into<String>,
+            limit: usize,
+            time_range: TimeRange,
+            order: Order,
+        ) -> Option<Vec<UserFollow>> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_user_followers(username, limit, time_range, order)
+                .send_native()
+                .await;
+            match result {
+                Ok(ServerRes::UserFollows(users)) => Some(users),
+                _ => None,
+            }
+        }
+
+        pub async fn get_user_following(
+            &self,
+            server_time: u128,
+            username: impl Into<String>,
+            limit: usize,
+            time_range: TimeRange,
+            order: Order,
+        ) -> Option<Vec<UserFollow>> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_user_following(username, limit, time_range, order)
+                .send_native()
+                .await;
+            match result {
+                Ok(ServerRes::UserFollows(users)) => Some(users),
+                _ => None,
+            }
+        }
+
+        pub async fn get_posts_following(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            limit: usize,
+            cursor: CursorRange,
+            order: Order,
+        ) -> Option<Vec<UserPost>> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_posts_following(limit, cursor, order)
+                .send_native_with_token(auth_token)
+                .await;
+            match result {
+                Ok(ServerRes::PostPage { posts, .. }) => Some(posts),
+                _ => None,
+            }
+        }
+
+        pub async fn get_notifications(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            limit: usize,
+            time_range: TimeRange,
+            order: Order,
+        ) -> Option<Vec<UserNotification>> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_notifications(limit, time_range, order)
+                .send_native_with_token(auth_token)
+                .await;
+            match result {
+                Ok(ServerRes::Notifications(notifications)) => Some(notifications),
+                _ => None,
+            }
+        }
+
+        pub async fn get_notification_unread_count(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+        ) -> Option<u64> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_notification_unread_count()
+                .send_native_with_token(auth_token)
+                .await;
+            match result {
+                Ok(ServerRes::Count(count)) => Some(count),
+                _ => None,
+            }
+        }
+
+        pub async fn read_notification(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            notification_key: impl Into<String>,
+        ) -> Result<(), ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .read_notification(notification_key)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Ok => Ok(()),
+                res => panic!("fix code, invalid response, expected Ok, got {res:?}"),
+            }
+        }
+
+        pub async fn read_notification_all(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+        ) -> Option<()> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .read_notification_all()
+                .send_native_with_token(auth_token)
+                .await;
+
+            if result == Ok(ServerRes::Ok) {
+                Some(())
+            } else {
+                None
+            }
+        }
+
         pub async fn register(
             &self,
             server_time: u128,
@@ -3328,6 +4965,7 @@ pub mod tests {
                     username,
                     email,
                     key,
+                    role,
                 }) => true,
                 _ => false,
             };
@@ -4021,4 +5659,107 @@ pub mod tests {
             .await
             .unwrap();
     }
+
+    #[tokio::test]
+    async fn api_json_wire_format_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+        app.register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        let send_json = async |path: &str, body: Vec<u8>, accept: Option<&str>| {
+            let url = format!("{}{path}", crate::path::PATH_API);
+            let url = app.api.server.server_url(&url).unwrap();
+            let mut req = reqwest::Client::new()
+                .post(url)
+                .header(
+                    http::header::CONTENT_TYPE,
+                    "application/json; charset=utf-8",
+                )
+                .body(body);
+            if let Some(accept) = accept {
+                req = req.header(http::header::ACCEPT, accept);
+            }
+            let res = req.send().await.unwrap();
+            let content_type = res.headers()[http::header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .to_string();
+            (content_type, res.bytes().await.unwrap())
+        };
+        let get_user = |username: &str| {
+            serde_json::to_vec(&ServerReq::GetUser {
+                username: username.to_string(),
+            })
+            .unwrap()
+        };
+
+        // json in, json out
+        let (content_type, bytes) = send_json(
+            crate::path::PATH_API_USER,
+            get_user("hey"),
+            Some("text/html, application/json;q=0.9"),
+        )
+        .await;
+        assert_eq!(content_type, WireFormat::JSON_MIME);
+        let result: Result<ServerRes, ServerErr> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(
+            result,
+            Ok(ServerRes::User {
+                username: "hey".to_string()
+            })
+        );
+
+        // errors are encoded the same way
+        let (_, bytes) = send_json(
+            crate::path::PATH_API_USER,
+            get_user("nobody"),
+            Some(WireFormat::JSON_MIME),
+        )
+        .await;
+        let result: Result<ServerRes, ServerErr> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(result, Err(ServerErr::NotFoundErr(Server404Err::NotFound)));
+
+        let (_, bytes) = send_json(
+            crate::path::PATH_API_USER,
+            b"{\"GetUser\":{}}".to_vec(),
+            Some(WireFormat::JSON_MIME),
+        )
+        .await;
+        let result: Result<ServerRes, ServerErr> = serde_json::from_slice(&bytes).unwrap();
+        assert!(matches!(
+            result,
+            Err(ServerErr::DesErr(ServerDesErr::ServerDesJsonErr(_)))
+        ));
+
+        // rejections from the auth middleware too
+        let (_, bytes) = send_json(
+            crate::path::PATH_API_ACC,
+            serde_json::to_vec(&ServerReq::None).unwrap(),
+            Some(WireFormat::JSON_MIME),
+        )
+        .await;
+        let result: Result<ServerRes, ServerErr> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(
+            result,
+            Err(ServerErr::AuthErr(
+                ServerAuthErr::ServerUnauthorizedNoCookie
+            ))
+        );
+
+        // without an accept header the response stays rkyv
+        let (content_type, bytes) =
+            send_json(crate::path::PATH_API_USER, get_user("hey"), None).await;
+        assert_eq!(content_type, WireFormat::RKYV_MIME);
+        let result =
+            rkyv::from_bytes::<Result<ServerRes, ServerErr>, rkyv::rancor::Error>(&bytes).unwrap();
+        assert_eq!(
+            result,
+            Ok(ServerRes::User {
+                username: "hey".to_string()
+            })
+        );
+    }
 }
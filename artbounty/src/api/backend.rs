@@ -1,12 +1,20 @@
 use crate::api::app_state::AppState;
 use crate::api::backend::post::get_img_resolution;
+use crate::api::shared::account::{AccountDeletionMode, DATA_EXPORT_RETENTION_NS};
+use crate::api::shared::admin::{AdminErr, UserRole};
+use crate::api::shared::api_token::{API_TOKEN_LAST_USED_RESOLUTION_NS, ApiTokenScope};
+use crate::api::shared::post_comment::UserPostComment;
+use crate::api::shared::webhook::WebhookEvent;
 use crate::api::{
     AuthToken, ChangeUsernameErr, EmailChangeErr, EmailChangeNewErr, EmailChangeStage,
     EmailChangeTokenErr, Server404Err, ServerAddPostErr, ServerAuthErr, ServerDecodeInviteErr,
     ServerDesErr, ServerErr, ServerErrImg, ServerErrImgMeta, ServerLoginErr, ServerRegistrationErr,
     ServerReq, ServerRes, ServerTokenErr, ServerUpdatePostDescriptionErr, User, UserPost,
-    UserPostFile, auth_token_get, hash_password, verify_password,
+    UserPostFile, WIRE_FORMAT, WireFormat, api_token_get, auth_token_get, hash_api_token,
+    hash_password, verify_password,
 };
+use crate::db::account::{DBAccountDeletionMode, DBDataExport, DBDataExportStatus};
+use crate::db::audit::DBAudit;
 use crate::db::email_change::create_email_change_id;
 use crate::db::{AddUserErr, email_change::DBChangeEmailErr};
 use crate::db::{DB404Err, DBChangeUsernameErr, DBUserPost, DbEngine, create_user_id};
@@ -21,10 +29,11 @@ use axum::Extension;
 use axum::extract::State;
 use axum::response::IntoResponse;
 use gxhash::{gxhash64, gxhash128};
-use http::header::{AUTHORIZATION, COOKIE};
+use http::header::{AUTHORIZATION, COOKIE, USER_AGENT};
 use http::{HeaderMap, StatusCode};
 use leptos_meta::Formatter;
 use std::ffi::OsStr;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 use std::time::Duration;
 use std::{io::Cursor, path::Path, str::FromStr};
@@ -32,13 +41,35 @@ use surrealdb::types::{RecordId, ToSql};
 use tokio::fs;
 use tracing::{debug, error, info, trace};
 
+pub mod account;
+pub mod activitypub;
+pub mod admin;
+pub mod api_token;
+pub mod audit;
 pub mod auth;
 pub mod change_email;
 pub mod change_password;
 pub mod change_username;
+pub mod collection;
+pub mod conversation;
+pub mod date;
+pub mod embed;
+pub mod feed;
+pub mod live;
+pub mod notification;
+pub mod outbound;
 pub mod post;
 pub mod post_comment;
 pub mod post_like;
+pub mod rating;
+pub mod report;
+pub mod revision;
+pub mod sitemap;
+pub mod trash;
+pub mod user_block;
+pub mod user_follow;
+pub mod v1;
+pub mod webhook;
 
 pub fn scale_res_by_width(width: u32, height: u32, new_width: u32) -> (u32, u32) {
     let ratio = height as f32 / width as f32;
@@ -69,6 +100,9 @@ fn test_scale_res_by_height() {
     assert_eq!(result, (405, 720));
 }
 
+/// longest side of the processed thumbnails
+pub const THUMBNAIL_RESOLUTION_LIMIT: u32 = 1280;
+
 pub fn scale_resolution(width: u32, height: u32, clamp: u32) -> (u32, u32) {
     match (width, height) {
         (width, height) if width > clamp && width >= height => {
@@ -221,6 +255,268 @@ pub async fn proccess_post_files(
     Ok(())
 }
 
+pub async fn publish_scheduled_posts(app: &AppState) -> Result<(), anyhow::Error> {
+    let time = app.time().await;
+    let posts = app.db.update_post_publish_scheduled(time).await?;
+    for post in posts {
+        info!("published scheduled post {}", post.id.key.to_sql());
+        activitypub::dispatch_ap_post_create(app, time, &post).await;
+        webhook::dispatch_webhook_post_event(app, time, WebhookEvent::PostCreated, &post).await;
+    }
+    Ok(())
+}
+
+/// ignores files that are already gone
+async fn remove_file_if_exists(path: impl AsRef<Path>) -> Result<(), std::io::Error> {
+    match fs::remove_file(path).await {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// removes files and thumbnails that no post or message points to anymore
+async fn remove_unused_files<'a>(
+    app: &AppState,
+    files_path: &str,
+    files: impl IntoIterator<Item = &'a DBUserPostFile>,
+) -> Result<(), anyhow::Error> {
+    for file in files {
+        if app.db.is_file_hash_used(&file.hash).await? {
+            continue;
+        }
+        remove_file_if_exists(file.to_file_path(files_path)).await?;
+        remove_file_if_exists(file.to_thumbnail_path(files_path)).await?;
+    }
+    Ok(())
+}
+
+/// removes trash that outlived the retention window, files are only deleted once no other post or message uses the same hash
+pub async fn purge_trash(app: &AppState) -> Result<(), anyhow::Error> {
+    let time = app.time().await;
+    let files_path = app.get_file_path().await;
+    let posts = app.db.purge_trash(time).await?;
+    for post in posts {
+        remove_unused_files(app, &files_path, &post.file).await?;
+        info!("purged trashed post {}", post.id.key.to_sql());
+    }
+    Ok(())
+}
+
+pub fn to_data_export_path(exports_path: impl AsRef<str>, export_key: impl AsRef<str>) -> PathBuf {
+    Path::new(exports_path.as_ref())
+        .join(export_key.as_ref())
+        .with_extension("zip")
+}
+
+/// writes the zip for one export and returns its owner and size,
+/// the zip is written next to its final path first so a crash never leaves a half written download behind
+async fn write_data_export(
+    app: &AppState,
+    export: &DBDataExport,
+) -> Result<(DBUser, u64), anyhow::Error> {
+    let files_path = app.get_file_path().await;
+    let exports_path = app.get_exports_path().await;
+    let data = app.db.get_user_data(export.user.clone()).await?;
+
+    let profile = serde_json::json!({
+        "username": data.user.username,
+        "email": data.user.email,
+        "role": data.user.role,
+        "max_rating": data.user.max_rating,
+        "blur_rating": data.user.blur_rating,
+        "used_storage_bytes": data.user.used_storage_bytes,
+        "created_at": data.user.created_at,
+    });
+    let likes = data
+        .likes
+        .iter()
+        .map(|like| {
+            serde_json::json!({
+                "post": like.post.key.to_sql(),
+                "created_at": like.created_at,
+            })
+        })
+        .collect::<Vec<_>>();
+    let sent_emails = data
+        .sent_emails
+        .iter()
+        .map(|email| {
+            serde_json::json!({
+                "reason": email.reason,
+                "body": email.body,
+                "created_at": email.created_at,
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut files = data
+        .posts
+        .iter()
+        .flat_map(|post| post.file.iter())
+        .map(|file| {
+            (
+                format!("files/{}.{}", file.hash, file.extension),
+                file.to_file_path(&files_path),
+            )
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files.dedup();
+    let posts = data
+        .posts
+        .into_iter()
+        .map(UserPost::from)
+        .collect::<Vec<_>>();
+    let comments = data
+        .comments
+        .into_iter()
+        .map(UserPostComment::from)
+        .collect::<Vec<_>>();
+    let documents = vec![
+        ("profile.json", serde_json::to_vec_pretty(&profile)?),
+        ("posts.json", serde_json::to_vec_pretty(&posts)?),
+        ("comments.json", serde_json::to_vec_pretty(&comments)?),
+        ("likes.json", serde_json::to_vec_pretty(&likes)?),
+        ("sent_emails.json", serde_json::to_vec_pretty(&sent_emails)?),
+    ];
+
+    let path = to_data_export_path(&exports_path, export.id.key.to_sql());
+    let size_bytes = tokio::task::spawn_blocking(move || -> Result<u64, anyhow::Error> {
+        use std::io::Write;
+
+        let tmp_path = path.with_extension("zip.tmp");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&tmp_path)?);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        for (name, bytes) in documents {
+            zip.start_file(name, options)?;
+            zip.write_all(&bytes)?;
+        }
+        for (name, file_path) in files {
+            // uploads that never finished have nothing on disk
+            let mut file = match std::fs::File::open(&file_path) {
+                Ok(file) => file,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+            zip.start_file(name, options)?;
+            std::io::copy(&mut file, &mut zip)?;
+        }
+        zip.finish()?;
+        std::fs::rename(&tmp_path, &path)?;
+
+        Ok(std::fs::metadata(&path)?.len())
+    })
+    .await??;
+
+    Ok((data.user, size_bytes))
+}
+
+/// builds every pending export and drops the ones past their retention
+pub async fn proccess_data_exports(app: &AppState) -> Result<(), anyhow::Error> {
+    let time = app.time().await;
+    let exports_path = app.get_exports_path().await;
+
+    for export in app.db.delete_data_exports_expired(time).await? {
+        remove_file_if_exists(to_data_export_path(&exports_path, export.id.key.to_sql())).await?;
+    }
+
+    for export in app.db.get_data_exports_pending().await? {
+        match write_data_export(app, &export).await {
+            Ok((user, size_bytes)) => {
+                app.db
+                    .update_data_export_status(
+                        time,
+                        export.id.clone(),
+                        DBDataExportStatus::Ready,
+                        size_bytes,
+                        time + DATA_EXPORT_RETENTION_NS,
+                    )
+                    .await?;
+                app.send_email_data_export_ready(time, user.email).await?;
+                info!("data export {} ready", export.id.key.to_sql());
+            }
+            Err(err) => {
+                error!("data export {} failed: {err}", export.id.key.to_sql());
+                app.db
+                    .update_data_export_status(
+                        time,
+                        export.id.clone(),
+                        DBDataExportStatus::Failed,
+                        0,
+                        0,
+                    )
+                    .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// erases every account whose grace period ran out
+pub async fn purge_account_deletions(app: &AppState) -> Result<(), anyhow::Error> {
+    let time = app.time().await;
+    let files_path = app.get_file_path().await;
+    let exports_path = app.get_exports_path().await;
+
+    for deletion in app.db.get_account_deletions_due(time).await? {
+        let mode = match AccountDeletionMode::from_db(&deletion.mode) {
+            AccountDeletionMode::Anonymize => DBAccountDeletionMode::Anonymize,
+            AccountDeletionMode::Delete => DBAccountDeletionMode::Delete,
+        };
+        let erased = app
+            .db
+            .erase_account(time, deletion.user.clone(), mode)
+            .await?;
+        let files = erased
+            .posts
+            .iter()
+            .flat_map(|post| post.file.iter())
+            .chain(erased.message_files.iter());
+        remove_unused_files(app, &files_path, files).await?;
+        for export in erased.exports {
+            remove_file_if_exists(to_data_export_path(&exports_path, export.key.to_sql())).await?;
+        }
+        info!("erased account {}", deletion.user.key.to_sql());
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_purge_trash() {
+    crate::init_test_log();
+    const FILES_PATH: &str = "/tmp/test_purge_trash";
+    let app = crate::api::tests::ApiTestApp::new_with_exp_and_files(1, FILES_PATH).await;
+    let img_path = "../assets/upload.svg";
+
+    let auth_token = app
+        .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+        .await
+        .unwrap();
+    let post = app
+        .add_post(0, &auth_token, "title1", "cat", "one")
+        .await
+        .unwrap();
+    let post = app
+        .add_post_file(0, &auth_token, post.key.clone(), img_path)
+        .await
+        .unwrap();
+    let file_path = app.state.db.get_post_all().await.unwrap()[0].file[0].to_file_path(FILES_PATH);
+    assert!(file_path.exists());
+
+    app.delete_post(1, &auth_token, post.key.clone())
+        .await
+        .unwrap();
+    app.set_time(2).await;
+    purge_trash(&app.state).await.unwrap();
+    assert!(file_path.exists());
+
+    app.set_time(1 + crate::api::shared::trash::TRASH_RETENTION_NS)
+        .await;
+    purge_trash(&app.state).await.unwrap();
+    assert!(!file_path.exists());
+    assert!(app.state.db.get_post_all().await.unwrap().is_empty());
+}
+
 #[tokio::test]
 async fn test_proccess_post_files() {
     // TODO delete files after test ends
@@ -282,18 +578,25 @@ pub async fn get_user(
         ))));
     };
 
-    let user = app_state
+    let user = get_user_by_username(&app_state, username).await?;
+
+    Ok(ServerRes::User {
+        username: user.username,
+    })
+}
+
+pub async fn get_user_by_username(
+    app_state: &AppState,
+    username: String,
+) -> Result<DBUser, ServerErr> {
+    app_state
         .db
         .get_user_by_username(username)
         .await
         .map_err(|err| match err {
             DB404Err::NotFound => Server404Err::NotFound.into(),
             _ => ServerErr::DbErr,
-        })?;
-
-    Ok(ServerRes::User {
-        username: user.username,
-    })
+        })
 }
 
 pub async fn get_account(
@@ -305,6 +608,7 @@ pub async fn get_account(
         key: db_user.id.key.to_sql(),
         username: db_user.username.clone(),
         email: db_user.email.clone(),
+        role: UserRole::from_db(&db_user.role),
     })
 }
 
@@ -318,7 +622,7 @@ pub async fn auth_optional_middleware(
     let result = {
         let headers = req.headers();
         // let jar = CookieJar::from_headers(headers);
-        check_auth(&app_state, &headers).await
+        check_auth(&app_state, &headers, api_token_scope(&req)).await
     };
 
     match result {
@@ -336,6 +640,16 @@ pub async fn auth_optional_middleware(
     next.run(req).await
 }
 
+/// picks the response format from the accept header for everything the request produces,
+/// including rejections from the auth middlewares
+pub async fn wire_format_middleware(
+    req: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let format = WireFormat::from_accept(req.headers());
+    WIRE_FORMAT.scope(format, next.run(req)).await
+}
+
 pub async fn auth_middleware(
     State(app_state): State<AppState>,
     mut req: axum::extract::Request,
@@ -344,7 +658,7 @@ pub async fn auth_middleware(
     let result = {
         let headers = req.headers();
         // let jar = CookieJar::from_headers(headers);
-        check_auth(&app_state, &headers).await
+        check_auth(&app_state, &headers, api_token_scope(&req)).await
     };
     match result {
         Ok((token, user)) => {
@@ -362,24 +676,174 @@ pub async fn auth_middleware(
     }
 }
 
+/// same as auth_middleware but only lets staff through, handlers still check their own permission
+pub async fn moderation_middleware(
+    State(app_state): State<AppState>,
+    mut req: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let result = {
+        let headers = req.headers();
+        check_auth(&app_state, &headers, Some(ApiTokenScope::Admin)).await
+    };
+    match result {
+        Ok((token, user)) => {
+            if !UserRole::from_db(&user.role).is_staff() {
+                return ServerErr::from(AdminErr::Forbidden).into_response();
+            }
+            {
+                let extensions = req.extensions_mut();
+                extensions.insert(token);
+                extensions.insert(user);
+            }
+            next.run(req).await
+        }
+        Err(err) => err.into_response(),
+    }
+}
+
+/// scope a personal access token needs for the matched route, none for session only routes
+pub fn api_token_scope(req: &axum::extract::Request) -> Option<ApiTokenScope> {
+    req.extensions()
+        .get::<axum::extract::MatchedPath>()
+        .and_then(|path| api_token::required_scope(path.as_str()))
+}
+
+/// a personal access token in the authorization header wins over the session cookie,
+/// `scope` is what the token needs to have, none means only sessions are accepted
 pub async fn check_auth(
     app: &AppState,
     headers: &HeaderMap,
+    scope: Option<ApiTokenScope>,
 ) -> Result<(AuthToken, DBUser), ServerErr>
 where
     ServerErr: std::error::Error + 'static,
 {
     trace!("CHECKING AUTH");
-    let secret = app.get_secret().await;
-    let token = auth_token_get(headers, COOKIE).ok_or(ServerErr::AuthErr(
-        ServerAuthErr::ServerUnauthorizedNoCookie,
-    ))?;
-
-    trace!("CHECKING AUTH SESSION");
-    let session = app.db.get_session(&token).await.map_err(|err| match err {
-        DB404Err::NotFound => ServerErr::from(ServerAuthErr::ServerUnauthorizedInvalidCookie),
-        _ => ServerErr::DbErr,
-    })?;
-
-    Ok((AuthToken(token), session.user))
+    let (token, user) = match api_token_get(headers) {
+        Some(secret) => check_api_token(app, secret, scope).await?,
+        None => {
+            let token = auth_token_get(headers, COOKIE).ok_or(ServerErr::AuthErr(
+                ServerAuthErr::ServerUnauthorizedNoCookie,
+            ))?;
+
+            trace!("CHECKING AUTH SESSION");
+            let session = app.db.get_session(&token).await.map_err(|err| match err {
+                DB404Err::NotFound => {
+                    ServerErr::from(ServerAuthErr::ServerUnauthorizedInvalidCookie)
+                }
+                _ => ServerErr::DbErr,
+            })?;
+            (AuthToken(token), session.user)
+        }
+    };
+
+    if user.banned {
+        return Err(ServerAuthErr::ServerBanned.into());
+    }
+
+    if user.suspended_until > app.time().await {
+        return Err(ServerAuthErr::ServerSuspended(user.suspended_until).into());
+    }
+
+    Ok((token, user))
+}
+
+async fn check_api_token(
+    app: &AppState,
+    secret: String,
+    scope: Option<ApiTokenScope>,
+) -> Result<(AuthToken, DBUser), ServerErr> {
+    trace!("CHECKING AUTH API TOKEN");
+    let time = app.time().await;
+    let auth = app
+        .db
+        .get_api_token_auth(
+            time,
+            hash_api_token(&secret),
+            API_TOKEN_LAST_USED_RESOLUTION_NS,
+        )
+        .await
+        .map_err(|err| match err {
+            DB404Err::NotFound => ServerErr::from(ServerAuthErr::ServerApiTokenInvalid),
+            _ => ServerErr::DbErr,
+        })?;
+
+    if auth.token.expires != 0 && auth.token.expires <= time {
+        return Err(ServerAuthErr::ServerApiTokenExpired.into());
+    }
+
+    let Some(scope) = scope else {
+        return Err(ServerAuthErr::ServerApiTokenNotAccepted.into());
+    };
+    let has_scope = auth
+        .token
+        .scopes
+        .iter()
+        .any(|v| ApiTokenScope::from_db(v) == Some(scope));
+    if !has_scope {
+        return Err(ServerAuthErr::ServerApiTokenScope(scope.to_string()).into());
+    }
+
+    Ok((AuthToken(secret), auth.user))
+}
+
+/// address the request came from, forwarded headers only count when the peer is one of the trusted proxies.
+/// empty when the server wasn't started with connect info, like in tests
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientIp(pub String);
+
+impl axum::extract::FromRequestParts<AppState> for ClientIp {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut http::request::Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<axum::extract::ConnectInfo<SocketAddr>>()
+            .map(|info| info.0.ip());
+        Ok(Self(client_ip(
+            peer,
+            &parts.headers,
+            &state.settings.site.trusted_proxies,
+        )))
+    }
+}
+
+/// the first address in x-forwarded-for, read from the right, that isn't a trusted proxy itself
+pub fn client_ip(peer: Option<IpAddr>, headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> String {
+    let Some(peer) = peer else {
+        return String::new();
+    };
+    if !trusted_proxies.contains(&peer) {
+        return peer.to_string();
+    }
+
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    header("x-forwarded-for")
+        .and_then(|v| {
+            v.rsplit(',')
+                .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+                .find(|ip| !trusted_proxies.contains(ip))
+        })
+        .or_else(|| header("x-real-ip").and_then(|ip| ip.trim().parse::<IpAddr>().ok()))
+        .unwrap_or(peer)
+        .to_string()
+}
+
+/// request metadata for the audit log
+pub fn audit_meta(actor: Option<RecordId>, client_ip: &ClientIp, headers: &HeaderMap) -> DBAudit {
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string())
+        .unwrap_or_default();
+
+    DBAudit {
+        actor,
+        ip: client_ip.0.clone(),
+        user_agent,
+    }
 }
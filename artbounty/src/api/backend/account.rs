@@ -0,0 +1,651 @@
+use axum::{
+    Extension,
+    body::Body,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+};
+use http::{HeaderMap, HeaderValue, header};
+use surrealdb::types::ToSql;
+use tokio_util::io::ReaderStream;
+use tracing::trace;
+
+use crate::{
+    api::{
+        ServerDesErr, ServerErr, ServerReq, ServerRes,
+        app_state::AppState,
+        backend::{ClientIp, audit_meta, to_data_export_path},
+        shared::account::{
+            ACCOUNT_DELETION_GRACE_NS, AccountDeletionErr, AccountDeletionMode, DataExportErr,
+            UserAccountDeletion, UserDataExport,
+        },
+        verify_password,
+    },
+    db::{DB404Err, DBDataExportErr, DBUser, account::DBAccountDeletionMode},
+};
+
+fn account_deletion_404_err(err: DB404Err) -> ServerErr {
+    match err {
+        DB404Err::NotFound => AccountDeletionErr::NotFound.into(),
+        DB404Err::DB(_) => ServerErr::DbErr,
+    }
+}
+
+/// first step, the deletion only gets scheduled once the emailed link is opened
+pub async fn request_account_deletion(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::RequestAccountDeletion { password, mode } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected RequestAccountDeletion, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    verify_password(password, db_user.password.clone())
+        .inspect_err(|err| trace!("passwords verification failed {err}"))
+        .map_err(|_| ServerErr::from(AccountDeletionErr::WrongPassword))?;
+
+    let mode = match mode {
+        AccountDeletionMode::Anonymize => DBAccountDeletionMode::Anonymize,
+        AccountDeletionMode::Delete => DBAccountDeletionMode::Delete,
+    };
+    let deletion = app
+        .db
+        .add_account_deletion(time, db_user.id.clone(), mode, app.new_exp().await)
+        .await
+        .map_err(|_| ServerErr::DbErr)?;
+    app.send_email_account_deletion(time, db_user.email.clone(), deletion.id.key.to_sql())
+        .await?;
+
+    Ok(ServerRes::AccountDeletion(Some(deletion.into())))
+}
+
+pub async fn confirm_account_deletion(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::ConfirmToken { token } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected ConfirmToken, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    let deletion = app
+        .db
+        .confirm_account_deletion(
+            time,
+            db_user.id.clone(),
+            token,
+            time + ACCOUNT_DELETION_GRACE_NS,
+            audit_meta(Some(db_user.id.clone()), &client_ip, &headers),
+        )
+        .await
+        .map_err(account_deletion_404_err)?;
+
+    Ok(ServerRes::AccountDeletion(Some(deletion.into())))
+}
+
+pub async fn cancel_account_deletion(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::None = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected None, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    app.db
+        .delete_account_deletion(
+            time,
+            db_user.id.clone(),
+            audit_meta(Some(db_user.id.clone()), &client_ip, &headers),
+        )
+        .await
+        .map_err(account_deletion_404_err)?;
+
+    Ok(ServerRes::Ok)
+}
+
+pub async fn get_account_deletion(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::None = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected None, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    let deletion = match app.db.get_account_deletion(time, db_user.id.clone()).await {
+        Ok(deletion) => Some(UserAccountDeletion::from(deletion)),
+        Err(DB404Err::NotFound) => None,
+        Err(DB404Err::DB(_)) => return Err(ServerErr::DbErr),
+    };
+
+    Ok(ServerRes::AccountDeletion(deletion))
+}
+
+pub async fn add_data_export(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::None = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected None, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    let export = app
+        .db
+        .add_data_export(
+            time,
+            db_user.id.clone(),
+            audit_meta(Some(db_user.id.clone()), &client_ip, &headers),
+        )
+        .await
+        .map_err(|err| match err {
+            DBDataExportErr::AlreadyPending => ServerErr::from(DataExportErr::AlreadyPending),
+            DBDataExportErr::DB(_) => ServerErr::DbErr,
+        })?;
+
+    Ok(ServerRes::DataExport(export.into()))
+}
+
+pub async fn get_data_exports(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::None = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected None, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    let exports = app
+        .db
+        .get_data_exports(time, db_user.id.clone())
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserDataExport::from)
+        .collect::<Vec<UserDataExport>>();
+
+    Ok(ServerRes::DataExports(exports))
+}
+
+/// plain GET so the browser can download it straight from a link
+pub async fn download_data_export(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    Path(export_key): Path<String>,
+) -> Result<Response, ServerErr> {
+    let time = app.time().await;
+
+    let export = app
+        .db
+        .get_data_export(time, db_user.id.clone(), export_key)
+        .await
+        .map_err(|err| match err {
+            DB404Err::NotFound => ServerErr::from(DataExportErr::NotFound),
+            DB404Err::DB(_) => ServerErr::DbErr,
+        })?;
+    let key = export.id.key.to_sql();
+    let path = to_data_export_path(app.get_exports_path().await, &key);
+    let file = tokio::fs::File::open(&path)
+        .await
+        .map_err(|_| ServerErr::from(DataExportErr::NotFound))?;
+
+    let disposition = format!(
+        "attachment; filename=\"artbounty_{}_{key}.zip\"",
+        db_user.username
+    );
+    let mut response = Body::from_stream(ReaderStream::new(file)).into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/zip"),
+    );
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&disposition).map_err(|_| ServerErr::InternalServerErr)?,
+    );
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use crate::api::backend::{proccess_data_exports, purge_account_deletions};
+    use crate::api::shared::account::{
+        ACCOUNT_DELETION_GRACE_NS, AccountDeletionErr, AccountDeletionMode,
+        DATA_EXPORT_RETENTION_NS, DataExportErr, DataExportStatus, UserAccountDeletion,
+        UserDataExport,
+    };
+    use surrealdb::types::ToSql;
+
+    use crate::api::shared::cursor::CursorRange;
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Api, Order, ServerErr, ServerRes};
+    use crate::db::DBSentEmailReason;
+    use crate::path::link_api_data_export;
+
+    impl ApiTestApp {
+        pub async fn request_account_deletion(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            password: impl Into<String>,
+            mode: AccountDeletionMode,
+        ) -> Result<Option<UserAccountDeletion>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .request_account_deletion(password, mode)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::AccountDeletion(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected AccountDeletion, got {res:?}"),
+            }
+        }
+
+        pub async fn confirm_account_deletion(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            confirm_key: impl Into<String>,
+        ) -> Result<Option<UserAccountDeletion>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .confirm_account_deletion(confirm_key)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::AccountDeletion(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected AccountDeletion, got {res:?}"),
+            }
+        }
+
+        pub async fn cancel_account_deletion(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+        ) -> Result<(), ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .cancel_account_deletion()
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Ok => Ok(()),
+                res => panic!("fix code, invalid response, expected Ok, got {res:?}"),
+            }
+        }
+
+        pub async fn get_account_deletion(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+        ) -> Result<Option<UserAccountDeletion>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_account_deletion()
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::AccountDeletion(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected AccountDeletion, got {res:?}"),
+            }
+        }
+
+        pub async fn add_data_export(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+        ) -> Result<UserDataExport, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .add_data_export()
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::DataExport(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected DataExport, got {res:?}"),
+            }
+        }
+
+        pub async fn get_data_exports(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+        ) -> Result<Vec<UserDataExport>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_data_exports()
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::DataExports(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected DataExports, got {res:?}"),
+            }
+        }
+
+        pub async fn download_data_export(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            export_key: impl AsRef<str>,
+        ) -> Result<Vec<u8>, ServerErr> {
+            self.set_time(server_time).await;
+            let cookie = crate::api::create_auth_header(auth_token);
+            let url = self
+                .api
+                .server
+                .server_url(&link_api_data_export(export_key))
+                .unwrap();
+            let response = reqwest::Client::new()
+                .get(url)
+                .header(http::header::COOKIE, cookie)
+                .send()
+                .await
+                .unwrap();
+            let status = response.status();
+            let bytes = response.bytes().await.unwrap().to_vec();
+            if status.is_success() {
+                return Ok(bytes);
+            }
+            match rkyv::from_bytes::<Result<ServerRes, ServerErr>, rkyv::rancor::Error>(&bytes)
+                .unwrap()
+            {
+                Err(err) => Err(err),
+                Ok(res) => panic!("fix code, expected an error, got {res:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_data_export() {
+        crate::init_test_log();
+        let app = ApiTestApp::new(1).await;
+        let img_path = "../assets/upload.svg";
+
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let post = app
+            .add_post(1, &auth_token, "title1", "cat", "one")
+            .await
+            .unwrap();
+        app.add_post_file(1, &auth_token, post.key.clone(), img_path)
+            .await
+            .unwrap();
+        app.add_post_comment(2, &auth_token, post.key.clone(), None, "hello")
+            .await
+            .unwrap();
+
+        let export = app.add_data_export(3, &auth_token).await.unwrap();
+        assert_eq!(export.status, DataExportStatus::Pending);
+        let result = app.add_data_export(3, &auth_token).await;
+        assert_eq!(
+            result,
+            Err(ServerErr::DataExportErr(DataExportErr::AlreadyPending))
+        );
+        let result = app.download_data_export(3, &auth_token, &export.key).await;
+        assert_eq!(
+            result,
+            Err(ServerErr::DataExportErr(DataExportErr::NotFound))
+        );
+
+        app.set_time(4).await;
+        proccess_data_exports(&app.state).await.unwrap();
+        let exports = app.get_data_exports(4, &auth_token).await.unwrap();
+        assert_eq!(exports.len(), 1);
+        assert_eq!(exports[0].status, DataExportStatus::Ready);
+        assert_eq!(exports[0].expires, 4 + DATA_EXPORT_RETENTION_NS);
+        assert!(
+            app.get_data_exports(4, &auth_token2)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let emails = app
+            .state
+            .db
+            .get_sent_email_by_email("hey@heyadora.com")
+            .await
+            .unwrap();
+        assert_eq!(
+            emails[0].reason,
+            DBSentEmailReason::DataExportReady.to_string()
+        );
+
+        // only the owner can download it
+        let result = app.download_data_export(5, &auth_token2, &export.key).await;
+        assert_eq!(
+            result,
+            Err(ServerErr::DataExportErr(DataExportErr::NotFound))
+        );
+        let bytes = app
+            .download_data_export(5, &auth_token, &export.key)
+            .await
+            .unwrap();
+        assert_eq!(bytes.len() as u64, exports[0].size_bytes);
+
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut profile = String::new();
+        zip.by_name("profile.json")
+            .unwrap()
+            .read_to_string(&mut profile)
+            .unwrap();
+        assert!(profile.contains("hey@heyadora.com"));
+        let mut comments = String::new();
+        zip.by_name("comments.json")
+            .unwrap()
+            .read_to_string(&mut comments)
+            .unwrap();
+        assert!(comments.contains("hello"));
+        for name in ["posts.json", "likes.json", "sent_emails.json"] {
+            assert!(zip.by_name(name).is_ok());
+        }
+        assert!(zip.file_names().any(|name| name.starts_with("files/")));
+
+        // expired exports are removed along with their zip
+        app.set_time(4 + DATA_EXPORT_RETENTION_NS).await;
+        proccess_data_exports(&app.state).await.unwrap();
+        assert!(
+            app.get_data_exports(4 + DATA_EXPORT_RETENTION_NS, &auth_token)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let result = app
+            .download_data_export(4 + DATA_EXPORT_RETENTION_NS, &auth_token, &export.key)
+            .await;
+        assert_eq!(
+            result,
+            Err(ServerErr::DataExportErr(DataExportErr::NotFound))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_account_deletion() {
+        crate::init_test_log();
+        let app = ApiTestApp::new(100).await;
+
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        app.add_post(1, &auth_token, "title1", "cat", "one")
+            .await
+            .unwrap();
+        let post2 = app
+            .add_post(1, &auth_token2, "title2", "cat", "two")
+            .await
+            .unwrap();
+        app.add_post_comment(2, &auth_token, post2.key.clone(), None, "hello")
+            .await
+            .unwrap();
+        app.add_post_like(2, &auth_token, post2.key.clone())
+            .await
+            .unwrap();
+
+        let result = app
+            .request_account_deletion(3, &auth_token, "wrong", AccountDeletionMode::Delete)
+            .await;
+        assert_eq!(
+            result,
+            Err(ServerErr::AccountDeletionErr(
+                AccountDeletionErr::WrongPassword
+            ))
+        );
+
+        let deletion = app
+            .request_account_deletion(
+                3,
+                &auth_token,
+                "pas$word123456789",
+                AccountDeletionMode::Delete,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!deletion.confirmed);
+        let emails = app
+            .state
+            .db
+            .get_sent_email_by_email("hey@heyadora.com")
+            .await
+            .unwrap();
+        let link = emails[0].body.clone();
+        let confirm_key = link.split("delete_account=").nth(1).unwrap().to_string();
+
+        let result = app
+            .confirm_account_deletion(4, &auth_token2, confirm_key.clone())
+            .await;
+        assert_eq!(
+            result,
+            Err(ServerErr::AccountDeletionErr(AccountDeletionErr::NotFound))
+        );
+        let deletion = app
+            .confirm_account_deletion(4, &auth_token, confirm_key.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(deletion.confirmed);
+        assert_eq!(deletion.delete_at, 4 + ACCOUNT_DELETION_GRACE_NS);
+
+        // cancelling during the grace period keeps the account
+        app.cancel_account_deletion(5, &auth_token).await.unwrap();
+        assert_eq!(
+            app.get_account_deletion(5, &auth_token).await.unwrap(),
+            None
+        );
+        app.set_time(4 + ACCOUNT_DELETION_GRACE_NS).await;
+        purge_account_deletions(&app.state).await.unwrap();
+        assert!(app.state.db.get_user_by_username("hey").await.is_ok());
+
+        let deletion = app
+            .request_account_deletion(
+                6,
+                &auth_token,
+                "pas$word123456789",
+                AccountDeletionMode::Delete,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!deletion.confirmed);
+        let emails = app
+            .state
+            .db
+            .get_sent_email_by_email("hey@heyadora.com")
+            .await
+            .unwrap();
+        let confirm_key = emails[0]
+            .body
+            .split("delete_account=")
+            .nth(1)
+            .unwrap()
+            .to_string();
+        app.confirm_account_deletion(7, &auth_token, confirm_key)
+            .await
+            .unwrap();
+
+        app.set_time(6 + ACCOUNT_DELETION_GRACE_NS).await;
+        purge_account_deletions(&app.state).await.unwrap();
+        assert!(app.state.db.get_user_by_username("hey").await.is_ok());
+
+        app.set_time(7 + ACCOUNT_DELETION_GRACE_NS).await;
+        purge_account_deletions(&app.state).await.unwrap();
+        assert!(app.state.db.get_user_by_username("hey").await.is_err());
+        assert!(
+            app.state
+                .db
+                .get_user_by_email("hey@heyadora.com")
+                .await
+                .is_err()
+        );
+
+        // sessions, posts, likes and comments are gone
+        let result = app.get_account_deletion(8, &auth_token).await;
+        assert!(result.is_err());
+        let posts = app.state.db.get_post_all().await.unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].id.key.to_sql(), post2.key);
+        assert_eq!(posts[0].favorites, 0);
+        let comments = app
+            .get_post_comments(
+                8,
+                &auth_token2,
+                post2.key.clone(),
+                None,
+                10,
+                CursorRange::None,
+                Order::OneTwoThree,
+            )
+            .await
+            .unwrap();
+        assert!(comments.is_empty());
+
+        // the email can be used again
+        app.register(9, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+    }
+}
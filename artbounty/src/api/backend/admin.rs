@@ -0,0 +1,633 @@
+use axum::{Extension, extract::State};
+use http::HeaderMap;
+
+use crate::{
+    api::{
+        AuthToken, ServerDesErr, ServerErr, ServerReq, ServerRes, UserPost,
+        app_state::AppState,
+        backend::{ClientIp, audit_meta},
+        shared::{
+            admin::{AdminErr, AdminUser, Permission, UserRole},
+            live::LiveEvent,
+            post_comment::UserPostComment,
+            rating::PostRatingErr,
+        },
+    },
+    db::{DB404Err, DBUser, create_user_id},
+};
+
+pub fn require(db_user: &DBUser, permission: Permission) -> Result<(), ServerErr> {
+    if UserRole::from_db(&db_user.role).can(permission) {
+        Ok(())
+    } else {
+        Err(AdminErr::Forbidden.into())
+    }
+}
+
+pub fn require_other(db_user: &DBUser, user_key: &str) -> Result<(), ServerErr> {
+    if db_user.id == create_user_id(user_key) {
+        Err(AdminErr::SelfTarget.into())
+    } else {
+        Ok(())
+    }
+}
+
+fn user_404_err(user_key: String) -> impl FnOnce(DB404Err) -> ServerErr {
+    move |err| match err {
+        DB404Err::NotFound => AdminErr::UserNotFound(user_key).into(),
+        DB404Err::DB(_) => ServerErr::DbErr,
+    }
+}
+
+pub async fn get_users(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::AdminGetUsers {
+        query,
+        limit,
+        time_range,
+        order,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AdminGetUsers, received: {req:?}"
+        ))));
+    };
+
+    require(&db_user, Permission::SuspendUser)?;
+    let show_email = UserRole::from_db(&db_user.role).can(Permission::ManageUser);
+
+    let users = app
+        .db
+        .user_search(query, limit, time_range, order)
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(AdminUser::from)
+        .map(|user| AdminUser {
+            email: user.email.filter(|_| show_email),
+            ..user
+        })
+        .collect::<Vec<AdminUser>>();
+
+    Ok(ServerRes::AdminUsers(users))
+}
+
+pub async fn get_user_posts(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::AdminGetUserPosts {
+        username,
+        limit,
+        time_range,
+        order,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AdminGetUserPosts, received: {req:?}"
+        ))));
+    };
+    require(&db_user, Permission::HidePost)?;
+
+    let posts = app
+        .db
+        .get_user_posts_moderation(username, limit, time_range, order)
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserPost::from)
+        .collect::<Vec<UserPost>>();
+
+    Ok(ServerRes::Posts(posts))
+}
+
+pub async fn update_user_storage(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::AdminUpdateUserStorage {
+        user_key,
+        max_storage_bytes,
+        max_storage_per_file_bytes,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AdminUpdateUserStorage, received: {req:?}"
+        ))));
+    };
+    require(&db_user, Permission::ManageUser)?;
+    if max_storage_per_file_bytes > max_storage_bytes {
+        return Err(AdminErr::InvalidStorage.into());
+    }
+    let time = app.time().await;
+
+    let user = app
+        .db
+        .update_user_storage(
+            time,
+            create_user_id(user_key.clone()),
+            max_storage_bytes as usize,
+            max_storage_per_file_bytes as usize,
+            audit_meta(Some(db_user.id.clone()), &client_ip, &headers),
+        )
+        .await
+        .map_err(user_404_err(user_key))?;
+
+    Ok(ServerRes::AdminUser(user.into()))
+}
+
+pub async fn update_user_role(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::AdminUpdateUserRole { user_key, role } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AdminUpdateUserRole, received: {req:?}"
+        ))));
+    };
+    require(&db_user, Permission::ManageUser)?;
+    require_other(&db_user, &user_key)?;
+    let time = app.time().await;
+
+    let user = app
+        .db
+        .update_user_role(
+            time,
+            create_user_id(user_key.clone()),
+            role.to_string(),
+            audit_meta(Some(db_user.id.clone()), &client_ip, &headers),
+        )
+        .await
+        .map_err(user_404_err(user_key))?;
+
+    Ok(ServerRes::AdminUser(user.into()))
+}
+
+pub async fn update_user_ban(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::AdminUpdateUserBan { user_key, banned } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AdminUpdateUserBan, received: {req:?}"
+        ))));
+    };
+    require(&db_user, Permission::BanUser)?;
+    require_other(&db_user, &user_key)?;
+    let time = app.time().await;
+
+    let user = app
+        .db
+        .update_user_banned(
+            time,
+            create_user_id(user_key.clone()),
+            banned,
+            audit_meta(Some(db_user.id.clone()), &client_ip, &headers),
+        )
+        .await
+        .map_err(user_404_err(user_key))?;
+
+    Ok(ServerRes::AdminUser(user.into()))
+}
+
+pub async fn update_post_show(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::AdminUpdatePostShow { post_key, show } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AdminUpdatePostShow, received: {req:?}"
+        ))));
+    };
+    require(&db_user, Permission::HidePost)?;
+    let time = app.time().await;
+
+    let post = app
+        .db
+        .update_post_show(
+            time,
+            post_key,
+            show,
+            audit_meta(Some(db_user.id.clone()), &client_ip, &headers),
+        )
+        .await
+        .map_err(|err| match err {
+            DB404Err::NotFound => AdminErr::PostNotFound.into(),
+            DB404Err::DB(_) => ServerErr::DbErr,
+        })?;
+
+    Ok(ServerRes::Post(post.into()))
+}
+
+/// moderator override, locks the rating so the author can't change it back
+pub async fn update_post_rating(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::EditPostRating { post_key, rating } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected EditPostRating, received: {req:?}"
+        ))));
+    };
+    require(&db_user, Permission::HidePost)?;
+    let time = app.time().await;
+
+    let post = app
+        .db
+        .update_post_rating_moderation(
+            time,
+            post_key,
+            rating.into(),
+            audit_meta(Some(db_user.id.clone()), &client_ip, &headers),
+        )
+        .await
+        .map_err(|err| match err {
+            DB404Err::NotFound => PostRatingErr::NotFound.into(),
+            DB404Err::DB(_) => ServerErr::DbErr,
+        })?;
+
+    Ok(ServerRes::Post(post.into()))
+}
+
+pub async fn delete_post_comment(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::CommentId { comment_id } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected CommentId, received: {req:?}"
+        ))));
+    };
+    require(&db_user, Permission::DeleteComment)?;
+    let time = app.time().await;
+
+    let comment = app
+        .db
+        .get_post_comment(comment_id.clone())
+        .await
+        .map(UserPostComment::from)
+        .map_err(|err| match err {
+            DB404Err::NotFound => AdminErr::CommentNotFound.into(),
+            DB404Err::DB(_) => ServerErr::DbErr,
+        })?;
+
+    app.db
+        .delete_post_comment_moderation(
+            time,
+            comment_id,
+            audit_meta(Some(db_user.id.clone()), &client_ip, &headers),
+        )
+        .await
+        .map_err(|err| match err {
+            DB404Err::NotFound => AdminErr::CommentNotFound.into(),
+            DB404Err::DB(_) => ServerErr::DbErr,
+        })?;
+
+    app.live.publish(LiveEvent::CommentDeleted {
+        post_key: comment.post_key,
+        comment_key: comment.key,
+        parent_key: comment.parent_key,
+    });
+
+    Ok(ServerRes::Ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::settings::Settings;
+    use crate::api::shared::admin::{AdminErr, AdminUser, UserRole};
+    use crate::api::shared::cursor::{Cursor, CursorRange};
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Api, Order, ServerAuthErr, ServerErr, ServerRes, TimeRange, UserPost};
+
+    impl ApiTestApp {
+        pub async fn admin_get_users(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            query: impl Into<String>,
+        ) -> Result<Vec<AdminUser>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .admin_get_users(query, 100, TimeRange::None, Order::OneTwoThree)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::AdminUsers(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected AdminUsers, got {res:?}"),
+            }
+        }
+
+        pub async fn admin_get_user_posts(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            username: impl Into<String>,
+        ) -> Result<Vec<UserPost>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .admin_get_user_posts(username, 100, TimeRange::None, Order::ThreeTwoOne)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Posts(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Posts, got {res:?}"),
+            }
+        }
+
+        pub async fn admin_update_user_storage(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            user_key: impl Into<String>,
+            max_storage_bytes: u64,
+            max_storage_per_file_bytes: u64,
+        ) -> Result<AdminUser, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .admin_update_user_storage(user_key, max_storage_bytes, max_storage_per_file_bytes)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::AdminUser(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected AdminUser, got {res:?}"),
+            }
+        }
+
+        pub async fn admin_update_user_role(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            user_key: impl Into<String>,
+            role: UserRole,
+        ) -> Result<AdminUser, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .admin_update_user_role(user_key, role)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::AdminUser(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected AdminUser, got {res:?}"),
+            }
+        }
+
+        pub async fn admin_update_user_ban(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            user_key: impl Into<String>,
+            banned: bool,
+        ) -> Result<AdminUser, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .admin_update_user_ban(user_key, banned)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::AdminUser(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected AdminUser, got {res:?}"),
+            }
+        }
+
+        pub async fn admin_update_post_show(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            post_key: impl Into<String>,
+            show: bool,
+        ) -> Result<UserPost, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .admin_update_post_show(post_key, show)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Post(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Post, got {res:?}"),
+            }
+        }
+
+        pub async fn admin_delete_post_comment(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            comment_key: impl Into<String>,
+        ) -> Result<(), ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .admin_delete_post_comment(comment_key)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Ok => Ok(()),
+                res => panic!("fix code, invalid response, expected Ok, got {res:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn api_admin_test() {
+        crate::init_test_log();
+
+        let mut settings = Settings::new_testing(1);
+        settings.auth.admin_emails = vec!["admin@heyadora.com".to_string()];
+        let app = ApiTestApp::new_with_settings(settings).await;
+
+        let admin_token = app
+            .register(0, "admin", "admin@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let mod_token = app
+            .register(1, "moddy", "moddy@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token = app
+            .register(2, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        let result = app.admin_get_users(3, &auth_token, "").await;
+        assert_eq!(result, Err(AdminErr::Forbidden.into()));
+
+        let users = app.admin_get_users(3, &admin_token, "").await.unwrap();
+        assert_eq!(users.len(), 3);
+        assert_eq!(users[0].role, UserRole::Admin);
+        let admin_key = users[0].key.clone();
+        let mod_key = users[1].key.clone();
+        let user_key = users[2].key.clone();
+        let users = app.admin_get_users(3, &admin_token, "HEY@").await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].key, user_key);
+        assert_eq!(users[0].email.as_deref(), Some("hey@heyadora.com"));
+
+        let result = app
+            .admin_update_user_role(4, &admin_token, admin_key.clone(), UserRole::User)
+            .await;
+        assert_eq!(result, Err(AdminErr::SelfTarget.into()));
+        let user = app
+            .admin_update_user_role(4, &admin_token, mod_key.clone(), UserRole::Moderator)
+            .await
+            .unwrap();
+        assert_eq!(user.role, UserRole::Moderator);
+        let users = app.admin_get_users(4, &mod_token, "HEY@").await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].email, None);
+        let result = app
+            .admin_update_user_role(4, &mod_token, user_key.clone(), UserRole::Admin)
+            .await;
+        assert_eq!(result, Err(AdminErr::Forbidden.into()));
+
+        let result = app
+            .admin_update_user_storage(5, &admin_token, user_key.clone(), 10, 20)
+            .await;
+        assert_eq!(result, Err(AdminErr::InvalidStorage.into()));
+        let user = app
+            .admin_update_user_storage(5, &admin_token, user_key.clone(), 20, 10)
+            .await
+            .unwrap();
+        assert_eq!(user.max_storage_bytes, 20);
+        assert_eq!(user.max_storage_per_file_bytes, 10);
+        let result = app
+            .admin_update_user_storage(5, &admin_token, "404", 20, 10)
+            .await;
+        assert_eq!(
+            result,
+            Err(AdminErr::UserNotFound("404".to_string()).into())
+        );
+
+        let post = app
+            .add_post(6, &auth_token, "title1", "", "one")
+            .await
+            .unwrap();
+        let post = app
+            .admin_update_post_show(7, &mod_token, post.key.clone(), false)
+            .await
+            .unwrap();
+        assert!(!post.show);
+        let result = app
+            .api
+            .get_posts(
+                100,
+                CursorRange::Before(Cursor::at(9)),
+                Order::ThreeTwoOne,
+                "",
+                "",
+            )
+            .send_native()
+            .await;
+        assert_eq!(
+            result,
+            Ok(ServerRes::PostPage {
+                posts: Vec::new(),
+                next: None,
+                prev: None,
+            })
+        );
+        let posts = app
+            .admin_get_user_posts(8, &mod_token, "hey")
+            .await
+            .unwrap();
+        assert_eq!(posts.len(), 1);
+        let result = app.api.get_post(post.key.clone()).send_native().await;
+        assert!(matches!(result, Err(ServerErr::NotFoundErr(_))));
+        let result = app
+            .api
+            .get_post(post.key.clone())
+            .send_native_with_token(&auth_token)
+            .await;
+        assert!(matches!(result, Ok(ServerRes::Post(_))));
+        let result = app
+            .api
+            .get_post(post.key.clone())
+            .send_native_with_token(&mod_token)
+            .await;
+        assert!(matches!(result, Ok(ServerRes::Post(_))));
+
+        let comment = app
+            .add_post_comment(9, &auth_token, post.key.clone(), None, "hello")
+            .await
+            .unwrap();
+        app.admin_delete_post_comment(10, &mod_token, comment.key.clone())
+            .await
+            .unwrap();
+        let result = app
+            .admin_delete_post_comment(10, &mod_token, comment.key.clone())
+            .await;
+        assert_eq!(result, Err(AdminErr::CommentNotFound.into()));
+
+        let result = app
+            .admin_update_user_ban(11, &mod_token, user_key.clone(), true)
+            .await;
+        assert_eq!(result, Err(AdminErr::Forbidden.into()));
+        let user = app
+            .admin_update_user_ban(11, &admin_token, user_key.clone(), true)
+            .await
+            .unwrap();
+        assert!(user.banned);
+        let result = app
+            .api
+            .get_my_acc()
+            .send_native_with_token(&auth_token)
+            .await;
+        assert!(matches!(
+            result,
+            Err(ServerErr::AuthErr(
+                ServerAuthErr::ServerUnauthorizedInvalidCookie
+            ))
+        ));
+        let result = app
+            .api
+            .login("hey@heyadora.com", "pas$word123456789")
+            .send_native()
+            .await;
+        assert_eq!(result, Err(ServerAuthErr::ServerBanned.into()));
+
+        app.admin_update_user_ban(12, &admin_token, user_key.clone(), false)
+            .await
+            .unwrap();
+        let result = app
+            .api
+            .login("hey@heyadora.com", "pas$word123456789")
+            .send_native()
+            .await;
+        assert!(result.is_ok());
+    }
+}
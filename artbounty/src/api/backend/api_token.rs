@@ -0,0 +1,357 @@
+use axum::{Extension, extract::State};
+use http::HeaderMap;
+use rand::distr::{Alphanumeric, SampleString};
+use tracing::trace;
+
+use crate::{
+    api::{
+        ServerDesErr, ServerErr, ServerReq, ServerRes,
+        app_state::AppState,
+        backend::{ClientIp, audit_meta},
+        hash_api_token,
+        shared::{
+            admin::UserRole,
+            api_token::{
+                API_TOKEN_NAME_MAX_LENGTH, API_TOKEN_PREFIX, API_TOKEN_SECRET_LENGTH,
+                API_TOKENS_MAX, ApiTokenErr, ApiTokenScope, UserApiToken,
+            },
+        },
+    },
+    db::{DB404Err, DBApiTokenErr, DBUser},
+    path::{
+        PATH_API, PATH_API_ACC, PATH_API_COLLECTION_GET, PATH_API_COLLECTION_POSTS_GET,
+        PATH_API_COLLECTIONS_GET, PATH_API_NOTIFICATION_UNREAD_COUNT, PATH_API_NOTIFICATIONS_GET,
+        PATH_API_POST_ADD, PATH_API_POST_COLLECTIONS_GET, PATH_API_POST_COMMENT_ADD,
+        PATH_API_POST_COMMENT_DELETE, PATH_API_POST_COMMENT_GET, PATH_API_POST_COMMENT_UPDATE,
+        PATH_API_POST_DELETE, PATH_API_POST_FILE_ADD, PATH_API_POST_GET, PATH_API_POST_LIKE_ADD,
+        PATH_API_POST_LIKE_CHECK, PATH_API_POST_LIKE_DELETE, PATH_API_POST_UPDATE_DESCRIPTION,
+        PATH_API_POST_UPDATE_EMBEDDABLE, PATH_API_POST_UPDATE_RATING, PATH_API_POST_UPDATE_TAGS,
+        PATH_API_POST_UPDATE_TITLE, PATH_API_POST_UPDATE_VISIBILITY, PATH_API_POSTS_GET,
+        PATH_API_POSTS_GET_FOLLOWING, PATH_API_RATING_PREFERENCE_GET, PATH_API_REVISIONS_GET,
+        PATH_API_TRASH_GET, PATH_API_TRASH_POST_COMMENT_RESTORE, PATH_API_TRASH_POST_RESTORE,
+        PATH_API_USER_BLOCK_CHECK, PATH_API_USER_BLOCKS_GET, PATH_API_USER_FOLLOW_CHECK,
+        PATH_API_V1, PATH_API_V1_OPENAPI, PATH_API_V1_POST_LIKES,
+    },
+};
+
+/// what a personal access token needs for a route, `matched_path` includes the api prefix.
+/// routes that aren't listed only take a login session, that covers account, email and token management
+pub fn required_scope(matched_path: &str) -> Option<ApiTokenScope> {
+    let path = matched_path.strip_prefix(PATH_API).unwrap_or(matched_path);
+    if let Some(path) = path.strip_prefix(PATH_API_V1) {
+        return match path {
+            PATH_API_V1_OPENAPI => None,
+            PATH_API_V1_POST_LIKES => Some(ApiTokenScope::CommentWrite),
+            _ => Some(ApiTokenScope::Read),
+        };
+    }
+
+    match path {
+        PATH_API_ACC
+        | PATH_API_POST_GET
+        | PATH_API_POSTS_GET
+        | PATH_API_POSTS_GET_FOLLOWING
+        | PATH_API_POST_COMMENT_GET
+        | PATH_API_POST_LIKE_CHECK
+        | PATH_API_POST_COLLECTIONS_GET
+        | PATH_API_COLLECTION_GET
+        | PATH_API_COLLECTIONS_GET
+        | PATH_API_COLLECTION_POSTS_GET
+        | PATH_API_USER_BLOCK_CHECK
+        | PATH_API_USER_BLOCKS_GET
+        | PATH_API_USER_FOLLOW_CHECK
+        | PATH_API_NOTIFICATIONS_GET
+        | PATH_API_NOTIFICATION_UNREAD_COUNT
+        | PATH_API_RATING_PREFERENCE_GET
+        | PATH_API_REVISIONS_GET
+        | PATH_API_TRASH_GET => Some(ApiTokenScope::Read),
+        PATH_API_POST_ADD
+        | PATH_API_POST_FILE_ADD
+        | PATH_API_POST_UPDATE_TITLE
+        | PATH_API_POST_UPDATE_DESCRIPTION
+        | PATH_API_POST_UPDATE_TAGS
+        | PATH_API_POST_UPDATE_RATING
+        | PATH_API_POST_UPDATE_VISIBILITY
+        | PATH_API_POST_UPDATE_EMBEDDABLE
+        | PATH_API_POST_DELETE
+        | PATH_API_TRASH_POST_RESTORE => Some(ApiTokenScope::PostWrite),
+        PATH_API_POST_COMMENT_ADD
+        | PATH_API_POST_COMMENT_UPDATE
+        | PATH_API_POST_COMMENT_DELETE
+        | PATH_API_TRASH_POST_COMMENT_RESTORE
+        | PATH_API_POST_LIKE_ADD
+        | PATH_API_POST_LIKE_DELETE => Some(ApiTokenScope::CommentWrite),
+        _ => None,
+    }
+}
+
+pub async fn add_api_token(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::AddApiToken {
+        name,
+        mut scopes,
+        expires,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AddApiToken, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    let name = name.trim().to_string();
+    let name_len = name.chars().count();
+    if name_len == 0 || name_len > API_TOKEN_NAME_MAX_LENGTH {
+        return Err(ApiTokenErr::InvalidName.into());
+    }
+    scopes.sort_by_key(|scope| *scope as u8);
+    scopes.dedup();
+    if scopes.is_empty() {
+        return Err(ApiTokenErr::NoScopes.into());
+    }
+    if scopes.contains(&ApiTokenScope::Admin) && !UserRole::from_db(&db_user.role).is_staff() {
+        return Err(ApiTokenErr::AdminScopeForbidden.into());
+    }
+    if expires != 0 && expires <= time {
+        return Err(ApiTokenErr::InvalidExpiry.into());
+    }
+
+    let secret = format!(
+        "{API_TOKEN_PREFIX}{}",
+        Alphanumeric.sample_string(&mut rand::rng(), API_TOKEN_SECRET_LENGTH)
+    );
+    let token = app
+        .db
+        .add_api_token(
+            time,
+            db_user.id.clone(),
+            name,
+            hash_api_token(&secret),
+            scopes.iter().map(|scope| scope.to_string()).collect(),
+            expires,
+            API_TOKENS_MAX,
+            audit_meta(Some(db_user.id.clone()), &client_ip, &headers),
+        )
+        .await
+        .inspect_err(|err| trace!("failed to add api token {err}"))
+        .map_err(|err| match err {
+            DBApiTokenErr::TooMany => ApiTokenErr::TooMany.into(),
+            DBApiTokenErr::DB(_) => ServerErr::DbErr,
+        })?;
+
+    Ok(ServerRes::ApiToken {
+        token: token.into(),
+        secret,
+    })
+}
+
+pub async fn get_api_tokens(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::None = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected None, received: {req:?}"
+        ))));
+    };
+
+    let tokens = app
+        .db
+        .get_api_tokens(db_user.id.clone())
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserApiToken::from)
+        .collect();
+
+    Ok(ServerRes::ApiTokens(tokens))
+}
+
+pub async fn delete_api_token(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::DeleteApiToken { token_key } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected DeleteApiToken, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    app.db
+        .delete_api_token(
+            time,
+            db_user.id.clone(),
+            token_key,
+            audit_meta(Some(db_user.id.clone()), &client_ip, &headers),
+        )
+        .await
+        .map_err(|err| match err {
+            DB404Err::NotFound => ApiTokenErr::NotFound.into(),
+            DB404Err::DB(_) => ServerErr::DbErr,
+        })?;
+
+    Ok(ServerRes::Ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::shared::api_token::{ApiTokenErr, ApiTokenScope, UserApiToken};
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Api, ServerAuthErr, ServerErr, ServerRes};
+
+    impl ApiTestApp {
+        pub async fn add_api_token(
+            &self,
+            time: u128,
+            auth_token: impl AsRef<str>,
+            name: impl Into<String>,
+            scopes: Vec<ApiTokenScope>,
+            expires: u128,
+        ) -> Result<(UserApiToken, String), ServerErr> {
+            self.set_time(time).await;
+            let result = self
+                .api
+                .add_api_token(name, scopes, expires)
+                .send_native_with_token(auth_token)
+                .await;
+            match result {
+                Ok(ServerRes::ApiToken { token, secret }) => Ok((token, secret)),
+                Ok(res) => panic!("fix code, expected ApiToken, got {res:?}"),
+                Err(err) => Err(err),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn api_api_token_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+        let session = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        // validation
+        let result = app
+            .add_api_token(1, &session, " ", vec![ApiTokenScope::Read], 0)
+            .await;
+        assert_eq!(result, Err(ApiTokenErr::InvalidName.into()));
+        let result = app.add_api_token(1, &session, "seed", Vec::new(), 0).await;
+        assert_eq!(result, Err(ApiTokenErr::NoScopes.into()));
+        let result = app
+            .add_api_token(1, &session, "seed", vec![ApiTokenScope::Admin], 0)
+            .await;
+        assert_eq!(result, Err(ApiTokenErr::AdminScopeForbidden.into()));
+        let result = app
+            .add_api_token(1, &session, "seed", vec![ApiTokenScope::Read], 1)
+            .await;
+        assert_eq!(result, Err(ApiTokenErr::InvalidExpiry.into()));
+
+        let (read_token, read_secret) = app
+            .add_api_token(
+                1,
+                &session,
+                "reader",
+                vec![ApiTokenScope::Read, ApiTokenScope::Read],
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(read_token.scopes, vec![ApiTokenScope::Read]);
+        let (_, post_secret) = app
+            .add_api_token(2, &session, "seed", vec![ApiTokenScope::PostWrite], 10)
+            .await
+            .unwrap();
+
+        // scopes
+        app.set_time(3).await;
+        let result = app
+            .api
+            .get_my_acc()
+            .send_native_with_token(&read_secret)
+            .await;
+        assert!(matches!(result, Ok(ServerRes::Acc { .. })));
+        let result = app
+            .api
+            .get_my_acc()
+            .send_native_with_token(&post_secret)
+            .await;
+        assert_eq!(
+            result,
+            Err(ServerAuthErr::ServerApiTokenScope(ApiTokenScope::Read.to_string()).into())
+        );
+        app.add_post(3, &post_secret, "title1", "cat", "one")
+            .await
+            .unwrap();
+
+        // tokens can't manage tokens
+        let result = app
+            .add_api_token(3, &read_secret, "sneaky", vec![ApiTokenScope::Read], 0)
+            .await;
+        assert_eq!(result, Err(ServerAuthErr::ServerApiTokenNotAccepted.into()));
+
+        // expiry
+        let result = app
+            .api
+            .add_post_like("none")
+            .send_native_with_token(&post_secret)
+            .await;
+        assert_eq!(
+            result,
+            Err(ServerAuthErr::ServerApiTokenScope(ApiTokenScope::CommentWrite.to_string()).into())
+        );
+        app.set_time(10).await;
+        let result = app
+            .api
+            .get_my_acc()
+            .send_native_with_token(&post_secret)
+            .await;
+        assert_eq!(result, Err(ServerAuthErr::ServerApiTokenExpired.into()));
+
+        // listing and revoking
+        let result = app
+            .api
+            .get_api_tokens()
+            .send_native_with_token(&session)
+            .await;
+        let Ok(ServerRes::ApiTokens(tokens)) = result else {
+            panic!("expected ApiTokens, got {result:?}");
+        };
+        assert_eq!(
+            tokens.iter().map(|v| v.name.as_str()).collect::<Vec<_>>(),
+            vec!["seed", "reader"]
+        );
+        assert_eq!(tokens[1].last_used_at, 3);
+        assert_eq!(tokens[0].expires, 10);
+
+        let result = app
+            .api
+            .delete_api_token(&read_token.key)
+            .send_native_with_token(&session)
+            .await;
+        assert_eq!(result, Ok(ServerRes::Ok));
+        let result = app
+            .api
+            .delete_api_token(&read_token.key)
+            .send_native_with_token(&session)
+            .await;
+        assert_eq!(result, Err(ApiTokenErr::NotFound.into()));
+        let result = app
+            .api
+            .get_my_acc()
+            .send_native_with_token(&read_secret)
+            .await;
+        assert_eq!(result, Err(ServerAuthErr::ServerApiTokenInvalid.into()));
+    }
+}
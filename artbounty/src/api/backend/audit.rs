@@ -0,0 +1,260 @@
+use axum::{Extension, extract::State};
+
+use crate::{
+    api::{
+        AuthToken, ServerDesErr, ServerErr, ServerReq, ServerRes,
+        app_state::AppState,
+        backend::admin::require,
+        shared::{
+            admin::{AdminErr, Permission},
+            audit::{AUDIT_EVENTS_FETCH_MAX, UserAuditEvent},
+        },
+    },
+    db::{DB404Err, DBUser},
+};
+
+/// the users own security history, actions done by staff don't reveal who did them or from where
+pub async fn get_audit_events(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::GetAuditEvents {
+        limit,
+        time_range,
+        order,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected GetAuditEvents, received: {req:?}"
+        ))));
+    };
+
+    let events = app
+        .db
+        .get_audit_events(
+            Some(db_user.id.clone()),
+            true,
+            None,
+            limit.min(AUDIT_EVENTS_FETCH_MAX),
+            time_range,
+            order,
+        )
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(|event| {
+            let by_self = event.actor.as_ref().is_none_or(|v| *v == db_user.id);
+            let mut event = UserAuditEvent::from(event);
+            if !by_self {
+                event.actor_username = None;
+                event.ip = String::new();
+                event.user_agent = String::new();
+            }
+            event
+        })
+        .collect::<Vec<UserAuditEvent>>();
+
+    Ok(ServerRes::AuditEvents(events))
+}
+
+pub async fn admin_get_audit_events(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::AdminGetAuditEvents {
+        username,
+        kind,
+        limit,
+        time_range,
+        order,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AdminGetAuditEvents, received: {req:?}"
+        ))));
+    };
+    require(&db_user, Permission::ViewAudit)?;
+
+    let user_id = match username.map(|v| v.trim().to_string()) {
+        Some(username) if !username.is_empty() => {
+            let user = app
+                .db
+                .get_user_by_username(username.clone())
+                .await
+                .map_err(|err| match err {
+                    DB404Err::NotFound => AdminErr::UserNotFound(username).into(),
+                    DB404Err::DB(_) => ServerErr::DbErr,
+                })?;
+            Some(user.id)
+        }
+        _ => None,
+    };
+
+    let events = app
+        .db
+        .get_audit_events(
+            user_id,
+            false,
+            kind.map(|v| v.to_string()),
+            limit.min(AUDIT_EVENTS_FETCH_MAX),
+            time_range,
+            order,
+        )
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserAuditEvent::from)
+        .collect::<Vec<UserAuditEvent>>();
+
+    Ok(ServerRes::AuditEvents(events))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderMap;
+
+    use crate::api::backend::client_ip;
+    use crate::api::settings::Settings;
+    use crate::api::shared::admin::{AdminErr, UserRole};
+    use crate::api::shared::audit::{AuditKind, UserAuditEvent};
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Api, Order, ServerErr, ServerRes, TimeRange};
+
+    impl ApiTestApp {
+        pub async fn get_audit_events(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+        ) -> Result<Vec<UserAuditEvent>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_audit_events(100, TimeRange::None, Order::ThreeTwoOne)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::AuditEvents(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected AuditEvents, got {res:?}"),
+            }
+        }
+
+        pub async fn admin_get_audit_events(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            username: Option<&str>,
+            kind: Option<AuditKind>,
+        ) -> Result<Vec<UserAuditEvent>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .admin_get_audit_events(
+                    username.map(String::from),
+                    kind,
+                    100,
+                    TimeRange::None,
+                    Order::ThreeTwoOne,
+                )
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::AuditEvents(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected AuditEvents, got {res:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn api_audit_test() {
+        crate::init_test_log();
+
+        let mut settings = Settings::new_testing(1);
+        settings.auth.admin_emails = vec!["admin@heyadora.com".to_string()];
+        let app = ApiTestApp::new_with_settings(settings).await;
+
+        let admin_token = app
+            .register(0, "admin", "admin@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let user_token = app
+            .register(1, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        let result = app.login(2, "hey@heyadora.com", "wrong$password123").await;
+        assert!(result.is_none());
+
+        let users = app.admin_get_users(3, &admin_token, "hey@").await.unwrap();
+        app.admin_update_user_role(3, &admin_token, users[0].key.clone(), UserRole::Moderator)
+            .await
+            .unwrap();
+
+        let result = app.get_audit_events(4, &user_token).await.unwrap();
+        assert_eq!(
+            result.iter().map(|v| v.kind).collect::<Vec<_>>(),
+            vec![
+                AuditKind::RoleChange,
+                AuditKind::LoginFailed,
+                AuditKind::Login
+            ]
+        );
+        assert_eq!(result[0].actor_username, None);
+        assert_eq!(result[0].before, "user");
+        assert_eq!(result[0].after, "moderator");
+        assert_eq!(result[2].actor_username, Some("hey".to_string()));
+
+        let result = app.admin_get_audit_events(5, &user_token, None, None).await;
+        assert_eq!(result, Err(AdminErr::Forbidden.into()));
+
+        let result = app
+            .admin_get_audit_events(5, &admin_token, Some("hey"), Some(AuditKind::RoleChange))
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].actor_username, Some("admin".to_string()));
+        assert_eq!(result[0].target_username, Some("hey".to_string()));
+
+        let result = app
+            .admin_get_audit_events(5, &admin_token, Some("404"), None)
+            .await;
+        assert_eq!(
+            result,
+            Err(AdminErr::UserNotFound("404".to_string()).into())
+        );
+
+        let result = app
+            .admin_get_audit_events(5, &admin_token, None, None)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 5);
+    }
+
+    #[test]
+    fn audit_client_ip() {
+        let proxy = "10.0.0.2".parse().unwrap();
+        let client = "203.0.113.7".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "198.51.100.1, 203.0.113.7".parse().unwrap(),
+        );
+
+        // forwarded headers from anyone but a trusted proxy are ignored
+        assert_eq!(client_ip(Some(client), &headers, &[]), "203.0.113.7");
+        assert_eq!(client_ip(Some(proxy), &headers, &[]), "10.0.0.2");
+        assert_eq!(client_ip(None, &headers, &[proxy]), "");
+
+        assert_eq!(client_ip(Some(proxy), &headers, &[proxy]), "203.0.113.7");
+        assert_eq!(
+            client_ip(Some(proxy), &HeaderMap::new(), &[proxy]),
+            "10.0.0.2"
+        );
+        headers.insert("x-forwarded-for", "203.0.113.7, 10.0.0.3".parse().unwrap());
+        let proxies = [proxy, "10.0.0.3".parse().unwrap()];
+        assert_eq!(client_ip(Some(proxy), &headers, &proxies), "203.0.113.7");
+    }
+}
@@ -1,6 +1,11 @@
 use surrealdb::types::ToSql;
 
 use crate::api::app_state::AppState;
+use crate::api::backend::v1::V1User;
+use crate::api::backend::webhook::dispatch_webhook_event;
+use crate::api::backend::{ClientIp, audit_meta};
+use crate::api::shared::admin::UserRole;
+use crate::api::shared::webhook::WebhookEvent;
 use crate::api::{
     AuthToken, ChangeUsernameErr, EmailChangeErr, EmailChangeNewErr, EmailChangeStage,
     EmailChangeTokenErr, Server404Err, ServerAddPostErr, ServerAuthErr, ServerDecodeInviteErr,
@@ -9,14 +14,18 @@ use crate::api::{
     auth_token_get, hash_password, verify_password,
 };
 use crate::db::DB404Err;
+use crate::db::audit::{DBAudit, DBAuditKind};
 use crate::db::{AddUserErr, DBEmailIsTakenErr};
 use crate::valid::auth::{proccess_email, proccess_password, proccess_username};
 use axum::extract::State;
+use http::HeaderMap;
 use http::header::COOKIE;
 use tracing::{debug, error, info, trace};
 
 pub async fn register(
     State(app_state): State<AppState>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
     req: ServerReq,
 ) -> Result<ServerRes, ServerErr> {
     let ServerReq::Register {
@@ -88,6 +97,21 @@ pub async fn register(
             _ => ServerErr::DbErr,
         })?;
 
+    let audit = audit_meta(Some(user.id.clone()), &client_ip, &headers);
+
+    if app_state.settings.auth.admin_emails.contains(&user.email) {
+        app_state
+            .db
+            .update_user_role(
+                time_ns,
+                user.id.clone(),
+                UserRole::Admin.to_string(),
+                audit.clone(),
+            )
+            .await
+            .map_err(|_| ServerErr::DbErr)?;
+    }
+
     let result = app_state
         .db
         .update_invite_used(time_ns, invite_token.clone())
@@ -97,16 +121,31 @@ pub async fn register(
 
     let session = app_state
         .db
-        .add_session(time_ns, &user.username)
+        .add_session(time_ns, &user.username, audit)
         .await
         .map_err(|err| ServerErr::DbErr)?;
 
     let token = session.id.key.to_sql();
 
+    let data = serde_json::json!({ "user": V1User::from(user) });
+    dispatch_webhook_event(
+        &app_state,
+        time_ns,
+        WebhookEvent::UserRegistered,
+        None,
+        data,
+    )
+    .await;
+
     Ok(ServerRes::SetAuthCookie { token })
 }
 
-pub async fn login(State(app): State<AppState>, req: ServerReq) -> Result<ServerRes, ServerErr> {
+pub async fn login(
+    State(app): State<AppState>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
     let ServerReq::Login { email, password } = req else {
         return Err(
             ServerDesErr::ServerWrongInput(format!("expected Login, received: {req:?}")).into(),
@@ -122,13 +161,37 @@ pub async fn login(State(app): State<AppState>, req: ServerReq) -> Result<Server
         .inspect_err(|err| trace!("user not found - {err}"))
         .map_err(|_| ServerErr::LoginErr(ServerLoginErr::WrongCredentials))?;
 
-    verify_password(password, user.password)
-        .inspect_err(|err| trace!("passwords verification failed {err}"))
-        .map_err(|_| ServerErr::LoginErr(ServerLoginErr::WrongCredentials))?;
+    let audit = audit_meta(Some(user.id.clone()), &client_ip, &headers);
+
+    if let Err(err) = verify_password(password, user.password) {
+        trace!("passwords verification failed {err}");
+        let _ = app
+            .db
+            .add_audit_event(
+                time,
+                DBAudit {
+                    actor: None,
+                    ..audit
+                },
+                DBAuditKind::LoginFailed,
+                Some(user.id.clone()),
+            )
+            .await
+            .inspect_err(|err| error!("failed to record failed login {err}"));
+        return Err(ServerErr::LoginErr(ServerLoginErr::WrongCredentials));
+    }
+
+    if user.banned {
+        return Err(ServerAuthErr::ServerBanned.into());
+    }
+
+    if user.suspended_until > time {
+        return Err(ServerAuthErr::ServerSuspended(user.suspended_until).into());
+    }
 
     let session = app
         .db
-        .add_session(time_ns, &user.username)
+        .add_session(time_ns, &user.username, audit)
         .await
         .map_err(|err| ServerErr::DbErr)?;
 
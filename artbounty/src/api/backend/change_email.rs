@@ -1,4 +1,5 @@
 use crate::api::app_state::AppState;
+use crate::api::backend::{ClientIp, audit_meta};
 use crate::api::{
     AuthToken, EmailChangeErr, EmailChangeNewErr, EmailChangeStage, EmailChangeTokenErr,
     Server404Err, ServerAddPostErr, ServerAuthErr, ServerDecodeInviteErr, ServerDesErr, ServerErr,
@@ -11,6 +12,7 @@ use crate::db::{DB404Err, DBEmailIsTakenErr};
 use crate::valid::auth::{proccess_password, proccess_username};
 use axum::Extension;
 use axum::extract::State;
+use http::HeaderMap;
 use http::header::COOKIE;
 use tracing::{debug, error, info, trace};
 
@@ -243,6 +245,8 @@ pub async fn change_email(
     State(app): State<AppState>,
     auth_token: Extension<AuthToken>,
     db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
     req: ServerReq,
 ) -> Result<ServerRes, ServerErr> {
     type ResErr = EmailChangeNewErr;
@@ -290,7 +294,12 @@ pub async fn change_email(
 
     let result = app
         .db
-        .update_user_email(db_user.id.clone(), new.email.clone(), time)
+        .update_user_email(
+            db_user.id.clone(),
+            new.email.clone(),
+            time,
+            audit_meta(Some(db_user.id.clone()), &client_ip, &headers),
+        )
         .await
         .map_err(|err| match err {
             DBChangeEmailErr::EmailIsTaken(email) => ServerErr::from(ResErr::EmailIsTaken(email)),
@@ -1,4 +1,5 @@
 use axum::{Extension, extract::State};
+use http::HeaderMap;
 use surrealdb::types::ToSql;
 use thiserror::Error;
 use tracing::{debug, trace};
@@ -6,7 +7,10 @@ use tracing::{debug, trace};
 use crate::{
     api::{
         AuthToken, ChangePasswordErr, ChangeUsernameErr, Server404Err, ServerDesErr, ServerErr,
-        ServerErrImg, ServerReq, ServerRes, app_state::AppState, hash_password, verify_password,
+        ServerErrImg, ServerReq, ServerRes,
+        app_state::AppState,
+        backend::{ClientIp, audit_meta},
+        hash_password, verify_password,
     },
     db::{DB404Err, DBChangeUsernameErr, DBUser},
     valid::auth::proccess_password,
@@ -64,6 +68,8 @@ pub async fn send_password_change(
 
 pub async fn confirm_password_change(
     State(app): State<AppState>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
     req: ServerReq,
 ) -> Result<ServerRes, ServerErr> {
     type ResErr = ChangePasswordErr;
@@ -95,7 +101,12 @@ pub async fn confirm_password_change(
 
     let db_user = app
         .db
-        .update_user_password_by_email(time, email, new_password)
+        .update_user_password_by_email(
+            time,
+            email,
+            new_password,
+            audit_meta(None, &client_ip, &headers),
+        )
         .await
         .map_err(|err| match err {
             DB404Err::NotFound => ServerErr::from(ResErr::NotFound),
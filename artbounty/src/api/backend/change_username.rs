@@ -1,10 +1,13 @@
 use axum::{Extension, extract::State};
+use http::HeaderMap;
 use tracing::{debug, trace};
 
 use crate::{
     api::{
         AuthToken, ChangeUsernameErr, ServerDesErr, ServerErr, ServerReq, ServerRes,
-        app_state::AppState, verify_password,
+        app_state::AppState,
+        backend::{ClientIp, audit_meta},
+        verify_password,
     },
     db::{DBChangeUsernameErr, DBUser},
 };
@@ -13,6 +16,8 @@ pub async fn change_username(
     State(app_state): State<AppState>,
     auth_token: Extension<AuthToken>,
     db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
     req: ServerReq,
 ) -> Result<ServerRes, ServerErr> {
     let ServerReq::ChangeUsername { username, password } = req else {
@@ -30,7 +35,12 @@ pub async fn change_username(
 
     let result = app_state
         .db
-        .update_user_username(db_user.id.clone(), username, time)
+        .update_user_username(
+            db_user.id.clone(),
+            username,
+            time,
+            audit_meta(Some(db_user.id.clone()), &client_ip, &headers),
+        )
         .await
         .map_err(|err| match err {
             DBChangeUsernameErr::DB(err) => ServerErr::DbErr,
@@ -0,0 +1,541 @@
+use axum::{Extension, extract::State};
+use surrealdb::types::ToSql;
+
+use crate::{
+    api::{
+        AuthToken, ServerDesErr, ServerErr, ServerReq, ServerRes, UserPost,
+        app_state::AppState,
+        shared::collection::{CollectionErr, UserCollection},
+    },
+    db::{DB404Err, DBCollectionErr, DBUser},
+    valid::auth::proccess_collection_title,
+};
+
+fn collection_404_err(err: DB404Err) -> ServerErr {
+    match err {
+        DB404Err::NotFound => CollectionErr::NotFound.into(),
+        DB404Err::DB(_) => ServerErr::DbErr,
+    }
+}
+
+fn collection_err(err: DBCollectionErr) -> ServerErr {
+    match err {
+        DBCollectionErr::NotFound => CollectionErr::NotFound.into(),
+        DBCollectionErr::PostNotFound(post_key) => CollectionErr::PostNotFound(post_key).into(),
+        DBCollectionErr::Duplicate => CollectionErr::Duplicate.into(),
+        DBCollectionErr::ItemNotFound => CollectionErr::ItemNotFound.into(),
+        DBCollectionErr::OutOfRange { new_pos } => CollectionErr::OutOfRange(new_pos).into(),
+        DBCollectionErr::DB(_) => ServerErr::DbErr,
+    }
+}
+
+pub async fn add_collection(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::AddCollection { title, private } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AddCollection, received: {req:?}"
+        ))));
+    };
+    proccess_collection_title(&title).map_err(CollectionErr::InvalidTitle)?;
+    let time = app.time().await;
+
+    let collection = app
+        .db
+        .add_collection(time, db_user.id.clone(), title, private)
+        .await
+        .map_err(|_| ServerErr::DbErr)?;
+
+    Ok(ServerRes::Collection(collection.into()))
+}
+
+pub async fn get_collection(
+    State(app): State<AppState>,
+    auth_token: Extension<Option<AuthToken>>,
+    db_user: Extension<Option<DBUser>>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::CollectionId { collection_key } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected CollectionId, received: {req:?}"
+        ))));
+    };
+    let viewer_id = db_user.0.map(|v| v.id);
+
+    let collection = app
+        .db
+        .get_collection(viewer_id, collection_key)
+        .await
+        .map_err(collection_404_err)?;
+
+    Ok(ServerRes::Collection(collection.into()))
+}
+
+pub async fn update_collection(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::UpdateCollection {
+        collection_key,
+        title,
+        private,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected UpdateCollection, received: {req:?}"
+        ))));
+    };
+    proccess_collection_title(&title).map_err(CollectionErr::InvalidTitle)?;
+    let time = app.time().await;
+
+    let collection = app
+        .db
+        .update_collection(time, db_user.id.clone(), collection_key, title, private)
+        .await
+        .map_err(collection_404_err)?;
+
+    Ok(ServerRes::Collection(collection.into()))
+}
+
+pub async fn delete_collection(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::CollectionId { collection_key } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected CollectionId, received: {req:?}"
+        ))));
+    };
+
+    app.db
+        .delete_collection(db_user.id.clone(), collection_key)
+        .await
+        .map_err(collection_404_err)?;
+
+    Ok(ServerRes::Ok)
+}
+
+pub async fn get_user_collections(
+    State(app): State<AppState>,
+    auth_token: Extension<Option<AuthToken>>,
+    db_user: Extension<Option<DBUser>>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::GetUserCollections {
+        username,
+        limit,
+        time_range,
+        order,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected GetUserCollections, received: {req:?}"
+        ))));
+    };
+    let viewer_id = db_user.0.map(|v| v.id);
+
+    let collections = app
+        .db
+        .get_user_collections(viewer_id, username, limit, time_range, order)
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserCollection::from)
+        .collect::<Vec<UserCollection>>();
+
+    Ok(ServerRes::Collections(collections))
+}
+
+pub async fn add_collection_item(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::CollectionItem {
+        collection_key,
+        post_key,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected CollectionItem, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    let collection = app
+        .db
+        .add_collection_item(time, db_user.id.clone(), collection_key, post_key)
+        .await
+        .map_err(collection_err)?;
+
+    Ok(ServerRes::Collection(collection.into()))
+}
+
+pub async fn delete_collection_item(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::CollectionItem {
+        collection_key,
+        post_key,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected CollectionItem, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    app.db
+        .delete_collection_item(time, db_user.id.clone(), collection_key, post_key)
+        .await
+        .map_err(collection_err)?;
+
+    Ok(ServerRes::Ok)
+}
+
+pub async fn order_collection_item(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::OrderCollectionItem {
+        collection_key,
+        post_key,
+        new_pos,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected OrderCollectionItem, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    app.db
+        .update_collection_item_position(
+            time,
+            db_user.id.clone(),
+            collection_key,
+            post_key,
+            new_pos,
+        )
+        .await
+        .map_err(collection_err)?;
+
+    Ok(ServerRes::Ok)
+}
+
+pub async fn get_collection_posts(
+    State(app): State<AppState>,
+    auth_token: Extension<Option<AuthToken>>,
+    db_user: Extension<Option<DBUser>>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::GetCollectionPosts {
+        collection_key,
+        limit,
+        offset,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected GetCollectionPosts, received: {req:?}"
+        ))));
+    };
+    let viewer_id = db_user.0.map(|v| v.id);
+
+    let posts = app
+        .db
+        .get_collection_posts(viewer_id, collection_key, limit, offset)
+        .await
+        .map_err(collection_404_err)?
+        .into_iter()
+        .map(UserPost::from)
+        .collect::<Vec<UserPost>>();
+
+    Ok(ServerRes::Posts(posts))
+}
+
+pub async fn get_post_collections(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::PostId { post_key } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected PostId, received: {req:?}"
+        ))));
+    };
+
+    let keys = app
+        .db
+        .get_post_collections(db_user.id.clone(), post_key)
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(|v| v.key.to_sql())
+        .collect::<Vec<String>>();
+
+    Ok(ServerRes::CollectionKeys(keys))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::shared::collection::{CollectionErr, UserCollection};
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Api, Order, ServerErr, ServerRes, TimeRange, UserPost};
+
+    impl ApiTestApp {
+        pub async fn add_collection(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            title: impl Into<String>,
+            private: bool,
+        ) -> Result<UserCollection, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .add_collection(title, private)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Collection(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Collection, got {res:?}"),
+            }
+        }
+
+        pub async fn get_user_collections(
+            &self,
+            server_time: u128,
+            auth_token: Option<&str>,
+            username: impl Into<String>,
+        ) -> Result<Vec<UserCollection>, ServerErr> {
+            self.set_time(server_time).await;
+            let req =
+                self.api
+                    .get_user_collections(username, 100, TimeRange::None, Order::ThreeTwoOne);
+            let result = match auth_token {
+                Some(auth_token) => req.send_native_with_token(auth_token).await?,
+                None => req.send_native().await?,
+            };
+            match result {
+                ServerRes::Collections(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Collections, got {res:?}"),
+            }
+        }
+
+        pub async fn add_collection_item(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            collection_key: impl Into<String>,
+            post_key: impl Into<String>,
+        ) -> Result<UserCollection, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .add_collection_item(collection_key, post_key)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Collection(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Collection, got {res:?}"),
+            }
+        }
+
+        pub async fn delete_collection_item(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            collection_key: impl Into<String>,
+            post_key: impl Into<String>,
+        ) -> Result<(), ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .delete_collection_item(collection_key, post_key)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Ok => Ok(()),
+                res => panic!("fix code, invalid response, expected Ok, got {res:?}"),
+            }
+        }
+
+        pub async fn order_collection_item(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            collection_key: impl Into<String>,
+            post_key: impl Into<String>,
+            new_pos: usize,
+        ) -> Result<(), ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .order_collection_item(collection_key, post_key, new_pos)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Ok => Ok(()),
+                res => panic!("fix code, invalid response, expected Ok, got {res:?}"),
+            }
+        }
+
+        pub async fn get_collection_posts(
+            &self,
+            server_time: u128,
+            auth_token: Option<&str>,
+            collection_key: impl Into<String>,
+        ) -> Result<Vec<UserPost>, ServerErr> {
+            self.set_time(server_time).await;
+            let req = self.api.get_collection_posts(collection_key, 100, 0);
+            let result = match auth_token {
+                Some(auth_token) => req.send_native_with_token(auth_token).await?,
+                None => req.send_native().await?,
+            };
+            match result {
+                ServerRes::Posts(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Posts, got {res:?}"),
+            }
+        }
+
+        pub async fn get_post_collections(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            post_key: impl Into<String>,
+        ) -> Result<Vec<String>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_post_collections(post_key)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::CollectionKeys(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected CollectionKeys, got {res:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn api_collection_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        let post1 = app
+            .add_post(1, &auth_token2, "title1", "", "one")
+            .await
+            .unwrap();
+        let post2 = app
+            .add_post(2, &auth_token2, "title2", "", "two")
+            .await
+            .unwrap();
+
+        let result = app.add_collection(3, &auth_token, "  ", false).await;
+        assert!(matches!(
+            result,
+            Err(ServerErr::CollectionErr(CollectionErr::InvalidTitle(_)))
+        ));
+        let refs = app
+            .add_collection(3, &auth_token, "Refs for backgrounds", false)
+            .await
+            .unwrap();
+        let to_buy = app
+            .add_collection(4, &auth_token, "To buy", true)
+            .await
+            .unwrap();
+
+        let collections = app.get_user_collections(5, None, "hey").await.unwrap();
+        assert_eq!(collections.len(), 1);
+        assert_eq!(collections[0].key, refs.key);
+        let collections = app
+            .get_user_collections(5, Some(&auth_token), "hey")
+            .await
+            .unwrap();
+        assert_eq!(collections.len(), 2);
+
+        app.add_collection_item(6, &auth_token, refs.key.clone(), post1.key.clone())
+            .await
+            .unwrap();
+        let collection = app
+            .add_collection_item(6, &auth_token, refs.key.clone(), post2.key.clone())
+            .await
+            .unwrap();
+        assert_eq!(collection.items_count, 2);
+        let result = app
+            .add_collection_item(6, &auth_token, refs.key.clone(), post2.key.clone())
+            .await;
+        assert_eq!(result, Err(CollectionErr::Duplicate.into()));
+        let result = app
+            .add_collection_item(6, &auth_token2, refs.key.clone(), post2.key.clone())
+            .await;
+        assert_eq!(result, Err(CollectionErr::NotFound.into()));
+
+        app.order_collection_item(7, &auth_token, refs.key.clone(), post2.key.clone(), 0)
+            .await
+            .unwrap();
+        let posts = app
+            .get_collection_posts(8, None, refs.key.clone())
+            .await
+            .unwrap();
+        let keys = posts.into_iter().map(|v| v.key).collect::<Vec<String>>();
+        assert_eq!(keys, vec![post2.key.clone(), post1.key.clone()]);
+
+        app.add_collection_item(9, &auth_token, to_buy.key.clone(), post1.key.clone())
+            .await
+            .unwrap();
+        let result = app.get_collection_posts(9, None, to_buy.key.clone()).await;
+        assert_eq!(result, Err(CollectionErr::NotFound.into()));
+        let posts = app
+            .get_collection_posts(9, Some(&auth_token), to_buy.key.clone())
+            .await
+            .unwrap();
+        assert_eq!(posts.len(), 1);
+
+        let mut keys = app
+            .get_post_collections(10, &auth_token, post1.key.clone())
+            .await
+            .unwrap();
+        keys.sort();
+        let mut expected = vec![refs.key.clone(), to_buy.key.clone()];
+        expected.sort();
+        assert_eq!(keys, expected);
+
+        app.delete_collection_item(11, &auth_token, refs.key.clone(), post1.key.clone())
+            .await
+            .unwrap();
+        let result = app
+            .delete_collection_item(11, &auth_token, refs.key.clone(), post1.key.clone())
+            .await;
+        assert_eq!(result, Err(CollectionErr::ItemNotFound.into()));
+        let posts = app
+            .get_collection_posts(12, None, refs.key.clone())
+            .await
+            .unwrap();
+        assert_eq!(posts.len(), 1);
+    }
+}
@@ -0,0 +1,650 @@
+use axum::{
+    Extension, Json,
+    extract::{Multipart, State},
+    response::IntoResponse,
+};
+use tracing::{debug, trace};
+
+use crate::{
+    api::{
+        AuthToken, ServerAddPostFileErr, ServerDesErr, ServerErr, ServerReq, ServerRes,
+        app_state::AppState,
+        backend::post::{SavedImg, save_img_field},
+        shared::conversation::{ConversationErr, UserConversation, UserMessage},
+    },
+    db::{DB404Err, DBConversationErr, DBUser, DBUserPostFile},
+    valid::auth::proccess_message,
+};
+
+fn conversation_404_err(err: DB404Err) -> ServerErr {
+    match err {
+        DB404Err::NotFound => ConversationErr::NotFound.into(),
+        DB404Err::DB(_) => ServerErr::DbErr,
+    }
+}
+
+pub async fn add_conversation(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    type ResErr = ConversationErr;
+
+    let ServerReq::Username { username } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected Username, received: {req:?}"
+        ))));
+    };
+    if db_user.username == username {
+        return Err(ResErr::MessageSelf.into());
+    }
+    let time = app.time().await;
+
+    let conversation = app
+        .db
+        .add_conversation(time, db_user.id.clone(), username.clone())
+        .await
+        .map_err(|err| match err {
+            DBConversationErr::UserNotFound(_) => ResErr::UserNotFound(username.clone()).into(),
+            DBConversationErr::NotFound => ResErr::NotFound.into(),
+            _ => ServerErr::DbErr,
+        })?;
+
+    Ok(ServerRes::Conversation(conversation.into()))
+}
+
+pub async fn get_conversation(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::ConversationId { conversation_key } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected ConversationId, received: {req:?}"
+        ))));
+    };
+
+    let conversation = app
+        .db
+        .get_conversation(db_user.id.clone(), conversation_key)
+        .await
+        .map_err(conversation_404_err)?;
+
+    Ok(ServerRes::Conversation(conversation.into()))
+}
+
+pub async fn get_conversations(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::GetConversations {
+        limit,
+        time_range,
+        order,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected GetConversations, received: {req:?}"
+        ))));
+    };
+
+    let conversations = app
+        .db
+        .get_conversations(db_user.id.clone(), limit, time_range, order)
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserConversation::from)
+        .collect::<Vec<UserConversation>>();
+
+    Ok(ServerRes::Conversations(conversations))
+}
+
+pub async fn get_conversation_unread_count(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+) -> Result<ServerRes, ServerErr> {
+    let count = app
+        .db
+        .get_conversation_unread_count(db_user.id.clone())
+        .await
+        .map_err(|_| ServerErr::DbErr)?;
+
+    Ok(ServerRes::Count(count))
+}
+
+pub async fn read_conversation(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::ConversationId { conversation_key } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected ConversationId, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    app.db
+        .update_conversation_read(time, db_user.id.clone(), conversation_key)
+        .await
+        .map_err(conversation_404_err)?;
+
+    Ok(ServerRes::Ok)
+}
+
+async fn update_conversation_blocked(
+    app: AppState,
+    db_user: DBUser,
+    req: ServerReq,
+    blocked: bool,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::ConversationId { conversation_key } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected ConversationId, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    app.db
+        .update_conversation_blocked(time, db_user.id, conversation_key, blocked)
+        .await
+        .map_err(conversation_404_err)?;
+
+    Ok(ServerRes::Ok)
+}
+
+pub async fn block_conversation(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    update_conversation_blocked(app, db_user.0, req, true).await
+}
+
+pub async fn unblock_conversation(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    update_conversation_blocked(app, db_user.0, req, false).await
+}
+
+pub async fn add_message(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    type ResErr = ConversationErr;
+
+    let ServerReq::AddMessage {
+        conversation_key,
+        text,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AddMessage, received: {req:?}"
+        ))));
+    };
+    proccess_message(&text).map_err(ResErr::InvalidText)?;
+    let time = app.time().await;
+
+    let message = app
+        .db
+        .add_message(time, db_user.id.clone(), conversation_key, text)
+        .await
+        .map_err(|err| match err {
+            DBConversationErr::NotFound => ResErr::NotFound.into(),
+            DBConversationErr::Blocked => ResErr::Blocked.into(),
+            _ => ServerErr::DbErr,
+        })?;
+
+    Ok(ServerRes::Message(message.into()))
+}
+
+pub async fn get_messages(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::GetMessages {
+        conversation_key,
+        limit,
+        time_range,
+        order,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected GetMessages, received: {req:?}"
+        ))));
+    };
+
+    let messages = app
+        .db
+        .get_messages(
+            db_user.id.clone(),
+            conversation_key,
+            limit,
+            time_range,
+            order,
+        )
+        .await
+        .map_err(conversation_404_err)?
+        .into_iter()
+        .map(UserMessage::from)
+        .collect::<Vec<UserMessage>>();
+
+    Ok(ServerRes::Messages(messages))
+}
+
+pub async fn add_message_file(
+    State(app): State<AppState>,
+    params: axum::extract::RawPathParams,
+    db_user: Extension<DBUser>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    type Err = ServerAddPostFileErr;
+
+    trace!("running add_message_file api");
+
+    let max_storage = db_user.max_storage_bytes;
+    let max_storage_per_file = db_user.max_storage_per_file_bytes;
+    let mut used_storage = db_user.used_storage_bytes;
+
+    let mut inner = async || -> Result<ServerRes, ServerErr> {
+        let time = app.clock.now().await;
+
+        let message_key = params
+            .iter()
+            .find(|(name, _)| *name == "message_id")
+            .map(|(_, value)| value)
+            .ok_or(ServerErr::from(ServerDesErr::ServerWrongInput(
+                "message_id param not found".to_string(),
+            )))?;
+
+        let mut message = None;
+        while let Ok(Some(field)) = multipart.next_field().await {
+            let storage_left = max_storage.saturating_sub(used_storage);
+            let storage_per_file = storage_left.min(max_storage_per_file);
+
+            let Some(SavedImg {
+                file,
+                extension,
+                width,
+                height,
+            }) = save_img_field(&app, field, storage_per_file).await?
+            else {
+                continue;
+            };
+
+            let file = DBUserPostFile {
+                proccesed: false,
+                extension,
+                hash: file.hash,
+                size_bytes: file.size_bytes,
+                width,
+                height,
+            };
+            let size_bytes = file.size_bytes;
+            let result = app
+                .db
+                .add_message_file(time, db_user.id.clone(), message_key, file)
+                .await
+                .map_err(|err| match err {
+                    DBConversationErr::Duplicate(_) => ServerErr::from(Err::Duplicate),
+                    DBConversationErr::NotFound => ConversationErr::NotFound.into(),
+                    _ => ServerErr::DbErr,
+                })?;
+            used_storage += size_bytes;
+            message = Some(result);
+        }
+
+        let Some(message) = message else {
+            return Err(ServerErr::from(ServerDesErr::ServerWrongInput(
+                "no files were uploaded".to_string(),
+            )));
+        };
+
+        Ok(ServerRes::Message(message.into()))
+    };
+    let result = inner().await;
+
+    Json(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::trace;
+
+    use crate::api::shared::conversation::{ConversationErr, UserConversation, UserMessage};
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Api, Order, ServerErr, ServerRes, TimeRange};
+
+    impl ApiTestApp {
+        pub async fn add_conversation(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            username: impl Into<String>,
+        ) -> Result<UserConversation, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .add_conversation(username)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Conversation(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Conversation, got {res:?}"),
+            }
+        }
+
+        pub async fn get_conversations(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            limit: usize,
+            time_range: TimeRange,
+            order: Order,
+        ) -> Option<Vec<UserConversation>> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_conversations(limit, time_range, order)
+                .send_native_with_token(auth_token)
+                .await;
+            match result {
+                Ok(ServerRes::Conversations(conversations)) => Some(conversations),
+                _ => None,
+            }
+        }
+
+        pub async fn get_conversation_unread_count(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+        ) -> Option<u64> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_conversation_unread_count()
+                .send_native_with_token(auth_token)
+                .await;
+            match result {
+                Ok(ServerRes::Count(count)) => Some(count),
+                _ => None,
+            }
+        }
+
+        pub async fn read_conversation(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            conversation_key: impl Into<String>,
+        ) -> Result<(), ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .read_conversation(conversation_key)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Ok => Ok(()),
+                res => panic!("fix code, invalid response, expected Ok, got {res:?}"),
+            }
+        }
+
+        pub async fn block_conversation(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            conversation_key: impl Into<String>,
+            blocked: bool,
+        ) -> Result<(), ServerErr> {
+            self.set_time(server_time).await;
+            let conversation_key = conversation_key.into();
+            let req = if blocked {
+                self.api.block_conversation(conversation_key)
+            } else {
+                self.api.unblock_conversation(conversation_key)
+            };
+            let result = req.send_native_with_token(auth_token).await?;
+            match result {
+                ServerRes::Ok => Ok(()),
+                res => panic!("fix code, invalid response, expected Ok, got {res:?}"),
+            }
+        }
+
+        pub async fn add_message(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            conversation_key: impl Into<String>,
+            text: impl Into<String>,
+        ) -> Result<UserMessage, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .add_message(conversation_key, text)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Message(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Message, got {res:?}"),
+            }
+        }
+
+        pub async fn get_messages(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            conversation_key: impl Into<String>,
+            limit: usize,
+            time_range: TimeRange,
+            order: Order,
+        ) -> Result<Vec<UserMessage>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_messages(conversation_key, limit, time_range, order)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Messages(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Messages, got {res:?}"),
+            }
+        }
+
+        pub async fn add_message_file(
+            &self,
+            time: u128,
+            auth_token: impl AsRef<str>,
+            message_key: impl AsRef<str>,
+            file_path: impl AsRef<str>,
+        ) -> Result<UserMessage, ServerErr> {
+            self.set_time(time).await;
+            let current_dir = std::env::current_dir().unwrap();
+            let file_path = current_dir.join(file_path.as_ref());
+
+            let form = reqwest::multipart::Form::new()
+                .file("key", file_path)
+                .await
+                .unwrap();
+
+            let cookie = crate::api::create_auth_header(auth_token);
+            let url = crate::path::link_api_message_add_file(message_key);
+            let url = self.api.server.server_url(&url).unwrap();
+
+            let result = reqwest::Client::new()
+                .post(url)
+                .multipart(form)
+                .header(http::header::COOKIE, cookie)
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+
+            trace!("{result}");
+            let result: Result<ServerRes, ServerErr> = serde_json::from_str(&result).unwrap();
+            match result? {
+                ServerRes::Message(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Message, got {res:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn api_conversation_test() {
+        const FILE_PATH: &str = "../assets/upload.svg";
+        const FILES_PATH: &str = "/tmp/api_conversation_test";
+
+        crate::init_test_log();
+
+        let app = ApiTestApp::new_with_exp_and_files(1, FILES_PATH).await;
+
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token3 = app
+            .register(0, "hey3", "hey3@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        let result = app.add_conversation(1, &auth_token, "hey").await;
+        assert_eq!(result, Err(ConversationErr::MessageSelf.into()));
+        let result = app.add_conversation(1, &auth_token, "none").await;
+        assert_eq!(
+            result,
+            Err(ConversationErr::UserNotFound("none".to_string()).into())
+        );
+
+        let conversation = app.add_conversation(1, &auth_token, "hey2").await.unwrap();
+        assert_eq!(conversation.peer.username, "hey2");
+
+        for (time, text) in [(2, "hi1"), (3, "hi2"), (4, "hi3")] {
+            app.add_message(time, &auth_token, conversation.key.clone(), text)
+                .await
+                .unwrap();
+        }
+
+        let result = app
+            .add_message(5, &auth_token3, conversation.key.clone(), "hi")
+            .await;
+        assert_eq!(result, Err(ConversationErr::NotFound.into()));
+        let result = app
+            .get_messages(
+                5,
+                &auth_token3,
+                conversation.key.clone(),
+                10,
+                TimeRange::None,
+                Order::ThreeTwoOne,
+            )
+            .await;
+        assert_eq!(result, Err(ConversationErr::NotFound.into()));
+
+        let messages = app
+            .get_messages(
+                5,
+                &auth_token2,
+                conversation.key.clone(),
+                2,
+                TimeRange::LessOrEqual(5),
+                Order::ThreeTwoOne,
+            )
+            .await
+            .unwrap();
+        let texts = messages
+            .iter()
+            .map(|v| v.text.clone())
+            .collect::<Vec<String>>();
+        assert_eq!(texts, vec!["hi3", "hi2"]);
+        let messages = app
+            .get_messages(
+                5,
+                &auth_token2,
+                conversation.key.clone(),
+                2,
+                TimeRange::Less(messages[1].created_at),
+                Order::ThreeTwoOne,
+            )
+            .await
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].user.username, "hey");
+
+        let count = app
+            .get_conversation_unread_count(5, &auth_token2)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        let conversations = app
+            .get_conversations(5, &auth_token2, 10, TimeRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap();
+        assert_eq!(conversations.len(), 1);
+        assert!(conversations[0].unread);
+        assert_eq!(conversations[0].peer.username, "hey");
+
+        let result = app
+            .read_conversation(6, &auth_token3, conversation.key.clone())
+            .await;
+        assert_eq!(result, Err(ConversationErr::NotFound.into()));
+        app.read_conversation(6, &auth_token2, conversation.key.clone())
+            .await
+            .unwrap();
+        let count = app
+            .get_conversation_unread_count(6, &auth_token2)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let message = app
+            .add_message(7, &auth_token2, conversation.key.clone(), "")
+            .await
+            .unwrap();
+        let result = app
+            .add_message_file(7, &auth_token, message.key.clone(), FILE_PATH)
+            .await;
+        assert_eq!(result, Err(ConversationErr::NotFound.into()));
+        let message = app
+            .add_message_file(7, &auth_token2, message.key.clone(), FILE_PATH)
+            .await
+            .unwrap();
+        assert_eq!(message.file.len(), 1);
+        assert_eq!(message.file[0].extension, "svg");
+        assert_eq!(message.file[0].width, 15);
+
+        app.block_conversation(8, &auth_token2, conversation.key.clone(), true)
+            .await
+            .unwrap();
+        let result = app
+            .add_message(9, &auth_token, conversation.key.clone(), "blocked")
+            .await;
+        assert_eq!(result, Err(ConversationErr::Blocked.into()));
+
+        app.block_conversation(10, &auth_token2, conversation.key.clone(), false)
+            .await
+            .unwrap();
+        app.add_message(11, &auth_token, conversation.key.clone(), "unblocked")
+            .await
+            .unwrap();
+    }
+}
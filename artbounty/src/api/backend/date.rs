@@ -0,0 +1,103 @@
+//! utc date formatting for feeds, http headers and federation without pulling in a date crate
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// (year, month, day, hour, minute, second, weekday with 0 as monday) in utc
+fn civil_time(time_ns: u128) -> (i64, u32, u32, u32, u32, u32, u32) {
+    let secs = (time_ns / 1_000_000_000) as i64;
+    let days = secs.div_euclid(86_400);
+    let rem = secs.rem_euclid(86_400);
+
+    // days since epoch to y-m-d, http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    // 1970-01-01 was a thursday
+    let weekday = (days + 3).rem_euclid(7) as u32;
+
+    (
+        year,
+        month,
+        day,
+        (rem / 3600) as u32,
+        (rem % 3600 / 60) as u32,
+        (rem % 60) as u32,
+        weekday,
+    )
+}
+
+pub fn fmt_rfc3339(time_ns: u128) -> String {
+    let (year, month, day, hour, minute, second, _) = civil_time(time_ns);
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z")
+}
+
+/// rfc 7231 date, also valid as an rss rfc 822 date
+pub fn fmt_http_date(time_ns: u128) -> String {
+    let (year, month, day, hour, minute, second, weekday) = civil_time(time_ns);
+    format!(
+        "{}, {day:02} {} {year:04} {hour:02}:{minute:02}:{second:02} GMT",
+        WEEKDAYS[weekday as usize],
+        MONTHS[month as usize - 1]
+    )
+}
+
+/// inverse of fmt_http_date, other http date formats are not accepted
+pub fn parse_http_date(value: &str) -> Option<u128> {
+    let (_, rest) = value.trim().split_once(", ")?;
+    let mut parts = rest.split(' ');
+    let day = parts.next()?.parse::<i64>().ok()?;
+    let month = MONTHS.iter().position(|v| Some(*v) == parts.next())? as i64 + 1;
+    let year = parts.next()?.parse::<i64>().ok()?;
+    let mut time = parts.next()?.split(':').map(|v| v.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if parts.next()? != "GMT" || parts.next().is_some() || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // y-m-d to days since epoch, same source as civil_time
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = days * 86_400 + hour * 3600 + minute * 60 + second;
+    u128::try_from(secs).ok().map(|v| v * 1_000_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fmt_http_date, fmt_rfc3339, parse_http_date};
+
+    #[test]
+    fn http_dates() {
+        assert_eq!(fmt_rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(fmt_http_date(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        let time = 784_111_777_000_000_000;
+        assert_eq!(fmt_rfc3339(time), "1994-11-06T08:49:37Z");
+        assert_eq!(fmt_http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+        let time = 1_709_210_096_000_000_000;
+        assert_eq!(fmt_rfc3339(time), "2024-02-29T12:34:56Z");
+
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(784_111_777_000_000_000)
+        );
+        assert_eq!(parse_http_date(&fmt_http_date(time)), Some(time));
+        assert_eq!(parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 UTC"), None);
+    }
+}
@@ -0,0 +1,419 @@
+//! post cards other sites can put in an iframe and the oembed endpoint that hands them out.
+//! only posts anyone could open by link embed, and the author can turn it off per post.
+
+use axum::extract::{Path, Query, State};
+use axum::response::IntoResponse;
+use http::header::{CACHE_CONTROL, CONTENT_SECURITY_POLICY, CONTENT_TYPE};
+use http::{HeaderMap, HeaderValue, StatusCode};
+use serde_json::json;
+use thiserror::Error;
+
+use crate::api::app_state::AppState;
+use crate::api::backend::THUMBNAIL_RESOLUTION_LIMIT;
+use crate::api::backend::feed::escape_xml;
+use crate::api::backend::scale_resolution;
+use crate::api::shared::rating::PostRating;
+use crate::api::shared::visibility::PostVisibility;
+use crate::api::{Server404Err, ServerErr, UserPost};
+use crate::db::{DB404Err, DBUserPost};
+use crate::path::{link_embed, link_img_thumbnail, link_post, link_user};
+
+pub const EMBED_WIDTH: u32 = 400;
+pub const EMBED_HEIGHT: u32 = 480;
+/// seconds, both for the card and the oembed response
+pub const EMBED_CACHE_AGE: u64 = 300;
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct OEmbedQuery {
+    /// post page or embed url on this site
+    pub url: String,
+    /// only json is supported
+    pub format: Option<String>,
+    pub maxwidth: Option<u32>,
+    pub maxheight: Option<u32>,
+}
+
+#[derive(Debug, Error)]
+pub enum EmbedErr {
+    #[error(transparent)]
+    Server(#[from] ServerErr),
+
+    #[error("only json is supported")]
+    Format,
+}
+
+impl From<Server404Err> for EmbedErr {
+    fn from(value: Server404Err) -> Self {
+        Self::Server(value.into())
+    }
+}
+
+/// the oembed spec wants 404 for urls it can't embed and 501 for unsupported formats
+impl IntoResponse for EmbedErr {
+    fn into_response(self) -> axum::response::Response {
+        let status = match &self {
+            EmbedErr::Server(ServerErr::NotFoundErr(_)) => StatusCode::NOT_FOUND,
+            EmbedErr::Server(ServerErr::DesErr(_)) => StatusCode::BAD_REQUEST,
+            EmbedErr::Server(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            EmbedErr::Format => StatusCode::NOT_IMPLEMENTED,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+/// the same posts the post page shows to anyone, minus the ones the author opted out
+pub fn post_is_embeddable(post: &DBUserPost, time: u128) -> bool {
+    post.show
+        && post.embeddable
+        && post.deleted_at == 0
+        && post.publish_at <= time
+        && PostVisibility::from_db(&post.visibility).is_published()
+        && !post.user.banned
+        && post.user.erased_at == 0
+}
+
+async fn get_embeddable_post(
+    app: &AppState,
+    username: &str,
+    post_key: String,
+) -> Result<UserPost, EmbedErr> {
+    let time = app.time().await;
+    let post = app.db.get_post(post_key).await.map_err(|err| match err {
+        DB404Err::NotFound => ServerErr::from(Server404Err::NotFound),
+        DB404Err::DB(_) => ServerErr::DbErr,
+    })?;
+    if post.user.username != username || !post_is_embeddable(&post, time) {
+        return Err(Server404Err::NotFound.into());
+    }
+
+    Ok(post.into())
+}
+
+/// username and post key from a post page or embed url on this site
+pub fn post_from_url(address: &str, url: &str) -> Option<(String, String)> {
+    let path = url.strip_prefix(address)?;
+    let path = path.split(['?', '#']).next()?;
+    let mut parts = path.trim_start_matches('/').split('/');
+    match (parts.next()?, parts.next()?, parts.next()?, parts.next()) {
+        ("u" | "embed", username, post_key, None)
+            if !username.is_empty() && !post_key.is_empty() =>
+        {
+            Some((username.to_string(), post_key.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// absolute url and size of the thumbnail, only general posts get one since other sites don't blur
+fn embed_thumbnail(address: &str, post: &UserPost) -> Option<(String, u32, u32)> {
+    post.file
+        .first()
+        .filter(|file| file.proccesed && post.rating == PostRating::General)
+        .map(|file| {
+            let (width, height) =
+                scale_resolution(file.width, file.height, THUMBNAIL_RESOLUTION_LIMIT);
+            (
+                format!("{address}{}", link_img_thumbnail(&file.hash)),
+                width,
+                height,
+            )
+        })
+}
+
+pub async fn get_embed(
+    State(app): State<AppState>,
+    Path((username, post_key)): Path<(String, String)>,
+) -> Result<impl IntoResponse, EmbedErr> {
+    let post = get_embeddable_post(&app, &username, post_key).await?;
+    let address = app.get_address().await;
+
+    let mut res_headers = HeaderMap::new();
+    res_headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("text/html; charset=utf-8"),
+    );
+    res_headers.insert(
+        CACHE_CONTROL,
+        HeaderValue::from_str(&format!("public, max-age={EMBED_CACHE_AGE}"))
+            .expect("valid header value"),
+    );
+    // any site may frame the card, but the card itself only loads its own images
+    if let Ok(value) = HeaderValue::from_str(&format!(
+        "default-src 'none'; img-src {address}; style-src 'unsafe-inline'; frame-ancestors *"
+    )) {
+        res_headers.insert(CONTENT_SECURITY_POLICY, value);
+    }
+
+    Ok((StatusCode::OK, res_headers, render_embed(&address, &post)))
+}
+
+pub async fn get_oembed(
+    State(app): State<AppState>,
+    Query(query): Query<OEmbedQuery>,
+) -> Result<impl IntoResponse, EmbedErr> {
+    if query
+        .format
+        .as_deref()
+        .is_some_and(|format| format != "json")
+    {
+        return Err(EmbedErr::Format);
+    }
+    let address = app.get_address().await;
+    let (username, post_key) = post_from_url(&address, &query.url).ok_or(Server404Err::NotFound)?;
+    let post = get_embeddable_post(&app, &username, post_key).await?;
+
+    let width = query.maxwidth.map_or(EMBED_WIDTH, |v| v.min(EMBED_WIDTH));
+    let height = query
+        .maxheight
+        .map_or(EMBED_HEIGHT, |v| v.min(EMBED_HEIGHT));
+    let src = format!("{address}{}", link_embed(&post.user.username, &post.key));
+    let mut body = json!({
+        "version": "1.0",
+        "type": "rich",
+        "provider_name": "artbounty",
+        "provider_url": address,
+        "title": post.title,
+        "author_name": post.user.username,
+        "author_url": format!("{address}{}", link_user(&post.user.username)),
+        "html": format!(
+            r#"<iframe src="{}" width="{width}" height="{height}" style="border:0" loading="lazy" title="{}"></iframe>"#,
+            escape_xml(&src),
+            escape_xml(&post.title),
+        ),
+        "width": width,
+        "height": height,
+        "cache_age": EMBED_CACHE_AGE,
+    });
+    if let Some((url, width, height)) = embed_thumbnail(&address, &post) {
+        body["thumbnail_url"] = json!(url);
+        body["thumbnail_width"] = json!(width);
+        body["thumbnail_height"] = json!(height);
+    }
+
+    Ok((
+        [(CONTENT_TYPE, "application/json; charset=utf-8")],
+        body.to_string(),
+    ))
+}
+
+/// a single self contained page, the whole card links back to the post
+pub fn render_embed(address: &str, post: &UserPost) -> String {
+    let username = &post.user.username;
+    let link = escape_xml(&format!("{address}{}", link_post(username, &post.key)));
+    let title = escape_xml(&post.title);
+    let artist = escape_xml(username);
+    let image = match embed_thumbnail(address, post) {
+        Some((url, _, _)) => format!(r#"<img src="{}" alt="{title}">"#, escape_xml(&url)),
+        None => r#"<div class="empty">view on artbounty</div>"#.to_string(),
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title} by {artist} - artbounty</title>
+<style>
+html, body {{ margin: 0; height: 100%; background: #002b36; color: #93a1a1; font-family: sans-serif; }}
+a {{ display: flex; flex-direction: column; height: 100%; color: inherit; text-decoration: none; }}
+img, .empty {{ flex: 1; min-height: 0; width: 100%; object-fit: contain; background: #073642; }}
+.empty {{ display: flex; align-items: center; justify-content: center; }}
+.info {{ display: flex; gap: 0.5rem; align-items: baseline; padding: 0.5rem; }}
+.title {{ flex: 1; overflow: hidden; white-space: nowrap; text-overflow: ellipsis; color: #eee8d5; }}
+</style>
+</head>
+<body>
+<a href="{link}" target="_blank" rel="noopener">
+{image}
+<div class="info"><span class="title">{title}</span><span>by {artist}</span><span>&#9829; {}</span></div>
+</a>
+</body>
+</html>
+"#,
+        post.favorites
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use http::header::{CONTENT_SECURITY_POLICY, CONTENT_TYPE};
+
+    use crate::api::shared::visibility::PostVisibility;
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Api, ServerRes};
+    use crate::db::audit::DBAudit;
+    use crate::path::{PATH_OEMBED, link_embed, link_oembed, link_post};
+
+    use super::post_from_url;
+
+    #[test]
+    fn embed_post_from_url() {
+        let address = "http://localhost:3000";
+        assert_eq!(
+            post_from_url(address, "http://localhost:3000/u/hey/p1?s=2"),
+            Some(("hey".to_string(), "p1".to_string()))
+        );
+        assert_eq!(
+            post_from_url(address, "http://localhost:3000/embed/hey/p1"),
+            Some(("hey".to_string(), "p1".to_string()))
+        );
+        assert_eq!(post_from_url(address, "http://localhost:3000/u/hey"), None);
+        assert_eq!(
+            post_from_url(address, "http://localhost:3000/u/hey/p1/x"),
+            None
+        );
+        assert_eq!(post_from_url(address, "http://example.com/u/hey/p1"), None);
+        assert_eq!(
+            link_oembed(address, "http://localhost:3000/u/hey/p1"),
+            "http://localhost:3000/oembed?url=http%3A%2F%2Flocalhost%3A3000%2Fu%2Fhey%2Fp1&format=json"
+        );
+    }
+
+    #[tokio::test]
+    async fn api_embed_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let post = app
+            .add_post(1, &auth_token, "<cats>", "", "cat")
+            .await
+            .unwrap();
+        assert!(post.embeddable);
+
+        let get = async |path: String, query: Vec<(&str, String)>| {
+            let url = app.api.server.server_url(&path).unwrap();
+            let res = reqwest::Client::new()
+                .get(url)
+                .query(&query)
+                .send()
+                .await
+                .unwrap();
+            let status = res.status();
+            let headers = res.headers().clone();
+            (status, headers, res.text().await.unwrap())
+        };
+        let post_url = format!("http://localhost:3000{}", link_post("hey", &post.key));
+
+        let (status, headers, body) = get(link_embed("hey", &post.key), vec![]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            headers[CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/html")
+        );
+        assert!(
+            headers[CONTENT_SECURITY_POLICY]
+                .to_str()
+                .unwrap()
+                .contains("frame-ancestors *")
+        );
+        assert!(body.contains("&lt;cats&gt;"));
+        assert!(body.contains("by hey"));
+        assert!(body.contains("&#9829; 0"));
+        assert!(body.contains(&post_url));
+
+        // the username has to match the post
+        let (status, _, _) = get(link_embed("hey2", &post.key), vec![]).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, headers, body) = get(
+            PATH_OEMBED.to_string(),
+            vec![("url", post_url.clone()), ("maxwidth", "300".to_string())],
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            headers[CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("application/json")
+        );
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["type"], "rich");
+        assert_eq!(body["title"], "<cats>");
+        assert_eq!(body["author_name"], "hey");
+        assert_eq!(body["width"], 300);
+        assert!(
+            body["html"]
+                .as_str()
+                .unwrap()
+                .contains(&link_embed("hey", &post.key))
+        );
+
+        let (status, _, _) = get(
+            PATH_OEMBED.to_string(),
+            vec![("url", post_url.clone()), ("format", "xml".to_string())],
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+        let (status, _, _) = get(
+            PATH_OEMBED.to_string(),
+            vec![("url", "http://example.com/u/hey/p1".to_string())],
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // the author opts out
+        let result = app
+            .api
+            .update_post_embeddable(post.key.clone(), false)
+            .send_native_with_token(&auth_token)
+            .await
+            .unwrap();
+        let ServerRes::Post(updated) = result else {
+            panic!("expected Post, got {result:?}");
+        };
+        assert!(!updated.embeddable);
+        let (status, _, _) = get(link_embed("hey", &post.key), vec![]).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _, _) = get(PATH_OEMBED.to_string(), vec![("url", post_url.clone())]).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        app.api
+            .update_post_embeddable(post.key.clone(), true)
+            .send_native_with_token(&auth_token)
+            .await
+            .unwrap();
+        let (status, _, _) = get(link_embed("hey", &post.key), vec![]).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // hidden by moderation
+        app.state
+            .db
+            .update_post_show(2, post.key.clone(), false, DBAudit::default())
+            .await
+            .unwrap();
+        let (status, _, _) = get(link_embed("hey", &post.key), vec![]).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        app.state
+            .db
+            .update_post_show(3, post.key.clone(), true, DBAudit::default())
+            .await
+            .unwrap();
+
+        // drafts don't embed
+        app.update_post_visibility(4, &auth_token, post.key.clone(), PostVisibility::Draft, 0)
+            .await
+            .unwrap();
+        let (status, _, _) = get(PATH_OEMBED.to_string(), vec![("url", post_url)]).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // other users can't change it
+        let auth_token2 = app
+            .register(5, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let result = app
+            .api
+            .update_post_embeddable(post.key.clone(), false)
+            .send_native_with_token(&auth_token2)
+            .await;
+        assert!(result.is_err());
+    }
+}
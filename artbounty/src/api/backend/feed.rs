@@ -0,0 +1,600 @@
+//! atom and rss feeds for feed readers outside the site, built from post_search like the
+//! gallery. anonymous like any feed reader, so only public posts within the requested rating.
+
+use std::str::FromStr;
+
+use axum::extract::{Path, Query, State};
+use axum::response::IntoResponse;
+use gxhash::gxhash64;
+use http::header::{
+    CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use http::{HeaderMap, HeaderValue, StatusCode};
+use tracing::trace;
+
+use crate::api::app_state::AppState;
+use crate::api::backend::date::{fmt_http_date, fmt_rfc3339};
+use crate::api::backend::get_user_by_username;
+use crate::api::shared::cursor::{Cursor, CursorRange, page_limit};
+use crate::api::shared::rating::PostRating;
+use crate::api::{Order, ServerDesErr, ServerErr, UserPost};
+use crate::db::to_post_thumbnail_path;
+use crate::path::{
+    PATH_FEED, link_feed_tags, link_feed_user, link_home, link_home_search, link_img_thumbnail,
+    link_post, link_user,
+};
+
+pub const FEED_LIMIT_DEFAULT: usize = 25;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum FeedFormat {
+    #[default]
+    Atom,
+    Rss,
+}
+
+impl FeedFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+        }
+    }
+}
+
+/// the same filters post_search takes, on top of whatever the route already fixed
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct FeedQuery {
+    /// space separated, added to the tags of the route
+    pub tags: Option<String>,
+    /// only on PATH_FEED, the user routes take it from the path
+    pub username: Option<String>,
+    /// highest rating to include, defaults to general
+    pub rating: Option<String>,
+    /// atom or rss, defaults to atom
+    pub format: Option<String>,
+    /// opaque cursor to continue an older page
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// feed readers only look at the status, the body is a plain message
+#[derive(Debug)]
+pub struct FeedErr(pub ServerErr);
+
+impl From<ServerErr> for FeedErr {
+    fn from(value: ServerErr) -> Self {
+        Self(value)
+    }
+}
+
+impl IntoResponse for FeedErr {
+    fn into_response(self) -> axum::response::Response {
+        let status = match &self.0 {
+            ServerErr::NotFoundErr(_) => StatusCode::NOT_FOUND,
+            ServerErr::DesErr(_) | ServerErr::CursorErr(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, self.0.to_string()).into_response()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedMeta {
+    pub title: String,
+    /// absolute url of the feed itself
+    pub url: String,
+    /// absolute url of the same listing on the site
+    pub link: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedEntry {
+    pub title: String,
+    pub link: String,
+    pub author: String,
+    pub author_link: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    /// absolute url and size in bytes of the processed webp thumbnail
+    pub thumbnail: Option<(String, u64)>,
+    pub created_at: u128,
+    pub updated_at: u128,
+}
+
+pub async fn get_feed(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<FeedQuery>,
+) -> Result<impl IntoResponse, FeedErr> {
+    let address = app_state.get_address().await;
+    let username = query.username.clone().unwrap_or_default();
+    let tags = query.tags.clone().unwrap_or_default();
+    let (title, link) = match (username.is_empty(), tags.trim().is_empty()) {
+        (true, true) => ("artbounty".to_string(), link_home()),
+        (true, false) => (
+            format!("artbounty - {}", tags.trim()),
+            link_home_search(tags_to_query(&tags)),
+        ),
+        (false, _) => (format!("artbounty - {username}"), link_user(&username)),
+    };
+    let meta = FeedMeta {
+        title,
+        url: format!("{address}{PATH_FEED}"),
+        link: format!("{address}{link}"),
+    };
+    feed(&app_state, &headers, meta, String::new(), username, query).await
+}
+
+pub async fn get_user_feed(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(username): Path<String>,
+    Query(query): Query<FeedQuery>,
+) -> Result<impl IntoResponse, FeedErr> {
+    // an unknown user would otherwise look like a user without posts
+    let user = get_user_by_username(&app_state, username).await?;
+    let address = app_state.get_address().await;
+    let meta = FeedMeta {
+        title: format!("artbounty - {}", user.username),
+        url: format!("{address}{}", link_feed_user(&user.username)),
+        link: format!("{address}{}", link_user(&user.username)),
+    };
+    feed(
+        &app_state,
+        &headers,
+        meta,
+        String::new(),
+        user.username,
+        query,
+    )
+    .await
+}
+
+pub async fn get_tags_feed(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(tags): Path<String>,
+    Query(query): Query<FeedQuery>,
+) -> Result<impl IntoResponse, FeedErr> {
+    let tags = tags_from_path(&tags);
+    let address = app_state.get_address().await;
+    let meta = FeedMeta {
+        title: format!("artbounty - {tags}"),
+        url: format!("{address}{}", link_feed_tags(&tags)),
+        link: format!("{address}{}", link_home_search(tags_to_query(&tags))),
+    };
+    feed(&app_state, &headers, meta, tags, String::new(), query).await
+}
+
+async fn feed(
+    app_state: &AppState,
+    headers: &HeaderMap,
+    meta: FeedMeta,
+    tags: String,
+    username: String,
+    query: FeedQuery,
+) -> Result<impl IntoResponse, FeedErr> {
+    let format = parse_query_value::<FeedFormat>(query.format)?;
+    let rating = parse_query_value::<PostRating>(query.rating)?;
+    let limit = page_limit(query.limit.unwrap_or(FEED_LIMIT_DEFAULT)).map_err(ServerErr::from)?;
+    let cursor = match query.cursor {
+        Some(cursor) => CursorRange::Before(cursor.parse::<Cursor>().map_err(ServerErr::from)?),
+        None => CursorRange::None,
+    };
+    let tags = format!("{tags} {}", query.tags.unwrap_or_default());
+
+    let posts = app_state
+        .db
+        .post_search(
+            limit,
+            cursor,
+            Order::ThreeTwoOne,
+            tags,
+            username,
+            None,
+            rating.into(),
+        )
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserPost::from)
+        .collect::<Vec<UserPost>>();
+
+    let address = app_state.get_address().await;
+    let files_path = app_state.get_file_path().await;
+    let mut entries = Vec::with_capacity(posts.len());
+    for post in posts {
+        entries.push(feed_entry(&address, &files_path, post).await);
+    }
+
+    let body = match format {
+        FeedFormat::Atom => render_atom(&meta, &entries),
+        FeedFormat::Rss => render_rss(&meta, &entries),
+    };
+    let etag = format!("\"{:x}\"", gxhash64(body.as_bytes(), 0));
+    let last_modified = entries
+        .iter()
+        .map(|v| v.updated_at)
+        .max()
+        .map(fmt_http_date);
+
+    let mut res_headers = HeaderMap::new();
+    res_headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(format.content_type()),
+    );
+    res_headers.insert(
+        CACHE_CONTROL,
+        HeaderValue::from_static("public, max-age=300"),
+    );
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        res_headers.insert(ETAG, value);
+    }
+    if let Some(value) = last_modified
+        .as_ref()
+        .and_then(|v| HeaderValue::from_str(v).ok())
+    {
+        res_headers.insert(LAST_MODIFIED, value);
+    }
+
+    if is_not_modified(headers, &etag, last_modified.as_deref()) {
+        trace!("feed not modified {etag}");
+        return Ok((StatusCode::NOT_MODIFIED, res_headers, String::new()));
+    }
+
+    Ok((StatusCode::OK, res_headers, body))
+}
+
+async fn feed_entry(address: &str, files_path: &str, post: UserPost) -> FeedEntry {
+    let mut thumbnail = None;
+    if let Some(file) = post.file.first().filter(|file| file.proccesed) {
+        let path = to_post_thumbnail_path(&file.hash, files_path);
+        let size = tokio::fs::metadata(path)
+            .await
+            .map(|v| v.len())
+            .unwrap_or(0);
+        thumbnail = Some((format!("{address}{}", link_img_thumbnail(&file.hash)), size));
+    }
+    FeedEntry {
+        link: format!("{address}{}", link_post(&post.user.username, &post.key)),
+        author_link: format!("{address}{}", link_user(&post.user.username)),
+        title: post.title,
+        author: post.user.username,
+        description: post.description,
+        tags: post.tags.split_whitespace().map(String::from).collect(),
+        thumbnail,
+        created_at: post.created_at,
+        updated_at: post.modified_at.max(post.created_at),
+    }
+}
+
+fn parse_query_value<T: FromStr + Default>(value: Option<String>) -> Result<T, ServerErr> {
+    match value {
+        Some(value) => T::from_str(&value.to_lowercase()).map_err(|_| {
+            ServerDesErr::ServerWrongInput(format!("invalid query value: {value}")).into()
+        }),
+        None => Ok(T::default()),
+    }
+}
+
+/// `cat+dog` and `cat,dog` read better in a url than an encoded space
+pub fn tags_from_path(tags: &str) -> String {
+    tags.split(['+', ',', ' '])
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn tags_to_query(tags: &str) -> String {
+    tags.split_whitespace().collect::<Vec<&str>>().join("+")
+}
+
+/// if-none-match wins, if-modified-since is only compared to the exact value we sent out
+fn is_not_modified(headers: &HeaderMap, etag: &str, last_modified: Option<&str>) -> bool {
+    if let Some(if_none_match) = headers.get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+        return if_none_match
+            .split(',')
+            .map(|v| v.trim().trim_start_matches("W/"))
+            .any(|v| v == etag || v == "*");
+    }
+    match (
+        headers.get(IF_MODIFIED_SINCE).and_then(|v| v.to_str().ok()),
+        last_modified,
+    ) {
+        (Some(since), Some(last_modified)) => since.trim() == last_modified,
+        _ => false,
+    }
+}
+
+pub fn render_atom(meta: &FeedMeta, entries: &[FeedEntry]) -> String {
+    let updated = entries.iter().map(|v| v.updated_at).max().unwrap_or(0);
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    out.push_str(&format!("<title>{}</title>\n", escape_xml(&meta.title)));
+    out.push_str(&format!("<id>{}</id>\n", escape_xml(&meta.url)));
+    out.push_str(&format!(
+        "<link rel=\"self\" type=\"application/atom+xml\" href=\"{}\"/>\n",
+        escape_xml(&meta.url)
+    ));
+    out.push_str(&format!(
+        "<link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n",
+        escape_xml(&meta.link)
+    ));
+    out.push_str(&format!("<updated>{}</updated>\n", fmt_rfc3339(updated)));
+    for entry in entries {
+        out.push_str("<entry>\n");
+        out.push_str(&format!("<title>{}</title>\n", escape_xml(&entry.title)));
+        out.push_str(&format!("<id>{}</id>\n", escape_xml(&entry.link)));
+        out.push_str(&format!(
+            "<link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n",
+            escape_xml(&entry.link)
+        ));
+        if let Some((url, size)) = &entry.thumbnail {
+            out.push_str(&format!(
+                "<link rel=\"enclosure\" type=\"image/webp\" length=\"{size}\" href=\"{}\"/>\n",
+                escape_xml(url)
+            ));
+        }
+        out.push_str(&format!(
+            "<author><name>{}</name><uri>{}</uri></author>\n",
+            escape_xml(&entry.author),
+            escape_xml(&entry.author_link)
+        ));
+        out.push_str(&format!(
+            "<published>{}</published>\n",
+            fmt_rfc3339(entry.created_at)
+        ));
+        out.push_str(&format!(
+            "<updated>{}</updated>\n",
+            fmt_rfc3339(entry.updated_at)
+        ));
+        for tag in &entry.tags {
+            out.push_str(&format!("<category term=\"{}\"/>\n", escape_xml(tag)));
+        }
+        out.push_str(&format!(
+            "<summary>{}</summary>\n",
+            escape_xml(&entry.description)
+        ));
+        out.push_str("</entry>\n");
+    }
+    out.push_str("</feed>\n");
+    out
+}
+
+pub fn render_rss(meta: &FeedMeta, entries: &[FeedEntry]) -> String {
+    let updated = entries.iter().map(|v| v.updated_at).max().unwrap_or(0);
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n");
+    out.push_str("<channel>\n");
+    out.push_str(&format!("<title>{}</title>\n", escape_xml(&meta.title)));
+    out.push_str(&format!("<link>{}</link>\n", escape_xml(&meta.link)));
+    out.push_str(&format!(
+        "<description>{}</description>\n",
+        escape_xml(&meta.title)
+    ));
+    out.push_str(&format!(
+        "<atom:link rel=\"self\" type=\"application/rss+xml\" href=\"{}\"/>\n",
+        escape_xml(&meta.url)
+    ));
+    out.push_str(&format!(
+        "<lastBuildDate>{}</lastBuildDate>\n",
+        fmt_http_date(updated)
+    ));
+    for entry in entries {
+        out.push_str("<item>\n");
+        out.push_str(&format!("<title>{}</title>\n", escape_xml(&entry.title)));
+        out.push_str(&format!("<link>{}</link>\n", escape_xml(&entry.link)));
+        out.push_str(&format!(
+            "<guid isPermaLink=\"true\">{}</guid>\n",
+            escape_xml(&entry.link)
+        ));
+        out.push_str(&format!(
+            "<pubDate>{}</pubDate>\n",
+            fmt_http_date(entry.created_at)
+        ));
+        out.push_str(&format!(
+            "<atom:updated>{}</atom:updated>\n",
+            fmt_rfc3339(entry.updated_at)
+        ));
+        out.push_str(&format!(
+            "<atom:author><atom:name>{}</atom:name></atom:author>\n",
+            escape_xml(&entry.author)
+        ));
+        for tag in &entry.tags {
+            out.push_str(&format!("<category>{}</category>\n", escape_xml(tag)));
+        }
+        if let Some((url, size)) = &entry.thumbnail {
+            out.push_str(&format!(
+                "<enclosure url=\"{}\" length=\"{size}\" type=\"image/webp\"/>\n",
+                escape_xml(url)
+            ));
+        }
+        out.push_str(&format!(
+            "<description>{}</description>\n",
+            escape_xml(&entry.description)
+        ));
+        out.push_str("</item>\n");
+    }
+    out.push_str("</channel>\n");
+    out.push_str("</rss>\n");
+    out
+}
+
+pub fn escape_xml(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // not allowed in xml 1.0 at all
+            c if (c as u32) < 0x20 && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use http::header::{CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+
+    use crate::api::tests::ApiTestApp;
+    use crate::path::{PATH_FEED, link_feed_tags, link_feed_user};
+
+    use super::{FeedEntry, FeedMeta, escape_xml, render_atom, render_rss, tags_from_path};
+
+    #[test]
+    fn feed_render() {
+        assert_eq!(escape_xml("a<b>&\"'\u{1}"), "a&lt;b&gt;&amp;&quot;&apos;");
+        assert_eq!(tags_from_path("cat+dog,,fox"), "cat dog fox");
+
+        let meta = FeedMeta {
+            title: "artbounty - hey".to_string(),
+            url: "http://localhost/u/hey/feed.xml".to_string(),
+            link: "http://localhost/u/hey".to_string(),
+        };
+        let entry = FeedEntry {
+            title: "cats & dogs".to_string(),
+            link: "http://localhost/u/hey/1".to_string(),
+            author: "hey".to_string(),
+            author_link: "http://localhost/u/hey".to_string(),
+            description: "<b>bold</b>".to_string(),
+            tags: vec!["cat".to_string(), "dog".to_string()],
+            thumbnail: Some((
+                "http://localhost/file/A_thumbnail_default.webp".to_string(),
+                42,
+            )),
+            created_at: 0,
+            updated_at: 784_111_777_000_000_000,
+        };
+
+        let atom = render_atom(&meta, &[entry.clone()]);
+        assert!(atom.contains("<title>cats &amp; dogs</title>"));
+        assert!(atom.contains("<summary>&lt;b&gt;bold&lt;/b&gt;</summary>"));
+        assert!(atom.contains("<published>1970-01-01T00:00:00Z</published>"));
+        assert!(atom.contains("<updated>1994-11-06T08:49:37Z</updated>"));
+        assert!(atom.contains(
+            "<link rel=\"enclosure\" type=\"image/webp\" length=\"42\" href=\"http://localhost/file/A_thumbnail_default.webp\"/>"
+        ));
+        assert!(atom.contains("<category term=\"dog\"/>"));
+
+        let rss = render_rss(&meta, &[entry]);
+        assert!(rss.contains("<lastBuildDate>Sun, 06 Nov 1994 08:49:37 GMT</lastBuildDate>"));
+        assert!(rss.contains("<pubDate>Thu, 01 Jan 1970 00:00:00 GMT</pubDate>"));
+        assert!(rss.contains(
+            "<enclosure url=\"http://localhost/file/A_thumbnail_default.webp\" length=\"42\" type=\"image/webp\"/>"
+        ));
+    }
+
+    #[tokio::test]
+    async fn api_feed_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        app.add_post(1, &auth_token, "title1", "cat", "one two")
+            .await
+            .unwrap();
+        app.add_post(2, &auth_token, "title2", "cat", "one")
+            .await
+            .unwrap();
+        app.add_post(3, &auth_token2, "title3", "dog", "two")
+            .await
+            .unwrap();
+
+        let get = async |path: String, headers: Vec<(http::HeaderName, String)>| {
+            let url = app.api.server.server_url(&path).unwrap();
+            let mut req = reqwest::Client::new().get(url);
+            for (name, value) in headers {
+                req = req.header(name, value);
+            }
+            let res = req.send().await.unwrap();
+            let status = res.status();
+            let headers = res.headers().clone();
+            (status, headers, res.text().await.unwrap())
+        };
+
+        let (status, headers, body) = get(link_feed_user("hey"), vec![]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            headers[CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("application/atom+xml")
+        );
+        assert!(body.contains("<title>title2</title>"));
+        assert!(body.contains("<title>title1</title>"));
+        assert!(!body.contains("<title>title3</title>"));
+        assert!(body.find("title2").unwrap() < body.find("title1").unwrap());
+
+        // conditional requests
+        let etag = headers[ETAG].to_str().unwrap().to_string();
+        let last_modified = headers[LAST_MODIFIED].to_str().unwrap().to_string();
+        let (status, _, body) =
+            get(link_feed_user("hey"), vec![(IF_NONE_MATCH, etag.clone())]).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert!(body.is_empty());
+        let (status, _, _) = get(
+            link_feed_user("hey"),
+            vec![(IF_MODIFIED_SINCE, last_modified)],
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+        app.add_post(4, &auth_token, "title4", "cat", "one")
+            .await
+            .unwrap();
+        let (status, headers, body) =
+            get(link_feed_user("hey"), vec![(IF_NONE_MATCH, etag.clone())]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_ne!(headers[ETAG].to_str().unwrap(), etag);
+        assert!(body.contains("<title>title4</title>"));
+
+        let (status, headers, body) =
+            get(format!("{}?format=rss", link_feed_tags("two")), vec![]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            headers[CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("application/rss+xml")
+        );
+        assert!(body.contains("<title>title1</title>"));
+        assert!(body.contains("<title>title3</title>"));
+        assert!(!body.contains("<title>title2</title>"));
+
+        let (_, _, body) = get(format!("{}", link_feed_tags("one+two")), vec![]).await;
+        assert!(body.contains("<title>title1</title>"));
+        assert!(!body.contains("<title>title2</title>"));
+
+        let (_, _, body) = get(format!("{PATH_FEED}?username=hey2&limit=1"), vec![]).await;
+        assert!(body.contains("<title>title3</title>"));
+        assert_eq!(body.matches("<entry>").count(), 1);
+
+        let (_, _, body) = get(format!("{PATH_FEED}?tags=one&limit=2"), vec![]).await;
+        assert_eq!(body.matches("<entry>").count(), 2);
+        assert!(body.contains("<title>title4</title>"));
+
+        let (status, _, _) = get(link_feed_user("nobody"), vec![]).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _, _) = get(format!("{PATH_FEED}?format=json"), vec![]).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _, _) = get(format!("{PATH_FEED}?cursor=zz"), vec![]).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use axum::{
+    Extension,
+    extract::{
+        Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::{IntoResponse, Response},
+};
+use http::StatusCode;
+use surrealdb::types::ToSql;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::{Instant, interval_at};
+use tracing::{debug, error, trace, warn};
+
+use crate::{
+    api::{
+        AuthToken, app_state::AppState, backend::post::post_visible_to, shared::live::LiveEvent,
+    },
+    db::{DB404Err, DBUser, user_block::DBUserBlockKind},
+};
+
+/// how often an open socket checks the post is still visible to its viewer
+pub const LIVE_POST_RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct LivePostQuery {
+    pub post_key: String,
+}
+
+pub async fn live_post(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    Query(query): Query<LivePostQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    if let Err(status) = check_live_post(&app, &db_user, &query.post_key).await {
+        return status.into_response();
+    }
+    let blocked = match app.db.get_user_block_targets(db_user.id.clone()).await {
+        Ok(blocked) => blocked
+            .into_iter()
+            .map(|id| id.key.to_sql())
+            .collect::<HashSet<String>>(),
+        Err(err) => {
+            error!("failed to load blocks of \"{}\": {err}", db_user.username);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    // subscribe before the upgrade so events sent during the handshake are not lost
+    let receiver = app.live.subscribe();
+    let db_user = db_user.0;
+    ws.on_upgrade(move |socket| {
+        live_post_socket(socket, receiver, app, db_user, query.post_key, blocked)
+    })
+}
+
+/// same rules as opening the post, plus authors that blocked the viewer
+async fn check_live_post(
+    app: &AppState,
+    db_user: &DBUser,
+    post_key: &str,
+) -> Result<(), StatusCode> {
+    let post = app.db.get_post(post_key).await.map_err(|err| match err {
+        DB404Err::NotFound => StatusCode::NOT_FOUND,
+        DB404Err::DB(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    })?;
+    if !post_visible_to(&post, Some(db_user)) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    match app
+        .db
+        .get_user_block(post.user.id.clone(), db_user.username.clone())
+        .await
+    {
+        Ok(block) if block.kind == DBUserBlockKind::Block.to_string() => Err(StatusCode::NOT_FOUND),
+        Ok(_) | Err(DB404Err::NotFound) => Ok(()),
+        Err(DB404Err::DB(_)) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// comments by users the viewer blocked or muted are hidden from them, same as in the comment list
+fn live_event_visible(event: &LiveEvent, blocked: &HashSet<String>) -> bool {
+    match event {
+        LiveEvent::CommentAdded(comment) | LiveEvent::CommentUpdated(comment) => {
+            !blocked.contains(&comment.user.key)
+        }
+        LiveEvent::CommentDeleted { .. } | LiveEvent::PostLikeCount { .. } => true,
+    }
+}
+
+async fn live_post_socket(
+    mut socket: WebSocket,
+    mut receiver: broadcast::Receiver<LiveEvent>,
+    app: AppState,
+    db_user: DBUser,
+    post_key: String,
+    blocked: HashSet<String>,
+) {
+    let username = db_user.username.clone();
+    let mut recheck = interval_at(
+        Instant::now() + LIVE_POST_RECHECK_INTERVAL,
+        LIVE_POST_RECHECK_INTERVAL,
+    );
+    debug!("live post \"{post_key}\" opened by \"{username}\"");
+    loop {
+        tokio::select! {
+            event = receiver.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("live post \"{post_key}\" lagged behind by {skipped} events");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                if event.post_key() != post_key || !live_event_visible(&event, &blocked) {
+                    continue;
+                }
+                let bytes = match event.to_bytes() {
+                    Ok(bytes) => bytes,
+                    Err(err) => {
+                        error!("failed to serialize live event {event:?}: {err}");
+                        continue;
+                    }
+                };
+                trace!("live post \"{post_key}\" send {event:?}");
+                if socket.send(Message::Binary(bytes.into())).await.is_err() {
+                    break;
+                }
+            }
+            _ = recheck.tick() => {
+                // drafted, hidden or deleted since the socket opened
+                if check_live_post(&app, &db_user, &post_key).await == Err(StatusCode::NOT_FOUND) {
+                    trace!("live post \"{post_key}\" no longer visible to \"{username}\"");
+                    break;
+                }
+            }
+            msg = socket.recv() => {
+                match msg {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+    debug!("live post \"{post_key}\" closed by \"{username}\"");
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use tokio::sync::broadcast::Receiver;
+
+    use std::collections::HashSet;
+
+    use crate::api::backend::live::{check_live_post, live_event_visible};
+    use crate::api::shared::live::LiveEvent;
+    use crate::api::shared::user_block::UserBlockKind;
+    use crate::api::shared::visibility::PostVisibility;
+    use crate::api::tests::ApiTestApp;
+
+    fn next_event(receiver: &mut Receiver<LiveEvent>) -> LiveEvent {
+        receiver.try_recv().unwrap()
+    }
+
+    #[tokio::test]
+    async fn api_live_events_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+        let mut receiver = app.state.live.subscribe();
+
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let post = app
+            .add_post(1, &auth_token, "title1", "", "one")
+            .await
+            .unwrap();
+
+        let comment = app
+            .add_post_comment(2, &auth_token2, &post.key, None, "hello")
+            .await
+            .unwrap();
+        let LiveEvent::CommentAdded(event_comment) = next_event(&mut receiver) else {
+            panic!("expected CommentAdded");
+        };
+        assert_eq!(event_comment, comment);
+        assert_eq!(event_comment.post_key, post.key);
+
+        let reply = app
+            .add_post_comment(3, &auth_token, &post.key, Some(comment.key.clone()), "hi")
+            .await
+            .unwrap();
+        let LiveEvent::CommentAdded(event_comment) = next_event(&mut receiver) else {
+            panic!("expected CommentAdded");
+        };
+        assert_eq!(event_comment.parent_key, vec![comment.key.clone()]);
+
+        app.update_post_comment(4, &auth_token2, &comment.key, "hello2")
+            .await
+            .unwrap();
+        let LiveEvent::CommentUpdated(event_comment) = next_event(&mut receiver) else {
+            panic!("expected CommentUpdated");
+        };
+        assert_eq!(event_comment.text, "hello2");
+
+        // deleting someone else's comment does nothing and must not be broadcast
+        app.delete_post_comment(5, &auth_token, &comment.key)
+            .await
+            .unwrap();
+        assert!(receiver.try_recv().is_err());
+
+        app.delete_post_comment(5, &auth_token, &reply.key)
+            .await
+            .unwrap();
+        assert_eq!(
+            next_event(&mut receiver),
+            LiveEvent::CommentDeleted {
+                post_key: post.key.clone(),
+                comment_key: reply.key.clone(),
+                parent_key: vec![comment.key.clone()],
+            }
+        );
+
+        app.add_post_like(6, &auth_token2, &post.key).await.unwrap();
+        assert_eq!(
+            next_event(&mut receiver),
+            LiveEvent::PostLikeCount {
+                post_key: post.key.clone(),
+                count: 1,
+            }
+        );
+        app.delete_post_like(7, &auth_token2, &post.key)
+            .await
+            .unwrap();
+        assert_eq!(
+            next_event(&mut receiver),
+            LiveEvent::PostLikeCount {
+                post_key: post.key.clone(),
+                count: 0,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn api_live_post_access_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        app.register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let author = app.state.db.get_user_by_username("hey").await.unwrap();
+        let viewer = app.state.db.get_user_by_username("hey2").await.unwrap();
+        let post = app
+            .add_post(1, &auth_token, "title1", "", "one")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            check_live_post(&app.state, &viewer, &post.key).await,
+            Ok(())
+        );
+        assert_eq!(
+            check_live_post(&app.state, &viewer, "404").await,
+            Err(StatusCode::NOT_FOUND)
+        );
+
+        app.update_post_visibility(2, &auth_token, &post.key, PostVisibility::Draft, 0)
+            .await
+            .unwrap();
+        assert_eq!(
+            check_live_post(&app.state, &viewer, &post.key).await,
+            Err(StatusCode::NOT_FOUND)
+        );
+        assert_eq!(
+            check_live_post(&app.state, &author, &post.key).await,
+            Ok(())
+        );
+
+        app.update_post_visibility(3, &auth_token, &post.key, PostVisibility::Public, 0)
+            .await
+            .unwrap();
+        app.add_user_block(4, &auth_token, "hey2", UserBlockKind::Mute)
+            .await
+            .unwrap();
+        assert_eq!(
+            check_live_post(&app.state, &viewer, &post.key).await,
+            Ok(())
+        );
+        app.add_user_block(5, &auth_token, "hey2", UserBlockKind::Block)
+            .await
+            .unwrap();
+        assert_eq!(
+            check_live_post(&app.state, &viewer, &post.key).await,
+            Err(StatusCode::NOT_FOUND)
+        );
+    }
+
+    #[tokio::test]
+    async fn api_live_blocked_comments_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let post = app
+            .add_post(1, &auth_token, "title1", "", "one")
+            .await
+            .unwrap();
+        let comment = app
+            .add_post_comment(2, &auth_token2, &post.key, None, "hello")
+            .await
+            .unwrap();
+
+        let added = LiveEvent::CommentAdded(comment.clone());
+        let updated = LiveEvent::CommentUpdated(comment.clone());
+        let likes = LiveEvent::PostLikeCount {
+            post_key: post.key.clone(),
+            count: 1,
+        };
+        let blocked = HashSet::from([comment.user.key.clone()]);
+        assert!(live_event_visible(&added, &HashSet::new()));
+        assert!(!live_event_visible(&added, &blocked));
+        assert!(!live_event_visible(&updated, &blocked));
+        assert!(live_event_visible(&likes, &blocked));
+    }
+}
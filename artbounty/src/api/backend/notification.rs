@@ -0,0 +1,199 @@
+use axum::{Extension, extract::State};
+use tracing::{debug, trace};
+
+use crate::{
+    api::{
+        AuthToken, Server404Err, ServerDesErr, ServerErr, ServerReq, ServerRes,
+        app_state::AppState, shared::notification::UserNotification,
+    },
+    db::{DB404Err, DBUser},
+};
+
+pub async fn get_notifications(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::GetNotifications {
+        limit,
+        time_range,
+        order,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected GetNotifications, received: {req:?}"
+        ))));
+    };
+
+    let notifications = app
+        .db
+        .get_notifications(db_user.id.clone(), limit, time_range, order)
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserNotification::from)
+        .collect::<Vec<UserNotification>>();
+
+    Ok(ServerRes::Notifications(notifications))
+}
+
+pub async fn get_notification_unread_count(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+) -> Result<ServerRes, ServerErr> {
+    let count = app
+        .db
+        .get_notification_unread_count(db_user.id.clone())
+        .await
+        .map_err(|_| ServerErr::DbErr)?;
+
+    Ok(ServerRes::Count(count))
+}
+
+pub async fn read_notification(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::NotificationId { notification_key } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected NotificationId, received: {req:?}"
+        ))));
+    };
+
+    app.db
+        .update_notification_read(db_user.id.clone(), notification_key)
+        .await
+        .map_err(|err| match err {
+            DB404Err::NotFound => ServerErr::NotFoundErr(Server404Err::NotFound),
+            DB404Err::DB(_) => ServerErr::DbErr,
+        })?;
+
+    Ok(ServerRes::Ok)
+}
+
+pub async fn read_notification_all(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+) -> Result<ServerRes, ServerErr> {
+    app.db
+        .update_notification_read_all(db_user.id.clone())
+        .await
+        .map_err(|_| ServerErr::DbErr)?;
+
+    Ok(ServerRes::Ok)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::api::shared::notification::NotificationKind;
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Order, Server404Err, ServerErr, TimeRange};
+
+    #[tokio::test]
+    async fn api_notification_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token3 = app
+            .register(0, "hey3", "hey3@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        let post = app
+            .add_post(0, &auth_token, "title1", "cat", "one")
+            .await
+            .unwrap();
+
+        app.add_post_like(1, &auth_token, post.key.clone())
+            .await
+            .unwrap();
+        let count = app
+            .get_notification_unread_count(1, &auth_token)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+
+        app.add_post_like(2, &auth_token2, post.key.clone())
+            .await
+            .unwrap();
+        app.add_post_like(3, &auth_token3, post.key.clone())
+            .await
+            .unwrap();
+        let comment = app
+            .add_post_comment(4, &auth_token2, post.key.clone(), None, "wow")
+            .await
+            .unwrap();
+        app.add_post_comment(
+            5,
+            &auth_token,
+            post.key.clone(),
+            Some(comment.key.clone()),
+            "thanks",
+        )
+        .await
+        .unwrap();
+
+        let count = app
+            .get_notification_unread_count(5, &auth_token)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let notifications = app
+            .get_notifications(5, &auth_token, 10, TimeRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap();
+        assert_eq!(notifications.len(), 2);
+        assert_eq!(notifications[0].kind, NotificationKind::PostComment);
+        assert_eq!(notifications[1].kind, NotificationKind::PostLike);
+        assert_eq!(notifications[1].actors, vec!["hey2", "hey3"]);
+        assert_eq!(notifications[1].text(), "hey2 and hey3 liked your post \"title1\"");
+
+        let notifications2 = app
+            .get_notifications(5, &auth_token2, 10, TimeRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap();
+        assert_eq!(notifications2.len(), 1);
+        assert_eq!(notifications2[0].kind, NotificationKind::CommentReply);
+
+        let result = app
+            .read_notification(6, &auth_token2, notifications[0].key.clone())
+            .await;
+        assert_eq!(result, Err(ServerErr::NotFoundErr(Server404Err::NotFound)));
+
+        app.read_notification(6, &auth_token, notifications[0].key.clone())
+            .await
+            .unwrap();
+        let count = app
+            .get_notification_unread_count(6, &auth_token)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        app.read_notification_all(7, &auth_token).await.unwrap();
+        let count = app
+            .get_notification_unread_count(7, &auth_token)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+        let count = app
+            .get_notification_unread_count(7, &auth_token2)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,166 @@
+//! guards for requests the server makes to urls its users and remote instances hand it
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum OutboundErr {
+    #[error("url has no host")]
+    NoHost,
+
+    #[error("failed to resolve {0}")]
+    Resolve(String),
+
+    #[error("{0} is not a public address")]
+    NotPublic(IpAddr),
+}
+
+/// loopback, private, link-local, unspecified and broadcast addresses all point back into the server's own network,
+/// so do shared (carrier nat), "this network" and benchmarking addresses and ipv6 addresses that embed an ipv4 one
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (18..20).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            // nat64 (64:ff9b::/96) and the deprecated ipv4-compatible ::a.b.c.d
+            let embedded = if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0]
+                || (segments[..6] == [0; 6] && !ip.is_loopback() && !ip.is_unspecified())
+            {
+                let [.., a, b, c, d] = ip.octets();
+                Some(Ipv4Addr::new(a, b, c, d))
+            } else {
+                ip.to_ipv4_mapped()
+            };
+            match embedded {
+                Some(ip) => is_public_ip(IpAddr::V4(ip)),
+                None => {
+                    !(ip.is_loopback()
+                        || ip.is_unique_local()
+                        || ip.is_unicast_link_local()
+                        || ip.is_unspecified())
+                }
+            }
+        }
+    }
+}
+
+/// resolves the host of the url, every address it resolves to has to be public unless `allow_private` is set
+pub async fn resolve_outbound(
+    url: &reqwest::Url,
+    allow_private: bool,
+) -> Result<SocketAddr, OutboundErr> {
+    let host = url.host_str().ok_or(OutboundErr::NoHost)?;
+    let port = url.port_or_known_default().ok_or(OutboundErr::NoHost)?;
+    let addrs = match host.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|_| OutboundErr::Resolve(host.to_string()))?
+            .collect(),
+    };
+
+    let private = addrs
+        .iter()
+        .find(|addr| !allow_private && !is_public_ip(addr.ip()));
+    if let Some(addr) = private {
+        return Err(OutboundErr::NotPublic(addr.ip()));
+    }
+    addrs
+        .into_iter()
+        .next()
+        .ok_or_else(|| OutboundErr::Resolve(host.to_string()))
+}
+
+/// client pinned to the checked address so a second lookup can't swap it, redirects are not followed for the same reason
+pub fn outbound_client(
+    url: &reqwest::Url,
+    addr: SocketAddr,
+    timeout: Duration,
+) -> Result<reqwest::Client, reqwest::Error> {
+    let builder = reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::none());
+    let builder = match url.host_str() {
+        Some(host) => builder.resolve(host, addr),
+        None => builder,
+    };
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OutboundErr, is_public_ip, resolve_outbound};
+
+    #[test]
+    fn outbound_public_ip() {
+        for ip in [
+            "93.184.216.34",
+            "2606:2800:220:1:248:1893:25c8:1946",
+            "100.128.0.1",
+            "198.20.0.1",
+            "64:ff9b::5db8:d822",
+        ] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "100.64.0.1",
+            "100.127.255.254",
+            "0.1.2.3",
+            "198.18.0.1",
+            "198.19.255.254",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a00:1",
+            "::127.0.0.1",
+            "::10.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn outbound_resolve() {
+        let url = reqwest::Url::parse("http://127.0.0.1:8080/hook").unwrap();
+        assert_eq!(
+            resolve_outbound(&url, false).await,
+            Err(OutboundErr::NotPublic("127.0.0.1".parse().unwrap()))
+        );
+        assert_eq!(
+            resolve_outbound(&url, true).await,
+            Ok("127.0.0.1:8080".parse().unwrap())
+        );
+
+        let url = reqwest::Url::parse("https://[::1]/inbox").unwrap();
+        assert_eq!(
+            resolve_outbound(&url, false).await,
+            Err(OutboundErr::NotPublic("::1".parse().unwrap()))
+        );
+
+        let url = reqwest::Url::parse("http://localhost/hook").unwrap();
+        assert!(matches!(
+            resolve_outbound(&url, false).await,
+            Err(OutboundErr::NotPublic(_))
+        ));
+    }
+}
@@ -6,7 +6,15 @@ use std::process::Stdio;
 use std::str::FromStr;
 
 use crate::api::app_state::AppState;
+use crate::api::backend::activitypub::{dispatch_ap_post_create, dispatch_ap_post_delete};
+use crate::api::backend::webhook::{dispatch_webhook_post_event, webhook_post_published};
+use crate::api::cache::RELATED_POSTS_CACHE_LIMIT;
+use crate::api::shared::admin::{Permission, UserRole};
+use crate::api::shared::cursor::{page_cursors, page_limit};
 use crate::api::shared::post_comment::{PostCommentErrResolver, UserPostComment};
+use crate::api::shared::rating::{PostRatingErr, RatingPreference};
+use crate::api::shared::visibility::{PostVisibility, PostVisibilityErr};
+use crate::api::shared::webhook::WebhookEvent;
 use crate::api::{
     AuthToken, ChangeUsernameErr, EmailChangeErr, EmailChangeNewErr, EmailChangeStage,
     EmailChangeTokenErr, Server404Err, ServerAddPostErr, ServerAddPostFileErr, ServerAuthErr,
@@ -15,9 +23,11 @@ use crate::api::{
     ServerUpdatePostTagsErr, ServerUpdatePostTitleErr, User, UserPost, UserPostFile,
     auth_token_get, hash_password, verify_password,
 };
-use crate::db::{AddUserErr, DBPostAddFileErr, DBPostCommentErr, DBUser};
-use crate::db::{DB404Err, DBUserPostFile};
-use crate::valid::SUPPORTED_FILE_EXTENSIONS;
+use crate::db::{
+    AddUserErr, DBPostAddFileErr, DBPostCommentErr, DBPostRating, DBPostRatingErr, DBUser,
+};
+use crate::db::{DB404Err, DBUserPost, DBUserPostFile};
+use crate::valid::{MAX_POST_RELATED_LIMIT, SUPPORTED_FILE_EXTENSIONS};
 use crate::valid::auth::{
     proccess_password, proccess_post_description, proccess_post_tags, proccess_post_title,
     proccess_username,
@@ -33,6 +43,7 @@ use futures_util::StreamExt;
 use gxhash::GxHasher;
 use std::hash::{DefaultHasher, Hasher};
 // use axum_extra::extract::CookieJar;
+use http::HeaderMap;
 use http::header::COOKIE;
 use std::{io, pin::pin};
 use tokio::fs;
@@ -45,23 +56,36 @@ use tracing::{debug, error, info, trace};
 
 pub async fn get_posts(
     State(app_state): State<AppState>,
+    db_user: Extension<Option<DBUser>>,
+    headers: HeaderMap,
     req: ServerReq,
 ) -> Result<ServerRes, ServerErr> {
-    let ServerReq::GetPosts2 {
-        time,
+    let ServerReq::GetPosts {
+        cursor,
         order,
         limit,
         tags,
         username,
     } = req
     else {
-        return Err(
-            ServerDesErr::ServerWrongInput(format!("expected GetPost, received: {req:?}")).into(),
-        );
+        return Err(ServerDesErr::ServerWrongInput(format!(
+            "expected GetPosts, received: {req:?}"
+        ))
+        .into());
     };
-    let post = app_state
+    let limit = page_limit(limit)?;
+    let rating = RatingPreference::from_request(db_user.0.as_ref(), &headers);
+    let posts = app_state
         .db
-        .post_search(limit as usize, time, order, tags, username)
+        .post_search(
+            limit,
+            cursor,
+            order,
+            tags,
+            username,
+            db_user.0.as_ref().map(|user| user.id.clone()),
+            rating.max_rating.into(),
+        )
         .await
         .map_err(|_| ServerErr::DbErr)?
         .into_iter()
@@ -72,12 +96,16 @@ pub async fn get_posts(
     //     _ => ServerErr::DbErr,
     // })?;
 
+    let (next, prev) = page_cursors(&posts, limit);
+
     // Ok(ServerRes::Ok)
-    Ok(ServerRes::Posts(post))
+    Ok(ServerRes::PostPage { posts, next, prev })
 }
 
 pub async fn get_post(
     State(app_state): State<AppState>,
+    auth_token: Extension<Option<AuthToken>>,
+    db_user: Extension<Option<DBUser>>,
     req: ServerReq,
 ) -> Result<ServerRes, ServerErr> {
     let ServerReq::PostId { post_key: post_id } = req else {
@@ -94,282 +122,255 @@ pub async fn get_post(
             _ => ServerErr::DbErr,
         })?;
 
+    if !post_visible_to(&post, db_user.0.as_ref()) {
+        return Err(ServerErr::NotFoundErr(Server404Err::NotFound));
+    }
+
     Ok(ServerRes::Post(post.into()))
 }
 
-pub async fn get_posts_newer_or_equal_for_user(
-    State(app_state): State<AppState>,
-    req: ServerReq,
-) -> Result<ServerRes, ServerErr> {
-    let ServerReq::GetUserPosts {
-        time,
-        limit,
-        username,
-    } = req
-    else {
-        return Err(ServerDesErr::ServerWrongInput(format!(
-            "expected GetPostAfter, received: {req:?}"
-        ))
-        .into());
-    };
-
-    let user = app_state
-        .db
-        .get_user_by_username(username)
-        .await
-        .map_err(|err| match err {
-            DB404Err::NotFound => Server404Err::NotFound.into(),
-            DB404Err::DB(_) => ServerErr::DbErr,
-        })?;
+pub fn post_visible_to(post: &DBUserPost, db_user: Option<&DBUser>) -> bool {
+    // hidden posts are only visible to the owner and staff
+    let visible = post.show
+        || db_user.is_some_and(|user| {
+            user.id == post.user.id || UserRole::from_db(&user.role).can(Permission::HidePost)
+        });
 
-    let posts = app_state
-        .db
-        .get_post_newer_or_equal_for_user(time, limit, user.id.clone())
-        .await
-        .map_err(|_| ServerErr::DbErr)?
-        .into_iter()
-        .map(UserPost::from)
-        .collect::<Vec<UserPost>>();
+    // drafts and scheduled posts are only visible to the owner until they're published
+    let published = PostVisibility::from_db(&post.visibility).is_published()
+        || db_user.is_some_and(|user| user.id == post.user.id);
 
-    Ok(ServerRes::Posts(posts))
+    visible && published
 }
 
-pub async fn get_posts_older_or_equal_for_user(
+pub async fn get_posts_related(
     State(app_state): State<AppState>,
+    db_user: Extension<Option<DBUser>>,
+    headers: HeaderMap,
     req: ServerReq,
 ) -> Result<ServerRes, ServerErr> {
-    let ServerReq::GetUserPosts {
-        time,
-        limit,
-        username,
-    } = req
-    else {
+    let ServerReq::GetRelatedPosts { post_key, limit } = req else {
         return Err(ServerDesErr::ServerWrongInput(format!(
-            "expected GetPostAfter, received: {req:?}"
+            "expected GetRelatedPosts, received: {req:?}"
         ))
         .into());
     };
+    let limit = limit.min(MAX_POST_RELATED_LIMIT);
+    let time = app_state.time().await;
 
-    let user = app_state
+    let post = app_state
         .db
-        .get_user_by_username(username)
+        .get_post(post_key.clone())
         .await
         .map_err(|err| match err {
-            DB404Err::NotFound => Server404Err::NotFound.into(),
-            DB404Err::DB(_) => ServerErr::DbErr,
+            DB404Err::NotFound => ServerErr::NotFoundErr(Server404Err::NotFound),
+            _ => ServerErr::DbErr,
         })?;
+    if !post_visible_to(&post, db_user.0.as_ref()) {
+        return Err(ServerErr::NotFoundErr(Server404Err::NotFound));
+    }
 
-    let posts = app_state
-        .db
-        .get_post_older_or_equal_for_user(time, limit, user.id.clone())
-        .await
-        .map_err(|_| ServerErr::DbErr)?
-        .into_iter()
-        .map(UserPost::from)
-        .collect::<Vec<UserPost>>();
-
-    Ok(ServerRes::Posts(posts))
-}
-
-pub async fn get_posts_older_for_user(
-    State(app_state): State<AppState>,
-    req: ServerReq,
-) -> Result<ServerRes, ServerErr> {
-    let ServerReq::GetUserPosts {
-        time,
-        limit,
-        username,
-    } = req
-    else {
-        return Err(ServerDesErr::ServerWrongInput(format!(
-            "expected GetPostAfter, received: {req:?}"
-        ))
-        .into());
+    // the cache is shared by every viewer, rating and blocks are filtered per request
+    let candidates = match app_state.related_posts_cache.get(time, &post_key).await {
+        Some(posts) => {
+            trace!("related posts cache hit for {post_key}");
+            posts
+        }
+        None => {
+            let posts = app_state
+                .db
+                .get_post_related(post_key.clone(), RELATED_POSTS_CACHE_LIMIT)
+                .await
+                .map_err(|err| match err {
+                    DB404Err::NotFound => ServerErr::NotFoundErr(Server404Err::NotFound),
+                    _ => ServerErr::DbErr,
+                })?;
+            app_state
+                .related_posts_cache
+                .set(time, post_key, posts.clone())
+                .await;
+            posts
+        }
     };
 
-    let user = app_state
-        .db
-        .get_user_by_username(username)
-        .await
-        .map_err(|err| match err {
-            DB404Err::NotFound => Server404Err::NotFound.into(),
-            DB404Err::DB(_) => ServerErr::DbErr,
-        })?;
+    let rating = RatingPreference::from_request(db_user.0.as_ref(), &headers);
+    let ratings = DBPostRating::from(rating.max_rating).up_to();
+    let blocked = match db_user.0.as_ref() {
+        Some(user) => app_state
+            .db
+            .get_user_block_targets(user.id.clone())
+            .await
+            .map_err(|_| ServerErr::DbErr)?,
+        None => Vec::new(),
+    };
 
-    let posts = app_state
-        .db
-        .get_post_older_for_user(time, limit, user.id.clone())
-        .await
-        .map_err(|_| ServerErr::DbErr)?
+    let posts = candidates
         .into_iter()
+        .filter(|post| ratings.contains(&post.rating) && !blocked.contains(&post.user.id))
+        .take(limit)
         .map(UserPost::from)
         .collect::<Vec<UserPost>>();
 
     Ok(ServerRes::Posts(posts))
 }
 
-pub async fn get_posts_newer_for_user(
-    State(app_state): State<AppState>,
+pub async fn update_post_title(
+    State(app): State<AppState>,
+    // auth_token: axum::Extension<AuthToken>,
+    db_user: Extension<DBUser>,
     req: ServerReq,
 ) -> Result<ServerRes, ServerErr> {
-    let ServerReq::GetUserPosts {
-        time,
-        limit,
-        username,
+    type ResErr = ServerUpdatePostTitleErr;
+
+    let ServerReq::EditPostTitle {
+        post_key,
+        new_title,
     } = req
     else {
         return Err(ServerDesErr::ServerWrongInput(format!(
-            "expected GetPostAfter, received: {req:?}"
+            "expected EditPostDescription, received: {req:?}"
         ))
         .into());
     };
 
-    let user = app_state
+    let new_title = new_title.trim();
+    proccess_post_title(new_title).map_err(|err| ResErr::TooLong)?;
+
+    let post = app
         .db
-        .get_user_by_username(username)
+        .update_post_title(0, db_user.id.clone(), post_key, new_title)
         .await
         .map_err(|err| match err {
-            DB404Err::NotFound => Server404Err::NotFound.into(),
-            DB404Err::DB(_) => ServerErr::DbErr,
+            DB404Err::NotFound => ResErr::NotFound.into(),
+            _ => ServerErr::DbErr,
         })?;
+    //
+    let time = app.time().await;
+    dispatch_webhook_post_event(&app, time, WebhookEvent::PostUpdated, &post).await;
 
-    let posts = app_state
-        .db
-        .get_post_newer_for_user(time, limit, user.id.clone())
-        .await
-        .map_err(|_| ServerErr::DbErr)?
-        .into_iter()
-        .map(UserPost::from)
-        .collect::<Vec<UserPost>>();
-
-    Ok(ServerRes::Posts(posts))
+    Ok(ServerRes::Post(post.into()))
 }
 
-pub async fn get_posts_newer_or_equal(
-    State(app_state): State<AppState>,
+pub async fn update_post_rating(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
     req: ServerReq,
 ) -> Result<ServerRes, ServerErr> {
-    let ServerReq::GetPosts { time, limit } = req else {
-        return Err(ServerDesErr::ServerWrongInput(format!(
-            "expected GetPostAfter, received: {req:?}"
-        ))
-        .into());
-    };
-    let posts = app_state
-        .db
-        .get_post_newer_or_equal(time, limit)
-        .await
-        .map_err(|_| ServerErr::DbErr)?
-        .into_iter()
-        .map(UserPost::from)
-        .collect::<Vec<UserPost>>();
+    type ResErr = PostRatingErr;
 
-    Ok(ServerRes::Posts(posts))
-}
-
-pub async fn get_posts_older_or_equal(
-    State(app_state): State<AppState>,
-    req: ServerReq,
-) -> Result<ServerRes, ServerErr> {
-    let ServerReq::GetPosts { time, limit } = req else {
+    let ServerReq::EditPostRating { post_key, rating } = req else {
         return Err(ServerDesErr::ServerWrongInput(format!(
-            "expected GetPostAfter, received: {req:?}"
+            "expected EditPostRating, received: {req:?}"
         ))
         .into());
     };
+    let time = app.time().await;
 
-    let posts = app_state
+    let post = app
         .db
-        .get_post_older_or_equal(time, limit)
+        .update_post_rating(time, db_user.id.clone(), post_key, rating.into())
         .await
-        .map_err(|_| ServerErr::DbErr)?
-        .into_iter()
-        .map(UserPost::from)
-        .collect::<Vec<UserPost>>();
+        .map_err(|err| match err {
+            DBPostRatingErr::NotFound => ResErr::NotFound.into(),
+            DBPostRatingErr::Locked => ResErr::Locked.into(),
+            DBPostRatingErr::DB(_) => ServerErr::DbErr,
+        })?;
+    dispatch_webhook_post_event(&app, time, WebhookEvent::PostUpdated, &post).await;
 
-    Ok(ServerRes::Posts(posts))
+    Ok(ServerRes::Post(post.into()))
 }
 
-pub async fn get_posts_newer(
-    State(app_state): State<AppState>,
+pub async fn update_post_visibility(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
     req: ServerReq,
 ) -> Result<ServerRes, ServerErr> {
-    let ServerReq::GetPosts { time, limit } = req else {
+    let ServerReq::EditPostVisibility {
+        post_key,
+        visibility,
+        publish_at,
+    } = req
+    else {
         return Err(ServerDesErr::ServerWrongInput(format!(
-            "expected GetPostAfter, received: {req:?}"
+            "expected EditPostVisibility, received: {req:?}"
         ))
         .into());
     };
-    let posts = app_state
-        .db
-        .get_post_newer(time, limit)
-        .await
-        .map_err(|_| ServerErr::DbErr)?
-        .into_iter()
-        .map(UserPost::from)
-        .collect::<Vec<UserPost>>();
-
-    Ok(ServerRes::Posts(posts))
-}
-
-pub async fn get_posts_older(
-    State(app_state): State<AppState>,
-    req: ServerReq,
-) -> Result<ServerRes, ServerErr> {
-    let ServerReq::GetPosts { time, limit } = req else {
-        return Err(ServerDesErr::ServerWrongInput(format!(
-            "expected GetPostAfter, received: {req:?}"
-        ))
-        .into());
+    let time = app.time().await;
+    let publish_at = valid_publish_at(time, visibility, publish_at)?;
+    let was_published = match app.db.get_post(post_key.clone()).await {
+        Ok(post) => webhook_post_published(&post),
+        Err(DB404Err::NotFound) => return Err(PostVisibilityErr::NotFound.into()),
+        Err(DB404Err::DB(_)) => return Err(ServerErr::DbErr),
     };
 
-    let posts = app_state
+    let post = app
         .db
-        .get_post_older(time, limit)
+        .update_post_visibility(
+            time,
+            db_user.id.clone(),
+            post_key,
+            visibility.into(),
+            publish_at,
+        )
         .await
-        .map_err(|_| ServerErr::DbErr)?
-        .into_iter()
-        .map(UserPost::from)
-        .collect::<Vec<UserPost>>();
+        .map_err(|err| match err {
+            DB404Err::NotFound => PostVisibilityErr::NotFound.into(),
+            DB404Err::DB(_) => ServerErr::DbErr,
+        })?;
+    let event = if !was_published && webhook_post_published(&post) {
+        WebhookEvent::PostCreated
+    } else {
+        WebhookEvent::PostUpdated
+    };
+    dispatch_webhook_post_event(&app, time, event, &post).await;
 
-    Ok(ServerRes::Posts(posts))
+    Ok(ServerRes::Post(post.into()))
 }
 
-pub async fn update_post_title(
+pub async fn update_post_embeddable(
     State(app): State<AppState>,
-    // auth_token: axum::Extension<AuthToken>,
     db_user: Extension<DBUser>,
     req: ServerReq,
 ) -> Result<ServerRes, ServerErr> {
-    type ResErr = ServerUpdatePostTitleErr;
-
-    let ServerReq::EditPostTitle {
+    let ServerReq::EditPostEmbeddable {
         post_key,
-        new_title,
+        embeddable,
     } = req
     else {
         return Err(ServerDesErr::ServerWrongInput(format!(
-            "expected EditPostDescription, received: {req:?}"
+            "expected EditPostEmbeddable, received: {req:?}"
         ))
         .into());
     };
-
-    let new_title = new_title.trim();
-    proccess_post_title(new_title).map_err(|err| ResErr::TooLong)?;
+    let time = app.time().await;
 
     let post = app
         .db
-        .update_post_title(0, db_user.id.clone(), post_key, new_title)
+        .update_post_embeddable(time, db_user.id.clone(), post_key, embeddable)
         .await
         .map_err(|err| match err {
-            DB404Err::NotFound => ResErr::NotFound.into(),
-            _ => ServerErr::DbErr,
+            DB404Err::NotFound => PostVisibilityErr::NotFound.into(),
+            DB404Err::DB(_) => ServerErr::DbErr,
         })?;
-    //
+    dispatch_webhook_post_event(&app, time, WebhookEvent::PostUpdated, &post).await;
 
     Ok(ServerRes::Post(post.into()))
 }
 
+/// only scheduled posts keep a publish time and it has to be in the future
+pub fn valid_publish_at(
+    time: u128,
+    visibility: PostVisibility,
+    publish_at: u128,
+) -> Result<u128, PostVisibilityErr> {
+    match visibility {
+        PostVisibility::Scheduled if publish_at <= time => Err(PostVisibilityErr::PublishAtInPast),
+        PostVisibility::Scheduled => Ok(publish_at),
+        _ => Ok(0),
+    }
+}
+
 pub async fn update_post_description(
     State(app): State<AppState>,
     // auth_token: axum::Extension<AuthToken>,
@@ -401,6 +402,8 @@ pub async fn update_post_description(
             _ => ServerErr::DbErr,
         })?;
     //
+    let time = app.time().await;
+    dispatch_webhook_post_event(&app, time, WebhookEvent::PostUpdated, &post).await;
 
     Ok(ServerRes::Post(post.into()))
 }
@@ -435,6 +438,8 @@ pub async fn update_post_tags(
             _ => ServerErr::DbErr,
         })?;
     //
+    let time = app.time().await;
+    dispatch_webhook_post_event(&app, time, WebhookEvent::PostUpdated, &post).await;
 
     Ok(ServerRes::Post(post.into()))
 }
@@ -450,11 +455,18 @@ pub async fn delete_post(
         );
     };
 
-    app.db
-        .delete_post(db_user.id.clone(), post_key)
+    // the purge deletes it for real once it's been in the trash long enough
+    let time = app.time().await;
+    let post = app
+        .db
+        .trash_post(time, db_user.id.clone(), post_key)
         .await
-        .map_err(|_| ServerErr::DbErr)?;
-    //
+        .map_err(|err| match err {
+            DB404Err::NotFound => Server404Err::NotFound.into(),
+            DB404Err::DB(_) => ServerErr::DbErr,
+        })?;
+    dispatch_webhook_post_event(&app, time, WebhookEvent::PostDeleted, &post).await;
+    dispatch_ap_post_delete(&app, time, &post).await;
 
     Ok(ServerRes::Ok)
 }
@@ -592,6 +604,84 @@ pub async fn get_img_resolution(img_path: impl AsRef<str>) -> anyhow::Result<(u3
     resolution_from_str(result)
 }
 
+pub struct SavedImg {
+    pub file: SavedFile,
+    pub extension: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// saves a single multipart image field into the files directory,
+/// fields without a file name are skipped with None
+pub async fn save_img_field(
+    app: &AppState,
+    field: axum::extract::multipart::Field<'_>,
+    storage_per_file: usize,
+) -> Result<Option<SavedImg>, ServerErr> {
+    type Err = ServerAddPostFileErr;
+
+    let file_name = if let Some(file_name) = field.file_name() {
+        file_name.to_owned()
+    } else {
+        return Ok(None);
+    };
+
+    let Some(extension) = Path::new(&file_name).extension().and_then(|v| v.to_str()) else {
+        return Err(ServerErr::from(Err::FileHasNoExtension(
+            file_name.to_string(),
+        )));
+    };
+    let extension = extension.to_string();
+    let is_supported = SUPPORTED_FILE_EXTENSIONS
+        .into_iter()
+        .any(|v| *v == extension);
+    if !is_supported {
+        return Err(ServerErr::from(Err::UnsupportedExtension(extension)));
+    }
+
+    let file_path = app.get_file_path().await;
+    let stream = field.map_err(io::Error::other);
+    let file = handle_file_saving(stream, &extension, file_path, storage_per_file)
+        .await
+        .map_err(|err| match err {
+            SaveFileErr::FileTooBig {
+                got_bytes,
+                max_bytes,
+            } => ServerErr::from(Err::FileTooBig {
+                file_name: file_name.to_string(),
+                max: max_bytes,
+                got: got_bytes,
+            }),
+            SaveFileErr::IoErr(err) => ServerErr::from(Err::IoErr(err.to_string())),
+            SaveFileErr::StreamErr(err) => ServerErr::from(Err::StreamErr(err.to_string())),
+        })?;
+
+    let result = get_img_resolution(file.saved_path.to_str().unwrap()).await;
+    let (width, height) = match result {
+        Ok(v) => v,
+        Err(err) => {
+            tokio::fs::remove_file(&file.saved_path)
+                .await
+                .map_err(|err| ServerErr::from(Err::IoErr(err.to_string())))?;
+            return Err(ServerErr::from(Err::ReadingResolutionErr(err.to_string())));
+        }
+    };
+
+    if width == 0 || height == 0 {
+        tokio::fs::remove_file(&file.saved_path)
+            .await
+            .map_err(|err| ServerErr::from(Err::IoErr(err.to_string())))?;
+        return Err(ServerErr::from(Err::InvalidResolution { width, height }));
+    }
+
+    Ok(Some(SavedImg {
+        file,
+        extension,
+        width,
+        height,
+    }))
+}
+
 pub async fn add_post_file(
     State(app): State<AppState>,
     params: axum::extract::RawPathParams,
@@ -605,6 +695,7 @@ pub async fn add_post_file(
     let max_storage = db_user.max_storage_bytes;
     let max_storage_per_file = db_user.max_storage_per_file_bytes;
     let mut used_storage = db_user.used_storage_bytes;
+    let mut added = 0;
 
     let mut inner = async || -> Result<ServerRes, ServerErr> {
         let time = app.clock.now().await;
@@ -618,26 +709,6 @@ pub async fn add_post_file(
         trace!("PATH PARAMS {post_key:?}");
 
         while let Ok(Some(field)) = multipart.next_field().await {
-            let file_name = if let Some(file_name) = field.file_name() {
-                file_name.to_owned()
-            } else {
-                continue;
-            };
-
-            let Some(extension) = Path::new(&file_name).extension().and_then(|v| v.to_str()) else {
-                return Err(ServerErr::from(Err::FileHasNoExtension(
-                    file_name.to_string(),
-                )));
-            };
-            let is_supported = SUPPORTED_FILE_EXTENSIONS
-                .into_iter()
-                .any(|v| *v == extension);
-            if !is_supported {
-                return Err(ServerErr::from(Err::UnsupportedExtension(
-                    extension.to_string(),
-                )));
-            }
-
             let storage_left = max_storage.saturating_sub(used_storage);
             let storage_per_file = if storage_left < max_storage_per_file {
                 storage_left
@@ -652,41 +723,16 @@ pub async fn add_post_file(
             //     }));
             // }
 
-            let file_path = app.get_file_path().await;
-            let stream = field.map_err(io::Error::other);
-            let file = handle_file_saving(stream, extension, file_path, storage_per_file)
-                .await
-                .map_err(|err| match err {
-                    SaveFileErr::FileTooBig {
-                        got_bytes,
-                        max_bytes,
-                    } => ServerErr::from(Err::FileTooBig {
-                        file_name: file_name.to_string(),
-                        max: max_bytes,
-                        got: got_bytes,
-                    }),
-                    SaveFileErr::IoErr(err) => ServerErr::from(Err::IoErr(err.to_string())),
-                    SaveFileErr::StreamErr(err) => ServerErr::from(Err::StreamErr(err.to_string())),
-                })?;
-
-            let result = get_img_resolution(file.saved_path.to_str().unwrap()).await;
-            let (width, height) = match result {
-                Ok(v) => v,
-                Err(err) => {
-                    tokio::fs::remove_file(&file.saved_path)
-                        .await
-                        .map_err(|err| ServerErr::from(Err::IoErr(err.to_string())))?;
-                    return Err(ServerErr::from(Err::ReadingResolutionErr(err.to_string())));
-                }
+            let Some(SavedImg {
+                file,
+                extension,
+                width,
+                height,
+            }) = save_img_field(&app, field, storage_per_file).await?
+            else {
+                continue;
             };
 
-            if width == 0 || height == 0 {
-                tokio::fs::remove_file(&file.saved_path)
-                    .await
-                    .map_err(|err| ServerErr::from(Err::IoErr(err.to_string())))?;
-                return Err(ServerErr::from(Err::InvalidResolution { width, height }));
-            }
-
             let result = app
                 .db
                 .add_post_file(
@@ -718,6 +764,7 @@ pub async fn add_post_file(
             // .map_err(|v| ServerErr::DbErr)?;
 
             used_storage = post.user.used_storage_bytes;
+            added += 1;
 
             // post.user.used_storage_bytes >
 
@@ -728,6 +775,10 @@ pub async fn add_post_file(
             DB404Err::NotFound => Err::NotFound.into(),
             DB404Err::DB(_) => ServerErr::DbErr,
         })?;
+        // posts are created empty, they federate once the first upload fills them
+        if added > 0 && post.file.len() == added {
+            dispatch_ap_post_create(&app, time, &post).await;
+        }
 
         Ok(ServerRes::Post(post.into()))
     };
@@ -908,6 +959,9 @@ pub async fn add_post(
         title,
         description,
         tags,
+        rating,
+        visibility,
+        publish_at,
         // files,
     } = req
     else {
@@ -927,6 +981,7 @@ pub async fn add_post(
     proccess_post_title(title).map_err(|err| Err::InvalidTitle(err.to_string()))?;
     proccess_post_description(description)
         .map_err(|err| Err::InvalidDescription(err.to_string()))?;
+    let publish_at = valid_publish_at(time, visibility, publish_at)?;
 
     // let (files, errs) = files
     //     .into_iter()
@@ -1064,11 +1119,17 @@ pub async fn add_post(
             description,
             tags,
             0,
+            rating.into(),
+            visibility.into(),
+            publish_at,
             // post_files,
         )
         .await
         .inspect_err(|err| error!("failed to save images {err:?}"))
         .map_err(|_| ServerErr::DbErr)?;
+    if webhook_post_published(&post) {
+        dispatch_webhook_post_event(&app, time, WebhookEvent::PostCreated, &post).await;
+    }
 
     Ok(ServerRes::Post(post.into()))
 }
@@ -1096,14 +1157,20 @@ mod tests {
 
     use crate::api::app_state::AppState;
     use crate::api::backend::post::{SaveFileErr, handle_file_saving, resolution_from_str};
+    use crate::api::backend::publish_scheduled_posts;
     use crate::api::shared::post_comment::UserPostComment;
+    use crate::api::shared::rating::PostRating;
+    use crate::api::shared::user_block::UserBlockKind;
+    use crate::api::shared::visibility::{PostVisibility, PostVisibilityErr};
     use crate::api::tests::ApiTestApp;
     use crate::api::{
         Api, ApiTest, EmailChangeErr, EmailChangeNewErr, EmailChangeStage, EmailChangeTokenErr,
         Order, PostLikeErr, Server404Err, ServerAddPostFileErr, ServerAuthErr, ServerErr,
         ServerLoginErr, ServerRegistrationErr, ServerReqImg, ServerRes, ServerSendInviteErr,
-        TimeRange, UserPost,
+        UserPost,
+        shared::cursor::{Cursor, CursorErr, CursorRange},
     };
+    use crate::db::audit::DBAudit;
     use crate::db::email_change::create_email_change_id;
     use crate::db::post_comment::DBPostComment;
     use crate::db::{DB404Err, to_post_file_path, to_post_thumbnail_path};
@@ -1136,7 +1203,7 @@ mod tests {
                 3,
                 auth_token,
                 3,
-                TimeRange::Less(3),
+                CursorRange::Before(Cursor::at(3)),
                 Order::ThreeTwoOne,
                 "one",
                 "hey",
@@ -1147,6 +1214,105 @@ mod tests {
         assert_eq!(posts.len(), 3);
     }
 
+    #[tokio::test]
+    async fn api_post_related_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        let post0 = app
+            .add_post(0, &auth_token, "title1", "cat", "one two three")
+            .await
+            .unwrap();
+        app.add_post(1, &auth_token, "title2", "cat", "one two")
+            .await
+            .unwrap();
+
+        let result = app.get_posts_related(2, "404", 10).await;
+        assert_eq!(result, Err(ServerErr::NotFoundErr(Server404Err::NotFound)));
+
+        let posts = app.get_posts_related(2, &post0.key, 10).await.unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].title, "title2");
+
+        // cached
+        app.add_post(3, &auth_token, "title3", "cat", "one")
+            .await
+            .unwrap();
+        let posts = app.get_posts_related(4, &post0.key, 10).await.unwrap();
+        assert_eq!(posts.len(), 1);
+
+        let time = 2 + crate::api::cache::RELATED_POSTS_CACHE_TTL_NS;
+        let posts = app.get_posts_related(time, &post0.key, 10).await.unwrap();
+        assert_eq!(posts.len(), 2);
+        assert_eq!(posts[0].title, "title2");
+        assert_eq!(posts[1].title, "title3");
+
+        // cached once for every viewer, rating and blocks still apply per viewer
+        let auth_token2 = app
+            .register(time, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token3 = app
+            .register(time, "hey3", "hey3@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        app.add_post(time + 1, &auth_token2, "title4", "cat", "one")
+            .await
+            .unwrap();
+        let post5 = app
+            .add_post(time + 2, &auth_token2, "title5", "cat", "one")
+            .await
+            .unwrap();
+        app.update_post_rating(time + 3, &auth_token2, &post5.key, PostRating::Explicit)
+            .await
+            .unwrap();
+        app.update_rating_preference(time + 3, &auth_token2, PostRating::Explicit, false)
+            .await
+            .unwrap();
+        app.add_user_block(time + 3, &auth_token3, "hey2", UserBlockKind::Block)
+            .await
+            .unwrap();
+
+        let time = time + crate::api::cache::RELATED_POSTS_CACHE_TTL_NS;
+        app.set_time(time).await;
+        let titles = async |auth_token: Option<&str>| {
+            let req = app.api.get_posts_related(&post0.key, 10);
+            let result = match auth_token {
+                Some(auth_token) => req.send_native_with_token(auth_token).await,
+                None => req.send_native().await,
+            };
+            let Ok(ServerRes::Posts(posts)) = result else {
+                panic!("expected Posts, received {result:?}");
+            };
+            posts
+                .into_iter()
+                .map(|post| post.title)
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            titles(Some(auth_token2.as_str())).await,
+            ["title2", "title3", "title5", "title4"]
+        );
+        assert_eq!(titles(None).await, ["title2", "title3", "title4"]);
+        assert_eq!(
+            titles(Some(auth_token3.as_str())).await,
+            ["title2", "title3"]
+        );
+
+        // the source post has to be visible to the viewer as well
+        app.update_post_visibility(time, &auth_token, &post0.key, PostVisibility::Draft, 0)
+            .await
+            .unwrap();
+        let result = app.get_posts_related(time, &post0.key, 10).await;
+        assert_eq!(result, Err(ServerErr::NotFoundErr(Server404Err::NotFound)));
+        assert_eq!(titles(Some(auth_token.as_str())).await.len(), 3);
+    }
+
     #[tokio::test]
     async fn api_post_delete_test() {
         crate::init_test_log();
@@ -1170,7 +1336,10 @@ mod tests {
             .unwrap();
 
         let posts = app.state.db.get_post_all().await.unwrap();
-        assert_eq!(posts.len(), 0);
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].deleted_at, 1);
+        let result = app.state.db.get_post(post0.key.clone()).await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
     }
 
     #[tokio::test]
@@ -1335,7 +1504,13 @@ mod tests {
             let user = app
                 .state
                 .db
-                .update_user_storage(0, user.id.clone(), max_storage, max_file)
+                .update_user_storage(
+                    0,
+                    user.id.clone(),
+                    max_storage,
+                    max_file,
+                    DBAudit::default(),
+                )
                 .await
                 .unwrap();
 
@@ -1517,6 +1692,72 @@ mod tests {
         app.expect_posts(0, 1, 2, 0, 1).await.unwrap();
         app.expect_posts(1, 0, 1, 1, 2).await.unwrap();
         app.expect_posts(2, 0, 0, 2, 2).await.unwrap();
+
+        // posts sharing a nanosecond are told apart by key, so no page skips or repeats one
+        for i in 0..3 {
+            app.add_post(2, &auth_token, format!("same{i}"), "cat", "same")
+                .await
+                .unwrap();
+        }
+        let mut keys = Vec::new();
+        let mut cursor = CursorRange::None;
+        loop {
+            let result = app
+                .api
+                .get_posts(2, cursor, Order::ThreeTwoOne, "same", "")
+                .send_native()
+                .await;
+            let (posts, next, prev) = match result {
+                Ok(ServerRes::PostPage { posts, next, prev }) => (posts, next, prev),
+                res => panic!("expected PostPage, received {res:?}"),
+            };
+            assert_eq!(prev, posts.first().map(Cursor::from));
+            keys.extend(posts.into_iter().map(|v| v.key));
+            let Some(next) = next else {
+                break;
+            };
+            cursor = CursorRange::Before(next);
+        }
+        assert_eq!(keys.len(), 3);
+        let mut unique = keys.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 3);
+
+        // walking back with prev returns the same posts in the opposite order
+        let result = app
+            .api
+            .get_posts(
+                10,
+                CursorRange::After(Cursor::at(2)),
+                Order::OneTwoThree,
+                "same",
+                "",
+            )
+            .send_native()
+            .await;
+        let posts = match result {
+            Ok(ServerRes::PostPage { posts, .. }) => posts,
+            res => panic!("expected PostPage, received {res:?}"),
+        };
+        keys.reverse();
+        assert_eq!(posts.into_iter().map(|v| v.key).collect::<Vec<_>>(), keys);
+
+        let result = app
+            .api
+            .get_posts(0, CursorRange::None, Order::ThreeTwoOne, "", "")
+            .send_native()
+            .await;
+        assert!(matches!(
+            result,
+            Err(ServerErr::CursorErr(CursorErr::InvalidLimit))
+        ));
+        let result = app
+            .api
+            .get_posts(1_000_000, CursorRange::None, Order::ThreeTwoOne, "", "")
+            .send_native()
+            .await;
+        assert!(matches!(result, Ok(ServerRes::PostPage { posts, .. }) if posts.len() == 5));
     }
 
     #[tokio::test]
@@ -1572,7 +1813,7 @@ mod tests {
                 0,
                 &auth_token,
                 2,
-                TimeRange::MoreOrEqual(0),
+                CursorRange::After(Cursor::at(0)),
                 Order::OneTwoThree,
                 "",
                 "",
@@ -1626,7 +1867,7 @@ mod tests {
                 0,
                 &auth_token,
                 2,
-                TimeRange::MoreOrEqual(0),
+                CursorRange::After(Cursor::at(0)),
                 Order::OneTwoThree,
                 "",
                 "",
@@ -1666,7 +1907,7 @@ mod tests {
                 0,
                 &auth_token,
                 2,
-                TimeRange::MoreOrEqual(0),
+                CursorRange::After(Cursor::at(0)),
                 Order::OneTwoThree,
                 "",
                 "",
@@ -1676,4 +1917,164 @@ mod tests {
         assert_eq!(posts.len(), 1);
         assert_eq!(posts[0].tags, "one");
     }
+
+    impl ApiTestApp {
+        pub async fn add_post_visibility(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            title: impl Into<String>,
+            visibility: PostVisibility,
+            publish_at: u128,
+        ) -> Result<UserPost, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .add_post(title, "", "", PostRating::General, visibility, publish_at)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Post(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Post, got {res:?}"),
+            }
+        }
+
+        pub async fn update_post_visibility(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            post_key: impl Into<String>,
+            visibility: PostVisibility,
+            publish_at: u128,
+        ) -> Result<UserPost, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .update_post_visibility(post_key, visibility, publish_at)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Post(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Post, got {res:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn api_post_visibility() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        let result = app
+            .add_post_visibility(1, &auth_token, "late", PostVisibility::Scheduled, 1)
+            .await;
+        assert_eq!(result, Err(PostVisibilityErr::PublishAtInPast.into()));
+
+        let draft = app
+            .add_post_visibility(1, &auth_token, "draft", PostVisibility::Draft, 0)
+            .await
+            .unwrap();
+        let scheduled = app
+            .add_post_visibility(2, &auth_token, "scheduled", PostVisibility::Scheduled, 10)
+            .await
+            .unwrap();
+        let unlisted = app
+            .add_post_visibility(3, &auth_token, "unlisted", PostVisibility::Unlisted, 0)
+            .await
+            .unwrap();
+        app.add_post_visibility(4, &auth_token, "public", PostVisibility::Public, 0)
+            .await
+            .unwrap();
+        assert_eq!(scheduled.publish_at, 10);
+
+        for post in [&draft, &scheduled] {
+            let result = app
+                .api
+                .get_post(post.key.clone())
+                .send_native_with_token(&auth_token2)
+                .await;
+            assert_eq!(result, Err(Server404Err::NotFound.into()));
+            let result = app
+                .api
+                .get_post(post.key.clone())
+                .send_native_with_token(&auth_token)
+                .await;
+            assert!(matches!(result, Ok(ServerRes::Post(_))));
+        }
+        let result = app.api.get_post(unlisted.key.clone()).send_native().await;
+        assert!(matches!(result, Ok(ServerRes::Post(_))));
+
+        let titles = async |auth_token: &str, username: &str| {
+            app.get_posts(
+                5,
+                auth_token,
+                10,
+                CursorRange::None,
+                Order::ThreeTwoOne,
+                "",
+                username,
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|v| v.title)
+            .collect::<Vec<String>>()
+        };
+        assert_eq!(titles(&auth_token2, "").await, vec!["public"]);
+        assert_eq!(titles(&auth_token2, "hey").await, vec!["public"]);
+        assert_eq!(
+            titles(&auth_token, "hey").await,
+            vec!["public", "unlisted", "scheduled", "draft"]
+        );
+
+        app.set_time(10).await;
+        publish_scheduled_posts(&app.state).await.unwrap();
+        assert_eq!(titles(&auth_token2, "").await, vec!["scheduled", "public"]);
+
+        let result = app
+            .update_post_visibility(
+                11,
+                &auth_token2,
+                draft.key.clone(),
+                PostVisibility::Public,
+                0,
+            )
+            .await;
+        assert_eq!(result, Err(PostVisibilityErr::NotFound.into()));
+        let result = app
+            .update_post_visibility(
+                11,
+                &auth_token,
+                draft.key.clone(),
+                PostVisibility::Scheduled,
+                11,
+            )
+            .await;
+        assert_eq!(result, Err(PostVisibilityErr::PublishAtInPast.into()));
+        let post = app
+            .update_post_visibility(
+                11,
+                &auth_token,
+                draft.key.clone(),
+                PostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(post.visibility, PostVisibility::Public);
+        assert_eq!(post.created_at, 11);
+        assert_eq!(
+            titles(&auth_token2, "").await,
+            vec!["draft", "scheduled", "public"]
+        );
+    }
 }
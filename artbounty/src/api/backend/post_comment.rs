@@ -1,5 +1,10 @@
 use crate::api::app_state::AppState;
+use crate::api::backend::v1::V1Comment;
+use crate::api::backend::webhook::dispatch_webhook_event;
+use crate::api::shared::cursor::{page_cursors, page_limit};
+use crate::api::shared::live::LiveEvent;
 use crate::api::shared::post_comment::{PostCommentErrResolver, UserPostComment};
+use crate::api::shared::webhook::WebhookEvent;
 use crate::api::{
     AddPostCommentErr, AuthToken, ChangeUsernameErr, EmailChangeErr, EmailChangeNewErr,
     EmailChangeStage, EmailChangeTokenErr, Server404Err, ServerAddPostErr, ServerAuthErr,
@@ -46,6 +51,7 @@ pub async fn update_post_comment(
         })?;
     // DBPostCommentErr::PostNotFound(_) => ResErr::NotFound(format!("post \"{post_id}\" not found")).into(),
     let comment = UserPostComment::from(comment);
+    app.live.publish(LiveEvent::CommentUpdated(comment.clone()));
 
     // //
     Ok(ServerRes::Comment(comment))
@@ -85,10 +91,28 @@ pub async fn add_post_comment(
         .await
         .map_err(|err| match err {
             DBPostCommentErr::PostNotFound(_) => ResErr::NotFound.into(),
+            DBPostCommentErr::Blocked => ResErr::Blocked.into(),
             DBPostCommentErr::DB(_) | DBPostCommentErr::ReplyCommentNotFound(_) => ServerErr::DbErr,
         })?;
     // DBPostCommentErr::PostNotFound(_) => ResErr::NotFound(format!("post \"{post_id}\" not found")).into(),
+    if let Err(err) = app.db.add_notification_post_comment(time, &comment).await {
+        error!("failed to add post comment notification: {err}");
+    }
+    let post_id = comment.post.clone();
     let comment = UserPostComment::from(comment);
+    app.live.publish(LiveEvent::CommentAdded(comment.clone()));
+    let data = serde_json::json!({
+        "post_key": comment.post_key,
+        "comment": V1Comment::from(comment.clone()),
+    });
+    dispatch_webhook_event(
+        &app,
+        time,
+        WebhookEvent::CommentCreated,
+        Some(post_id),
+        data,
+    )
+    .await;
 
     // //
     Ok(ServerRes::Comment(comment))
@@ -96,6 +120,7 @@ pub async fn add_post_comment(
 
 pub async fn get_post_comment(
     State(app): State<AppState>,
+    db_user: Extension<Option<DBUser>>,
     req: ServerReq,
 ) -> Result<ServerRes, ServerErr> {
     type ResErr = Server404Err;
@@ -104,7 +129,7 @@ pub async fn get_post_comment(
         post_key,
         comment_key,
         limit,
-        time_range,
+        cursor,
         order,
         flatten,
     } = req
@@ -113,6 +138,7 @@ pub async fn get_post_comment(
             "get_post_comment expected PostId, received: {req:?}"
         ))));
     };
+    let limit = page_limit(limit)?;
     let time = app.time().await;
 
     let comments: Vec<UserPostComment> = app
@@ -123,8 +149,9 @@ pub async fn get_post_comment(
             comment_key.clone(),
             flatten,
             limit,
-            time_range,
+            cursor,
             order,
+            db_user.0.as_ref().map(|user| user.id.clone()),
         )
         .await
         .map_err(|err| match err {
@@ -133,11 +160,17 @@ pub async fn get_post_comment(
             DB404Err::DB(_) => ServerErr::DbErr,
         })?
         .into_iter()
-        .map(|v| UserPostComment::from(v))
+        .map(|v| UserPostComment::from(v).redacted())
         .collect();
 
+    let (next, prev) = page_cursors(&comments, limit);
+
     // //
-    Ok(ServerRes::Comments(comments))
+    Ok(ServerRes::CommentPage {
+        comments,
+        next,
+        prev,
+    })
 }
 
 pub async fn delete_post_comment(
@@ -154,10 +187,28 @@ pub async fn delete_post_comment(
     };
     let time = app.time().await;
 
-    app.db
-        .delete_post_comment(db_user.id.clone(), comment_id.clone())
+    // someone else's comment is left alone without telling who owns it
+    let comment = match app
+        .db
+        .trash_post_comment(time, db_user.id.clone(), comment_id.clone())
         .await
-        .map_err(|err| ServerErr::DbErr)?;
+    {
+        Ok(comment) => UserPostComment::from(comment),
+        Err(DB404Err::NotFound) => return Ok(ServerRes::Ok),
+        Err(DB404Err::DB(_)) => return Err(ServerErr::DbErr),
+    };
+
+    // replies keep the trashed comment around as a placeholder
+    if comment.replies_count > 0 {
+        app.live
+            .publish(LiveEvent::CommentUpdated(comment.redacted()));
+    } else {
+        app.live.publish(LiveEvent::CommentDeleted {
+            post_key: comment.post_key,
+            comment_key: comment.key,
+            parent_key: comment.parent_key,
+        });
+    }
 
     Ok(ServerRes::Ok)
 }
@@ -165,8 +216,12 @@ pub async fn delete_post_comment(
 #[cfg(test)]
 mod tests {
     use crate::api::{
-        AddPostCommentErr, Api, Order, ServerErr, ServerRes, TimeRange, UpdatePostCommentErr,
-        shared::post_comment::UserPostComment, tests::ApiTestApp,
+        AddPostCommentErr, Api, Order, ServerErr, ServerRes, UpdatePostCommentErr,
+        shared::{
+            cursor::{CursorErr, CursorRange},
+            post_comment::UserPostComment,
+        },
+        tests::ApiTestApp,
     };
     use surrealdb::types::ToSql;
     use tracing::{debug, error, trace};
@@ -230,6 +285,26 @@ mod tests {
             }
         }
 
+        pub async fn delete_post_comment(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            comment_key: impl Into<String>,
+        ) -> Option<()> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .delete_post_comment(comment_key)
+                .send_native_with_token(auth_token)
+                .await;
+
+            if result == Ok(ServerRes::Ok) {
+                Some(())
+            } else {
+                None
+            }
+        }
+
         pub async fn get_post_comments(
             &self,
             server_time: u128,
@@ -237,21 +312,21 @@ mod tests {
             post_key: impl Into<String>,
             comment_key: Option<String>,
             limit: usize,
-            time_range: TimeRange,
+            cursor: CursorRange,
             order: Order,
         ) -> Option<Vec<UserPostComment>> {
             self.set_time(server_time).await;
             let result = self
                 .api
-                .get_post_comment(post_key, comment_key, limit, time_range, order, false)
+                .get_post_comment(post_key, comment_key, limit, cursor, order, false)
                 .send_native_with_token(auth_token)
                 .await;
 
-            let Ok(ServerRes::Comments(comment)) = result else {
+            let Ok(ServerRes::CommentPage { comments, .. }) = result else {
                 return None;
             };
 
-            Some(comment)
+            Some(comments)
         }
     }
 
@@ -355,7 +430,7 @@ mod tests {
                 post.key.clone(),
                 None::<String>,
                 2,
-                TimeRange::None,
+                CursorRange::None,
                 Order::ThreeTwoOne,
             )
             .await
@@ -366,6 +441,50 @@ mod tests {
         let comment_first = comments.first().unwrap();
 
         assert_eq!(comment_first.text, "wowza2");
+
+        // comments sharing a nanosecond are told apart by key, so no page skips or repeats one
+        for text in ["same1", "same2"] {
+            app.add_post_comment(3, &auth_token, post.key.clone(), None, text.to_string())
+                .await
+                .unwrap();
+        }
+        let mut texts = Vec::new();
+        let mut cursor = CursorRange::None;
+        loop {
+            let result = app
+                .api
+                .get_post_comment(post.key.clone(), None, 1, cursor, Order::ThreeTwoOne, false)
+                .send_native_with_token(&auth_token)
+                .await;
+            let (comments, next) = match result {
+                Ok(ServerRes::CommentPage { comments, next, .. }) => (comments, next),
+                res => panic!("expected CommentPage, received {res:?}"),
+            };
+            texts.extend(comments.into_iter().map(|v| v.text));
+            let Some(next) = next else {
+                break;
+            };
+            cursor = CursorRange::Before(next);
+        }
+        texts.sort();
+        assert_eq!(texts, vec!["same1", "same2", "wowza", "wowza2"]);
+
+        let result = app
+            .api
+            .get_post_comment(
+                post.key.clone(),
+                None,
+                0,
+                CursorRange::None,
+                Order::ThreeTwoOne,
+                false,
+            )
+            .send_native_with_token(&auth_token)
+            .await;
+        assert!(matches!(
+            result,
+            Err(ServerErr::CursorErr(CursorErr::InvalidLimit))
+        ));
     }
 
     #[tokio::test]
@@ -408,7 +527,7 @@ mod tests {
                 post.key.clone(),
                 None::<String>,
                 2,
-                TimeRange::None,
+                CursorRange::None,
                 Order::ThreeTwoOne,
             )
             .await
@@ -425,7 +544,7 @@ mod tests {
                 post.key.clone(),
                 Some(comment_first.key.clone()),
                 2,
-                TimeRange::None,
+                CursorRange::None,
                 Order::ThreeTwoOne,
             )
             .await
@@ -1,13 +1,15 @@
 use axum::{Extension, extract::State};
 use thiserror::Error;
-use tracing::{debug, trace};
+use tracing::{debug, error, trace};
 
 use crate::{
     api::{
         AuthToken, PostLikeErr, Server404Err, ServerDesErr, ServerErr, ServerReq, ServerRes,
         app_state::AppState,
+        backend::webhook::dispatch_webhook_event,
+        shared::{live::LiveEvent, webhook::WebhookEvent},
     },
-    db::{DB404Err, DBPostLikeErr, DBUser, post_like::create_post_like_id},
+    db::{DB404Err, DBPostLikeErr, DBUser, post::create_post_id, post_like::create_post_like_id},
 };
 
 pub async fn add_post_like(
@@ -31,9 +33,31 @@ pub async fn add_post_like(
         .map_err(|err| match err {
             DBPostLikeErr::PostNotFound(_) => ResErr::PostNotFound(post_id.clone()).into(),
             DBPostLikeErr::PostWasAlreadyLiked => ResErr::PostAlreadyLiked(post_id.clone()).into(),
+            DBPostLikeErr::Blocked => ResErr::Blocked.into(),
             DBPostLikeErr::DB(_) => ServerErr::DbErr,
         })?;
 
+    if let Err(err) = app
+        .db
+        .add_notification_post_like(time, db_user.id.clone(), post_id.clone())
+        .await
+    {
+        error!("failed to add post like notification: {err}");
+    }
+    let data = serde_json::json!({
+        "post_key": post_id,
+        "username": db_user.username,
+    });
+    dispatch_webhook_event(
+        &app,
+        time,
+        WebhookEvent::LikeCreated,
+        Some(create_post_id(post_id.clone())),
+        data,
+    )
+    .await;
+    publish_post_like_count(&app, post_id).await;
+
     Ok(ServerRes::Ok)
 }
 
@@ -80,10 +104,22 @@ pub async fn delete_post_like(
         .delete_post_like(db_user.id.clone(), post_id.clone())
         .await
         .map_err(|err| ServerErr::DbErr)?;
+    publish_post_like_count(&app, post_id).await;
 
     Ok(ServerRes::Ok)
 }
 
+async fn publish_post_like_count(app: &AppState, post_key: String) {
+    match app.db.update_post_favorites(post_key.clone()).await {
+        Ok(count) => {
+            app.live.publish(LiveEvent::PostLikeCount { post_key, count });
+        }
+        Err(err) => {
+            error!("failed to update post favorites: {err}");
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use surrealdb::types::{RecordId, ToSql};
@@ -0,0 +1,281 @@
+use axum::{Extension, extract::State};
+use http::HeaderMap;
+
+use crate::{
+    api::{
+        ServerDesErr, ServerErr, ServerReq, ServerRes,
+        app_state::AppState,
+        shared::rating::{PostRating, RatingPreference},
+    },
+    db::DBUser,
+};
+
+pub async fn get_rating_preference(
+    State(app): State<AppState>,
+    db_user: Extension<Option<DBUser>>,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::None = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected None, received: {req:?}"
+        ))));
+    };
+
+    Ok(ServerRes::RatingPreference(RatingPreference::from_request(
+        db_user.0.as_ref(),
+        &headers,
+    )))
+}
+
+/// logged in users keep the preference on their account, everyone else gets a cookie
+pub async fn update_rating_preference(
+    State(app): State<AppState>,
+    db_user: Extension<Option<DBUser>>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::UpdateRatingPreference { max_rating, blur } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected UpdateRatingPreference, received: {req:?}"
+        ))));
+    };
+
+    let Some(db_user) = db_user.0 else {
+        return Ok(ServerRes::SetRatingCookie { max_rating });
+    };
+    let time = app.time().await;
+
+    let user = app
+        .db
+        .update_user_rating_preference(time, db_user.id, max_rating.into(), blur)
+        .await
+        .map_err(|_| ServerErr::DbErr)?;
+
+    Ok(ServerRes::RatingPreference(RatingPreference {
+        max_rating: PostRating::from_db(&user.max_rating),
+        blur: user.blur_rating,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::settings::Settings;
+    use crate::api::shared::cursor::CursorRange;
+    use crate::api::shared::rating::{PostRating, PostRatingErr, RatingPreference};
+    use crate::api::shared::visibility::PostVisibility;
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Api, Order, ServerErr, ServerRes, UserPost};
+
+    impl ApiTestApp {
+        pub async fn add_post_rated(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            title: impl Into<String>,
+            rating: PostRating,
+        ) -> Result<UserPost, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .add_post(title, "", "", rating, PostVisibility::Public, 0)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Post(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Post, got {res:?}"),
+            }
+        }
+
+        pub async fn update_post_rating(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            post_key: impl Into<String>,
+            rating: PostRating,
+        ) -> Result<UserPost, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .update_post_rating(post_key, rating)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Post(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Post, got {res:?}"),
+            }
+        }
+
+        pub async fn admin_update_post_rating(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            post_key: impl Into<String>,
+            rating: PostRating,
+        ) -> Result<UserPost, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .admin_update_post_rating(post_key, rating)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Post(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Post, got {res:?}"),
+            }
+        }
+
+        pub async fn update_rating_preference(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            max_rating: PostRating,
+            blur: bool,
+        ) -> Result<RatingPreference, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .update_rating_preference(max_rating, blur)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::RatingPreference(v) => Ok(v),
+                res => {
+                    panic!("fix code, invalid response, expected RatingPreference, got {res:?}")
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn api_rating_test() {
+        crate::init_test_log();
+
+        let mut settings = Settings::new_testing(1);
+        settings.auth.admin_emails = vec!["admin@heyadora.com".to_string()];
+        let mut app = ApiTestApp::new_with_settings(settings).await;
+
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let admin_token = app
+            .register(0, "admin", "admin@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        let post1 = app
+            .add_post_rated(1, &auth_token, "title1", PostRating::General)
+            .await
+            .unwrap();
+        let post2 = app
+            .add_post_rated(2, &auth_token, "title2", PostRating::Questionable)
+            .await
+            .unwrap();
+        app.add_post_rated(3, &auth_token, "title3", PostRating::Explicit)
+            .await
+            .unwrap();
+        assert_eq!(post2.rating, PostRating::Questionable);
+
+        let posts = app
+            .get_posts(
+                4,
+                &auth_token2,
+                10,
+                CursorRange::None,
+                Order::ThreeTwoOne,
+                "",
+                "",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            posts.iter().map(|v| v.title.as_str()).collect::<Vec<_>>(),
+            vec!["title1"]
+        );
+
+        let pref = app
+            .update_rating_preference(5, &auth_token2, PostRating::Questionable, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            pref,
+            RatingPreference {
+                max_rating: PostRating::Questionable,
+                blur: false
+            }
+        );
+        let posts = app
+            .get_posts(
+                5,
+                &auth_token2,
+                10,
+                CursorRange::None,
+                Order::ThreeTwoOne,
+                "",
+                "",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            posts.iter().map(|v| v.title.as_str()).collect::<Vec<_>>(),
+            vec!["title2", "title1"]
+        );
+
+        let result = app.api.get_rating_preference().send_native().await;
+        assert_eq!(
+            result,
+            Ok(ServerRes::RatingPreference(RatingPreference::default()))
+        );
+        let result = app
+            .api
+            .update_rating_preference(PostRating::Explicit, false)
+            .send_native()
+            .await;
+        assert_eq!(result, Ok(ServerRes::Ok));
+        app.api.auth_token_overwrite = "rating=explicit".to_string();
+        let result = app.api.get_rating_preference().send_native().await;
+        assert_eq!(
+            result,
+            Ok(ServerRes::RatingPreference(RatingPreference {
+                max_rating: PostRating::Explicit,
+                blur: true
+            }))
+        );
+        let result = app
+            .api
+            .get_posts(10, CursorRange::None, Order::ThreeTwoOne, "", "")
+            .send_native()
+            .await;
+        assert!(matches!(result, Ok(ServerRes::PostPage { posts, .. }) if posts.len() == 3));
+        app.api.auth_token_overwrite = String::new();
+
+        let result = app
+            .update_post_rating(6, &auth_token2, post1.key.clone(), PostRating::Explicit)
+            .await;
+        assert_eq!(result, Err(PostRatingErr::NotFound.into()));
+        let post = app
+            .update_post_rating(6, &auth_token, post1.key.clone(), PostRating::Explicit)
+            .await
+            .unwrap();
+        assert_eq!(post.rating, PostRating::Explicit);
+
+        let result = app
+            .admin_update_post_rating(7, &auth_token, post1.key.clone(), PostRating::General)
+            .await;
+        assert!(result.is_err());
+        let post = app
+            .admin_update_post_rating(7, &admin_token, post1.key.clone(), PostRating::Questionable)
+            .await
+            .unwrap();
+        assert_eq!(post.rating, PostRating::Questionable);
+        assert!(post.rating_locked);
+        let result = app
+            .update_post_rating(8, &auth_token, post1.key.clone(), PostRating::General)
+            .await;
+        assert_eq!(result, Err(PostRatingErr::Locked.into()));
+    }
+}
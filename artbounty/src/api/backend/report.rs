@@ -0,0 +1,583 @@
+use axum::{Extension, extract::State};
+use http::HeaderMap;
+use surrealdb::types::ToSql;
+use tracing::trace;
+
+use crate::{
+    api::{
+        AuthToken, ServerDesErr, ServerErr, ServerReq, ServerRes,
+        app_state::AppState,
+        backend::{
+            ClientIp,
+            admin::{require, require_other},
+            audit_meta,
+        },
+        shared::{
+            admin::{AdminErr, Permission, UserRole},
+            live::LiveEvent,
+            post_comment::UserPostComment,
+            report::{
+                REPORT_SUSPEND_MAX_DAYS, REPORT_TEXT_MAX_LEN, ReportAction, ReportErr,
+                ReportTarget, UserReport,
+            },
+        },
+    },
+    db::{DB404Err, DBReportErr, DBUser, report::DBReportTarget},
+};
+
+pub const REPORT_DAY_NS: u128 = 24 * 60 * 60 * 1_000_000_000;
+pub const REPORTS_FETCH_MAX: usize = 100;
+
+fn report_404_err(err: DB404Err) -> ServerErr {
+    match err {
+        DB404Err::NotFound => ReportErr::TargetNotFound.into(),
+        DB404Err::DB(_) => ServerErr::DbErr,
+    }
+}
+
+fn report_err(err: DBReportErr) -> ServerErr {
+    match err {
+        DBReportErr::NotFound => ReportErr::NotFound.into(),
+        DBReportErr::AlreadyReported => ReportErr::AlreadyReported.into(),
+        DBReportErr::AlreadyResolved => ReportErr::AlreadyResolved.into(),
+        DBReportErr::DB(_) => ServerErr::DbErr,
+    }
+}
+
+pub async fn add_report(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::AddReport {
+        target,
+        reason,
+        text,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AddReport, received: {req:?}"
+        ))));
+    };
+    let text = text.trim().to_string();
+    if text.chars().count() > REPORT_TEXT_MAX_LEN {
+        return Err(ReportErr::TextTooLong(REPORT_TEXT_MAX_LEN).into());
+    }
+    let time = app.time().await;
+
+    let target = match target {
+        ReportTarget::Post { post_key } => {
+            let post = app.db.get_post(post_key).await.map_err(report_404_err)?;
+            DBReportTarget {
+                kind: "post".to_string(),
+                id: post.id.clone(),
+                user: post.user.id,
+                post: Some(post.id),
+            }
+        }
+        ReportTarget::Comment { comment_key } => {
+            let comment = app
+                .db
+                .get_post_comment(comment_key)
+                .await
+                .map_err(report_404_err)?;
+            DBReportTarget {
+                kind: "comment".to_string(),
+                id: comment.id,
+                user: comment.user.id,
+                post: Some(comment.post),
+            }
+        }
+        ReportTarget::User { username } => {
+            let user = app
+                .db
+                .get_user_by_username(username)
+                .await
+                .map_err(report_404_err)?;
+            DBReportTarget {
+                kind: "user".to_string(),
+                id: user.id.clone(),
+                user: user.id,
+                post: None,
+            }
+        }
+    };
+    if target.user == db_user.id {
+        return Err(ReportErr::SelfReport.into());
+    }
+
+    app.db
+        .add_report(
+            time,
+            db_user.id.clone(),
+            target,
+            reason.to_string(),
+            reason.severity(),
+            text,
+        )
+        .await
+        .map_err(report_err)?;
+
+    Ok(ServerRes::Ok)
+}
+
+pub async fn get_reports(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::AdminGetReports { limit, offset } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AdminGetReports, received: {req:?}"
+        ))));
+    };
+    require(&db_user, Permission::ResolveReport)?;
+
+    let reports = app
+        .db
+        .get_reports_open(limit.min(REPORTS_FETCH_MAX), offset)
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserReport::from)
+        .collect::<Vec<UserReport>>();
+
+    Ok(ServerRes::Reports(reports))
+}
+
+pub async fn resolve_report(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::AdminResolveReport {
+        report_key,
+        action,
+        reason,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AdminResolveReport, received: {req:?}"
+        ))));
+    };
+    require(&db_user, Permission::ResolveReport)?;
+    let reason = reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(ReportErr::MissingReason.into());
+    }
+    let time = app.time().await;
+    let audit = audit_meta(Some(db_user.id.clone()), &client_ip, &headers);
+
+    let report = app
+        .db
+        .get_report(report_key.clone())
+        .await
+        .map_err(|err| match err {
+            DB404Err::NotFound => ReportErr::NotFound.into(),
+            DB404Err::DB(_) => ServerErr::DbErr,
+        })?;
+    if report.resolved {
+        return Err(ReportErr::AlreadyResolved.into());
+    }
+    let target_user = report.target_user.clone();
+    let report = UserReport::from(report);
+
+    match &action {
+        ReportAction::Dismiss => {}
+        ReportAction::HideContent => match &report.target {
+            ReportTarget::Post { post_key } => {
+                app.db
+                    .update_post_show(time, post_key.clone(), false, audit.clone())
+                    .await
+                    .map_err(report_404_err)?;
+            }
+            ReportTarget::Comment { comment_key } => {
+                let comment = app
+                    .db
+                    .get_post_comment(comment_key.clone())
+                    .await
+                    .map(UserPostComment::from)
+                    .map_err(report_404_err)?;
+                app.db
+                    .delete_post_comment_moderation(time, comment_key.clone(), audit.clone())
+                    .await
+                    .map_err(report_404_err)?;
+                app.live.publish(LiveEvent::CommentDeleted {
+                    post_key: comment.post_key,
+                    comment_key: comment.key,
+                    parent_key: comment.parent_key,
+                });
+            }
+            ReportTarget::User { .. } => {
+                return Err(ReportErr::NothingToHide.into());
+            }
+        },
+        ReportAction::WarnUser => {
+            app.send_email_moderation_warning(time, target_user.email, &reason)
+                .await?;
+        }
+        ReportAction::SuspendUser { days } => {
+            require(&db_user, Permission::SuspendUser)?;
+            if *days == 0 || *days > REPORT_SUSPEND_MAX_DAYS {
+                return Err(ReportErr::InvalidSuspension(REPORT_SUSPEND_MAX_DAYS).into());
+            }
+            require_other(&db_user, &target_user.id.key.to_sql())?;
+            if UserRole::from_db(&target_user.role).is_staff() {
+                return Err(AdminErr::Forbidden.into());
+            }
+            app.db
+                .update_user_suspended(
+                    time,
+                    target_user.id,
+                    time + (*days as u128) * REPORT_DAY_NS,
+                    audit.clone(),
+                )
+                .await
+                .map_err(report_404_err)?;
+        }
+    }
+
+    let resolved = app
+        .db
+        .resolve_report(
+            time,
+            report_key,
+            db_user.id.clone(),
+            action.to_string(),
+            reason,
+            audit,
+        )
+        .await
+        .map_err(report_err)?;
+
+    for report in resolved {
+        trace!("notifying reporter {}", report.reporter.username);
+        app.send_email_report_resolved(time, report.reporter.email, &action)
+            .await?;
+    }
+
+    Ok(ServerRes::Ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::backend::report::REPORT_DAY_NS;
+    use crate::api::settings::Settings;
+    use crate::api::shared::admin::{AdminErr, UserRole};
+    use crate::api::shared::report::{
+        ReportAction, ReportErr, ReportReason, ReportTarget, UserReport,
+    };
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Api, ServerAuthErr, ServerErr, ServerRes};
+
+    impl ApiTestApp {
+        pub async fn add_report(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            target: ReportTarget,
+            reason: ReportReason,
+        ) -> Result<(), ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .add_report(target, reason, "report text")
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Ok => Ok(()),
+                res => panic!("fix code, invalid response, expected Ok, got {res:?}"),
+            }
+        }
+
+        pub async fn admin_get_reports(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+        ) -> Result<Vec<UserReport>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .admin_get_reports(100, 0)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Reports(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Reports, got {res:?}"),
+            }
+        }
+
+        pub async fn admin_resolve_report(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            report_key: impl Into<String>,
+            action: ReportAction,
+            reason: impl Into<String>,
+        ) -> Result<(), ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .admin_resolve_report(report_key, action, reason)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Ok => Ok(()),
+                res => panic!("fix code, invalid response, expected Ok, got {res:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn api_report_test() {
+        crate::init_test_log();
+
+        let mut settings = Settings::new_testing(1);
+        settings.auth.admin_emails = vec!["admin@heyadora.com".to_string()];
+        let app = ApiTestApp::new_with_settings(settings).await;
+
+        let admin_token = app
+            .register(0, "admin", "admin@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let mod_token = app
+            .register(1, "moddy", "moddy@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token = app
+            .register(2, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(3, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let users = app.admin_get_users(4, &admin_token, "moddy").await.unwrap();
+        app.admin_update_user_role(4, &admin_token, users[0].key.clone(), UserRole::Moderator)
+            .await
+            .unwrap();
+
+        let post = app
+            .add_post(5, &auth_token, "title1", "", "one")
+            .await
+            .unwrap();
+        let comment = app
+            .add_post_comment(6, &auth_token2, post.key.clone(), None, "mean")
+            .await
+            .unwrap();
+
+        let result = app
+            .add_report(
+                7,
+                &auth_token,
+                ReportTarget::Post {
+                    post_key: post.key.clone(),
+                },
+                ReportReason::Spam,
+            )
+            .await;
+        assert_eq!(result, Err(ReportErr::SelfReport.into()));
+        let result = app
+            .add_report(
+                7,
+                &auth_token2,
+                ReportTarget::Post {
+                    post_key: "404".to_string(),
+                },
+                ReportReason::Spam,
+            )
+            .await;
+        assert_eq!(result, Err(ReportErr::TargetNotFound.into()));
+        app.add_report(
+            7,
+            &auth_token2,
+            ReportTarget::Post {
+                post_key: post.key.clone(),
+            },
+            ReportReason::StolenArt,
+        )
+        .await
+        .unwrap();
+        let result = app
+            .add_report(
+                7,
+                &auth_token2,
+                ReportTarget::Post {
+                    post_key: post.key.clone(),
+                },
+                ReportReason::StolenArt,
+            )
+            .await;
+        assert_eq!(result, Err(ReportErr::AlreadyReported.into()));
+        app.add_report(
+            8,
+            &mod_token,
+            ReportTarget::Comment {
+                comment_key: comment.key.clone(),
+            },
+            ReportReason::Harassment,
+        )
+        .await
+        .unwrap();
+        app.add_report(
+            9,
+            &auth_token,
+            ReportTarget::User {
+                username: "hey2".to_string(),
+            },
+            ReportReason::Illegal,
+        )
+        .await
+        .unwrap();
+
+        let result = app.admin_get_reports(10, &auth_token).await;
+        assert_eq!(result, Err(AdminErr::Forbidden.into()));
+        let reports = app.admin_get_reports(10, &mod_token).await.unwrap();
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[0].reason, ReportReason::Illegal);
+        assert_eq!(reports[0].target_user.username, "hey2");
+        assert_eq!(
+            reports[1].target,
+            ReportTarget::Post {
+                post_key: post.key.clone()
+            }
+        );
+        assert_eq!(
+            reports[2].target,
+            ReportTarget::Comment {
+                comment_key: comment.key.clone()
+            }
+        );
+        assert_eq!(reports[2].post_key, Some(post.key.clone()));
+        assert_eq!(reports[2].post_username, Some("hey".to_string()));
+        let user_report_key = reports[0].key.clone();
+        let post_report_key = reports[1].key.clone();
+        let comment_report_key = reports[2].key.clone();
+
+        let result = app
+            .admin_resolve_report(11, &mod_token, &post_report_key, ReportAction::Dismiss, " ")
+            .await;
+        assert_eq!(result, Err(ReportErr::MissingReason.into()));
+        let result = app
+            .admin_resolve_report(
+                11,
+                &mod_token,
+                &user_report_key,
+                ReportAction::HideContent,
+                "nothing",
+            )
+            .await;
+        assert_eq!(result, Err(ReportErr::NothingToHide.into()));
+
+        app.admin_resolve_report(
+            11,
+            &mod_token,
+            &post_report_key,
+            ReportAction::HideContent,
+            "stolen",
+        )
+        .await
+        .unwrap();
+        let result = app.api.get_post(post.key.clone()).send_native().await;
+        assert!(matches!(result, Err(ServerErr::NotFoundErr(_))));
+        let email = app
+            .state
+            .db
+            .get_sent_email_by_email_latest("hey2@heyadora.com")
+            .await
+            .unwrap();
+        assert_eq!(email.reason, "report_resolved");
+        let result = app
+            .admin_resolve_report(
+                11,
+                &mod_token,
+                &post_report_key,
+                ReportAction::Dismiss,
+                "again",
+            )
+            .await;
+        assert_eq!(result, Err(ReportErr::AlreadyResolved.into()));
+
+        app.admin_resolve_report(
+            12,
+            &mod_token,
+            &comment_report_key,
+            ReportAction::WarnUser,
+            "be nice",
+        )
+        .await
+        .unwrap();
+        let email = app
+            .state
+            .db
+            .get_sent_email_by_email_latest("hey2@heyadora.com")
+            .await
+            .unwrap();
+        assert_eq!(email.reason, "moderation_warning");
+        assert!(email.body.contains("be nice"));
+        let email = app
+            .state
+            .db
+            .get_sent_email_by_email_latest("moddy@heyadora.com")
+            .await
+            .unwrap();
+        assert_eq!(email.reason, "report_resolved");
+
+        let result = app
+            .admin_resolve_report(
+                13,
+                &mod_token,
+                &user_report_key,
+                ReportAction::SuspendUser { days: 0 },
+                "illegal",
+            )
+            .await;
+        assert_eq!(result, Err(ReportErr::InvalidSuspension(365).into()));
+        app.admin_resolve_report(
+            13,
+            &mod_token,
+            &user_report_key,
+            ReportAction::SuspendUser { days: 3 },
+            "illegal",
+        )
+        .await
+        .unwrap();
+        let result = app
+            .api
+            .get_my_acc()
+            .send_native_with_token(&auth_token2)
+            .await;
+        assert!(matches!(
+            result,
+            Err(ServerErr::AuthErr(
+                ServerAuthErr::ServerUnauthorizedInvalidCookie
+            ))
+        ));
+        let result = app
+            .api
+            .login("hey2@heyadora.com", "pas$word123456789")
+            .send_native()
+            .await;
+        assert_eq!(
+            result,
+            Err(ServerAuthErr::ServerSuspended(13 + 3 * REPORT_DAY_NS).into())
+        );
+
+        let reports = app.admin_get_reports(14, &mod_token).await.unwrap();
+        assert!(reports.is_empty());
+        let hey2 = app.state.db.get_user_by_username("hey2").await.unwrap();
+        let actions = app.state.db.get_moderation_actions(hey2.id).await.unwrap();
+        assert_eq!(
+            actions
+                .iter()
+                .map(|v| v.action.as_str())
+                .collect::<Vec<_>>(),
+            vec!["suspend_user", "warn_user"]
+        );
+    }
+}
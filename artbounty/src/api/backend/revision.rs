@@ -0,0 +1,178 @@
+use axum::{Extension, extract::State};
+
+use crate::{
+    api::{
+        ServerDesErr, ServerErr, ServerReq, ServerRes,
+        app_state::AppState,
+        shared::{
+            admin::{Permission, UserRole},
+            revision::{REVISIONS_FETCH_MAX, RevisionErr, RevisionTarget, UserRevision},
+        },
+    },
+    db::{DB404Err, DBUser},
+};
+
+fn revision_404_err(err: DB404Err) -> ServerErr {
+    match err {
+        DB404Err::NotFound => RevisionErr::NotFound.into(),
+        DB404Err::DB(_) => ServerErr::DbErr,
+    }
+}
+
+/// the author can always see their own edits, otherwise it takes the same permission as moderating the target
+pub async fn get_revisions(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::GetRevisions { target, limit } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected GetRevisions, received: {req:?}"
+        ))));
+    };
+
+    let (target_id, owner_id, permission) = match target {
+        RevisionTarget::Post { post_key } => {
+            let post = app.db.get_post(post_key).await.map_err(revision_404_err)?;
+            (post.id, post.user.id, Permission::HidePost)
+        }
+        RevisionTarget::Comment { comment_key } => {
+            let comment = app
+                .db
+                .get_post_comment(comment_key)
+                .await
+                .map_err(revision_404_err)?;
+            (comment.id, comment.user.id, Permission::DeleteComment)
+        }
+    };
+    if owner_id != db_user.id && !UserRole::from_db(&db_user.role).can(permission) {
+        return Err(RevisionErr::Forbidden.into());
+    }
+
+    let revisions = app
+        .db
+        .get_revisions(target_id, limit.min(REVISIONS_FETCH_MAX))
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserRevision::from)
+        .collect::<Vec<UserRevision>>();
+
+    Ok(ServerRes::Revisions(revisions))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::settings::Settings;
+    use crate::api::shared::revision::{RevisionErr, RevisionField, RevisionTarget, UserRevision};
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Api, ServerErr, ServerRes};
+
+    impl ApiTestApp {
+        pub async fn get_revisions(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            target: RevisionTarget,
+        ) -> Result<Vec<UserRevision>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_revisions(target, 10)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Revisions(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Revisions, got {res:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn api_revision_test() {
+        crate::init_test_log();
+
+        let mut settings = Settings::new_testing(1);
+        settings.auth.admin_emails = vec!["admin@heyadora.com".to_string()];
+        let app = ApiTestApp::new_with_settings(settings).await;
+
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let admin_token = app
+            .register(0, "admin", "admin@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        let post = app
+            .add_post(1, &auth_token, "title1", "cat", "one")
+            .await
+            .unwrap();
+        assert_eq!(post.edited_at, 0);
+        let post = app
+            .update_post_title(2, &auth_token, post.key.clone(), "title2")
+            .await
+            .unwrap();
+        assert_eq!(post.edited_at, 2);
+
+        let target = RevisionTarget::Post {
+            post_key: post.key.clone(),
+        };
+        let revisions = app
+            .get_revisions(3, &auth_token, target.clone())
+            .await
+            .unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].field, RevisionField::Title);
+        assert_eq!(revisions[0].before, "title1");
+        assert_eq!(revisions[0].after, "title2");
+        assert_eq!(revisions[0].user.username, "hey");
+
+        let result = app.get_revisions(3, &auth_token2, target.clone()).await;
+        assert_eq!(result, Err(RevisionErr::Forbidden.into()));
+        let revisions = app
+            .get_revisions(3, &admin_token, target.clone())
+            .await
+            .unwrap();
+        assert_eq!(revisions.len(), 1);
+
+        let comment = app
+            .add_post_comment(4, &auth_token2, post.key.clone(), None, "nice")
+            .await
+            .unwrap();
+        let comment = app
+            .update_post_comment(5, &auth_token2, comment.key.clone(), "ugly")
+            .await
+            .unwrap();
+        assert_eq!(comment.edited_at, 5);
+
+        let target = RevisionTarget::Comment {
+            comment_key: comment.key.clone(),
+        };
+        let result = app.get_revisions(6, &auth_token, target.clone()).await;
+        assert_eq!(result, Err(RevisionErr::Forbidden.into()));
+        let revisions = app
+            .get_revisions(6, &admin_token, target.clone())
+            .await
+            .unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].field, RevisionField::Text);
+        assert_eq!(revisions[0].before, "nice");
+
+        let result = app
+            .get_revisions(
+                6,
+                &admin_token,
+                RevisionTarget::Comment {
+                    comment_key: "404".to_string(),
+                },
+            )
+            .await;
+        assert_eq!(result, Err(RevisionErr::NotFound.into()));
+    }
+}
@@ -0,0 +1,437 @@
+//! sitemaps and robots.txt for crawlers. the index splits public posts and profiles into pages
+//! of SITEMAP_PAGE_LIMIT urls and each page streams from the db in created_at batches, every batch
+//! continuing after the last row of the one before.
+
+use std::str::FromStr;
+
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use http::{HeaderValue, StatusCode};
+use surrealdb::types::ToSql;
+use tracing::trace;
+
+use crate::api::app_state::AppState;
+use crate::api::backend::date::fmt_rfc3339;
+use crate::api::backend::feed::{FeedErr, escape_xml};
+use crate::api::settings::Robots;
+use crate::api::shared::cursor::{Cursor, CursorRange};
+use crate::api::{Server404Err, ServerErr};
+use crate::db::DB404Err;
+use crate::path::{
+    PATH_ADMIN, PATH_API, PATH_MESSAGES, PATH_NOTIFICATIONS, PATH_SETTINGS, PATH_SITEMAP,
+    link_post, link_sitemap_page, link_user,
+};
+
+/// the protocol allows 50k, smaller pages keep a single response short
+pub const SITEMAP_PAGE_LIMIT: usize = 10_000;
+pub const SITEMAP_BATCH_LIMIT: usize = 500;
+/// seconds
+pub const SITEMAP_CACHE_AGE: u64 = 60 * 60;
+const SITEMAP_XMLNS: &str = "http://www.sitemaps.org/schemas/sitemap/0.9";
+
+#[derive(Debug, Clone, Copy, PartialEq, strum::EnumString, strum::Display, strum::EnumIter)]
+#[strum(serialize_all = "lowercase")]
+pub enum SitemapKind {
+    Posts,
+    Users,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapUrl {
+    pub loc: String,
+    pub lastmod: u128,
+}
+
+/// what's left of one sitemap page, moved through the stream between batches
+struct SitemapPage {
+    app: AppState,
+    address: String,
+    kind: SitemapKind,
+    time: u128,
+    /// each batch continues after the last row of the one before
+    cursor: CursorRange,
+    remaining: usize,
+}
+
+enum SitemapChunk {
+    Head,
+    Urls,
+    Tail,
+}
+
+async fn sitemap_count(
+    app: &AppState,
+    kind: SitemapKind,
+    time: u128,
+) -> Result<usize, surrealdb::Error> {
+    let count = match kind {
+        SitemapKind::Posts => app.db.get_sitemap_post_count(time).await?,
+        SitemapKind::Users => app.db.get_sitemap_user_count().await?,
+    };
+    Ok(count as usize)
+}
+
+/// cursor right before the first row of the page, only the page start is looked up by position
+async fn sitemap_page_cursor(
+    app: &AppState,
+    kind: SitemapKind,
+    time: u128,
+    start: usize,
+) -> Result<CursorRange, DB404Err> {
+    if start == 0 {
+        return Ok(CursorRange::None);
+    }
+    let cursor = match kind {
+        SitemapKind::Posts => {
+            let post = app.db.get_sitemap_post_at(time, start - 1).await?;
+            Cursor::new(post.created_at, post.id.key.to_sql())
+        }
+        SitemapKind::Users => {
+            let user = app.db.get_sitemap_user_at(start - 1).await?;
+            Cursor::new(user.created_at, user.id.key.to_sql())
+        }
+    };
+    Ok(CursorRange::After(cursor))
+}
+
+/// urls of the next batch and the cursor of its last row
+async fn sitemap_urls(
+    page: &SitemapPage,
+    limit: usize,
+) -> Result<(Vec<SitemapUrl>, Option<Cursor>), surrealdb::Error> {
+    let address = &page.address;
+    let cursor = page.cursor.clone();
+    let (urls, last) = match page.kind {
+        SitemapKind::Posts => {
+            let posts = page
+                .app
+                .db
+                .get_sitemap_posts(page.time, cursor, limit)
+                .await?;
+            let last = posts
+                .last()
+                .map(|post| Cursor::new(post.created_at, post.id.key.to_sql()));
+            let urls = posts
+                .into_iter()
+                .map(|post| SitemapUrl {
+                    loc: format!(
+                        "{address}{}",
+                        link_post(&post.username, post.id.key.to_sql())
+                    ),
+                    lastmod: post.modified_at,
+                })
+                .collect();
+            (urls, last)
+        }
+        SitemapKind::Users => {
+            let users = page.app.db.get_sitemap_users(cursor, limit).await?;
+            let last = users
+                .last()
+                .map(|user| Cursor::new(user.created_at, user.id.key.to_sql()));
+            let urls = users
+                .into_iter()
+                .map(|user| SitemapUrl {
+                    loc: format!("{address}{}", link_user(&user.username)),
+                    lastmod: user.modified_at,
+                })
+                .collect();
+            (urls, last)
+        }
+    };
+    Ok((urls, last))
+}
+
+fn pages_for(count: usize) -> usize {
+    count.div_ceil(SITEMAP_PAGE_LIMIT)
+}
+
+fn cache_headers(content_type: &'static str) -> [(http::HeaderName, HeaderValue); 2] {
+    [
+        (CONTENT_TYPE, HeaderValue::from_static(content_type)),
+        (
+            CACHE_CONTROL,
+            HeaderValue::from_str(&format!("public, max-age={SITEMAP_CACHE_AGE}"))
+                .expect("valid header value"),
+        ),
+    ]
+}
+
+pub async fn get_sitemap_index(State(app): State<AppState>) -> Result<impl IntoResponse, FeedErr> {
+    use strum::IntoEnumIterator;
+
+    let time = app.time().await;
+    let address = app.get_address().await;
+    let mut pages = Vec::new();
+    for kind in SitemapKind::iter() {
+        let count = sitemap_count(&app, kind, time)
+            .await
+            .map_err(|_| ServerErr::DbErr)?;
+        pages.extend(
+            (0..pages_for(count)).map(|page| format!("{address}{}", link_sitemap_page(kind, page))),
+        );
+    }
+
+    Ok((
+        cache_headers("application/xml; charset=utf-8"),
+        render_sitemap_index(&pages),
+    ))
+}
+
+pub async fn get_sitemap_page(
+    State(app): State<AppState>,
+    Path((kind, page)): Path<(String, usize)>,
+) -> Result<impl IntoResponse, FeedErr> {
+    let kind = SitemapKind::from_str(&kind).map_err(|_| ServerErr::from(Server404Err::NotFound))?;
+    let time = app.time().await;
+    let count = sitemap_count(&app, kind, time)
+        .await
+        .map_err(|_| ServerErr::DbErr)?;
+    if page >= pages_for(count) {
+        return Err(ServerErr::from(Server404Err::NotFound).into());
+    }
+    let start = page * SITEMAP_PAGE_LIMIT;
+    let cursor = sitemap_page_cursor(&app, kind, time, start)
+        .await
+        .map_err(|err| match err {
+            DB404Err::NotFound => ServerErr::from(Server404Err::NotFound),
+            DB404Err::DB(_) => ServerErr::DbErr,
+        })?;
+    let page = SitemapPage {
+        address: app.get_address().await,
+        app,
+        kind,
+        time,
+        cursor,
+        remaining: SITEMAP_PAGE_LIMIT.min(count - start),
+    };
+
+    // one db batch per chunk, the page is never held in memory as a whole
+    let stream = futures::stream::try_unfold(
+        (page, Some(SitemapChunk::Head)),
+        |(mut page, chunk)| async move {
+            let Some(chunk) = chunk else {
+                return Ok::<_, surrealdb::Error>(None);
+            };
+            match chunk {
+                SitemapChunk::Head => Ok(Some((
+                    format!(
+                        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"{SITEMAP_XMLNS}\">\n"
+                    ),
+                    (page, Some(SitemapChunk::Urls)),
+                ))),
+                SitemapChunk::Urls => {
+                    let limit = SITEMAP_BATCH_LIMIT.min(page.remaining);
+                    let (urls, last) = sitemap_urls(&page, limit).await.inspect_err(|err| {
+                        tracing::error!("sitemap batch after {:?} failed: {err}", page.cursor)
+                    })?;
+                    trace!(
+                        "sitemap batch after {:?} with {} urls",
+                        page.cursor,
+                        urls.len()
+                    );
+                    page.remaining -= urls.len();
+                    let next = match last {
+                        Some(last) if urls.len() == limit && page.remaining > 0 => {
+                            page.cursor = CursorRange::After(last);
+                            SitemapChunk::Urls
+                        }
+                        _ => SitemapChunk::Tail,
+                    };
+                    Ok(Some((render_sitemap_urls(&urls), (page, Some(next)))))
+                }
+                SitemapChunk::Tail => Ok(Some(("</urlset>\n".to_string(), (page, None)))),
+            }
+        },
+    );
+
+    Ok((
+        StatusCode::OK,
+        cache_headers("application/xml; charset=utf-8"),
+        Body::from_stream(stream),
+    ))
+}
+
+pub async fn get_robots(State(app): State<AppState>) -> impl IntoResponse {
+    let address = app.get_address().await;
+    (
+        cache_headers("text/plain; charset=utf-8"),
+        render_robots(&address, &app.settings.robots),
+    )
+}
+
+pub fn render_sitemap_index(pages: &[String]) -> String {
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<sitemapindex xmlns=\"{SITEMAP_XMLNS}\">\n"
+    );
+    for page in pages {
+        out += &format!("<sitemap><loc>{}</loc></sitemap>\n", escape_xml(page));
+    }
+    out += "</sitemapindex>\n";
+    out
+}
+
+pub fn render_sitemap_urls(urls: &[SitemapUrl]) -> String {
+    let mut out = String::new();
+    for url in urls {
+        out += &format!(
+            "<url><loc>{}</loc><lastmod>{}</lastmod></url>\n",
+            escape_xml(&url.loc),
+            fmt_rfc3339(url.lastmod)
+        );
+    }
+    out
+}
+
+/// private pages and machine endpoints are always off limits, the rest comes from the settings
+pub fn render_robots(address: &str, robots: &Robots) -> String {
+    let mut out = String::from("User-agent: *\n");
+    if robots.disallow_all {
+        out += "Disallow: /\n";
+    } else {
+        let api = format!("{PATH_API}/");
+        let defaults = [
+            api.as_str(),
+            "/ap/",
+            "/embed/",
+            PATH_SETTINGS,
+            PATH_MESSAGES,
+            PATH_NOTIFICATIONS,
+            PATH_ADMIN,
+        ];
+        for path in defaults
+            .into_iter()
+            .chain(robots.disallow.iter().map(String::as_str))
+        {
+            out += &format!("Disallow: {path}\n");
+        }
+    }
+    if !robots.extra.trim().is_empty() {
+        out += &format!("\n{}\n", robots.extra.trim());
+    }
+    if !robots.disallow_all {
+        out += &format!("\nSitemap: {address}{PATH_SITEMAP}\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use http::header::CONTENT_TYPE;
+
+    use crate::api::settings::{Robots, Settings};
+    use crate::api::tests::ApiTestApp;
+    use crate::path::{PATH_ROBOTS, PATH_SITEMAP, link_sitemap_page};
+
+    use super::{SitemapKind, SitemapUrl, render_robots, render_sitemap_urls};
+
+    #[test]
+    fn sitemap_render() {
+        let urls = render_sitemap_urls(&[SitemapUrl {
+            loc: "http://localhost/u/a&b".to_string(),
+            lastmod: 0,
+        }]);
+        assert_eq!(
+            urls,
+            "<url><loc>http://localhost/u/a&amp;b</loc><lastmod>1970-01-01T00:00:00Z</lastmod></url>\n"
+        );
+
+        let robots = render_robots(
+            "http://localhost",
+            &Robots {
+                disallow_all: false,
+                disallow: vec!["/c/".to_string()],
+                extra: "User-agent: GPTBot\nDisallow: /".to_string(),
+            },
+        );
+        assert!(robots.starts_with("User-agent: *\nDisallow: /api/\n"));
+        assert!(robots.contains("Disallow: /c/\n"));
+        assert!(robots.contains("User-agent: GPTBot\nDisallow: /\n"));
+        assert!(robots.ends_with("Sitemap: http://localhost/sitemap.xml\n"));
+
+        let robots = render_robots(
+            "http://localhost",
+            &Robots {
+                disallow_all: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(robots, "User-agent: *\nDisallow: /\n");
+    }
+
+    #[tokio::test]
+    async fn api_sitemap_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let post1 = app
+            .add_post(1, &auth_token, "title1", "", "")
+            .await
+            .unwrap();
+        let post2 = app
+            .add_post(2, &auth_token, "title2", "", "")
+            .await
+            .unwrap();
+
+        let get = async |path: String| {
+            let url = app.api.server.server_url(&path).unwrap();
+            let res = reqwest::Client::new().get(url).send().await.unwrap();
+            let status = res.status();
+            let headers = res.headers().clone();
+            (status, headers, res.text().await.unwrap())
+        };
+
+        let (status, headers, body) = get(PATH_SITEMAP.to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            headers[CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("application/xml")
+        );
+        assert!(body.contains("<sitemapindex"));
+        assert!(body.contains(&link_sitemap_page(SitemapKind::Posts, 0)));
+        assert!(body.contains(&link_sitemap_page(SitemapKind::Users, 0)));
+        assert!(!body.contains(&link_sitemap_page(SitemapKind::Posts, 1)));
+
+        let (status, _, body) = get(link_sitemap_page(SitemapKind::Posts, 0)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with("<?xml"));
+        assert!(body.ends_with("</urlset>\n"));
+        let first = body.find(&format!("/u/hey/{}", post1.key)).unwrap();
+        let second = body.find(&format!("/u/hey/{}", post2.key)).unwrap();
+        assert!(first < second);
+
+        let (status, _, body) = get(link_sitemap_page(SitemapKind::Users, 0)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<loc>http://localhost:3000/u/hey</loc>"));
+
+        let (status, _, _) = get(link_sitemap_page(SitemapKind::Posts, 1)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _, _) = get("/sitemap/comments/0".to_string()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, headers, body) = get(PATH_ROBOTS.to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            headers[CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/plain")
+        );
+        assert!(body.contains("Sitemap: http://localhost:3000/sitemap.xml"));
+
+        let mut settings = Settings::new_testing(1);
+        settings.robots.disallow_all = true;
+        let app = ApiTestApp::new_with_settings(settings).await;
+        let url = app.api.server.server_url(PATH_ROBOTS).unwrap();
+        let body = reqwest::get(url).await.unwrap().text().await.unwrap();
+        assert_eq!(body, "User-agent: *\nDisallow: /\n");
+    }
+}
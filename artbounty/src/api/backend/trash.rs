@@ -0,0 +1,280 @@
+use axum::{Extension, extract::State};
+
+use crate::{
+    api::{
+        ServerDesErr, ServerErr, ServerReq, ServerRes, UserPost,
+        app_state::AppState,
+        shared::{
+            live::LiveEvent,
+            post_comment::UserPostComment,
+            trash::{TrashErr, UserTrash},
+        },
+    },
+    db::{DB404Err, DBUser},
+};
+
+fn trash_404_err(err: DB404Err) -> ServerErr {
+    match err {
+        DB404Err::NotFound => TrashErr::NotFound.into(),
+        DB404Err::DB(_) => ServerErr::DbErr,
+    }
+}
+
+pub async fn get_trash(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::None = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected None, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    let posts = app
+        .db
+        .get_trash_posts(time, db_user.id.clone())
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserPost::from)
+        .collect::<Vec<UserPost>>();
+    let comments = app
+        .db
+        .get_trash_post_comments(time, db_user.id.clone())
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserPostComment::from)
+        .collect::<Vec<UserPostComment>>();
+
+    Ok(ServerRes::Trash(UserTrash { posts, comments }))
+}
+
+pub async fn restore_post(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::PostId { post_key } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected PostId, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    let post = app
+        .db
+        .restore_post(time, db_user.id.clone(), post_key)
+        .await
+        .map_err(trash_404_err)?;
+
+    Ok(ServerRes::Post(post.into()))
+}
+
+pub async fn restore_post_comment(
+    State(app): State<AppState>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::CommentId { comment_id } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected CommentId, received: {req:?}"
+        ))));
+    };
+    let time = app.time().await;
+
+    let comment = app
+        .db
+        .restore_post_comment(time, db_user.id.clone(), comment_id)
+        .await
+        .map_err(trash_404_err)
+        .map(UserPostComment::from)?;
+    app.live.publish(LiveEvent::CommentUpdated(comment.clone()));
+
+    Ok(ServerRes::Comment(comment))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::shared::cursor::CursorRange;
+    use crate::api::shared::post_comment::UserPostComment;
+    use crate::api::shared::trash::{TRASH_RETENTION_NS, TrashErr, UserTrash};
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Api, Order, ServerErr, ServerRes, UserPost};
+
+    impl ApiTestApp {
+        pub async fn get_trash(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+        ) -> Result<UserTrash, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_trash()
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Trash(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Trash, got {res:?}"),
+            }
+        }
+
+        pub async fn restore_post(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            post_key: impl Into<String>,
+        ) -> Result<UserPost, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .restore_post(post_key)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Post(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Post, got {res:?}"),
+            }
+        }
+
+        pub async fn restore_post_comment(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            comment_key: impl Into<String>,
+        ) -> Result<UserPostComment, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .restore_post_comment(comment_key)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Comment(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected Comment, got {res:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn api_trash_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        let post = app
+            .add_post(1, &auth_token, "title1", "cat", "one")
+            .await
+            .unwrap();
+        app.delete_post(2, &auth_token, post.key.clone())
+            .await
+            .unwrap();
+        let trash = app.get_trash(3, &auth_token).await.unwrap();
+        assert_eq!(trash.posts.len(), 1);
+        assert_eq!(trash.posts[0].deleted_at, 2);
+        assert!(
+            app.get_trash(3, &auth_token2)
+                .await
+                .unwrap()
+                .posts
+                .is_empty()
+        );
+
+        let result = app.restore_post(3, &auth_token2, post.key.clone()).await;
+        assert_eq!(result, Err(TrashErr::NotFound.into()));
+        let restored = app
+            .restore_post(3, &auth_token, post.key.clone())
+            .await
+            .unwrap();
+        assert_eq!(restored.deleted_at, 0);
+        assert!(
+            app.get_trash(3, &auth_token)
+                .await
+                .unwrap()
+                .posts
+                .is_empty()
+        );
+
+        let comment = app
+            .add_post_comment(4, &auth_token2, post.key.clone(), None, "hello")
+            .await
+            .unwrap();
+        app.add_post_comment(
+            5,
+            &auth_token,
+            post.key.clone(),
+            Some(comment.key.clone()),
+            "hi",
+        )
+        .await
+        .unwrap();
+        app.delete_post_comment(6, &auth_token2, &comment.key)
+            .await
+            .unwrap();
+
+        let comments = app
+            .get_post_comments(
+                7,
+                &auth_token,
+                post.key.clone(),
+                None,
+                10,
+                CursorRange::None,
+                Order::OneTwoThree,
+            )
+            .await
+            .unwrap();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].key, comment.key);
+        assert_eq!(comments[0].text, "");
+        assert_eq!(comments[0].deleted_at, 6);
+        let trash = app.get_trash(7, &auth_token2).await.unwrap();
+        assert_eq!(trash.comments.len(), 1);
+        assert_eq!(trash.comments[0].text, "hello");
+
+        let restored = app
+            .restore_post_comment(8, &auth_token2, comment.key.clone())
+            .await
+            .unwrap();
+        assert_eq!(restored.text, "hello");
+        let comments = app
+            .get_post_comments(
+                8,
+                &auth_token,
+                post.key.clone(),
+                None,
+                10,
+                CursorRange::None,
+                Order::OneTwoThree,
+            )
+            .await
+            .unwrap();
+        assert_eq!(comments[0].text, "hello");
+
+        app.delete_post_comment(9, &auth_token2, &comment.key)
+            .await
+            .unwrap();
+        let result = app
+            .restore_post_comment(9 + TRASH_RETENTION_NS, &auth_token2, comment.key.clone())
+            .await;
+        assert_eq!(result, Err(TrashErr::NotFound.into()));
+        assert!(
+            app.get_trash(9 + TRASH_RETENTION_NS, &auth_token2)
+                .await
+                .unwrap()
+                .comments
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use axum::{Extension, extract::State};
+
+use crate::{
+    api::{
+        AuthToken, ServerDesErr, ServerErr, ServerReq, ServerRes,
+        app_state::AppState,
+        shared::user_block::{USER_BLOCKS_FETCH_MAX, UserBlock, UserBlockErr, UserBlockKind},
+    },
+    db::{DB404Err, DBUser, DBUserBlockErr},
+};
+
+pub async fn add_user_block(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    type ResErr = UserBlockErr;
+
+    let ServerReq::AddUserBlock { username, kind } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected AddUserBlock, received: {req:?}"
+        ))));
+    };
+    if db_user.username == username {
+        return Err(ResErr::BlockSelf.into());
+    }
+    let time = app.time().await;
+
+    app.db
+        .add_user_block(time, db_user.id.clone(), username.clone(), kind.into())
+        .await
+        .map_err(|err| match err {
+            DBUserBlockErr::UserNotFound(_) => ResErr::UserNotFound(username.clone()).into(),
+            DBUserBlockErr::DB(_) => ServerErr::DbErr,
+        })?;
+
+    Ok(ServerRes::Ok)
+}
+
+pub async fn check_user_block(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::Username { username } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected Username, received: {req:?}"
+        ))));
+    };
+
+    let result = app.db.get_user_block(db_user.id.clone(), username).await;
+    match result {
+        Ok(block) => Ok(ServerRes::UserBlockKind(Some(UserBlockKind::from_db(
+            &block.kind,
+        )))),
+        Err(DB404Err::NotFound) => Ok(ServerRes::UserBlockKind(None)),
+        Err(DB404Err::DB(_)) => Err(ServerErr::DbErr),
+    }
+}
+
+pub async fn delete_user_block(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::Username { username } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected Username, received: {req:?}"
+        ))));
+    };
+
+    app.db
+        .delete_user_block(db_user.id.clone(), username)
+        .await
+        .map_err(|_| ServerErr::DbErr)?;
+
+    Ok(ServerRes::Ok)
+}
+
+pub async fn get_user_blocks(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::GetUserBlocks {
+        limit,
+        time_range,
+        order,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected GetUserBlocks, received: {req:?}"
+        ))));
+    };
+
+    let users = app
+        .db
+        .get_user_blocks(
+            db_user.id.clone(),
+            limit.min(USER_BLOCKS_FETCH_MAX),
+            time_range,
+            order,
+        )
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserBlock::from)
+        .collect::<Vec<UserBlock>>();
+
+    Ok(ServerRes::UserBlocks(users))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::shared::cursor::CursorRange;
+    use crate::api::shared::user_block::{UserBlock, UserBlockErr, UserBlockKind};
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{AddPostCommentErr, Api, Order, ServerErr, ServerRes, TimeRange};
+
+    impl ApiTestApp {
+        pub async fn add_user_block(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            username: impl Into<String>,
+            kind: UserBlockKind,
+        ) -> Result<(), ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .add_user_block(username, kind)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Ok => Ok(()),
+                res => panic!("fix code, invalid response, expected Ok, got {res:?}"),
+            }
+        }
+
+        pub async fn check_user_block(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            username: impl Into<String>,
+        ) -> Result<Option<UserBlockKind>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .check_user_block(username)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::UserBlockKind(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected UserBlockKind, got {res:?}"),
+            }
+        }
+
+        pub async fn delete_user_block(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+            username: impl Into<String>,
+        ) -> Result<(), ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .delete_user_block(username)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::Ok => Ok(()),
+                res => panic!("fix code, invalid response, expected Ok, got {res:?}"),
+            }
+        }
+
+        pub async fn get_user_blocks(
+            &self,
+            server_time: u128,
+            auth_token: impl AsRef<str>,
+        ) -> Result<Vec<UserBlock>, ServerErr> {
+            self.set_time(server_time).await;
+            let result = self
+                .api
+                .get_user_blocks(100, TimeRange::None, Order::ThreeTwoOne)
+                .send_native_with_token(auth_token)
+                .await?;
+            match result {
+                ServerRes::UserBlocks(v) => Ok(v),
+                res => panic!("fix code, invalid response, expected UserBlocks, got {res:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn api_user_block_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token3 = app
+            .register(0, "hey3", "hey3@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        let post = app
+            .add_post(1, &auth_token, "title1", "", "one")
+            .await
+            .unwrap();
+        app.add_post(2, &auth_token2, "title2", "", "one")
+            .await
+            .unwrap();
+        app.add_post(3, &auth_token3, "title3", "", "one")
+            .await
+            .unwrap();
+        app.add_post_comment(4, &auth_token2, post.key.clone(), None, "hi")
+            .await
+            .unwrap();
+
+        let result = app
+            .add_user_block(5, &auth_token, "hey", UserBlockKind::Block)
+            .await;
+        assert_eq!(result, Err(UserBlockErr::BlockSelf.into()));
+        let result = app
+            .add_user_block(5, &auth_token, "404", UserBlockKind::Block)
+            .await;
+        assert_eq!(
+            result,
+            Err(UserBlockErr::UserNotFound("404".to_string()).into())
+        );
+
+        let result = app.check_user_block(5, &auth_token, "hey2").await.unwrap();
+        assert_eq!(result, None);
+        app.add_user_block(5, &auth_token, "hey2", UserBlockKind::Block)
+            .await
+            .unwrap();
+        app.add_user_block(6, &auth_token, "hey3", UserBlockKind::Mute)
+            .await
+            .unwrap();
+        let result = app.check_user_block(6, &auth_token, "hey2").await.unwrap();
+        assert_eq!(result, Some(UserBlockKind::Block));
+
+        let result = app.get_user_blocks(6, &auth_token).await.unwrap();
+        assert_eq!(
+            result
+                .iter()
+                .map(|v| (v.user.username.as_str(), v.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                ("hey3", UserBlockKind::Mute),
+                ("hey2", UserBlockKind::Block)
+            ]
+        );
+
+        let posts = app
+            .get_posts(
+                7,
+                &auth_token,
+                10,
+                CursorRange::None,
+                Order::ThreeTwoOne,
+                "",
+                "",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            posts.iter().map(|v| v.title.as_str()).collect::<Vec<_>>(),
+            vec!["title1"]
+        );
+        let posts = app
+            .get_posts(
+                7,
+                &auth_token2,
+                10,
+                CursorRange::None,
+                Order::ThreeTwoOne,
+                "",
+                "",
+            )
+            .await
+            .unwrap();
+        assert_eq!(posts.len(), 3);
+
+        let comments = app
+            .get_post_comments(
+                7,
+                &auth_token,
+                post.key.clone(),
+                None,
+                10,
+                CursorRange::None,
+                Order::OneTwoThree,
+            )
+            .await
+            .unwrap();
+        assert!(comments.is_empty());
+
+        let result = app
+            .add_post_comment(8, &auth_token2, post.key.clone(), None, "hi")
+            .await;
+        assert_eq!(result, Err(AddPostCommentErr::Blocked));
+        let result = app.add_post_like(8, &auth_token2, post.key.clone()).await;
+        assert!(result.is_none());
+        let result = app
+            .add_post_comment(8, &auth_token3, post.key.clone(), None, "hi")
+            .await;
+        assert!(result.is_ok());
+
+        app.delete_user_block(9, &auth_token, "hey2").await.unwrap();
+        let result = app.check_user_block(9, &auth_token, "hey2").await.unwrap();
+        assert_eq!(result, None);
+        let result = app.add_post_like(9, &auth_token2, post.key.clone()).await;
+        assert!(result.is_some());
+    }
+}
@@ -0,0 +1,326 @@
+use axum::{Extension, extract::State};
+use tracing::{debug, trace};
+
+use crate::{
+    api::{
+        AuthToken, Server404Err, ServerDesErr, ServerErr, ServerReq, ServerRes, UserFollow,
+        UserFollowErr, UserPost,
+        app_state::AppState,
+        shared::{
+            cursor::{page_cursors, page_limit},
+            rating::PostRating,
+        },
+    },
+    db::{DB404Err, DBUser, DBUserFollowErr, user_follow::DBUserFollowSide},
+};
+
+pub async fn add_user_follow(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    type ResErr = UserFollowErr;
+
+    let ServerReq::Username { username } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected Username, received: {req:?}"
+        ))));
+    };
+    if db_user.username == username {
+        return Err(ResErr::FollowSelf.into());
+    }
+    let time = app.time().await;
+
+    app.db
+        .add_user_follow(time, db_user.id.clone(), username.clone())
+        .await
+        .map_err(|err| match err {
+            DBUserFollowErr::UserNotFound(_) => ResErr::UserNotFound(username.clone()).into(),
+            DBUserFollowErr::UserWasAlreadyFollowed => {
+                ResErr::UserAlreadyFollowed(username.clone()).into()
+            }
+            DBUserFollowErr::DB(_) => ServerErr::DbErr,
+        })?;
+
+    Ok(ServerRes::Ok)
+}
+
+pub async fn check_user_follow(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::Username { username } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected Username, received: {req:?}"
+        ))));
+    };
+
+    let err = app
+        .db
+        .check_user_follow(db_user.id.clone(), username.clone())
+        .await;
+    match err {
+        Ok(v) => Ok(ServerRes::Condition(true)),
+        Err(DB404Err::NotFound) => Ok(ServerRes::Condition(false)),
+        Err(DB404Err::DB(_)) => Err(ServerErr::DbErr),
+    }
+}
+
+pub async fn delete_user_follow(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::Username { username } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected Username, received: {req:?}"
+        ))));
+    };
+
+    app.db
+        .delete_user_follow(db_user.id.clone(), username.clone())
+        .await
+        .map_err(|err| ServerErr::DbErr)?;
+
+    Ok(ServerRes::Ok)
+}
+
+pub async fn get_user_follow_stats(
+    State(app): State<AppState>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::Username { username } = req else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected Username, received: {req:?}"
+        ))));
+    };
+
+    let stats = app
+        .db
+        .get_user_follow_stats(username)
+        .await
+        .map_err(|err| match err {
+            DB404Err::NotFound => ServerErr::NotFoundErr(Server404Err::NotFound),
+            _ => ServerErr::DbErr,
+        })?;
+
+    Ok(ServerRes::UserFollowStats {
+        followers: stats.followers,
+        following: stats.following,
+    })
+}
+
+async fn get_user_follows(
+    app: AppState,
+    req: ServerReq,
+    side: DBUserFollowSide,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::GetUserFollows {
+        username,
+        limit,
+        time_range,
+        order,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected GetUserFollows, received: {req:?}"
+        ))));
+    };
+
+    let users = app
+        .db
+        .get_user_follow_users(username, side, limit, time_range, order)
+        .await
+        .map_err(|err| match err {
+            DB404Err::NotFound => ServerErr::NotFoundErr(Server404Err::NotFound),
+            _ => ServerErr::DbErr,
+        })?
+        .into_iter()
+        .map(UserFollow::from)
+        .collect::<Vec<UserFollow>>();
+
+    Ok(ServerRes::UserFollows(users))
+}
+
+pub async fn get_user_followers(
+    State(app): State<AppState>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    get_user_follows(app, req, DBUserFollowSide::Followers).await
+}
+
+pub async fn get_user_following(
+    State(app): State<AppState>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    get_user_follows(app, req, DBUserFollowSide::Following).await
+}
+
+pub async fn get_posts_following(
+    State(app): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    req: ServerReq,
+) -> Result<ServerRes, ServerErr> {
+    let ServerReq::GetFollowingPosts {
+        limit,
+        cursor,
+        order,
+    } = req
+    else {
+        return Err(ServerErr::from(ServerDesErr::ServerWrongInput(format!(
+            "expected GetFollowingPosts, received: {req:?}"
+        ))));
+    };
+
+    let limit = page_limit(limit)?;
+    let posts = app
+        .db
+        .post_search_following(
+            db_user.id.clone(),
+            limit,
+            cursor,
+            order,
+            PostRating::from_db(&db_user.max_rating).into(),
+        )
+        .await
+        .map_err(|_| ServerErr::DbErr)?
+        .into_iter()
+        .map(UserPost::from)
+        .collect::<Vec<UserPost>>();
+    let (next, prev) = page_cursors(&posts, limit);
+
+    Ok(ServerRes::PostPage { posts, next, prev })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use tracing::trace;
+
+    use crate::api::shared::cursor::{Cursor, CursorErr, CursorRange};
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Api, Order, ServerErr, TimeRange, UserFollowErr};
+
+    #[tokio::test]
+    async fn api_user_follow_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token2 = app
+            .register(0, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        app.check_user_follow(0, &auth_token, "hey2", false)
+            .await
+            .unwrap();
+        app.add_user_follow(1, &auth_token, "hey2").await.unwrap();
+        app.check_user_follow(1, &auth_token, "hey2", true)
+            .await
+            .unwrap();
+
+        let result = app.add_user_follow_err(1, &auth_token, "hey2").await;
+        assert!(matches!(
+            result,
+            ServerErr::UserFollowErr(UserFollowErr::UserAlreadyFollowed(_))
+        ));
+        let result = app.add_user_follow_err(1, &auth_token, "none").await;
+        assert!(matches!(
+            result,
+            ServerErr::UserFollowErr(UserFollowErr::UserNotFound(_))
+        ));
+        let result = app.add_user_follow_err(1, &auth_token, "hey").await;
+        assert!(matches!(
+            result,
+            ServerErr::UserFollowErr(UserFollowErr::FollowSelf)
+        ));
+
+        let stats = app.get_user_follow_stats(1, "hey2").await.unwrap();
+        assert_eq!(stats, (1, 0));
+        let stats = app.get_user_follow_stats(1, "hey").await.unwrap();
+        assert_eq!(stats, (0, 1));
+
+        let followers = app
+            .get_user_followers(1, "hey2", 10, TimeRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|v| v.user.username)
+            .collect::<Vec<String>>();
+        assert_eq!(followers, vec!["hey"]);
+        let following = app
+            .get_user_following(1, "hey", 10, TimeRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|v| v.user.username)
+            .collect::<Vec<String>>();
+        assert_eq!(following, vec!["hey2"]);
+
+        app.add_post(2, &auth_token2, "title1", "", "one")
+            .await
+            .unwrap();
+        app.add_post(3, &auth_token, "title2", "", "one")
+            .await
+            .unwrap();
+        app.add_post(4, &auth_token2, "title3", "", "one")
+            .await
+            .unwrap();
+
+        let posts = app
+            .get_posts_following(5, &auth_token, 10, CursorRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|v| v.title)
+            .collect::<Vec<String>>();
+        assert_eq!(posts, vec!["title3", "title1"]);
+        let posts = app
+            .get_posts_following(
+                5,
+                &auth_token,
+                10,
+                CursorRange::Before(Cursor::at(4)),
+                Order::ThreeTwoOne,
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|v| v.title)
+            .collect::<Vec<String>>();
+        assert_eq!(posts, vec!["title1"]);
+        let posts = app
+            .get_posts_following(5, &auth_token2, 10, CursorRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap();
+        assert!(posts.is_empty());
+        let result = app
+            .api
+            .get_posts_following(0, CursorRange::None, Order::ThreeTwoOne)
+            .send_native_with_token(&auth_token)
+            .await;
+        assert_eq!(result, Err(ServerErr::CursorErr(CursorErr::InvalidLimit)));
+
+        app.delete_user_follow(6, &auth_token, "hey2")
+            .await
+            .unwrap();
+        app.check_user_follow(6, &auth_token, "hey2", false)
+            .await
+            .unwrap();
+        let posts = app
+            .get_posts_following(6, &auth_token, 10, CursorRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap();
+        trace!("following feed after unfollow {posts:?}");
+        assert!(posts.is_empty());
+    }
+}
@@ -0,0 +1,645 @@
+//! versioned public api, plain json over resource shaped routes so outside clients don't
+//! have to speak the internal ServerReq protocol. handlers build the internal request from
+//! typed extractors and reuse the existing handlers so permissions stay in one place.
+
+use axum::extract::{Path, Query, State};
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use http::header::AUTHORIZATION;
+use http::{HeaderMap, StatusCode};
+use tracing::trace;
+use utoipa::OpenApi;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, Http, HttpAuthScheme, SecurityScheme};
+
+use crate::api::app_state::AppState;
+use crate::api::backend::{
+    api_token_scope, check_auth, get_user_by_username, post, post_comment, post_like,
+};
+use crate::api::shared::cursor::{Cursor, CursorRange};
+use crate::api::shared::post_comment::UserPostComment;
+use crate::api::{
+    AuthToken, Order, PostLikeErr, ServerAuthErr, ServerErr, ServerReq, ServerRes, UserPost,
+    UserPostFile,
+};
+use crate::db::DBUser;
+use crate::path::link_img;
+
+pub const V1_LIMIT_DEFAULT: usize = 25;
+pub const V1_LIMIT_MAX: usize = 100;
+
+/// set on list responses when there might be more, pass it back as `cursor` to get the next page
+pub const V1_HEADER_NEXT_CURSOR: &str = "x-next-cursor";
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct V1Post {
+    pub key: String,
+    pub username: String,
+    pub title: String,
+    pub description: String,
+    pub tags: String,
+    /// general, questionable or explicit
+    pub rating: String,
+    /// public or unlisted, drafts only show up for their author
+    pub visibility: String,
+    pub favorites: u64,
+    pub files: Vec<V1PostFile>,
+    /// nanoseconds since unix epoch, 0 if never edited
+    pub edited_at: u64,
+    /// nanoseconds since unix epoch
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct V1PostFile {
+    pub url: String,
+    pub hash: String,
+    pub extension: String,
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct V1Comment {
+    pub key: String,
+    pub username: String,
+    /// direct parent, none for top level comments
+    pub parent_key: Option<String>,
+    /// empty if the comment is in its authors trash
+    pub text: String,
+    pub replies_count: u64,
+    /// nanoseconds since unix epoch, 0 if never edited
+    pub edited_at: u64,
+    /// nanoseconds since unix epoch
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct V1User {
+    pub username: String,
+    /// nanoseconds since unix epoch
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct V1Error {
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct V1PostsQuery {
+    /// space separated tags, all of them have to match
+    pub tags: Option<String>,
+    /// only posts created before this time, nanoseconds since unix epoch
+    pub before: Option<u64>,
+    /// opaque value from the x-next-cursor header of the previous page, wins over before
+    pub cursor: Option<String>,
+    /// defaults to 25, at most 100
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct V1PageQuery {
+    /// only items created before this time, nanoseconds since unix epoch
+    pub before: Option<u64>,
+    /// opaque value from the x-next-cursor header of the previous page, wins over before
+    pub cursor: Option<String>,
+    /// defaults to 25, at most 100
+    pub limit: Option<usize>,
+}
+
+impl From<UserPost> for V1Post {
+    fn from(value: UserPost) -> Self {
+        Self {
+            key: value.key,
+            username: value.user.username,
+            title: value.title,
+            description: value.description,
+            tags: value.tags,
+            rating: value.rating.to_string(),
+            visibility: value.visibility.to_string(),
+            favorites: value.favorites,
+            files: value.file.into_iter().map(V1PostFile::from).collect(),
+            edited_at: value.edited_at as u64,
+            created_at: value.created_at as u64,
+        }
+    }
+}
+
+impl From<UserPostFile> for V1PostFile {
+    fn from(value: UserPostFile) -> Self {
+        Self {
+            url: link_img(&value.hash, &value.extension),
+            hash: value.hash,
+            extension: value.extension,
+            width: value.width,
+            height: value.height,
+            size_bytes: value.size_bytes as u64,
+        }
+    }
+}
+
+impl From<UserPostComment> for V1Comment {
+    fn from(value: UserPostComment) -> Self {
+        Self {
+            key: value.key,
+            username: value.user.username,
+            parent_key: value.parent_key.last().cloned(),
+            text: value.text,
+            replies_count: value.replies_count as u64,
+            edited_at: value.edited_at as u64,
+            created_at: value.created_at as u64,
+        }
+    }
+}
+
+impl From<DBUser> for V1User {
+    fn from(value: DBUser) -> Self {
+        Self {
+            username: value.username,
+            created_at: value.created_at as u64,
+        }
+    }
+}
+
+/// same errors as the internal api but with real status codes and a small json body
+#[derive(Debug)]
+pub struct V1Err(pub ServerErr);
+
+impl From<ServerErr> for V1Err {
+    fn from(value: ServerErr) -> Self {
+        Self(value)
+    }
+}
+
+impl V1Err {
+    pub fn status(&self) -> StatusCode {
+        match &self.0 {
+            ServerErr::NotFoundErr(_) | ServerErr::PostLikeErr(PostLikeErr::PostNotFound(_)) => {
+                StatusCode::NOT_FOUND
+            }
+            ServerErr::PostLikeErr(PostLikeErr::PostAlreadyLiked(_)) => StatusCode::CONFLICT,
+            ServerErr::PostLikeErr(PostLikeErr::Blocked)
+            | ServerErr::AuthErr(
+                ServerAuthErr::ServerBanned
+                | ServerAuthErr::ServerSuspended(_)
+                | ServerAuthErr::ServerApiTokenScope(_)
+                | ServerAuthErr::ServerApiTokenNotAccepted,
+            ) => StatusCode::FORBIDDEN,
+            ServerErr::AuthErr(_) => StatusCode::UNAUTHORIZED,
+            ServerErr::DesErr(_) | ServerErr::CursorErr(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for V1Err {
+    fn into_response(self) -> axum::response::Response {
+        let status = self.status();
+        let body = V1Error {
+            error: self.0.to_string(),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+fn unexpected(res: ServerRes) -> V1Err {
+    trace!("v1 unexpected response {res:?}");
+    V1Err(ServerErr::InternalServerErr)
+}
+
+fn page(
+    before: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<usize>,
+) -> Result<(CursorRange, usize), V1Err> {
+    let cursor = match (cursor, before) {
+        (Some(cursor), _) => {
+            CursorRange::Before(cursor.parse::<Cursor>().map_err(ServerErr::from)?)
+        }
+        (None, Some(before)) => CursorRange::Before(Cursor::at(before as u128)),
+        (None, None) => CursorRange::None,
+    };
+    let limit = limit.unwrap_or(V1_LIMIT_DEFAULT).clamp(1, V1_LIMIT_MAX);
+    Ok((cursor, limit))
+}
+
+fn next_cursor_header(next: Option<Cursor>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Some(value) = next.and_then(|next| next.to_string().parse().ok()) {
+        headers.insert(V1_HEADER_NEXT_CURSOR, value);
+    }
+    headers
+}
+
+/// v1 counterpart of auth_middleware, rejections are v1 errors instead of the internal encoding
+pub async fn v1_auth_middleware(
+    State(app_state): State<AppState>,
+    mut req: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    match check_auth(&app_state, req.headers(), api_token_scope(&req)).await {
+        Ok((token, user)) => {
+            let extensions = req.extensions_mut();
+            extensions.insert(token);
+            extensions.insert(user);
+            next.run(req).await
+        }
+        Err(err) => V1Err(err).into_response(),
+    }
+}
+
+async fn search_posts(
+    app_state: AppState,
+    db_user: Option<DBUser>,
+    headers: HeaderMap,
+    username: String,
+    query: V1PostsQuery,
+) -> Result<(HeaderMap, Json<Vec<V1Post>>), V1Err> {
+    let (cursor, limit) = page(query.before, query.cursor, query.limit)?;
+    let req = ServerReq::GetPosts {
+        cursor,
+        order: Order::ThreeTwoOne,
+        limit,
+        tags: query.tags.unwrap_or_default(),
+        username,
+    };
+    match post::get_posts(State(app_state), Extension(db_user), headers, req).await? {
+        ServerRes::PostPage { posts, next, .. } => Ok((
+            next_cursor_header(next),
+            Json(posts.into_iter().map(V1Post::from).collect()),
+        )),
+        res => Err(unexpected(res)),
+    }
+}
+
+/// newest posts first
+#[utoipa::path(
+    get,
+    path = "/posts",
+    params(V1PostsQuery),
+    responses(
+        (status = 200, body = Vec<V1Post>, headers(("x-next-cursor" = String, description = "cursor of the next page"))),
+        (status = 400, body = V1Error),
+        (status = 500, body = V1Error),
+    ),
+)]
+pub async fn get_posts(
+    State(app_state): State<AppState>,
+    db_user: Extension<Option<DBUser>>,
+    headers: HeaderMap,
+    Query(query): Query<V1PostsQuery>,
+) -> Result<(HeaderMap, Json<Vec<V1Post>>), V1Err> {
+    search_posts(app_state, db_user.0, headers, String::new(), query).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{post_key}",
+    params(("post_key" = String, Path)),
+    responses(
+        (status = 200, body = V1Post),
+        (status = 404, body = V1Error),
+    ),
+)]
+pub async fn get_post(
+    State(app_state): State<AppState>,
+    auth_token: Extension<Option<AuthToken>>,
+    db_user: Extension<Option<DBUser>>,
+    Path(post_key): Path<String>,
+) -> Result<Json<V1Post>, V1Err> {
+    let req = ServerReq::PostId { post_key };
+    match post::get_post(State(app_state), auth_token, db_user, req).await? {
+        ServerRes::Post(post) => Ok(Json(post.into())),
+        res => Err(unexpected(res)),
+    }
+}
+
+/// top level comments, newest first
+#[utoipa::path(
+    get,
+    path = "/posts/{post_key}/comments",
+    params(("post_key" = String, Path), V1PageQuery),
+    responses(
+        (status = 200, body = Vec<V1Comment>, headers(("x-next-cursor" = String, description = "cursor of the next page"))),
+        (status = 400, body = V1Error),
+        (status = 404, body = V1Error),
+    ),
+)]
+pub async fn get_post_comments(
+    State(app_state): State<AppState>,
+    db_user: Extension<Option<DBUser>>,
+    Path(post_key): Path<String>,
+    Query(query): Query<V1PageQuery>,
+) -> Result<(HeaderMap, Json<Vec<V1Comment>>), V1Err> {
+    let (cursor, limit) = page(query.before, query.cursor, query.limit)?;
+    let req = ServerReq::GetComments {
+        post_key,
+        comment_key: None,
+        limit,
+        cursor,
+        order: Order::ThreeTwoOne,
+        flatten: false,
+    };
+    match post_comment::get_post_comment(State(app_state), db_user, req).await? {
+        ServerRes::CommentPage { comments, next, .. } => Ok((
+            next_cursor_header(next),
+            Json(comments.into_iter().map(V1Comment::from).collect()),
+        )),
+        res => Err(unexpected(res)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{post_key}/likes",
+    params(("post_key" = String, Path)),
+    security(("cookie" = []), ("api_token" = ["comment:write"])),
+    responses(
+        (status = 204),
+        (status = 401, body = V1Error),
+        (status = 403, body = V1Error),
+        (status = 404, body = V1Error),
+        (status = 409, body = V1Error),
+    ),
+)]
+pub async fn add_post_like(
+    State(app_state): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    Path(post_key): Path<String>,
+) -> Result<StatusCode, V1Err> {
+    let req = ServerReq::PostId { post_key };
+    post_like::add_post_like(State(app_state), auth_token, db_user, req).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/posts/{post_key}/likes",
+    params(("post_key" = String, Path)),
+    security(("cookie" = []), ("api_token" = ["comment:write"])),
+    responses(
+        (status = 204),
+        (status = 401, body = V1Error),
+    ),
+)]
+pub async fn delete_post_like(
+    State(app_state): State<AppState>,
+    auth_token: Extension<AuthToken>,
+    db_user: Extension<DBUser>,
+    Path(post_key): Path<String>,
+) -> Result<StatusCode, V1Err> {
+    let req = ServerReq::PostId { post_key };
+    post_like::delete_post_like(State(app_state), auth_token, db_user, req).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{username}",
+    params(("username" = String, Path)),
+    responses(
+        (status = 200, body = V1User),
+        (status = 404, body = V1Error),
+    ),
+)]
+pub async fn get_user(
+    State(app_state): State<AppState>,
+    Path(username): Path<String>,
+) -> Result<Json<V1User>, V1Err> {
+    let user = get_user_by_username(&app_state, username).await?;
+    Ok(Json(user.into()))
+}
+
+/// newest posts of one user first
+#[utoipa::path(
+    get,
+    path = "/users/{username}/posts",
+    params(("username" = String, Path), V1PostsQuery),
+    responses(
+        (status = 200, body = Vec<V1Post>, headers(("x-next-cursor" = String, description = "cursor of the next page"))),
+        (status = 400, body = V1Error),
+        (status = 404, body = V1Error),
+    ),
+)]
+pub async fn get_user_posts(
+    State(app_state): State<AppState>,
+    db_user: Extension<Option<DBUser>>,
+    headers: HeaderMap,
+    Path(username): Path<String>,
+    Query(query): Query<V1PostsQuery>,
+) -> Result<(HeaderMap, Json<Vec<V1Post>>), V1Err> {
+    // an unknown user would otherwise look like a user without posts
+    let user = get_user_by_username(&app_state, username).await?;
+    search_posts(app_state, db_user.0, headers, user.username, query).await
+}
+
+pub async fn get_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiV1Doc::openapi())
+}
+
+/// the session cookie from login or a personal access token as a bearer token
+struct AuthSchemes;
+
+impl utoipa::Modify for AuthSchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "cookie",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(AUTHORIZATION.as_str()))),
+        );
+        components.add_security_scheme(
+            "api_token",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "artbounty", version = "1"),
+    servers((url = "/api/v1")),
+    paths(
+        get_posts,
+        get_post,
+        get_post_comments,
+        add_post_like,
+        delete_post_like,
+        get_user,
+        get_user_posts,
+    ),
+    components(schemas(V1Post, V1PostFile, V1Comment, V1User, V1Error)),
+    modifiers(&AuthSchemes),
+)]
+pub struct ApiV1Doc;
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use http::header::COOKIE;
+
+    use crate::api::create_auth_header;
+    use crate::api::tests::ApiTestApp;
+    use crate::path::{
+        PATH_API, PATH_API_V1, PATH_API_V1_OPENAPI, PATH_API_V1_POST, PATH_API_V1_POST_COMMENTS,
+        PATH_API_V1_POST_LIKES, PATH_API_V1_POSTS, PATH_API_V1_USER, PATH_API_V1_USER_POSTS,
+    };
+
+    use super::{V1_HEADER_NEXT_CURSOR, V1Comment, V1Error, V1Post, V1User};
+
+    #[tokio::test]
+    async fn api_v1_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let post0 = app
+            .add_post(0, &auth_token, "title1", "cat", "one two")
+            .await
+            .unwrap();
+        let post1 = app
+            .add_post(1, &auth_token, "title2", "cat", "one")
+            .await
+            .unwrap();
+        app.add_post_comment(2, &auth_token, post0.key.clone(), None, "hello")
+            .await
+            .unwrap();
+        app.set_time(3).await;
+
+        let send = async |method: reqwest::Method, path: String, auth: bool| {
+            let url = format!("{PATH_API}{PATH_API_V1}{path}");
+            let url = app.api.server.server_url(&url).unwrap();
+            let mut req = reqwest::Client::new().request(method, url);
+            if auth {
+                req = req.header(COOKIE, create_auth_header(&auth_token));
+            }
+            let res = req.send().await.unwrap();
+            (res.status(), res.bytes().await.unwrap())
+        };
+        let get = async |path: String| send(reqwest::Method::GET, path, false).await;
+
+        let (status, bytes) = get(PATH_API_V1_POSTS.to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        let posts: Vec<V1Post> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(
+            posts.iter().map(|v| v.key.clone()).collect::<Vec<_>>(),
+            vec![post1.key.clone(), post0.key.clone()]
+        );
+        assert_eq!(posts[0].username, "hey");
+
+        let (_, bytes) = get(format!("{PATH_API_V1_POSTS}?tags=two&limit=1")).await;
+        let posts: Vec<V1Post> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].key, post0.key);
+
+        let (_, bytes) = get(format!("{PATH_API_V1_POSTS}?before=1")).await;
+        let posts: Vec<V1Post> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].key, post0.key);
+
+        let (status, _) = get(format!("{PATH_API_V1_POSTS}?limit=abc")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // cursor paging
+        let url = format!("{PATH_API}{PATH_API_V1}{PATH_API_V1_POSTS}?limit=1");
+        let res = reqwest::get(app.api.server.server_url(&url).unwrap())
+            .await
+            .unwrap();
+        let next = res.headers()[V1_HEADER_NEXT_CURSOR]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let posts: Vec<V1Post> = serde_json::from_slice(&res.bytes().await.unwrap()).unwrap();
+        assert_eq!(posts[0].key, post1.key);
+        let (_, bytes) = get(format!("{PATH_API_V1_POSTS}?limit=1&cursor={next}")).await;
+        let posts: Vec<V1Post> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].key, post0.key);
+
+        let (status, _) = get(format!("{PATH_API_V1_POSTS}?cursor=zz")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, bytes) = get(PATH_API_V1_POST.replace("{post_key}", &post0.key)).await;
+        assert_eq!(status, StatusCode::OK);
+        let post: V1Post = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(post.title, "title1");
+
+        let (status, bytes) = get(PATH_API_V1_POST.replace("{post_key}", "404")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let err: V1Error = serde_json::from_slice(&bytes).unwrap();
+        assert!(!err.error.is_empty());
+
+        let (status, bytes) =
+            get(PATH_API_V1_POST_COMMENTS.replace("{post_key}", &post0.key)).await;
+        assert_eq!(status, StatusCode::OK);
+        let comments: Vec<V1Comment> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].text, "hello");
+        assert_eq!(comments[0].parent_key, None);
+
+        let (status, bytes) = get(PATH_API_V1_USER.replace("{username}", "hey")).await;
+        assert_eq!(status, StatusCode::OK);
+        let user: V1User = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(user.username, "hey");
+
+        let (status, _) = get(PATH_API_V1_USER.replace("{username}", "nobody")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, bytes) = get(PATH_API_V1_USER_POSTS.replace("{username}", "hey")).await;
+        assert_eq!(status, StatusCode::OK);
+        let posts: Vec<V1Post> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(posts.len(), 2);
+
+        let (status, _) = get(PATH_API_V1_USER_POSTS.replace("{username}", "nobody")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // likes
+        let likes = PATH_API_V1_POST_LIKES.replace("{post_key}", &post0.key);
+        let (status, _) = send(reqwest::Method::POST, likes.clone(), false).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(reqwest::Method::POST, likes.clone(), true).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(reqwest::Method::POST, likes.clone(), true).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        app.check_post_like(3, &auth_token, post0.key.clone(), true)
+            .await
+            .unwrap();
+        let (status, _) = send(reqwest::Method::DELETE, likes.clone(), true).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        app.check_post_like(3, &auth_token, post0.key.clone(), false)
+            .await
+            .unwrap();
+        let (status, _) = send(
+            reqwest::Method::POST,
+            PATH_API_V1_POST_LIKES.replace("{post_key}", "404"),
+            true,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // spec
+        let (status, bytes) = get(PATH_API_V1_OPENAPI.to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        let spec: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        for path in [
+            PATH_API_V1_POSTS,
+            PATH_API_V1_POST,
+            PATH_API_V1_POST_COMMENTS,
+            PATH_API_V1_POST_LIKES,
+            PATH_API_V1_USER,
+            PATH_API_V1_USER_POSTS,
+        ] {
+            assert!(spec["paths"][path].is_object(), "{path} missing from spec");
+        }
+        assert!(spec["paths"][PATH_API_V1_POST_LIKES]["post"].is_object());
+        assert!(spec["paths"][PATH_API_V1_POST_LIKES]["delete"].is_object());
+        assert!(spec["components"]["schemas"]["V1Post"].is_object());
+    }
+}
@@ -0,0 +1,584 @@
+use std::time::Duration;
+
+use axum::{Extension, extract::State};
+use futures::StreamExt;
+use http::HeaderMap;
+use http::header::CONTENT_TYPE;
+use rand::distr::{Alphanumeric, SampleString};
+use surrealdb::types::{RecordId, ToSql};
+use tracing::{error, info, trace};
+
+use crate::{
+    api::{
+        ServerDesErr, ServerErr, ServerReq, ServerRes, UserPost,
+        app_state::AppState,
+        backend::{
+            ClientIp, audit_meta,
+            outbound::{outbound_client, resolve_outbound},
+            v1::V1Post,
+        },
+        shared::{
+            admin::UserRole,
+            webhook::{
+                UserWebhook, UserWebhookDelivery, WEBHOOK_ATTEMPTS_MAX,
+                WEBHOOK_DELIVERIES_FETCH_MAX, WEBHOOK_DELIVERY_RETENTION_NS,
+                WEBHOOK_ERROR_MAX_LENGTH, WEBHOOK_HEADER_DELIVERY, WEBHOOK_HEADER_EVENT,
+                WEBHOOK_HEADER_SIGNATURE, WEBHOOK_SECRET_LENGTH, WEBHOOK_SECRET_PREFIX,
+                WEBHOOK_URL_MAX_LENGTH, WEBHOOKS_MAX, WebhookErr, WebhookEvent,
+                webhook_retry_delay,
+            },
+        },
+    },
+    db::{
+        DB404Err, DBPostVisibility, DBUser, DBUserPost, DBWebhookErr,
+        webhook::{DBWebhookDeliveryDue, DBWebhookDeliveryStatus},
+    },
+};
+
+/// how many due deliveries one run of the worker sends
+pub const WEBHOOK_DELIVERIES_BATCH: usize = 50;
+
+/// how many of those are in flight at the same time
+pub const WEBHOOK_DELIVERIES_CONCURRENCY: usize = 10;
+
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `sha256=` followed by the hex hmac-sha256 of the body, receivers compute the same with their copy of the secret
+pub fn webhook_signature(secret: impl AsRef<str>, body: impl AsRef<str>) -> String {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_ref().as_bytes())
+        .expect("hmac accepts keys of any length");
+    mac.update(body.as_ref().as_bytes());
+    format!("sha256={:x}", mac.finalize().into_bytes())
+}
+
+/// the host has to resolve to public addresses so webhooks can't be aimed at the server's own network
+async fn valid_webhook_url(url: &str, allow_private: bool) -> bool {
+    if url.len() > WEBHOOK_URL_MAX_LENGTH
+        || !(url.starts_with("http://") || url.starts_with("https://"))
+    {
+        return false;
+    }
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    resolve_outbound(&url, allow_private)
+        .await
+        .inspect_err(|err| trace!("webhook url {url} rejected: {err}"))
+        .is_ok()
+}
+
+/// queues the event for every matching webhook, `post_id` decides whose user level webhooks match.
+/// failures are only logged so a webhook can never fail the request that caused it
+pub async fn dispatch_webhook_event(
+    app: &AppState,
+    time: u128,
+    event: WebhookEvent,
+    post_id: Option<RecordId>,
+    data: serde_json::Value,
+) {
+    let payload = serde_json::json!({
+        "event": event.to_string(),
+        "created_at": time as u64,
+        "data": data,
+    });
+    let result = app
+        .db
+        .add_webhook_deliveries(time, event.to_string(), post_id, payload.to_string())
+        .await;
+    match result {
+        Ok(queued) => trace!("queued {queued} webhook deliveries for {event}"),
+        Err(err) => error!("failed to queue {event} webhook deliveries: {err}"),
+    }
+}
+
+/// post events carry the post in the same shape as the v1 api
+pub async fn dispatch_webhook_post_event(
+    app: &AppState,
+    time: u128,
+    event: WebhookEvent,
+    post: &DBUserPost,
+) {
+    let data = serde_json::json!({
+        "post": V1Post::from(UserPost::from(post.clone())),
+    });
+    dispatch_webhook_event(app, time, event, Some(post.id.clone()), data).await;
+}
+
+/// drafts and scheduled posts only count as created once they are published
+pub fn webhook_post_published(post: &DBUserPost) -> bool {
+    post.visibility != DBPostVisibility::Draft.to_string()
+        && post.visibility != DBPostVisibility::Scheduled.to_string()
+}
+
+/// returns the status code, or 0 and the error if the receiver never answered.
+/// the host is checked again since it could resolve somewhere else by now, response bodies are not kept
+async fn send_webhook_delivery(
+    delivery: &DBWebhookDeliveryDue,
+    allow_private: bool,
+) -> (u16, String) {
+    let send = async || -> Result<reqwest::Response, anyhow::Error> {
+        let url = reqwest::Url::parse(&delivery.webhook.url)?;
+        let addr = resolve_outbound(&url, allow_private).await?;
+        let client = outbound_client(&url, addr, WEBHOOK_TIMEOUT)?;
+        let response = client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(WEBHOOK_HEADER_EVENT, &delivery.event)
+            .header(WEBHOOK_HEADER_DELIVERY, delivery.id.key.to_sql())
+            .header(
+                WEBHOOK_HEADER_SIGNATURE,
+                webhook_signature(&delivery.webhook.secret, &delivery.payload),
+            )
+            .body(delivery.payload.clone())
+            .send()
+            .await?;
+        Ok(response)
+    };
+
+    match send().await {
+        Ok(response) => (response.status().as_u16(), String::new()),
+        Err(err) => (
+            0,
+            err.to_string()
+                .chars()
+                .take(WEBHOOK_ERROR_MAX_LENGTH)
+                .collect(),
+        ),
+    }
+}
+
+/// sends every due delivery once, failed ones are retried with exponential backoff until they run out of attempts
+pub async fn proccess_webhook_deliveries(app: &AppState) -> Result<(), anyhow::Error> {
+    let time = app.time().await;
+    app.db
+        .delete_webhook_deliveries_expired(time, WEBHOOK_DELIVERY_RETENTION_NS)
+        .await?;
+
+    let deliveries = app
+        .db
+        .get_webhook_deliveries_due(time, WEBHOOK_DELIVERIES_BATCH)
+        .await?;
+    if deliveries.is_empty() {
+        return Ok(());
+    }
+
+    // a slow receiver only holds up its own deliveries
+    let allow_private = app.settings.site.allow_private_outbound;
+    let results = futures::stream::iter(deliveries)
+        .map(|delivery| attempt_webhook_delivery(app, time, delivery, allow_private))
+        .buffer_unordered(WEBHOOK_DELIVERIES_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+    results.into_iter().collect()
+}
+
+/// sends one delivery and records the outcome
+async fn attempt_webhook_delivery(
+    app: &AppState,
+    time: u128,
+    delivery: DBWebhookDeliveryDue,
+    allow_private: bool,
+) -> Result<(), anyhow::Error> {
+    let attempts = delivery.attempts + 1;
+    let (response_code, response) = send_webhook_delivery(&delivery, allow_private).await;
+    let (status, next_attempt_at) = if (200..300).contains(&response_code) {
+        (DBWebhookDeliveryStatus::Success, 0)
+    } else if attempts >= WEBHOOK_ATTEMPTS_MAX {
+        info!(
+            "webhook delivery {} failed for good after {attempts} attempts",
+            delivery.id.key.to_sql()
+        );
+        (DBWebhookDeliveryStatus::Failed, 0)
+    } else {
+        (
+            DBWebhookDeliveryStatus::Pending,
+            time + webhook_retry_delay(attempts),
+        )
+    };
+
+    app.db
+        .update_webhook_delivery(
+            time,
+            delivery.id,
+            status,
+            attempts,
+            next_attempt_at,
+            response_code,
+            response,
+        )
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::Router;
+    use axum::routing::post;
+    use http::{HeaderMap, StatusCode};
+    use tokio::sync::Mutex;
+
+    use crate::api::backend::publish_scheduled_posts;
+    use crate::api::backend::webhook::{proccess_webhook_deliveries, webhook_signature};
+    use crate::api::settings::Settings;
+    use crate::api::shared::visibility::PostVisibility;
+    use crate::api::shared::webhook::{
+        UserWebhook, UserWebhookDelivery, WEBHOOK_HEADER_DELIVERY, WEBHOOK_HEADER_EVENT,
+        WEBHOOK_HEADER_SIGNATURE, WebhookDeliveryStatus, WebhookErr, WebhookEvent,
+        webhook_retry_delay,
+    };
+    use crate::api::tests::ApiTestApp;
+    use crate::api::{Api, ServerErr, ServerRes, TimeRange};
+
+    impl ApiTestApp {
+        pub async fn add_webhook(
+            &self,
+            time: u128,
+            auth_token: impl AsRef<str>,
+            url: impl Into<String>,
+            events: Vec<WebhookEvent>,
+            admin: bool,
+        ) -> Result<UserWebhook, ServerErr> {
+            self.set_time(time).await;
+            let result = self
+                .api
+                .add_webhook(url, events, admin)
+                .send_native_with_token(auth_token)
+                .await;
+            match result {
+                Ok(ServerRes::Webhook(webhook)) => Ok(webhook),
+                Ok(res) => panic!("fix code, expected Webhook, got {res:?}"),
+                Err(err) => Err(err),
+            }
+        }
+
+        pub async fn get_webhook_deliveries(
+            &self,
+            time: u128,
+            auth_token: impl AsRef<str>,
+            webhook_key: impl Into<String>,
+        ) -> Result<Vec<UserWebhookDelivery>, ServerErr> {
+            self.set_time(time).await;
+            let result = self
+                .api
+                .get_webhook_deliveries(webhook_key, 100, TimeRange::None)
+                .send_native_with_token(auth_token)
+                .await;
+            match result {
+                Ok(ServerRes::WebhookDeliveries(deliveries)) => Ok(deliveries),
+                Ok(res) => panic!("fix code, expected WebhookDeliveries, got {res:?}"),
+                Err(err) => Err(err),
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct Receiver {
+        status: u16,
+        received: Vec<(HeaderMap, String)>,
+    }
+
+    /// a local endpoint that records every delivery and answers with whatever status is set
+    async fn spawn_receiver() -> (String, Arc<Mutex<Receiver>>) {
+        let receiver = Arc::new(Mutex::new(Receiver {
+            status: 200,
+            received: Vec::new(),
+        }));
+        let router = Router::new().route(
+            "/hook",
+            post({
+                let receiver = receiver.clone();
+                move |headers: HeaderMap, body: String| {
+                    let receiver = receiver.clone();
+                    async move {
+                        let mut receiver = receiver.lock().await;
+                        receiver.received.push((headers, body));
+                        StatusCode::from_u16(receiver.status).unwrap()
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        (url, receiver)
+    }
+
+    #[tokio::test]
+    async fn api_webhook_test() {
+        crate::init_test_log();
+
+        let mut settings = Settings::new_testing(1);
+        settings.auth.admin_emails = vec!["admin@heyadora.com".to_string()];
+        let app = ApiTestApp::new_with_settings(settings).await;
+        let (url, receiver) = spawn_receiver().await;
+
+        let admin_token = app
+            .register(0, "admin", "admin@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let auth_token = app
+            .register(1, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+
+        // validation
+        let result = app
+            .add_webhook(
+                2,
+                &auth_token,
+                "ftp://nope",
+                vec![WebhookEvent::PostCreated],
+                false,
+            )
+            .await;
+        assert_eq!(result, Err(WebhookErr::InvalidUrl.into()));
+        let result = app
+            .add_webhook(2, &auth_token, &url, Vec::new(), false)
+            .await;
+        assert_eq!(result, Err(WebhookErr::NoEvents.into()));
+        let result = app
+            .add_webhook(2, &auth_token, &url, vec![WebhookEvent::PostCreated], true)
+            .await;
+        assert_eq!(result, Err(WebhookErr::AdminForbidden.into()));
+        let result = app
+            .add_webhook(
+                2,
+                &auth_token,
+                &url,
+                vec![WebhookEvent::UserRegistered],
+                false,
+            )
+            .await;
+        assert_eq!(result, Err(WebhookErr::AdminEventForbidden.into()));
+
+        let webhook = app
+            .add_webhook(
+                2,
+                &auth_token,
+                &url,
+                vec![
+                    WebhookEvent::PostCreated,
+                    WebhookEvent::PostUpdated,
+                    WebhookEvent::CommentCreated,
+                    WebhookEvent::LikeCreated,
+                    WebhookEvent::PostCreated,
+                ],
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(webhook.events.len(), 4);
+        assert!(webhook.secret.starts_with("whsec_"));
+        let admin_webhook = app
+            .add_webhook(
+                2,
+                &admin_token,
+                &url,
+                vec![WebhookEvent::UserRegistered],
+                true,
+            )
+            .await
+            .unwrap();
+
+        // user hooks only hear about their own posts, admin hooks about the whole site
+        app.register(3, "hey2", "hey2@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        let post = app
+            .add_post(4, &auth_token, "title1", "cat", "one")
+            .await
+            .unwrap();
+        app.add_post(4, &admin_token, "title2", "cat", "one")
+            .await
+            .unwrap();
+        app.add_post_comment(5, &admin_token, &post.key, None, "nice")
+            .await
+            .unwrap();
+        app.add_post_like(6, &admin_token, &post.key).await.unwrap();
+        assert!(receiver.lock().await.received.is_empty());
+
+        app.set_time(7).await;
+        proccess_webhook_deliveries(&app.state).await.unwrap();
+        {
+            let receiver = receiver.lock().await;
+            // deliveries are sent concurrently so they can arrive in any order
+            let mut events = receiver
+                .received
+                .iter()
+                .map(|(headers, _)| headers[WEBHOOK_HEADER_EVENT].to_str().unwrap())
+                .collect::<Vec<_>>();
+            events.sort();
+            assert_eq!(
+                events,
+                vec![
+                    "comment.created",
+                    "like.created",
+                    "post.created",
+                    "user.registered"
+                ]
+            );
+            for (headers, body) in &receiver.received {
+                let secret = if headers[WEBHOOK_HEADER_EVENT] == "user.registered" {
+                    &admin_webhook.secret
+                } else {
+                    &webhook.secret
+                };
+                assert_eq!(
+                    headers[WEBHOOK_HEADER_SIGNATURE].to_str().unwrap(),
+                    webhook_signature(secret, body)
+                );
+                assert!(headers.contains_key(WEBHOOK_HEADER_DELIVERY));
+            }
+            let (_, body) = receiver
+                .received
+                .iter()
+                .find(|(headers, _)| headers[WEBHOOK_HEADER_EVENT] == "post.created")
+                .unwrap();
+            let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+            assert_eq!(payload["event"], "post.created");
+            assert_eq!(payload["data"]["post"]["key"], post.key.as_str());
+            assert_eq!(payload["data"]["post"]["username"], "hey");
+        }
+
+        // failed deliveries stay queued and back off
+        receiver.lock().await.status = 500;
+        app.api
+            .update_post_title(&post.key, "title2")
+            .send_native_with_token(&auth_token)
+            .await
+            .unwrap();
+        proccess_webhook_deliveries(&app.state).await.unwrap();
+        let deliveries = app
+            .get_webhook_deliveries(7, &auth_token, &webhook.key)
+            .await
+            .unwrap();
+        assert_eq!(deliveries[0].event, "post.updated");
+        assert_eq!(deliveries[0].status, WebhookDeliveryStatus::Pending);
+        assert_eq!(deliveries[0].attempts, 1);
+        assert_eq!(deliveries[0].response_code, 500);
+        assert_eq!(deliveries[0].response, "");
+        let next_attempt_at = deliveries[0].next_attempt_at;
+        assert_eq!(next_attempt_at, 7 + webhook_retry_delay(1));
+
+        proccess_webhook_deliveries(&app.state).await.unwrap();
+        assert_eq!(receiver.lock().await.received.len(), 5);
+
+        receiver.lock().await.status = 200;
+        app.set_time(next_attempt_at).await;
+        proccess_webhook_deliveries(&app.state).await.unwrap();
+        assert_eq!(receiver.lock().await.received.len(), 6);
+        let deliveries = app
+            .get_webhook_deliveries(next_attempt_at, &auth_token, &webhook.key)
+            .await
+            .unwrap();
+        assert_eq!(deliveries.len(), 4);
+        assert_eq!(deliveries[0].status, WebhookDeliveryStatus::Success);
+        assert_eq!(deliveries[0].attempts, 2);
+        assert_eq!(deliveries[0].response_code, 200);
+        assert_eq!(deliveries[0].next_attempt_at, 0);
+
+        // only the owner sees the log or deletes the webhook
+        let result = app
+            .get_webhook_deliveries(next_attempt_at, &admin_token, &webhook.key)
+            .await;
+        assert_eq!(result, Err(WebhookErr::NotFound.into()));
+        let result = app
+            .api
+            .delete_webhook(&webhook.key)
+            .send_native_with_token(&admin_token)
+            .await;
+        assert_eq!(result, Err(WebhookErr::NotFound.into()));
+        let result = app
+            .api
+            .delete_webhook(&webhook.key)
+            .send_native_with_token(&auth_token)
+            .await;
+        assert_eq!(result, Ok(ServerRes::Ok));
+        let result = app
+            .api
+            .get_webhooks()
+            .send_native_with_token(&auth_token)
+            .await;
+        assert_eq!(result, Ok(ServerRes::Webhooks(Vec::new())));
+    }
+
+    #[tokio::test]
+    async fn api_webhook_post_published_test() {
+        crate::init_test_log();
+
+        let app = ApiTestApp::new(1).await;
+        let (url, receiver) = spawn_receiver().await;
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        app.add_webhook(1, &auth_token, &url, vec![WebhookEvent::PostCreated], false)
+            .await
+            .unwrap();
+
+        // drafts and scheduled posts are announced once they are published
+        let draft = app
+            .add_post_visibility(2, &auth_token, "draft", PostVisibility::Draft, 0)
+            .await
+            .unwrap();
+        let scheduled = app
+            .add_post_visibility(2, &auth_token, "scheduled", PostVisibility::Scheduled, 10)
+            .await
+            .unwrap();
+        proccess_webhook_deliveries(&app.state).await.unwrap();
+        assert!(receiver.lock().await.received.is_empty());
+
+        app.update_post_visibility(3, &auth_token, &draft.key, PostVisibility::Public, 0)
+            .await
+            .unwrap();
+        app.set_time(10).await;
+        publish_scheduled_posts(&app.state).await.unwrap();
+        proccess_webhook_deliveries(&app.state).await.unwrap();
+
+        let receiver = receiver.lock().await;
+        let mut keys = receiver
+            .received
+            .iter()
+            .map(|(headers, body)| {
+                assert_eq!(headers[WEBHOOK_HEADER_EVENT], "post.created");
+                let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+                payload["data"]["post"]["key"].as_str().unwrap().to_string()
+            })
+            .collect::<Vec<_>>();
+        keys.sort();
+        let mut expected = vec![draft.key, scheduled.key];
+        expected.sort();
+        assert_eq!(keys, expected);
+    }
+
+    #[tokio::test]
+    async fn api_webhook_private_url_test() {
+        crate::init_test_log();
+
+        let mut settings = Settings::new_testing(1);
+        settings.site.allow_private_outbound = false;
+        let app = ApiTestApp::new_with_settings(settings).await;
+        let (url, _receiver) = spawn_receiver().await;
+
+        let auth_token = app
+            .register(0, "hey", "hey@heyadora.com", "pas$word123456789")
+            .await
+            .unwrap();
+        for url in [
+            url.as_str(),
+            "http://localhost/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/hook",
+        ] {
+            let result = app
+                .add_webhook(1, &auth_token, url, vec![WebhookEvent::PostCreated], false)
+                .await;
+            assert_eq!(result, Err(WebhookErr::InvalidUrl.into()), "{url}");
+        }
+    }
+}
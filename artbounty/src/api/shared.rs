@@ -1 +1,17 @@
+pub mod account;
+pub mod admin;
+pub mod api_token;
+pub mod audit;
+pub mod collection;
+pub mod conversation;
+pub mod cursor;
+pub mod live;
+pub mod notification;
 pub mod post_comment;
+pub mod rating;
+pub mod report;
+pub mod revision;
+pub mod trash;
+pub mod user_block;
+pub mod visibility;
+pub mod webhook;
@@ -0,0 +1,177 @@
+use std::str::FromStr;
+
+/// confirmed deletions wait this long before the account is erased, the user can cancel until then
+pub const ACCOUNT_DELETION_GRACE_NS: u128 = 14 * 24 * 60 * 60 * 1_000_000_000;
+
+/// finished exports can be downloaded for this long
+pub const DATA_EXPORT_RETENTION_NS: u128 = 7 * 24 * 60 * 60 * 1_000_000_000;
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIter,
+    strum::EnumIs,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum AccountDeletionMode {
+    /// posts and comments stay up under a placeholder name
+    #[default]
+    Anonymize,
+    /// posts, comments and messages are deleted along with the account
+    Delete,
+}
+
+impl AccountDeletionMode {
+    pub fn from_db(mode: impl AsRef<str>) -> Self {
+        Self::from_str(mode.as_ref()).unwrap_or_default()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AccountDeletionMode::Anonymize => "Keep my posts and comments, but anonymize them",
+            AccountDeletionMode::Delete => "Delete my posts, comments and messages",
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserAccountDeletion {
+    pub mode: AccountDeletionMode,
+    /// false until the link from the confirmation email is opened
+    pub confirmed: bool,
+    /// 0 until confirmed
+    pub delete_at: u128,
+    pub created_at: u128,
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::account::DBAccountDeletion> for UserAccountDeletion {
+    fn from(value: crate::db::account::DBAccountDeletion) -> Self {
+        Self {
+            mode: AccountDeletionMode::from_db(&value.mode),
+            confirmed: value.confirmed,
+            delete_at: value.delete_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIs,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum DataExportStatus {
+    #[default]
+    Pending,
+    Ready,
+    Failed,
+}
+
+impl DataExportStatus {
+    pub fn from_db(status: impl AsRef<str>) -> Self {
+        Self::from_str(status.as_ref()).unwrap_or_default()
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserDataExport {
+    pub key: String,
+    pub status: DataExportStatus,
+    pub size_bytes: u64,
+    /// 0 until the export is ready
+    pub expires: u128,
+    pub created_at: u128,
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::account::DBDataExport> for UserDataExport {
+    fn from(value: crate::db::account::DBDataExport) -> Self {
+        use surrealdb::types::ToSql;
+
+        Self {
+            key: value.id.key.to_sql(),
+            status: DataExportStatus::from_db(&value.status),
+            size_bytes: value.size_bytes,
+            expires: value.expires,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(
+    thiserror::Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum AccountDeletionErr {
+    #[error("account deletion not found or expired")]
+    NotFound,
+
+    #[error("wrong password")]
+    WrongPassword,
+}
+
+#[derive(
+    thiserror::Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum DataExportErr {
+    #[error("data export not found or expired")]
+    NotFound,
+
+    #[error("an export is already being prepared")]
+    AlreadyPending,
+}
@@ -0,0 +1,138 @@
+use std::str::FromStr;
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIter,
+    strum::EnumIs,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum UserRole {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    HidePost,
+    DeleteComment,
+    BanUser,
+    ManageUser,
+    ResolveReport,
+    SuspendUser,
+    ViewAudit,
+}
+
+impl UserRole {
+    /// unknown roles fall back to a regular user
+    pub fn from_db(role: impl AsRef<str>) -> Self {
+        Self::from_str(role.as_ref()).unwrap_or_default()
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        match self {
+            UserRole::Admin => true,
+            UserRole::Moderator => {
+                matches!(
+                    permission,
+                    Permission::HidePost
+                        | Permission::DeleteComment
+                        | Permission::ResolveReport
+                        | Permission::SuspendUser
+                )
+            }
+            UserRole::User => false,
+        }
+    }
+
+    pub fn is_staff(&self) -> bool {
+        !self.is_user()
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct AdminUser {
+    pub key: String,
+    pub username: String,
+    /// only sent to roles that can manage users
+    pub email: Option<String>,
+    pub role: UserRole,
+    pub banned: bool,
+    pub suspended_until: u128,
+    pub used_storage_bytes: u64,
+    pub max_storage_bytes: u64,
+    pub max_storage_per_file_bytes: u64,
+    pub created_at: u128,
+}
+
+#[derive(
+    thiserror::Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum AdminErr {
+    #[error("missing permission")]
+    Forbidden,
+
+    #[error("user \"{0}\" not found")]
+    UserNotFound(String),
+
+    #[error("post not found")]
+    PostNotFound,
+
+    #[error("comment not found")]
+    CommentNotFound,
+
+    #[error("can't change your own account")]
+    SelfTarget,
+
+    #[error("per file storage can't be bigger than total storage")]
+    InvalidStorage,
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::DBUser> for AdminUser {
+    fn from(value: crate::db::DBUser) -> Self {
+        use surrealdb::types::ToSql;
+
+        Self {
+            key: value.id.key.to_sql(),
+            role: UserRole::from_db(&value.role),
+            username: value.username,
+            email: Some(value.email),
+            banned: value.banned,
+            suspended_until: value.suspended_until,
+            used_storage_bytes: value.used_storage_bytes as u64,
+            max_storage_bytes: value.max_storage_bytes as u64,
+            max_storage_per_file_bytes: value.max_storage_per_file_bytes as u64,
+            created_at: value.created_at,
+        }
+    }
+}
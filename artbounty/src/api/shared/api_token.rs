@@ -0,0 +1,136 @@
+use std::str::FromStr;
+
+/// every personal access token starts with this, the native client uses it to tell them apart from session tokens
+pub const API_TOKEN_PREFIX: &str = "abt_";
+
+/// random part of a token, after the prefix
+pub const API_TOKEN_SECRET_LENGTH: usize = 40;
+
+pub const API_TOKENS_MAX: usize = 20;
+
+pub const API_TOKEN_NAME_MAX_LENGTH: usize = 64;
+
+/// last used time is only written once per this window so busy scripts don't write on every request
+pub const API_TOKEN_LAST_USED_RESOLUTION_NS: u128 = 60 * 1_000_000_000;
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIter,
+    strum::EnumIs,
+)]
+pub enum ApiTokenScope {
+    /// everything a logged in user can see
+    #[default]
+    #[strum(serialize = "read")]
+    Read,
+    /// create, edit and delete own posts
+    #[strum(serialize = "post:write")]
+    PostWrite,
+    /// comment on and like posts
+    #[strum(serialize = "comment:write")]
+    CommentWrite,
+    /// moderation endpoints, only staff can create tokens with it
+    #[strum(serialize = "admin")]
+    Admin,
+}
+
+impl ApiTokenScope {
+    /// unknown scopes are dropped so a removed scope never grants anything
+    pub fn from_db(scope: impl AsRef<str>) -> Option<Self> {
+        Self::from_str(scope.as_ref()).ok()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ApiTokenScope::Read => "Read posts, comments, notifications and your account",
+            ApiTokenScope::PostWrite => "Create, edit and delete your posts",
+            ApiTokenScope::CommentWrite => "Comment on and like posts",
+            ApiTokenScope::Admin => "Moderation",
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserApiToken {
+    pub key: String,
+    pub name: String,
+    pub scopes: Vec<ApiTokenScope>,
+    /// 0 if the token never expires
+    pub expires: u128,
+    /// 0 if never used
+    pub last_used_at: u128,
+    pub created_at: u128,
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::api_token::DBApiToken> for UserApiToken {
+    fn from(value: crate::db::api_token::DBApiToken) -> Self {
+        use surrealdb::types::ToSql;
+
+        Self {
+            key: value.id.key.to_sql(),
+            name: value.name,
+            scopes: value
+                .scopes
+                .iter()
+                .filter_map(ApiTokenScope::from_db)
+                .collect(),
+            expires: value.expires,
+            last_used_at: value.last_used_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(
+    thiserror::Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum ApiTokenErr {
+    #[error("api token not found")]
+    NotFound,
+
+    #[error("name must be between 1 and {API_TOKEN_NAME_MAX_LENGTH} characters")]
+    InvalidName,
+
+    #[error("pick at least one scope")]
+    NoScopes,
+
+    #[error("only staff can create tokens with the admin scope")]
+    AdminScopeForbidden,
+
+    #[error("expiry has to be in the future")]
+    InvalidExpiry,
+
+    #[error("you can have at most {API_TOKENS_MAX} tokens")]
+    TooMany,
+}
@@ -0,0 +1,119 @@
+use std::str::FromStr;
+
+pub const AUDIT_EVENTS_FETCH_MAX: usize = 100;
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIter,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum AuditKind {
+    Login,
+    LoginFailed,
+    UsernameChange,
+    PasswordChange,
+    EmailChange,
+    RoleChange,
+    BanChange,
+    SuspendChange,
+    StorageChange,
+    PostShowChange,
+    PostRatingChange,
+    CommentDelete,
+    ReportResolve,
+    DataExport,
+    AccountDeletionSchedule,
+    AccountDeletionCancel,
+    ApiTokenAdd,
+    ApiTokenDelete,
+    WebhookAdd,
+    WebhookDelete,
+    #[default]
+    Other,
+}
+
+impl AuditKind {
+    /// unknown kinds fall back to other
+    pub fn from_db(kind: impl AsRef<str>) -> Self {
+        Self::from_str(kind.as_ref()).unwrap_or_default()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditKind::Login => "Logged in",
+            AuditKind::LoginFailed => "Failed login",
+            AuditKind::UsernameChange => "Username changed",
+            AuditKind::PasswordChange => "Password changed",
+            AuditKind::EmailChange => "Email changed",
+            AuditKind::RoleChange => "Role changed",
+            AuditKind::BanChange => "Ban changed",
+            AuditKind::SuspendChange => "Suspension changed",
+            AuditKind::StorageChange => "Storage changed",
+            AuditKind::PostShowChange => "Post visibility changed",
+            AuditKind::PostRatingChange => "Post rating changed",
+            AuditKind::CommentDelete => "Comment deleted",
+            AuditKind::ReportResolve => "Report resolved",
+            AuditKind::DataExport => "Data export requested",
+            AuditKind::AccountDeletionSchedule => "Account deletion scheduled",
+            AuditKind::AccountDeletionCancel => "Account deletion cancelled",
+            AuditKind::ApiTokenAdd => "API token created",
+            AuditKind::ApiTokenDelete => "API token deleted",
+            AuditKind::WebhookAdd => "Webhook created",
+            AuditKind::WebhookDelete => "Webhook deleted",
+            AuditKind::Other => "Other",
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserAuditEvent {
+    pub key: String,
+    pub kind: AuditKind,
+    /// none for anonymous actions like failed logins or password resets by email
+    pub actor_username: Option<String>,
+    pub target_username: Option<String>,
+    pub ip: String,
+    pub user_agent: String,
+    pub before: String,
+    pub after: String,
+    pub created_at: u128,
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::audit::DBAuditEvent> for UserAuditEvent {
+    fn from(value: crate::db::audit::DBAuditEvent) -> Self {
+        use surrealdb::types::ToSql;
+
+        Self {
+            key: value.id.key.to_sql(),
+            kind: AuditKind::from_db(&value.kind),
+            actor_username: value.actor_username,
+            target_username: value.target_username,
+            ip: value.ip,
+            user_agent: value.user_agent,
+            before: value.before,
+            after: value.after,
+            created_at: value.created_at,
+        }
+    }
+}
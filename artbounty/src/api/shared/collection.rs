@@ -0,0 +1,69 @@
+use crate::api::User;
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserCollection {
+    pub key: String,
+    pub user: User,
+    pub title: String,
+    pub private: bool,
+    pub items_count: u64,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(
+    thiserror::Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum CollectionErr {
+    #[error("collection not found")]
+    NotFound,
+
+    #[error("post \"{0}\" not found")]
+    PostNotFound(String),
+
+    #[error("post is already in the collection")]
+    Duplicate,
+
+    #[error("post is not in the collection")]
+    ItemNotFound,
+
+    #[error("position {0} is out of range")]
+    OutOfRange(usize),
+
+    #[error("invalid title {0}")]
+    InvalidTitle(String),
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::collection::DBCollection> for UserCollection {
+    fn from(value: crate::db::collection::DBCollection) -> Self {
+        use surrealdb::types::ToSql;
+
+        Self {
+            key: value.id.key.to_sql(),
+            user: value.user.into(),
+            title: value.title,
+            private: value.private,
+            items_count: value.items_count,
+            modified_at: value.modified_at,
+            created_at: value.created_at,
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use crate::api::{User, UserPostFile};
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserConversation {
+    pub key: String,
+    pub peer: User,
+    pub read_at: u128,
+    pub blocked: bool,
+    pub unread: bool,
+    pub last_message_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserMessage {
+    pub key: String,
+    pub conversation_key: String,
+    pub user: User,
+    pub text: String,
+    pub file: Vec<UserPostFile>,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(
+    thiserror::Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum ConversationErr {
+    #[error("user \"{0}\" not found")]
+    UserNotFound(String),
+
+    #[error("cant message yourself")]
+    MessageSelf,
+
+    #[error("conversation not found")]
+    NotFound,
+
+    #[error("conversation is blocked")]
+    Blocked,
+
+    #[error("invalid text {0}")]
+    InvalidText(String),
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::conversation::DBConversationView> for UserConversation {
+    fn from(value: crate::db::conversation::DBConversationView) -> Self {
+        use surrealdb::types::ToSql;
+
+        Self {
+            key: value.id.key.to_sql(),
+            peer: value.peer.into(),
+            read_at: value.read_at,
+            blocked: value.blocked,
+            unread: value.unread,
+            last_message_at: value.last_message_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::conversation::DBMessage> for UserMessage {
+    fn from(value: crate::db::conversation::DBMessage) -> Self {
+        use surrealdb::types::ToSql;
+
+        Self {
+            key: value.id.key.to_sql(),
+            conversation_key: value.conversation.key.to_sql(),
+            user: value.user.into(),
+            text: value.text,
+            file: value.file.into_iter().map(UserPostFile::from).collect(),
+            modified_at: value.modified_at,
+            created_at: value.created_at,
+        }
+    }
+}
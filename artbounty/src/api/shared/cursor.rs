@@ -0,0 +1,200 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::api::{UserPost, shared::post_comment::UserPostComment};
+
+/// bigger limits are capped to this, for posts and comments alike
+pub const PAGE_LIMIT_MAX: usize = 100;
+
+/// encoded cursors longer than this are rejected before decoding
+pub const CURSOR_MAX_LENGTH: usize = 256;
+
+/// position of an item in a listing, items created in the same nanosecond are told apart by
+/// their key so a page boundary never skips or repeats them. sent as an opaque string in json.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+#[serde(into = "String", try_from = "String")]
+pub struct Cursor {
+    pub created_at: u128,
+    /// empty sorts before every key
+    pub key: String,
+}
+
+impl Cursor {
+    pub fn new(created_at: u128, key: impl Into<String>) -> Self {
+        Self {
+            created_at,
+            key: key.into(),
+        }
+    }
+
+    /// sits before every item created at `time`
+    pub fn at(time: u128) -> Self {
+        Self {
+            created_at: time,
+            key: String::new(),
+        }
+    }
+}
+
+impl Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for byte in format!("{}:{}", self.created_at, self.key).bytes() {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = CursorErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() > CURSOR_MAX_LENGTH
+            || s.len() % 2 != 0
+            || !s.bytes().all(|v| v.is_ascii_hexdigit())
+        {
+            return Err(CursorErr::Invalid);
+        }
+        let bytes = (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| CursorErr::Invalid)?;
+        let decoded = String::from_utf8(bytes).map_err(|_| CursorErr::Invalid)?;
+        let (created_at, key) = decoded.split_once(':').ok_or(CursorErr::Invalid)?;
+        let created_at = created_at.parse::<u128>().map_err(|_| CursorErr::Invalid)?;
+
+        Ok(Self::new(created_at, key))
+    }
+}
+
+impl From<Cursor> for String {
+    fn from(value: Cursor) -> Self {
+        value.to_string()
+    }
+}
+
+impl TryFrom<String> for Cursor {
+    type Error = CursorErr;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from_str(&value)
+    }
+}
+
+impl From<&UserPost> for Cursor {
+    fn from(value: &UserPost) -> Self {
+        Self::new(value.created_at, value.key.clone())
+    }
+}
+
+impl From<&UserPostComment> for Cursor {
+    fn from(value: &UserPostComment) -> Self {
+        Self::new(value.created_at, value.key.clone())
+    }
+}
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::EnumIs,
+)]
+pub enum CursorRange {
+    #[default]
+    None,
+    /// items that come before the cursor, ordered by created_at then key
+    Before(Cursor),
+    /// items that come after the cursor, ordered by created_at then key
+    After(Cursor),
+}
+
+/// zero is rejected, anything above PAGE_LIMIT_MAX is capped
+pub fn page_limit(limit: usize) -> Result<usize, CursorErr> {
+    if limit == 0 {
+        return Err(CursorErr::InvalidLimit);
+    }
+    Ok(limit.min(PAGE_LIMIT_MAX))
+}
+
+/// cursors around a page that's already in the requested order, next continues after the
+/// last item and is none once a page comes back short, prev walks back from the first item
+pub fn page_cursors<T>(items: &[T], limit: usize) -> (Option<Cursor>, Option<Cursor>)
+where
+    for<'a> &'a T: Into<Cursor>,
+{
+    let next: Option<Cursor> = if items.len() >= limit {
+        items.last().map(Into::into)
+    } else {
+        None
+    };
+    let prev: Option<Cursor> = items.first().map(Into::into);
+    (next, prev)
+}
+
+#[derive(
+    thiserror::Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum CursorErr {
+    #[error("invalid cursor")]
+    Invalid,
+
+    #[error("limit must be at least 1")]
+    InvalidLimit,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cursor, CursorErr};
+
+    #[test]
+    fn cursor_encoding() {
+        let cursor = Cursor::new(1234, "abc");
+        let encoded = cursor.to_string();
+        assert!(!encoded.contains("abc"));
+        assert_eq!(encoded.parse::<Cursor>(), Ok(cursor.clone()));
+
+        let json = serde_json::to_string(&cursor).unwrap();
+        assert_eq!(json, format!("\"{encoded}\""));
+        assert_eq!(serde_json::from_str::<Cursor>(&json).unwrap(), cursor);
+
+        assert_eq!(
+            Cursor::at(5).to_string().parse::<Cursor>(),
+            Ok(Cursor::at(5))
+        );
+        assert!(Cursor::at(5) < Cursor::new(5, "a"));
+        assert!(Cursor::new(5, "b") < Cursor::new(6, "a"));
+
+        assert_eq!("zz".parse::<Cursor>(), Err(CursorErr::Invalid));
+        assert_eq!("abc".parse::<Cursor>(), Err(CursorErr::Invalid));
+        assert_eq!("616263".parse::<Cursor>(), Err(CursorErr::Invalid));
+        assert!(serde_json::from_str::<Cursor>("\"zz\"").is_err());
+    }
+}
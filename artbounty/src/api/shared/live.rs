@@ -0,0 +1,46 @@
+use crate::api::shared::post_comment::UserPostComment;
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum LiveEvent {
+    CommentAdded(UserPostComment),
+    CommentUpdated(UserPostComment),
+    CommentDeleted {
+        post_key: String,
+        comment_key: String,
+        parent_key: Vec<String>,
+    },
+    PostLikeCount {
+        post_key: String,
+        count: u64,
+    },
+}
+
+impl LiveEvent {
+    pub fn post_key(&self) -> &str {
+        match self {
+            LiveEvent::CommentAdded(comment) | LiveEvent::CommentUpdated(comment) => {
+                &comment.post_key
+            }
+            LiveEvent::CommentDeleted { post_key, .. }
+            | LiveEvent::PostLikeCount { post_key, .. } => post_key,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, rkyv::rancor::Error> {
+        rkyv::to_bytes::<rkyv::rancor::Error>(self).map(|bytes| bytes.to_vec())
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, rkyv::rancor::Error> {
+        let archived = rkyv::access::<ArchivedLiveEvent, rkyv::rancor::Error>(bytes)?;
+        rkyv::deserialize::<LiveEvent, rkyv::rancor::Error>(archived)
+    }
+}
@@ -0,0 +1,81 @@
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIter,
+    strum::EnumIs,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum NotificationKind {
+    #[default]
+    PostLike,
+    PostComment,
+    CommentReply,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserNotification {
+    pub key: String,
+    pub kind: NotificationKind,
+    pub post_key: String,
+    pub post_title: String,
+    pub post_username: String,
+    pub actors: Vec<String>,
+    pub read: bool,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+impl UserNotification {
+    pub fn text(&self) -> String {
+        let who = match self.actors.as_slice() {
+            [] => "someone".to_string(),
+            [one] => one.clone(),
+            [one, two] => format!("{one} and {two}"),
+            actors => format!("{} people", actors.len()),
+        };
+        let what = match self.kind {
+            NotificationKind::PostLike => "liked your post",
+            NotificationKind::PostComment => "commented on your post",
+            NotificationKind::CommentReply => "replied to your comment on",
+        };
+        format!("{who} {what} \"{}\"", self.post_title)
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::notification::DBNotificationView> for UserNotification {
+    fn from(value: crate::db::notification::DBNotificationView) -> Self {
+        use std::str::FromStr;
+        use surrealdb::types::ToSql;
+
+        Self {
+            key: value.id.key.to_sql(),
+            kind: NotificationKind::from_str(&value.kind).unwrap_or_default(),
+            post_key: value.post.key.to_sql(),
+            post_title: value.post_title,
+            post_username: value.post_username,
+            actors: value.actor_usernames,
+            read: value.read,
+            modified_at: value.modified_at,
+            created_at: value.created_at,
+        }
+    }
+}
@@ -17,10 +17,25 @@ pub struct UserPostComment {
     pub parent_key: Vec<String>,
     pub text: String,
     pub replies_count: usize,
+    /// 0 if the text was never changed
+    pub edited_at: u128,
+    /// 0 unless the comment is in its authors trash
+    pub deleted_at: u128,
     pub modified_at: u128,
     pub created_at: u128,
 }
 
+impl UserPostComment {
+    /// trashed comments are only shown as a placeholder to keep the thread together
+    pub fn redacted(mut self) -> Self {
+        if self.deleted_at > 0 {
+            self.text = String::new();
+            self.edited_at = 0;
+        }
+        self
+    }
+}
+
 #[derive(
     thiserror::Error,
     Debug,
@@ -73,6 +88,8 @@ impl From<crate::db::post_comment::DBPostComment> for UserPostComment {
             parent_key: value.parent.into_iter().map(|v| v.key.to_sql()).collect(),
             text: value.text,
             replies_count: value.replies_count,
+            edited_at: value.edited_at,
+            deleted_at: value.deleted_at,
             modified_at: value.modified_at,
             created_at: value.created_at,
         }
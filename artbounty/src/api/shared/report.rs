@@ -0,0 +1,185 @@
+use std::str::FromStr;
+
+use crate::api::User;
+
+pub const REPORT_TEXT_MAX_LEN: usize = 1000;
+pub const REPORT_SUSPEND_MAX_DAYS: u32 = 365;
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIter,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum ReportReason {
+    StolenArt,
+    Harassment,
+    Illegal,
+    Spam,
+    #[default]
+    Other,
+}
+
+impl ReportReason {
+    /// unknown reasons fall back to other
+    pub fn from_db(reason: impl AsRef<str>) -> Self {
+        Self::from_str(reason.as_ref()).unwrap_or_default()
+    }
+
+    /// the moderation queue is ordered by this first, then by age
+    pub fn severity(&self) -> u64 {
+        match self {
+            ReportReason::Illegal => 3,
+            ReportReason::Harassment => 2,
+            ReportReason::StolenArt => 2,
+            ReportReason::Spam => 1,
+            ReportReason::Other => 0,
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum ReportTarget {
+    Post { post_key: String },
+    Comment { comment_key: String },
+    User { username: String },
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::Display,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum ReportAction {
+    Dismiss,
+    HideContent,
+    WarnUser,
+    SuspendUser { days: u32 },
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserReport {
+    pub key: String,
+    pub reporter: User,
+    pub target_user: User,
+    pub target: ReportTarget,
+    /// post of the reported post or comment, used for linking
+    pub post_key: Option<String>,
+    pub post_username: Option<String>,
+    pub reason: ReportReason,
+    pub severity: u64,
+    pub text: String,
+    pub resolved: bool,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(
+    thiserror::Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum ReportErr {
+    #[error("report not found")]
+    NotFound,
+
+    #[error("reported content not found")]
+    TargetNotFound,
+
+    #[error("can't report yourself")]
+    SelfReport,
+
+    #[error("you already reported this")]
+    AlreadyReported,
+
+    #[error("report is already resolved")]
+    AlreadyResolved,
+
+    #[error("report text is too long, max {0} characters")]
+    TextTooLong(usize),
+
+    #[error("moderation reason is required")]
+    MissingReason,
+
+    #[error("there is no content to hide on a user report")]
+    NothingToHide,
+
+    #[error("suspension has to be between 1 and {0} days")]
+    InvalidSuspension(u32),
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::report::DBReport> for UserReport {
+    fn from(value: crate::db::report::DBReport) -> Self {
+        use surrealdb::types::ToSql;
+
+        let target_key = value.target.key.to_sql();
+        let target = match value.target_kind.as_str() {
+            "post" => ReportTarget::Post {
+                post_key: target_key,
+            },
+            "comment" => ReportTarget::Comment {
+                comment_key: target_key,
+            },
+            _ => ReportTarget::User {
+                username: value.target_user.username.clone(),
+            },
+        };
+
+        Self {
+            key: value.id.key.to_sql(),
+            reporter: value.reporter.into(),
+            target_user: value.target_user.into(),
+            target,
+            post_key: value.post.map(|v| v.key.to_sql()),
+            post_username: value.post_username,
+            reason: ReportReason::from_db(&value.reason),
+            severity: value.severity,
+            text: value.text,
+            resolved: value.resolved,
+            modified_at: value.modified_at,
+            created_at: value.created_at,
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::str::FromStr;
+
+use crate::api::User;
+
+pub const REVISIONS_FETCH_MAX: usize = 50;
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum RevisionTarget {
+    Post { post_key: String },
+    Comment { comment_key: String },
+}
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIs,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum RevisionField {
+    Title,
+    Description,
+    Tags,
+    #[default]
+    Text,
+}
+
+impl RevisionField {
+    pub fn from_db(field: impl AsRef<str>) -> Self {
+        Self::from_str(field.as_ref()).unwrap_or_default()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RevisionField::Title => "Title",
+            RevisionField::Description => "Description",
+            RevisionField::Tags => "Tags",
+            RevisionField::Text => "Text",
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserRevision {
+    pub key: String,
+    pub user: User,
+    pub field: RevisionField,
+    pub before: String,
+    pub after: String,
+    pub created_at: u128,
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::revision::DBRevision> for UserRevision {
+    fn from(value: crate::db::revision::DBRevision) -> Self {
+        use surrealdb::types::ToSql;
+
+        Self {
+            key: value.id.key.to_sql(),
+            user: value.user.into(),
+            field: RevisionField::from_db(&value.field),
+            before: value.before,
+            after: value.after,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(
+    thiserror::Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum RevisionErr {
+    #[error("not found")]
+    NotFound,
+
+    #[error("only the author and moderators can see the edit history")]
+    Forbidden,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffPart {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+/// word level diff, whitespace sticks to the word before it
+pub fn diff_words(before: &str, after: &str) -> Vec<DiffPart> {
+    let a = before
+        .split_inclusive(char::is_whitespace)
+        .collect::<Vec<&str>>();
+    let b = after
+        .split_inclusive(char::is_whitespace)
+        .collect::<Vec<&str>>();
+
+    // lcs[i][j] is the longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0_u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut output = Vec::<DiffPart>::new();
+    let mut push = |part: DiffPart| match (output.last_mut(), part) {
+        (Some(DiffPart::Same(last)), DiffPart::Same(v))
+        | (Some(DiffPart::Added(last)), DiffPart::Added(v))
+        | (Some(DiffPart::Removed(last)), DiffPart::Removed(v)) => last.push_str(&v),
+        (_, part) => output.push(part),
+    };
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            push(DiffPart::Same(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            push(DiffPart::Removed(a[i].to_string()));
+            i += 1;
+        } else {
+            push(DiffPart::Added(b[j].to_string()));
+            j += 1;
+        }
+    }
+    for v in &a[i..] {
+        push(DiffPart::Removed(v.to_string()));
+    }
+    for v in &b[j..] {
+        push(DiffPart::Added(v.to_string()));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::shared::revision::{DiffPart, diff_words};
+
+    #[test]
+    fn diff_words_test() {
+        assert_eq!(diff_words("", ""), vec![]);
+        assert_eq!(
+            diff_words("", "hello"),
+            vec![DiffPart::Added("hello".to_string())]
+        );
+        assert_eq!(
+            diff_words("hello world", "hello world"),
+            vec![DiffPart::Same("hello world".to_string())]
+        );
+        assert_eq!(
+            diff_words("the quick brown fox", "the slow brown fox jumps"),
+            vec![
+                DiffPart::Same("the ".to_string()),
+                DiffPart::Removed("quick ".to_string()),
+                DiffPart::Added("slow ".to_string()),
+                DiffPart::Same("brown ".to_string()),
+                DiffPart::Removed("fox".to_string()),
+                DiffPart::Added("fox jumps".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use crate::api::UserPost;
+use crate::api::shared::post_comment::UserPostComment;
+
+/// trashed posts and comments can be restored for this long, after that the purge deletes them for real
+pub const TRASH_RETENTION_NS: u128 = 30 * 24 * 60 * 60 * 1_000_000_000;
+
+/// shown in place of a trashed comment that still has replies
+pub const DELETED_COMMENT_TEXT: &str = "[deleted]";
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserTrash {
+    pub posts: Vec<UserPost>,
+    pub comments: Vec<UserPostComment>,
+}
+
+#[derive(
+    thiserror::Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum TrashErr {
+    #[error("not found in trash")]
+    NotFound,
+}
@@ -0,0 +1,93 @@
+use std::str::FromStr;
+
+use crate::api::User;
+
+pub const USER_BLOCKS_FETCH_MAX: usize = 100;
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIter,
+    strum::EnumIs,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum UserBlockKind {
+    /// hides the user and stops them from commenting, replying or liking
+    #[default]
+    Block,
+    /// only hides the user from your galleries and comment threads
+    Mute,
+}
+
+impl UserBlockKind {
+    /// unknown kinds fall back to a block
+    pub fn from_db(kind: impl AsRef<str>) -> Self {
+        Self::from_str(kind.as_ref()).unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<UserBlockKind> for crate::db::user_block::DBUserBlockKind {
+    fn from(value: UserBlockKind) -> Self {
+        match value {
+            UserBlockKind::Block => Self::Block,
+            UserBlockKind::Mute => Self::Mute,
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserBlock {
+    pub user: User,
+    pub kind: UserBlockKind,
+    pub created_at: u128,
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::user_block::DBUserBlockUser> for UserBlock {
+    fn from(value: crate::db::user_block::DBUserBlockUser) -> Self {
+        Self {
+            user: value.user.into(),
+            kind: UserBlockKind::from_db(&value.kind),
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(
+    thiserror::Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum UserBlockErr {
+    #[error("user \"{0}\" not found")]
+    UserNotFound(String),
+
+    #[error("can't block yourself")]
+    BlockSelf,
+}
@@ -0,0 +1,81 @@
+use std::str::FromStr;
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIter,
+    strum::EnumIs,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum PostVisibility {
+    /// only the owner can see it
+    Draft,
+    /// goes public at `publish_at`
+    Scheduled,
+    /// reachable by link, never listed
+    Unlisted,
+    #[default]
+    Public,
+}
+
+impl PostVisibility {
+    /// unknown visibility falls back to draft so nothing leaks by accident
+    pub fn from_db(visibility: impl AsRef<str>) -> Self {
+        Self::from_str(visibility.as_ref()).unwrap_or(Self::Draft)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PostVisibility::Draft => "Draft",
+            PostVisibility::Scheduled => "Scheduled",
+            PostVisibility::Unlisted => "Unlisted",
+            PostVisibility::Public => "Public",
+        }
+    }
+
+    /// anyone with the link can open the post
+    pub fn is_published(&self) -> bool {
+        matches!(self, PostVisibility::Unlisted | PostVisibility::Public)
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<PostVisibility> for crate::db::DBPostVisibility {
+    fn from(value: PostVisibility) -> Self {
+        match value {
+            PostVisibility::Draft => Self::Draft,
+            PostVisibility::Scheduled => Self::Scheduled,
+            PostVisibility::Unlisted => Self::Unlisted,
+            PostVisibility::Public => Self::Public,
+        }
+    }
+}
+
+#[derive(
+    thiserror::Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum PostVisibilityErr {
+    #[error("post not found")]
+    NotFound,
+
+    #[error("publish time must be in the future")]
+    PublishAtInPast,
+}
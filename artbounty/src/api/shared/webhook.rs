@@ -0,0 +1,240 @@
+use std::str::FromStr;
+
+pub const WEBHOOKS_MAX: usize = 10;
+
+pub const WEBHOOK_DELIVERIES_FETCH_MAX: usize = 100;
+
+pub const WEBHOOK_URL_MAX_LENGTH: usize = 2048;
+
+/// every signing secret starts with this so it's easy to spot in a config file
+pub const WEBHOOK_SECRET_PREFIX: &str = "whsec_";
+
+/// random part of a signing secret, after the prefix
+pub const WEBHOOK_SECRET_LENGTH: usize = 32;
+
+/// hex hmac-sha256 of the raw request body keyed with the webhook secret, prefixed with `sha256=`
+pub const WEBHOOK_HEADER_SIGNATURE: &str = "x-artbounty-signature";
+
+pub const WEBHOOK_HEADER_EVENT: &str = "x-artbounty-event";
+
+/// stays the same across retries so receivers can drop duplicates
+pub const WEBHOOK_HEADER_DELIVERY: &str = "x-artbounty-delivery";
+
+/// a delivery is marked failed after this many attempts
+pub const WEBHOOK_ATTEMPTS_MAX: u32 = 8;
+
+/// wait before the first retry, doubled after every failed attempt
+pub const WEBHOOK_RETRY_BASE_NS: u128 = 60 * 1_000_000_000;
+
+/// finished deliveries are kept in the log this long
+pub const WEBHOOK_DELIVERY_RETENTION_NS: u128 = 30 * 24 * 60 * 60 * 1_000_000_000;
+
+/// how much of the error is kept in the log when the receiver never answered
+pub const WEBHOOK_ERROR_MAX_LENGTH: usize = 512;
+
+/// when the next attempt is due after `attempts` failed ones
+pub fn webhook_retry_delay(attempts: u32) -> u128 {
+    WEBHOOK_RETRY_BASE_NS << attempts.saturating_sub(1).min(16)
+}
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIter,
+    strum::EnumIs,
+)]
+pub enum WebhookEvent {
+    #[default]
+    #[strum(serialize = "post.created")]
+    PostCreated,
+    /// title, description, tags, rating or visibility changed
+    #[strum(serialize = "post.updated")]
+    PostUpdated,
+    /// the post was moved to the trash
+    #[strum(serialize = "post.deleted")]
+    PostDeleted,
+    #[strum(serialize = "comment.created")]
+    CommentCreated,
+    #[strum(serialize = "like.created")]
+    LikeCreated,
+    /// only sent to admin webhooks
+    #[strum(serialize = "user.registered")]
+    UserRegistered,
+}
+
+impl WebhookEvent {
+    /// unknown events are dropped so a removed event never fires
+    pub fn from_db(event: impl AsRef<str>) -> Option<Self> {
+        Self::from_str(event.as_ref()).ok()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            WebhookEvent::PostCreated => "A post was created",
+            WebhookEvent::PostUpdated => "A post was edited",
+            WebhookEvent::PostDeleted => "A post was deleted",
+            WebhookEvent::CommentCreated => "Someone commented on a post",
+            WebhookEvent::LikeCreated => "Someone liked a post",
+            WebhookEvent::UserRegistered => "A new account was registered",
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIs,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum WebhookDeliveryStatus {
+    #[default]
+    Pending,
+    Success,
+    Failed,
+}
+
+impl WebhookDeliveryStatus {
+    pub fn from_db(status: impl AsRef<str>) -> Self {
+        Self::from_str(status.as_ref()).unwrap_or_default()
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserWebhook {
+    pub key: String,
+    pub url: String,
+    /// used to sign every delivery, only the owner ever sees it
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    /// admin webhooks get events from the whole site instead of only the owners posts
+    pub admin: bool,
+    pub created_at: u128,
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::webhook::DBWebhook> for UserWebhook {
+    fn from(value: crate::db::webhook::DBWebhook) -> Self {
+        use surrealdb::types::ToSql;
+
+        Self {
+            key: value.id.key.to_sql(),
+            url: value.url,
+            secret: value.secret,
+            events: value
+                .events
+                .iter()
+                .filter_map(WebhookEvent::from_db)
+                .collect(),
+            admin: value.admin,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserWebhookDelivery {
+    pub key: String,
+    pub event: String,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: u32,
+    /// status code of the last attempt, 0 if the receiver never answered
+    pub response_code: u16,
+    /// why the last attempt failed when the receiver never answered, response bodies are not kept
+    pub response: String,
+    /// 0 once the delivery is finished
+    pub next_attempt_at: u128,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::db::webhook::DBWebhookDelivery> for UserWebhookDelivery {
+    fn from(value: crate::db::webhook::DBWebhookDelivery) -> Self {
+        use surrealdb::types::ToSql;
+
+        Self {
+            key: value.id.key.to_sql(),
+            event: value.event,
+            status: WebhookDeliveryStatus::from_db(&value.status),
+            attempts: value.attempts,
+            response_code: value.response_code,
+            response: value.response,
+            next_attempt_at: value.next_attempt_at,
+            modified_at: value.modified_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(
+    thiserror::Error,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum WebhookErr {
+    #[error("webhook not found")]
+    NotFound,
+
+    #[error(
+        "url must start with http:// or https:// and be at most {WEBHOOK_URL_MAX_LENGTH} characters"
+    )]
+    InvalidUrl,
+
+    #[error("pick at least one event")]
+    NoEvents,
+
+    #[error("only staff can create admin webhooks")]
+    AdminForbidden,
+
+    #[error("user.registered is only sent to admin webhooks")]
+    AdminEventForbidden,
+
+    #[error("you can have at most {WEBHOOKS_MAX} webhooks")]
+    TooMany,
+}
@@ -12,6 +12,7 @@ use surrealdb::{Surreal, opt::IntoEndpoint};
 use thiserror::Error;
 use tracing::{error, trace};
 
+use crate::db::audit::{DBAudit, DBAuditKind, q_audit_event_add};
 use crate::db::post::create_post_id;
 use crate::valid::{MAX_STORAGE, MAX_STORAGE_PER_FILE};
 
@@ -214,6 +215,13 @@ pub struct DBUser {
     pub username: String,
     pub email: String,
     pub password: String,
+    pub role: String,
+    pub banned: bool,
+    pub suspended_until: u128,
+    pub max_rating: String,
+    pub blur_rating: bool,
+    /// 0 unless the account was deleted, the row stays behind without any personal data
+    pub erased_at: u128,
     pub modified_at: u128,
     pub created_at: u128,
 }
@@ -226,13 +234,71 @@ pub struct DBUserPost {
     pub title: String,
     pub tags: String,
     pub description: String,
+    pub rating: String,
+    pub rating_locked: bool,
+    pub visibility: String,
+    pub publish_at: u128,
+    pub embeddable: bool,
     pub favorites: u64,
     pub size_bytes: usize,
     pub file: Vec<DBUserPostFile>,
+    pub edited_at: u128,
+    pub deleted_at: u128,
     pub modified_at: u128,
     pub created_at: u128,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum DBPostRating {
+    General,
+    Questionable,
+    Explicit,
+}
+
+impl DBPostRating {
+    /// every rating a viewer with this preference is allowed to see
+    pub fn up_to(&self) -> Vec<String> {
+        [Self::General, Self::Questionable, Self::Explicit]
+            .into_iter()
+            .filter(|v| v <= self)
+            .map(|v| v.to_string())
+            .collect()
+    }
+}
+
+impl Display for DBPostRating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            DBPostRating::General => "general",
+            DBPostRating::Questionable => "questionable",
+            DBPostRating::Explicit => "explicit",
+        };
+
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DBPostVisibility {
+    Draft,
+    Scheduled,
+    Unlisted,
+    Public,
+}
+
+impl Display for DBPostVisibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            DBPostVisibility::Draft => "draft",
+            DBPostVisibility::Scheduled => "scheduled",
+            DBPostVisibility::Unlisted => "unlisted",
+            DBPostVisibility::Public => "public",
+        };
+
+        write!(f, "{}", text)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
 pub struct DBUserPostFile {
     pub proccesed: bool,
@@ -307,6 +373,10 @@ pub enum DBSentEmailReason {
     ConfirmPasswordChange,
     ConfirmEmailChange,
     ConfirmEmailChangeNewEmail,
+    ConfirmAccountDeletion,
+    DataExportReady,
+    ReportResolved,
+    ModerationWarning,
 }
 
 impl Display for DBSentEmailReason {
@@ -315,6 +385,10 @@ impl Display for DBSentEmailReason {
             DBSentEmailReason::ConfirmPasswordChange => "confirm_password_change",
             DBSentEmailReason::ConfirmEmailChange => "confirm_email_change",
             DBSentEmailReason::ConfirmEmailChangeNewEmail => "confirm_email_change_new_email",
+            DBSentEmailReason::ConfirmAccountDeletion => "confirm_account_deletion",
+            DBSentEmailReason::DataExportReady => "data_export_ready",
+            DBSentEmailReason::ReportResolved => "report_resolved",
+            DBSentEmailReason::ModerationWarning => "moderation_warning",
         };
 
         write!(f, "{}", text)
@@ -410,6 +484,9 @@ pub enum DBPostCommentErr {
 
     #[error("reply_comment \"{0}\" was not found")]
     ReplyCommentNotFound(String),
+
+    #[error("blocked by the author")]
+    Blocked,
 }
 
 #[derive(Debug, Error)]
@@ -422,6 +499,123 @@ pub enum DBPostLikeErr {
 
     #[error("post \"{0}\" was not found")]
     PostNotFound(String),
+
+    #[error("blocked by the author")]
+    Blocked,
+}
+
+#[derive(Debug, Error)]
+pub enum DBDataExportErr {
+    #[error("DB error {0}")]
+    DB(#[from] surrealdb::Error),
+
+    #[error("an export is already pending")]
+    AlreadyPending,
+}
+
+#[derive(Debug, Error)]
+pub enum DBApiTokenErr {
+    #[error("DB error {0}")]
+    DB(#[from] surrealdb::Error),
+
+    #[error("too many api tokens")]
+    TooMany,
+}
+
+#[derive(Debug, Error)]
+pub enum DBWebhookErr {
+    #[error("DB error {0}")]
+    DB(#[from] surrealdb::Error),
+
+    #[error("too many webhooks")]
+    TooMany,
+}
+
+#[derive(Debug, Error)]
+pub enum DBUserFollowErr {
+    #[error("DB error {0}")]
+    DB(#[from] surrealdb::Error),
+
+    #[error("user was already followed")]
+    UserWasAlreadyFollowed,
+
+    #[error("user \"{0}\" was not found")]
+    UserNotFound(String),
+}
+
+#[derive(Debug, Error)]
+pub enum DBConversationErr {
+    #[error("DB error {0}")]
+    DB(#[from] surrealdb::Error),
+
+    #[error("user \"{0}\" was not found")]
+    UserNotFound(String),
+
+    #[error("conversation not found")]
+    NotFound,
+
+    #[error("conversation is blocked")]
+    Blocked,
+
+    #[error("file {0} already exists")]
+    Duplicate(String),
+}
+
+#[derive(Debug, Error)]
+pub enum DBCollectionErr {
+    #[error("DB error {0}")]
+    DB(#[from] surrealdb::Error),
+
+    #[error("collection not found")]
+    NotFound,
+
+    #[error("post \"{0}\" was not found")]
+    PostNotFound(String),
+
+    #[error("post is already in the collection")]
+    Duplicate,
+
+    #[error("post is not in the collection")]
+    ItemNotFound,
+
+    #[error("out of range, new_pos {new_pos}")]
+    OutOfRange { new_pos: usize },
+}
+
+#[derive(Debug, Error)]
+pub enum DBReportErr {
+    #[error("DB error {0}")]
+    DB(#[from] surrealdb::Error),
+
+    #[error("report not found")]
+    NotFound,
+
+    #[error("already reported")]
+    AlreadyReported,
+
+    #[error("report already resolved")]
+    AlreadyResolved,
+}
+
+#[derive(Debug, Error)]
+pub enum DBUserBlockErr {
+    #[error("DB error {0}")]
+    DB(#[from] surrealdb::Error),
+
+    #[error("user \"{0}\" was not found")]
+    UserNotFound(String),
+}
+
+#[derive(Debug, Error)]
+pub enum DBPostRatingErr {
+    #[error("DB error {0}")]
+    DB(#[from] surrealdb::Error),
+
+    #[error("post not found")]
+    NotFound,
+
+    #[error("rating was locked by a moderator")]
+    Locked,
 }
 
 #[derive(Debug, Error)]
@@ -456,7 +650,22 @@ impl Db<local::Db> {
 pub fn create_user_id(id: impl Into<String>) -> RecordId {
     RecordId::new("user", id.into())
 }
+pub mod account;
+pub mod activitypub;
+pub mod admin;
+pub mod api_token;
+pub mod audit;
+pub mod collection;
+pub mod conversation;
+pub mod cursor;
+pub mod notification;
 pub mod post_comment;
+pub mod report;
+pub mod revision;
+pub mod sitemap;
+pub mod trash;
+pub mod user_block;
+pub mod webhook;
 pub mod invite {
     use crate::db::DB404Err;
     use crate::db::DBEmailIsTakenErr;
@@ -805,7 +1014,7 @@ pub mod migration {
 
     impl<C: Connection> Db<C> {
         pub async fn migrate(&self, time: u128) -> Result<(), surrealdb::Error> {
-            for _ in 0..1 {
+            loop {
                 let current_version = self
                     .get_migration_latest()
                     .await
@@ -817,8 +1026,76 @@ pub mod migration {
                         info!("db migrating from v0 to v1");
                         self.migration_v1(time).await?;
                     }
+                    1 => {
+                        info!("db migrating from v1 to v2");
+                        self.migration_v2(time).await?;
+                    }
+                    2 => {
+                        info!("db migrating from v2 to v3");
+                        self.migration_v3(time).await?;
+                    }
+                    3 => {
+                        info!("db migrating from v3 to v4");
+                        self.migration_v4(time).await?;
+                    }
+                    4 => {
+                        info!("db migrating from v4 to v5");
+                        self.migration_v5(time).await?;
+                    }
+                    5 => {
+                        info!("db migrating from v5 to v6");
+                        self.migration_v6(time).await?;
+                    }
+                    6 => {
+                        info!("db migrating from v6 to v7");
+                        self.migration_v7(time).await?;
+                    }
+                    7 => {
+                        info!("db migrating from v7 to v8");
+                        self.migration_v8(time).await?;
+                    }
+                    8 => {
+                        info!("db migrating from v8 to v9");
+                        self.migration_v9(time).await?;
+                    }
+                    9 => {
+                        info!("db migrating from v9 to v10");
+                        self.migration_v10(time).await?;
+                    }
+                    10 => {
+                        info!("db migrating from v10 to v11");
+                        self.migration_v11(time).await?;
+                    }
+                    11 => {
+                        info!("db migrating from v11 to v12");
+                        self.migration_v12(time).await?;
+                    }
+                    12 => {
+                        info!("db migrating from v12 to v13");
+                        self.migration_v13(time).await?;
+                    }
+                    13 => {
+                        info!("db migrating from v13 to v14");
+                        self.migration_v14(time).await?;
+                    }
+                    14 => {
+                        info!("db migrating from v14 to v15");
+                        self.migration_v15(time).await?;
+                    }
+                    15 => {
+                        info!("db migrating from v15 to v16");
+                        self.migration_v16(time).await?;
+                    }
+                    16 => {
+                        info!("db migrating from v16 to v17");
+                        self.migration_v17(time).await?;
+                    }
+                    17 => {
+                        info!("db migrating from v17 to v18");
+                        self.migration_v18(time).await?;
+                    }
                     _ => {
-                        info!("db on latest version v1");
+                        info!("db on latest version v18");
                         break;
                     }
                 }
@@ -962,115 +1239,671 @@ pub mod migration {
             Ok(())
         }
 
-        pub async fn create_migration(
-            &self,
-            time: u128,
-            version: u64,
-        ) -> Result<DBMigration, DBMigrationErr> {
-            self.db
+        pub async fn migration_v2(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
                 .query(
                     r#"
-                 CREATE migration SET
-                    version = $version,
-                    modified_at = $time,
-                    created_at = $time
+                    --user follow
+                    DEFINE TABLE user_follow SCHEMAFULL;
+                    DEFINE FIELD follower ON TABLE user_follow TYPE record<user>;
+                    DEFINE FIELD following ON TABLE user_follow TYPE record<user>;
+                    DEFINE FIELD modified_at ON TABLE user_follow TYPE number;
+                    DEFINE FIELD created_at ON TABLE user_follow TYPE number;
+                    DEFINE INDEX idx_follower_following ON TABLE user_follow COLUMNS follower, following UNIQUE;
+                    DEFINE INDEX idx_following ON TABLE user_follow COLUMNS following;
+
+                    CREATE migration SET version = 2, modified_at = $time, created_at = $time;
                 "#,
                 )
                 .bind(("time", time))
-                .bind(("version", version))
-                .await
-                .check_good(|err| match err {
-                    err if err.index_exists("idx_migration_version") => {
-                        DBMigrationErr::AlreadyExists(version)
-                    }
-                    err => err.into(),
-                })
-                .and_then_take_expect(0)
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
         }
 
-        pub async fn get_migration_latest(&self) -> Result<DBMigration, DB404Err> {
-            self.db
+        pub async fn migration_v3(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
                 .query(
                     r#"
-                        SELECT * FROM ONLY migration  
-                                ORDER BY created_at DESC
+                    --notification
+                    DEFINE TABLE notification SCHEMAFULL;
+                    DEFINE FIELD user ON TABLE notification TYPE record<user>;
+                    DEFINE FIELD kind ON TABLE notification TYPE string;
+                    DEFINE FIELD group_key ON TABLE notification TYPE string;
+                    DEFINE FIELD post ON TABLE notification TYPE record<post>;
+                    DEFINE FIELD actors ON TABLE notification TYPE array<record<user>>;
+                    DEFINE FIELD read ON TABLE notification TYPE bool;
+                    DEFINE FIELD modified_at ON TABLE notification TYPE number;
+                    DEFINE FIELD created_at ON TABLE notification TYPE number;
+                    DEFINE INDEX idx_notification_user ON TABLE notification COLUMNS user, read;
+
+                    CREATE migration SET version = 3, modified_at = $time, created_at = $time;
                 "#,
                 )
+                .bind(("time", time))
                 .await
-                .check_good(DB404Err::from)
-                .and_then_take_or(0, DB404Err::NotFound)
+                .inspect_err(|result| trace!("DB RESULT {:#?}", result))?;
+            result.check()?;
+            Ok(())
         }
-    }
-}
-
-pub mod session {
-    use crate::db::DB404Err;
-    use crate::db::DBPostLikeErr;
-    use crate::db::DBUser;
-    use crate::db::DBUserPost;
-    use crate::db::SurrealCheckUtils;
-    use crate::db::SurrealErrUtils;
-    use crate::db::SurrealSerializeUtils;
-    use crate::db::post::create_post_id;
-
-    use super::Db;
-    pub use surrealdb::Connection;
-    use surrealdb::types::RecordId;
-    use surrealdb::types::RecordIdKey;
-    use surrealdb::types::SurrealValue;
-    use surrealdb::types::ToSql;
-    use tracing::{info, trace};
 
-    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
-    pub struct DBSession {
-        pub id: RecordId,
-        // pub access_token: String,
-        pub user: DBUser,
-        pub modified_at: u128,
-        pub created_at: u128,
-    }
+        pub async fn migration_v4(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --conversation
+                    DEFINE TABLE conversation SCHEMAFULL;
+                    DEFINE FIELD pair_key ON TABLE conversation TYPE string;
+                    DEFINE FIELD users ON TABLE conversation TYPE array<record<user>>;
+                    DEFINE FIELD modified_at ON TABLE conversation TYPE number;
+                    DEFINE FIELD created_at ON TABLE conversation TYPE number;
+                    DEFINE INDEX idx_conversation_pair_key ON TABLE conversation COLUMNS pair_key UNIQUE;
+
+                    --conversation member
+                    DEFINE TABLE conversation_member SCHEMAFULL;
+                    DEFINE FIELD conversation ON TABLE conversation_member TYPE record<conversation>;
+                    DEFINE FIELD user ON TABLE conversation_member TYPE record<user>;
+                    DEFINE FIELD peer ON TABLE conversation_member TYPE record<user>;
+                    DEFINE FIELD read_at ON TABLE conversation_member TYPE number;
+                    DEFINE FIELD blocked ON TABLE conversation_member TYPE bool;
+                    DEFINE FIELD last_message_at ON TABLE conversation_member TYPE number;
+                    DEFINE FIELD modified_at ON TABLE conversation_member TYPE number;
+                    DEFINE FIELD created_at ON TABLE conversation_member TYPE number;
+                    DEFINE INDEX idx_conversation_member ON TABLE conversation_member COLUMNS conversation, user UNIQUE;
+                    DEFINE INDEX idx_conversation_member_user ON TABLE conversation_member COLUMNS user, last_message_at;
+
+                    --message
+                    DEFINE TABLE message SCHEMAFULL;
+                    DEFINE FIELD conversation ON TABLE message TYPE record<conversation>;
+                    DEFINE FIELD user ON TABLE message TYPE record<user>;
+                    DEFINE FIELD text ON TABLE message TYPE string;
+                    DEFINE FIELD file ON TABLE message TYPE array<object>;
+                    DEFINE FIELD file.*.proccesed ON TABLE message TYPE bool;
+                    DEFINE FIELD file.*.extension ON TABLE message TYPE string;
+                    DEFINE FIELD file.*.hash ON TABLE message TYPE string;
+                    DEFINE FIELD file.*.size_bytes ON TABLE message TYPE int;
+                    DEFINE FIELD file.*.width ON TABLE message TYPE int;
+                    DEFINE FIELD file.*.height ON TABLE message TYPE int;
+                    DEFINE FIELD modified_at ON TABLE message TYPE number;
+                    DEFINE FIELD created_at ON TABLE message TYPE number;
+                    DEFINE INDEX idx_message_conversation ON TABLE message COLUMNS conversation, created_at;
+
+                    CREATE migration SET version = 4, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
 
-    #[derive(Debug, thiserror::Error)]
-    pub enum AddSessionErr {
-        #[error("DB error {0}")]
-        DB(#[from] surrealdb::Error),
+        pub async fn migration_v5(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --collection
+                    DEFINE TABLE collection SCHEMAFULL;
+                    DEFINE FIELD user ON TABLE collection TYPE record<user>;
+                    DEFINE FIELD title ON TABLE collection TYPE string;
+                    DEFINE FIELD private ON TABLE collection TYPE bool;
+                    DEFINE FIELD items_count ON TABLE collection TYPE number;
+                    DEFINE FIELD modified_at ON TABLE collection TYPE number;
+                    DEFINE FIELD created_at ON TABLE collection TYPE number;
+                    DEFINE INDEX idx_collection_user ON TABLE collection COLUMNS user, created_at;
+
+                    --collection item
+                    DEFINE TABLE collection_item SCHEMAFULL;
+                    DEFINE FIELD collection ON TABLE collection_item TYPE record<collection>;
+                    DEFINE FIELD post ON TABLE collection_item TYPE record<post>;
+                    DEFINE FIELD position ON TABLE collection_item TYPE int;
+                    DEFINE FIELD modified_at ON TABLE collection_item TYPE number;
+                    DEFINE FIELD created_at ON TABLE collection_item TYPE number;
+                    DEFINE INDEX idx_collection_item ON TABLE collection_item COLUMNS collection, post UNIQUE;
+                    DEFINE INDEX idx_collection_item_position ON TABLE collection_item COLUMNS collection, position;
+
+                    CREATE migration SET version = 5, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
 
-        #[error("user \"{0}\" not found")]
-        UserNotFound(String),
-    }
+        pub async fn migration_v6(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --user role
+                    DEFINE FIELD role ON TABLE user TYPE string DEFAULT "user";
+                    DEFINE FIELD banned ON TABLE user TYPE bool DEFAULT false;
+                    DEFINE INDEX idx_user_role ON TABLE user COLUMNS role;
+                    UPDATE user SET role = "user", banned = false;
 
-    pub fn create_session_id(id: impl Into<String>) -> RecordId {
-        RecordId::new("session", id.into())
-    }
+                    CREATE migration SET version = 6, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
 
-    impl<C: Connection> Db<C> {
-        pub async fn add_session(
-            &self,
-            time: u128,
-            username: impl Into<String>,
-        ) -> Result<DBSession, AddSessionErr> {
-            let username: String = username.into();
-            self.db
-            .query(
-                r#"
-                     LET $user = SELECT id FROM ONLY user WHERE username = $username;
-                     CREATE session SET user = $user.id, modified_at = $time, created_at = $time RETURN *, user.*;
+        pub async fn migration_v7(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --user suspension
+                    DEFINE FIELD suspended_until ON TABLE user TYPE number DEFAULT 0;
+                    UPDATE user SET suspended_until = 0;
+
+                    --report
+                    DEFINE TABLE report SCHEMAFULL;
+                    DEFINE FIELD reporter ON TABLE report TYPE record<user>;
+                    DEFINE FIELD target_user ON TABLE report TYPE record<user>;
+                    DEFINE FIELD target_kind ON TABLE report TYPE string;
+                    DEFINE FIELD target ON TABLE report TYPE record<post | post_comment | user>;
+                    DEFINE FIELD post ON TABLE report TYPE option<record<post>>;
+                    DEFINE FIELD reason ON TABLE report TYPE string;
+                    DEFINE FIELD severity ON TABLE report TYPE int;
+                    DEFINE FIELD text ON TABLE report TYPE string;
+                    DEFINE FIELD resolved ON TABLE report TYPE bool;
+                    DEFINE FIELD modified_at ON TABLE report TYPE number;
+                    DEFINE FIELD created_at ON TABLE report TYPE number;
+                    DEFINE INDEX idx_report_queue ON TABLE report COLUMNS resolved, severity, created_at;
+                    DEFINE INDEX idx_report_target ON TABLE report COLUMNS target, resolved;
+
+                    --moderation action
+                    DEFINE TABLE moderation_action SCHEMAFULL;
+                    DEFINE FIELD moderator ON TABLE moderation_action TYPE record<user>;
+                    DEFINE FIELD report ON TABLE moderation_action TYPE record<report>;
+                    DEFINE FIELD target_user ON TABLE moderation_action TYPE record<user>;
+                    DEFINE FIELD action ON TABLE moderation_action TYPE string;
+                    DEFINE FIELD reason ON TABLE moderation_action TYPE string;
+                    DEFINE FIELD modified_at ON TABLE moderation_action TYPE number;
+                    DEFINE FIELD created_at ON TABLE moderation_action TYPE number;
+                    DEFINE INDEX idx_moderation_action_target_user ON TABLE moderation_action COLUMNS target_user, created_at;
+
+                    CREATE migration SET version = 7, modified_at = $time, created_at = $time;
                 "#,
-            )
-            .bind(("time", time))
-            .bind(("username", username.clone()))
-            .await
-            .check_good(|err| match err {
-                err if err.field_value_null("user") => AddSessionErr::UserNotFound(username),
-                err => err.into(),
-            })
-            .and_then_take_expect(1)
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
         }
 
-        pub async fn delete_session_user(&self, user_id: RecordId) -> Result<(), surrealdb::Error> {
-            self.db
-                .query("DELETE session WHERE user = $user_id;")
-                .bind(("user_id", user_id))
+        pub async fn migration_v8(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --audit event
+                    DEFINE TABLE audit_event SCHEMAFULL;
+                    DEFINE FIELD kind ON TABLE audit_event TYPE string;
+                    DEFINE FIELD actor ON TABLE audit_event TYPE option<record<user>>;
+                    DEFINE FIELD target ON TABLE audit_event TYPE option<record<user>>;
+                    DEFINE FIELD ip ON TABLE audit_event TYPE string;
+                    DEFINE FIELD user_agent ON TABLE audit_event TYPE string;
+                    DEFINE FIELD before ON TABLE audit_event TYPE string;
+                    DEFINE FIELD after ON TABLE audit_event TYPE string;
+                    DEFINE FIELD created_at ON TABLE audit_event TYPE number;
+                    DEFINE INDEX idx_audit_event_target ON TABLE audit_event COLUMNS target, created_at;
+                    DEFINE INDEX idx_audit_event_actor ON TABLE audit_event COLUMNS actor, created_at;
+                    DEFINE INDEX idx_audit_event_created_at ON TABLE audit_event COLUMNS created_at;
+                    DEFINE EVENT audit_event_append_only ON TABLE audit_event WHEN $event = "UPDATE" OR $event = "DELETE" THEN {
+                        THROW "audit events are append only";
+                    };
+
+                    CREATE migration SET version = 8, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
+
+        pub async fn migration_v9(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --user block
+                    DEFINE TABLE user_block SCHEMAFULL;
+                    DEFINE FIELD user ON TABLE user_block TYPE record<user>;
+                    DEFINE FIELD target ON TABLE user_block TYPE record<user>;
+                    DEFINE FIELD kind ON TABLE user_block TYPE string ASSERT $value IN ["block", "mute"];
+                    DEFINE FIELD modified_at ON TABLE user_block TYPE number;
+                    DEFINE FIELD created_at ON TABLE user_block TYPE number;
+                    DEFINE INDEX idx_user_block_user_target ON TABLE user_block COLUMNS user, target UNIQUE;
+                    DEFINE INDEX idx_user_block_target ON TABLE user_block COLUMNS target;
+
+                    CREATE migration SET version = 9, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
+
+        pub async fn migration_v10(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --post rating
+                    DEFINE FIELD rating ON TABLE post TYPE string DEFAULT "general" ASSERT $value IN ["general", "questionable", "explicit"];
+                    DEFINE FIELD rating_locked ON TABLE post TYPE bool DEFAULT false;
+                    DEFINE INDEX idx_post_rating ON TABLE post COLUMNS rating;
+                    UPDATE post SET rating = "general", rating_locked = false;
+
+                    --user rating preference
+                    DEFINE FIELD max_rating ON TABLE user TYPE string DEFAULT "general" ASSERT $value IN ["general", "questionable", "explicit"];
+                    DEFINE FIELD blur_rating ON TABLE user TYPE bool DEFAULT true;
+                    UPDATE user SET max_rating = "general", blur_rating = true;
+
+                    CREATE migration SET version = 10, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
+
+        pub async fn migration_v11(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --post visibility
+                    DEFINE FIELD visibility ON TABLE post TYPE string DEFAULT "public" ASSERT $value IN ["draft", "scheduled", "unlisted", "public"];
+                    DEFINE FIELD publish_at ON TABLE post TYPE number DEFAULT 0;
+                    DEFINE INDEX idx_post_visibility ON TABLE post COLUMNS visibility, publish_at;
+                    UPDATE post SET visibility = "public", publish_at = 0;
+
+                    CREATE migration SET version = 11, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
+
+        pub async fn migration_v12(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --revision
+                    DEFINE TABLE revision SCHEMAFULL;
+                    DEFINE FIELD user ON TABLE revision TYPE record<user>;
+                    DEFINE FIELD target ON TABLE revision TYPE record<post | post_comment>;
+                    DEFINE FIELD field ON TABLE revision TYPE string ASSERT $value IN ["title", "description", "tags", "text"];
+                    DEFINE FIELD before ON TABLE revision TYPE string;
+                    DEFINE FIELD after ON TABLE revision TYPE string;
+                    DEFINE FIELD created_at ON TABLE revision TYPE number;
+                    DEFINE INDEX idx_revision_target ON TABLE revision COLUMNS target, created_at;
+
+                    --edited marker
+                    DEFINE FIELD edited_at ON TABLE post TYPE number DEFAULT 0;
+                    UPDATE post SET edited_at = 0;
+                    DEFINE FIELD edited_at ON TABLE post_comment TYPE number DEFAULT 0;
+                    UPDATE post_comment SET edited_at = 0;
+
+                    CREATE migration SET version = 12, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
+
+        pub async fn migration_v13(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --trash
+                    DEFINE FIELD deleted_at ON TABLE post TYPE number DEFAULT 0;
+                    UPDATE post SET deleted_at = 0;
+                    DEFINE INDEX idx_post_deleted_at ON TABLE post COLUMNS deleted_at;
+                    DEFINE FIELD deleted_at ON TABLE post_comment TYPE number DEFAULT 0;
+                    UPDATE post_comment SET deleted_at = 0;
+                    DEFINE INDEX idx_post_comment_deleted_at ON TABLE post_comment COLUMNS deleted_at;
+
+                    CREATE migration SET version = 13, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
+
+        pub async fn migration_v14(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --erased accounts
+                    DEFINE FIELD erased_at ON TABLE user TYPE number DEFAULT 0;
+                    UPDATE user SET erased_at = 0;
+                    --audit events are never deleted, erasing an account may only clear the ip and user agent of its events
+                    REMOVE EVENT audit_event_append_only ON TABLE audit_event;
+                    DEFINE EVENT audit_event_append_only ON TABLE audit_event WHEN $event = "DELETE" OR ($event = "UPDATE" AND !(
+                        ($before.actor.erased_at > 0 OR $before.target.erased_at > 0)
+                        AND $after.ip = "" AND $after.user_agent = ""
+                        AND $after.kind = $before.kind AND $after.actor = $before.actor AND $after.target = $before.target
+                        AND $after.before = $before.before AND $after.after = $before.after AND $after.created_at = $before.created_at
+                    )) THEN {
+                        THROW "audit events are append only";
+                    };
+                    --account deletion
+                    DEFINE TABLE account_deletion SCHEMAFULL;
+                    DEFINE FIELD user ON TABLE account_deletion TYPE record<user>;
+                    DEFINE FIELD mode ON TABLE account_deletion TYPE string ASSERT $value IN ["anonymize", "delete"];
+                    DEFINE FIELD confirmed ON TABLE account_deletion TYPE bool;
+                    DEFINE FIELD expires ON TABLE account_deletion TYPE number;
+                    DEFINE FIELD delete_at ON TABLE account_deletion TYPE number;
+                    DEFINE FIELD modified_at ON TABLE account_deletion TYPE number;
+                    DEFINE FIELD created_at ON TABLE account_deletion TYPE number;
+                    DEFINE INDEX idx_account_deletion_user ON TABLE account_deletion COLUMNS user UNIQUE;
+                    DEFINE INDEX idx_account_deletion_delete_at ON TABLE account_deletion COLUMNS confirmed, delete_at;
+                    --data export
+                    DEFINE TABLE data_export SCHEMAFULL;
+                    DEFINE FIELD user ON TABLE data_export TYPE record<user>;
+                    DEFINE FIELD status ON TABLE data_export TYPE string ASSERT $value IN ["pending", "ready", "failed"];
+                    DEFINE FIELD size_bytes ON TABLE data_export TYPE number;
+                    DEFINE FIELD expires ON TABLE data_export TYPE number;
+                    DEFINE FIELD modified_at ON TABLE data_export TYPE number;
+                    DEFINE FIELD created_at ON TABLE data_export TYPE number;
+                    DEFINE INDEX idx_data_export_user ON TABLE data_export COLUMNS user, created_at;
+                    DEFINE INDEX idx_data_export_status ON TABLE data_export COLUMNS status;
+
+                    CREATE migration SET version = 14, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
+
+        pub async fn migration_v15(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --personal access tokens
+                    DEFINE TABLE api_token SCHEMAFULL;
+                    DEFINE FIELD user ON TABLE api_token TYPE record<user>;
+                    DEFINE FIELD name ON TABLE api_token TYPE string;
+                    DEFINE FIELD secret_hash ON TABLE api_token TYPE string;
+                    DEFINE FIELD scopes ON TABLE api_token TYPE array<string>;
+                    DEFINE FIELD expires ON TABLE api_token TYPE number;
+                    DEFINE FIELD last_used_at ON TABLE api_token TYPE number;
+                    DEFINE FIELD modified_at ON TABLE api_token TYPE number;
+                    DEFINE FIELD created_at ON TABLE api_token TYPE number;
+                    DEFINE INDEX idx_api_token_secret_hash ON TABLE api_token COLUMNS secret_hash UNIQUE;
+                    DEFINE INDEX idx_api_token_user ON TABLE api_token COLUMNS user, created_at;
+
+                    CREATE migration SET version = 15, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
+
+        pub async fn migration_v16(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --outgoing webhooks
+                    DEFINE TABLE webhook SCHEMAFULL;
+                    DEFINE FIELD user ON TABLE webhook TYPE record<user>;
+                    DEFINE FIELD admin ON TABLE webhook TYPE bool;
+                    DEFINE FIELD url ON TABLE webhook TYPE string;
+                    DEFINE FIELD secret ON TABLE webhook TYPE string;
+                    DEFINE FIELD events ON TABLE webhook TYPE array<string>;
+                    DEFINE FIELD modified_at ON TABLE webhook TYPE number;
+                    DEFINE FIELD created_at ON TABLE webhook TYPE number;
+                    DEFINE INDEX idx_webhook_user ON TABLE webhook COLUMNS user, created_at;
+
+                    --delivery queue and log
+                    DEFINE TABLE webhook_delivery SCHEMAFULL;
+                    DEFINE FIELD webhook ON TABLE webhook_delivery TYPE record<webhook>;
+                    DEFINE FIELD event ON TABLE webhook_delivery TYPE string;
+                    DEFINE FIELD payload ON TABLE webhook_delivery TYPE string;
+                    DEFINE FIELD status ON TABLE webhook_delivery TYPE string;
+                    DEFINE FIELD attempts ON TABLE webhook_delivery TYPE number;
+                    DEFINE FIELD next_attempt_at ON TABLE webhook_delivery TYPE number;
+                    DEFINE FIELD response_code ON TABLE webhook_delivery TYPE number;
+                    DEFINE FIELD response ON TABLE webhook_delivery TYPE string;
+                    DEFINE FIELD modified_at ON TABLE webhook_delivery TYPE number;
+                    DEFINE FIELD created_at ON TABLE webhook_delivery TYPE number;
+                    DEFINE INDEX idx_webhook_delivery_webhook ON TABLE webhook_delivery COLUMNS webhook, created_at;
+                    DEFINE INDEX idx_webhook_delivery_status ON TABLE webhook_delivery COLUMNS status, next_attempt_at;
+
+                    CREATE migration SET version = 16, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
+
+        pub async fn migration_v17(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --activitypub signing keys of local actors
+                    DEFINE TABLE ap_key SCHEMAFULL;
+                    DEFINE FIELD user ON TABLE ap_key TYPE record<user>;
+                    DEFINE FIELD private_key_pem ON TABLE ap_key TYPE string;
+                    DEFINE FIELD public_key_pem ON TABLE ap_key TYPE string;
+                    DEFINE FIELD created_at ON TABLE ap_key TYPE number;
+                    DEFINE INDEX idx_ap_key_user ON TABLE ap_key COLUMNS user UNIQUE;
+
+                    --remote followers
+                    DEFINE TABLE ap_follower SCHEMAFULL;
+                    DEFINE FIELD user ON TABLE ap_follower TYPE record<user>;
+                    DEFINE FIELD actor ON TABLE ap_follower TYPE string;
+                    DEFINE FIELD inbox ON TABLE ap_follower TYPE string;
+                    DEFINE FIELD created_at ON TABLE ap_follower TYPE number;
+                    DEFINE INDEX idx_ap_follower_user_actor ON TABLE ap_follower COLUMNS user, actor UNIQUE;
+
+                    --remote likes and announces
+                    DEFINE TABLE ap_reaction SCHEMAFULL;
+                    DEFINE FIELD post ON TABLE ap_reaction TYPE record<post>;
+                    DEFINE FIELD actor ON TABLE ap_reaction TYPE string;
+                    DEFINE FIELD kind ON TABLE ap_reaction TYPE string;
+                    DEFINE FIELD activity ON TABLE ap_reaction TYPE string;
+                    DEFINE FIELD created_at ON TABLE ap_reaction TYPE number;
+                    DEFINE INDEX idx_ap_reaction_activity ON TABLE ap_reaction COLUMNS activity UNIQUE;
+                    DEFINE INDEX idx_ap_reaction_post ON TABLE ap_reaction COLUMNS post, created_at;
+
+                    --outgoing activities
+                    DEFINE TABLE ap_delivery SCHEMAFULL;
+                    DEFINE FIELD user ON TABLE ap_delivery TYPE record<user>;
+                    DEFINE FIELD inbox ON TABLE ap_delivery TYPE string;
+                    DEFINE FIELD payload ON TABLE ap_delivery TYPE string;
+                    DEFINE FIELD status ON TABLE ap_delivery TYPE string;
+                    DEFINE FIELD attempts ON TABLE ap_delivery TYPE number;
+                    DEFINE FIELD next_attempt_at ON TABLE ap_delivery TYPE number;
+                    DEFINE FIELD response_code ON TABLE ap_delivery TYPE number;
+                    DEFINE FIELD response ON TABLE ap_delivery TYPE string;
+                    DEFINE FIELD modified_at ON TABLE ap_delivery TYPE number;
+                    DEFINE FIELD created_at ON TABLE ap_delivery TYPE number;
+                    DEFINE INDEX idx_ap_delivery_user ON TABLE ap_delivery COLUMNS user, created_at;
+                    DEFINE INDEX idx_ap_delivery_status ON TABLE ap_delivery COLUMNS status, next_attempt_at;
+
+                    CREATE migration SET version = 17, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
+
+        pub async fn migration_v18(&self, time: u128) -> Result<(), surrealdb::Error> {
+            let db = &self.db;
+            let result = db
+                .query(
+                    r#"
+                    --whether other sites may embed the post
+                    DEFINE FIELD embeddable ON TABLE post TYPE bool DEFAULT true;
+                    UPDATE post SET embeddable = true;
+
+                    CREATE migration SET version = 18, modified_at = $time, created_at = $time;
+                "#,
+                )
+                .bind(("time", time))
+                .await.inspect_err(|result| trace!("DB RESULT {:#?}", result) )?;
+            result.check()?;
+            Ok(())
+        }
+
+        pub async fn create_migration(
+            &self,
+            time: u128,
+            version: u64,
+        ) -> Result<DBMigration, DBMigrationErr> {
+            self.db
+                .query(
+                    r#"
+                 CREATE migration SET
+                    version = $version,
+                    modified_at = $time,
+                    created_at = $time
+                "#,
+                )
+                .bind(("time", time))
+                .bind(("version", version))
+                .await
+                .check_good(|err| match err {
+                    err if err.index_exists("idx_migration_version") => {
+                        DBMigrationErr::AlreadyExists(version)
+                    }
+                    err => err.into(),
+                })
+                .and_then_take_expect(0)
+        }
+
+        pub async fn get_migration_latest(&self) -> Result<DBMigration, DB404Err> {
+            self.db
+                .query(
+                    r#"
+                        SELECT * FROM ONLY migration  
+                                ORDER BY version DESC
+                                LIMIT 1
+                "#,
+                )
+                .await
+                .check_good(DB404Err::from)
+                .and_then_take_or(0, DB404Err::NotFound)
+        }
+    }
+}
+
+pub mod session {
+    use crate::db::DB404Err;
+    use crate::db::DBPostLikeErr;
+    use crate::db::DBUser;
+    use crate::db::DBUserPost;
+    use crate::db::SurrealCheckUtils;
+    use crate::db::SurrealErrUtils;
+    use crate::db::SurrealSerializeUtils;
+    use crate::db::audit::{DBAudit, DBAuditKind, q_audit_event_add};
+    use crate::db::post::create_post_id;
+
+    use super::Db;
+    pub use surrealdb::Connection;
+    use surrealdb::types::RecordId;
+    use surrealdb::types::RecordIdKey;
+    use surrealdb::types::SurrealValue;
+    use surrealdb::types::ToSql;
+    use tracing::{info, trace};
+
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+    pub struct DBSession {
+        pub id: RecordId,
+        // pub access_token: String,
+        pub user: DBUser,
+        pub modified_at: u128,
+        pub created_at: u128,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    pub enum AddSessionErr {
+        #[error("DB error {0}")]
+        DB(#[from] surrealdb::Error),
+
+        #[error("user \"{0}\" not found")]
+        UserNotFound(String),
+    }
+
+    pub fn create_session_id(id: impl Into<String>) -> RecordId {
+        RecordId::new("session", id.into())
+    }
+
+    impl<C: Connection> Db<C> {
+        /// every new session is recorded as a login in the audit log
+        pub async fn add_session(
+            &self,
+            time: u128,
+            username: impl Into<String>,
+            audit: DBAudit,
+        ) -> Result<DBSession, AddSessionErr> {
+            let username: String = username.into();
+            let q_audit = q_audit_event_add(DBAuditKind::Login, "$user.id", "\"\"", "\"\"");
+            let q = format!(
+                r#"
+                     BEGIN TRANSACTION;
+                     LET $user = SELECT id FROM ONLY user WHERE username = $username LIMIT 1;
+                     IF !$user {{
+                        THROW "user not found";
+                     }};
+                     LET $session = CREATE ONLY session SET user = $user.id, modified_at = $time, created_at = $time RETURN *, user.*;
+                     {q_audit}
+                     RETURN $session;
+                     COMMIT TRANSACTION;
+                "#
+            );
+            trace!("about to run {q}");
+            self.db
+                .query(q)
+                .bind(("time", time))
+                .bind(("username", username.clone()))
+                .bind(("audit", audit))
+                .await
+                .check_better(|err| match err.message() {
+                    "An error occurred: user not found" => AddSessionErr::UserNotFound(username),
+                    _ => AddSessionErr::DB(err),
+                })
+                .and_then_take_expect(5)
+        }
+
+        pub async fn delete_session_user(&self, user_id: RecordId) -> Result<(), surrealdb::Error> {
+            self.db
+                .query("DELETE session WHERE user = $user_id;")
+                .bind(("user_id", user_id))
                 .await
                 .check_good(surrealdb::Error::from)
                 .map(|_| ())
@@ -1124,11 +1957,43 @@ pub mod session {
             api::{ChangeUsernameErr, ServerRes},
             db::{
                 AddUserErr, DB404Err, DBChangeUsernameErr, DBEmailIsTakenErr, DBPostLikeErr,
-                DBSentEmailReason, DBUserPostFile, Db, post_like::create_post_like_id,
-                session::AddSessionErr,
+                DBPostRating, DBPostVisibility, DBSentEmailReason, DBUserPostFile, Db,
+                audit::DBAudit, post_like::create_post_like_id, session::AddSessionErr,
             },
         };
 
+        #[tokio::test]
+        async fn db_migrate_test() {
+            crate::init_test_log();
+
+            let db = Db::new::<Mem>(()).await.unwrap();
+            db.migrate(0).await.unwrap();
+            let version = db.get_migration_latest().await.unwrap().version;
+            assert_eq!(version, 18);
+            db.migrate(1).await.unwrap();
+            assert_eq!(db.get_migration_latest().await.unwrap().version, version);
+
+            db.add_user(0, "hey", "hey@hey.com", "hey").await.unwrap();
+            let post = db
+                .add_post(
+                    0,
+                    "hey",
+                    "title",
+                    "description",
+                    "tag1",
+                    0,
+                    DBPostRating::General,
+                    DBPostVisibility::Public,
+                    0,
+                )
+                .await
+                .unwrap();
+            let result = db.get_post(post.id.key.clone()).await.unwrap();
+            assert_eq!(result.id, post.id);
+            assert_eq!(result.title, "title");
+            assert!(result.embeddable);
+        }
+
         #[tokio::test]
         async fn db_session() {
             crate::init_test_log();
@@ -1142,10 +2007,10 @@ pub mod session {
                 .unwrap();
 
             trace!("created {user:#?}");
-            let session = db.add_session(0, "hey").await.unwrap();
+            let session = db.add_session(0, "hey", DBAudit::default()).await.unwrap();
             let token1 = session.id.key.to_sql();
 
-            let session = db.add_session(0, "hey2").await;
+            let session = db.add_session(0, "hey2", DBAudit::default()).await;
             trace!("session: {session:?}");
             assert!(matches!(session, Err(AddSessionErr::UserNotFound(_))));
 
@@ -1159,9 +2024,12 @@ pub mod session {
             let session = db.get_session(token1).await;
             assert!(matches!(session, Err(DB404Err::NotFound)));
 
-            let session = db.add_session(0, "hey").await.unwrap();
+            let session = db.add_session(0, "hey", DBAudit::default()).await.unwrap();
             let token1 = session.id.key.to_sql();
-            let session = db.add_session(0, "hey11").await.unwrap();
+            let session = db
+                .add_session(0, "hey11", DBAudit::default())
+                .await
+                .unwrap();
             let token2 = session.id.key.to_sql();
             db.delete_session_user(user.id.clone()).await.unwrap();
 
@@ -1215,27 +2083,33 @@ pub mod post_like {
             self.db
                 .query(
                     r#"
-                 LET $post = SELECT id FROM ONLY $post_id;
+                 BEGIN TRANSACTION;
+                 LET $post = SELECT id, user FROM ONLY $post_id;
+                 IF $post.user IN (SELECT VALUE user FROM user_block WHERE target = $user_id AND kind = "block") {
+                    THROW "blocked";
+                 };
                  CREATE post_like SET
                     user = $user_id,
                     post = $post.id,
                     modified_at = $time,
                     created_at = $time
                  RETURN *;
+                 COMMIT TRANSACTION;
                 "#,
                 )
                 .bind(("time", time))
                 .bind(("user_id", user_id))
                 .bind(("post_id", create_post_id(post_id.clone())))
                 .await
-                .check_good(|err| match err {
+                .check_better(|err| match err {
+                    err if err.message() == "An error occurred: blocked" => DBPostLikeErr::Blocked,
                     err if err.index_exists("idx_user_post") => DBPostLikeErr::PostWasAlreadyLiked,
                     err if err.field_value_null("post") => {
                         DBPostLikeErr::PostNotFound(post_id.to_sql())
                     }
                     err => err.into(),
                 })
-                .and_then_take_expect(1)
+                .and_then_take_expect(3)
         }
 
         //
@@ -1255,142 +2129,709 @@ pub mod post_like {
                 .bind(("user_id", user))
                 .bind(("post_id", create_post_id(post_id.into())))
                 .await
-                .check_good(surrealdb::Error::from)
-                // .check_good(Surreal::from)
-                .map(|_| ())
-            // .and_then_take_or(0, DB404Err::NotFound)
+                .check_good(surrealdb::Error::from)
+                // .check_good(Surreal::from)
+                .map(|_| ())
+            // .and_then_take_or(0, DB404Err::NotFound)
+        }
+
+        pub async fn get_post_like_all(
+            &self,
+            // time: u128,
+            // user: RecordId,
+            // post: RecordId,
+        ) -> Result<Vec<DBPostLike>, DB404Err> {
+            self.db
+                .query("SELECT * FROM ONLY post_like ORDER BY created_at ASC")
+                // .bind(("time", time))
+                // .bind(("user_user", user))
+                // .bind(("user_post", post))
+                .await
+                .check_good(DB404Err::from)
+                .and_then_take_all(0)
+        }
+
+        pub async fn get_post_like(
+            &self,
+            time: u128,
+            user: RecordId,
+            post: RecordId,
+        ) -> Result<DBPostLike, DB404Err> {
+            self.db
+                .query(
+                    r#"
+                        SELECT * FROM ONLY post_like WHERE
+                                user = $user_user AND
+                                post = $user_post
+                    "#,
+                )
+                .bind(("time", time))
+                .bind(("user_user", user))
+                .bind(("user_post", post))
+                .await
+                .check_good(DB404Err::from)
+                .and_then_take_or(0, DB404Err::NotFound)
+        }
+
+        pub async fn check_post_like(
+            &self,
+            time: u128,
+            user: RecordId,
+            post_id: impl Into<RecordIdKey>,
+        ) -> Result<RecordId, DB404Err> {
+            let post_id = post_id.into();
+            self.db
+                .query(
+                    r#"
+                        LET $result = SELECT id FROM ONLY post_like WHERE
+                                user = $user_id AND
+                                post = $post_id;
+                        return $result.id;
+                    "#,
+                )
+                .bind(("time", time))
+                .bind(("user_id", user))
+                .bind(("post_id", create_post_id(post_id.clone())))
+                .await
+                .check_good(DB404Err::from)
+                .and_then_take_or(1, DB404Err::NotFound)
+        }
+
+        pub async fn update_post_favorites(
+            &self,
+            post_id: impl Into<RecordIdKey>,
+        ) -> Result<u64, DB404Err> {
+            self.db
+                .query(
+                    r#"
+                        UPDATE $post_id SET
+                            favorites = count(SELECT id FROM post_like WHERE post = $post_id)
+                        RETURN VALUE favorites;
+                    "#,
+                )
+                .bind(("post_id", create_post_id(post_id.into())))
+                .await
+                .check_good(DB404Err::from)
+                .and_then_take_or(0, DB404Err::NotFound)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+
+        use std::time::Duration;
+
+        use surrealdb::{engine::local::Mem, types::RecordId};
+        use tracing::trace;
+
+        use crate::{
+            api::{ChangeUsernameErr, ServerRes},
+            db::{
+                AddUserErr, DB404Err, DBChangeUsernameErr, DBEmailIsTakenErr, DBPostLikeErr,
+                DBPostRating, DBPostVisibility, DBSentEmailReason, DBUserPostFile, Db,
+                post_like::create_post_like_id,
+            },
+        };
+
+        #[tokio::test]
+        async fn db_post_like() {
+            crate::init_test_log();
+            let db = Db::new::<Mem>(()).await.unwrap();
+            db.migrate(0).await.unwrap();
+
+            let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+            let post = db
+                .add_post(
+                    0,
+                    "hey1",
+                    "title",
+                    "description",
+                    "",
+                    0,
+                    DBPostRating::General,
+                    DBPostVisibility::Public,
+                    0,
+                )
+                .await
+                .unwrap();
+
+            // TODO add more failure tests
+
+            let result = db.add_post_like(0, user.id.clone(), "wtf").await;
+            assert!(matches!(result, Err(DBPostLikeErr::PostNotFound(_))));
+
+            let result = db.delete_post_like(user.id.clone(), "wtf").await;
+            assert!(result.is_ok());
+
+            let result = db
+                .add_post_like(0, user.id.clone(), post.id.key.clone())
+                .await;
+            assert!(result.is_ok());
+
+            let result = db
+                .delete_post_like(user.id.clone(), post.id.key.clone())
+                .await;
+            assert!(result.is_ok());
+
+            let result = db
+                .add_post_like(0, user.id.clone(), post.id.key.clone())
+                .await;
+            assert!(result.is_ok());
+
+            let result = db
+                .add_post_like(0, user.id.clone(), post.id.key.clone())
+                .await;
+            assert!(matches!(result, Err(DBPostLikeErr::PostWasAlreadyLiked)));
+
+            let result = db.get_post_like(0, user.id.clone(), post.id.clone()).await;
+            assert!(result.is_ok());
+
+            let result = db
+                .check_post_like(0, user.id.clone(), post.id.key.clone())
+                .await;
+            assert!(result.is_ok());
+
+            let result = db.check_post_like(0, user.id.clone(), "none").await;
+            assert!(matches!(result, Err(DB404Err::NotFound)));
+
+            let favorites = db.update_post_favorites(post.id.key.clone()).await.unwrap();
+            assert_eq!(favorites, 1);
+
+            db.delete_post_like(user.id.clone(), post.id.key.clone())
+                .await
+                .unwrap();
+            let favorites = db.update_post_favorites(post.id.key.clone()).await.unwrap();
+            assert_eq!(favorites, 0);
+
+            let result = db.update_post_favorites("none").await;
+            assert!(matches!(result, Err(DB404Err::NotFound)));
+        }
+    }
+}
+
+pub mod user_follow {
+
+    use crate::api::shared::cursor::{CursorRange, PAGE_LIMIT_MAX};
+    use crate::api::{Order, TimeRange};
+    use crate::db::DB404Err;
+    use crate::db::DBPostRating;
+    use crate::db::DBUser;
+    use crate::db::DBUserFollowErr;
+    use crate::db::DBUserPost;
+    use crate::db::SurrealCheckUtils;
+    use crate::db::SurrealErrUtils;
+    use crate::db::SurrealSerializeUtils;
+    use crate::db::cursor::{order_query, q_cursor_range, time_range_query};
+    use crate::db::user_block::q_user_block_filter;
+
+    use super::Db;
+    pub use surrealdb::Connection;
+    use surrealdb::types::RecordId;
+    use surrealdb::types::RecordIdKey;
+    use surrealdb::types::SurrealValue;
+    use tracing::trace;
+
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+    pub struct DBUserFollow {
+        pub id: RecordId,
+        pub follower: RecordId,
+        pub following: RecordId,
+        pub modified_at: u128,
+        pub created_at: u128,
+    }
+
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+    pub struct DBUserFollowUser {
+        pub user: DBUser,
+        pub created_at: u128,
+    }
+
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+    pub struct DBUserFollowStats {
+        pub followers: u64,
+        pub following: u64,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum DBUserFollowSide {
+        Followers,
+        Following,
+    }
+
+    pub fn create_user_follow_id(id: impl Into<RecordIdKey>) -> RecordId {
+        RecordId::new("user_follow", id)
+    }
+
+    impl<C: Connection> Db<C> {
+        pub async fn add_user_follow(
+            &self,
+            time: u128,
+            follower_id: RecordId,
+            username: impl Into<String>,
+        ) -> Result<DBUserFollow, DBUserFollowErr> {
+            let username = username.into();
+            self.db
+                .query(
+                    r#"
+                 LET $user = SELECT id FROM ONLY user WHERE username = $username LIMIT 1;
+                 CREATE user_follow SET
+                    follower = $follower_id,
+                    following = $user.id,
+                    modified_at = $time,
+                    created_at = $time
+                 RETURN *;
+                "#,
+                )
+                .bind(("time", time))
+                .bind(("follower_id", follower_id))
+                .bind(("username", username.clone()))
+                .await
+                .check_good(|err| match err {
+                    err if err.index_exists("idx_follower_following") => {
+                        DBUserFollowErr::UserWasAlreadyFollowed
+                    }
+                    err if err.field_value_null("following") => {
+                        DBUserFollowErr::UserNotFound(username)
+                    }
+                    err => err.into(),
+                })
+                .and_then_take_expect(1)
+        }
+
+        pub async fn delete_user_follow(
+            &self,
+            follower_id: RecordId,
+            username: impl Into<String>,
+        ) -> Result<(), surrealdb::Error> {
+            self.db
+                .query(
+                    r#"
+                        DELETE user_follow WHERE
+                            follower = $follower_id AND
+                            following = (SELECT id FROM ONLY user WHERE username = $username LIMIT 1).id;
+                    "#,
+                )
+                .bind(("follower_id", follower_id))
+                .bind(("username", username.into()))
+                .await
+                .check_good(surrealdb::Error::from)
+                .map(|_| ())
+        }
+
+        pub async fn check_user_follow(
+            &self,
+            follower_id: RecordId,
+            username: impl Into<String>,
+        ) -> Result<RecordId, DB404Err> {
+            self.db
+                .query(
+                    r#"
+                        LET $user = SELECT id FROM ONLY user WHERE username = $username LIMIT 1;
+                        LET $result = SELECT id FROM ONLY user_follow WHERE
+                                follower = $follower_id AND
+                                following = $user.id
+                                LIMIT 1;
+                        return $result.id;
+                    "#,
+                )
+                .bind(("follower_id", follower_id))
+                .bind(("username", username.into()))
+                .await
+                .check_good(DB404Err::from)
+                .and_then_take_or(2, DB404Err::NotFound)
         }
 
-        pub async fn get_post_like_all(
-            &self,
-            // time: u128,
-            // user: RecordId,
-            // post: RecordId,
-        ) -> Result<Vec<DBPostLike>, DB404Err> {
+        pub async fn get_user_follow_all(&self) -> Result<Vec<DBUserFollow>, DB404Err> {
             self.db
-                .query("SELECT * FROM ONLY post_like ORDER BY created_at ASC")
-                // .bind(("time", time))
-                // .bind(("user_user", user))
-                // .bind(("user_post", post))
+                .query("SELECT * FROM user_follow ORDER BY created_at ASC")
                 .await
                 .check_good(DB404Err::from)
                 .and_then_take_all(0)
         }
 
-        pub async fn get_post_like(
+        pub async fn get_user_follow_stats(
             &self,
-            time: u128,
-            user: RecordId,
-            post: RecordId,
-        ) -> Result<DBPostLike, DB404Err> {
+            username: impl Into<String>,
+        ) -> Result<DBUserFollowStats, DB404Err> {
+            let user = self.get_user_by_username(username).await?;
             self.db
                 .query(
                     r#"
-                        SELECT * FROM ONLY post_like WHERE
-                                user = $user_user AND
-                                post = $user_post
+                        RETURN {
+                            followers: array::len(SELECT VALUE id FROM user_follow WHERE following = $user_id),
+                            following: array::len(SELECT VALUE id FROM user_follow WHERE follower = $user_id)
+                        };
                     "#,
                 )
-                .bind(("time", time))
-                .bind(("user_user", user))
-                .bind(("user_post", post))
+                .bind(("user_id", user.id))
                 .await
                 .check_good(DB404Err::from)
                 .and_then_take_or(0, DB404Err::NotFound)
         }
 
-        pub async fn check_post_like(
+        pub async fn get_user_follow_users(
             &self,
-            time: u128,
-            user: RecordId,
-            post_id: impl Into<RecordIdKey>,
-        ) -> Result<RecordId, DB404Err> {
-            let post_id = post_id.into();
+            username: impl Into<String>,
+            side: DBUserFollowSide,
+            limit: usize,
+            time_range: TimeRange,
+            order: Order,
+        ) -> Result<Vec<DBUserFollowUser>, DB404Err> {
+            let user = self.get_user_by_username(username).await?;
+            let (q_time, time_range_val) = time_range_query(&time_range, "created_at");
+            let q_order = order_query(&order);
+            let (q_user, q_match) = match side {
+                DBUserFollowSide::Followers => ("follower", "following"),
+                DBUserFollowSide::Following => ("following", "follower"),
+            };
+
+            let q = format!(
+                "
+                SELECT {q_user}.* AS user, created_at FROM user_follow WHERE
+                    {q_match} = $user_id {q_time}
+                    ORDER BY created_at {q_order}
+                    LIMIT $get_limit;
+            "
+            );
+            trace!("about to run {q}");
+
             self.db
-                .query(
-                    r#"
-                        LET $result = SELECT id FROM ONLY post_like WHERE
-                                user = $user_id AND
-                                post = $post_id;
-                        return $result.id;
-                    "#,
-                )
-                .bind(("time", time))
-                .bind(("user_id", user))
-                .bind(("post_id", create_post_id(post_id.clone())))
+                .query(q)
+                .bind(("user_id", user.id))
+                .bind(("get_limit", limit))
+                .bind(("time_range", time_range_val))
                 .await
                 .check_good(DB404Err::from)
-                .and_then_take_or(1, DB404Err::NotFound)
+                .and_then_take_all(0)
+        }
+
+        pub async fn post_search_following(
+            &self,
+            follower_id: RecordId,
+            limit: usize,
+            cursor: CursorRange,
+            order: Order,
+            max_rating: DBPostRating,
+        ) -> Result<Vec<DBUserPost>, surrealdb::Error> {
+            let limit = limit.min(PAGE_LIMIT_MAX);
+            let (q_cursor, cursor_time, cursor_id) = q_cursor_range(&cursor, "post");
+            let q_cursor = if q_cursor.is_empty() {
+                String::new()
+            } else {
+                format!("AND {q_cursor}")
+            };
+            let q_order = order_query(&order);
+            let q_block = q_user_block_filter("user", true);
+
+            let q = format!(
+                "
+                LET $following = SELECT VALUE following FROM user_follow WHERE follower = $follower_id;
+                SELECT *, user.* FROM post WHERE
+                    user IN $following AND show = true AND deleted_at = 0 AND visibility = 'public' AND rating IN $ratings AND {q_block} {q_cursor}
+                    ORDER BY created_at {q_order}, id {q_order}
+                    LIMIT $get_limit;
+            "
+            );
+            trace!("about to run {q}");
+
+            self.db
+                .query(q)
+                .bind(("viewer_id", follower_id.clone()))
+                .bind(("follower_id", follower_id))
+                .bind(("ratings", max_rating.up_to()))
+                .bind(("get_limit", limit))
+                .bind(("cursor_time", cursor_time))
+                .bind(("cursor_id", cursor_id))
+                .await
+                .check_good(surrealdb::Error::from)
+                .and_then_take_all(1)
         }
     }
 
     #[cfg(test)]
     mod tests {
-
-        use std::time::Duration;
-
-        use surrealdb::{engine::local::Mem, types::RecordId};
-        use tracing::trace;
+        use surrealdb::{engine::local::Mem, types::ToSql};
 
         use crate::{
-            api::{ChangeUsernameErr, ServerRes},
+            api::{
+                Order, TimeRange,
+                shared::cursor::{Cursor, CursorRange},
+            },
             db::{
-                AddUserErr, DB404Err, DBChangeUsernameErr, DBEmailIsTakenErr, DBPostLikeErr,
-                DBSentEmailReason, DBUserPostFile, Db, post_like::create_post_like_id,
+                DB404Err, DBPostRating, DBPostVisibility, DBUserFollowErr, Db,
+                user_follow::DBUserFollowSide,
             },
         };
 
         #[tokio::test]
-        async fn db_post_like() {
+        async fn db_user_follow() {
             crate::init_test_log();
             let db = Db::new::<Mem>(()).await.unwrap();
             db.migrate(0).await.unwrap();
 
-            let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
-            let post = db
-                .add_post(0, "hey1", "title", "description", "", 0)
-                .await
-                .unwrap();
+            let user1 = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+            let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+            let user3 = db.add_user(0, "hey3", "hey3@hey.com", "123").await.unwrap();
 
-            // TODO add more failure tests
+            let result = db.add_user_follow(0, user1.id.clone(), "wtf").await;
+            assert!(matches!(result, Err(DBUserFollowErr::UserNotFound(_))));
 
-            let result = db.add_post_like(0, user.id.clone(), "wtf").await;
-            assert!(matches!(result, Err(DBPostLikeErr::PostNotFound(_))));
+            let result = db.delete_user_follow(user1.id.clone(), "wtf").await;
+            assert!(result.is_ok());
 
-            let result = db.delete_post_like(user.id.clone(), "wtf").await;
+            let result = db.add_user_follow(1, user1.id.clone(), "hey2").await;
             assert!(result.is_ok());
 
-            let result = db
-                .add_post_like(0, user.id.clone(), post.id.key.clone())
-                .await;
+            let result = db.add_user_follow(1, user1.id.clone(), "hey2").await;
+            assert!(matches!(
+                result,
+                Err(DBUserFollowErr::UserWasAlreadyFollowed)
+            ));
+
+            let result = db.add_user_follow(2, user3.id.clone(), "hey2").await;
             assert!(result.is_ok());
 
-            let result = db
-                .delete_post_like(user.id.clone(), post.id.key.clone())
-                .await;
+            let result = db.add_user_follow(3, user2.id.clone(), "hey1").await;
             assert!(result.is_ok());
 
-            let result = db
-                .add_post_like(0, user.id.clone(), post.id.key.clone())
-                .await;
+            let result = db.check_user_follow(user1.id.clone(), "hey2").await;
             assert!(result.is_ok());
 
-            let result = db
-                .add_post_like(0, user.id.clone(), post.id.key.clone())
-                .await;
-            assert!(matches!(result, Err(DBPostLikeErr::PostWasAlreadyLiked)));
+            let result = db.check_user_follow(user2.id.clone(), "hey3").await;
+            assert!(matches!(result, Err(DB404Err::NotFound)));
 
-            let result = db.get_post_like(0, user.id.clone(), post.id.clone()).await;
-            assert!(result.is_ok());
+            let stats = db.get_user_follow_stats("hey2").await.unwrap();
+            assert_eq!(stats.followers, 2);
+            assert_eq!(stats.following, 1);
 
-            let result = db
-                .check_post_like(0, user.id.clone(), post.id.key.clone())
-                .await;
+            let stats = db.get_user_follow_stats("hey3").await.unwrap();
+            assert_eq!(stats.followers, 0);
+            assert_eq!(stats.following, 1);
+
+            let result = db.get_user_follow_stats("wtf").await;
+            assert!(matches!(result, Err(DB404Err::NotFound)));
+
+            let followers = db
+                .get_user_follow_users(
+                    "hey2",
+                    DBUserFollowSide::Followers,
+                    10,
+                    TimeRange::None,
+                    Order::ThreeTwoOne,
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|v| v.user.username)
+                .collect::<Vec<String>>();
+            assert_eq!(followers, vec!["hey3", "hey1"]);
+
+            let followers = db
+                .get_user_follow_users(
+                    "hey2",
+                    DBUserFollowSide::Followers,
+                    10,
+                    TimeRange::Less(2),
+                    Order::ThreeTwoOne,
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|v| v.user.username)
+                .collect::<Vec<String>>();
+            assert_eq!(followers, vec!["hey1"]);
+
+            let following = db
+                .get_user_follow_users(
+                    "hey2",
+                    DBUserFollowSide::Following,
+                    10,
+                    TimeRange::None,
+                    Order::ThreeTwoOne,
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|v| v.user.username)
+                .collect::<Vec<String>>();
+            assert_eq!(following, vec!["hey1"]);
+
+            let result = db.delete_user_follow(user1.id.clone(), "hey2").await;
             assert!(result.is_ok());
 
-            let result = db.check_post_like(0, user.id.clone(), "none").await;
+            let result = db.check_user_follow(user1.id.clone(), "hey2").await;
             assert!(matches!(result, Err(DB404Err::NotFound)));
         }
+
+        #[tokio::test]
+        async fn db_post_search_following() {
+            crate::init_test_log();
+            let db = Db::new::<Mem>(()).await.unwrap();
+            db.migrate(0).await.unwrap();
+
+            let user1 = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+            db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+            db.add_user(0, "hey3", "hey3@hey.com", "123").await.unwrap();
+
+            db.add_post(
+                0,
+                "hey2",
+                "0",
+                "",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+            db.add_post(
+                1,
+                "hey3",
+                "1",
+                "",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+            db.add_post(
+                2,
+                "hey1",
+                "2",
+                "",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+            db.add_post(
+                3,
+                "hey2",
+                "3",
+                "",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+
+            let posts = db
+                .post_search_following(
+                    user1.id.clone(),
+                    10,
+                    CursorRange::None,
+                    Order::ThreeTwoOne,
+                    DBPostRating::Explicit,
+                )
+                .await
+                .unwrap();
+            assert!(posts.is_empty());
+
+            db.add_user_follow(0, user1.id.clone(), "hey2")
+                .await
+                .unwrap();
+
+            let posts = db
+                .post_search_following(
+                    user1.id.clone(),
+                    10,
+                    CursorRange::None,
+                    Order::ThreeTwoOne,
+                    DBPostRating::Explicit,
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|v| v.title)
+                .collect::<Vec<String>>();
+            assert_eq!(posts, vec!["3", "0"]);
+
+            db.add_user_follow(0, user1.id.clone(), "hey3")
+                .await
+                .unwrap();
+
+            let posts = db
+                .post_search_following(
+                    user1.id.clone(),
+                    10,
+                    CursorRange::Before(Cursor::at(3)),
+                    Order::ThreeTwoOne,
+                    DBPostRating::Explicit,
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|v| v.title)
+                .collect::<Vec<String>>();
+            assert_eq!(posts, vec!["1", "0"]);
+
+            let posts = db
+                .post_search_following(
+                    user1.id.clone(),
+                    1,
+                    CursorRange::After(Cursor::at(1)),
+                    Order::OneTwoThree,
+                    DBPostRating::Explicit,
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|v| v.title)
+                .collect::<Vec<String>>();
+            assert_eq!(posts, vec!["1"]);
+
+            // posts created at the same time are neither skipped nor repeated between pages
+            for title in ["4", "5", "6"] {
+                db.add_post(
+                    4,
+                    "hey2",
+                    title,
+                    "",
+                    "",
+                    0,
+                    DBPostRating::General,
+                    DBPostVisibility::Public,
+                    0,
+                )
+                .await
+                .unwrap();
+            }
+            let mut titles = Vec::new();
+            let mut cursor = CursorRange::None;
+            loop {
+                let posts = db
+                    .post_search_following(
+                        user1.id.clone(),
+                        2,
+                        cursor,
+                        Order::ThreeTwoOne,
+                        DBPostRating::Explicit,
+                    )
+                    .await
+                    .unwrap();
+                let Some(last) = posts.last() else {
+                    break;
+                };
+                cursor = CursorRange::Before(Cursor::new(last.created_at, last.id.key.to_sql()));
+                titles.extend(posts.into_iter().map(|v| v.title));
+            }
+            assert_eq!(titles.len(), 6);
+            assert_eq!(titles[3..], ["3", "1", "0"]);
+            titles.sort();
+            titles.dedup();
+            assert_eq!(titles.len(), 6);
+        }
     }
 }
 
@@ -1569,33 +3010,172 @@ pub mod post {
 
     use surrealdb::{
         Connection,
-        types::{RecordId, RecordIdKey},
+        types::{RecordId, RecordIdKey, SurrealValue},
+    };
+
+    use std::collections::HashSet;
+
+    use crate::api::shared::cursor::{CursorRange, PAGE_LIMIT_MAX};
+    use crate::db::cursor::q_cursor_range;
+    use crate::db::revision::{DBRevisionField, q_update_with_revision};
+    use crate::db::user_block::q_user_block_filter;
+    use crate::db::{
+        DBPostAddFileErr, DBPostOrderFileErr, DBPostRating, DBPostRatingErr, DBPostRemoveFileErr,
+        DBPostVisibility, DBUserPostFile,
+    };
+    use crate::{
+        api::Order,
+        db::{DB404Err, DBUserPost, Db, SurrealCheckUtils, SurrealSerializeUtils},
     };
+    use tracing::trace;
+
+    pub fn create_post_id(id: impl Into<RecordIdKey>) -> RecordId {
+        RecordId::new("post", id.into())
+    }
+
+    /// just enough of a post to score it, the full posts are only loaded for the winners
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+    pub struct DBPostRelatedCandidate {
+        pub id: RecordId,
+        pub user: RecordId,
+        pub tags: String,
+        pub created_at: u128,
+    }
+
+    // tag jaccard similarity is the base, co-likes and same artist only nudge the order
+    pub fn post_related_score(
+        tags_a: &str,
+        tags_b: &str,
+        co_likes: usize,
+        same_artist: bool,
+    ) -> f64 {
+        let tags_a = tags_a.to_lowercase();
+        let tags_b = tags_b.to_lowercase();
+        let tags_a = tags_a.split_whitespace().collect::<HashSet<&str>>();
+        let tags_b = tags_b.split_whitespace().collect::<HashSet<&str>>();
+
+        let union = tags_a.union(&tags_b).count();
+        let jaccard = if union == 0 {
+            0.0
+        } else {
+            tags_a.intersection(&tags_b).count() as f64 / union as f64
+        };
+        let co_likes = 1.0 - 1.0 / (1.0 + co_likes as f64);
+        let same_artist = if same_artist { 0.25 } else { 0.0 };
+
+        jaccard + co_likes * 0.5 + same_artist
+    }
+
+    impl<C: Connection> Db<C> {
+        pub async fn update_post_description(
+            &self,
+            time: u128,
+            user_id: RecordId,
+            post_key: impl Into<RecordIdKey>,
+            text: impl Into<String>,
+        ) -> Result<DBUserPost, DB404Err> {
+            self.update_post_field(time, user_id, post_key, DBRevisionField::Description, text)
+                .await
+        }
+
+        pub async fn update_post_tags(
+            &self,
+            time: u128,
+            user_id: RecordId,
+            post_key: impl Into<RecordIdKey>,
+            text: impl Into<String>,
+        ) -> Result<DBUserPost, DB404Err> {
+            self.update_post_field(time, user_id, post_key, DBRevisionField::Tags, text)
+                .await
+        }
+
+        pub async fn update_post_title(
+            &self,
+            time: u128,
+            user_id: RecordId,
+            post_key: impl Into<RecordIdKey>,
+            title: impl Into<String>,
+        ) -> Result<DBUserPost, DB404Err> {
+            self.update_post_field(time, user_id, post_key, DBRevisionField::Title, title)
+                .await
+        }
 
-    use crate::db::{DBPostAddFileErr, DBPostOrderFileErr, DBPostRemoveFileErr, DBUserPostFile};
-    use crate::{
-        api::{Order, TimeRange},
-        db::{DB404Err, DBUserPost, Db, SurrealCheckUtils, SurrealSerializeUtils},
-    };
-    use tracing::trace;
+        async fn update_post_field(
+            &self,
+            time: u128,
+            user_id: RecordId,
+            post_key: impl Into<RecordIdKey>,
+            field: DBRevisionField,
+            value: impl Into<String>,
+        ) -> Result<DBUserPost, DB404Err> {
+            let q = q_update_with_revision(field);
+            trace!("about to run {q}");
+            self.db
+                .query(q)
+                .bind(("time", time))
+                .bind(("user_id", user_id))
+                .bind(("target_id", create_post_id(post_key)))
+                .bind(("value", value.into()))
+                .await
+                .check_better(|err| match err.message() {
+                    "An error occurred: not found" => DB404Err::NotFound,
+                    _ => DB404Err::DB(err),
+                })
+                .and_then_take_or(4, DB404Err::NotFound)
+        }
 
-    pub fn create_post_id(id: impl Into<RecordIdKey>) -> RecordId {
-        RecordId::new("post", id.into())
-    }
+        /// once a moderator has overridden the rating the author can't change it anymore
+        pub async fn update_post_rating(
+            &self,
+            time: u128,
+            user_id: RecordId,
+            post_key: impl Into<RecordIdKey>,
+            rating: DBPostRating,
+        ) -> Result<DBUserPost, DBPostRatingErr> {
+            let post_id = create_post_id(post_key);
+            let q = r#"
+                 BEGIN TRANSACTION;
+                 LET $post = SELECT id, user, rating_locked FROM ONLY $post_id;
+                 IF !$post OR $post.user != $user_id {
+                    THROW "post not found";
+                 };
+                 IF $post.rating_locked {
+                    THROW "rating locked";
+                 };
+                 UPDATE ONLY $post_id SET rating = $rating, modified_at = $time RETURN *, user.*;
+                 COMMIT TRANSACTION;
+                "#;
+            trace!("about to run {q}");
+            self.db
+                .query(q)
+                .bind(("rating", rating.to_string()))
+                .bind(("user_id", user_id))
+                .bind(("post_id", post_id))
+                .bind(("time", time))
+                .await
+                .check_better(|err| match err.message() {
+                    "An error occurred: post not found" => DBPostRatingErr::NotFound,
+                    "An error occurred: rating locked" => DBPostRatingErr::Locked,
+                    _ => DBPostRatingErr::DB(err),
+                })
+                .and_then_take_or(4, DBPostRatingErr::NotFound)
+        }
 
-    impl<C: Connection> Db<C> {
-        pub async fn update_post_description(
+        /// drafts and scheduled posts get a fresh created_at once published so they show up as new
+        pub async fn update_post_visibility(
             &self,
             time: u128,
             user_id: RecordId,
             post_key: impl Into<RecordIdKey>,
-            text: impl Into<String>,
+            visibility: DBPostVisibility,
+            publish_at: u128,
         ) -> Result<DBUserPost, DB404Err> {
-            // TODO maybe remove RETURN if im not using it anywhere
             let post_id = create_post_id(post_key);
             let q = r#"
                  UPDATE post SET
-                    description = $text,
+                    created_at = IF visibility IN ["draft", "scheduled"] AND $visibility IN ["unlisted", "public"] THEN $time ELSE created_at END,
+                    visibility = $visibility,
+                    publish_at = $publish_at,
                     modified_at = $time
                     WHERE id = $post_id AND user = $user_id
                  RETURN *, user.*;
@@ -1603,65 +3183,61 @@ pub mod post {
             trace!("about to run {q}");
             self.db
                 .query(q)
-                .bind(("time", time))
+                .bind(("visibility", visibility.to_string()))
+                .bind(("publish_at", publish_at))
                 .bind(("user_id", user_id))
                 .bind(("post_id", post_id))
-                .bind(("text", text.into()))
+                .bind(("time", time))
                 .await
                 .check_good(DB404Err::from)
                 .and_then_take_or(0, DB404Err::NotFound)
         }
 
-        pub async fn update_post_tags(
+        pub async fn update_post_embeddable(
             &self,
             time: u128,
             user_id: RecordId,
             post_key: impl Into<RecordIdKey>,
-            text: impl Into<String>,
+            embeddable: bool,
         ) -> Result<DBUserPost, DB404Err> {
-            // TODO maybe remove RETURN if im not using it anywhere
             let post_id = create_post_id(post_key);
             let q = r#"
-                 UPDATE post SET
-                    tags = $tags,
-                    modified_at = $time
+                 UPDATE post SET embeddable = $embeddable, modified_at = $time
                     WHERE id = $post_id AND user = $user_id
                  RETURN *, user.*;
                 "#;
             trace!("about to run {q}");
             self.db
                 .query(q)
-                .bind(("time", time))
+                .bind(("embeddable", embeddable))
                 .bind(("user_id", user_id))
                 .bind(("post_id", post_id))
-                .bind(("tags", text.into()))
+                .bind(("time", time))
                 .await
                 .check_good(DB404Err::from)
                 .and_then_take_or(0, DB404Err::NotFound)
         }
-        pub async fn update_post_title(
+
+        /// publishes every scheduled post that is due, returns the published posts
+        pub async fn update_post_publish_scheduled(
             &self,
             time: u128,
-            user_id: RecordId,
-            post_key: impl Into<RecordIdKey>,
-            title: impl Into<String>,
-        ) -> Result<DBUserPost, DB404Err> {
-            let post_id = create_post_id(post_key);
-            let title = title.into();
-
+        ) -> Result<Vec<DBUserPost>, surrealdb::Error> {
             self.db
                 .query(
                     r#"
-                     UPDATE post SET title = $title, modified_at = $time WHERE id = $post_id AND user = $user_id RETURN *, user.*;
-                    "#,
+                 UPDATE post SET
+                    visibility = "public",
+                    created_at = publish_at,
+                    modified_at = $time
+                    WHERE visibility = "scheduled" AND publish_at <= $time
+                 RETURN *, user.*;
+                "#,
                 )
-                .bind(("title", title))
-                .bind(("user_id", user_id))
-                .bind(("post_id", post_id))
                 .bind(("time", time))
                 .await
-                .check_good(DB404Err::from)
-                .and_then_take_or(0, DB404Err::NotFound)
+                .check_good(surrealdb::Error::from)
+                .and_then_take_all(0)
         }
 
         pub async fn update_post_file_order(
@@ -1929,13 +3505,14 @@ pub mod post {
         pub async fn post_search(
             &self,
             limit: usize,
-            time_range: TimeRange,
+            cursor: CursorRange,
             order: Order,
             tags: impl Into<String>,
             user: impl Into<String>,
+            viewer_id: Option<RecordId>,
+            max_rating: DBPostRating,
         ) -> Result<Vec<DBUserPost>, surrealdb::Error> {
-            // TODO make sure limit cant be millions
-
+            let limit = limit.min(PAGE_LIMIT_MAX);
             let tags = tags.into();
             let user = user.into();
 
@@ -1950,13 +3527,7 @@ pub mod post {
             //     .unwrap_or_default();
             // let user = user.unwrap_or_default();
 
-            let time_range_val = match time_range {
-                TimeRange::None => 0,
-                TimeRange::Less(v)
-                | TimeRange::LessOrEqual(v)
-                | TimeRange::More(v)
-                | TimeRange::MoreOrEqual(v) => v,
-            };
+            let (q_cursor, cursor_time, cursor_id) = q_cursor_range(&cursor, "post");
 
             let q_tags = if tags.len() > 0 {
                 "tags CONTAINSALL $tags"
@@ -1970,44 +3541,46 @@ pub mod post {
                 ""
             };
 
-            let q_time_after = match time_range {
-                TimeRange::None => "",
-                TimeRange::Less(_) => "created_at < $time_range",
-                TimeRange::LessOrEqual(_) => "created_at <= $time_range",
-                TimeRange::More(_) => "created_at > $time_range",
-                TimeRange::MoreOrEqual(_) => "created_at >= $time_range",
-            };
-
             let q_order = match order {
                 Order::OneTwoThree => "ASC",
                 Order::ThreeTwoOne => "DESC",
             };
 
-            let filters = [q_tags, q_time_after, q_user];
-            // let filters_len = filters.len();
-            let mut q_where = String::new();
-            let mut iter = filters.into_iter().peekable();
-            loop {
-                let Some(q) = iter.next() else {
-                    break;
-                };
-                if q.is_empty() {
-                    continue;
-                }
-                q_where.push_str(q);
-                // trace!("filters_len({filters_len}) == i({})", i + 1);
-                let next_is_empty = iter.peek().map(|v| v.is_empty()).unwrap_or(true);
-                if next_is_empty {
-                    break;
-                }
-                q_where.push_str(" AND ");
-            }
+            // muted users stay visible when their own gallery is opened
+            let q_block = if viewer_id.is_some() {
+                q_user_block_filter("user", user.is_empty())
+            } else {
+                String::new()
+            };
+
+            // owners also see their drafts, scheduled and unlisted posts in their own gallery
+            let q_visibility = if !user.is_empty() {
+                "(visibility = 'public' OR user = $viewer_id)"
+            } else {
+                "visibility = 'public'"
+            };
+
+            let filters = [
+                "show = true",
+                "deleted_at = 0",
+                q_visibility,
+                "rating IN $ratings",
+                q_tags,
+                q_cursor,
+                q_user,
+                q_block.as_str(),
+            ];
+            let q_where = filters
+                .into_iter()
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<&str>>()
+                .join(" AND ");
 
             let q = format!(
                 "
                 SELECT *, user.* FROM post WHERE 
                     {q_where}   
-                    ORDER BY created_at {q_order}
+                    ORDER BY created_at {q_order}, id {q_order}
                     LIMIT $get_limit;
             "
             );
@@ -2016,13 +3589,91 @@ pub mod post {
             self.db
                 .query(q)
                 .bind(("get_limit", limit))
-                .bind(("time_range", time_range_val))
+                .bind(("cursor_time", cursor_time))
+                .bind(("cursor_id", cursor_id))
                 .bind(("tags", tags))
                 .bind(("user", user))
+                .bind(("viewer_id", viewer_id))
+                .bind(("ratings", max_rating.up_to()))
                 .await
                 .check_good(surrealdb::Error::from)
                 .and_then_take_all(0)
         }
+
+        pub async fn get_post_related(
+            &self,
+            post_key: impl Into<RecordIdKey>,
+            limit: usize,
+        ) -> Result<Vec<DBUserPost>, DB404Err> {
+            let post_id = create_post_id(post_key);
+            let post = self.get_post(post_id.key.clone()).await?;
+
+            let tags = post
+                .tags
+                .to_lowercase()
+                .split_whitespace()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>();
+
+            // tags are matched word by word, a plain CONTAINSANY on the string would match substrings
+            let (candidates, co_liked) = self
+                .db
+                .query(
+                    r#"
+                 LET $likers = SELECT VALUE user FROM post_like WHERE post = $post_id;
+                 LET $co_liked = SELECT VALUE post FROM post_like WHERE user IN $likers AND post != $post_id;
+                 SELECT id, user, tags, created_at FROM post WHERE
+                    id != $post_id AND show = true AND deleted_at = 0 AND visibility = "public" AND
+                    (user = $user_id OR id IN $co_liked OR string::words(string::lowercase(tags)) CONTAINSANY $tags);
+                 RETURN $co_liked;
+                "#,
+                )
+                .bind(("post_id", post_id))
+                .bind(("user_id", post.user.id.clone()))
+                .bind(("tags", tags))
+                .await
+                .check_good(DB404Err::from)
+                .and_then(|mut result| {
+                    let candidates = result.take::<Vec<DBPostRelatedCandidate>>(2)?;
+                    let co_liked = result.take::<Vec<RecordId>>(3)?;
+                    Ok((candidates, co_liked))
+                })?;
+
+            // every candidate is ranked before the limit so older strong matches aren't cut off
+            let mut scored = candidates
+                .into_iter()
+                .map(|candidate| {
+                    let co_likes = co_liked.iter().filter(|v| **v == candidate.id).count();
+                    let same_artist = candidate.user == post.user.id;
+                    let score =
+                        post_related_score(&post.tags, &candidate.tags, co_likes, same_artist);
+                    (score, candidate)
+                })
+                .filter(|(score, _)| *score > 0.0)
+                .collect::<Vec<(f64, DBPostRelatedCandidate)>>();
+
+            scored.sort_by(|(score_a, post_a), (score_b, post_b)| {
+                score_b
+                    .total_cmp(score_a)
+                    .then(post_b.created_at.cmp(&post_a.created_at))
+            });
+            let ids = scored
+                .into_iter()
+                .take(limit)
+                .map(|(_, candidate)| candidate.id)
+                .collect::<Vec<RecordId>>();
+
+            let mut posts = self
+                .db
+                .query("SELECT *, user.* FROM post WHERE id IN $ids;")
+                .bind(("ids", ids.clone()))
+                .await
+                .check_good(DB404Err::from)
+                .and_then_take_all::<DBUserPost>(0)?;
+            posts.sort_by_key(|post| ids.iter().position(|id| *id == post.id));
+
+            Ok(posts)
+        }
     }
 
     #[cfg(test)]
@@ -2031,11 +3682,18 @@ pub mod post {
         use std::time::Duration;
 
         use surrealdb::engine::local::Mem;
+        use surrealdb::types::RecordId;
         use tracing::trace;
 
         use crate::{
-            api::{Order, TimeRange},
-            db::{DB404Err, DBEmailIsTakenErr, DBUserPostFile, Db, email_change::DBChangeEmailErr},
+            api::{
+                Order,
+                shared::cursor::{Cursor, CursorRange},
+            },
+            db::{
+                DB404Err, DBEmailIsTakenErr, DBPostRating, DBPostRatingErr, DBPostVisibility,
+                DBUserPostFile, Db, audit::DBAudit, email_change::DBChangeEmailErr,
+            },
         };
 
         #[tokio::test]
@@ -2048,15 +3706,45 @@ pub mod post {
             let user = db.add_user(0, "hey", "hey@hey.com", "123").await.unwrap();
 
             let post0 = db
-                .add_post(1, "hey", "1", "description", "one two three", 0)
+                .add_post(
+                    1,
+                    "hey",
+                    "1",
+                    "description",
+                    "one two three",
+                    0,
+                    DBPostRating::General,
+                    DBPostVisibility::Public,
+                    0,
+                )
                 .await
                 .unwrap();
             let post1 = db
-                .add_post(2, "hey", "2", "description", "one two", 0)
+                .add_post(
+                    2,
+                    "hey",
+                    "2",
+                    "description",
+                    "one two",
+                    0,
+                    DBPostRating::General,
+                    DBPostVisibility::Public,
+                    0,
+                )
                 .await
                 .unwrap();
             let post2 = db
-                .add_post(3, "hey", "3", "description", "one", 0)
+                .add_post(
+                    3,
+                    "hey",
+                    "3",
+                    "description",
+                    "one",
+                    0,
+                    DBPostRating::General,
+                    DBPostVisibility::Public,
+                    0,
+                )
                 .await
                 .unwrap();
 
@@ -2065,10 +3753,12 @@ pub mod post {
                 let result = db
                     .post_search(
                         3,
-                        TimeRange::LessOrEqual(3),
+                        CursorRange::Before(Cursor::at(4)),
                         Order::ThreeTwoOne,
                         " three  two     ",
                         "hey",
+                        None,
+                        DBPostRating::Explicit,
                     )
                     .await
                     .unwrap();
@@ -2078,10 +3768,12 @@ pub mod post {
                 let result = db
                     .post_search(
                         3,
-                        TimeRange::LessOrEqual(3),
+                        CursorRange::Before(Cursor::at(4)),
                         Order::ThreeTwoOne,
                         " three  two     ",
                         "hey2",
+                        None,
+                        DBPostRating::Explicit,
                     )
                     .await
                     .unwrap();
@@ -2091,10 +3783,12 @@ pub mod post {
             let result = db
                 .post_search(
                     3,
-                    TimeRange::LessOrEqual(3),
+                    CursorRange::Before(Cursor::at(4)),
                     Order::ThreeTwoOne,
                     " three  two     ",
                     String::new(),
+                    None,
+                    DBPostRating::Explicit,
                 )
                 .await
                 .unwrap();
@@ -2104,10 +3798,12 @@ pub mod post {
             let result = db
                 .post_search(
                     3,
-                    TimeRange::LessOrEqual(3),
+                    CursorRange::Before(Cursor::at(4)),
                     Order::ThreeTwoOne,
                     "three two",
                     String::new(),
+                    None,
+                    DBPostRating::Explicit,
                 )
                 .await
                 .unwrap();
@@ -2117,10 +3813,12 @@ pub mod post {
             let result = db
                 .post_search(
                     3,
-                    TimeRange::LessOrEqual(3),
+                    CursorRange::Before(Cursor::at(4)),
                     Order::ThreeTwoOne,
                     "two",
                     String::new(),
+                    None,
+                    DBPostRating::Explicit,
                 )
                 .await
                 .unwrap();
@@ -2131,10 +3829,28 @@ pub mod post {
             let result = db
                 .post_search(
                     3,
-                    TimeRange::LessOrEqual(3),
+                    CursorRange::Before(Cursor::at(4)),
+                    Order::OneTwoThree,
+                    "two",
+                    String::new(),
+                    None,
+                    DBPostRating::Explicit,
+                )
+                .await
+                .unwrap();
+            assert_eq!(result.len(), 2);
+            assert_eq!(&result[0].title, "1");
+            assert_eq!(&result[1].title, "2");
+
+            let result = db
+                .post_search(
+                    3,
+                    CursorRange::After(Cursor::at(1)),
                     Order::OneTwoThree,
                     "two",
                     String::new(),
+                    None,
+                    DBPostRating::Explicit,
                 )
                 .await
                 .unwrap();
@@ -2145,10 +3861,12 @@ pub mod post {
             let result = db
                 .post_search(
                     3,
-                    TimeRange::MoreOrEqual(1),
+                    CursorRange::None,
                     Order::OneTwoThree,
                     "two",
                     String::new(),
+                    None,
+                    DBPostRating::Explicit,
                 )
                 .await
                 .unwrap();
@@ -2157,7 +3875,15 @@ pub mod post {
             assert_eq!(&result[1].title, "2");
 
             let result = db
-                .post_search(3, TimeRange::None, Order::OneTwoThree, "two", String::new())
+                .post_search(
+                    3,
+                    CursorRange::Before(Cursor::at(3)),
+                    Order::OneTwoThree,
+                    "two",
+                    String::new(),
+                    None,
+                    DBPostRating::Explicit,
+                )
                 .await
                 .unwrap();
             assert_eq!(result.len(), 2);
@@ -2167,42 +3893,417 @@ pub mod post {
             let result = db
                 .post_search(
                     3,
-                    TimeRange::Less(3),
+                    CursorRange::Before(Cursor::at(2)),
+                    Order::OneTwoThree,
+                    "two",
+                    String::new(),
+                    None,
+                    DBPostRating::Explicit,
+                )
+                .await
+                .unwrap();
+            assert_eq!(result.len(), 1);
+            assert_eq!(&result[0].title, "1");
+
+            let result = db
+                .post_search(
+                    3,
+                    CursorRange::After(Cursor::at(2)),
                     Order::OneTwoThree,
                     "two",
                     String::new(),
+                    None,
+                    DBPostRating::Explicit,
+                )
+                .await
+                .unwrap();
+            assert_eq!(result.len(), 1);
+            assert_eq!(&result[0].title, "2");
+        }
+
+        #[tokio::test]
+        async fn db_post_rating() {
+            crate::init_test_log();
+
+            let db = Db::new::<Mem>(()).await.unwrap();
+            db.migrate(0).await.unwrap();
+
+            let user = db.add_user(0, "hey", "hey@hey.com", "123").await.unwrap();
+            let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+            assert_eq!(user.max_rating, "general");
+            assert!(user.blur_rating);
+
+            let post0 = db
+                .add_post(
+                    1,
+                    "hey",
+                    "0",
+                    "",
+                    "",
+                    0,
+                    DBPostRating::General,
+                    DBPostVisibility::Public,
+                    0,
+                )
+                .await
+                .unwrap();
+            let post1 = db
+                .add_post(
+                    2,
+                    "hey",
+                    "1",
+                    "",
+                    "",
+                    0,
+                    DBPostRating::Questionable,
+                    DBPostVisibility::Public,
+                    0,
+                )
+                .await
+                .unwrap();
+            db.add_post(
+                3,
+                "hey",
+                "2",
+                "",
+                "",
+                0,
+                DBPostRating::Explicit,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+            assert_eq!(post0.rating, "general");
+            assert_eq!(post1.rating, "questionable");
+
+            for (max_rating, expected) in [
+                (DBPostRating::General, vec!["0"]),
+                (DBPostRating::Questionable, vec!["1", "0"]),
+                (DBPostRating::Explicit, vec!["2", "1", "0"]),
+            ] {
+                let result = db
+                    .post_search(
+                        10,
+                        CursorRange::None,
+                        Order::ThreeTwoOne,
+                        "",
+                        "",
+                        None,
+                        max_rating,
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    result.iter().map(|v| v.title.as_str()).collect::<Vec<_>>(),
+                    expected
+                );
+            }
+
+            let result = db
+                .update_post_rating(
+                    4,
+                    user2.id.clone(),
+                    post0.id.key.clone(),
+                    DBPostRating::Explicit,
+                )
+                .await;
+            assert!(matches!(result, Err(DBPostRatingErr::NotFound)));
+            let result = db
+                .update_post_rating(
+                    4,
+                    user.id.clone(),
+                    post0.id.key.clone(),
+                    DBPostRating::Explicit,
+                )
+                .await
+                .unwrap();
+            assert_eq!(result.rating, "explicit");
+            assert!(!result.rating_locked);
+
+            let result = db
+                .update_post_rating_moderation(
+                    5,
+                    post0.id.key.clone(),
+                    DBPostRating::Questionable,
+                    DBAudit::default(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(result.rating, "questionable");
+            assert!(result.rating_locked);
+            let result = db
+                .update_post_rating(
+                    6,
+                    user.id.clone(),
+                    post0.id.key.clone(),
+                    DBPostRating::General,
+                )
+                .await;
+            assert!(matches!(result, Err(DBPostRatingErr::Locked)));
+            let result = db
+                .update_post_rating_moderation(6, "404", DBPostRating::General, DBAudit::default())
+                .await;
+            assert!(matches!(result, Err(DB404Err::NotFound)));
+
+            let user = db
+                .update_user_rating_preference(
+                    7,
+                    user.id.clone(),
+                    DBPostRating::Questionable,
+                    false,
+                )
+                .await
+                .unwrap();
+            assert_eq!(user.max_rating, "questionable");
+            assert!(!user.blur_rating);
+        }
+
+        #[tokio::test]
+        async fn db_post_visibility() {
+            crate::init_test_log();
+
+            let db = Db::new::<Mem>(()).await.unwrap();
+            db.migrate(0).await.unwrap();
+
+            let user = db.add_user(0, "hey", "hey@hey.com", "123").await.unwrap();
+            let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+
+            let mut posts = Vec::new();
+            for (time, title, visibility, publish_at) in [
+                (1, "draft", DBPostVisibility::Draft, 0),
+                (2, "scheduled", DBPostVisibility::Scheduled, 10),
+                (3, "unlisted", DBPostVisibility::Unlisted, 0),
+                (4, "public", DBPostVisibility::Public, 0),
+            ] {
+                let post = db
+                    .add_post(
+                        time,
+                        "hey",
+                        title,
+                        "",
+                        "",
+                        0,
+                        DBPostRating::General,
+                        visibility,
+                        publish_at,
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(post.visibility, visibility.to_string());
+                posts.push(post);
+            }
+
+            let search = async |user: &str, viewer_id: Option<RecordId>| {
+                db.post_search(
+                    10,
+                    CursorRange::None,
+                    Order::ThreeTwoOne,
+                    "",
+                    user,
+                    viewer_id,
+                    DBPostRating::Explicit,
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|v| v.title)
+                .collect::<Vec<String>>()
+            };
+
+            assert_eq!(search("", Some(user.id.clone())).await, vec!["public"]);
+            assert_eq!(search("hey", Some(user2.id.clone())).await, vec!["public"]);
+            assert_eq!(search("hey", None).await, vec!["public"]);
+            assert_eq!(
+                search("hey", Some(user.id.clone())).await,
+                vec!["public", "unlisted", "scheduled", "draft"]
+            );
+
+            let result = db.update_post_publish_scheduled(9).await.unwrap();
+            assert!(result.is_empty());
+            let result = db.update_post_publish_scheduled(10).await.unwrap();
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].visibility, "public");
+            assert_eq!(result[0].created_at, 10);
+            assert_eq!(search("", None).await, vec!["scheduled", "public"]);
+
+            let result = db
+                .update_post_visibility(
+                    11,
+                    user2.id.clone(),
+                    posts[0].id.key.clone(),
+                    DBPostVisibility::Public,
+                    0,
+                )
+                .await;
+            assert!(matches!(result, Err(DB404Err::NotFound)));
+            let result = db
+                .update_post_visibility(
+                    11,
+                    user.id.clone(),
+                    posts[0].id.key.clone(),
+                    DBPostVisibility::Public,
+                    0,
+                )
+                .await
+                .unwrap();
+            assert_eq!(result.visibility, "public");
+            assert_eq!(result.created_at, 11);
+
+            let result = db
+                .update_post_visibility(
+                    12,
+                    user.id.clone(),
+                    posts[0].id.key.clone(),
+                    DBPostVisibility::Unlisted,
+                    0,
+                )
+                .await
+                .unwrap();
+            assert_eq!(result.visibility, "unlisted");
+            assert_eq!(result.created_at, 11);
+        }
+
+        #[tokio::test]
+        async fn db_post_related() {
+            crate::init_test_log();
+
+            let db = Db::new::<Mem>(()).await.unwrap();
+            db.migrate(0).await.unwrap();
+
+            db.add_user(0, "hey", "hey@hey.com", "123").await.unwrap();
+            let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+            db.add_user(0, "hey3", "hey3@hey.com", "123").await.unwrap();
+
+            let post0 = db
+                .add_post(
+                    1,
+                    "hey",
+                    "0",
+                    "description",
+                    "cat dog bird",
+                    0,
+                    DBPostRating::General,
+                    DBPostVisibility::Public,
+                    0,
+                )
+                .await
+                .unwrap();
+            db.add_post(
+                2,
+                "hey2",
+                "1",
+                "description",
+                "cat dog bird",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+            db.add_post(
+                3,
+                "hey2",
+                "2",
+                "description",
+                "cat",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+            let post3 = db
+                .add_post(
+                    4,
+                    "hey",
+                    "3",
+                    "description",
+                    "tree",
+                    0,
+                    DBPostRating::General,
+                    DBPostVisibility::Public,
+                    0,
                 )
                 .await
                 .unwrap();
-            assert_eq!(result.len(), 2);
-            assert_eq!(&result[0].title, "1");
-            assert_eq!(&result[1].title, "2");
-
-            let result = db
-                .post_search(
-                    3,
-                    TimeRange::Less(2),
-                    Order::OneTwoThree,
-                    "two",
-                    String::new(),
+            let post4 = db
+                .add_post(
+                    5,
+                    "hey3",
+                    "4",
+                    "description",
+                    "car",
+                    0,
+                    DBPostRating::General,
+                    DBPostVisibility::Public,
+                    0,
                 )
                 .await
                 .unwrap();
-            assert_eq!(result.len(), 1);
-            assert_eq!(&result[0].title, "1");
+            db.add_post(
+                6,
+                "hey3",
+                "5",
+                "description",
+                "boat",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+            // only whole tags count, "category" is no match for "cat"
+            db.add_post(
+                6,
+                "hey3",
+                "6",
+                "description",
+                "category",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
 
-            let result = db
-                .post_search(
-                    3,
-                    TimeRange::More(1),
-                    Order::OneTwoThree,
-                    "two",
-                    String::new(),
-                )
+            let result = db.get_post_related("404", 10).await;
+            assert!(matches!(result, Err(DB404Err::NotFound)));
+
+            let result = db.get_post_related(post0.id.key.clone(), 10).await.unwrap();
+            let titles = result
+                .iter()
+                .map(|v| v.title.as_str())
+                .collect::<Vec<&str>>();
+            assert_eq!(titles, ["1", "2", "3"]);
+
+            db.add_post_like(7, user2.id.clone(), post0.id.key.clone())
                 .await
                 .unwrap();
-            assert_eq!(result.len(), 1);
-            assert_eq!(&result[0].title, "2");
+            db.add_post_like(7, user2.id.clone(), post4.id.key.clone())
+                .await
+                .unwrap();
+
+            let result = db.get_post_related(post0.id.key.clone(), 10).await.unwrap();
+            let titles = result
+                .iter()
+                .map(|v| v.title.as_str())
+                .collect::<Vec<&str>>();
+            assert_eq!(titles, ["1", "2", "4", "3"]);
+
+            let result = db.get_post_related(post0.id.key.clone(), 2).await.unwrap();
+            assert_eq!(result.len(), 2);
+
+            let result = db.get_post_related(post3.id.key.clone(), 10).await.unwrap();
+            let titles = result
+                .iter()
+                .map(|v| v.title.as_str())
+                .collect::<Vec<&str>>();
+            assert_eq!(titles, ["0"]);
         }
     }
 }
@@ -2215,6 +4316,7 @@ pub mod email_change {
     use crate::db::SurrealCheckUtils;
     use crate::db::SurrealErrUtils;
     use crate::db::SurrealSerializeUtils;
+    use crate::db::audit::{DBAudit, DBAuditKind, q_audit_event_add};
 
     use super::Db;
     // use super::Save;
@@ -2414,23 +4516,50 @@ pub mod email_change {
             user: RecordId,
             new_email: impl Into<String>,
             time: u128,
+            audit: DBAudit,
         ) -> Result<DBUser, DBChangeEmailErr> {
             let new_email = new_email.into();
+            let q_audit = q_audit_event_add(
+                DBAuditKind::EmailChange,
+                "$user_id",
+                "$user.email",
+                "$new_email",
+            );
+            let q = format!(
+                r#"
+                 BEGIN TRANSACTION;
+                 LET $user = SELECT id, email FROM ONLY $user_id;
+                 IF !$user {{
+                    THROW "user not found";
+                 }};
+                 IF $user.email != $new_email AND (SELECT id FROM ONLY user WHERE email = $new_email LIMIT 1) {{
+                    THROW "email is taken";
+                 }};
+                 LET $updated = UPDATE ONLY $user_id SET modified_at = $time, email = $new_email;
+                 {q_audit}
+                 RETURN $updated;
+                 COMMIT TRANSACTION;
+                "#
+            );
+            trace!("about to run {q}");
             self.db
-                .query(
-                    "UPDATE user SET modified_at = $time, email = $new_email WHERE id = $user_id;",
-                )
+                .query(q)
                 .bind(("user_id", user))
                 .bind(("new_email", new_email.clone()))
                 .bind(("time", time))
+                .bind(("audit", audit))
                 .await
-                .check_good(|err| match err {
-                    err if err.index_exists("idx_user_email") => {
+                .check_better(|err| match err.message() {
+                    "An error occurred: user not found" => DBChangeEmailErr::NotFound,
+                    "An error occurred: email is taken" => {
                         DBChangeEmailErr::EmailIsTaken(new_email)
                     }
-                    err => err.into(),
+                    _ if err.index_exists("idx_user_email") => {
+                        DBChangeEmailErr::EmailIsTaken(new_email)
+                    }
+                    _ => DBChangeEmailErr::DB(err),
                 })
-                .and_then_take_or(0, DBChangeEmailErr::NotFound)
+                .and_then_take_or(6, DBChangeEmailErr::NotFound)
         }
 
         pub async fn get_email_change_all(&self) -> Result<Vec<DBEmailChange>, surrealdb::Error> {
@@ -2518,7 +4647,9 @@ pub mod email_change {
         use surrealdb::engine::local::Mem;
         use tracing::trace;
 
-        use crate::db::{DB404Err, DBEmailIsTakenErr, Db, email_change::DBChangeEmailErr};
+        use crate::db::{
+            DB404Err, DBEmailIsTakenErr, Db, audit::DBAudit, email_change::DBChangeEmailErr,
+        };
 
         #[tokio::test]
         async fn db_email_change() {
@@ -2614,7 +4745,7 @@ pub mod email_change {
             let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
             let user2 = db.add_user(0, "hey3", "hey3@hey.com", "123").await.unwrap();
             let _result = db
-                .update_user_email(user.id.clone(), "hey2@hey.com", 0)
+                .update_user_email(user.id.clone(), "hey2@hey.com", 0, DBAudit::default())
                 .await
                 .unwrap();
             let user = db.get_user_by_email("hey2@hey.com").await.unwrap();
@@ -2625,7 +4756,7 @@ pub mod email_change {
             assert!(matches!(result, Err(DB404Err::NotFound)));
 
             let result = db
-                .update_user_email(user.id.clone(), "hey3@hey.com", 0)
+                .update_user_email(user.id.clone(), "hey3@hey.com", 0, DBAudit::default())
                 .await;
             assert!(matches!(result, Err(DBChangeEmailErr::EmailIsTaken(_))));
         }
@@ -2647,125 +4778,13 @@ impl<C: Connection> Db<C> {
 
     pub async fn get_post(&self, post_key: impl Into<RecordIdKey>) -> Result<DBUserPost, DB404Err> {
         self.db
-            .query("SELECT *, user.* FROM ONLY $post_id;")
+            .query("SELECT *, user.* FROM post WHERE id = $post_id AND deleted_at = 0;")
             .bind(("post_id", create_post_id(post_key)))
             .await
             .check_good(DB404Err::from)
             .and_then_take_or(0, DB404Err::NotFound)
     }
 
-    pub async fn get_post_newer_or_equal_for_user(
-        &self,
-        time: u128,
-        limit: u32,
-        user: RecordId,
-    ) -> Result<Vec<DBUserPost>, surrealdb::Error> {
-        self.db.query("(SELECT *, user.* FROM post WHERE created_at >= $created_at AND user = $user ORDER BY created_at ASC LIMIT $post_limit).reverse()")
-            .bind(("post_limit", limit))
-            .bind(("created_at", time))
-            .bind(("user", user))
-            .await
-            .check_good(surrealdb::Error::from)
-            .and_then_take_all(0)
-    }
-
-    pub async fn get_post_older_or_equal_for_user(
-        &self,
-        time: u128,
-        limit: u32,
-        user: RecordId,
-    ) -> Result<Vec<DBUserPost>, surrealdb::Error> {
-        self.db.query("SELECT *, user.* FROM post WHERE created_at <= $created_at AND user = $user ORDER BY created_at DESC LIMIT $post_limit")
-            .bind(("post_limit", limit))
-            .bind(("created_at", time))
-            .bind(("user", user))
-            .await
-            .check_good(surrealdb::Error::from)
-            .and_then_take_all(0)
-    }
-
-    pub async fn get_post_newer_for_user(
-        &self,
-        time: u128,
-        limit: u32,
-        user: RecordId,
-    ) -> Result<Vec<DBUserPost>, surrealdb::Error> {
-        self.db.query("(SELECT *, user.* FROM post WHERE created_at > $created_at AND user = $user ORDER BY created_at ASC LIMIT $post_limit).reverse()")
-            .bind(("post_limit", limit))
-            .bind(("created_at", time))
-            .bind(("user", user))
-            .await
-            .check_good(surrealdb::Error::from)
-            .and_then_take_all(0)
-    }
-
-    pub async fn get_post_older_for_user(
-        &self,
-        time: u128,
-        limit: u32,
-        user: RecordId,
-    ) -> Result<Vec<DBUserPost>, surrealdb::Error> {
-        self.db.query("SELECT *, user.* FROM post WHERE created_at < $created_at AND user = $user ORDER BY created_at DESC LIMIT $post_limit")
-            .bind(("post_limit", limit))
-            .bind(("created_at", time))
-            .bind(("user", user))
-            .await
-            .check_good(surrealdb::Error::from)
-            .and_then_take_all(0)
-    }
-
-    pub async fn get_post_newer_or_equal(
-        &self,
-        time: u128,
-        limit: u32,
-    ) -> Result<Vec<DBUserPost>, surrealdb::Error> {
-        self.db.query("(SELECT *, user.* FROM post WHERE created_at >= $created_at ORDER BY created_at ASC LIMIT $post_limit).reverse()")
-            .bind(("post_limit", limit))
-            .bind(("created_at", time))
-            .await
-            .check_good(surrealdb::Error::from)
-            .and_then_take_all(0)
-    }
-
-    pub async fn get_post_older_or_equal(
-        &self,
-        time: u128,
-        limit: u32,
-    ) -> Result<Vec<DBUserPost>, surrealdb::Error> {
-        self.db.query("SELECT *, user.* FROM post WHERE created_at <= $created_at ORDER BY created_at DESC LIMIT $post_limit")
-            .bind(("post_limit", limit))
-            .bind(("created_at", time))
-            .await
-            .check_good(surrealdb::Error::from)
-            .and_then_take_all(0)
-    }
-
-    pub async fn get_post_newer(
-        &self,
-        time: u128,
-        limit: u32,
-    ) -> Result<Vec<DBUserPost>, surrealdb::Error> {
-        self.db.query("(SELECT *, user.* FROM post WHERE created_at > $created_at ORDER BY created_at ASC LIMIT $post_limit).reverse()")
-            .bind(("post_limit", limit))
-            .bind(("created_at", time))
-            .await
-            .check_good(surrealdb::Error::from)
-            .and_then_take_all(0)
-    }
-
-    pub async fn get_post_older(
-        &self,
-        time: u128,
-        limit: u32,
-    ) -> Result<Vec<DBUserPost>, surrealdb::Error> {
-        self.db.query("SELECT *, user.* FROM post WHERE created_at < $created_at ORDER BY created_at DESC LIMIT $post_limit")
-            .bind(("post_limit", limit))
-            .bind(("created_at", time))
-            .await
-            .check_good(surrealdb::Error::from)
-            .and_then_take_all(0)
-    }
-
     pub async fn get_post_all(
         &self,
         // time: u128,
@@ -2788,6 +4807,9 @@ impl<C: Connection> Db<C> {
         description: impl Into<String>,
         tags: impl Into<String>,
         favorites: u64,
+        rating: DBPostRating,
+        visibility: DBPostVisibility,
+        publish_at: u128,
     ) -> Result<DBUserPost, AddPostErr> {
         let username = username.into();
         let title = title.into();
@@ -2805,6 +4827,9 @@ impl<C: Connection> Db<C> {
                 title = $title,
                 description = $description,
                 tags = $tags,
+                rating = $rating,
+                visibility = $visibility,
+                publish_at = $publish_at,
                 size_bytes = 0,
                 favorites = $favorites,
                 file = [],
@@ -2819,6 +4844,9 @@ impl<C: Connection> Db<C> {
             .bind(("description", description))
             .bind(("tags", tags))
             .bind(("favorites", favorites))
+            .bind(("rating", rating.to_string()))
+            .bind(("visibility", visibility.to_string()))
+            .bind(("publish_at", publish_at))
             .bind(("time", time))
             .await
             .check_good(|err| match err {
@@ -2840,9 +4868,19 @@ impl<C: Connection> Db<C> {
                 r#"
              BEGIN TRANSACTION;
 
-             DELETE post WHERE id = $post_id AND user = $user_id;
-             DELETE post_comment WHERE post == $post_id AND user = $user_id;
-             DELETE post_like WHERE post = $post_id AND user = $user_id;
+             LET $deleted = DELETE post WHERE id = $post_id AND user = $user_id RETURN BEFORE;
+             IF $deleted {
+                LET $comments = DELETE post_comment WHERE post = $post_id RETURN BEFORE;
+                DELETE revision WHERE target IN $comments.id;
+                DELETE post_like WHERE post = $post_id;
+                DELETE ap_reaction WHERE post = $post_id;
+                UPDATE $user_id SET used_storage_bytes -= math::sum($deleted.size_bytes);
+                DELETE notification WHERE post = $post_id;
+                DELETE revision WHERE target = $post_id;
+                LET $collections = SELECT VALUE collection FROM collection_item WHERE post = $post_id;
+                DELETE collection_item WHERE post = $post_id;
+                UPDATE $collections SET items_count -= 1;
+             };
 
              COMMIT TRANSACTION;
             "#,
@@ -2919,18 +4957,66 @@ impl<C: Connection> Db<C> {
         user: RecordId,
         max_storage_bytes: usize,
         max_storage_per_file_bytes: usize,
+        audit: DBAudit,
     ) -> Result<DBUser, DB404Err> {
+        let q_audit = q_audit_event_add(
+            DBAuditKind::StorageChange,
+            "$user_id",
+            "string::concat($user.max_storage_bytes, \" / \", $user.max_storage_per_file_bytes)",
+            "string::concat($max_storage, \" / \", $max_storage_per_file)",
+        );
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $user = SELECT id, max_storage_bytes, max_storage_per_file_bytes FROM ONLY $user_id;
+                 IF !$user {{
+                    THROW "user not found";
+                 }};
+                 LET $updated = UPDATE ONLY $user_id SET
+                    modified_at = $time,
+                    max_storage_bytes = $max_storage,
+                    max_storage_per_file_bytes = $max_storage_per_file;
+                 {q_audit}
+                 RETURN $updated;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
         self.db
-            .query(
-                "UPDATE $user_id SET 
-                    modified_at = $time, 
-                    max_storage_bytes = $max_storage, 
-                    max_storage_per_file_bytes = $max_storage_per_file;",
-            )
+            .query(q)
             .bind(("user_id", user))
             .bind(("max_storage", max_storage_bytes))
             .bind(("max_storage_per_file", max_storage_per_file_bytes))
             .bind(("time", time))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: user not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_or(5, DB404Err::NotFound)
+    }
+
+    pub async fn update_user_rating_preference(
+        &self,
+        time: u128,
+        user: RecordId,
+        max_rating: DBPostRating,
+        blur_rating: bool,
+    ) -> Result<DBUser, DB404Err> {
+        self.db
+            .query(
+                r#"
+                 UPDATE ONLY $user_id SET
+                    modified_at = $time,
+                    max_rating = $max_rating,
+                    blur_rating = $blur_rating;
+                "#,
+            )
+            .bind(("user_id", user))
+            .bind(("max_rating", max_rating.to_string()))
+            .bind(("blur_rating", blur_rating))
+            .bind(("time", time))
             .await
             .check_good(DB404Err::from)
             .and_then_take_or(0, DB404Err::NotFound)
@@ -2941,39 +5027,87 @@ impl<C: Connection> Db<C> {
         user: RecordId,
         new_username: impl Into<String>,
         time: u128,
+        audit: DBAudit,
     ) -> Result<DBUser, DBChangeUsernameErr> {
         let username = new_username.into();
+        let q_audit = q_audit_event_add(
+            DBAuditKind::UsernameChange,
+            "$user_id",
+            "$user.username",
+            "$new_username",
+        );
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $user = SELECT id, username FROM ONLY $user_id;
+                 IF !$user {{
+                    THROW "user not found";
+                 }};
+                 IF $user.username != $new_username AND (SELECT id FROM ONLY user WHERE username = $new_username LIMIT 1) {{
+                    THROW "username is taken";
+                 }};
+                 LET $updated = UPDATE ONLY $user_id SET modified_at = $time, username = $new_username;
+                 {q_audit}
+                 RETURN $updated;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
         self.db
-            .query(
-                "UPDATE user SET modified_at = $time, username = $new_username WHERE id = $user_id;",
-            )
+            .query(q)
             .bind(("user_id", user))
             .bind(("new_username", username.clone()))
             .bind(("time", time))
+            .bind(("audit", audit))
             .await
-            .check_good(|err| match err {
-                err if err.index_exists("idx_user_username") => DBChangeUsernameErr::UsernameIsTaken(username),
-                err => err.into(),
+            .check_better(|err| match err.message() {
+                "An error occurred: user not found" => DBChangeUsernameErr::NotFound,
+                "An error occurred: username is taken" => {
+                    DBChangeUsernameErr::UsernameIsTaken(username)
+                }
+                _ if err.index_exists("idx_user_username") => {
+                    DBChangeUsernameErr::UsernameIsTaken(username)
+                }
+                _ => DBChangeUsernameErr::DB(err),
             })
-            .and_then_take_or(0, DBChangeUsernameErr::NotFound)
+            .and_then_take_or(6, DBChangeUsernameErr::NotFound)
     }
 
+    /// the password hash itself never ends up in the audit log
     pub async fn update_user_password(
         &self,
         user: RecordId,
         new_password: impl Into<String>,
         time: u128,
+        audit: DBAudit,
     ) -> Result<DBUser, DB404Err> {
+        let q_audit = q_audit_event_add(DBAuditKind::PasswordChange, "$user_id", "\"\"", "\"\"");
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $user = SELECT id FROM ONLY $user_id;
+                 IF !$user {{
+                    THROW "user not found";
+                 }};
+                 LET $updated = UPDATE ONLY $user_id SET modified_at = $time, password = $new_password;
+                 {q_audit}
+                 RETURN $updated;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
         self.db
-            .query(
-                "UPDATE user SET modified_at = $time, password = $new_password WHERE id = $user_id;",
-            )
+            .query(q)
             .bind(("user_id", user))
             .bind(("new_password", new_password.into()))
             .bind(("time", time))
+            .bind(("audit", audit))
             .await
-            .check_good(DB404Err::from)
-            .and_then_take_or(0, DB404Err::NotFound)
+            .check_better(|err| match err.message() {
+                "An error occurred: user not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_or(5, DB404Err::NotFound)
     }
 
     pub async fn update_user_password_by_email(
@@ -2981,17 +5115,35 @@ impl<C: Connection> Db<C> {
         time: u128,
         email: impl Into<String>,
         new_password: impl Into<String>,
+        audit: DBAudit,
     ) -> Result<DBUser, DB404Err> {
+        let q_audit = q_audit_event_add(DBAuditKind::PasswordChange, "$user.id", "\"\"", "\"\"");
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $user = SELECT id FROM ONLY user WHERE email = $email LIMIT 1;
+                 IF !$user {{
+                    THROW "user not found";
+                 }};
+                 LET $updated = UPDATE ONLY $user.id SET modified_at = $time, password = $new_password;
+                 {q_audit}
+                 RETURN $updated;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
         self.db
-            .query(
-                "UPDATE user SET modified_at = $time, password = $new_password WHERE email = $email;",
-            )
+            .query(q)
             .bind(("email", email.into()))
             .bind(("new_password", new_password.into()))
             .bind(("time", time))
+            .bind(("audit", audit))
             .await
-            .check_good(DB404Err::from)
-            .and_then_take_or(0, DB404Err::NotFound)
+            .check_better(|err| match err.message() {
+                "An error occurred: user not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_or(5, DB404Err::NotFound)
     }
 
     pub async fn add_user<Username: Into<String>, Email: Into<String>, Password: Into<String>>(
@@ -3015,6 +5167,9 @@ impl<C: Connection> Db<C> {
                 max_storage_per_file_bytes = $max_storage_per_file,
                 max_storage_bytes = $max_storage,
                 password = $password,
+                role = "user",
+                banned = false,
+                suspended_until = 0,
                 modified_at = $time,
                 created_at = $time;
             "#,
@@ -3084,11 +5239,14 @@ mod tests {
     use tracing::trace;
 
     use crate::{
-        api::ChangeUsernameErr,
+        api::{
+            ChangeUsernameErr, Order,
+            shared::cursor::{Cursor, CursorRange},
+        },
         db::{
             AddUserErr, DB404Err, DBChangeUsernameErr, DBEmailIsTakenErr, DBPostAddFileErr,
-            DBPostOrderFileErr, DBPostRemoveFileErr, DBSentEmailReason, DBUserPost, DBUserPostFile,
-            Db,
+            DBPostOrderFileErr, DBPostRating, DBPostRemoveFileErr, DBPostVisibility,
+            DBSentEmailReason, DBUserPost, DBUserPostFile, Db, audit::DBAudit,
         },
         valid::{MAX_STORAGE, MAX_STORAGE_PER_FILE},
     };
@@ -3141,12 +5299,32 @@ mod tests {
         let user = db.add_user(0, "hey", "hey@hey.com", "123").await.unwrap();
 
         let post = db
-            .add_post(0, "hey", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
         let post2 = db
-            .add_post(0, "hey", "title2", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title2",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -3213,7 +5391,17 @@ mod tests {
         let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
 
         let post = db
-            .add_post(0, "hey", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -3231,7 +5419,17 @@ mod tests {
         let user = db.add_user(0, "hey", "hey@hey.com", "123").await.unwrap();
 
         let post = db
-            .add_post(0, "hey", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -3253,7 +5451,17 @@ mod tests {
         let user = db.add_user(0, "hey", "hey@hey.com", "123").await.unwrap();
 
         let post = db
-            .add_post(0, "hey", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -3276,12 +5484,32 @@ mod tests {
         let user = db.add_user(0, "hey", "hey@hey.com", "123").await.unwrap();
 
         let post0 = db
-            .add_post(0, "hey", "title0", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title0",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
         let post1 = db
-            .add_post(0, "hey", "title1", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title1",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -3354,11 +5582,31 @@ mod tests {
         let user = db.add_user(0, "hey", "hey@hey.com", "123").await.unwrap();
 
         let post = db
-            .add_post(0, "hey", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
         let post2 = db
-            .add_post(1, "hey", "title2", "description", "", 0)
+            .add_post(
+                1,
+                "hey",
+                "title2",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -3405,15 +5653,45 @@ mod tests {
         let user = db.add_user(0, "hey", "hey@hey.com", "123").await.unwrap();
 
         let post = db
-            .add_post(0, "hey", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
         let post2 = db
-            .add_post(0, "hey", "title2", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title2",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
         let post3 = db
-            .add_post(0, "hey", "title3", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title3",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
         assert!(post.file.len() == 0);
@@ -3446,7 +5724,17 @@ mod tests {
         let user = db.add_user(0, "hey", "hey@hey.com", "123").await.unwrap();
 
         let post = db
-            .add_post(0, "hey", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
         assert!(post.file.len() == 0);
@@ -3537,7 +5825,17 @@ mod tests {
         let user = db.add_user(0, "hey", "hey@hey.com", "123").await.unwrap();
         let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
         let post = db
-            .add_post(0, "hey", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
         let post_err = db
@@ -3555,7 +5853,17 @@ mod tests {
         db.migrate(0).await.unwrap();
         let user = db.add_user(0, "hey", "hey@hey.com", "123").await.unwrap();
         let post = db
-            .add_post(0, "hey", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -3645,7 +5953,17 @@ mod tests {
 
         //TODO using username instead of id? i dont like it
         let post = db
-            .add_post(0, "hey", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
         trace!("{post:#?}");
@@ -3656,26 +5974,58 @@ mod tests {
 
         for i in 1..=3 {
             let _post = db
-                .add_post(i, "hey", format!("title{i}"), "description", "", 0)
+                .add_post(
+                    i,
+                    "hey",
+                    format!("title{i}"),
+                    "description",
+                    "",
+                    0,
+                    DBPostRating::General,
+                    DBPostVisibility::Public,
+                    0,
+                )
                 .await
                 .unwrap();
         }
 
-        let posts = db.get_post_older(2, 3).await.unwrap();
+        let search = |limit: usize, cursor: CursorRange, order: Order, user: &'static str| {
+            db.post_search(limit, cursor, order, "", user, None, DBPostRating::Explicit)
+        };
+
+        let posts = search(
+            3,
+            CursorRange::Before(Cursor::at(2)),
+            Order::ThreeTwoOne,
+            "",
+        )
+        .await
+        .unwrap();
         assert_eq!(posts.len(), 2);
         assert_eq!(posts[0].title, "title1");
         assert_eq!(posts[1].title, "title");
 
-        let posts = db.get_post_older(2, 1).await.unwrap();
+        let posts = search(
+            1,
+            CursorRange::Before(Cursor::at(2)),
+            Order::ThreeTwoOne,
+            "",
+        )
+        .await
+        .unwrap();
         assert_eq!(posts.len(), 1);
         assert_eq!(posts[0].title, "title1");
 
-        let posts = db.get_post_newer(1, 3).await.unwrap();
+        let posts = search(3, CursorRange::After(Cursor::at(2)), Order::OneTwoThree, "")
+            .await
+            .unwrap();
         assert_eq!(posts.len(), 2);
-        assert_eq!(posts[0].title, "title3");
-        assert_eq!(posts[1].title, "title2");
+        assert_eq!(posts[0].title, "title2");
+        assert_eq!(posts[1].title, "title3");
 
-        let posts = db.get_post_newer(1, 1).await.unwrap();
+        let posts = search(1, CursorRange::After(Cursor::at(2)), Order::OneTwoThree, "")
+            .await
+            .unwrap();
         assert_eq!(posts.len(), 1);
         assert_eq!(posts[0].title, "title2");
 
@@ -3686,24 +6036,44 @@ mod tests {
         trace!("result: {post:#?}");
         assert!(matches!(post, Err(DB404Err::NotFound)));
 
-        let posts = db
-            .get_post_newer_or_equal_for_user(1, 3, user.id.clone())
-            .await
-            .unwrap();
+        let posts = search(
+            3,
+            CursorRange::After(Cursor::at(1)),
+            Order::ThreeTwoOne,
+            "hey",
+        )
+        .await
+        .unwrap();
         assert_eq!(posts.len(), 3);
         assert_eq!(posts[0].title, "title3");
 
-        let posts = db
-            .get_post_older_or_equal_for_user(1, 3, user.id.clone())
-            .await
-            .unwrap();
+        let posts = search(
+            3,
+            CursorRange::Before(Cursor::at(2)),
+            Order::ThreeTwoOne,
+            "hey",
+        )
+        .await
+        .unwrap();
         assert_eq!(posts.len(), 2);
         assert_eq!(posts[0].title, "title1");
 
-        let posts = db
-            .get_post_newer_or_equal_for_user(1, 3, user2.id.clone())
+        // the cursor of the last item continues right after it
+        let next = Cursor::new(posts[0].created_at, posts[0].id.key.to_sql());
+        let posts = search(3, CursorRange::Before(next), Order::ThreeTwoOne, "hey")
             .await
             .unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].title, "title");
+
+        let posts = search(
+            3,
+            CursorRange::After(Cursor::at(1)),
+            Order::ThreeTwoOne,
+            "hey2",
+        )
+        .await
+        .unwrap();
         assert_eq!(posts.len(), 0);
     }
 
@@ -3721,7 +6091,9 @@ mod tests {
         assert_eq!(user.max_storage_bytes, MAX_STORAGE);
         assert_eq!(user.max_storage_per_file_bytes, MAX_STORAGE_PER_FILE);
 
-        db.update_user_storage(0, user.id, 20, 10).await.unwrap();
+        db.update_user_storage(0, user.id, 20, 10, DBAudit::default())
+            .await
+            .unwrap();
 
         let user = db.get_user_by_username(user.username).await.unwrap();
         assert_eq!(user.max_storage_bytes, 20);
@@ -3770,7 +6142,7 @@ mod tests {
         assert!(matches!(result, Err(DB404Err::NotFound)));
 
         let result = db
-            .update_user_username(user1.id.clone(), "hey5", time)
+            .update_user_username(user1.id.clone(), "hey5", time, DBAudit::default())
             .await
             .unwrap();
         assert_eq!(result.username, "hey5");
@@ -3784,7 +6156,7 @@ mod tests {
             .unwrap();
 
         let result = db
-            .update_user_username(user1.id.clone(), "hey2", time)
+            .update_user_username(user1.id.clone(), "hey2", time, DBAudit::default())
             .await;
         assert!(matches!(
             result,
@@ -3792,13 +6164,13 @@ mod tests {
         ));
 
         let result = db
-            .update_user_password(user1.id.clone(), "pass1", time)
+            .update_user_password(user1.id.clone(), "pass1", time, DBAudit::default())
             .await;
 
         assert!(result.is_ok());
 
         let result = db
-            .update_user_password_by_email(time, "hey@hey.com", "pass3")
+            .update_user_password_by_email(time, "hey@hey.com", "pass3", DBAudit::default())
             .await;
 
         assert!(result.is_ok());
@@ -0,0 +1,841 @@
+use std::fmt::Display;
+
+use crate::api::shared::account::DATA_EXPORT_RETENTION_NS;
+use crate::db::DB404Err;
+use crate::db::DBDataExportErr;
+use crate::db::DBSentEmail;
+use crate::db::DBUser;
+use crate::db::DBUserPost;
+use crate::db::DBUserPostFile;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealSerializeUtils;
+use crate::db::audit::{DBAudit, DBAuditKind, q_audit_event_add};
+use crate::db::post_comment::DBPostComment;
+use crate::db::post_like::DBPostLike;
+use surrealdb::types::RecordIdKey;
+use surrealdb::types::SurrealValue;
+use surrealdb::types::ToSql;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBAccountDeletion {
+    pub id: RecordId,
+    pub user: RecordId,
+    pub mode: String,
+    pub confirmed: bool,
+    pub expires: u128,
+    pub delete_at: u128,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBDataExport {
+    pub id: RecordId,
+    pub user: RecordId,
+    pub status: String,
+    pub size_bytes: u64,
+    pub expires: u128,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+/// everything that goes into a data export besides the files themselves
+#[derive(Debug, Clone, PartialEq)]
+pub struct DBUserData {
+    pub user: DBUser,
+    pub posts: Vec<DBUserPost>,
+    pub comments: Vec<DBPostComment>,
+    pub likes: Vec<DBPostLike>,
+    pub sent_emails: Vec<DBSentEmail>,
+}
+
+/// what is left to clean up on disk after an account was erased
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBErasedAccount {
+    pub posts: Vec<DBUserPost>,
+    pub message_files: Vec<DBUserPostFile>,
+    pub exports: Vec<RecordId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DBAccountDeletionMode {
+    Anonymize,
+    Delete,
+}
+
+impl Display for DBAccountDeletionMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            DBAccountDeletionMode::Anonymize => "anonymize",
+            DBAccountDeletionMode::Delete => "delete",
+        };
+
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DBDataExportStatus {
+    Pending,
+    Ready,
+    Failed,
+}
+
+impl Display for DBDataExportStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            DBDataExportStatus::Pending => "pending",
+            DBDataExportStatus::Ready => "ready",
+            DBDataExportStatus::Failed => "failed",
+        };
+
+        write!(f, "{}", text)
+    }
+}
+
+pub fn create_account_deletion_id(id: impl Into<RecordIdKey>) -> RecordId {
+    RecordId::new("account_deletion", id.into())
+}
+
+pub fn create_data_export_id(id: impl Into<RecordIdKey>) -> RecordId {
+    RecordId::new("data_export", id.into())
+}
+
+impl<C: Connection> Db<C> {
+    /// replaces any earlier request, the record key is the secret that gets emailed
+    pub async fn add_account_deletion(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        mode: DBAccountDeletionMode,
+        expires: u128,
+    ) -> Result<DBAccountDeletion, surrealdb::Error> {
+        let q = r#"
+             BEGIN TRANSACTION;
+             DELETE account_deletion WHERE user = $user_id;
+             CREATE ONLY account_deletion SET
+                user = $user_id,
+                mode = $mode,
+                confirmed = false,
+                expires = $expires,
+                delete_at = 0,
+                modified_at = $time,
+                created_at = $time;
+             COMMIT TRANSACTION;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("mode", mode.to_string()))
+            .bind(("expires", expires))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_expect(2)
+    }
+
+    pub async fn confirm_account_deletion(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        deletion_key: impl Into<RecordIdKey>,
+        delete_at: u128,
+        audit: DBAudit,
+    ) -> Result<DBAccountDeletion, DB404Err> {
+        let q_audit = q_audit_event_add(
+            DBAuditKind::AccountDeletionSchedule,
+            "$user_id",
+            "\"\"",
+            "$deletion.mode",
+        );
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $deletion = SELECT * FROM ONLY $deletion_id;
+                 IF !$deletion OR $deletion.user != $user_id OR $deletion.confirmed OR $deletion.expires <= $time {{
+                    THROW "not found";
+                 }};
+                 LET $updated = UPDATE ONLY $deletion_id SET confirmed = true, delete_at = $delete_at, modified_at = $time;
+                 {q_audit}
+                 RETURN $updated;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("deletion_id", create_account_deletion_id(deletion_key)))
+            .bind(("delete_at", delete_at))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_or(5, DB404Err::NotFound)
+    }
+
+    pub async fn delete_account_deletion(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        audit: DBAudit,
+    ) -> Result<(), DB404Err> {
+        let q_audit = q_audit_event_add(
+            DBAuditKind::AccountDeletionCancel,
+            "$user_id",
+            "$deleted[0].mode",
+            "\"\"",
+        );
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $deleted = DELETE account_deletion WHERE user = $user_id RETURN BEFORE;
+                 IF !$deleted {{
+                    THROW "not found";
+                 }};
+                 {q_audit}
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .map(|_| ())
+    }
+
+    /// unconfirmed requests stop showing up once their link expires
+    pub async fn get_account_deletion(
+        &self,
+        time: u128,
+        user_id: RecordId,
+    ) -> Result<DBAccountDeletion, DB404Err> {
+        let q = r#"
+            SELECT * FROM account_deletion WHERE user = $user_id AND (confirmed = true OR expires > $time) LIMIT 1;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    pub async fn get_account_deletions_due(
+        &self,
+        time: u128,
+    ) -> Result<Vec<DBAccountDeletion>, surrealdb::Error> {
+        let q = r#"
+            SELECT * FROM account_deletion WHERE confirmed = true AND delete_at <= $time ORDER BY delete_at ASC;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    /// deletes or anonymizes the users contributions and strips the account down to a row without personal data,
+    /// the row stays so anonymized posts and other peoples audit events keep pointing somewhere
+    pub async fn erase_account(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        mode: DBAccountDeletionMode,
+    ) -> Result<DBErasedAccount, DB404Err> {
+        let delete_contributions = mode == DBAccountDeletionMode::Delete;
+        let key = user_id.key.to_sql();
+
+        // trashed content goes either way, nobody can restore it anymore.
+        // comments go newest first so own replies are gone before their parents are looked at,
+        // the ones that still have replies stay as empty placeholders
+        let q = r#"
+             BEGIN TRANSACTION;
+             LET $user = SELECT id, email FROM ONLY $user_id;
+             IF !$user {
+                THROW "not found";
+             };
+             LET $posts = SELECT *, user.* FROM post WHERE user = $user_id AND ($delete_contributions OR deleted_at > 0);
+             DELETE post WHERE id IN $posts.id;
+             LET $post_comments = DELETE post_comment WHERE post IN $posts.id RETURN BEFORE;
+             DELETE revision WHERE target IN $post_comments.id OR target IN $posts.id;
+             DELETE post_like WHERE post IN $posts.id;
+             DELETE ap_reaction WHERE post IN $posts.id;
+             DELETE notification WHERE post IN $posts.id;
+             LET $post_collections = DELETE collection_item WHERE post IN $posts.id RETURN BEFORE;
+             FOR $collection IN array::distinct($post_collections.collection) {
+                UPDATE $collection SET items_count = array::len((SELECT VALUE id FROM collection_item WHERE collection = $collection));
+             };
+             LET $comments = (SELECT id, created_at FROM post_comment WHERE user = $user_id AND ($delete_contributions OR deleted_at > 0) ORDER BY created_at DESC).id;
+             FOR $comment_id IN $comments {
+                LET $comment = SELECT id, parent, replies_count FROM ONLY $comment_id;
+                IF $comment.replies_count = 0 {
+                    LET $last = $comment.parent.last();
+                    IF $last {
+                        UPDATE $last SET replies_count -= 1 WHERE replies_count > 0;
+                    };
+                    DELETE $comment_id;
+                } ELSE {
+                    UPDATE $comment_id SET
+                        text = "",
+                        edited_at = 0,
+                        deleted_at = IF deleted_at > 0 THEN deleted_at ELSE $time END,
+                        modified_at = $time;
+                };
+                DELETE revision WHERE target = $comment_id;
+             };
+             LET $liked = DELETE post_like WHERE user = $user_id RETURN BEFORE;
+             UPDATE post SET favorites -= 1 WHERE id IN $liked.post AND favorites > 0;
+             DELETE user_follow WHERE follower = $user_id OR following = $user_id;
+             DELETE user_block WHERE user = $user_id OR target = $user_id;
+             DELETE notification WHERE user = $user_id;
+             UPDATE notification SET actors -= $user_id WHERE actors CONTAINS $user_id;
+             DELETE notification WHERE array::len(actors) = 0;
+             LET $collections = DELETE collection WHERE user = $user_id RETURN BEFORE;
+             DELETE collection_item WHERE collection IN $collections.id;
+             DELETE email_change WHERE user = $user_id;
+             DELETE session WHERE user = $user_id;
+             DELETE api_token WHERE user = $user_id;
+             LET $webhooks = DELETE webhook WHERE user = $user_id RETURN BEFORE;
+             DELETE webhook_delivery WHERE webhook IN $webhooks.id;
+             DELETE ap_key WHERE user = $user_id;
+             DELETE ap_follower WHERE user = $user_id;
+             DELETE ap_delivery WHERE user = $user_id;
+             DELETE sent_email WHERE to_email = $user.email;
+             DELETE confirm_email WHERE to_email = $user.email;
+             DELETE invite WHERE email = $user.email;
+             DELETE account_deletion WHERE user = $user_id;
+             LET $messages = DELETE message WHERE user = $user_id AND $delete_contributions RETURN BEFORE;
+             LET $exports = DELETE data_export WHERE user = $user_id RETURN BEFORE;
+             UPDATE ONLY $user_id SET
+                username = $username,
+                email = $email,
+                password = "",
+                role = "user",
+                used_storage_bytes = math::sum((SELECT VALUE size_bytes FROM post WHERE user = $user_id))
+                    + math::sum(array::flatten((SELECT VALUE file.size_bytes FROM message WHERE user = $user_id))),
+                erased_at = $time,
+                modified_at = $time;
+             -- the audit trail stays, only the address and browser the user made requests from go away
+             UPDATE audit_event SET ip = "", user_agent = "" WHERE actor = $user_id OR (target = $user_id AND actor = NONE);
+             RETURN {
+                posts: $posts,
+                message_files: array::flatten($messages.file),
+                exports: $exports.id,
+             };
+             COMMIT TRANSACTION;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("delete_contributions", delete_contributions))
+            .bind(("username", format!("deleted_{key}")))
+            .bind(("email", format!("{key}@deleted.invalid")))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_or::<DBErasedAccount>(39, DB404Err::NotFound)
+    }
+
+    pub async fn add_data_export(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        audit: DBAudit,
+    ) -> Result<DBDataExport, DBDataExportErr> {
+        let q_audit = q_audit_event_add(DBAuditKind::DataExport, "$user_id", "\"\"", "\"\"");
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $pending = SELECT id FROM data_export WHERE user = $user_id AND status = "pending";
+                 IF $pending {{
+                    THROW "pending";
+                 }};
+                 LET $created = CREATE ONLY data_export SET
+                    user = $user_id,
+                    status = "pending",
+                    size_bytes = 0,
+                    expires = 0,
+                    modified_at = $time,
+                    created_at = $time;
+                 {q_audit}
+                 RETURN $created;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: pending" => DBDataExportErr::AlreadyPending,
+                _ => DBDataExportErr::DB(err),
+            })
+            .and_then_take_expect(5)
+    }
+
+    /// newest first, failed exports disappear after the same window as finished ones
+    pub async fn get_data_exports(
+        &self,
+        time: u128,
+        user_id: RecordId,
+    ) -> Result<Vec<DBDataExport>, surrealdb::Error> {
+        let q = r#"
+            SELECT * FROM data_export WHERE
+                user = $user_id AND (expires > $time OR (status != "ready" AND created_at + $retention > $time))
+                ORDER BY created_at DESC;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("retention", DATA_EXPORT_RETENTION_NS))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    /// only finished exports owned by $user_id that haven't expired yet
+    pub async fn get_data_export(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        export_key: impl Into<RecordIdKey>,
+    ) -> Result<DBDataExport, DB404Err> {
+        let q = r#"
+            SELECT * FROM data_export WHERE id = $export_id AND user = $user_id AND status = "ready" AND expires > $time;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("export_id", create_data_export_id(export_key)))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    /// oldest first
+    pub async fn get_data_exports_pending(&self) -> Result<Vec<DBDataExport>, surrealdb::Error> {
+        let q = r#"
+            SELECT * FROM data_export WHERE status = "pending" ORDER BY created_at ASC;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    pub async fn update_data_export_status(
+        &self,
+        time: u128,
+        export_id: RecordId,
+        status: DBDataExportStatus,
+        size_bytes: u64,
+        expires: u128,
+    ) -> Result<DBDataExport, DB404Err> {
+        let q = r#"
+            UPDATE ONLY $export_id SET
+                status = $status,
+                size_bytes = $size_bytes,
+                expires = $expires,
+                modified_at = $time;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("export_id", export_id))
+            .bind(("status", status.to_string()))
+            .bind(("size_bytes", size_bytes))
+            .bind(("expires", expires))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    /// returns the deleted rows so their zips can be removed
+    pub async fn delete_data_exports_expired(
+        &self,
+        time: u128,
+    ) -> Result<Vec<DBDataExport>, surrealdb::Error> {
+        let q = r#"
+            DELETE data_export WHERE
+                (status = "ready" AND expires <= $time) OR (status != "ready" AND created_at + $retention <= $time)
+                RETURN BEFORE;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("retention", DATA_EXPORT_RETENTION_NS))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    pub async fn get_user_data(&self, user_id: RecordId) -> Result<DBUserData, DB404Err> {
+        let q = r#"
+            SELECT * FROM ONLY $user_id;
+            SELECT *, user.* FROM post WHERE user = $user_id ORDER BY created_at ASC;
+            SELECT *, user.* FROM post_comment WHERE user = $user_id ORDER BY created_at ASC;
+            SELECT * FROM post_like WHERE user = $user_id ORDER BY created_at ASC;
+            SELECT * FROM sent_email WHERE to_email = $user_id.email ORDER BY created_at ASC;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .await
+            .check_good(DB404Err::from)
+            .and_then(|mut result| {
+                let user = result
+                    .take::<Option<DBUser>>(0)?
+                    .ok_or(DB404Err::NotFound)?;
+                let posts = result.take::<Vec<DBUserPost>>(1)?;
+                let comments = result.take::<Vec<DBPostComment>>(2)?;
+                let likes = result.take::<Vec<DBPostLike>>(3)?;
+                let sent_emails = result.take::<Vec<DBSentEmail>>(4)?;
+                Ok(DBUserData {
+                    user,
+                    posts,
+                    comments,
+                    likes,
+                    sent_emails,
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::{engine::local::Mem, types::ToSql};
+
+    use crate::api::{Order, TimeRange};
+    use crate::db::account::{DBAccountDeletionMode, DBDataExportStatus};
+    use crate::db::audit::{DBAudit, DBAuditKind};
+    use crate::db::{
+        DB404Err, DBDataExportErr, DBPostRating, DBPostVisibility, DBSentEmailReason, Db,
+        SurrealCheckUtils,
+    };
+
+    #[tokio::test]
+    async fn db_account_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+
+        let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+
+        // deletion requests
+        let deletion = db
+            .add_account_deletion(1, user.id.clone(), DBAccountDeletionMode::Delete, 10)
+            .await
+            .unwrap();
+        let result = db
+            .confirm_account_deletion(
+                2,
+                user2.id.clone(),
+                deletion.id.key.clone(),
+                100,
+                DBAudit::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        let result = db
+            .confirm_account_deletion(
+                10,
+                user.id.clone(),
+                deletion.id.key.clone(),
+                100,
+                DBAudit::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        let result = db.get_account_deletion(10, user.id.clone()).await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        let deletion = db
+            .add_account_deletion(11, user.id.clone(), DBAccountDeletionMode::Delete, 20)
+            .await
+            .unwrap();
+        let confirmed = db
+            .confirm_account_deletion(
+                12,
+                user.id.clone(),
+                deletion.id.key.clone(),
+                100,
+                DBAudit::default(),
+            )
+            .await
+            .unwrap();
+        assert!(confirmed.confirmed);
+        assert_eq!(confirmed.delete_at, 100);
+        assert!(db.get_account_deletions_due(99).await.unwrap().is_empty());
+        assert_eq!(db.get_account_deletions_due(100).await.unwrap().len(), 1);
+        db.delete_account_deletion(13, user.id.clone(), DBAudit::default())
+            .await
+            .unwrap();
+        assert!(db.get_account_deletions_due(100).await.unwrap().is_empty());
+        let result = db
+            .delete_account_deletion(13, user.id.clone(), DBAudit::default())
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        // data exports
+        let export = db
+            .add_data_export(14, user.id.clone(), DBAudit::default())
+            .await
+            .unwrap();
+        let result = db
+            .add_data_export(14, user.id.clone(), DBAudit::default())
+            .await;
+        assert!(matches!(result, Err(DBDataExportErr::AlreadyPending)));
+        assert_eq!(db.get_data_exports_pending().await.unwrap().len(), 1);
+        let result = db
+            .get_data_export(15, user.id.clone(), export.id.key.clone())
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        db.update_data_export_status(15, export.id.clone(), DBDataExportStatus::Ready, 10, 50)
+            .await
+            .unwrap();
+        db.get_data_export(15, user.id.clone(), export.id.key.clone())
+            .await
+            .unwrap();
+        let result = db
+            .get_data_export(15, user2.id.clone(), export.id.key.clone())
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        assert_eq!(
+            db.get_data_exports(15, user.id.clone())
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(db.delete_data_exports_expired(49).await.unwrap().is_empty());
+        assert_eq!(db.delete_data_exports_expired(50).await.unwrap().len(), 1);
+        assert!(
+            db.get_data_exports(50, user.id.clone())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // erasing
+        let post = db
+            .add_post(
+                16,
+                "hey1",
+                "title",
+                "",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+        db.add_post_file(
+            16,
+            user.id.clone(),
+            post.id.key.clone(),
+            10,
+            "1",
+            "png",
+            10,
+            10,
+        )
+        .await
+        .unwrap();
+        let post2 = db
+            .add_post(
+                16,
+                "hey2",
+                "title",
+                "",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+        db.add_post_like(17, user.id.clone(), post2.id.key.clone())
+            .await
+            .unwrap();
+        db.update_post_favorites(post2.id.key.clone())
+            .await
+            .unwrap();
+        let comment = db
+            .add_post_comment(18, user.id.clone(), post2.id.key.clone(), None, "hello")
+            .await
+            .unwrap();
+        db.add_post_comment(
+            19,
+            user2.id.clone(),
+            post2.id.key.clone(),
+            Some(comment.id.key.to_sql()),
+            "hi",
+        )
+        .await
+        .unwrap();
+        db.add_post_comment(20, user.id.clone(), post2.id.key.clone(), None, "bye")
+            .await
+            .unwrap();
+        db.add_session(21, "hey1", DBAudit::default())
+            .await
+            .unwrap();
+        let audit = DBAudit {
+            actor: Some(user.id.clone()),
+            ip: "127.0.0.1".to_string(),
+            user_agent: "test".to_string(),
+        };
+        db.add_audit_event(21, audit, DBAuditKind::Login, Some(user.id.clone()))
+            .await
+            .unwrap();
+        let audit = DBAudit {
+            actor: Some(user2.id.clone()),
+            ip: "127.0.0.2".to_string(),
+            user_agent: "test".to_string(),
+        };
+        db.add_audit_event(21, audit, DBAuditKind::BanChange, Some(user.id.clone()))
+            .await
+            .unwrap();
+        db.add_sent_email(
+            21,
+            "body",
+            "hey1@hey.com",
+            DBSentEmailReason::ReportResolved,
+        )
+        .await
+        .unwrap();
+
+        let data = db.get_user_data(user.id.clone()).await.unwrap();
+        assert_eq!(data.posts.len(), 1);
+        assert_eq!(data.comments.len(), 2);
+        assert_eq!(data.likes.len(), 1);
+        assert_eq!(data.sent_emails.len(), 1);
+
+        let erased = db
+            .erase_account(22, user.id.clone(), DBAccountDeletionMode::Delete)
+            .await
+            .unwrap();
+        assert_eq!(erased.posts.len(), 1);
+        assert_eq!(erased.posts[0].file[0].hash, "1");
+
+        let erased_user = db
+            .get_user_by_username(format!("deleted_{}", user.id.key.to_sql()))
+            .await
+            .unwrap();
+        assert_eq!(erased_user.erased_at, 22);
+        assert_eq!(erased_user.password, "");
+        assert_eq!(erased_user.used_storage_bytes, 0);
+        let result = db.get_user_by_email("hey1@hey.com").await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        assert!(db.get_session_all().await.unwrap().is_empty());
+        assert!(
+            db.get_sent_email_by_email("hey1@hey.com")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let data = db.get_user_data(user.id.clone()).await.unwrap();
+        assert!(data.likes.is_empty());
+        assert_eq!(data.comments.len(), 1);
+        let post2 = db.get_post(post2.id.key.clone()).await.unwrap();
+        assert_eq!(post2.favorites, 0);
+        assert_eq!(db.get_post_all().await.unwrap().len(), 1);
+
+        // the comment with a reply stays as a placeholder, the other one is gone
+        let comments = db.get_post_comments_all().await.unwrap();
+        assert_eq!(comments.len(), 2);
+        let comment = db.get_post_comment(comment.id.key.clone()).await.unwrap();
+        assert_eq!(comment.text, "");
+        assert_eq!(comment.deleted_at, 22);
+
+        // audit events stay, only the erased user's own address and browser are cleared
+        let events = db
+            .get_audit_events(
+                Some(user.id.clone()),
+                true,
+                None,
+                10,
+                TimeRange::None,
+                Order::OneTwoThree,
+            )
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, "login");
+        assert_eq!(events[0].ip, "");
+        assert_eq!(events[0].user_agent, "");
+        assert_eq!(events[1].kind, "ban_change");
+        assert_eq!(events[1].ip, "127.0.0.2");
+        let result = db
+            .db
+            .query("DELETE audit_event;")
+            .await
+            .check_good(surrealdb::Error::from);
+        assert!(result.is_err());
+
+        // anonymizing keeps contributions
+        let post3 = db
+            .add_post(
+                23,
+                "hey2",
+                "title",
+                "",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+        db.erase_account(24, user2.id.clone(), DBAccountDeletionMode::Anonymize)
+            .await
+            .unwrap();
+        let post3 = db.get_post(post3.id.key.clone()).await.unwrap();
+        assert_eq!(
+            post3.user.username,
+            format!("deleted_{}", user2.id.key.to_sql())
+        );
+        assert_eq!(db.get_post_comments_all().await.unwrap().len(), 2);
+    }
+}
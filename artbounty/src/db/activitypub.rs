@@ -0,0 +1,541 @@
+use crate::db::DB404Err;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealSerializeUtils;
+use crate::db::webhook::DBWebhookDeliveryStatus;
+use surrealdb::types::SurrealValue;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+
+/// signing key of a local actor, created the first time someone looks the actor up
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBApKey {
+    pub id: RecordId,
+    pub user: RecordId,
+    pub private_key_pem: String,
+    pub public_key_pem: String,
+    pub created_at: u128,
+}
+
+/// remote actor following a local user, `actor` and `inbox` are absolute urls
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBApFollower {
+    pub id: RecordId,
+    pub user: RecordId,
+    pub actor: String,
+    pub inbox: String,
+    pub created_at: u128,
+}
+
+/// a remote like or announce of a local post
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBApReaction {
+    pub id: RecordId,
+    pub post: RecordId,
+    pub actor: String,
+    pub kind: String,
+    pub activity: String,
+    pub created_at: u128,
+}
+
+/// one signed activity for one remote inbox, same lifecycle as a webhook delivery
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBApDelivery {
+    pub id: RecordId,
+    pub user: RecordId,
+    pub inbox: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: u32,
+    pub next_attempt_at: u128,
+    pub response_code: u16,
+    pub response: String,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBApDeliveryDue {
+    pub id: RecordId,
+    pub user: RecordId,
+    pub username: String,
+    pub inbox: String,
+    pub payload: String,
+    pub attempts: u32,
+    pub next_attempt_at: u128,
+}
+
+impl<C: Connection> Db<C> {
+    pub async fn get_ap_key(&self, user_id: RecordId) -> Result<DBApKey, DB404Err> {
+        let q = r#"
+            SELECT * FROM ONLY ap_key WHERE user = $user_id LIMIT 1;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    /// fails on the unique index if the user already has a key
+    pub async fn add_ap_key(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        private_key_pem: impl Into<String>,
+        public_key_pem: impl Into<String>,
+    ) -> Result<DBApKey, surrealdb::Error> {
+        let q = r#"
+            CREATE ONLY ap_key SET
+                user = $user_id,
+                private_key_pem = $private_key_pem,
+                public_key_pem = $public_key_pem,
+                created_at = $time;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("private_key_pem", private_key_pem.into()))
+            .bind(("public_key_pem", public_key_pem.into()))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_expect(0)
+    }
+
+    /// following again only refreshes the inbox
+    pub async fn add_ap_follower(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        actor: impl Into<String>,
+        inbox: impl Into<String>,
+    ) -> Result<DBApFollower, surrealdb::Error> {
+        let q = r#"
+             BEGIN TRANSACTION;
+             DELETE ap_follower WHERE user = $user_id AND actor = $actor;
+             LET $created = CREATE ONLY ap_follower SET
+                user = $user_id,
+                actor = $actor,
+                inbox = $inbox,
+                created_at = $time;
+             RETURN $created;
+             COMMIT TRANSACTION;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("actor", actor.into()))
+            .bind(("inbox", inbox.into()))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_expect(3)
+    }
+
+    pub async fn delete_ap_follower(
+        &self,
+        user_id: RecordId,
+        actor: impl Into<String>,
+    ) -> Result<(), surrealdb::Error> {
+        let q = r#"
+            DELETE ap_follower WHERE user = $user_id AND actor = $actor;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .bind(("actor", actor.into()))
+            .await
+            .check_good(surrealdb::Error::from)
+            .map(|_| ())
+    }
+
+    pub async fn get_ap_followers(
+        &self,
+        user_id: RecordId,
+    ) -> Result<Vec<DBApFollower>, surrealdb::Error> {
+        let q = r#"
+            SELECT * FROM ap_follower WHERE user = $user_id ORDER BY created_at ASC;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    /// the same activity id twice is only stored once
+    pub async fn add_ap_reaction(
+        &self,
+        time: u128,
+        post_id: RecordId,
+        actor: impl Into<String>,
+        kind: impl Into<String>,
+        activity: impl Into<String>,
+    ) -> Result<DBApReaction, surrealdb::Error> {
+        let q = r#"
+             BEGIN TRANSACTION;
+             DELETE ap_reaction WHERE activity = $activity;
+             LET $created = CREATE ONLY ap_reaction SET
+                post = $post_id,
+                actor = $actor,
+                kind = $kind,
+                activity = $activity,
+                created_at = $time;
+             RETURN $created;
+             COMMIT TRANSACTION;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("post_id", post_id))
+            .bind(("actor", actor.into()))
+            .bind(("kind", kind.into()))
+            .bind(("activity", activity.into()))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_expect(3)
+    }
+
+    /// only the actor that sent the activity can take it back
+    pub async fn delete_ap_reaction(
+        &self,
+        actor: impl Into<String>,
+        activity: impl Into<String>,
+    ) -> Result<(), surrealdb::Error> {
+        let q = r#"
+            DELETE ap_reaction WHERE activity = $activity AND actor = $actor;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("actor", actor.into()))
+            .bind(("activity", activity.into()))
+            .await
+            .check_good(surrealdb::Error::from)
+            .map(|_| ())
+    }
+
+    pub async fn get_ap_reactions(
+        &self,
+        post_id: RecordId,
+    ) -> Result<Vec<DBApReaction>, surrealdb::Error> {
+        let q = r#"
+            SELECT * FROM ap_reaction WHERE post = $post_id ORDER BY created_at ASC;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("post_id", post_id))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    /// queues one delivery per inbox and returns how many were queued
+    pub async fn add_ap_deliveries(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        inboxes: Vec<String>,
+        payload: impl Into<String>,
+    ) -> Result<usize, surrealdb::Error> {
+        let q = r#"
+             BEGIN TRANSACTION;
+             FOR $inbox IN $inboxes {
+                CREATE ap_delivery SET
+                    user = $user_id,
+                    inbox = $inbox,
+                    payload = $payload,
+                    status = "pending",
+                    attempts = 0,
+                    next_attempt_at = $time,
+                    response_code = 0,
+                    response = "",
+                    modified_at = $time,
+                    created_at = $time;
+             };
+             RETURN count($inboxes);
+             COMMIT TRANSACTION;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("inboxes", inboxes))
+            .bind(("payload", payload.into()))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_expect(2)
+    }
+
+    /// oldest due first, with the username the worker needs for the key id
+    pub async fn get_ap_deliveries_due(
+        &self,
+        time: u128,
+        limit: usize,
+    ) -> Result<Vec<DBApDeliveryDue>, surrealdb::Error> {
+        let q = r#"
+            SELECT id, user, user.username AS username, inbox, payload, attempts, next_attempt_at FROM ap_delivery
+                WHERE status = "pending" AND next_attempt_at <= $time
+                ORDER BY next_attempt_at ASC
+                LIMIT $get_limit;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("get_limit", limit))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    /// records the outcome of one attempt, `next_attempt_at` is 0 once the delivery is finished
+    pub async fn update_ap_delivery(
+        &self,
+        time: u128,
+        delivery_id: RecordId,
+        status: DBWebhookDeliveryStatus,
+        attempts: u32,
+        next_attempt_at: u128,
+        response_code: u16,
+        response: impl Into<String>,
+    ) -> Result<DBApDelivery, DB404Err> {
+        let q = r#"
+            UPDATE ONLY $delivery_id SET
+                status = $status,
+                attempts = $attempts,
+                next_attempt_at = $next_attempt_at,
+                response_code = $response_code,
+                response = $response,
+                modified_at = $time;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("delivery_id", delivery_id))
+            .bind(("status", status.to_string()))
+            .bind(("attempts", attempts))
+            .bind(("next_attempt_at", next_attempt_at))
+            .bind(("response_code", response_code))
+            .bind(("response", response.into()))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    pub async fn get_ap_deliveries(
+        &self,
+        user_id: RecordId,
+        limit: usize,
+    ) -> Result<Vec<DBApDelivery>, surrealdb::Error> {
+        let q = r#"
+            SELECT * FROM ap_delivery WHERE user = $user_id ORDER BY created_at DESC LIMIT $get_limit;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .bind(("get_limit", limit))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    /// drops finished deliveries once they're older than the retention window
+    pub async fn delete_ap_deliveries_expired(
+        &self,
+        time: u128,
+        retention: u128,
+    ) -> Result<(), surrealdb::Error> {
+        let q = r#"
+            DELETE ap_delivery WHERE status != "pending" AND created_at + $retention < $time;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("retention", retention))
+            .await
+            .check_good(surrealdb::Error::from)
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::engine::local::Mem;
+
+    use crate::db::webhook::DBWebhookDeliveryStatus;
+    use crate::db::{DB404Err, DBPostRating, DBPostVisibility, Db};
+
+    #[tokio::test]
+    async fn db_activitypub_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+
+        let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+        let post = db
+            .add_post(
+                0,
+                "hey1",
+                "title",
+                "description",
+                "tags",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+
+        // one key per user
+        let result = db.get_ap_key(user.id.clone()).await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        db.add_ap_key(1, user.id.clone(), "private", "public")
+            .await
+            .unwrap();
+        let result = db
+            .add_ap_key(1, user.id.clone(), "private2", "public2")
+            .await;
+        assert!(result.is_err());
+        let key = db.get_ap_key(user.id.clone()).await.unwrap();
+        assert_eq!(key.public_key_pem, "public");
+
+        // following twice keeps one follower with the newest inbox
+        db.add_ap_follower(
+            2,
+            user.id.clone(),
+            "http://remote/a",
+            "http://remote/a/inbox",
+        )
+        .await
+        .unwrap();
+        db.add_ap_follower(3, user.id.clone(), "http://remote/a", "http://remote/inbox")
+            .await
+            .unwrap();
+        db.add_ap_follower(3, user.id.clone(), "http://remote/b", "http://remote/inbox")
+            .await
+            .unwrap();
+        let followers = db.get_ap_followers(user.id.clone()).await.unwrap();
+        assert_eq!(followers.len(), 2);
+        assert!(followers.iter().all(|v| v.inbox == "http://remote/inbox"));
+        assert!(
+            db.get_ap_followers(user2.id.clone())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        db.delete_ap_follower(user.id.clone(), "http://remote/a")
+            .await
+            .unwrap();
+        let followers = db.get_ap_followers(user.id.clone()).await.unwrap();
+        assert_eq!(followers.len(), 1);
+        assert_eq!(followers[0].actor, "http://remote/b");
+
+        // reactions are keyed by their activity and only removed by their actor
+        db.add_ap_reaction(
+            4,
+            post.id.clone(),
+            "http://remote/a",
+            "like",
+            "http://remote/1",
+        )
+        .await
+        .unwrap();
+        db.add_ap_reaction(
+            4,
+            post.id.clone(),
+            "http://remote/a",
+            "like",
+            "http://remote/1",
+        )
+        .await
+        .unwrap();
+        db.add_ap_reaction(
+            4,
+            post.id.clone(),
+            "http://remote/b",
+            "announce",
+            "http://remote/2",
+        )
+        .await
+        .unwrap();
+        assert_eq!(db.get_ap_reactions(post.id.clone()).await.unwrap().len(), 2);
+        db.delete_ap_reaction("http://remote/b", "http://remote/1")
+            .await
+            .unwrap();
+        assert_eq!(db.get_ap_reactions(post.id.clone()).await.unwrap().len(), 2);
+        db.delete_ap_reaction("http://remote/a", "http://remote/1")
+            .await
+            .unwrap();
+        let reactions = db.get_ap_reactions(post.id.clone()).await.unwrap();
+        assert_eq!(reactions.len(), 1);
+        assert_eq!(reactions[0].kind, "announce");
+
+        // delivery queue
+        let queued = db
+            .add_ap_deliveries(
+                5,
+                user.id.clone(),
+                vec![
+                    "http://remote/inbox".to_string(),
+                    "http://other/inbox".to_string(),
+                ],
+                "{}",
+            )
+            .await
+            .unwrap();
+        assert_eq!(queued, 2);
+        assert!(db.get_ap_deliveries_due(4, 10).await.unwrap().is_empty());
+        let due = db.get_ap_deliveries_due(5, 10).await.unwrap();
+        assert_eq!(due.len(), 2);
+        assert_eq!(due[0].username, "hey1");
+
+        db.update_ap_delivery(
+            6,
+            due[0].id.clone(),
+            DBWebhookDeliveryStatus::Pending,
+            1,
+            100,
+            500,
+            "oops",
+        )
+        .await
+        .unwrap();
+        db.update_ap_delivery(
+            6,
+            due[1].id.clone(),
+            DBWebhookDeliveryStatus::Success,
+            1,
+            0,
+            202,
+            "",
+        )
+        .await
+        .unwrap();
+        assert!(db.get_ap_deliveries_due(6, 10).await.unwrap().is_empty());
+        assert_eq!(db.get_ap_deliveries_due(100, 10).await.unwrap().len(), 1);
+
+        db.delete_ap_deliveries_expired(200, 100).await.unwrap();
+        let deliveries = db.get_ap_deliveries(user.id.clone(), 10).await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status, "pending");
+    }
+}
@@ -0,0 +1,582 @@
+use crate::api::Order;
+use crate::api::TimeRange;
+use crate::db::DB404Err;
+use crate::db::DBPostRating;
+use crate::db::DBUser;
+use crate::db::DBUserPost;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealErrUtils;
+use crate::db::SurrealSerializeUtils;
+use crate::db::audit::{DBAudit, DBAuditKind, q_audit_event_add};
+use crate::db::cursor::{order_query, time_range_query};
+use crate::db::post::create_post_id;
+use crate::db::post_comment::create_post_comment_id;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+use surrealdb::types::RecordIdKey;
+
+impl<C: Connection> Db<C> {
+    /// matches username or email, case insensitive, empty query matches everyone
+    pub async fn user_search(
+        &self,
+        query: impl Into<String>,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> Result<Vec<DBUser>, surrealdb::Error> {
+        let (q_time, time_range_val) = time_range_query(&time_range, "created_at");
+        let q_order = order_query(&order);
+        let q = format!(
+            "
+            SELECT * FROM user WHERE
+                (string::contains(string::lowercase(username), $query) OR
+                string::contains(string::lowercase(email), $query)) {q_time}
+                ORDER BY created_at {q_order}
+                LIMIT $get_limit;
+        "
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("query", query.into().trim().to_lowercase()))
+            .bind(("get_limit", limit))
+            .bind(("time_range", time_range_val))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    pub async fn update_user_role(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        role: impl Into<String>,
+        audit: DBAudit,
+    ) -> Result<DBUser, DB404Err> {
+        let q_audit = q_audit_event_add(DBAuditKind::RoleChange, "$user_id", "$user.role", "$role");
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $user = SELECT id, role FROM ONLY $user_id;
+                 IF !$user {{
+                    THROW "user not found";
+                 }};
+                 LET $updated = UPDATE ONLY $user_id SET modified_at = $time, role = $role;
+                 {q_audit}
+                 RETURN $updated;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .bind(("role", role.into()))
+            .bind(("time", time))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: user not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_or(5, DB404Err::NotFound)
+    }
+
+    pub async fn update_user_role_by_email(
+        &self,
+        time: u128,
+        emails: Vec<String>,
+        role: impl Into<String>,
+        audit: DBAudit,
+    ) -> Result<Vec<DBUser>, surrealdb::Error> {
+        let q_audit = q_audit_event_add(DBAuditKind::RoleChange, "$user.id", "$user.role", "$role");
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $users = UPDATE user SET modified_at = $time, role = $role WHERE email IN $emails AND role != $role RETURN BEFORE;
+                 FOR $user IN $users {{
+                    {q_audit}
+                 }};
+                 SELECT * FROM $users.id;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("emails", emails))
+            .bind(("role", role.into()))
+            .bind(("time", time))
+            .bind(("audit", audit))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(3)
+    }
+
+    /// banning also ends every session of the user
+    pub async fn update_user_banned(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        banned: bool,
+        audit: DBAudit,
+    ) -> Result<DBUser, DB404Err> {
+        let q_audit = q_audit_event_add(
+            DBAuditKind::BanChange,
+            "$user_id",
+            "$user.banned",
+            "$banned",
+        );
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $user = SELECT id, banned FROM ONLY $user_id;
+                 IF !$user {{
+                    THROW "user not found";
+                 }};
+                 LET $updated = UPDATE ONLY $user_id SET modified_at = $time, banned = $banned;
+                 IF $banned {{
+                    DELETE session WHERE user = $user_id;
+                 }};
+                 {q_audit}
+                 RETURN $updated;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .bind(("banned", banned))
+            .bind(("time", time))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: user not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_or(6, DB404Err::NotFound)
+    }
+
+    /// suspending also ends every session of the user, 0 lifts the suspension
+    pub async fn update_user_suspended(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        suspended_until: u128,
+        audit: DBAudit,
+    ) -> Result<DBUser, DB404Err> {
+        let q_audit = q_audit_event_add(
+            DBAuditKind::SuspendChange,
+            "$user_id",
+            "$user.suspended_until",
+            "$suspended_until",
+        );
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $user = SELECT id, suspended_until FROM ONLY $user_id;
+                 IF !$user {{
+                    THROW "user not found";
+                 }};
+                 LET $updated = UPDATE ONLY $user_id SET modified_at = $time, suspended_until = $suspended_until;
+                 IF $suspended_until > $time {{
+                    DELETE session WHERE user = $user_id;
+                 }};
+                 {q_audit}
+                 RETURN $updated;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .bind(("suspended_until", suspended_until))
+            .bind(("time", time))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: user not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_or(6, DB404Err::NotFound)
+    }
+
+    /// unlike the public listings this includes hidden posts
+    pub async fn get_user_posts_moderation(
+        &self,
+        username: impl Into<String>,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> Result<Vec<DBUserPost>, surrealdb::Error> {
+        let (q_time, time_range_val) = time_range_query(&time_range, "created_at");
+        let q_order = order_query(&order);
+        let q = format!(
+            "
+            LET $user = SELECT id FROM ONLY user WHERE username = $username LIMIT 1;
+            SELECT *, user.* FROM post WHERE
+                user = $user.id {q_time}
+                ORDER BY created_at {q_order}
+                LIMIT $get_limit;
+        "
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("username", username.into()))
+            .bind(("get_limit", limit))
+            .bind(("time_range", time_range_val))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(1)
+    }
+
+    /// the audit event targets the author, the post itself is in the summary
+    pub async fn update_post_show(
+        &self,
+        time: u128,
+        post_key: impl Into<RecordIdKey>,
+        show: bool,
+        audit: DBAudit,
+    ) -> Result<DBUserPost, DB404Err> {
+        let q_audit = q_audit_event_add(
+            DBAuditKind::PostShowChange,
+            "$post.user",
+            "string::concat(<string> $post_id, \" show \", $post.show)",
+            "string::concat(<string> $post_id, \" show \", $show)",
+        );
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $post = SELECT id, user, show FROM ONLY $post_id;
+                 IF !$post {{
+                    THROW "post not found";
+                 }};
+                 LET $updated = UPDATE ONLY $post_id SET show = $show RETURN *, user.*;
+                 {q_audit}
+                 RETURN $updated;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("post_id", create_post_id(post_key)))
+            .bind(("show", show))
+            .bind(("time", time))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: post not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_or(5, DB404Err::NotFound)
+    }
+
+    /// overrides the author's rating and locks it so the author can't change it back
+    pub async fn update_post_rating_moderation(
+        &self,
+        time: u128,
+        post_key: impl Into<RecordIdKey>,
+        rating: DBPostRating,
+        audit: DBAudit,
+    ) -> Result<DBUserPost, DB404Err> {
+        let q_audit = q_audit_event_add(
+            DBAuditKind::PostRatingChange,
+            "$post.user",
+            "string::concat(<string> $post_id, \" rating \", $post.rating)",
+            "string::concat(<string> $post_id, \" rating \", $rating)",
+        );
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $post = SELECT id, user, rating FROM ONLY $post_id;
+                 IF !$post {{
+                    THROW "post not found";
+                 }};
+                 LET $updated = UPDATE ONLY $post_id SET
+                    rating = $rating,
+                    rating_locked = true,
+                    modified_at = $time
+                    RETURN *, user.*;
+                 {q_audit}
+                 RETURN $updated;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("post_id", create_post_id(post_key)))
+            .bind(("rating", rating.to_string()))
+            .bind(("time", time))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: post not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_or(5, DB404Err::NotFound)
+    }
+
+    /// same as delete_post_comment but without the ownership check
+    pub async fn delete_post_comment_moderation(
+        &self,
+        time: u128,
+        comment_key: impl Into<RecordIdKey>,
+        audit: DBAudit,
+    ) -> Result<(), DB404Err> {
+        let q_audit = q_audit_event_add(
+            DBAuditKind::CommentDelete,
+            "$comment.user",
+            "string::concat(<string> $comment_id, \": \", $comment.text)",
+            "\"\"",
+        );
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $comment = SELECT id, user, text, parent FROM ONLY $comment_id;
+                 IF !$comment {{
+                    THROW "comment not found";
+                 }};
+                 LET $last = $comment.parent.last();
+                 LET $parent = IF $last {{
+                    SELECT id, replies_count FROM ONLY $last
+                 }} ELSE {{
+                    NULL
+                 }};
+                 IF $parent.replies_count > 0 {{
+                    UPDATE $parent.id SET replies_count = $parent.replies_count - 1;
+                 }};
+                 LET $deleted = DELETE post_comment WHERE parent.find($comment_id) OR id == $comment_id RETURN BEFORE;
+                 DELETE revision WHERE target IN $deleted.id;
+                 {q_audit}
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("comment_id", create_post_comment_id(comment_key)))
+            .bind(("time", time))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: comment not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::engine::local::Mem;
+
+    use crate::{
+        api::{Order, TimeRange, shared::cursor::CursorRange},
+        db::{DB404Err, DBPostRating, DBPostVisibility, Db, audit::DBAudit},
+    };
+
+    #[tokio::test]
+    async fn db_admin_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+
+        let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        let user2 = db.add_user(1, "Hey2", "hey2@hey.com", "123").await.unwrap();
+        assert_eq!(user.role, "user");
+        assert!(!user.banned);
+
+        let result = db
+            .user_search("HEY", 10, TimeRange::None, Order::OneTwoThree)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].id, user.id);
+        let result = db
+            .user_search("hey2@", 10, TimeRange::None, Order::OneTwoThree)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, user2.id);
+        let result = db
+            .user_search("", 10, TimeRange::More(0), Order::ThreeTwoOne)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+
+        let result = db
+            .update_user_role_by_email(
+                2,
+                vec!["hey1@hey.com".to_string()],
+                "admin",
+                DBAudit::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].role, "admin");
+        let result = db
+            .update_user_role_by_email(
+                2,
+                vec!["hey1@hey.com".to_string()],
+                "admin",
+                DBAudit::default(),
+            )
+            .await
+            .unwrap();
+        assert!(result.is_empty());
+
+        let result = db
+            .update_user_role(3, user2.id.clone(), "moderator", DBAudit::default())
+            .await
+            .unwrap();
+        assert_eq!(result.role, "moderator");
+        let result = db
+            .update_user_role(
+                3,
+                crate::db::create_user_id("404"),
+                "moderator",
+                DBAudit::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        db.add_session(4, "Hey2", DBAudit::default()).await.unwrap();
+        let result = db
+            .update_user_banned(5, user2.id.clone(), true, DBAudit::default())
+            .await
+            .unwrap();
+        assert!(result.banned);
+        let sessions = db.get_session_all().await.unwrap();
+        assert!(sessions.iter().all(|v| v.user.id != user2.id));
+        let result = db
+            .update_user_banned(6, user2.id.clone(), false, DBAudit::default())
+            .await
+            .unwrap();
+        assert!(!result.banned);
+
+        db.add_session(6, "Hey2", DBAudit::default()).await.unwrap();
+        let result = db
+            .update_user_suspended(6, user2.id.clone(), 100, DBAudit::default())
+            .await
+            .unwrap();
+        assert_eq!(result.suspended_until, 100);
+        let sessions = db.get_session_all().await.unwrap();
+        assert!(sessions.iter().all(|v| v.user.id != user2.id));
+        let result = db
+            .update_user_suspended(6, user2.id.clone(), 0, DBAudit::default())
+            .await
+            .unwrap();
+        assert_eq!(result.suspended_until, 0);
+
+        let post = db
+            .add_post(
+                7,
+                "hey1",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+        let result = db
+            .update_post_show(7, post.id.key.clone(), false, DBAudit::default())
+            .await
+            .unwrap();
+        assert!(!result.show);
+        let result = db
+            .post_search(
+                10,
+                CursorRange::None,
+                Order::ThreeTwoOne,
+                "",
+                "hey1",
+                None,
+                DBPostRating::Explicit,
+            )
+            .await
+            .unwrap();
+        assert!(result.is_empty());
+        let result = db
+            .get_user_posts_moderation("hey1", 10, TimeRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        let result = db
+            .update_post_show(7, "404", false, DBAudit::default())
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        let comment = db
+            .add_post_comment(8, user.id.clone(), post.id.key.clone(), None, "hello")
+            .await
+            .unwrap();
+        db.delete_post_comment_moderation(8, comment.id.key.clone(), DBAudit::default())
+            .await
+            .unwrap();
+        let result = db.get_post_comments_all().await.unwrap();
+        assert!(result.is_empty());
+        let result = db
+            .delete_post_comment_moderation(8, comment.id.key.clone(), DBAudit::default())
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        let result = db
+            .get_audit_events(
+                Some(user2.id.clone()),
+                true,
+                None,
+                10,
+                TimeRange::None,
+                Order::OneTwoThree,
+            )
+            .await
+            .unwrap();
+        // most of these happen at the same time so only the kinds are compared
+        let mut kinds = result.iter().map(|v| v.kind.as_str()).collect::<Vec<_>>();
+        kinds.sort();
+        assert_eq!(
+            kinds,
+            vec![
+                "ban_change",
+                "ban_change",
+                "login",
+                "login",
+                "role_change",
+                "suspend_change",
+                "suspend_change"
+            ]
+        );
+        let ban = result.iter().find(|v| v.created_at == 5).unwrap();
+        assert_eq!(ban.before, "false");
+        assert_eq!(ban.after, "true");
+        let result = db
+            .get_audit_events(
+                Some(user.id.clone()),
+                true,
+                None,
+                10,
+                TimeRange::None,
+                Order::OneTwoThree,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result.iter().map(|v| v.kind.as_str()).collect::<Vec<_>>(),
+            vec!["role_change", "post_show_change", "comment_delete"]
+        );
+        assert!(result[2].before.ends_with(": hello"));
+    }
+}
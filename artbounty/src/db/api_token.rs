@@ -0,0 +1,290 @@
+use crate::db::DB404Err;
+use crate::db::DBApiTokenErr;
+use crate::db::DBUser;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealSerializeUtils;
+use crate::db::audit::{DBAudit, DBAuditKind, q_audit_event_add};
+use surrealdb::types::RecordIdKey;
+use surrealdb::types::SurrealValue;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBApiToken {
+    pub id: RecordId,
+    pub user: RecordId,
+    pub name: String,
+    /// only the hash is kept, the token itself is shown once when it's created
+    pub secret_hash: String,
+    pub scopes: Vec<String>,
+    pub expires: u128,
+    pub last_used_at: u128,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBApiTokenAuth {
+    pub token: DBApiToken,
+    pub user: DBUser,
+}
+
+pub fn create_api_token_id(id: impl Into<RecordIdKey>) -> RecordId {
+    RecordId::new("api_token", id.into())
+}
+
+impl<C: Connection> Db<C> {
+    pub async fn add_api_token(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        name: impl Into<String>,
+        secret_hash: impl Into<String>,
+        scopes: Vec<String>,
+        expires: u128,
+        max: usize,
+        audit: DBAudit,
+    ) -> Result<DBApiToken, DBApiTokenErr> {
+        let q_audit = q_audit_event_add(
+            DBAuditKind::ApiTokenAdd,
+            "$user_id",
+            "\"\"",
+            "$created.name",
+        );
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $count = count(SELECT id FROM api_token WHERE user = $user_id);
+                 IF $count >= $max {{
+                    THROW "too many";
+                 }};
+                 LET $created = CREATE ONLY api_token SET
+                    user = $user_id,
+                    name = $name,
+                    secret_hash = $secret_hash,
+                    scopes = $scopes,
+                    expires = $expires,
+                    last_used_at = 0,
+                    modified_at = $time,
+                    created_at = $time;
+                 {q_audit}
+                 RETURN $created;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("name", name.into()))
+            .bind(("secret_hash", secret_hash.into()))
+            .bind(("scopes", scopes))
+            .bind(("expires", expires))
+            .bind(("max", max))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: too many" => DBApiTokenErr::TooMany,
+                _ => DBApiTokenErr::DB(err),
+            })
+            .and_then_take_expect(5)
+    }
+
+    /// newest first, expired tokens stay listed so the user can see why a script stopped working
+    pub async fn get_api_tokens(
+        &self,
+        user_id: RecordId,
+    ) -> Result<Vec<DBApiToken>, surrealdb::Error> {
+        let q = r#"
+            SELECT * FROM api_token WHERE user = $user_id ORDER BY created_at DESC;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    pub async fn delete_api_token(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        token_key: impl Into<RecordIdKey>,
+        audit: DBAudit,
+    ) -> Result<(), DB404Err> {
+        let q_audit = q_audit_event_add(
+            DBAuditKind::ApiTokenDelete,
+            "$user_id",
+            "$deleted[0].name",
+            "\"\"",
+        );
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $deleted = DELETE api_token WHERE id = $token_id AND user = $user_id RETURN BEFORE;
+                 IF !$deleted {{
+                    THROW "not found";
+                 }};
+                 {q_audit}
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("token_id", create_api_token_id(token_key)))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .map(|_| ())
+    }
+
+    /// looks the token up by its hash and bumps last_used_at at most once per resolution,
+    /// expiry is left to the caller so it can tell expired and unknown tokens apart
+    pub async fn get_api_token_auth(
+        &self,
+        time: u128,
+        secret_hash: impl Into<String>,
+        last_used_resolution: u128,
+    ) -> Result<DBApiTokenAuth, DB404Err> {
+        let q = r#"
+             BEGIN TRANSACTION;
+             LET $token = SELECT * FROM ONLY api_token WHERE secret_hash = $secret_hash LIMIT 1;
+             IF !$token {
+                THROW "not found";
+             };
+             IF ($token.expires = 0 OR $token.expires > $time) AND $token.last_used_at + $resolution <= $time {
+                UPDATE $token.id SET last_used_at = $time;
+             };
+             RETURN {
+                token: $token,
+                user: (SELECT * FROM ONLY $token.user),
+             };
+             COMMIT TRANSACTION;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("secret_hash", secret_hash.into()))
+            .bind(("resolution", last_used_resolution))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_or(4, DB404Err::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::engine::local::Mem;
+
+    use crate::db::audit::DBAudit;
+    use crate::db::{DB404Err, DBApiTokenErr, Db};
+
+    #[tokio::test]
+    async fn db_api_token_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+
+        let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+
+        let token = db
+            .add_api_token(
+                1,
+                user.id.clone(),
+                "seed",
+                "hash1",
+                vec!["read".to_string(), "post:write".to_string()],
+                0,
+                2,
+                DBAudit::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(token.last_used_at, 0);
+        db.add_api_token(
+            2,
+            user.id.clone(),
+            "bot",
+            "hash2",
+            vec!["read".to_string()],
+            10,
+            2,
+            DBAudit::default(),
+        )
+        .await
+        .unwrap();
+        let result = db
+            .add_api_token(
+                3,
+                user.id.clone(),
+                "third",
+                "hash3",
+                vec!["read".to_string()],
+                0,
+                2,
+                DBAudit::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(DBApiTokenErr::TooMany)));
+
+        let tokens = db.get_api_tokens(user.id.clone()).await.unwrap();
+        assert_eq!(
+            tokens.iter().map(|v| v.name.as_str()).collect::<Vec<_>>(),
+            vec!["bot", "seed"]
+        );
+
+        // lookup bumps last used, but only once per resolution
+        let auth = db.get_api_token_auth(5, "hash1", 10).await.unwrap();
+        assert_eq!(auth.user.id, user.id);
+        assert_eq!(auth.token.id, token.id);
+        db.get_api_token_auth(8, "hash1", 10).await.unwrap();
+        let tokens = db.get_api_tokens(user.id.clone()).await.unwrap();
+        assert_eq!(tokens[1].last_used_at, 5);
+
+        // expired tokens are still returned but not bumped
+        let auth = db.get_api_token_auth(20, "hash2", 10).await.unwrap();
+        assert_eq!(auth.token.expires, 10);
+        let tokens = db.get_api_tokens(user.id.clone()).await.unwrap();
+        assert_eq!(tokens[0].last_used_at, 0);
+
+        let result = db.get_api_token_auth(5, "nope", 10).await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        // only the owner can delete
+        let result = db
+            .delete_api_token(
+                6,
+                user2.id.clone(),
+                token.id.key.clone(),
+                DBAudit::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        db.delete_api_token(6, user.id.clone(), token.id.key.clone(), DBAudit::default())
+            .await
+            .unwrap();
+        let result = db.get_api_token_auth(7, "hash1", 10).await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        let tokens = db.get_api_tokens(user.id.clone()).await.unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].name, "bot");
+    }
+}
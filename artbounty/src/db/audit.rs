@@ -0,0 +1,297 @@
+use std::fmt::Display;
+
+use crate::api::Order;
+use crate::api::TimeRange;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealSerializeUtils;
+use crate::db::cursor::{order_query, time_range_query};
+use surrealdb::types::SurrealValue;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBAuditEvent {
+    pub id: RecordId,
+    pub kind: String,
+    pub actor: Option<RecordId>,
+    pub actor_username: Option<String>,
+    pub target: Option<RecordId>,
+    pub target_username: Option<String>,
+    pub ip: String,
+    pub user_agent: String,
+    pub before: String,
+    pub after: String,
+    pub created_at: u128,
+}
+
+/// who made the request, bound as $audit into every audited mutation
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBAudit {
+    pub actor: Option<RecordId>,
+    pub ip: String,
+    pub user_agent: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DBAuditKind {
+    Login,
+    LoginFailed,
+    UsernameChange,
+    PasswordChange,
+    EmailChange,
+    RoleChange,
+    BanChange,
+    SuspendChange,
+    StorageChange,
+    PostShowChange,
+    PostRatingChange,
+    CommentDelete,
+    ReportResolve,
+    DataExport,
+    AccountDeletionSchedule,
+    AccountDeletionCancel,
+    ApiTokenAdd,
+    ApiTokenDelete,
+    WebhookAdd,
+    WebhookDelete,
+}
+
+impl Display for DBAuditKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            DBAuditKind::Login => "login",
+            DBAuditKind::LoginFailed => "login_failed",
+            DBAuditKind::UsernameChange => "username_change",
+            DBAuditKind::PasswordChange => "password_change",
+            DBAuditKind::EmailChange => "email_change",
+            DBAuditKind::RoleChange => "role_change",
+            DBAuditKind::BanChange => "ban_change",
+            DBAuditKind::SuspendChange => "suspend_change",
+            DBAuditKind::StorageChange => "storage_change",
+            DBAuditKind::PostShowChange => "post_show_change",
+            DBAuditKind::PostRatingChange => "post_rating_change",
+            DBAuditKind::CommentDelete => "comment_delete",
+            DBAuditKind::ReportResolve => "report_resolve",
+            DBAuditKind::DataExport => "data_export",
+            DBAuditKind::AccountDeletionSchedule => "account_deletion_schedule",
+            DBAuditKind::AccountDeletionCancel => "account_deletion_cancel",
+            DBAuditKind::ApiTokenAdd => "api_token_add",
+            DBAuditKind::ApiTokenDelete => "api_token_delete",
+            DBAuditKind::WebhookAdd => "webhook_add",
+            DBAuditKind::WebhookDelete => "webhook_delete",
+        };
+
+        write!(f, "{}", text)
+    }
+}
+
+/// statement to embed into the transaction of an audited mutation,
+/// target, before and after are surrealql expressions, $audit and $time have to be bound
+pub fn q_audit_event_add(
+    kind: DBAuditKind,
+    target: impl Display,
+    before: impl Display,
+    after: impl Display,
+) -> String {
+    format!(
+        r#"
+                 CREATE audit_event SET
+                    kind = "{kind}",
+                    actor = $audit.actor,
+                    target = {target},
+                    ip = $audit.ip,
+                    user_agent = $audit.user_agent,
+                    before = <string> {before},
+                    after = <string> {after},
+                    created_at = $time;
+        "#
+    )
+}
+
+impl<C: Connection> Db<C> {
+    /// for events that don't come with a mutation, like failed logins
+    pub async fn add_audit_event(
+        &self,
+        time: u128,
+        audit: DBAudit,
+        kind: DBAuditKind,
+        target: Option<RecordId>,
+    ) -> Result<(), surrealdb::Error> {
+        let q = q_audit_event_add(kind, "$target", "\"\"", "\"\"");
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("audit", audit))
+            .bind(("target", target))
+            .bind(("time", time))
+            .await
+            .check_good(surrealdb::Error::from)
+            .map(|_| ())
+    }
+
+    /// user matches the actor or the target unless target_only is set, kind is optional
+    pub async fn get_audit_events(
+        &self,
+        user_id: Option<RecordId>,
+        target_only: bool,
+        kind: Option<String>,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> Result<Vec<DBAuditEvent>, surrealdb::Error> {
+        let (q_time, time_range_val) = time_range_query(&time_range, "created_at");
+        let q_order = order_query(&order);
+        let q_user = match (&user_id, target_only) {
+            (None, _) => "",
+            (Some(_), true) => "target = $user_id",
+            (Some(_), false) => "(target = $user_id OR actor = $user_id)",
+        };
+        let q_kind = if kind.is_some() { "kind = $kind" } else { "" };
+        let q_time = q_time.strip_prefix("AND ").unwrap_or_default();
+        let q_filter = [q_user, q_kind, q_time]
+            .into_iter()
+            .filter(|v| !v.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" AND ");
+        let q_where = if q_filter.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {q_filter}")
+        };
+        let q = format!(
+            "
+            SELECT *, actor.username AS actor_username, target.username AS target_username
+                FROM audit_event {q_where}
+                ORDER BY created_at {q_order}
+                LIMIT $get_limit;
+        "
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .bind(("kind", kind))
+            .bind(("get_limit", limit))
+            .bind(("time_range", time_range_val))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::engine::local::Mem;
+
+    use crate::{
+        api::{Order, TimeRange},
+        db::{
+            Db, SurrealCheckUtils,
+            audit::{DBAudit, DBAuditKind},
+        },
+    };
+
+    #[tokio::test]
+    async fn db_audit_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+
+        let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+        let audit = DBAudit {
+            actor: Some(user.id.clone()),
+            ip: "127.0.0.1".to_string(),
+            user_agent: "test".to_string(),
+        };
+
+        db.add_session(1, "hey1", audit.clone()).await.unwrap();
+        db.add_audit_event(
+            2,
+            DBAudit::default(),
+            DBAuditKind::LoginFailed,
+            Some(user.id.clone()),
+        )
+        .await
+        .unwrap();
+        db.update_user_username(user.id.clone(), "hey3", 3, audit.clone())
+            .await
+            .unwrap();
+        db.update_user_role(4, user2.id.clone(), "moderator", audit.clone())
+            .await
+            .unwrap();
+
+        let result = db
+            .get_audit_events(
+                Some(user.id.clone()),
+                true,
+                None,
+                10,
+                TimeRange::None,
+                Order::ThreeTwoOne,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result.iter().map(|v| v.kind.as_str()).collect::<Vec<_>>(),
+            vec!["username_change", "login_failed", "login"]
+        );
+        assert_eq!(result[0].before, "hey1");
+        assert_eq!(result[0].after, "hey3");
+        assert_eq!(result[0].ip, "127.0.0.1");
+        assert_eq!(result[0].user_agent, "test");
+        assert_eq!(result[0].actor_username, Some("hey3".to_string()));
+        assert_eq!(result[1].actor, None);
+
+        let result = db
+            .get_audit_events(
+                Some(user.id.clone()),
+                false,
+                None,
+                10,
+                TimeRange::None,
+                Order::ThreeTwoOne,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 4);
+        assert_eq!(result[0].kind, "role_change");
+        assert_eq!(result[0].target_username, Some("hey2".to_string()));
+        assert_eq!(result[0].before, "user");
+        assert_eq!(result[0].after, "moderator");
+
+        let result = db
+            .get_audit_events(
+                None,
+                false,
+                Some("login".to_string()),
+                10,
+                TimeRange::More(0),
+                Order::OneTwoThree,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+
+        let result = db
+            .db
+            .query("DELETE audit_event;")
+            .await
+            .check_good(surrealdb::Error::from);
+        assert!(result.is_err());
+        let result = db
+            .db
+            .query("UPDATE audit_event SET ip = '';")
+            .await
+            .check_good(surrealdb::Error::from);
+        assert!(result.is_err());
+        let result = db
+            .get_audit_events(None, false, None, 10, TimeRange::None, Order::OneTwoThree)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 4);
+    }
+}
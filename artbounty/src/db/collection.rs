@@ -0,0 +1,568 @@
+use crate::api::Order;
+use crate::api::TimeRange;
+use crate::db::DB404Err;
+use crate::db::DBCollectionErr;
+use crate::db::DBUser;
+use crate::db::DBUserPost;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealErrUtils;
+use crate::db::SurrealSerializeUtils;
+use crate::db::cursor::{order_query, time_range_query};
+use crate::db::post::create_post_id;
+use surrealdb::types::SurrealValue;
+use surrealdb::types::ToSql;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+use surrealdb::types::RecordIdKey;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBCollection {
+    pub id: RecordId,
+    pub user: DBUser,
+    pub title: String,
+    pub private: bool,
+    pub items_count: u64,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+pub fn create_collection_id(id: impl Into<RecordIdKey>) -> RecordId {
+    RecordId::new("collection", id.into())
+}
+
+pub fn create_collection_item_id(id: impl Into<RecordIdKey>) -> RecordId {
+    RecordId::new("collection_item", id.into())
+}
+
+// positions are rewritten after every change so they always stay 0..items_count
+const Q_COLLECTION_REINDEX: &str = "
+    FOR $item IN $reordered {
+        UPDATE $item SET position = array::find_index($reordered, $item), modified_at = $time;
+    };
+    UPDATE $collection_id SET items_count = $reordered.len(), modified_at = $time;
+";
+
+impl<C: Connection> Db<C> {
+    pub async fn add_collection(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        title: impl Into<String>,
+        private: bool,
+    ) -> Result<DBCollection, surrealdb::Error> {
+        self.db
+            .query(
+                r#"
+                 CREATE collection SET
+                    user = $user_id,
+                    title = $title,
+                    private = $private,
+                    items_count = 0,
+                    modified_at = $time,
+                    created_at = $time
+                 RETURN *, user.*;
+                "#,
+            )
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("title", title.into()))
+            .bind(("private", private))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_expect(0)
+    }
+
+    /// private collections are only visible to their owner
+    pub async fn get_collection(
+        &self,
+        viewer_id: Option<RecordId>,
+        collection_key: impl Into<RecordIdKey>,
+    ) -> Result<DBCollection, DB404Err> {
+        self.db
+            .query(
+                r#"
+                    SELECT *, user.* FROM ONLY $collection_id WHERE
+                        private = false OR user = $viewer_id;
+                "#,
+            )
+            .bind(("viewer_id", viewer_id))
+            .bind(("collection_id", create_collection_id(collection_key)))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    pub async fn get_user_collections(
+        &self,
+        viewer_id: Option<RecordId>,
+        username: impl Into<String>,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> Result<Vec<DBCollection>, surrealdb::Error> {
+        let (q_time, time_range_val) = time_range_query(&time_range, "created_at");
+        let q_order = order_query(&order);
+        let q = format!(
+            "
+            LET $user = SELECT id FROM ONLY user WHERE username = $username LIMIT 1;
+            SELECT *, user.* FROM collection WHERE
+                user = $user.id AND
+                (private = false OR user = $viewer_id) {q_time}
+                ORDER BY created_at {q_order}
+                LIMIT $get_limit;
+        "
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("viewer_id", viewer_id))
+            .bind(("username", username.into()))
+            .bind(("get_limit", limit))
+            .bind(("time_range", time_range_val))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(1)
+    }
+
+    pub async fn update_collection(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        collection_key: impl Into<RecordIdKey>,
+        title: impl Into<String>,
+        private: bool,
+    ) -> Result<DBCollection, DB404Err> {
+        self.db
+            .query(
+                r#"
+                    UPDATE collection SET
+                        title = $title,
+                        private = $private,
+                        modified_at = $time
+                        WHERE id = $collection_id AND user = $user_id
+                    RETURN *, user.*;
+                "#,
+            )
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("collection_id", create_collection_id(collection_key)))
+            .bind(("title", title.into()))
+            .bind(("private", private))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    pub async fn delete_collection(
+        &self,
+        user_id: RecordId,
+        collection_key: impl Into<RecordIdKey>,
+    ) -> Result<(), DB404Err> {
+        let q = r#"
+                 BEGIN TRANSACTION;
+                 LET $deleted = DELETE collection WHERE id = $collection_id AND user = $user_id RETURN BEFORE;
+                 IF !$deleted {
+                    THROW "collection not found";
+                 };
+                 DELETE collection_item WHERE collection = $collection_id;
+                 COMMIT TRANSACTION;
+                "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .bind(("collection_id", create_collection_id(collection_key)))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: collection not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .map(|_| ())
+    }
+
+    /// appended after the last position, deleted posts may leave gaps until the next reindex
+    pub async fn add_collection_item(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        collection_key: impl Into<RecordIdKey>,
+        post_key: impl Into<RecordIdKey>,
+    ) -> Result<DBCollection, DBCollectionErr> {
+        let post_key = post_key.into();
+        let q = r#"
+                 BEGIN TRANSACTION;
+                 LET $collection = SELECT id FROM ONLY $collection_id WHERE user = $user_id;
+                 IF !$collection {
+                    THROW "collection not found";
+                 };
+                 LET $post = SELECT id FROM ONLY $post_id;
+                 LET $last = (SELECT VALUE position FROM collection_item WHERE collection = $collection_id ORDER BY position DESC LIMIT 1)[0];
+                 CREATE collection_item SET
+                    collection = $collection_id,
+                    post = $post.id,
+                    position = IF $last = NONE { 0 } ELSE { $last + 1 },
+                    modified_at = $time,
+                    created_at = $time;
+                 UPDATE $collection_id SET items_count += 1, modified_at = $time RETURN *, user.*;
+                 COMMIT TRANSACTION;
+                "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("collection_id", create_collection_id(collection_key)))
+            .bind(("post_id", create_post_id(post_key.clone())))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: collection not found" => DBCollectionErr::NotFound,
+                _ if err.index_exists("idx_collection_item") => DBCollectionErr::Duplicate,
+                _ if err.field_value_null("post") => {
+                    DBCollectionErr::PostNotFound(post_key.to_sql())
+                }
+                _ => DBCollectionErr::DB(err),
+            })
+            .and_then_take_expect(6)
+    }
+
+    pub async fn delete_collection_item(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        collection_key: impl Into<RecordIdKey>,
+        post_key: impl Into<RecordIdKey>,
+    ) -> Result<(), DBCollectionErr> {
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $collection = SELECT id FROM ONLY $collection_id WHERE user = $user_id;
+                 IF !$collection {{
+                    THROW "collection not found";
+                 }};
+                 LET $deleted = DELETE collection_item WHERE collection = $collection_id AND post = $post_id RETURN BEFORE;
+                 IF !$deleted {{
+                    THROW "item not found";
+                 }};
+                 LET $reordered = (SELECT id, position FROM collection_item WHERE collection = $collection_id ORDER BY position ASC).id;
+                 {Q_COLLECTION_REINDEX}
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("collection_id", create_collection_id(collection_key)))
+            .bind(("post_id", create_post_id(post_key)))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: collection not found" => DBCollectionErr::NotFound,
+                "An error occurred: item not found" => DBCollectionErr::ItemNotFound,
+                _ => DBCollectionErr::DB(err),
+            })
+            .map(|_| ())
+    }
+
+    pub async fn update_collection_item_position(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        collection_key: impl Into<RecordIdKey>,
+        post_key: impl Into<RecordIdKey>,
+        new_pos: usize,
+    ) -> Result<(), DBCollectionErr> {
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $collection = SELECT id FROM ONLY $collection_id WHERE user = $user_id;
+                 IF !$collection {{
+                    THROW "collection not found";
+                 }};
+                 LET $item = (SELECT VALUE id FROM collection_item WHERE collection = $collection_id AND post = $post_id)[0];
+                 IF !$item {{
+                    THROW "item not found";
+                 }};
+                 LET $items = (SELECT id, position FROM collection_item WHERE collection = $collection_id ORDER BY position ASC).id;
+                 IF $items.len() <= $new_pos {{
+                    THROW "out of range";
+                 }};
+                 LET $reordered = $items.filter(|$v| $v != $item).insert($item, $new_pos);
+                 {Q_COLLECTION_REINDEX}
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("collection_id", create_collection_id(collection_key)))
+            .bind(("post_id", create_post_id(post_key)))
+            .bind(("new_pos", new_pos))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: collection not found" => DBCollectionErr::NotFound,
+                "An error occurred: item not found" => DBCollectionErr::ItemNotFound,
+                "An error occurred: out of range" => DBCollectionErr::OutOfRange { new_pos },
+                _ => DBCollectionErr::DB(err),
+            })
+            .map(|_| ())
+    }
+
+    /// posts in the order the owner arranged them, paged by offset
+    pub async fn get_collection_posts(
+        &self,
+        viewer_id: Option<RecordId>,
+        collection_key: impl Into<RecordIdKey>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<DBUserPost>, DB404Err> {
+        let collection_key = collection_key.into();
+        self.get_collection(viewer_id.clone(), collection_key.clone())
+            .await?;
+
+        self.db
+            .query(
+                r#"
+                    LET $posts = (SELECT post, position FROM collection_item WHERE
+                        collection = $collection_id AND post.show = true AND post.deleted_at = 0 AND
+                        (post.visibility IN ["public", "unlisted"] OR post.user = $viewer_id)
+                        ORDER BY position ASC
+                        LIMIT $get_limit
+                        START $offset).post;
+                    SELECT *, user.* FROM $posts;
+                "#,
+            )
+            .bind(("collection_id", create_collection_id(collection_key)))
+            .bind(("viewer_id", viewer_id))
+            .bind(("get_limit", limit))
+            .bind(("offset", offset))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_all(1)
+    }
+
+    /// keys of the user's own collections that already contain the post
+    pub async fn get_post_collections(
+        &self,
+        user_id: RecordId,
+        post_key: impl Into<RecordIdKey>,
+    ) -> Result<Vec<RecordId>, surrealdb::Error> {
+        self.db
+            .query(
+                r#"
+                    SELECT VALUE collection FROM collection_item WHERE
+                        post = $post_id AND
+                        collection.user = $user_id;
+                "#,
+            )
+            .bind(("user_id", user_id))
+            .bind(("post_id", create_post_id(post_key)))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::engine::local::Mem;
+
+    use crate::{
+        api::{Order, TimeRange},
+        db::{DB404Err, DBCollectionErr, DBPostRating, DBPostVisibility, Db},
+    };
+
+    #[tokio::test]
+    async fn db_collection_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+
+        let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+        let mut posts = Vec::new();
+        for i in 0..3 {
+            let post = db
+                .add_post(
+                    i,
+                    "hey2",
+                    format!("title{i}"),
+                    "description",
+                    "",
+                    0,
+                    DBPostRating::General,
+                    DBPostVisibility::Public,
+                    0,
+                )
+                .await
+                .unwrap();
+            posts.push(post);
+        }
+
+        let refs = db
+            .add_collection(1, user.id.clone(), "refs", false)
+            .await
+            .unwrap();
+        let hidden = db
+            .add_collection(2, user.id.clone(), "to buy", true)
+            .await
+            .unwrap();
+
+        let result = db.get_collection(None, hidden.id.key.clone()).await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        let result = db
+            .get_collection(Some(user2.id.clone()), hidden.id.key.clone())
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        let result = db
+            .get_collection(Some(user.id.clone()), hidden.id.key.clone())
+            .await;
+        assert!(result.is_ok());
+
+        let collections = db
+            .get_user_collections(None, "hey1", 10, TimeRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap();
+        assert_eq!(collections.len(), 1);
+        let collections = db
+            .get_user_collections(
+                Some(user.id.clone()),
+                "hey1",
+                10,
+                TimeRange::None,
+                Order::ThreeTwoOne,
+            )
+            .await
+            .unwrap();
+        assert_eq!(collections.len(), 2);
+        assert_eq!(collections[0].title, "to buy");
+
+        let result = db
+            .add_collection_item(
+                3,
+                user2.id.clone(),
+                refs.id.key.clone(),
+                posts[0].id.key.clone(),
+            )
+            .await;
+        assert!(matches!(result, Err(DBCollectionErr::NotFound)));
+        let result = db
+            .add_collection_item(3, user.id.clone(), refs.id.key.clone(), "none")
+            .await;
+        assert!(matches!(result, Err(DBCollectionErr::PostNotFound(_))));
+
+        for post in &posts {
+            db.add_collection_item(3, user.id.clone(), refs.id.key.clone(), post.id.key.clone())
+                .await
+                .unwrap();
+        }
+        let result = db
+            .add_collection_item(
+                3,
+                user.id.clone(),
+                refs.id.key.clone(),
+                posts[0].id.key.clone(),
+            )
+            .await;
+        assert!(matches!(result, Err(DBCollectionErr::Duplicate)));
+
+        let titles = |posts: Vec<crate::db::DBUserPost>| {
+            posts.into_iter().map(|v| v.title).collect::<Vec<String>>()
+        };
+        let result = db
+            .get_collection_posts(None, refs.id.key.clone(), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(titles(result), vec!["title0", "title1", "title2"]);
+
+        db.update_collection_item_position(
+            4,
+            user.id.clone(),
+            refs.id.key.clone(),
+            posts[2].id.key.clone(),
+            0,
+        )
+        .await
+        .unwrap();
+        let result = db
+            .get_collection_posts(None, refs.id.key.clone(), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(titles(result), vec!["title2", "title0", "title1"]);
+        let result = db
+            .get_collection_posts(None, refs.id.key.clone(), 2, 1)
+            .await
+            .unwrap();
+        assert_eq!(titles(result), vec!["title0", "title1"]);
+
+        let result = db
+            .update_collection_item_position(
+                4,
+                user.id.clone(),
+                refs.id.key.clone(),
+                posts[2].id.key.clone(),
+                3,
+            )
+            .await;
+        assert!(matches!(result, Err(DBCollectionErr::OutOfRange { .. })));
+
+        db.delete_collection_item(
+            5,
+            user.id.clone(),
+            refs.id.key.clone(),
+            posts[0].id.key.clone(),
+        )
+        .await
+        .unwrap();
+        let result = db
+            .delete_collection_item(
+                5,
+                user.id.clone(),
+                refs.id.key.clone(),
+                posts[0].id.key.clone(),
+            )
+            .await;
+        assert!(matches!(result, Err(DBCollectionErr::ItemNotFound)));
+        let result = db
+            .get_collection_posts(None, refs.id.key.clone(), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(titles(result), vec!["title2", "title1"]);
+        let collection = db.get_collection(None, refs.id.key.clone()).await.unwrap();
+        assert_eq!(collection.items_count, 2);
+
+        let result = db
+            .get_post_collections(user.id.clone(), posts[1].id.key.clone())
+            .await
+            .unwrap();
+        assert_eq!(result, vec![refs.id.clone()]);
+
+        let collection = db
+            .update_collection(6, user.id.clone(), refs.id.key.clone(), "backgrounds", true)
+            .await
+            .unwrap();
+        assert_eq!(collection.title, "backgrounds");
+        let result = db
+            .get_collection_posts(None, refs.id.key.clone(), 10, 0)
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        let result = db
+            .delete_collection(user2.id.clone(), refs.id.key.clone())
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        db.delete_collection(user.id.clone(), refs.id.key.clone())
+            .await
+            .unwrap();
+        let result = db
+            .get_post_collections(user.id.clone(), posts[1].id.key.clone())
+            .await
+            .unwrap();
+        assert!(result.is_empty());
+    }
+}
@@ -0,0 +1,560 @@
+use crate::api::Order;
+use crate::api::TimeRange;
+use crate::db::DB404Err;
+use crate::db::DBConversationErr;
+use crate::db::DBUser;
+use crate::db::DBUserPostFile;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealSerializeUtils;
+use crate::db::cursor::{order_query, time_range_query};
+use surrealdb::types::SurrealValue;
+use surrealdb::types::ToSql;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+use surrealdb::types::RecordIdKey;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBConversationView {
+    pub id: RecordId,
+    pub peer: DBUser,
+    pub read_at: u128,
+    pub blocked: bool,
+    pub unread: bool,
+    pub last_message_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBMessage {
+    pub id: RecordId,
+    pub conversation: RecordId,
+    pub user: DBUser,
+    pub text: String,
+    pub file: Vec<DBUserPostFile>,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+pub fn create_conversation_id(id: impl Into<RecordIdKey>) -> RecordId {
+    RecordId::new("conversation", id.into())
+}
+
+pub fn create_message_id(id: impl Into<RecordIdKey>) -> RecordId {
+    RecordId::new("message", id.into())
+}
+
+/// same key no matter who started the conversation
+fn conversation_pair_key(user_a: &RecordId, user_b: &RecordId) -> String {
+    let mut keys = [user_a.key.to_sql(), user_b.key.to_sql()];
+    keys.sort();
+    keys.join(":")
+}
+
+const Q_CONVERSATION_VIEW: &str = "
+    conversation AS id,
+    peer.* AS peer,
+    read_at,
+    blocked,
+    last_message_at > read_at AS unread,
+    last_message_at,
+    created_at
+";
+
+impl<C: Connection> Db<C> {
+    /// returns the existing conversation between both users or starts a new one
+    pub async fn add_conversation(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        username: impl Into<String>,
+    ) -> Result<DBConversationView, DBConversationErr> {
+        let username = username.into();
+        let peer = self
+            .get_user_by_username(username.clone())
+            .await
+            .map_err(|err| match err {
+                DB404Err::NotFound => DBConversationErr::UserNotFound(username),
+                DB404Err::DB(err) => DBConversationErr::DB(err),
+            })?;
+        let pair_key = conversation_pair_key(&user_id, &peer.id);
+        let q = r#"
+                 BEGIN TRANSACTION;
+                 LET $existing = (SELECT id FROM conversation WHERE pair_key = $pair_key LIMIT 1)[0];
+                 LET $conversation = IF $existing {
+                        $existing
+                     } ELSE {
+                        CREATE ONLY conversation SET
+                            pair_key = $pair_key,
+                            users = [$user_id, $peer_id],
+                            modified_at = $time,
+                            created_at = $time
+                        RETURN id
+                     };
+                 IF !$existing {
+                    CREATE conversation_member SET
+                        conversation = $conversation.id,
+                        user = $user_id,
+                        peer = $peer_id,
+                        read_at = $time,
+                        blocked = false,
+                        last_message_at = $time,
+                        modified_at = $time,
+                        created_at = $time;
+                    CREATE conversation_member SET
+                        conversation = $conversation.id,
+                        user = $peer_id,
+                        peer = $user_id,
+                        read_at = $time,
+                        blocked = false,
+                        last_message_at = $time,
+                        modified_at = $time,
+                        created_at = $time;
+                 };
+                 RETURN $conversation.id;
+                 COMMIT TRANSACTION;
+                "#;
+        trace!("about to run {q}");
+        let conversation_id: RecordId = self
+            .db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id.clone()))
+            .bind(("peer_id", peer.id))
+            .bind(("pair_key", pair_key))
+            .await
+            .check_good(DBConversationErr::from)
+            .and_then_take_expect(4)?;
+
+        self.get_conversation(user_id, conversation_id.key)
+            .await
+            .map_err(|err| match err {
+                DB404Err::NotFound => DBConversationErr::NotFound,
+                DB404Err::DB(err) => DBConversationErr::DB(err),
+            })
+    }
+
+    pub async fn get_conversation(
+        &self,
+        user_id: RecordId,
+        conversation_key: impl Into<RecordIdKey>,
+    ) -> Result<DBConversationView, DB404Err> {
+        let q = format!(
+            "
+            SELECT {Q_CONVERSATION_VIEW} FROM conversation_member WHERE
+                user = $user_id AND conversation = $conversation_id
+                LIMIT 1;
+        "
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .bind(("conversation_id", create_conversation_id(conversation_key)))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    pub async fn get_conversations(
+        &self,
+        user_id: RecordId,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> Result<Vec<DBConversationView>, surrealdb::Error> {
+        let (q_time, time_range_val) = time_range_query(&time_range, "last_message_at");
+        let q_order = order_query(&order);
+        let q = format!(
+            "
+            SELECT {Q_CONVERSATION_VIEW} FROM conversation_member WHERE
+                user = $user_id {q_time}
+                ORDER BY last_message_at {q_order}
+                LIMIT $get_limit;
+        "
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .bind(("get_limit", limit))
+            .bind(("time_range", time_range_val))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    pub async fn get_conversation_unread_count(
+        &self,
+        user_id: RecordId,
+    ) -> Result<u64, surrealdb::Error> {
+        self.db
+            .query(
+                r#"
+                    RETURN array::len(SELECT VALUE id FROM conversation_member WHERE user = $user_id AND last_message_at > read_at);
+                "#,
+            )
+            .bind(("user_id", user_id))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_expect(0)
+    }
+
+    pub async fn update_conversation_read(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        conversation_key: impl Into<RecordIdKey>,
+    ) -> Result<RecordId, DB404Err> {
+        self.db
+            .query(
+                r#"
+                    UPDATE conversation_member SET
+                        read_at = $time,
+                        modified_at = $time
+                        WHERE conversation = $conversation_id AND user = $user_id
+                    RETURN VALUE id;
+                "#,
+            )
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("conversation_id", create_conversation_id(conversation_key)))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    /// blocking is one sided, but it stops both users from sending new messages
+    pub async fn update_conversation_blocked(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        conversation_key: impl Into<RecordIdKey>,
+        blocked: bool,
+    ) -> Result<RecordId, DB404Err> {
+        self.db
+            .query(
+                r#"
+                    UPDATE conversation_member SET
+                        blocked = $blocked,
+                        modified_at = $time
+                        WHERE conversation = $conversation_id AND user = $user_id
+                    RETURN VALUE id;
+                "#,
+            )
+            .bind(("time", time))
+            .bind(("blocked", blocked))
+            .bind(("user_id", user_id))
+            .bind(("conversation_id", create_conversation_id(conversation_key)))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    pub async fn add_message(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        conversation_key: impl Into<RecordIdKey>,
+        text: impl Into<String>,
+    ) -> Result<DBMessage, DBConversationErr> {
+        let q = r#"
+                 BEGIN TRANSACTION;
+                 LET $members = SELECT user, blocked FROM conversation_member WHERE conversation = $conversation_id;
+                 IF !$members.find(|$v| $v.user = $user_id) {
+                    THROW "conversation not found";
+                 };
+                 IF $members.find(|$v| $v.blocked) {
+                    THROW "conversation is blocked";
+                 };
+                 UPDATE conversation_member SET
+                    last_message_at = $time,
+                    read_at = IF user = $user_id { $time } ELSE { read_at },
+                    modified_at = $time
+                    WHERE conversation = $conversation_id;
+                 CREATE message SET
+                    conversation = $conversation_id,
+                    user = $user_id,
+                    text = $message_text,
+                    file = [],
+                    modified_at = $time,
+                    created_at = $time
+                 RETURN *, user.*;
+                 COMMIT TRANSACTION;
+                "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("conversation_id", create_conversation_id(conversation_key)))
+            .bind(("message_text", text.into()))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: conversation not found" => DBConversationErr::NotFound,
+                "An error occurred: conversation is blocked" => DBConversationErr::Blocked,
+                _ => DBConversationErr::DB(err),
+            })
+            .and_then_take_expect(5)
+    }
+
+    pub async fn add_message_file(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        message_key: impl Into<RecordIdKey>,
+        file: DBUserPostFile,
+    ) -> Result<DBMessage, DBConversationErr> {
+        let file_hash = file.hash.clone();
+        let q = r#"
+                 BEGIN TRANSACTION;
+                 LET $message = SELECT file, user FROM ONLY $message_id;
+                 IF $message.user != $user_id {
+                    THROW "message not found";
+                 };
+                 IF $message.file.find(|$v| $v.hash = $file_hash) {
+                    THROW "hash already exists";
+                 };
+                 UPDATE $user_id SET
+                    used_storage_bytes += $size_bytes,
+                    modified_at = $time
+                 RETURN id;
+                 UPDATE $message_id SET
+                    file += $message_file,
+                    modified_at = $time
+                 RETURN id;
+                 COMMIT TRANSACTION;
+
+                 SELECT *, user.* FROM $message_id;
+                "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("message_id", create_message_id(message_key)))
+            .bind(("file_hash", file_hash.clone()))
+            .bind(("size_bytes", file.size_bytes))
+            .bind(("message_file", file))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: message not found" => DBConversationErr::NotFound,
+                "An error occurred: hash already exists" => DBConversationErr::Duplicate(file_hash),
+                _ => DBConversationErr::DB(err),
+            })
+            .and_then_take_or(7, DBConversationErr::NotFound)
+    }
+
+    pub async fn get_messages(
+        &self,
+        user_id: RecordId,
+        conversation_key: impl Into<RecordIdKey>,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> Result<Vec<DBMessage>, DB404Err> {
+        let conversation = self.get_conversation(user_id, conversation_key).await?;
+        let (q_time, time_range_val) = time_range_query(&time_range, "created_at");
+        let q_order = order_query(&order);
+        let q = format!(
+            "
+            SELECT *, user.* FROM message WHERE
+                conversation = $conversation_id {q_time}
+                ORDER BY created_at {q_order}
+                LIMIT $get_limit;
+        "
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("conversation_id", conversation.id))
+            .bind(("get_limit", limit))
+            .bind(("time_range", time_range_val))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_all(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::engine::local::Mem;
+
+    use crate::{
+        api::{Order, TimeRange},
+        db::{DB404Err, DBConversationErr, DBUserPostFile, Db},
+    };
+
+    #[tokio::test]
+    async fn db_conversation_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+
+        let user1 = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+        let user3 = db.add_user(0, "hey3", "hey3@hey.com", "123").await.unwrap();
+
+        let result = db.add_conversation(1, user1.id.clone(), "404").await;
+        assert!(matches!(result, Err(DBConversationErr::UserNotFound(_))));
+
+        let conversation = db
+            .add_conversation(1, user1.id.clone(), "hey2")
+            .await
+            .unwrap();
+        assert_eq!(conversation.peer.username, "hey2");
+        assert!(!conversation.unread);
+
+        // starting it from the other side returns the same conversation
+        let conversation2 = db
+            .add_conversation(2, user2.id.clone(), "hey1")
+            .await
+            .unwrap();
+        assert_eq!(conversation.id, conversation2.id);
+        assert_eq!(conversation2.peer.username, "hey1");
+
+        let result = db
+            .add_message(3, user3.id.clone(), conversation.id.key.clone(), "hi")
+            .await;
+        assert!(matches!(result, Err(DBConversationErr::NotFound)));
+
+        for (time, text) in [(3, "hi1"), (4, "hi2"), (5, "hi3")] {
+            db.add_message(time, user1.id.clone(), conversation.id.key.clone(), text)
+                .await
+                .unwrap();
+        }
+
+        let messages = db
+            .get_messages(
+                user2.id.clone(),
+                conversation.id.key.clone(),
+                2,
+                TimeRange::None,
+                Order::ThreeTwoOne,
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|v| v.text)
+            .collect::<Vec<String>>();
+        assert_eq!(messages, vec!["hi3", "hi2"]);
+
+        let messages = db
+            .get_messages(
+                user2.id.clone(),
+                conversation.id.key.clone(),
+                2,
+                TimeRange::Less(4),
+                Order::ThreeTwoOne,
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|v| v.text)
+            .collect::<Vec<String>>();
+        assert_eq!(messages, vec!["hi1"]);
+
+        let result = db
+            .get_messages(
+                user3.id.clone(),
+                conversation.id.key.clone(),
+                2,
+                TimeRange::None,
+                Order::ThreeTwoOne,
+            )
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        // sender reads their own messages, the receiver does not
+        let conversations = db
+            .get_conversations(user1.id.clone(), 10, TimeRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap();
+        assert_eq!(conversations.len(), 1);
+        assert!(!conversations[0].unread);
+        assert_eq!(conversations[0].last_message_at, 5);
+        let conversations = db
+            .get_conversations(user2.id.clone(), 10, TimeRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap();
+        assert!(conversations[0].unread);
+        let count = db
+            .get_conversation_unread_count(user2.id.clone())
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        db.update_conversation_read(6, user2.id.clone(), conversation.id.key.clone())
+            .await
+            .unwrap();
+        let count = db
+            .get_conversation_unread_count(user2.id.clone())
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let result = db
+            .update_conversation_read(6, user3.id.clone(), conversation.id.key.clone())
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        let message = db
+            .add_message(7, user2.id.clone(), conversation.id.key.clone(), "file")
+            .await
+            .unwrap();
+        let file = DBUserPostFile {
+            proccesed: false,
+            extension: String::from("webp"),
+            hash: String::from("one"),
+            size_bytes: 10,
+            width: 10,
+            height: 10,
+        };
+        let result = db
+            .add_message_file(7, user1.id.clone(), message.id.key.clone(), file.clone())
+            .await;
+        assert!(matches!(result, Err(DBConversationErr::NotFound)));
+        let message = db
+            .add_message_file(7, user2.id.clone(), message.id.key.clone(), file.clone())
+            .await
+            .unwrap();
+        assert_eq!(message.file, vec![file.clone()]);
+        let result = db
+            .add_message_file(7, user2.id.clone(), message.id.key.clone(), file)
+            .await;
+        assert!(matches!(result, Err(DBConversationErr::Duplicate(_))));
+        let user2 = db.get_user_by_username("hey2").await.unwrap();
+        assert_eq!(user2.used_storage_bytes, 10);
+
+        db.update_conversation_blocked(8, user2.id.clone(), conversation.id.key.clone(), true)
+            .await
+            .unwrap();
+        let conversation2 = db
+            .get_conversation(user2.id.clone(), conversation.id.key.clone())
+            .await
+            .unwrap();
+        assert!(conversation2.blocked);
+        let result = db
+            .add_message(9, user1.id.clone(), conversation.id.key.clone(), "blocked")
+            .await;
+        assert!(matches!(result, Err(DBConversationErr::Blocked)));
+        let result = db
+            .add_message(9, user2.id.clone(), conversation.id.key.clone(), "blocked")
+            .await;
+        assert!(matches!(result, Err(DBConversationErr::Blocked)));
+
+        db.update_conversation_blocked(10, user2.id.clone(), conversation.id.key.clone(), false)
+            .await
+            .unwrap();
+        db.add_message(
+            11,
+            user1.id.clone(),
+            conversation.id.key.clone(),
+            "unblocked",
+        )
+        .await
+        .unwrap();
+    }
+}
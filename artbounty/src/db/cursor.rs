@@ -0,0 +1,47 @@
+use surrealdb::types::RecordId;
+
+use crate::api::shared::cursor::CursorRange;
+use crate::api::{Order, TimeRange};
+
+/// condition that keeps rows of `table` on the requested side of the cursor, compared by
+/// created_at and then id so rows sharing a time are never skipped or repeated.
+/// the returned time and id have to be bound as $cursor_time and $cursor_id
+pub fn q_cursor_range(cursor: &CursorRange, table: &str) -> (&'static str, u128, Option<RecordId>) {
+    match cursor {
+        CursorRange::None => ("", 0, None),
+        CursorRange::Before(cursor) if cursor.key.is_empty() => {
+            ("created_at < $cursor_time", cursor.created_at, None)
+        }
+        CursorRange::After(cursor) if cursor.key.is_empty() => {
+            ("created_at >= $cursor_time", cursor.created_at, None)
+        }
+        CursorRange::Before(cursor) => (
+            "(created_at < $cursor_time OR (created_at = $cursor_time AND id < $cursor_id))",
+            cursor.created_at,
+            Some(RecordId::new(table, cursor.key.clone())),
+        ),
+        CursorRange::After(cursor) => (
+            "(created_at > $cursor_time OR (created_at = $cursor_time AND id > $cursor_id))",
+            cursor.created_at,
+            Some(RecordId::new(table, cursor.key.clone())),
+        ),
+    }
+}
+
+/// `AND` condition on `field` for the time range, the returned time has to be bound as $time_range
+pub(crate) fn time_range_query(time_range: &TimeRange, field: &str) -> (String, u128) {
+    match time_range {
+        TimeRange::None => (String::new(), 0),
+        TimeRange::Less(v) => (format!("AND {field} < $time_range"), *v),
+        TimeRange::LessOrEqual(v) => (format!("AND {field} <= $time_range"), *v),
+        TimeRange::More(v) => (format!("AND {field} > $time_range"), *v),
+        TimeRange::MoreOrEqual(v) => (format!("AND {field} >= $time_range"), *v),
+    }
+}
+
+pub(crate) fn order_query(order: &Order) -> &'static str {
+    match order {
+        Order::OneTwoThree => "ASC",
+        Order::ThreeTwoOne => "DESC",
+    }
+}
@@ -0,0 +1,416 @@
+use std::fmt::Display;
+
+use crate::api::Order;
+use crate::api::TimeRange;
+use crate::db::DB404Err;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealSerializeUtils;
+use crate::db::cursor::{order_query, time_range_query};
+use crate::db::post_comment::DBPostComment;
+use surrealdb::types::SurrealValue;
+use surrealdb::types::ToSql;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+use surrealdb::types::RecordIdKey;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBNotification {
+    pub id: RecordId,
+    pub user: RecordId,
+    pub kind: String,
+    pub group_key: String,
+    pub post: RecordId,
+    pub actors: Vec<RecordId>,
+    pub read: bool,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBNotificationView {
+    pub id: RecordId,
+    pub kind: String,
+    pub post: RecordId,
+    pub post_title: String,
+    pub post_username: String,
+    pub actor_usernames: Vec<String>,
+    pub read: bool,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub enum DBNotificationKind {
+    PostLike,
+    PostComment,
+    CommentReply,
+}
+
+impl Display for DBNotificationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            DBNotificationKind::PostLike => "post_like",
+            DBNotificationKind::PostComment => "post_comment",
+            DBNotificationKind::CommentReply => "comment_reply",
+        };
+
+        write!(f, "{}", text)
+    }
+}
+
+pub fn create_notification_id(id: impl Into<RecordIdKey>) -> RecordId {
+    RecordId::new("notification", id.into())
+}
+
+impl<C: Connection> Db<C> {
+    /// unread notifications with the same group are collapsed into one, so a burst of likes
+    /// ends up as a single "n people liked your post" entry
+    pub async fn add_notification(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        actor_id: RecordId,
+        kind: DBNotificationKind,
+        post_id: RecordId,
+        group_key: impl Into<String>,
+    ) -> Result<Option<DBNotification>, surrealdb::Error> {
+        if user_id == actor_id {
+            return Ok(None);
+        }
+        let q = r#"
+                 BEGIN TRANSACTION;
+                 LET $existing = (SELECT id FROM notification WHERE
+                        user = $user_id AND
+                        group_key = $group_key AND
+                        read = false
+                        LIMIT 1)[0];
+                 LET $result = IF $existing {
+                        UPDATE ONLY $existing.id SET
+                            actors = array::union(actors, [$actor_id]),
+                            modified_at = $time
+                        RETURN AFTER
+                     } ELSE {
+                        CREATE ONLY notification SET
+                            user = $user_id,
+                            kind = $kind,
+                            group_key = $group_key,
+                            post = $post_id,
+                            actors = [$actor_id],
+                            read = false,
+                            modified_at = $time,
+                            created_at = $time
+                        RETURN AFTER
+                     };
+                 RETURN $result;
+                 COMMIT TRANSACTION;
+                "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("actor_id", actor_id))
+            .bind(("kind", kind.to_string()))
+            .bind(("group_key", group_key.into()))
+            .bind(("post_id", post_id))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_expect(3)
+            .map(Some)
+    }
+
+    pub async fn add_notification_post_like(
+        &self,
+        time: u128,
+        actor_id: RecordId,
+        post_key: impl Into<RecordIdKey>,
+    ) -> Result<Option<DBNotification>, DB404Err> {
+        let post = self.get_post(post_key).await?;
+        let kind = DBNotificationKind::PostLike;
+        let group = format!("{kind}:{}", post.id.key.to_sql());
+        self.add_notification(time, post.user.id, actor_id, kind, post.id, group)
+            .await
+            .map_err(DB404Err::from)
+    }
+
+    pub async fn add_notification_post_comment(
+        &self,
+        time: u128,
+        comment: &DBPostComment,
+    ) -> Result<Option<DBNotification>, DB404Err> {
+        let (user_id, kind, group) = match comment.parent.last() {
+            Some(parent_id) => {
+                let parent = self.get_post_comment(parent_id.key.clone()).await?;
+                let kind = DBNotificationKind::CommentReply;
+                let group = format!("{kind}:{}", parent.id.key.to_sql());
+                (parent.user.id, kind, group)
+            }
+            None => {
+                let post = self.get_post(comment.post.key.clone()).await?;
+                let kind = DBNotificationKind::PostComment;
+                let group = format!("{kind}:{}", post.id.key.to_sql());
+                (post.user.id, kind, group)
+            }
+        };
+        self.add_notification(
+            time,
+            user_id,
+            comment.user.id.clone(),
+            kind,
+            comment.post.clone(),
+            group,
+        )
+        .await
+        .map_err(DB404Err::from)
+    }
+
+    pub async fn get_notifications(
+        &self,
+        user_id: RecordId,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> Result<Vec<DBNotificationView>, surrealdb::Error> {
+        let (q_time, time_range_val) = time_range_query(&time_range, "modified_at");
+        let q_order = order_query(&order);
+
+        let q = format!(
+            "
+            SELECT
+                id,
+                kind,
+                post,
+                post.title AS post_title,
+                post.user.username AS post_username,
+                actors.username AS actor_usernames,
+                read,
+                modified_at,
+                created_at
+            FROM notification WHERE
+                user = $user_id {q_time}
+                ORDER BY modified_at {q_order}
+                LIMIT $get_limit;
+        "
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .bind(("get_limit", limit))
+            .bind(("time_range", time_range_val))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    pub async fn get_notification_unread_count(
+        &self,
+        user_id: RecordId,
+    ) -> Result<u64, surrealdb::Error> {
+        self.db
+            .query(
+                r#"
+                    RETURN array::len(SELECT VALUE id FROM notification WHERE user = $user_id AND read = false);
+                "#,
+            )
+            .bind(("user_id", user_id))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_expect(0)
+    }
+
+    pub async fn update_notification_read(
+        &self,
+        user_id: RecordId,
+        notification_key: impl Into<RecordIdKey>,
+    ) -> Result<DBNotification, DB404Err> {
+        self.db
+            .query(
+                r#"
+                    UPDATE $notification_id SET read = true WHERE user = $user_id RETURN AFTER;
+                "#,
+            )
+            .bind(("user_id", user_id))
+            .bind(("notification_id", create_notification_id(notification_key)))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    pub async fn update_notification_read_all(
+        &self,
+        user_id: RecordId,
+    ) -> Result<(), surrealdb::Error> {
+        self.db
+            .query(
+                r#"
+                    UPDATE notification SET read = true WHERE user = $user_id AND read = false;
+                "#,
+            )
+            .bind(("user_id", user_id))
+            .await
+            .check_good(surrealdb::Error::from)
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::{engine::local::Mem, types::ToSql};
+
+    use crate::{
+        api::{Order, TimeRange},
+        db::{DB404Err, DBPostRating, DBPostVisibility, Db},
+    };
+
+    #[tokio::test]
+    async fn db_notification_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+
+        let user1 = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+        let user3 = db.add_user(0, "hey3", "hey3@hey.com", "123").await.unwrap();
+        let post = db
+            .add_post(
+                0,
+                "hey1",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+
+        // liking your own post does not notify you
+        let result = db
+            .add_notification_post_like(1, user1.id.clone(), post.id.key.clone())
+            .await
+            .unwrap();
+        assert!(result.is_none());
+
+        let result = db
+            .add_notification_post_like(1, user1.id.clone(), "404")
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        db.add_notification_post_like(2, user2.id.clone(), post.id.key.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        let collapsed = db
+            .add_notification_post_like(3, user3.id.clone(), post.id.key.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(collapsed.actors.len(), 2);
+        assert_eq!(collapsed.modified_at, 3);
+
+        // same actor twice is still counted once
+        let collapsed = db
+            .add_notification_post_like(4, user3.id.clone(), post.id.key.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(collapsed.actors.len(), 2);
+
+        let comment = db
+            .add_post_comment(5, user2.id.clone(), post.id.key.clone(), None, "wow")
+            .await
+            .unwrap();
+        db.add_notification_post_comment(5, &comment)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let reply = db
+            .add_post_comment(
+                6,
+                user1.id.clone(),
+                post.id.key.clone(),
+                Some(comment.id.key.to_sql()),
+                "thanks",
+            )
+            .await
+            .unwrap();
+        let reply_notification = db
+            .add_notification_post_comment(6, &reply)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reply_notification.user, user2.id);
+        assert_eq!(reply_notification.kind, "comment_reply");
+
+        let notifications = db
+            .get_notifications(user1.id.clone(), 10, TimeRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap();
+        assert_eq!(notifications.len(), 2);
+        assert_eq!(notifications[0].kind, "post_comment");
+        assert_eq!(notifications[1].kind, "post_like");
+        assert_eq!(notifications[1].actor_usernames, vec!["hey2", "hey3"]);
+        assert_eq!(notifications[1].post_title, "title");
+        assert_eq!(notifications[1].post_username, "hey1");
+
+        let notifications = db
+            .get_notifications(user1.id.clone(), 10, TimeRange::Less(5), Order::ThreeTwoOne)
+            .await
+            .unwrap();
+        assert_eq!(notifications.len(), 1);
+
+        let count = db
+            .get_notification_unread_count(user1.id.clone())
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let result = db
+            .update_notification_read(user2.id.clone(), notifications[0].id.key.clone())
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        let result = db
+            .update_notification_read(user1.id.clone(), notifications[0].id.key.clone())
+            .await
+            .unwrap();
+        assert!(result.read);
+
+        let count = db
+            .get_notification_unread_count(user1.id.clone())
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // once read, a new like starts a fresh notification
+        let user4 = db.add_user(0, "hey4", "hey4@hey.com", "123").await.unwrap();
+        let fresh = db
+            .add_notification_post_like(7, user4.id.clone(), post.id.key.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(fresh.actors.len(), 1);
+
+        db.update_notification_read_all(user1.id.clone())
+            .await
+            .unwrap();
+        let count = db
+            .get_notification_unread_count(user1.id.clone())
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let count = db
+            .get_notification_unread_count(user2.id.clone())
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::api::Order;
-use crate::api::TimeRange;
+use crate::api::shared::cursor::{CursorRange, PAGE_LIMIT_MAX};
 use crate::db::DB404Err;
 use crate::db::DBPostCommentErr;
 use crate::db::DBPostLikeErr;
@@ -8,7 +8,10 @@ use crate::db::DBUserPost;
 use crate::db::SurrealCheckUtils;
 use crate::db::SurrealErrUtils;
 use crate::db::SurrealSerializeUtils;
+use crate::db::cursor::q_cursor_range;
 use crate::db::post::create_post_id;
+use crate::db::revision::{DBRevisionField, q_update_with_revision};
+use crate::db::user_block::q_user_block_filter;
 use surrealdb::types::SurrealValue;
 use surrealdb::types::ToSql;
 use tracing::trace;
@@ -26,6 +29,8 @@ pub struct DBPostComment {
     pub replies_count: usize,
     pub parent: Vec<RecordId>,
     pub text: String,
+    pub edited_at: u128,
+    pub deleted_at: u128,
     pub modified_at: u128,
     pub created_at: u128,
 }
@@ -45,23 +50,20 @@ impl<C: Connection> Db<C> {
     ) -> Result<DBPostComment, DB404Err> {
         let comment_id = create_post_comment_id(comment_id);
         // let parent_id = post_comment_reply.map(|v| create_post_comment_id(v));
-        let q = r#"
-                 UPDATE post_comment SET
-                    text = $comment_text,
-                    modified_at = $time
-                    WHERE id = $comment_id AND user = $user_id
-                 RETURN *, user.*;
-                "#;
+        let q = q_update_with_revision(DBRevisionField::Text);
         trace!("about to run {q}");
         self.db
             .query(q)
             .bind(("time", time))
             .bind(("user_id", user_id))
-            .bind(("comment_id", comment_id))
-            .bind(("comment_text", text.into()))
+            .bind(("target_id", comment_id))
+            .bind(("value", text.into()))
             .await
-            .check_good(DB404Err::from)
-            .and_then_take_or(0, DB404Err::NotFound)
+            .check_better(|err| match err.message() {
+                "An error occurred: not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_or(4, DB404Err::NotFound)
     }
     pub async fn add_post_comment(
         &self,
@@ -75,12 +77,19 @@ impl<C: Connection> Db<C> {
         let parent_id = post_comment_reply.map(|v| create_post_comment_id(v));
         let q = r#"
                  BEGIN TRANSACTION;
-                 LET $post = SELECT id FROM ONLY $post_id;
+                 LET $post = SELECT id, user, deleted_at FROM ONLY $post_id;
                  LET $parent = IF $parent_id {
-                         SELECT id, parent, replies_count FROM ONLY $parent_id                 
+                         SELECT id, user, parent, replies_count, deleted_at FROM ONLY $parent_id                 
                      } ELSE {
                          NULL
                      };
+                 LET $blockers = SELECT VALUE user FROM user_block WHERE target = $user_id AND kind = "block";
+                 IF $post.user IN $blockers OR $parent.user IN $blockers {
+                    THROW "blocked";
+                 };
+                 IF $post.deleted_at > 0 OR $parent.deleted_at > 0 {
+                    THROW "trashed";
+                 };
 
                  IF $parent {
                     UPDATE $parent.id SET replies_count = $parent.replies_count + 1;
@@ -110,7 +119,11 @@ impl<C: Connection> Db<C> {
             .bind(("comment_text", text.into()))
             .bind(("parent_id", parent_id))
             .await
-            .check_good(|err| match err {
+            .check_better(|err| match err {
+                err if err.message() == "An error occurred: blocked" => DBPostCommentErr::Blocked,
+                err if err.message() == "An error occurred: trashed" => {
+                    DBPostCommentErr::PostNotFound(post_id.to_sql())
+                }
                 err if err.field_value_null("post_comment") => {
                     DBPostCommentErr::ReplyCommentNotFound(post_id.to_sql())
                 }
@@ -119,7 +132,7 @@ impl<C: Connection> Db<C> {
                 }
                 err => err.into(),
             })
-            .and_then_take_expect(4)
+            .and_then_take_expect(7)
     }
 
     pub async fn get_post_comments_all(&self) -> Result<Vec<DBPostComment>, DB404Err> {
@@ -162,24 +175,23 @@ impl<C: Connection> Db<C> {
         parent_id: Option<impl Into<RecordIdKey>>,
         flatten: bool,
         limit: usize,
-        time_range: TimeRange,
+        cursor: CursorRange,
         order: Order,
+        viewer_id: Option<RecordId>,
     ) -> Result<Vec<DBPostComment>, DB404Err> {
+        let limit = limit.min(PAGE_LIMIT_MAX);
         let parent_id = parent_id.map(|v| create_post_comment_id(v.into()));
-
-        let q_time_after = match time_range {
-            TimeRange::None => "",
-            TimeRange::Less(_) => "AND created_at < $time_range",
-            TimeRange::LessOrEqual(_) => "AND created_at <= $time_range",
-            TimeRange::More(_) => "AND created_at > $time_range",
-            TimeRange::MoreOrEqual(_) => "AND created_at >= $time_range",
+        let q_block = if viewer_id.is_some() {
+            format!("AND {}", q_user_block_filter("user", true))
+        } else {
+            String::new()
         };
-        let time_range = match time_range {
-            TimeRange::None => 0,
-            TimeRange::Less(v)
-            | TimeRange::LessOrEqual(v)
-            | TimeRange::More(v)
-            | TimeRange::MoreOrEqual(v) => v,
+
+        let (q_cursor, cursor_time, cursor_id) = q_cursor_range(&cursor, "post_comment");
+        let q_cursor = if q_cursor.is_empty() {
+            String::new()
+        } else {
+            format!("AND {q_cursor}")
         };
         let q_order = match order {
             Order::OneTwoThree => "ASC",
@@ -197,13 +209,16 @@ impl<C: Connection> Db<C> {
         // q_computed.push_str(q_parent);
         // let q_comupted = q_computed.trim();
 
+        // trashed comments that have replies stay as placeholders so the thread doesn't fall apart
         let q = format!(
             "
             SELECT *, user.* FROM post_comment WHERE
                     post = $post_id
-                    {q_time_after}
+                    AND (deleted_at = 0 OR replies_count > 0)
+                    {q_cursor}
                     {q_parent}
-                    ORDER BY created_at {q_order}
+                    {q_block}
+                    ORDER BY created_at {q_order}, id {q_order}
                     LIMIT $comment_limit
         "
         );
@@ -211,10 +226,12 @@ impl<C: Connection> Db<C> {
         self.db
             .query(q)
             .bind(("time", time))
-            .bind(("time_range", time_range))
+            .bind(("cursor_time", cursor_time))
+            .bind(("cursor_id", cursor_id))
             .bind(("parent_id", parent_id))
             .bind(("post_id", create_post_id(post_id.into())))
             .bind(("comment_limit", limit))
+            .bind(("viewer_id", viewer_id))
             .await
             .check_good(DB404Err::from)
             .and_then_take_all(0)
@@ -242,7 +259,8 @@ impl<C: Connection> Db<C> {
             if $parent.replies_count > 0 {
                 UPDATE $parent.id SET replies_count = $parent.replies_count - 1;
             };
-            DELETE post_comment WHERE (parent.find($comment_id) OR id == $comment_id) AND user = $user_id;
+            LET $deleted = DELETE post_comment WHERE (parent.find($comment_id) OR id == $comment_id) AND user = $user_id RETURN BEFORE;
+            DELETE revision WHERE target IN $deleted.id;
             COMMIT TRANSACTION;
             ";
         trace!("about to run {q} with input $comment_id: {comment_id:?}, $user_id: {user_id:?}");
@@ -267,10 +285,14 @@ mod tests {
     use tracing::trace;
 
     use crate::{
-        api::{ChangeUsernameErr, Order, ServerRes, TimeRange},
+        api::{
+            ChangeUsernameErr, Order, ServerRes,
+            shared::cursor::{Cursor, CursorRange},
+        },
         db::{
             AddUserErr, DB404Err, DBChangeUsernameErr, DBEmailIsTakenErr, DBPostLikeErr,
-            DBSentEmailReason, DBUserPostFile, Db, create_user_id, post_like::create_post_like_id,
+            DBPostRating, DBPostVisibility, DBSentEmailReason, DBUserPostFile, Db, create_user_id,
+            post_like::create_post_like_id,
         },
         init_test_log,
     };
@@ -283,7 +305,17 @@ mod tests {
         db.migrate(0).await.unwrap();
         let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
         let post = db
-            .add_post(0, "hey1", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey1",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -314,8 +346,9 @@ mod tests {
                 None::<String>,
                 false,
                 2,
-                TimeRange::More(0),
+                CursorRange::After(Cursor::at(1)),
                 Order::OneTwoThree,
+                None,
             )
             .await
             .unwrap();
@@ -333,8 +366,9 @@ mod tests {
                 None::<String>,
                 false,
                 2,
-                TimeRange::More(2),
+                CursorRange::After(Cursor::at(3)),
                 Order::OneTwoThree,
+                None,
             )
             .await
             .unwrap();
@@ -354,7 +388,17 @@ mod tests {
         db.migrate(0).await.unwrap();
         let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
         let post = db
-            .add_post(0, "hey1", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey1",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -385,8 +429,9 @@ mod tests {
                 None::<String>,
                 false,
                 2,
-                TimeRange::Less(4),
+                CursorRange::Before(Cursor::at(4)),
                 Order::ThreeTwoOne,
+                None,
             )
             .await
             .unwrap();
@@ -404,8 +449,9 @@ mod tests {
                 None::<String>,
                 false,
                 2,
-                TimeRange::Less(2),
+                CursorRange::Before(Cursor::at(2)),
                 Order::ThreeTwoOne,
+                None,
             )
             .await
             .unwrap();
@@ -425,7 +471,17 @@ mod tests {
         db.migrate(0).await.unwrap();
         let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
         let post = db
-            .add_post(0, "hey1", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey1",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -456,8 +512,9 @@ mod tests {
                 None::<String>,
                 false,
                 5,
-                TimeRange::More(2),
+                CursorRange::After(Cursor::at(3)),
                 Order::OneTwoThree,
+                None,
             )
             .await
             .unwrap();
@@ -475,8 +532,9 @@ mod tests {
                 None::<String>,
                 false,
                 5,
-                TimeRange::Less(2),
+                CursorRange::Before(Cursor::at(2)),
                 Order::OneTwoThree,
+                None,
             )
             .await
             .unwrap();
@@ -494,8 +552,9 @@ mod tests {
                 None::<String>,
                 false,
                 5,
-                TimeRange::MoreOrEqual(2),
+                CursorRange::After(Cursor::at(2)),
                 Order::OneTwoThree,
+                None,
             )
             .await
             .unwrap();
@@ -513,8 +572,9 @@ mod tests {
                 None::<String>,
                 false,
                 5,
-                TimeRange::LessOrEqual(2),
+                CursorRange::Before(Cursor::at(3)),
                 Order::OneTwoThree,
+                None,
             )
             .await
             .unwrap();
@@ -532,8 +592,9 @@ mod tests {
                 None::<String>,
                 false,
                 5,
-                TimeRange::LessOrEqual(2),
+                CursorRange::Before(Cursor::at(3)),
                 Order::ThreeTwoOne,
+                None,
             )
             .await
             .unwrap();
@@ -555,7 +616,17 @@ mod tests {
 
         let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
         let post = db
-            .add_post(0, "hey1", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey1",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -618,7 +689,17 @@ mod tests {
 
         let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
         let post = db
-            .add_post(0, "hey1", "title", "description", "", 0)
+            .add_post(
+                0,
+                "hey1",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -644,8 +725,9 @@ mod tests {
                 None::<String>,
                 false,
                 3,
-                TimeRange::None,
+                CursorRange::None,
                 Order::OneTwoThree,
+                None,
             )
             .await
             .unwrap();
@@ -663,8 +745,9 @@ mod tests {
                 None::<String>,
                 false,
                 3,
-                TimeRange::None,
+                CursorRange::None,
                 Order::OneTwoThree,
+                None,
             )
             .await
             .unwrap();
@@ -683,7 +766,17 @@ mod tests {
         let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
 
         let post = db
-            .add_post(1, "hey1", "title", "description", "", 0)
+            .add_post(
+                1,
+                "hey1",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -725,7 +818,17 @@ mod tests {
 
         let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
         let post = db
-            .add_post(1, "hey1", "title", "description", "", 0)
+            .add_post(
+                1,
+                "hey1",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
             .await
             .unwrap();
 
@@ -796,8 +899,9 @@ mod tests {
                 None::<String>,
                 false,
                 3,
-                TimeRange::None,
+                CursorRange::None,
                 Order::OneTwoThree,
+                None,
             )
             .await
             .unwrap();
@@ -810,8 +914,9 @@ mod tests {
                 Some(post_comment.id.key.clone().to_sql()),
                 false,
                 3,
-                TimeRange::None,
+                CursorRange::None,
                 Order::OneTwoThree,
+                None,
             )
             .await
             .unwrap();
@@ -825,8 +930,9 @@ mod tests {
                 Some(post_comment.id.key.clone().to_sql()),
                 true,
                 3,
-                TimeRange::None,
+                CursorRange::None,
                 Order::OneTwoThree,
+                None,
             )
             .await
             .unwrap();
@@ -841,8 +947,9 @@ mod tests {
                 None::<String>,
                 true,
                 3,
-                TimeRange::None,
+                CursorRange::None,
                 Order::OneTwoThree,
+                None,
             )
             .await
             .unwrap();
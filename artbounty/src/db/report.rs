@@ -0,0 +1,403 @@
+use crate::db::DB404Err;
+use crate::db::DBReportErr;
+use crate::db::DBUser;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealErrUtils;
+use crate::db::SurrealSerializeUtils;
+use crate::db::audit::{DBAudit, DBAuditKind, q_audit_event_add};
+use surrealdb::types::SurrealValue;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+use surrealdb::types::RecordIdKey;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBReport {
+    pub id: RecordId,
+    pub reporter: DBUser,
+    pub target_user: DBUser,
+    pub target_kind: String,
+    pub target: RecordId,
+    pub post: Option<RecordId>,
+    pub post_username: Option<String>,
+    pub reason: String,
+    pub severity: u64,
+    pub text: String,
+    pub resolved: bool,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBModerationAction {
+    pub id: RecordId,
+    pub moderator: DBUser,
+    pub report: RecordId,
+    pub target_user: RecordId,
+    pub action: String,
+    pub reason: String,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+/// what a new report points at, the post is set for both post and comment reports
+#[derive(Debug, Clone, PartialEq)]
+pub struct DBReportTarget {
+    pub kind: String,
+    pub id: RecordId,
+    pub user: RecordId,
+    pub post: Option<RecordId>,
+}
+
+const Q_REPORT_FIELDS: &str = "*, reporter.*, target_user.*, post.user.username AS post_username";
+
+pub fn create_report_id(id: impl Into<RecordIdKey>) -> RecordId {
+    RecordId::new("report", id.into())
+}
+
+impl<C: Connection> Db<C> {
+    /// a user can only have one open report per target
+    pub async fn add_report(
+        &self,
+        time: u128,
+        reporter_id: RecordId,
+        target: DBReportTarget,
+        reason: impl Into<String>,
+        severity: u64,
+        text: impl Into<String>,
+    ) -> Result<DBReport, DBReportErr> {
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $exists = SELECT id FROM ONLY report WHERE target = $target_id AND reporter = $reporter_id AND resolved = false LIMIT 1;
+                 IF $exists {{
+                    THROW "already reported";
+                 }};
+                 LET $report = CREATE ONLY report SET
+                    reporter = $reporter_id,
+                    target_user = $target_user_id,
+                    target_kind = $target_kind,
+                    target = $target_id,
+                    post = $post_id,
+                    reason = $reason,
+                    severity = $severity,
+                    text = $text,
+                    resolved = false,
+                    modified_at = $time,
+                    created_at = $time;
+                 SELECT {Q_REPORT_FIELDS} FROM ONLY $report.id;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("reporter_id", reporter_id))
+            .bind(("target_user_id", target.user))
+            .bind(("target_kind", target.kind))
+            .bind(("target_id", target.id))
+            .bind(("post_id", target.post))
+            .bind(("reason", reason.into()))
+            .bind(("severity", severity))
+            .bind(("text", text.into()))
+            .bind(("time", time))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: already reported" => DBReportErr::AlreadyReported,
+                _ => DBReportErr::DB(err),
+            })
+            .and_then_take_expect(4)
+    }
+
+    pub async fn get_report(
+        &self,
+        report_key: impl Into<RecordIdKey>,
+    ) -> Result<DBReport, DB404Err> {
+        self.db
+            .query(format!("SELECT {Q_REPORT_FIELDS} FROM ONLY $report_id;"))
+            .bind(("report_id", create_report_id(report_key)))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    /// most severe first, oldest first within the same severity
+    pub async fn get_reports_open(
+        &self,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<DBReport>, surrealdb::Error> {
+        self.db
+            .query(format!(
+                r#"
+                SELECT {Q_REPORT_FIELDS} FROM report WHERE resolved = false
+                    ORDER BY severity DESC, created_at ASC
+                    LIMIT $get_limit START $offset;
+                "#
+            ))
+            .bind(("get_limit", limit))
+            .bind(("offset", offset))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    /// resolves every open report on the same target and records the moderation action,
+    /// returns the resolved reports so their reporters can be notified
+    pub async fn resolve_report(
+        &self,
+        time: u128,
+        report_key: impl Into<RecordIdKey>,
+        moderator_id: RecordId,
+        action: impl Into<String>,
+        reason: impl Into<String>,
+        audit: DBAudit,
+    ) -> Result<Vec<DBReport>, DBReportErr> {
+        let q_audit = q_audit_event_add(
+            DBAuditKind::ReportResolve,
+            "$report.target_user",
+            "string::concat(<string> $report_id, \" \", $report.reason)",
+            "string::concat($action, \": \", $reason)",
+        );
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $report = SELECT id, target, target_user, reason, resolved FROM ONLY $report_id;
+                 IF !$report {{
+                    THROW "report not found";
+                 }};
+                 IF $report.resolved {{
+                    THROW "report already resolved";
+                 }};
+                 CREATE moderation_action SET
+                    moderator = $moderator_id,
+                    report = $report_id,
+                    target_user = $report.target_user,
+                    action = $action,
+                    reason = $reason,
+                    modified_at = $time,
+                    created_at = $time;
+                 LET $resolved = UPDATE report SET resolved = true, modified_at = $time WHERE target = $report.target AND resolved = false RETURN id;
+                 SELECT {Q_REPORT_FIELDS} FROM $resolved.id;
+                 {q_audit}
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("report_id", create_report_id(report_key)))
+            .bind(("moderator_id", moderator_id))
+            .bind(("action", action.into()))
+            .bind(("reason", reason.into()))
+            .bind(("time", time))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: report not found" => DBReportErr::NotFound,
+                "An error occurred: report already resolved" => DBReportErr::AlreadyResolved,
+                _ => DBReportErr::DB(err),
+            })
+            .and_then_take_all(6)
+    }
+
+    pub async fn get_moderation_actions(
+        &self,
+        target_user_id: RecordId,
+    ) -> Result<Vec<DBModerationAction>, surrealdb::Error> {
+        self.db
+            .query(
+                r#"
+                SELECT *, moderator.* FROM moderation_action WHERE target_user = $target_user_id ORDER BY created_at DESC;
+                "#,
+            )
+            .bind(("target_user_id", target_user_id))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::engine::local::Mem;
+
+    use crate::db::{
+        DB404Err, DBPostRating, DBPostVisibility, DBReportErr, Db, audit::DBAudit,
+        report::DBReportTarget,
+    };
+
+    #[tokio::test]
+    async fn db_report_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+
+        let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+        let user3 = db.add_user(0, "hey3", "hey3@hey.com", "123").await.unwrap();
+        let post = db
+            .add_post(
+                1,
+                "hey1",
+                "title",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+
+        let report = db
+            .add_report(
+                2,
+                user2.id.clone(),
+                DBReportTarget {
+                    kind: "post".to_string(),
+                    id: post.id.clone(),
+                    user: user.id.clone(),
+                    post: Some(post.id.clone()),
+                },
+                "spam",
+                1,
+                "buy stuff",
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.reporter.id, user2.id);
+        assert_eq!(report.target_user.id, user.id);
+        assert_eq!(report.post_username, Some("hey1".to_string()));
+        assert!(!report.resolved);
+
+        let result = db
+            .add_report(
+                3,
+                user2.id.clone(),
+                DBReportTarget {
+                    kind: "post".to_string(),
+                    id: post.id.clone(),
+                    user: user.id.clone(),
+                    post: Some(post.id.clone()),
+                },
+                "spam",
+                1,
+                "again",
+            )
+            .await;
+        assert!(matches!(result, Err(DBReportErr::AlreadyReported)));
+
+        let report2 = db
+            .add_report(
+                4,
+                user3.id.clone(),
+                DBReportTarget {
+                    kind: "post".to_string(),
+                    id: post.id.clone(),
+                    user: user.id.clone(),
+                    post: Some(post.id.clone()),
+                },
+                "spam",
+                1,
+                "",
+            )
+            .await
+            .unwrap();
+        let report3 = db
+            .add_report(
+                5,
+                user3.id.clone(),
+                DBReportTarget {
+                    kind: "user".to_string(),
+                    id: user.id.clone(),
+                    user: user.id.clone(),
+                    post: None,
+                },
+                "illegal",
+                3,
+                "",
+            )
+            .await
+            .unwrap();
+
+        let result = db.get_reports_open(10, 0).await.unwrap();
+        assert_eq!(
+            result.iter().map(|v| v.id.clone()).collect::<Vec<_>>(),
+            vec![report3.id.clone(), report.id.clone(), report2.id.clone()]
+        );
+        let result = db.get_reports_open(10, 1).await.unwrap();
+        assert_eq!(result.len(), 2);
+
+        let result = db
+            .resolve_report(
+                6,
+                report.id.key.clone(),
+                user3.id.clone(),
+                "dismiss",
+                "fine",
+                DBAudit::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(result.iter().all(|v| v.resolved));
+        let result = db.get_reports_open(10, 0).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, report3.id);
+
+        let result = db
+            .resolve_report(
+                7,
+                report2.id.key.clone(),
+                user3.id.clone(),
+                "dismiss",
+                "fine",
+                DBAudit::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(DBReportErr::AlreadyResolved)));
+        let result = db
+            .resolve_report(
+                7,
+                "404",
+                user3.id.clone(),
+                "dismiss",
+                "fine",
+                DBAudit::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(DBReportErr::NotFound)));
+
+        let result = db.get_moderation_actions(user.id.clone()).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].moderator.id, user3.id);
+        assert_eq!(result[0].action, "dismiss");
+        assert_eq!(result[0].reason, "fine");
+
+        // resolved reports no longer block reporting again
+        db.add_report(
+            8,
+            user2.id.clone(),
+            DBReportTarget {
+                kind: "post".to_string(),
+                id: post.id.clone(),
+                user: user.id.clone(),
+                post: Some(post.id.clone()),
+            },
+            "spam",
+            1,
+            "",
+        )
+        .await
+        .unwrap();
+
+        let result = db.get_report(report.id.key.clone()).await.unwrap();
+        assert!(result.resolved);
+        let result = db.get_report("404").await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt::Display;
+
+use crate::db::DBUser;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealSerializeUtils;
+use surrealdb::types::SurrealValue;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBRevision {
+    pub id: RecordId,
+    pub user: DBUser,
+    pub target: RecordId,
+    pub field: String,
+    pub before: String,
+    pub after: String,
+    pub created_at: u128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DBRevisionField {
+    Title,
+    Description,
+    Tags,
+    Text,
+}
+
+impl Display for DBRevisionField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            DBRevisionField::Title => "title",
+            DBRevisionField::Description => "description",
+            DBRevisionField::Tags => "tags",
+            DBRevisionField::Text => "text",
+        };
+
+        write!(f, "{}", text)
+    }
+}
+
+/// owner only update of a single text field on a record that isn't trashed, the old value is kept as a revision when it changes,
+/// $target_id, $user_id, $value and $time have to be bound, the updated record is at index 4
+pub fn q_update_with_revision(field: DBRevisionField) -> String {
+    format!(
+        r#"
+                 BEGIN TRANSACTION;
+                 LET $target = SELECT id, user, deleted_at, {field} FROM ONLY $target_id;
+                 IF !$target OR $target.user != $user_id OR $target.deleted_at > 0 {{
+                    THROW "not found";
+                 }};
+                 IF $target.{field} != $value {{
+                    CREATE revision SET
+                        user = $user_id,
+                        target = $target_id,
+                        field = "{field}",
+                        before = $target.{field},
+                        after = $value,
+                        created_at = $time;
+                 }};
+                 UPDATE ONLY $target_id SET
+                    edited_at = IF $target.{field} != $value THEN $time ELSE edited_at END,
+                    {field} = $value,
+                    modified_at = $time
+                 RETURN *, user.*;
+                 COMMIT TRANSACTION;
+        "#
+    )
+}
+
+impl<C: Connection> Db<C> {
+    /// newest first
+    pub async fn get_revisions(
+        &self,
+        target_id: RecordId,
+        limit: usize,
+    ) -> Result<Vec<DBRevision>, surrealdb::Error> {
+        let q = r#"
+            SELECT *, user.* FROM revision WHERE target = $target_id
+                ORDER BY created_at DESC
+                LIMIT $get_limit;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("target_id", target_id))
+            .bind(("get_limit", limit))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::engine::local::Mem;
+
+    use crate::db::{DB404Err, DBPostRating, DBPostVisibility, Db};
+
+    #[tokio::test]
+    async fn db_revision_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+
+        let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+        let post = db
+            .add_post(
+                1,
+                "hey1",
+                "title",
+                "",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(post.edited_at, 0);
+
+        let result = db
+            .update_post_title(2, user2.id.clone(), post.id.key.clone(), "stolen")
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        let result = db
+            .update_post_title(2, user.id.clone(), post.id.key.clone(), "title")
+            .await
+            .unwrap();
+        assert_eq!(result.edited_at, 0);
+        let revisions = db.get_revisions(post.id.clone(), 10).await.unwrap();
+        assert!(revisions.is_empty());
+
+        db.update_post_title(3, user.id.clone(), post.id.key.clone(), "title2")
+            .await
+            .unwrap();
+        let result = db
+            .update_post_description(4, user.id.clone(), post.id.key.clone(), "cat")
+            .await
+            .unwrap();
+        assert_eq!(result.title, "title2");
+        assert_eq!(result.description, "cat");
+        assert_eq!(result.edited_at, 4);
+
+        let revisions = db.get_revisions(post.id.clone(), 10).await.unwrap();
+        assert_eq!(
+            revisions
+                .iter()
+                .map(|v| (v.field.as_str(), v.before.as_str(), v.after.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("description", "", "cat"), ("title", "title", "title2")]
+        );
+        assert_eq!(revisions[0].user.id, user.id);
+
+        let comment = db
+            .add_post_comment(5, user2.id.clone(), post.id.key.clone(), None, "hello")
+            .await
+            .unwrap();
+        let result = db
+            .update_post_comment(6, user.id.clone(), comment.id.key.clone(), "bye")
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        let result = db
+            .update_post_comment(6, user2.id.clone(), comment.id.key.clone(), "bye")
+            .await
+            .unwrap();
+        assert_eq!(result.text, "bye");
+        assert_eq!(result.edited_at, 6);
+
+        let revisions = db.get_revisions(comment.id.clone(), 10).await.unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].before, "hello");
+        assert_eq!(revisions[0].after, "bye");
+
+        db.delete_post_comment(user2.id.clone(), comment.id.key.clone())
+            .await
+            .unwrap();
+        let revisions = db.get_revisions(comment.id.clone(), 10).await.unwrap();
+        assert!(revisions.is_empty());
+    }
+}
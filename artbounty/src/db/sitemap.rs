@@ -0,0 +1,243 @@
+use crate::api::shared::cursor::CursorRange;
+use crate::db::DB404Err;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealSerializeUtils;
+use crate::db::cursor::q_cursor_range;
+use surrealdb::types::SurrealValue;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+
+/// posts anyone can open from the gallery, unlisted ones stay out of search engines too
+const Q_SITEMAP_POST: &str = r#"show = true AND deleted_at = 0 AND visibility = "public" AND publish_at <= $time AND user.banned = false AND user.erased_at = 0"#;
+const Q_SITEMAP_USER: &str = "banned = false AND erased_at = 0";
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBSitemapPost {
+    pub id: RecordId,
+    pub username: String,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBSitemapUser {
+    pub id: RecordId,
+    pub username: String,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+impl<C: Connection> Db<C> {
+    pub async fn get_sitemap_post_count(&self, time: u128) -> Result<u64, surrealdb::Error> {
+        let q = format!(
+            "RETURN (SELECT count() FROM post WHERE {Q_SITEMAP_POST} GROUP ALL)[0].count ?? 0;"
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_expect(0)
+    }
+
+    /// the post at `position` in created_at order, a sitemap page starts right after the last post of the page before
+    pub async fn get_sitemap_post_at(
+        &self,
+        time: u128,
+        position: usize,
+    ) -> Result<DBSitemapPost, DB404Err> {
+        let q = format!(
+            r#"
+                SELECT id, user.username AS username, modified_at, created_at FROM post
+                    WHERE {Q_SITEMAP_POST}
+                    ORDER BY created_at ASC, id ASC
+                    LIMIT 1 START $position;
+            "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("position", position))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    /// one batch in created_at order that continues after the cursor
+    pub async fn get_sitemap_posts(
+        &self,
+        time: u128,
+        cursor: CursorRange,
+        limit: usize,
+    ) -> Result<Vec<DBSitemapPost>, surrealdb::Error> {
+        let (q_cursor, cursor_time, cursor_id) = q_cursor_range(&cursor, "post");
+        let q_cursor = if q_cursor.is_empty() {
+            String::new()
+        } else {
+            format!("AND {q_cursor}")
+        };
+        let q = format!(
+            r#"
+                SELECT id, user.username AS username, modified_at, created_at FROM post
+                    WHERE {Q_SITEMAP_POST} {q_cursor}
+                    ORDER BY created_at ASC, id ASC
+                    LIMIT $limit;
+            "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("cursor_time", cursor_time))
+            .bind(("cursor_id", cursor_id))
+            .bind(("limit", limit))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    pub async fn get_sitemap_user_count(&self) -> Result<u64, surrealdb::Error> {
+        let q = format!(
+            "RETURN (SELECT count() FROM user WHERE {Q_SITEMAP_USER} GROUP ALL)[0].count ?? 0;"
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_expect(0)
+    }
+
+    /// the account at `position` in created_at order, same as get_sitemap_post_at
+    pub async fn get_sitemap_user_at(&self, position: usize) -> Result<DBSitemapUser, DB404Err> {
+        let q = format!(
+            r#"
+                SELECT id, username, modified_at, created_at FROM user
+                    WHERE {Q_SITEMAP_USER}
+                    ORDER BY created_at ASC, id ASC
+                    LIMIT 1 START $position;
+            "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("position", position))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    /// one batch in created_at order that continues after the cursor
+    pub async fn get_sitemap_users(
+        &self,
+        cursor: CursorRange,
+        limit: usize,
+    ) -> Result<Vec<DBSitemapUser>, surrealdb::Error> {
+        let (q_cursor, cursor_time, cursor_id) = q_cursor_range(&cursor, "user");
+        let q_cursor = if q_cursor.is_empty() {
+            String::new()
+        } else {
+            format!("AND {q_cursor}")
+        };
+        let q = format!(
+            r#"
+                SELECT id, username, modified_at, created_at FROM user
+                    WHERE {Q_SITEMAP_USER} {q_cursor}
+                    ORDER BY created_at ASC, id ASC
+                    LIMIT $limit;
+            "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("cursor_time", cursor_time))
+            .bind(("cursor_id", cursor_id))
+            .bind(("limit", limit))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::{engine::local::Mem, types::ToSql};
+
+    use crate::api::shared::cursor::{Cursor, CursorRange};
+    use crate::db::{DB404Err, DBPostRating, DBPostVisibility, Db};
+
+    #[tokio::test]
+    async fn db_sitemap_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+        assert_eq!(db.get_sitemap_user_count().await.unwrap(), 0);
+
+        db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        db.add_user(1, "hey2", "hey2@hey.com", "123").await.unwrap();
+        for (time, title, visibility, publish_at) in [
+            (1, "one", DBPostVisibility::Public, 0),
+            (2, "two", DBPostVisibility::Public, 0),
+            (3, "three", DBPostVisibility::Unlisted, 0),
+            (4, "four", DBPostVisibility::Draft, 0),
+            (5, "five", DBPostVisibility::Scheduled, 100),
+            (6, "six", DBPostVisibility::Public, 0),
+        ] {
+            db.add_post(
+                time,
+                "hey1",
+                title,
+                "",
+                "",
+                0,
+                DBPostRating::General,
+                visibility,
+                publish_at,
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(db.get_sitemap_post_count(10).await.unwrap(), 3);
+        let first = db
+            .get_sitemap_posts(10, CursorRange::None, 2)
+            .await
+            .unwrap();
+        let last = first.last().unwrap();
+        let cursor = Cursor::new(last.created_at, last.id.key.to_sql());
+        let second = db
+            .get_sitemap_posts(10, CursorRange::After(cursor), 2)
+            .await
+            .unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(second.len(), 1);
+        assert_eq!(first[0].username, "hey1");
+        assert_eq!(
+            [
+                first[0].modified_at,
+                first[1].modified_at,
+                second[0].modified_at
+            ],
+            [1, 2, 6]
+        );
+        assert_eq!(db.get_sitemap_post_at(10, 1).await.unwrap(), first[1]);
+        let result = db.get_sitemap_post_at(10, 3).await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        assert_eq!(db.get_sitemap_user_count().await.unwrap(), 2);
+        let user = db.get_sitemap_user_at(0).await.unwrap();
+        assert_eq!(user.username, "hey1");
+        let cursor = Cursor::new(user.created_at, user.id.key.to_sql());
+        let users = db
+            .get_sitemap_users(CursorRange::After(cursor), 10)
+            .await
+            .unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].username, "hey2");
+    }
+}
@@ -0,0 +1,372 @@
+use crate::api::shared::trash::TRASH_RETENTION_NS;
+use crate::db::DB404Err;
+use crate::db::DBUserPost;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealSerializeUtils;
+use crate::db::post::create_post_id;
+use crate::db::post_comment::{DBPostComment, create_post_comment_id};
+use surrealdb::types::RecordIdKey;
+use surrealdb::types::SurrealValue;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+
+/// moves a post or comment owned by $user_id into the trash, $target_id, $user_id and $time have to be bound,
+/// the trashed record is at index 3
+const Q_TRASH: &str = r#"
+     BEGIN TRANSACTION;
+     LET $target = SELECT id, user, deleted_at FROM ONLY $target_id;
+     IF !$target OR $target.user != $user_id OR $target.deleted_at > 0 {
+        THROW "not found";
+     };
+     UPDATE ONLY $target_id SET deleted_at = $time, modified_at = $time RETURN *, user.*;
+     COMMIT TRANSACTION;
+"#;
+
+/// only works while the record is still inside the retention window, binds are the same as Q_TRASH plus $retention
+const Q_RESTORE: &str = r#"
+     BEGIN TRANSACTION;
+     LET $target = SELECT id, user, deleted_at FROM ONLY $target_id;
+     IF !$target OR $target.user != $user_id OR $target.deleted_at = 0 OR $target.deleted_at + $retention <= $time {
+        THROW "not found";
+     };
+     UPDATE ONLY $target_id SET deleted_at = 0, modified_at = $time RETURN *, user.*;
+     COMMIT TRANSACTION;
+"#;
+
+impl<C: Connection> Db<C> {
+    async fn trash_query<T: SurrealValue + serde::de::DeserializeOwned + std::fmt::Debug>(
+        &self,
+        q: &str,
+        time: u128,
+        user_id: RecordId,
+        target_id: RecordId,
+    ) -> Result<T, DB404Err> {
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("target_id", target_id))
+            .bind(("retention", TRASH_RETENTION_NS))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_or(3, DB404Err::NotFound)
+    }
+
+    pub async fn trash_post(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        post_key: impl Into<RecordIdKey>,
+    ) -> Result<DBUserPost, DB404Err> {
+        self.trash_query(Q_TRASH, time, user_id, create_post_id(post_key))
+            .await
+    }
+
+    pub async fn restore_post(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        post_key: impl Into<RecordIdKey>,
+    ) -> Result<DBUserPost, DB404Err> {
+        self.trash_query(Q_RESTORE, time, user_id, create_post_id(post_key))
+            .await
+    }
+
+    pub async fn trash_post_comment(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        comment_key: impl Into<RecordIdKey>,
+    ) -> Result<DBPostComment, DB404Err> {
+        self.trash_query(
+            Q_TRASH,
+            time,
+            user_id,
+            create_post_comment_id(comment_key.into()),
+        )
+        .await
+    }
+
+    pub async fn restore_post_comment(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        comment_key: impl Into<RecordIdKey>,
+    ) -> Result<DBPostComment, DB404Err> {
+        self.trash_query(
+            Q_RESTORE,
+            time,
+            user_id,
+            create_post_comment_id(comment_key.into()),
+        )
+        .await
+    }
+
+    /// newest first, anything past the retention window is left for the purge
+    pub async fn get_trash_posts(
+        &self,
+        time: u128,
+        user_id: RecordId,
+    ) -> Result<Vec<DBUserPost>, surrealdb::Error> {
+        let q = r#"
+            SELECT *, user.* FROM post WHERE
+                user = $user_id AND deleted_at > 0 AND deleted_at + $retention > $time
+                ORDER BY deleted_at DESC;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("retention", TRASH_RETENTION_NS))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    /// newest first, anything past the retention window is left for the purge
+    pub async fn get_trash_post_comments(
+        &self,
+        time: u128,
+        user_id: RecordId,
+    ) -> Result<Vec<DBPostComment>, surrealdb::Error> {
+        let q = r#"
+            SELECT *, user.* FROM post_comment WHERE
+                user = $user_id AND deleted_at > 0 AND deleted_at + $retention > $time
+                ORDER BY deleted_at DESC;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("retention", TRASH_RETENTION_NS))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    /// hard deletes everything that outlived the retention window, returns the purged posts so their files can be removed.
+    /// comments that still have replies lose their text and revisions but the row stays as a placeholder
+    pub async fn purge_trash(&self, time: u128) -> Result<Vec<DBUserPost>, surrealdb::Error> {
+        let q = r#"
+            SELECT *, user.* FROM post WHERE deleted_at > 0 AND deleted_at + $retention <= $time;
+            SELECT *, user.* FROM post_comment WHERE deleted_at > 0 AND deleted_at + $retention <= $time AND replies_count = 0;
+            LET $placeholders = UPDATE post_comment SET text = "" WHERE deleted_at > 0 AND deleted_at + $retention <= $time AND replies_count > 0 AND text != "";
+            DELETE revision WHERE target IN $placeholders.id;
+        "#;
+        trace!("about to run {q}");
+        let (posts, comments) = self
+            .db
+            .query(q)
+            .bind(("time", time))
+            .bind(("retention", TRASH_RETENTION_NS))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then(|mut result| {
+                let posts = result.take::<Vec<DBUserPost>>(0)?;
+                let comments = result.take::<Vec<DBPostComment>>(1)?;
+                Ok((posts, comments))
+            })?;
+
+        for post in &posts {
+            self.delete_post(post.user.id.clone(), post.id.key.clone())
+                .await?;
+        }
+        for comment in comments {
+            self.delete_post_comment(comment.user.id, comment.id.key)
+                .await?;
+        }
+
+        Ok(posts)
+    }
+
+    /// files are stored by hash, so one upload can back several posts and messages
+    pub async fn is_file_hash_used(
+        &self,
+        hash: impl Into<String>,
+    ) -> Result<bool, surrealdb::Error> {
+        let q = r#"
+            RETURN array::len(SELECT id FROM post WHERE file.hash CONTAINS $hash LIMIT 1) > 0
+                OR array::len(SELECT id FROM message WHERE file.hash CONTAINS $hash LIMIT 1) > 0;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("hash", hash.into()))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_expect(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::{engine::local::Mem, types::ToSql};
+
+    use crate::api::Order;
+    use crate::api::shared::cursor::CursorRange;
+    use crate::api::shared::trash::TRASH_RETENTION_NS;
+    use crate::db::{DB404Err, DBPostRating, DBPostVisibility, Db};
+
+    #[tokio::test]
+    async fn db_trash_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+
+        let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+        let post = db
+            .add_post(
+                1,
+                "hey1",
+                "title",
+                "",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+        db.add_post_file(
+            1,
+            user.id.clone(),
+            post.id.key.clone(),
+            10,
+            "1",
+            "png",
+            10,
+            10,
+        )
+        .await
+        .unwrap();
+
+        let result = db
+            .trash_post(2, user2.id.clone(), post.id.key.clone())
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        let trashed = db
+            .trash_post(2, user.id.clone(), post.id.key.clone())
+            .await
+            .unwrap();
+        assert_eq!(trashed.deleted_at, 2);
+        let result = db.get_post(post.id.key.clone()).await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        let trash = db.get_trash_posts(3, user.id.clone()).await.unwrap();
+        assert_eq!(trash.len(), 1);
+        assert!(
+            db.get_trash_posts(3, user2.id.clone())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let restored = db
+            .restore_post(3, user.id.clone(), post.id.key.clone())
+            .await
+            .unwrap();
+        assert_eq!(restored.deleted_at, 0);
+        db.get_post(post.id.key.clone()).await.unwrap();
+
+        // comments with replies stay as placeholders
+        let comment = db
+            .add_post_comment(4, user2.id.clone(), post.id.key.clone(), None, "hello")
+            .await
+            .unwrap();
+        let reply = db
+            .add_post_comment(
+                5,
+                user.id.clone(),
+                post.id.key.clone(),
+                Some(comment.id.key.to_sql()),
+                "hi",
+            )
+            .await
+            .unwrap();
+        db.trash_post_comment(6, user2.id.clone(), comment.id.key.clone())
+            .await
+            .unwrap();
+        let comments = db
+            .get_post_comments(
+                7,
+                post.id.key.clone(),
+                None::<String>,
+                true,
+                10,
+                CursorRange::None,
+                Order::OneTwoThree,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].deleted_at, 6);
+        db.trash_post_comment(7, user.id.clone(), reply.id.key.clone())
+            .await
+            .unwrap();
+        let comments = db
+            .get_post_comments(
+                7,
+                post.id.key.clone(),
+                None::<String>,
+                true,
+                10,
+                CursorRange::None,
+                Order::OneTwoThree,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(comments.len(), 1);
+
+        let result = db
+            .add_post_comment(
+                8,
+                user.id.clone(),
+                post.id.key.clone(),
+                Some(comment.id.key.to_sql()),
+                "hi",
+            )
+            .await;
+        assert!(result.is_err());
+
+        // the reply is purged first, the placeholder only loses its text until it has no replies left
+        db.trash_post(8, user.id.clone(), post.id.key.clone())
+            .await
+            .unwrap();
+        let purged = db.purge_trash(7 + TRASH_RETENTION_NS).await.unwrap();
+        assert!(purged.is_empty());
+        let comment = db.get_post_comment(comment.id.key.clone()).await.unwrap();
+        assert_eq!(comment.text, "");
+        assert_eq!(comment.replies_count, 0);
+        let result = db
+            .restore_post_comment(
+                7 + TRASH_RETENTION_NS,
+                user2.id.clone(),
+                comment.id.key.clone(),
+            )
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        assert!(db.is_file_hash_used("1").await.unwrap());
+
+        let purged = db.purge_trash(8 + TRASH_RETENTION_NS).await.unwrap();
+        assert_eq!(purged.len(), 1);
+        assert_eq!(purged[0].file[0].hash, "1");
+        assert!(db.get_post_all().await.unwrap().is_empty());
+        assert!(db.get_post_comments_all().await.unwrap().is_empty());
+        assert!(!db.is_file_hash_used("1").await.unwrap());
+        let user = db.get_user_by_username("hey1").await.unwrap();
+        assert_eq!(user.used_storage_bytes, 0);
+    }
+}
@@ -0,0 +1,449 @@
+use std::fmt::Display;
+
+use crate::api::Order;
+use crate::api::TimeRange;
+use crate::db::DB404Err;
+use crate::db::DBUser;
+use crate::db::DBUserBlockErr;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealSerializeUtils;
+use crate::db::cursor::{order_query, time_range_query};
+use surrealdb::types::SurrealValue;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+use surrealdb::types::RecordIdKey;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBUserBlock {
+    pub id: RecordId,
+    pub user: RecordId,
+    pub target: RecordId,
+    pub kind: String,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBUserBlockUser {
+    pub user: DBUser,
+    pub kind: String,
+    pub created_at: u128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DBUserBlockKind {
+    Block,
+    Mute,
+}
+
+impl Display for DBUserBlockKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            DBUserBlockKind::Block => "block",
+            DBUserBlockKind::Mute => "mute",
+        };
+
+        write!(f, "{}", text)
+    }
+}
+
+pub fn create_user_block_id(id: impl Into<RecordIdKey>) -> RecordId {
+    RecordId::new("user_block", id.into())
+}
+
+/// condition that drops content authored by users the viewer blocked,
+/// muted users are dropped too when include_muted is set, $viewer_id has to be bound
+pub fn q_user_block_filter(field: &str, include_muted: bool) -> String {
+    let q_kind = if include_muted {
+        ""
+    } else {
+        " AND kind = \"block\""
+    };
+    format!("{field} NOT IN (SELECT VALUE target FROM user_block WHERE user = $viewer_id{q_kind})")
+}
+
+impl<C: Connection> Db<C> {
+    /// replaces the previous block or mute of the same user, blocking also drops follows both ways
+    pub async fn add_user_block(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        username: impl Into<String>,
+        kind: DBUserBlockKind,
+    ) -> Result<DBUserBlock, DBUserBlockErr> {
+        let username = username.into();
+        let q = r#"
+                 BEGIN TRANSACTION;
+                 LET $target = SELECT id FROM ONLY user WHERE username = $username LIMIT 1;
+                 IF !$target.id {
+                    THROW "user not found";
+                 };
+                 DELETE user_block WHERE user = $user_id AND target = $target.id;
+                 IF $kind = "block" {
+                    DELETE user_follow WHERE
+                        (follower = $user_id AND following = $target.id) OR
+                        (follower = $target.id AND following = $user_id);
+                 };
+                 CREATE user_block SET
+                    user = $user_id,
+                    target = $target.id,
+                    kind = $kind,
+                    modified_at = $time,
+                    created_at = $time
+                 RETURN *;
+                 COMMIT TRANSACTION;
+                "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("username", username.clone()))
+            .bind(("kind", kind.to_string()))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: user not found" => DBUserBlockErr::UserNotFound(username),
+                _ => DBUserBlockErr::DB(err),
+            })
+            .and_then_take_expect(5)
+    }
+
+    pub async fn delete_user_block(
+        &self,
+        user_id: RecordId,
+        username: impl Into<String>,
+    ) -> Result<(), surrealdb::Error> {
+        self.db
+            .query(
+                r#"
+                    DELETE user_block WHERE
+                        user = $user_id AND
+                        target = (SELECT id FROM ONLY user WHERE username = $username LIMIT 1).id;
+                "#,
+            )
+            .bind(("user_id", user_id))
+            .bind(("username", username.into()))
+            .await
+            .check_good(surrealdb::Error::from)
+            .map(|_| ())
+    }
+
+    pub async fn get_user_block(
+        &self,
+        user_id: RecordId,
+        username: impl Into<String>,
+    ) -> Result<DBUserBlock, DB404Err> {
+        self.db
+            .query(
+                r#"
+                    LET $target = SELECT id FROM ONLY user WHERE username = $username LIMIT 1;
+                    SELECT * FROM ONLY user_block WHERE
+                        user = $user_id AND
+                        target = $target.id
+                        LIMIT 1;
+                "#,
+            )
+            .bind(("user_id", user_id))
+            .bind(("username", username.into()))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(1, DB404Err::NotFound)
+    }
+
+    /// every user the viewer blocked or muted
+    pub async fn get_user_block_targets(
+        &self,
+        user_id: RecordId,
+    ) -> Result<Vec<RecordId>, surrealdb::Error> {
+        self.db
+            .query("SELECT VALUE target FROM user_block WHERE user = $user_id;")
+            .bind(("user_id", user_id))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    pub async fn get_user_blocks(
+        &self,
+        user_id: RecordId,
+        limit: usize,
+        time_range: TimeRange,
+        order: Order,
+    ) -> Result<Vec<DBUserBlockUser>, DB404Err> {
+        let (q_time, time_range_val) = time_range_query(&time_range, "created_at");
+        let q_order = order_query(&order);
+
+        let q = format!(
+            "
+            SELECT target.* AS user, kind, created_at FROM user_block WHERE
+                user = $user_id {q_time}
+                ORDER BY created_at {q_order}
+                LIMIT $get_limit;
+        "
+        );
+        trace!("about to run {q}");
+
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .bind(("get_limit", limit))
+            .bind(("time_range", time_range_val))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_all(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::engine::local::Mem;
+    use surrealdb::types::ToSql;
+
+    use crate::{
+        api::{Order, TimeRange, shared::cursor::CursorRange},
+        db::{
+            DB404Err, DBPostCommentErr, DBPostLikeErr, DBPostRating, DBPostVisibility,
+            DBUserBlockErr, Db, user_block::DBUserBlockKind,
+        },
+    };
+
+    #[tokio::test]
+    async fn db_user_block_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+
+        let user1 = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+        let user3 = db.add_user(0, "hey3", "hey3@hey.com", "123").await.unwrap();
+        let post1 = db
+            .add_post(
+                1,
+                "hey1",
+                "title1",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+        let post2 = db
+            .add_post(
+                2,
+                "hey2",
+                "title2",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+        let post3 = db
+            .add_post(
+                3,
+                "hey3",
+                "title3",
+                "description",
+                "",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+        let comment2 = db
+            .add_post_comment(4, user2.id.clone(), post1.id.key.clone(), None, "hi")
+            .await
+            .unwrap();
+        let comment3 = db
+            .add_post_comment(5, user3.id.clone(), post1.id.key.clone(), None, "hello")
+            .await
+            .unwrap();
+        db.add_user_follow(5, user2.id.clone(), "hey1")
+            .await
+            .unwrap();
+
+        let result = db
+            .add_user_block(6, user1.id.clone(), "404", DBUserBlockKind::Block)
+            .await;
+        assert!(matches!(result, Err(DBUserBlockErr::UserNotFound(_))));
+
+        db.add_user_block(6, user1.id.clone(), "hey2", DBUserBlockKind::Mute)
+            .await
+            .unwrap();
+        let result = db
+            .add_user_block(7, user1.id.clone(), "hey2", DBUserBlockKind::Block)
+            .await
+            .unwrap();
+        assert_eq!(result.kind, "block");
+        db.add_user_block(8, user1.id.clone(), "hey3", DBUserBlockKind::Mute)
+            .await
+            .unwrap();
+
+        let result = db.get_user_follow_all().await.unwrap();
+        assert!(result.is_empty());
+
+        let result = db
+            .get_user_blocks(user1.id.clone(), 10, TimeRange::None, Order::ThreeTwoOne)
+            .await
+            .unwrap();
+        assert_eq!(
+            result
+                .iter()
+                .map(|v| (v.user.username.as_str(), v.kind.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("hey3", "mute"), ("hey2", "block")]
+        );
+
+        let result = db.get_user_block(user1.id.clone(), "hey3").await.unwrap();
+        assert_eq!(result.kind, "mute");
+        let result = db.get_user_block(user2.id.clone(), "hey1").await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+
+        let result = db
+            .post_search(
+                10,
+                CursorRange::None,
+                Order::ThreeTwoOne,
+                "",
+                "",
+                None,
+                DBPostRating::Explicit,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 3);
+        let result = db
+            .post_search(
+                10,
+                CursorRange::None,
+                Order::ThreeTwoOne,
+                "",
+                "",
+                Some(user1.id.clone()),
+                DBPostRating::Explicit,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result.iter().map(|v| v.id.clone()).collect::<Vec<_>>(),
+            vec![post1.id.clone()]
+        );
+        let result = db
+            .post_search(
+                10,
+                CursorRange::None,
+                Order::ThreeTwoOne,
+                "",
+                "hey3",
+                Some(user1.id.clone()),
+                DBPostRating::Explicit,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result.iter().map(|v| v.id.clone()).collect::<Vec<_>>(),
+            vec![post3.id.clone()]
+        );
+        let result = db
+            .post_search(
+                10,
+                CursorRange::None,
+                Order::ThreeTwoOne,
+                "",
+                "hey2",
+                Some(user1.id.clone()),
+                DBPostRating::Explicit,
+            )
+            .await
+            .unwrap();
+        assert!(result.is_empty());
+
+        let result = db
+            .get_post_comments(
+                9,
+                post1.id.key.clone(),
+                None::<String>,
+                false,
+                10,
+                CursorRange::None,
+                Order::OneTwoThree,
+                Some(user1.id.clone()),
+            )
+            .await
+            .unwrap();
+        assert!(result.is_empty());
+        let result = db
+            .get_post_comments(
+                9,
+                post1.id.key.clone(),
+                None::<String>,
+                false,
+                10,
+                CursorRange::None,
+                Order::OneTwoThree,
+                Some(user3.id.clone()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 2);
+
+        let result = db
+            .add_post_comment(10, user2.id.clone(), post1.id.key.clone(), None, "hi")
+            .await;
+        assert!(matches!(result, Err(DBPostCommentErr::Blocked)));
+        let result = db
+            .add_post_like(10, user2.id.clone(), post1.id.key.clone())
+            .await;
+        assert!(matches!(result, Err(DBPostLikeErr::Blocked)));
+        let result = db
+            .add_post_like(10, user3.id.clone(), post1.id.key.clone())
+            .await;
+        assert!(result.is_ok());
+
+        db.delete_user_block(user1.id.clone(), "hey2")
+            .await
+            .unwrap();
+        db.add_user_block(11, user3.id.clone(), "hey2", DBUserBlockKind::Block)
+            .await
+            .unwrap();
+        let result = db
+            .add_post_comment(
+                12,
+                user2.id.clone(),
+                post1.id.key.clone(),
+                Some(comment3.id.key.to_sql()),
+                "reply",
+            )
+            .await;
+        assert!(matches!(result, Err(DBPostCommentErr::Blocked)));
+        let result = db
+            .add_post_comment(
+                12,
+                user2.id.clone(),
+                post1.id.key.clone(),
+                Some(comment2.id.key.to_sql()),
+                "reply",
+            )
+            .await;
+        assert!(result.is_ok());
+        let result = db
+            .add_post_like(12, user2.id.clone(), post1.id.key.clone())
+            .await;
+        assert!(result.is_ok());
+        let result = db
+            .add_post_comment(13, user3.id.clone(), post2.id.key.clone(), None, "hi")
+            .await;
+        assert!(result.is_ok());
+    }
+}
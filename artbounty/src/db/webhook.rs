@@ -0,0 +1,552 @@
+use crate::api::TimeRange;
+use crate::db::DB404Err;
+use crate::db::DBWebhookErr;
+use crate::db::SurrealCheckUtils;
+use crate::db::SurrealSerializeUtils;
+use crate::db::audit::{DBAudit, DBAuditKind, q_audit_event_add};
+use crate::db::cursor::time_range_query;
+use std::fmt::Display;
+use surrealdb::types::RecordIdKey;
+use surrealdb::types::SurrealValue;
+use tracing::trace;
+
+use super::Db;
+pub use surrealdb::Connection;
+use surrealdb::types::RecordId;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBWebhook {
+    pub id: RecordId,
+    pub user: RecordId,
+    pub admin: bool,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+/// one queued request to one webhook, the queue and the delivery log are the same table
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBWebhookDelivery {
+    pub id: RecordId,
+    pub webhook: RecordId,
+    pub event: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: u32,
+    pub next_attempt_at: u128,
+    pub response_code: u16,
+    pub response: String,
+    pub modified_at: u128,
+    pub created_at: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, SurrealValue)]
+pub struct DBWebhookDeliveryDue {
+    pub id: RecordId,
+    pub webhook: DBWebhook,
+    pub event: String,
+    pub payload: String,
+    pub attempts: u32,
+    pub next_attempt_at: u128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DBWebhookDeliveryStatus {
+    Pending,
+    Success,
+    Failed,
+}
+
+impl Display for DBWebhookDeliveryStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            DBWebhookDeliveryStatus::Pending => "pending",
+            DBWebhookDeliveryStatus::Success => "success",
+            DBWebhookDeliveryStatus::Failed => "failed",
+        };
+
+        write!(f, "{}", text)
+    }
+}
+
+pub fn create_webhook_id(id: impl Into<RecordIdKey>) -> RecordId {
+    RecordId::new("webhook", id.into())
+}
+
+impl<C: Connection> Db<C> {
+    pub async fn add_webhook(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        admin: bool,
+        url: impl Into<String>,
+        secret: impl Into<String>,
+        events: Vec<String>,
+        max: usize,
+        audit: DBAudit,
+    ) -> Result<DBWebhook, DBWebhookErr> {
+        let q_audit =
+            q_audit_event_add(DBAuditKind::WebhookAdd, "$user_id", "\"\"", "$created.url");
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $count = count(SELECT id FROM webhook WHERE user = $user_id);
+                 IF $count >= $max {{
+                    THROW "too many";
+                 }};
+                 LET $created = CREATE ONLY webhook SET
+                    user = $user_id,
+                    admin = $admin,
+                    url = $url,
+                    secret = $secret,
+                    events = $events,
+                    modified_at = $time,
+                    created_at = $time;
+                 {q_audit}
+                 RETURN $created;
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("admin", admin))
+            .bind(("url", url.into()))
+            .bind(("secret", secret.into()))
+            .bind(("events", events))
+            .bind(("max", max))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: too many" => DBWebhookErr::TooMany,
+                _ => DBWebhookErr::DB(err),
+            })
+            .and_then_take_expect(5)
+    }
+
+    pub async fn get_webhooks(
+        &self,
+        user_id: RecordId,
+    ) -> Result<Vec<DBWebhook>, surrealdb::Error> {
+        let q = r#"
+            SELECT * FROM webhook WHERE user = $user_id ORDER BY created_at DESC;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    /// pending deliveries go with it
+    pub async fn delete_webhook(
+        &self,
+        time: u128,
+        user_id: RecordId,
+        webhook_key: impl Into<RecordIdKey>,
+        audit: DBAudit,
+    ) -> Result<(), DB404Err> {
+        let q_audit = q_audit_event_add(
+            DBAuditKind::WebhookDelete,
+            "$user_id",
+            "$deleted[0].url",
+            "\"\"",
+        );
+        let q = format!(
+            r#"
+                 BEGIN TRANSACTION;
+                 LET $deleted = DELETE webhook WHERE id = $webhook_id AND user = $user_id RETURN BEFORE;
+                 IF !$deleted {{
+                    THROW "not found";
+                 }};
+                 DELETE webhook_delivery WHERE webhook = $webhook_id;
+                 {q_audit}
+                 COMMIT TRANSACTION;
+                "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("user_id", user_id))
+            .bind(("webhook_id", create_webhook_id(webhook_key)))
+            .bind(("audit", audit))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .map(|_| ())
+    }
+
+    /// queues one delivery per matching webhook and returns how many were queued,
+    /// admin webhooks match every event while their owner is still staff, the rest only match events on their owners posts
+    pub async fn add_webhook_deliveries(
+        &self,
+        time: u128,
+        event: impl Into<String>,
+        post_id: Option<RecordId>,
+        payload: impl Into<String>,
+    ) -> Result<usize, surrealdb::Error> {
+        let q = r#"
+             BEGIN TRANSACTION;
+             LET $owner = $post_id.user;
+             LET $webhooks = SELECT VALUE id FROM webhook WHERE events CONTAINS $event
+                AND ((admin = true AND user.role IN ["moderator", "admin"]) OR user = $owner);
+             FOR $webhook IN $webhooks {
+                CREATE webhook_delivery SET
+                    webhook = $webhook,
+                    event = $event,
+                    payload = $payload,
+                    status = "pending",
+                    attempts = 0,
+                    next_attempt_at = $time,
+                    response_code = 0,
+                    response = "",
+                    modified_at = $time,
+                    created_at = $time;
+             };
+             RETURN count($webhooks);
+             COMMIT TRANSACTION;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("event", event.into()))
+            .bind(("post_id", post_id))
+            .bind(("payload", payload.into()))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_expect(4)
+    }
+
+    /// oldest due first, fetches the webhook so the worker has the url and secret
+    pub async fn get_webhook_deliveries_due(
+        &self,
+        time: u128,
+        limit: usize,
+    ) -> Result<Vec<DBWebhookDeliveryDue>, surrealdb::Error> {
+        let q = r#"
+            SELECT id, webhook, event, payload, attempts, next_attempt_at FROM webhook_delivery
+                WHERE status = "pending" AND next_attempt_at <= $time
+                ORDER BY next_attempt_at ASC
+                LIMIT $get_limit
+                FETCH webhook;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("get_limit", limit))
+            .await
+            .check_good(surrealdb::Error::from)
+            .and_then_take_all(0)
+    }
+
+    /// records the outcome of one attempt, `next_attempt_at` is 0 once the delivery is finished
+    pub async fn update_webhook_delivery(
+        &self,
+        time: u128,
+        delivery_id: RecordId,
+        status: DBWebhookDeliveryStatus,
+        attempts: u32,
+        next_attempt_at: u128,
+        response_code: u16,
+        response: impl Into<String>,
+    ) -> Result<DBWebhookDelivery, DB404Err> {
+        let q = r#"
+            UPDATE ONLY $delivery_id SET
+                status = $status,
+                attempts = $attempts,
+                next_attempt_at = $next_attempt_at,
+                response_code = $response_code,
+                response = $response,
+                modified_at = $time;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("delivery_id", delivery_id))
+            .bind(("status", status.to_string()))
+            .bind(("attempts", attempts))
+            .bind(("next_attempt_at", next_attempt_at))
+            .bind(("response_code", response_code))
+            .bind(("response", response.into()))
+            .await
+            .check_good(DB404Err::from)
+            .and_then_take_or(0, DB404Err::NotFound)
+    }
+
+    /// newest first, only the owner of the webhook can read its log
+    pub async fn get_webhook_deliveries(
+        &self,
+        user_id: RecordId,
+        webhook_key: impl Into<RecordIdKey>,
+        limit: usize,
+        time_range: TimeRange,
+    ) -> Result<Vec<DBWebhookDelivery>, DB404Err> {
+        let (q_time, time_range_val) = time_range_query(&time_range, "created_at");
+
+        let q = format!(
+            r#"
+             BEGIN TRANSACTION;
+             LET $webhook = SELECT id FROM ONLY webhook WHERE id = $webhook_id AND user = $user_id LIMIT 1;
+             IF !$webhook {{
+                THROW "not found";
+             }};
+             SELECT * FROM webhook_delivery WHERE webhook = $webhook_id {q_time}
+                ORDER BY created_at DESC
+                LIMIT $get_limit;
+             COMMIT TRANSACTION;
+            "#
+        );
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("user_id", user_id))
+            .bind(("webhook_id", create_webhook_id(webhook_key)))
+            .bind(("get_limit", limit))
+            .bind(("time_range", time_range_val))
+            .await
+            .check_better(|err| match err.message() {
+                "An error occurred: not found" => DB404Err::NotFound,
+                _ => DB404Err::DB(err),
+            })
+            .and_then_take_all(3)
+    }
+
+    /// drops finished deliveries from the log once they're older than the retention window
+    pub async fn delete_webhook_deliveries_expired(
+        &self,
+        time: u128,
+        retention: u128,
+    ) -> Result<(), surrealdb::Error> {
+        let q = r#"
+            DELETE webhook_delivery WHERE status != "pending" AND created_at + $retention < $time;
+        "#;
+        trace!("about to run {q}");
+        self.db
+            .query(q)
+            .bind(("time", time))
+            .bind(("retention", retention))
+            .await
+            .check_good(surrealdb::Error::from)
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::engine::local::Mem;
+
+    use crate::api::TimeRange;
+    use crate::db::audit::DBAudit;
+    use crate::db::webhook::DBWebhookDeliveryStatus;
+    use crate::db::{DB404Err, DBPostRating, DBPostVisibility, DBWebhookErr, Db};
+
+    #[tokio::test]
+    async fn db_webhook_test() {
+        crate::init_test_log();
+        let db = Db::new::<Mem>(()).await.unwrap();
+        db.migrate(0).await.unwrap();
+
+        let user = db.add_user(0, "hey1", "hey1@hey.com", "123").await.unwrap();
+        let user2 = db.add_user(0, "hey2", "hey2@hey.com", "123").await.unwrap();
+        db.update_user_role(0, user2.id.clone(), "admin", DBAudit::default())
+            .await
+            .unwrap();
+        let post = db
+            .add_post(
+                0,
+                "hey1",
+                "title",
+                "description",
+                "tags",
+                0,
+                DBPostRating::General,
+                DBPostVisibility::Public,
+                0,
+            )
+            .await
+            .unwrap();
+
+        let webhook = db
+            .add_webhook(
+                1,
+                user.id.clone(),
+                false,
+                "http://localhost/hook",
+                "secret1",
+                vec!["post.created".to_string(), "like.created".to_string()],
+                2,
+                DBAudit::default(),
+            )
+            .await
+            .unwrap();
+        let admin_webhook = db
+            .add_webhook(
+                2,
+                user2.id.clone(),
+                true,
+                "http://localhost/admin",
+                "secret2",
+                vec!["post.created".to_string(), "user.registered".to_string()],
+                2,
+                DBAudit::default(),
+            )
+            .await
+            .unwrap();
+        db.add_webhook(
+            3,
+            user.id.clone(),
+            false,
+            "http://localhost/hook2",
+            "secret3",
+            vec!["post.deleted".to_string()],
+            2,
+            DBAudit::default(),
+        )
+        .await
+        .unwrap();
+        let result = db
+            .add_webhook(
+                4,
+                user.id.clone(),
+                false,
+                "http://localhost/hook3",
+                "secret4",
+                vec!["post.deleted".to_string()],
+                2,
+                DBAudit::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(DBWebhookErr::TooMany)));
+
+        let webhooks = db.get_webhooks(user.id.clone()).await.unwrap();
+        assert_eq!(
+            webhooks.iter().map(|v| v.url.as_str()).collect::<Vec<_>>(),
+            vec!["http://localhost/hook2", "http://localhost/hook"]
+        );
+
+        // the owner and the admin webhook both match, the other webhook doesn't listen for it
+        let queued = db
+            .add_webhook_deliveries(5, "post.created", Some(post.id.clone()), "{}")
+            .await
+            .unwrap();
+        assert_eq!(queued, 2);
+
+        // only admin webhooks get events that aren't about a post
+        let queued = db
+            .add_webhook_deliveries(6, "user.registered", None, "{}")
+            .await
+            .unwrap();
+        assert_eq!(queued, 1);
+
+        let due = db.get_webhook_deliveries_due(5, 10).await.unwrap();
+        assert_eq!(due.len(), 2);
+        assert!(due.iter().all(|v| v.event == "post.created"));
+        let due = db.get_webhook_deliveries_due(6, 10).await.unwrap();
+        assert_eq!(due.len(), 3);
+
+        let own = due
+            .iter()
+            .find(|v| v.webhook.id == webhook.id)
+            .unwrap()
+            .clone();
+        assert_eq!(own.webhook.secret, "secret1");
+        db.update_webhook_delivery(
+            7,
+            own.id.clone(),
+            DBWebhookDeliveryStatus::Pending,
+            1,
+            100,
+            500,
+            "oops",
+        )
+        .await
+        .unwrap();
+        let due = db.get_webhook_deliveries_due(7, 10).await.unwrap();
+        assert_eq!(due.len(), 2);
+        let due = db.get_webhook_deliveries_due(100, 10).await.unwrap();
+        assert_eq!(due.len(), 3);
+        db.update_webhook_delivery(
+            101,
+            own.id.clone(),
+            DBWebhookDeliveryStatus::Success,
+            2,
+            0,
+            200,
+            "",
+        )
+        .await
+        .unwrap();
+
+        // only the owner reads the log
+        let result = db
+            .get_webhook_deliveries(
+                user2.id.clone(),
+                webhook.id.key.clone(),
+                10,
+                TimeRange::None,
+            )
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        let deliveries = db
+            .get_webhook_deliveries(user.id.clone(), webhook.id.key.clone(), 10, TimeRange::None)
+            .await
+            .unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status, "success");
+        assert_eq!(deliveries[0].attempts, 2);
+        assert_eq!(deliveries[0].response_code, 200);
+
+        // finished deliveries expire, pending ones stay queued
+        db.delete_webhook_deliveries_expired(200, 100)
+            .await
+            .unwrap();
+        let deliveries = db
+            .get_webhook_deliveries(user.id.clone(), webhook.id.key.clone(), 10, TimeRange::None)
+            .await
+            .unwrap();
+        assert!(deliveries.is_empty());
+        let deliveries = db
+            .get_webhook_deliveries(
+                user2.id.clone(),
+                admin_webhook.id.key.clone(),
+                10,
+                TimeRange::None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(deliveries.len(), 2);
+
+        // deleting takes the queue with it
+        let result = db
+            .delete_webhook(
+                8,
+                user.id.clone(),
+                admin_webhook.id.key.clone(),
+                DBAudit::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(DB404Err::NotFound)));
+        db.delete_webhook(
+            8,
+            user2.id.clone(),
+            admin_webhook.id.key.clone(),
+            DBAudit::default(),
+        )
+        .await
+        .unwrap();
+        let due = db.get_webhook_deliveries_due(200, 10).await.unwrap();
+        assert!(due.is_empty());
+    }
+}
@@ -32,16 +32,19 @@ pub mod valid {
     pub const SUPPORTED_FILE_EXTENSIONS: &[&str] = &["ico", "svg", "jpg", "jpeg", "png", "webp"];
     pub const MAX_POST_DESCRIPTION_LENGTH: usize = 2000;
     pub const MAX_POST_COMMENT_LENGTH: usize = 2000;
+    pub const MAX_MESSAGE_LENGTH: usize = 2000;
     pub const MAX_POST_TAGS_LENGTH: usize = 2000;
     pub const MAX_POST_TITLE_LENGTH: usize = 120;
+    pub const MAX_COLLECTION_TITLE_LENGTH: usize = 120;
+    pub const MAX_POST_RELATED_LIMIT: usize = 50;
 
     use tracing::trace;
 
     pub mod auth {
 
         use crate::valid::{
-            MAX_POST_COMMENT_LENGTH, MAX_POST_DESCRIPTION_LENGTH, MAX_POST_TAGS_LENGTH,
-            MAX_POST_TITLE_LENGTH,
+            MAX_COLLECTION_TITLE_LENGTH, MAX_MESSAGE_LENGTH, MAX_POST_COMMENT_LENGTH,
+            MAX_POST_DESCRIPTION_LENGTH, MAX_POST_TAGS_LENGTH, MAX_POST_TITLE_LENGTH,
         };
 
         use super::Validator;
@@ -110,6 +113,27 @@ pub mod valid {
             }
         }
 
+        pub fn proccess_collection_title<S: AsRef<str>>(title: S) -> Result<(), String> {
+            let mut errors = String::new();
+            let input = title.as_ref();
+
+            if input.trim().is_empty() {
+                errors += "title cant be empty\n";
+            }
+
+            if input.is_bigger_than(MAX_COLLECTION_TITLE_LENGTH) {
+                errors += "title must be shorter than 121 characters length\n";
+            }
+
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                let _ = errors.pop();
+                trace!("errors {errors}");
+                Err(errors)
+            }
+        }
+
         pub fn proccess_post_comment<S: AsRef<str>>(comment: S) -> Result<(), String> {
             let mut errors = String::new();
             let input = comment.as_ref();
@@ -131,6 +155,24 @@ pub mod valid {
             }
         }
 
+        /// text can be empty when the message only carries image attachments
+        pub fn proccess_message<S: AsRef<str>>(message: S) -> Result<(), String> {
+            let mut errors = String::new();
+            let input = message.as_ref();
+
+            if input.len() > MAX_MESSAGE_LENGTH {
+                errors += "max message length is 2000 chars\n";
+            }
+
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                let _ = errors.pop();
+                trace!("errors {errors}");
+                Err(errors)
+            }
+        }
+
         pub fn proccess_post_description<S: AsRef<str>>(description: S) -> Result<(), String> {
             let mut errors = String::new();
             let input = description.as_ref();
@@ -315,17 +357,123 @@ pub mod path {
 
     pub const PATH_API: &'static str = "/api";
 
+    // versioned public api, nested under PATH_API
+    pub const PATH_API_V1: &'static str = "/v1";
+    pub const PATH_API_V1_OPENAPI: &'static str = "/openapi.json";
+    pub const PATH_API_V1_POSTS: &'static str = "/posts";
+    pub const PATH_API_V1_POST: &'static str = "/posts/{post_key}";
+    pub const PATH_API_V1_POST_COMMENTS: &'static str = "/posts/{post_key}/comments";
+    pub const PATH_API_V1_POST_LIKES: &'static str = "/posts/{post_key}/likes";
+    pub const PATH_API_V1_USER: &'static str = "/users/{username}";
+    pub const PATH_API_V1_USER_POSTS: &'static str = "/users/{username}/posts";
+
     // post comment
     pub const PATH_API_POST_COMMENT_UPDATE: &'static str = "/update_post_comment";
     pub const PATH_API_POST_COMMENT_ADD: &'static str = "/add_post_comment";
     pub const PATH_API_POST_COMMENT_GET: &'static str = "/get_post_comment";
     pub const PATH_API_POST_COMMENT_DELETE: &'static str = "/delete_post_comment";
 
+    // conversation
+    pub const PATH_API_CONVERSATION_ADD: &'static str = "/add_conversation";
+    pub const PATH_API_CONVERSATION_GET: &'static str = "/get_conversation";
+    pub const PATH_API_CONVERSATIONS_GET: &'static str = "/get_conversations";
+    pub const PATH_API_CONVERSATION_UNREAD_COUNT: &'static str = "/get_conversation_unread_count";
+    pub const PATH_API_CONVERSATION_READ: &'static str = "/read_conversation";
+    pub const PATH_API_CONVERSATION_BLOCK: &'static str = "/block_conversation";
+    pub const PATH_API_CONVERSATION_UNBLOCK: &'static str = "/unblock_conversation";
+    pub const PATH_API_MESSAGE_ADD: &'static str = "/add_message";
+    pub const PATH_API_MESSAGES_GET: &'static str = "/get_messages";
+    pub const PATH_API_MESSAGE_FILE_ADD: &'static str = "/message/{message_id}/add_file";
+
+    // collection
+    pub const PATH_API_COLLECTION_ADD: &'static str = "/add_collection";
+    pub const PATH_API_COLLECTION_GET: &'static str = "/get_collection";
+    pub const PATH_API_COLLECTION_UPDATE: &'static str = "/update_collection";
+    pub const PATH_API_COLLECTION_DELETE: &'static str = "/delete_collection";
+    pub const PATH_API_COLLECTIONS_GET: &'static str = "/get_user_collections";
+    pub const PATH_API_COLLECTION_ITEM_ADD: &'static str = "/add_collection_item";
+    pub const PATH_API_COLLECTION_ITEM_DELETE: &'static str = "/delete_collection_item";
+    pub const PATH_API_COLLECTION_ITEM_ORDER: &'static str = "/order_collection_item";
+    pub const PATH_API_COLLECTION_POSTS_GET: &'static str = "/get_collection_posts";
+    pub const PATH_API_POST_COLLECTIONS_GET: &'static str = "/get_post_collections";
+
+    // admin
+    pub const PATH_API_ADMIN_USERS_GET: &'static str = "/admin_get_users";
+    pub const PATH_API_ADMIN_USER_POSTS_GET: &'static str = "/admin_get_user_posts";
+    pub const PATH_API_ADMIN_USER_STORAGE_UPDATE: &'static str = "/admin_update_user_storage";
+    pub const PATH_API_ADMIN_USER_ROLE_UPDATE: &'static str = "/admin_update_user_role";
+    pub const PATH_API_ADMIN_USER_BAN_UPDATE: &'static str = "/admin_update_user_ban";
+    pub const PATH_API_ADMIN_POST_SHOW_UPDATE: &'static str = "/admin_update_post_show";
+    pub const PATH_API_ADMIN_POST_RATING_UPDATE: &'static str = "/admin_update_post_rating";
+    pub const PATH_API_ADMIN_POST_COMMENT_DELETE: &'static str = "/admin_delete_post_comment";
+    pub const PATH_API_ADMIN_REPORTS_GET: &'static str = "/admin_get_reports";
+    pub const PATH_API_ADMIN_REPORT_RESOLVE: &'static str = "/admin_resolve_report";
+    pub const PATH_API_ADMIN_AUDIT_EVENTS_GET: &'static str = "/admin_get_audit_events";
+
+    // audit
+    pub const PATH_API_AUDIT_EVENTS_GET: &'static str = "/get_audit_events";
+
+    // report
+    pub const PATH_API_REPORT_ADD: &'static str = "/add_report";
+
+    // revision
+    pub const PATH_API_REVISIONS_GET: &'static str = "/get_revisions";
+
+    // trash
+    pub const PATH_API_TRASH_GET: &'static str = "/get_trash";
+    pub const PATH_API_TRASH_POST_RESTORE: &'static str = "/restore_post";
+    pub const PATH_API_TRASH_POST_COMMENT_RESTORE: &'static str = "/restore_post_comment";
+
+    // account
+    pub const PATH_API_ACCOUNT_DELETION_REQUEST: &'static str = "/request_account_deletion";
+    pub const PATH_API_ACCOUNT_DELETION_CONFIRM: &'static str = "/confirm_account_deletion";
+    pub const PATH_API_ACCOUNT_DELETION_CANCEL: &'static str = "/cancel_account_deletion";
+    pub const PATH_API_ACCOUNT_DELETION_GET: &'static str = "/get_account_deletion";
+    pub const PATH_API_DATA_EXPORT_ADD: &'static str = "/add_data_export";
+    pub const PATH_API_DATA_EXPORTS_GET: &'static str = "/get_data_exports";
+    pub const PATH_API_DATA_EXPORT_DOWNLOAD: &'static str = "/data_export/{export_id}";
+
+    // api token
+    pub const PATH_API_API_TOKEN_ADD: &'static str = "/add_api_token";
+    pub const PATH_API_API_TOKENS_GET: &'static str = "/get_api_tokens";
+    pub const PATH_API_API_TOKEN_DELETE: &'static str = "/delete_api_token";
+    pub const PATH_API_WEBHOOK_ADD: &'static str = "/add_webhook";
+    pub const PATH_API_WEBHOOKS_GET: &'static str = "/get_webhooks";
+    pub const PATH_API_WEBHOOK_DELETE: &'static str = "/delete_webhook";
+    pub const PATH_API_WEBHOOK_DELIVERIES_GET: &'static str = "/get_webhook_deliveries";
+
     // post like
     pub const PATH_API_POST_LIKE_ADD: &'static str = "/add_post_like";
     pub const PATH_API_POST_LIKE_CHECK: &'static str = "/check_post_like";
     pub const PATH_API_POST_LIKE_DELETE: &'static str = "/delete_post_like";
 
+    // notification
+    pub const PATH_API_NOTIFICATIONS_GET: &'static str = "/get_notifications";
+    pub const PATH_API_NOTIFICATION_UNREAD_COUNT: &'static str = "/get_notification_unread_count";
+    pub const PATH_API_NOTIFICATION_READ: &'static str = "/read_notification";
+    pub const PATH_API_NOTIFICATION_READ_ALL: &'static str = "/read_notification_all";
+
+    // live
+    pub const PATH_API_LIVE_POST: &'static str = "/live_post";
+
+    // user block
+    pub const PATH_API_USER_BLOCK_ADD: &'static str = "/add_user_block";
+    pub const PATH_API_USER_BLOCK_CHECK: &'static str = "/check_user_block";
+    pub const PATH_API_USER_BLOCK_DELETE: &'static str = "/delete_user_block";
+    pub const PATH_API_USER_BLOCKS_GET: &'static str = "/get_user_blocks";
+
+    // rating
+    pub const PATH_API_RATING_PREFERENCE_GET: &'static str = "/get_rating_preference";
+    pub const PATH_API_RATING_PREFERENCE_UPDATE: &'static str = "/update_rating_preference";
+
+    // user follow
+    pub const PATH_API_USER_FOLLOW_ADD: &'static str = "/add_user_follow";
+    pub const PATH_API_USER_FOLLOW_CHECK: &'static str = "/check_user_follow";
+    pub const PATH_API_USER_FOLLOW_DELETE: &'static str = "/delete_user_follow";
+    pub const PATH_API_USER_FOLLOW_STATS: &'static str = "/get_user_follow_stats";
+    pub const PATH_API_USER_FOLLOWERS_GET: &'static str = "/get_user_followers";
+    pub const PATH_API_USER_FOLLOWING_GET: &'static str = "/get_user_following";
+
     // change password
     pub const PATH_API_CHANGE_PASSWORD_SEND: &'static str = "/send_change_password";
     pub const PATH_API_CHANGE_PASSWORD_CONFIRM: &'static str = "/confirm_change_password";
@@ -353,19 +501,31 @@ pub mod path {
     pub const PATH_API_POST_DELETE: &'static str = "/post/delete";
     pub const PATH_API_POST_UPDATE_TAGS: &'static str = "/post/update_tags";
     pub const PATH_API_POST_UPDATE_TITLE: &'static str = "/post/update_title";
+    pub const PATH_API_POST_UPDATE_RATING: &'static str = "/post/update_rating";
+    pub const PATH_API_POST_UPDATE_VISIBILITY: &'static str = "/post/update_visibility";
+    pub const PATH_API_POST_UPDATE_EMBEDDABLE: &'static str = "/post/update_embeddable";
     pub const PATH_API_POST_UPDATE_DESCRIPTION: &'static str = "/post/update_description";
     pub const PATH_API_POST_ADD: &'static str = "/post/add";
     pub const PATH_API_POST_FILE_ADD: &'static str = "/post/{post_id}/add_file";
     pub const PATH_API_POST_GET: &'static str = "/post/get";
     pub const PATH_API_POSTS_GET: &'static str = "/post/search";
-    pub const PATH_API_POST_GET_OLDER: &'static str = "/post/get_older";
-    pub const PATH_API_POST_GET_NEWER: &'static str = "/post/get_newer";
-    pub const PATH_API_POST_GET_OLDER_OR_EQUAL: &'static str = "/post/get_older_or_equal";
-    pub const PATH_API_POST_GET_NEWER_OR_EQUAL: &'static str = "/post/get_newer_or_equal";
-    pub const PATH_API_USER_POST_GET_OLDER: &'static str = "/post/get_user_older";
-    pub const PATH_API_USER_POST_GET_NEWER: &'static str = "/post/get_user_newer";
-    pub const PATH_API_USER_POST_GET_OLDER_OR_EQUAL: &'static str = "/post/get_user_older_or_equal";
-    pub const PATH_API_USER_POST_GET_NEWER_OR_EQUAL: &'static str = "/post/get_user_newer_or_equal";
+    pub const PATH_API_POSTS_GET_RELATED: &'static str = "/post/related";
+    pub const PATH_API_POSTS_GET_FOLLOWING: &'static str = "/post/following";
+    pub const PATH_FEED: &'static str = "/feed.xml";
+    pub const PATH_FEED_USER: &'static str = "/u/{username}/feed.xml";
+    pub const PATH_FEED_TAGS: &'static str = "/tags/{tags}/feed.xml";
+    pub const PATH_WEBFINGER: &'static str = "/.well-known/webfinger";
+    pub const PATH_AP_INBOX: &'static str = "/ap/inbox";
+    pub const PATH_AP_USER: &'static str = "/ap/users/{username}";
+    pub const PATH_AP_USER_INBOX: &'static str = "/ap/users/{username}/inbox";
+    pub const PATH_AP_USER_OUTBOX: &'static str = "/ap/users/{username}/outbox";
+    pub const PATH_AP_USER_FOLLOWERS: &'static str = "/ap/users/{username}/followers";
+    pub const PATH_AP_POST: &'static str = "/ap/posts/{post_key}";
+    pub const PATH_EMBED: &'static str = "/embed/{username}/{post_key}";
+    pub const PATH_OEMBED: &'static str = "/oembed";
+    pub const PATH_SITEMAP: &'static str = "/sitemap.xml";
+    pub const PATH_SITEMAP_PAGE: &'static str = "/sitemap/{kind}/{page}";
+    pub const PATH_ROBOTS: &'static str = "/robots.txt";
     pub const PATH_HOME: &'static str = "/";
     pub const PATH_HOME_BS: () = path!("/");
     pub const PATH_U_USER: &'static str = "/u/:user";
@@ -374,6 +534,10 @@ pub mod path {
     pub const PATH_REGISTER: &'static str = "/register";
     pub const PATH_UPLOAD: &'static str = "/upload";
     pub const PATH_SETTINGS: &'static str = "/settings";
+    pub const PATH_NOTIFICATIONS: &'static str = "/notifications";
+    pub const PATH_MESSAGES: &'static str = "/messages";
+    pub const PATH_COLLECTION: &'static str = "/c";
+    pub const PATH_ADMIN: &'static str = "/admin";
 
     pub fn link_post_with_history(
         user: impl AsRef<str>,
@@ -396,22 +560,122 @@ pub mod path {
         // http://localhost:3000/api/post/5idoghr47bvsajsi5izx/add_file
         format!("/api/post/{}/add_file", post_key.as_ref())
     }
+    pub fn link_api_message_add_file(message_key: impl AsRef<str>) -> String {
+        format!("/api/message/{}/add_file", message_key.as_ref())
+    }
     // pub fn link_absolute_api_post_add_file(host: impl AsRef<str>, post_key: impl AsRef<str>) -> String {
     //     // http://localhost:3000/api/post/5idoghr47bvsajsi5izx/add_file
     //     format!("{}/api/post/{}/add_file", host.as_ref(), post_key.as_ref())
     // }
+    pub fn link_api_data_export(export_key: impl AsRef<str>) -> String {
+        format!("/api/data_export/{}", export_key.as_ref())
+    }
     pub fn link_img(hash: impl AsRef<str>, extension: impl AsRef<str>) -> String {
         format!("/file/{}.{}", hash.as_ref(), extension.as_ref())
     }
 
+    pub fn link_img_thumbnail(hash: impl AsRef<str>) -> String {
+        format!("/file/{}_thumbnail_default.webp", hash.as_ref())
+    }
+
     pub fn link_user(user: impl AsRef<str>) -> String {
         format!("/u/{}", user.as_ref())
     }
 
+    pub fn link_feed_user(user: impl AsRef<str>) -> String {
+        format!("/u/{}/feed.xml", user.as_ref())
+    }
+
+    pub fn link_ap_user(user: impl AsRef<str>) -> String {
+        format!("/ap/users/{}", user.as_ref())
+    }
+
+    pub fn link_ap_user_inbox(user: impl AsRef<str>) -> String {
+        format!("/ap/users/{}/inbox", user.as_ref())
+    }
+
+    pub fn link_ap_user_outbox(user: impl AsRef<str>) -> String {
+        format!("/ap/users/{}/outbox", user.as_ref())
+    }
+
+    pub fn link_ap_user_followers(user: impl AsRef<str>) -> String {
+        format!("/ap/users/{}/followers", user.as_ref())
+    }
+
+    pub fn link_ap_post(post: impl AsRef<str>) -> String {
+        format!("/ap/posts/{}", post.as_ref())
+    }
+
+    pub fn link_embed(user: impl AsRef<str>, post: impl AsRef<str>) -> String {
+        format!("/embed/{}/{}", user.as_ref(), post.as_ref())
+    }
+
+    pub fn link_sitemap_page(kind: impl std::fmt::Display, page: usize) -> String {
+        format!("/sitemap/{kind}/{page}")
+    }
+
+    /// absolute, the url is percent encoded into the query
+    pub fn link_oembed(origin: impl AsRef<str>, url: impl AsRef<str>) -> String {
+        let link = format!("{}{PATH_OEMBED}", origin.as_ref());
+        reqwest::Url::parse_with_params(&link, [("url", url.as_ref()), ("format", "json")])
+            .map(String::from)
+            .unwrap_or(link)
+    }
+
+    pub fn link_feed_tags(tags: impl AsRef<str>) -> String {
+        let tags = tags
+            .as_ref()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join("+");
+        format!("/tags/{tags}/feed.xml")
+    }
+
     pub fn link_settings() -> String {
         PATH_SETTINGS.to_string()
     }
 
+    pub fn link_settings_data_exports() -> String {
+        format!("{}#data_export", PATH_SETTINGS)
+    }
+
+    pub fn link_settings_account_deletion_confirm(confirm_key: impl AsRef<str>) -> String {
+        format!("{}?delete_account={}", PATH_SETTINGS, confirm_key.as_ref())
+    }
+
+    pub fn link_notifications() -> String {
+        PATH_NOTIFICATIONS.to_string()
+    }
+
+    pub fn link_messages() -> String {
+        PATH_MESSAGES.to_string()
+    }
+
+    pub fn link_admin() -> String {
+        PATH_ADMIN.to_string()
+    }
+
+    pub fn link_collection(collection_key: impl AsRef<str>) -> String {
+        format!("{PATH_COLLECTION}/{}", collection_key.as_ref())
+    }
+
+    pub fn link_conversation(conversation_key: impl AsRef<str>) -> String {
+        format!("{PATH_MESSAGES}/{}", conversation_key.as_ref())
+    }
+
+    pub fn link_live_post(origin: impl AsRef<str>, post_key: impl AsRef<str>) -> String {
+        let origin = origin.as_ref();
+        let origin = origin
+            .strip_prefix("https://")
+            .map(|v| format!("wss://{v}"))
+            .or_else(|| origin.strip_prefix("http://").map(|v| format!("ws://{v}")))
+            .unwrap_or_else(|| origin.to_string());
+        format!(
+            "{origin}{PATH_API}{PATH_API_LIVE_POST}?post_key={}",
+            post_key.as_ref()
+        )
+    }
+
     pub fn link_login() -> String {
         PATH_LOGIN.to_string()
     }
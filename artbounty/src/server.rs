@@ -4,16 +4,21 @@ use std::time::Duration;
 #[cfg(feature = "ssr")]
 use axum_server::tls_rustls::RustlsConfig;
 use leptos::{logging, prelude::*};
-#[cfg(feature = "ssr")]
-use tokio::fs;
 use tracing::trace;
 
 #[cfg(feature = "ssr")]
-use crate::api::{ServerReq, app_state::AppState, backend::proccess_post_files};
+use crate::api::{
+    ServerReq,
+    app_state::AppState,
+    backend::{
+        THUMBNAIL_RESOLUTION_LIMIT, activitypub::proccess_ap_deliveries, proccess_data_exports,
+        proccess_post_files, publish_scheduled_posts, purge_account_deletions, purge_trash,
+        webhook::proccess_webhook_deliveries,
+    },
+};
 use crate::path::{
     PATH_API, PATH_API_ACC, PATH_API_INVITE_DECODE, PATH_API_LOGIN, PATH_API_LOGOUT,
-    PATH_API_POST_ADD, PATH_API_POST_GET_OLDER, PATH_API_REGISTER, PATH_API_SEND_EMAIL_INVITE,
-    PATH_API_USER,
+    PATH_API_POST_ADD, PATH_API_REGISTER, PATH_API_SEND_EMAIL_INVITE, PATH_API_USER,
 };
 
 #[cfg(feature = "ssr")]
@@ -57,7 +62,7 @@ pub async fn server() {
     let conf = get_configuration(Some("leptos.toml")).unwrap();
     let leptos_options = conf.leptos_options;
     let addr = leptos_options.site_addr;
-    // let routes = generate_route_list(App);
+    let routes = generate_route_list(App);
 
     let comppression_layer = CompressionLayer::new()
         .br(true)
@@ -71,23 +76,30 @@ pub async fn server() {
         .allow_methods([Method::GET, Method::POST])
         .allow_origin(cors::Any);
 
-    // let leptos_router = Router::new()
-    //     .leptos_routes(&leptos_options, routes, {
-    //         let leptos_options = leptos_options.clone();
-    //         move || shell(leptos_options.clone())
-    //     })
-    //     .fallback(leptos_axum::file_and_error_handler(shell))
-    //     .with_state(leptos_options);
+    // pages render on the server with AppState in context so their meta can come straight from the db
+    let leptos_router = Router::new()
+        .leptos_routes_with_context(
+            &leptos_options,
+            routes,
+            {
+                let app_state = app_state.clone();
+                move || provide_context(app_state.clone())
+            },
+            {
+                let leptos_options = leptos_options.clone();
+                move || shell(leptos_options.clone())
+            },
+        )
+        .fallback(leptos_axum::file_and_error_handler(shell))
+        .with_state(leptos_options);
 
     let api_router = create_api_router(app_state.clone()).with_state(app_state.clone());
     // let fallback_router = Router::new();
 
     let app = Router::new()
         .nest_service("/file", ServeDir::new(&file_path))
-        // .merge(leptos_router)
         .merge(api_router)
-        // .fallback(ServeDir::new(&file_path))
-        .fallback(fallback_api)
+        .merge(leptos_router)
         // .route_layer(middleware::from_fn_with_state(
         //     app_state,
         //     auth_optional_middleware,
@@ -120,7 +132,9 @@ pub async fn server() {
                     _ = interval.tick() => {},
                 };
 
-                let result = proccess_post_files(db.clone(), files_path.clone(), 1280).await;
+                let result =
+                    proccess_post_files(db.clone(), files_path.clone(), THUMBNAIL_RESOLUTION_LIMIT)
+                        .await;
                 if let Err(err) = result {
                     tracing::error!("{err}");
                     break;
@@ -129,98 +143,155 @@ pub async fn server() {
         }
     });
 
-    let shutdown = async {
-        proccess_files.await.unwrap();
-        // tokio::signal::ctrl_c().await.unwrap();
-        tracing::info!("Shutting down...");
-    };
-    axum::serve(listener, app.into_make_service())
-        .with_graceful_shutdown(shutdown)
-        .await
-        .unwrap();
-}
+    let publish_scheduled = tokio::spawn({
+        let app_state = app_state.clone();
 
-#[cfg(feature = "ssr")]
-use axum::{
-    extract::{DefaultBodyLimit, State},
-    http::{
-        Request, Response, StatusCode, Uri,
-        header::{self, HeaderMap, HeaderName},
-    },
-    response::IntoResponse,
-};
-// #[cfg(feature = "ssr")]
-// use http::Uri;
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(10));
+            loop {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {
+                        break;
+                    },
+                    _ = interval.tick() => {},
+                };
 
-#[cfg(feature = "ssr")]
-pub async fn fallback_api(
-    uri: Uri,
-    // State(app_state): State<AppState>,
-    // req: ServerReq,
-) -> impl IntoResponse {
-    // uri.ex
-    let a = uri.path();
+                let result = publish_scheduled_posts(&app_state).await;
+                if let Err(err) = result {
+                    tracing::error!("{err}");
+                }
+            }
+        }
+    });
 
-    trace!("fallback uri path: {a}");
+    let purge_trashed = tokio::spawn({
+        let app_state = app_state.clone();
 
-    // uri.
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {
+                        break;
+                    },
+                    _ = interval.tick() => {},
+                };
 
-    // let req = Request::builder()
-    //     .uri(uri.clone())
-    //     .body(Body::empty())
-    //     .unwrap();
-    // let read_file = async move |name: String| {
-    //
-    // };
-
-    // TODO make sure people cant path inject
-
-    let (headers, name) = match a {
-        "/pkg/artbounty_1_bg.wasm" => (
-            [(header::CONTENT_TYPE, "application/wasm")],
-            "/pkg/artbounty_1_bg.wasm",
-        ),
-        "/pkg/artbounty_1.css" => ([(header::CONTENT_TYPE, "text/css")], "/pkg/artbounty_1.css"),
-        // "/pkg/artbounty_1.js"
-        "/pkg/artbounty_1.js" => (
-            [(header::CONTENT_TYPE, "text/javascript")],
-            "/pkg/artbounty_1.js",
-        ),
-        "/atkinson_hyperlegible_next/atkinson_hyperlegible_next_vf-variable.woff2" => (
-            [(header::CONTENT_TYPE, "application/font-woff2")],
-            "/atkinson_hyperlegible_next/atkinson_hyperlegible_next_vf-variable.woff2",
-        ),
-        "/upload.svg" => ([(header::CONTENT_TYPE, "image/svg+xml")], "/upload.svg"),
-        "/favicon.ico" => ([(header::CONTENT_TYPE, "image/x-icon")], "/favicon.ico"),
-        _ => ([(header::CONTENT_TYPE, "text/html")], "/index.html"),
-        // artbounty_1.css
-        // v => {
-        //     trace!("not found {v}");
-        //     return Err((StatusCode::NOT_FOUND, v.to_string()));
-        // }
-    };
+                let result = purge_trash(&app_state).await;
+                if let Err(err) = result {
+                    tracing::error!("{err}");
+                }
+            }
+        }
+    });
 
-    let path = format!("target/site{}", name);
-    let file = match fs::read(path.clone()).await {
-        Ok(file) => file,
-        Err(err) => {
-            return Err((StatusCode::NOT_FOUND, path));
+    let proccess_exports = tokio::spawn({
+        let app_state = app_state.clone();
+
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(10));
+            loop {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {
+                        break;
+                    },
+                    _ = interval.tick() => {},
+                };
+
+                let result = proccess_data_exports(&app_state).await;
+                if let Err(err) = result {
+                    tracing::error!("{err}");
+                }
+            }
         }
-    };
+    });
+
+    let purge_deleted_accounts = tokio::spawn({
+        let app_state = app_state.clone();
+
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {
+                        break;
+                    },
+                    _ = interval.tick() => {},
+                };
+
+                let result = purge_account_deletions(&app_state).await;
+                if let Err(err) = result {
+                    tracing::error!("{err}");
+                }
+            }
+        }
+    });
 
-    Ok((headers, file))
-
-    // let headers = Headers([
-    //     (header::CONTENT_TYPE, "text/toml; charset=utf-8"),
-    //     (
-    //         header::CONTENT_DISPOSITION,
-    //         "attachment; filename=\"Cargo.toml\"",
-    //     ),
-    // ]);
-    //
-    // a
+    let proccess_webhooks = tokio::spawn({
+        let app_state = app_state.clone();
+
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(10));
+            loop {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {
+                        break;
+                    },
+                    _ = interval.tick() => {},
+                };
+
+                let result = proccess_webhook_deliveries(&app_state).await;
+                if let Err(err) = result {
+                    tracing::error!("{err}");
+                }
+            }
+        }
+    });
+
+    let proccess_ap = tokio::spawn({
+        let app_state = app_state.clone();
+
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(10));
+            loop {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {
+                        break;
+                    },
+                    _ = interval.tick() => {},
+                };
+
+                let result = proccess_ap_deliveries(&app_state).await;
+                if let Err(err) = result {
+                    tracing::error!("{err}");
+                }
+            }
+        }
+    });
+
+    let shutdown = async {
+        proccess_files.await.unwrap();
+        publish_scheduled.await.unwrap();
+        purge_trashed.await.unwrap();
+        proccess_exports.await.unwrap();
+        purge_deleted_accounts.await.unwrap();
+        proccess_webhooks.await.unwrap();
+        proccess_ap.await.unwrap();
+        // tokio::signal::ctrl_c().await.unwrap();
+        tracing::info!("Shutting down...");
+    };
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown)
+    .await
+    .unwrap();
 }
 
+#[cfg(feature = "ssr")]
+use axum::extract::DefaultBodyLimit;
+
 #[cfg(feature = "ssr")]
 pub fn create_api_router(
     app_state: crate::api::app_state::AppState,
@@ -230,13 +301,16 @@ pub fn create_api_router(
         extract::{Query, Request, State},
         http::Method,
         middleware::{self, Next},
-        routing::post,
+        routing::{get, post},
     };
 
     use crate::{
         api::{
             self,
-            backend::{auth_middleware, auth_optional_middleware},
+            backend::{
+                auth_middleware, auth_optional_middleware, moderation_middleware,
+                wire_format_middleware,
+            },
         },
         path::{self},
     };
@@ -246,6 +320,10 @@ pub fn create_api_router(
             // "/test_upload_big_file",
             post(api::backend::post::add_post_file),
         )
+        .route(
+            path::PATH_API_MESSAGE_FILE_ADD,
+            post(api::backend::conversation::add_message_file),
+        )
         .layer(DefaultBodyLimit::max(1024 * 1000_000_000))
         .route_layer(middleware::from_fn_with_state(
             app_state.clone(),
@@ -253,11 +331,6 @@ pub fn create_api_router(
         ));
 
     let api_router_public = Router::new()
-        .route(
-            path::PATH_API_POST_COMMENT_GET,
-            post(api::backend::post_comment::get_post_comment),
-        )
-        //
         .route(
             path::PATH_API_CHANGE_PASSWORD_CONFIRM,
             post(api::backend::change_password::confirm_password_change),
@@ -275,41 +348,18 @@ pub fn create_api_router(
             post(api::backend::auth::send_email_invite),
         )
         .route(path::PATH_API_USER, post(api::backend::get_user))
-        .route(path::PATH_API_POST_GET, post(api::backend::post::get_post))
-        .route(path::PATH_API_POSTS_GET, post(api::backend::post::get_posts))
-        .route(
-            path::PATH_API_POST_GET_OLDER,
-            post(api::backend::post::get_posts_older),
-        )
-        .route(
-            path::PATH_API_POST_GET_NEWER,
-            post(api::backend::post::get_posts_newer),
-        )
-        .route(
-            path::PATH_API_POST_GET_OLDER_OR_EQUAL,
-            post(api::backend::post::get_posts_older_or_equal),
-        )
-        .route(
-            path::PATH_API_POST_GET_NEWER_OR_EQUAL,
-            post(api::backend::post::get_posts_newer_or_equal),
-        )
-        .route(
-            path::PATH_API_USER_POST_GET_OLDER,
-            post(api::backend::post::get_posts_older_for_user),
-        )
         .route(
-            path::PATH_API_USER_POST_GET_NEWER,
-            post(api::backend::post::get_posts_newer_for_user),
+            path::PATH_API_USER_FOLLOW_STATS,
+            post(api::backend::user_follow::get_user_follow_stats),
         )
         .route(
-            path::PATH_API_USER_POST_GET_OLDER_OR_EQUAL,
-            post(api::backend::post::get_posts_older_or_equal_for_user),
+            path::PATH_API_USER_FOLLOWERS_GET,
+            post(api::backend::user_follow::get_user_followers),
         )
         .route(
-            path::PATH_API_USER_POST_GET_NEWER_OR_EQUAL,
-            post(api::backend::post::get_posts_newer_or_equal_for_user),
+            path::PATH_API_USER_FOLLOWING_GET,
+            post(api::backend::user_follow::get_user_following),
         )
-
         // .fallback(fallback_api)
         // .fallback(ServeDir::new(&file_path))
         ;
@@ -338,6 +388,119 @@ pub fn create_api_router(
             path::PATH_API_POST_LIKE_DELETE,
             post(api::backend::post_like::delete_post_like),
         )
+        .route(
+            path::PATH_API_USER_BLOCK_ADD,
+            post(api::backend::user_block::add_user_block),
+        )
+        .route(
+            path::PATH_API_USER_BLOCK_CHECK,
+            post(api::backend::user_block::check_user_block),
+        )
+        .route(
+            path::PATH_API_USER_BLOCK_DELETE,
+            post(api::backend::user_block::delete_user_block),
+        )
+        .route(
+            path::PATH_API_USER_BLOCKS_GET,
+            post(api::backend::user_block::get_user_blocks),
+        )
+        .route(
+            path::PATH_API_USER_FOLLOW_ADD,
+            post(api::backend::user_follow::add_user_follow),
+        )
+        .route(
+            path::PATH_API_USER_FOLLOW_CHECK,
+            post(api::backend::user_follow::check_user_follow),
+        )
+        .route(
+            path::PATH_API_USER_FOLLOW_DELETE,
+            post(api::backend::user_follow::delete_user_follow),
+        )
+        .route(
+            path::PATH_API_POSTS_GET_FOLLOWING,
+            post(api::backend::user_follow::get_posts_following),
+        )
+        .route(
+            path::PATH_API_NOTIFICATIONS_GET,
+            post(api::backend::notification::get_notifications),
+        )
+        .route(
+            path::PATH_API_NOTIFICATION_UNREAD_COUNT,
+            post(api::backend::notification::get_notification_unread_count),
+        )
+        .route(
+            path::PATH_API_NOTIFICATION_READ,
+            post(api::backend::notification::read_notification),
+        )
+        .route(
+            path::PATH_API_NOTIFICATION_READ_ALL,
+            post(api::backend::notification::read_notification_all),
+        )
+        .route(
+            path::PATH_API_COLLECTION_ADD,
+            post(api::backend::collection::add_collection),
+        )
+        .route(
+            path::PATH_API_COLLECTION_UPDATE,
+            post(api::backend::collection::update_collection),
+        )
+        .route(
+            path::PATH_API_COLLECTION_DELETE,
+            post(api::backend::collection::delete_collection),
+        )
+        .route(
+            path::PATH_API_COLLECTION_ITEM_ADD,
+            post(api::backend::collection::add_collection_item),
+        )
+        .route(
+            path::PATH_API_COLLECTION_ITEM_DELETE,
+            post(api::backend::collection::delete_collection_item),
+        )
+        .route(
+            path::PATH_API_COLLECTION_ITEM_ORDER,
+            post(api::backend::collection::order_collection_item),
+        )
+        .route(
+            path::PATH_API_POST_COLLECTIONS_GET,
+            post(api::backend::collection::get_post_collections),
+        )
+        .route(
+            path::PATH_API_CONVERSATION_ADD,
+            post(api::backend::conversation::add_conversation),
+        )
+        .route(
+            path::PATH_API_CONVERSATION_GET,
+            post(api::backend::conversation::get_conversation),
+        )
+        .route(
+            path::PATH_API_CONVERSATIONS_GET,
+            post(api::backend::conversation::get_conversations),
+        )
+        .route(
+            path::PATH_API_CONVERSATION_UNREAD_COUNT,
+            post(api::backend::conversation::get_conversation_unread_count),
+        )
+        .route(
+            path::PATH_API_CONVERSATION_READ,
+            post(api::backend::conversation::read_conversation),
+        )
+        .route(
+            path::PATH_API_CONVERSATION_BLOCK,
+            post(api::backend::conversation::block_conversation),
+        )
+        .route(
+            path::PATH_API_CONVERSATION_UNBLOCK,
+            post(api::backend::conversation::unblock_conversation),
+        )
+        .route(
+            path::PATH_API_MESSAGE_ADD,
+            post(api::backend::conversation::add_message),
+        )
+        .route(
+            path::PATH_API_MESSAGES_GET,
+            post(api::backend::conversation::get_messages),
+        )
+        .route(path::PATH_API_LIVE_POST, get(api::backend::live::live_post))
         .route(path::PATH_API_ACC, post(api::backend::get_account))
         .route(
             path::PATH_API_CHANGE_USERNAME,
@@ -385,6 +548,18 @@ pub fn create_api_router(
             path::PATH_API_POST_UPDATE_TITLE,
             post(api::backend::post::update_post_title),
         )
+        .route(
+            path::PATH_API_POST_UPDATE_RATING,
+            post(api::backend::post::update_post_rating),
+        )
+        .route(
+            path::PATH_API_POST_UPDATE_VISIBILITY,
+            post(api::backend::post::update_post_visibility),
+        )
+        .route(
+            path::PATH_API_POST_UPDATE_EMBEDDABLE,
+            post(api::backend::post::update_post_embeddable),
+        )
         .route(
             path::PATH_API_POST_UPDATE_TAGS,
             post(api::backend::post::update_post_tags),
@@ -397,23 +572,270 @@ pub fn create_api_router(
             path::PATH_API_POST_DELETE,
             post(api::backend::post::delete_post),
         )
+        .route(
+            path::PATH_API_REPORT_ADD,
+            post(api::backend::report::add_report),
+        )
+        .route(
+            path::PATH_API_AUDIT_EVENTS_GET,
+            post(api::backend::audit::get_audit_events),
+        )
+        .route(
+            path::PATH_API_REVISIONS_GET,
+            post(api::backend::revision::get_revisions),
+        )
+        .route(
+            path::PATH_API_TRASH_GET,
+            post(api::backend::trash::get_trash),
+        )
+        .route(
+            path::PATH_API_TRASH_POST_RESTORE,
+            post(api::backend::trash::restore_post),
+        )
+        .route(
+            path::PATH_API_TRASH_POST_COMMENT_RESTORE,
+            post(api::backend::trash::restore_post_comment),
+        )
+        .route(
+            path::PATH_API_ACCOUNT_DELETION_REQUEST,
+            post(api::backend::account::request_account_deletion),
+        )
+        .route(
+            path::PATH_API_ACCOUNT_DELETION_CONFIRM,
+            post(api::backend::account::confirm_account_deletion),
+        )
+        .route(
+            path::PATH_API_ACCOUNT_DELETION_CANCEL,
+            post(api::backend::account::cancel_account_deletion),
+        )
+        .route(
+            path::PATH_API_ACCOUNT_DELETION_GET,
+            post(api::backend::account::get_account_deletion),
+        )
+        .route(
+            path::PATH_API_DATA_EXPORT_ADD,
+            post(api::backend::account::add_data_export),
+        )
+        .route(
+            path::PATH_API_DATA_EXPORTS_GET,
+            post(api::backend::account::get_data_exports),
+        )
+        .route(
+            path::PATH_API_DATA_EXPORT_DOWNLOAD,
+            get(api::backend::account::download_data_export),
+        )
+        .route(
+            path::PATH_API_API_TOKEN_ADD,
+            post(api::backend::api_token::add_api_token),
+        )
+        .route(
+            path::PATH_API_API_TOKENS_GET,
+            post(api::backend::api_token::get_api_tokens),
+        )
+        .route(
+            path::PATH_API_API_TOKEN_DELETE,
+            post(api::backend::api_token::delete_api_token),
+        )
+        .route(
+            path::PATH_API_WEBHOOK_ADD,
+            post(api::backend::webhook::add_webhook),
+        )
+        .route(
+            path::PATH_API_WEBHOOKS_GET,
+            post(api::backend::webhook::get_webhooks),
+        )
+        .route(
+            path::PATH_API_WEBHOOK_DELETE,
+            post(api::backend::webhook::delete_webhook),
+        )
+        .route(
+            path::PATH_API_WEBHOOK_DELIVERIES_GET,
+            post(api::backend::webhook::get_webhook_deliveries),
+        )
         .route_layer(middleware::from_fn_with_state(
             app_state.clone(),
             auth_middleware,
         ));
+    let api_router_moderation = Router::new()
+        .route(
+            path::PATH_API_ADMIN_USERS_GET,
+            post(api::backend::admin::get_users),
+        )
+        .route(
+            path::PATH_API_ADMIN_USER_POSTS_GET,
+            post(api::backend::admin::get_user_posts),
+        )
+        .route(
+            path::PATH_API_ADMIN_USER_STORAGE_UPDATE,
+            post(api::backend::admin::update_user_storage),
+        )
+        .route(
+            path::PATH_API_ADMIN_USER_ROLE_UPDATE,
+            post(api::backend::admin::update_user_role),
+        )
+        .route(
+            path::PATH_API_ADMIN_USER_BAN_UPDATE,
+            post(api::backend::admin::update_user_ban),
+        )
+        .route(
+            path::PATH_API_ADMIN_POST_SHOW_UPDATE,
+            post(api::backend::admin::update_post_show),
+        )
+        .route(
+            path::PATH_API_ADMIN_POST_RATING_UPDATE,
+            post(api::backend::admin::update_post_rating),
+        )
+        .route(
+            path::PATH_API_ADMIN_POST_COMMENT_DELETE,
+            post(api::backend::admin::delete_post_comment),
+        )
+        .route(
+            path::PATH_API_ADMIN_REPORTS_GET,
+            post(api::backend::report::get_reports),
+        )
+        .route(
+            path::PATH_API_ADMIN_REPORT_RESOLVE,
+            post(api::backend::report::resolve_report),
+        )
+        .route(
+            path::PATH_API_ADMIN_AUDIT_EVENTS_GET,
+            post(api::backend::audit::admin_get_audit_events),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            moderation_middleware,
+        ));
     let api_router_auth_optional = Router::new()
+        .route(path::PATH_API_POST_GET, post(api::backend::post::get_post))
+        .route(
+            path::PATH_API_POSTS_GET,
+            post(api::backend::post::get_posts),
+        )
+        .route(
+            path::PATH_API_POSTS_GET_RELATED,
+            post(api::backend::post::get_posts_related),
+        )
+        .route(
+            path::PATH_API_POST_COMMENT_GET,
+            post(api::backend::post_comment::get_post_comment),
+        )
+        .route(
+            path::PATH_API_RATING_PREFERENCE_GET,
+            post(api::backend::rating::get_rating_preference),
+        )
+        .route(
+            path::PATH_API_RATING_PREFERENCE_UPDATE,
+            post(api::backend::rating::update_rating_preference),
+        )
         .route(
             path::PATH_API_CHANGE_PASSWORD_SEND,
             post(api::backend::change_password::send_password_change),
         )
+        .route(
+            path::PATH_API_COLLECTION_GET,
+            post(api::backend::collection::get_collection),
+        )
+        .route(
+            path::PATH_API_COLLECTIONS_GET,
+            post(api::backend::collection::get_user_collections),
+        )
+        .route(
+            path::PATH_API_COLLECTION_POSTS_GET,
+            post(api::backend::collection::get_collection_posts),
+        )
         .route_layer(middleware::from_fn_with_state(
-            app_state,
+            app_state.clone(),
             auth_optional_middleware,
         ));
+    let api_router_v1_auth = Router::new()
+        .route(
+            path::PATH_API_V1_POST_LIKES,
+            post(api::backend::v1::add_post_like).delete(api::backend::v1::delete_post_like),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            api::backend::v1::v1_auth_middleware,
+        ));
+    let api_router_v1 = Router::new()
+        .route(path::PATH_API_V1_POSTS, get(api::backend::v1::get_posts))
+        .route(path::PATH_API_V1_POST, get(api::backend::v1::get_post))
+        .route(
+            path::PATH_API_V1_POST_COMMENTS,
+            get(api::backend::v1::get_post_comments),
+        )
+        .route(path::PATH_API_V1_USER, get(api::backend::v1::get_user))
+        .route(
+            path::PATH_API_V1_USER_POSTS,
+            get(api::backend::v1::get_user_posts),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            app_state,
+            auth_optional_middleware,
+        ))
+        .route(
+            path::PATH_API_V1_OPENAPI,
+            get(api::backend::v1::get_openapi),
+        )
+        .merge(api_router_v1_auth);
     let api_router = Router::new()
+        .nest(path::PATH_API_V1, api_router_v1)
         .merge(api_router_upload)
         .merge(api_router_public)
         .merge(api_router_auth_optional)
-        .merge(api_router_auth);
-    Router::new().nest(path::PATH_API, api_router)
+        .merge(api_router_moderation)
+        .merge(api_router_auth)
+        .layer(middleware::from_fn(wire_format_middleware));
+    // feed readers expect these next to the pages they mirror, not under PATH_API
+    let feed_router = Router::new()
+        .route(path::PATH_FEED, get(api::backend::feed::get_feed))
+        .route(path::PATH_FEED_USER, get(api::backend::feed::get_user_feed))
+        .route(path::PATH_FEED_TAGS, get(api::backend::feed::get_tags_feed));
+    // other instances look these up by the exact urls the actors advertise
+    let ap_router = Router::new()
+        .route(
+            path::PATH_WEBFINGER,
+            get(api::backend::activitypub::webfinger),
+        )
+        .route(
+            path::PATH_AP_INBOX,
+            post(api::backend::activitypub::post_shared_inbox),
+        )
+        .route(
+            path::PATH_AP_USER,
+            get(api::backend::activitypub::get_actor),
+        )
+        .route(
+            path::PATH_AP_USER_INBOX,
+            post(api::backend::activitypub::post_user_inbox),
+        )
+        .route(
+            path::PATH_AP_USER_OUTBOX,
+            get(api::backend::activitypub::get_outbox),
+        )
+        .route(
+            path::PATH_AP_USER_FOLLOWERS,
+            get(api::backend::activitypub::get_followers),
+        )
+        .route(path::PATH_AP_POST, get(api::backend::activitypub::get_note));
+
+    let embed_router = Router::new()
+        .route(path::PATH_EMBED, get(api::backend::embed::get_embed))
+        .route(path::PATH_OEMBED, get(api::backend::embed::get_oembed));
+
+    let sitemap_router = Router::new()
+        .route(
+            path::PATH_SITEMAP,
+            get(api::backend::sitemap::get_sitemap_index),
+        )
+        .route(
+            path::PATH_SITEMAP_PAGE,
+            get(api::backend::sitemap::get_sitemap_page),
+        )
+        .route(path::PATH_ROBOTS, get(api::backend::sitemap::get_robots));
+    Router::new()
+        .nest(path::PATH_API, api_router)
+        .merge(feed_router)
+        .merge(ap_router)
+        .merge(embed_router)
+        .merge(sitemap_router)
 }
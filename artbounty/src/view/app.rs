@@ -1,11 +1,15 @@
 use std::time::Duration;
 
 use leptos::prelude::*;
+use leptos_meta::{Title, provide_meta_context};
 use leptos_router::components::*;
 use leptos_router::path;
 use log::error;
 use log::trace;
-use page::{home, login, post, profile, register, settings, upload};
+use page::{
+    admin, collection, home, login, messages, notifications, post, profile, register, settings,
+    upload,
+};
 use tracing::info;
 
 use crate::api::Api;
@@ -14,7 +18,10 @@ use crate::api::ApiWebTmp;
 use crate::api::ServerAuthErr;
 use crate::api::ServerErr;
 use crate::api::ServerRes;
+use crate::api::shared::admin::{Permission, UserRole};
+use crate::api::shared::rating::RatingPreference;
 use crate::path::link_user;
+use crate::view::app::components::page_meta::SITE_NAME;
 use crate::view::toolbox::prelude::*;
 
 pub mod components;
@@ -25,6 +32,8 @@ pub mod page;
 pub struct GlobalState {
     pub acc: RwSignal<Option<Acc>>,
     pub acc_pending: RwSignal<bool>,
+    /// comes from the account when logged in, otherwise from the rating cookie
+    pub rating: RwSignal<RatingPreference>,
     pub time: RwSignal<u128>,
 }
 
@@ -43,6 +52,10 @@ impl GlobalState {
     pub fn get_acc_id_tracked(&self) -> Option<String> {
         self.acc.with(|acc| acc.as_ref().map(|acc| acc.key.clone()))
     }
+    pub fn get_acc_id_untracked(&self) -> Option<String> {
+        self.acc
+            .with_untracked(|acc| acc.as_ref().map(|acc| acc.key.clone()))
+    }
     pub fn get_email_tracked(&self) -> Option<String> {
         self.acc
             .with(|acc| acc.as_ref().map(|acc| acc.email.clone()))
@@ -60,6 +73,36 @@ impl GlobalState {
             .with_untracked(|acc| acc.as_ref().map(|acc| acc.username.clone()))
     }
 
+    pub fn get_role_tracked(&self) -> UserRole {
+        self.acc
+            .with(|acc| acc.as_ref().map(|acc| acc.role).unwrap_or_default())
+    }
+    pub fn can(&self, permission: Permission) -> bool {
+        self.get_role_tracked().can(permission)
+    }
+
+    pub fn update_rating(&self) {
+        let rating = self.rating;
+        ApiWebTmp::new()
+            .get_rating_preference()
+            .send_web(move |result| async move {
+                match result {
+                    Ok(ServerRes::RatingPreference(v)) => {
+                        let r = rating.try_set(v);
+                        if r.is_some() {
+                            error!("global state rating was disposed somehow");
+                        }
+                    }
+                    Ok(res) => {
+                        error!("expected RatingPreference, received {res:?}");
+                    }
+                    Err(err) => {
+                        error!("{err}");
+                    }
+                }
+            });
+    }
+
     pub fn change_username(&self, username: impl Into<String>) {
         let username = username.into();
         self.acc.update(|v| {
@@ -97,12 +140,14 @@ impl GlobalState {
                 username,
                 email,
                 key,
+                role,
             }) => {
                 info!("logged in as {username}");
                 let r = self.acc.try_set(Some(Acc {
                     username,
                     email,
                     key,
+                    role,
                 }));
                 if r.is_some() {
                     error!("global state acc was disposed somehow");
@@ -119,6 +164,7 @@ impl GlobalState {
         if r.is_some() {
             error!("global state acc was disposed somehow");
         }
+        self.update_rating();
     }
     pub fn is_logged_in(&self) -> Option<bool> {
         let pending = self.acc_pending.get();
@@ -135,6 +181,7 @@ impl GlobalState {
         if self.acc.with_untracked(|v| v.is_some()) {
             let api = ApiWebTmp::new();
             let acc = self.acc;
+            let this = self.clone();
             api.logout().send_web(move |result| async move {
                 match result {
                     Ok(_) => {
@@ -142,6 +189,7 @@ impl GlobalState {
                         if r.is_some() {
                             error!("global state acc was disposed somehow");
                         }
+                        this.update_rating();
                     }
                     Err(ServerErr::AuthErr(_)) => {
                         let r = acc.try_set(None);
@@ -161,10 +209,12 @@ pub struct Acc {
     pub key: String,
     pub username: String,
     pub email: String,
+    pub role: UserRole,
 }
 
 #[component]
 pub fn App() -> impl IntoView {
+    provide_meta_context();
     provide_context(GlobalState::new());
     let global_state = expect_context::<GlobalState>();
 
@@ -192,13 +242,19 @@ pub fn App() -> impl IntoView {
     };
 
     view! {
+        <Title text=SITE_NAME />
         <Router>
             <Routes fallback=|| "not found">
                 <Route path=path!("") view=home::Page />
                 <Route path=path!("/u/:username/:post") view=post::Page />
                 <Route path=path!("/u/:username") view=profile::Page />
+                <Route path=path!("/c/:collection") view=collection::Page />
                 <ProtectedRoute path=path!("/settings") condition=move||global_state.is_logged_in() redirect_path view=settings::Page />
                 <ProtectedRoute path=path!("/upload") condition=move||global_state.is_logged_in() redirect_path view=upload::Page />
+                <ProtectedRoute path=path!("/notifications") condition=move||global_state.is_logged_in() redirect_path view=notifications::Page />
+                <ProtectedRoute path=path!("/admin") condition=move||global_state.is_logged_in().map(|v| v && global_state.get_role_tracked().is_staff()) redirect_path view=admin::Page />
+                <ProtectedRoute path=path!("/messages") condition=move||global_state.is_logged_in() redirect_path view=messages::Page />
+                <ProtectedRoute path=path!("/messages/:conversation") condition=move||global_state.is_logged_in() redirect_path view=messages::ConversationPage />
                 <ProtectedRoute path=path!("/login") condition=move||global_state.is_logged_in().map(|v| !v) redirect_path view=login::Page />
                 <ProtectedRoute path=path!("/register") condition=move||global_state.is_logged_in().map(|v| !v) redirect_path view=register::Page />
             </Routes>
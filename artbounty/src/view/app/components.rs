@@ -321,17 +321,613 @@ pub mod btn_primary {
     }
 }
 
+pub mod report_btn {
+    use crate::api::shared::report::{REPORT_TEXT_MAX_LEN, ReportReason, ReportTarget};
+    use crate::api::{Api, ApiWeb, ServerRes};
+    use leptos::{html, prelude::*};
+    use strum::IntoEnumIterator;
+    use tracing::error;
+    use web_sys::SubmitEvent;
+
+    #[component]
+    pub fn ReportBtn(
+        #[prop(into)] target: Signal<Option<ReportTarget>>,
+        #[prop(optional, into)] class: Option<Callback<(), String>>,
+    ) -> impl IntoView {
+        let api = ApiWeb::new();
+        let open = RwSignal::new(false);
+        let reported = RwSignal::new(false);
+        let err = RwSignal::new(String::new());
+        let reason_input = NodeRef::<html::Select>::new();
+        let text_input = NodeRef::<html::Textarea>::new();
+        let class_fn = move || class.map(|v| v.run(())).unwrap_or_default();
+
+        let on_submit = move |e: SubmitEvent| {
+            e.prevent_default();
+            let (Some(target), Some(reason_input), Some(text_input)) = (
+                target.get_untracked(),
+                reason_input.get_untracked(),
+                text_input.get_untracked(),
+            ) else {
+                return;
+            };
+            let reason = ReportReason::from_db(reason_input.value());
+            api.add_report(target, reason, text_input.value())
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Ok) => {
+                            err.set(String::new());
+                            reported.set(true);
+                            open.set(false);
+                        }
+                        Ok(res) => {
+                            error!("expected Ok, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        view! {
+            <span class="relative">
+                <button
+                    class=move || format!("text-base03 hover:text-base05 disabled:hover:text-base03 {}", class_fn())
+                    disabled=move || reported.get()
+                    on:click=move |_| open.update(|v| *v = !*v)
+                >
+                    {move || if reported.get() { "Reported" } else { "Report" }}
+                </button>
+                <Show when=move || open.get() >
+                    <form class="absolute right-0 z-10 flex flex-col gap-2 w-[16rem] p-2 rounded-xl bg-base01 border-2 border-base02" on:submit=on_submit >
+                        <select node_ref=reason_input class="bg-base02 px-2 rounded-lg">
+                            {ReportReason::iter().map(|reason| view! {
+                                <option value=reason.to_string()>{reason.to_string().replace("_", " ")}</option>
+                            }).collect_view()}
+                        </select>
+                        <textarea node_ref=text_input maxlength=REPORT_TEXT_MAX_LEN placeholder="details" class="bg-base02 px-2 rounded-lg" />
+                        <Show when=move || err.with(|v| !v.is_empty()) >
+                            <span class="text-base08">{move || err.get()}</span>
+                        </Show>
+                        <input type="submit" value="Send report" class="px-2 border-2 border-base05 rounded-lg" />
+                    </form>
+                </Show>
+            </span>
+        }
+    }
+}
+
+pub mod user_block_btn {
+    use crate::api::shared::user_block::UserBlockKind;
+    use crate::api::{Api, ApiWeb, ServerRes};
+    use leptos::prelude::*;
+    use tracing::error;
+
+    /// block and mute toggles for a profile, picking one replaces the other
+    #[component]
+    pub fn UserBlockBtn(username: Memo<Option<String>>) -> impl IntoView {
+        let api = ApiWeb::new();
+        let current = RwSignal::new(None::<UserBlockKind>);
+
+        Effect::new(move || {
+            let Some(username) = username.get() else {
+                return;
+            };
+            api.check_user_block(username)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::UserBlockKind(kind)) => {
+                            current.set(kind);
+                        }
+                        Ok(res) => {
+                            error!("expected UserBlockKind, received {res:?}");
+                        }
+                        Err(err) => {
+                            error!("user block: {err}");
+                        }
+                    }
+                });
+        });
+
+        let on_toggle = move |kind: UserBlockKind| {
+            let Some(username) = username.get_untracked() else {
+                return;
+            };
+            let (req, new_kind) = if current.get_untracked() == Some(kind) {
+                (api.delete_user_block(username), None)
+            } else {
+                (api.add_user_block(username, kind), Some(kind))
+            };
+            req.send_web(move |result| async move {
+                match result {
+                    Ok(ServerRes::Ok) => {
+                        current.set(new_kind);
+                    }
+                    Ok(res) => {
+                        error!("expected Ok, received {res:?}");
+                    }
+                    Err(err) => {
+                        error!("user block: {err}");
+                    }
+                }
+            });
+        };
+
+        let btn_text = move |kind: UserBlockKind| match (kind, current.get() == Some(kind)) {
+            (UserBlockKind::Block, false) => "Block",
+            (UserBlockKind::Block, true) => "Unblock",
+            (UserBlockKind::Mute, false) => "Mute",
+            (UserBlockKind::Mute, true) => "Unmute",
+        };
+
+        view! {
+            <button
+                class="text-base03 hover:text-base05 disabled:hover:text-base03"
+                disabled=move || api.is_pending_tracked()
+                on:click=move |_| on_toggle(UserBlockKind::Mute)
+            >
+                {move || btn_text(UserBlockKind::Mute)}
+            </button>
+            <button
+                class="text-base03 hover:text-base08 disabled:hover:text-base03"
+                disabled=move || api.is_pending_tracked()
+                on:click=move |_| on_toggle(UserBlockKind::Block)
+            >
+                {move || btn_text(UserBlockKind::Block)}
+            </button>
+        }
+    }
+}
+
+pub mod audit_event {
+    use crate::api::shared::audit::UserAuditEvent;
+    use crate::view::app::GlobalState;
+    use crate::view::toolbox::prelude::*;
+    use leptos::prelude::*;
+
+    #[component]
+    pub fn AuditEventRow(event: UserAuditEvent) -> impl IntoView {
+        let global_state = expect_context::<GlobalState>();
+        let created_at = event.created_at;
+        let who = match (&event.actor_username, &event.target_username) {
+            (Some(actor), Some(target)) if actor != target => format!("{actor} on {target}"),
+            (Some(actor), _) => actor.clone(),
+            (None, Some(target)) => target.clone(),
+            (None, None) => String::new(),
+        };
+        let change = if event.before.is_empty() && event.after.is_empty() {
+            String::new()
+        } else {
+            format!("{} -> {}", event.before, event.after)
+        };
+        let origin = [event.ip.as_str(), event.user_agent.as_str()]
+            .into_iter()
+            .filter(|v| !v.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        view! {
+            <div class="flex flex-col rounded-xl bg-base01 p-2">
+                <div class="flex flex-wrap gap-2 items-center">
+                    <span class="text-base0E">{event.kind.label()}</span>
+                    <span class="text-base0B">{who}</span>
+                    <span class="text-base03">{move || ns_to_str(global_state.get_time_ns().saturating_sub(created_at))}" ago"</span>
+                </div>
+                <span class="text-base04 break-all">{change}</span>
+                <span class="text-base03 break-all">{origin}</span>
+            </div>
+        }
+    }
+}
+
+pub mod revision_history {
+    use crate::api::shared::revision::{
+        DiffPart, REVISIONS_FETCH_MAX, RevisionTarget, UserRevision, diff_words,
+    };
+    use crate::api::{Api, ApiWeb, ServerRes};
+    use crate::view::app::GlobalState;
+    use crate::view::toolbox::prelude::*;
+    use leptos::prelude::*;
+    use tracing::error;
+
+    /// "edited" marker that opens the edit history, revisions are fetched on first open
+    #[component]
+    pub fn RevisionHistory(
+        #[prop(into)] target: Signal<Option<RevisionTarget>>,
+        #[prop(into)] edited_at: Signal<u128, LocalStorage>,
+        /// only the author and moderators can open the history, everyone else just sees the marker
+        #[prop(into)]
+        can_view: Signal<bool>,
+    ) -> impl IntoView {
+        let global_state = expect_context::<GlobalState>();
+        let api = ApiWeb::new();
+        let open = RwSignal::new(false);
+        let revisions = RwSignal::new(Vec::<UserRevision>::new());
+        let err = RwSignal::new(String::new());
+
+        let on_toggle = move |_| {
+            let is_open = !open.get_untracked();
+            open.set(is_open);
+            let Some(target) = target.get_untracked() else {
+                return;
+            };
+            if !is_open {
+                return;
+            }
+            api.get_revisions(target, REVISIONS_FETCH_MAX)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Revisions(v)) => {
+                            err.set(String::new());
+                            revisions.set(v);
+                        }
+                        Ok(res) => {
+                            error!("expected Revisions, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        view! {
+            <Show when=move || { edited_at.get() > 0 } >
+                <span class="relative">
+                    <Show
+                        when=move || can_view.get()
+                        fallback=move || view! { <span class="text-base03">"edited"</span> }
+                    >
+                        <button class="text-base03 hover:text-base05" on:click=on_toggle>"edited"</button>
+                    </Show>
+                    <Show when=move || open.get() >
+                        <div class="absolute left-0 z-10 flex flex-col gap-2 w-[20rem] max-h-[20rem] overflow-y-auto p-2 rounded-xl bg-base01 border-2 border-base02">
+                            <Show when=move || err.with(|v| !v.is_empty()) >
+                                <span class="text-base08">{move || err.get()}</span>
+                            </Show>
+                            <For
+                                each=move || revisions.get()
+                                key=|revision| revision.key.clone()
+                                children=move |revision| {
+                                    let created_at = revision.created_at;
+                                    let diff = diff_words(&revision.before, &revision.after)
+                                        .into_iter()
+                                        .map(|part| match part {
+                                            DiffPart::Same(v) => view! { <span>{v}</span> }.into_any(),
+                                            DiffPart::Added(v) => view! { <span class="text-base0B">{v}</span> }.into_any(),
+                                            DiffPart::Removed(v) => view! { <span class="line-through text-base08">{v}</span> }.into_any(),
+                                        })
+                                        .collect_view();
+                                    view! {
+                                        <div class="flex flex-col">
+                                            <div class="flex flex-wrap gap-2 items-center">
+                                                <span class="text-base0E">{revision.field.label()}</span>
+                                                <span class="text-base0B">{revision.user.username}</span>
+                                                <span class="text-base03">{move || ns_to_str(global_state.get_time_ns().saturating_sub(created_at))}" ago"</span>
+                                            </div>
+                                            <p class="whitespace-pre-wrap break-all">{diff}</p>
+                                        </div>
+                                    }
+                                }
+                            />
+                        </div>
+                    </Show>
+                </span>
+            </Show>
+        }
+    }
+}
+
+pub mod page_meta {
+    use leptos::prelude::*;
+    use leptos_meta::{Link, Meta, Title};
+
+    use crate::api::UserPost;
+    use crate::api::shared::rating::PostRating;
+    use crate::path::{link_img_thumbnail, link_oembed, link_post, link_user};
+
+    pub const SITE_NAME: &str = "artbounty";
+    pub const META_DESCRIPTION_MAX_LENGTH: usize = 200;
+
+    /// what link previews and crawlers read, resolved on the server so it's in the first response
+    #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+    pub struct PageMeta {
+        pub title: String,
+        pub description: String,
+        /// absolute url of the processed thumbnail
+        pub image: Option<String>,
+        /// absolute url of the page
+        pub canonical: String,
+        /// og:type
+        pub kind: String,
+        /// absolute oembed discovery url, only for posts the author lets other sites embed
+        pub oembed: Option<String>,
+    }
+
+    impl PageMeta {
+        /// only general posts get a preview image, other sites don't blur
+        pub fn from_post(address: &str, post: &UserPost) -> Self {
+            let username = &post.user.username;
+            let description = match post.description.trim() {
+                "" => format!("{} by {username}", post.title),
+                description => description
+                    .chars()
+                    .take(META_DESCRIPTION_MAX_LENGTH)
+                    .collect(),
+            };
+            let image = post
+                .file
+                .first()
+                .filter(|file| file.proccesed && post.rating == PostRating::General)
+                .map(|file| format!("{address}{}", link_img_thumbnail(&file.hash)));
+            let canonical = format!("{address}{}", link_post(username, &post.key));
+            let oembed = post.embeddable.then(|| link_oembed(address, &canonical));
+            Self {
+                title: format!("{} by {username} - {SITE_NAME}", post.title),
+                description,
+                image,
+                canonical,
+                kind: "article".to_string(),
+                oembed,
+            }
+        }
+
+        pub fn from_user(address: &str, username: &str) -> Self {
+            Self {
+                title: format!("{username} - {SITE_NAME}"),
+                description: format!("Art by {username} on {SITE_NAME}"),
+                image: None,
+                canonical: format!("{address}{}", link_user(username)),
+                kind: "profile".to_string(),
+                oembed: None,
+            }
+        }
+    }
+
+    /// loads page meta and the public page data from the db while rendering on the server and through the api after hydration
+    #[derive(Clone)]
+    pub struct MetaLoader {
+        #[cfg(feature = "ssr")]
+        app_state: Option<crate::api::app_state::AppState>,
+    }
+
+    impl Default for MetaLoader {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl MetaLoader {
+        pub fn new() -> Self {
+            Self {
+                #[cfg(feature = "ssr")]
+                app_state: use_context::<crate::api::app_state::AppState>(),
+            }
+        }
+
+        /// the same posts the post page would show to anyone, drafts and trashed posts are left to the client
+        #[cfg(feature = "ssr")]
+        pub async fn public_post(self, post_key: String) -> Option<UserPost> {
+            use crate::api::shared::visibility::PostVisibility;
+
+            let app_state = self.app_state?;
+            let time = app_state.time().await;
+            let post = app_state.db.get_post(post_key).await.ok()?;
+            let visible = post.show
+                && post.publish_at <= time
+                && PostVisibility::from_db(&post.visibility).is_published()
+                && !post.user.banned
+                && post.user.erased_at == 0;
+            if !visible {
+                return None;
+            }
+            Some(UserPost::from(post))
+        }
+
+        #[cfg(not(feature = "ssr"))]
+        pub async fn public_post(self, post_key: String) -> Option<UserPost> {
+            use crate::api::{Api, ApiWebTmp, ServerRes};
+
+            send_wrapper::SendWrapper::new(async move {
+                match ApiWebTmp::new().get_post(post_key).send_native().await {
+                    Ok(ServerRes::Post(post)) => Some(post),
+                    _ => None,
+                }
+            })
+            .await
+        }
+
+        /// drafts and trashed posts have no meta
+        pub async fn post(self, post_key: String) -> Option<PageMeta> {
+            let address = self.address().await?;
+            let post = self.public_post(post_key).await?;
+            Some(PageMeta::from_post(&address, &post))
+        }
+
+        /// username as the profile page shows it
+        #[cfg(feature = "ssr")]
+        pub async fn profile(self, username: String) -> Option<String> {
+            let app_state = self.app_state?;
+            let user = app_state.db.get_user_by_username(username).await.ok()?;
+            Some(user.username)
+        }
+
+        #[cfg(not(feature = "ssr"))]
+        pub async fn profile(self, username: String) -> Option<String> {
+            use crate::api::{Api, ApiWebTmp, ServerRes};
+
+            send_wrapper::SendWrapper::new(async move {
+                match ApiWebTmp::new().get_user(username).send_native().await {
+                    Ok(ServerRes::User { username }) => Some(username),
+                    _ => None,
+                }
+            })
+            .await
+        }
+
+        #[cfg(feature = "ssr")]
+        pub async fn user(self, username: String) -> Option<PageMeta> {
+            let app_state = self.app_state?;
+            let user = app_state.db.get_user_by_username(username).await.ok()?;
+            if user.banned || user.erased_at != 0 {
+                return None;
+            }
+            let address = app_state.get_address().await;
+            Some(PageMeta::from_user(&address, &user.username))
+        }
+
+        #[cfg(not(feature = "ssr"))]
+        pub async fn user(self, username: String) -> Option<PageMeta> {
+            let address = self.address().await?;
+            let username = self.profile(username).await?;
+            Some(PageMeta::from_user(&address, &username))
+        }
+
+        #[cfg(feature = "ssr")]
+        async fn address(&self) -> Option<String> {
+            Some(self.app_state.as_ref()?.get_address().await)
+        }
+
+        #[cfg(not(feature = "ssr"))]
+        async fn address(&self) -> Option<String> {
+            location().origin().ok()
+        }
+    }
+
+    /// title, description, canonical link and the og/twitter cards, meant for a blocking resource
+    #[component]
+    pub fn PageMetaTags(meta: Resource<Option<PageMeta>>) -> impl IntoView {
+        view! {
+            <Suspense>
+                {move || Suspend::new(async move {
+                    meta.await.map(|meta| {
+                        let card = if meta.image.is_some() { "summary_large_image" } else { "summary" };
+                        view! {
+                            <Title text=meta.title.clone() />
+                            <Meta name="description" content=meta.description.clone() />
+                            <Link rel="canonical" href=meta.canonical.clone() />
+                            <Meta property="og:site_name" content=SITE_NAME />
+                            <Meta property="og:type" content=meta.kind.clone() />
+                            <Meta property="og:title" content=meta.title.clone() />
+                            <Meta property="og:description" content=meta.description.clone() />
+                            <Meta property="og:url" content=meta.canonical.clone() />
+                            <Meta name="twitter:card" content=card />
+                            <Meta name="twitter:title" content=meta.title.clone() />
+                            <Meta name="twitter:description" content=meta.description.clone() />
+                            {meta.image.map(|image| view! {
+                                <Meta property="og:image" content=image.clone() />
+                                <Meta name="twitter:image" content=image />
+                            })}
+                            {meta.oembed.map(|oembed| view! {
+                                <Link rel="alternate" type_="application/json+oembed" href=oembed title=meta.title.clone() />
+                            })}
+                        }
+                    })
+                })}
+            </Suspense>
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::api::shared::rating::PostRating;
+        use crate::api::shared::visibility::PostVisibility;
+        use crate::api::{User, UserPost, UserPostFile};
+
+        use super::PageMeta;
+
+        #[test]
+        fn page_meta_from_post() {
+            let mut post = UserPost {
+                key: "p1".to_string(),
+                user: User {
+                    key: "u1".to_string(),
+                    username: "hey".to_string(),
+                    used_storage_bytes: 0,
+                    max_storage_bytes: 0,
+                    max_storage_per_file_bytes: 0,
+                    created_at: 0,
+                },
+                show: true,
+                title: "cats".to_string(),
+                description: String::new(),
+                tags: "cat".to_string(),
+                rating: PostRating::General,
+                rating_locked: false,
+                visibility: PostVisibility::Public,
+                publish_at: 0,
+                embeddable: true,
+                favorites: 0,
+                file: vec![UserPostFile {
+                    extension: "png".to_string(),
+                    hash: "A".to_string(),
+                    proccesed: true,
+                    size_bytes: 1,
+                    width: 1,
+                    height: 1,
+                }],
+                edited_at: 0,
+                deleted_at: 0,
+                modified_at: 0,
+                created_at: 0,
+            };
+
+            let meta = PageMeta::from_post("http://localhost", &post);
+            assert_eq!(meta.title, "cats by hey - artbounty");
+            assert_eq!(meta.description, "cats by hey");
+            assert_eq!(meta.canonical, "http://localhost/u/hey/p1");
+            assert_eq!(
+                meta.oembed.as_deref(),
+                Some(
+                    "http://localhost/oembed?url=http%3A%2F%2Flocalhost%2Fu%2Fhey%2Fp1&format=json"
+                )
+            );
+            assert_eq!(
+                meta.image.as_deref(),
+                Some("http://localhost/file/A_thumbnail_default.webp")
+            );
+
+            post.description = "a".repeat(300);
+            post.rating = PostRating::Explicit;
+            let meta = PageMeta::from_post("http://localhost", &post);
+            assert_eq!(meta.description.len(), 200);
+            assert_eq!(meta.image, None);
+
+            post.embeddable = false;
+            let meta = PageMeta::from_post("http://localhost", &post);
+            assert_eq!(meta.oembed, None);
+        }
+    }
+}
+
 pub mod nav {
 
     use crate::path::{link_home, link_home_search, link_post};
     use crate::{
-        api::{Api, ApiWeb},
-        path::{PATH_LOGIN, PATH_UPLOAD, link_settings, link_user},
-        view::{app::GlobalState, toolbox::prelude::*},
+        api::{
+            Api, ApiWeb,
+            shared::{rating::PostRating, visibility::PostVisibility},
+        },
+        path::{
+            PATH_LOGIN, PATH_UPLOAD, link_admin, link_messages, link_notifications, link_settings,
+            link_user,
+        },
+        view::{
+            app::{
+                GlobalState,
+                hook::{
+                    use_conversation_count::use_conversation_count,
+                    use_notification_count::use_notification_count,
+                },
+            },
+            toolbox::prelude::*,
+        },
     };
     use leptos::{html, prelude::*};
     use leptos_router::hooks::query_signal;
     use log::error;
+    use strum::IntoEnumIterator;
     use tracing::trace;
     use web_sys::{HtmlDivElement, HtmlInputElement, KeyboardEvent, SubmitEvent};
 
@@ -343,7 +939,10 @@ pub mod nav {
         let navigate = leptos_router::hooks::use_navigate();
         // let search_ref = NodeRef::new();
         let api_upload = ApiWeb::new();
+        let api_rating = ApiWeb::new();
         let api = ApiWeb::new();
+        let notification_count = use_notification_count();
+        let conversation_count = use_conversation_count();
         let logout_or_loading = move || {
             if api.is_pending_tracked() {
                 "loading..."
@@ -364,7 +963,8 @@ pub mod nav {
                 let navigate = navigate.clone();
 
                 api_upload
-                    .add_post("", "", "")
+                    // new uploads start as drafts, the owner publishes them from the post page
+                    .add_post("", "", "", PostRating::General, PostVisibility::Draft, 0)
                     .send_web(async move |result| {
                         match result {
                             Ok(crate::api::ServerRes::Post(post)) => {
@@ -391,6 +991,26 @@ pub mod nav {
             global_state.logout();
         };
 
+        // logged out visitors only get the max rating, it's kept in a cookie
+        let on_max_rating = move |e: web_sys::Event| {
+            let max_rating = PostRating::from_db(event_target_value(&e));
+            api_rating
+                .update_rating_preference(max_rating, true)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(crate::api::ServerRes::Ok) => {
+                            global_state.rating.update(|v| v.max_rating = max_rating);
+                        }
+                        Ok(res) => {
+                            error!("expected Ok, received {res:?}");
+                        }
+                        Err(err) => {
+                            error!("{err}");
+                        }
+                    }
+                });
+        };
+
         // let search_fn = move || {
         //     // e.prevent_default();
         //     trace!("search_fn running");
@@ -493,7 +1113,16 @@ pub mod nav {
                 <div class=move||format!("{}", if global_state.acc_pending() { "" } else { "hidden" })>
                     <p>"loading..."</p>
                 </div>
-                <div class=move||format!("{}", if global_state.is_logged_in().unwrap_or_default() || global_state.acc_pending() { "hidden" } else { "" })>
+                <div class=move||format!("flex gap-2 {}", if global_state.is_logged_in().unwrap_or_default() || global_state.acc_pending() { "hidden" } else { "" })>
+                    <select
+                        class="bg-base01 px-2 rounded"
+                        prop:value=move || global_state.rating.get().max_rating.to_string()
+                        on:change=on_max_rating
+                    >
+                        {PostRating::iter().map(|rating| view! {
+                            <option value=rating.to_string()>{rating.label()}</option>
+                        }).collect_view()}
+                    </select>
                     <a href=PATH_LOGIN>"Login"</a>
                 </div>
                 <div class=move||format!("flex gap-2 {}", if global_state.is_logged_in().unwrap_or_default() { "" } else { "hidden" })>
@@ -502,6 +1131,19 @@ pub mod nav {
                         <input type="submit" value="Upload" class="transition-all duration-300 ease-in hover:font-bold"/>
                     </form>
                     <a href=move|| link_user(acc_username())>{acc_username}</a>
+                    <a href=move|| link_notifications() class="flex gap-1 items-center">
+                        "Notifications"
+                        <span class=move||format!("rounded-full bg-base08 text-base00 text-[0.8rem] px-[0.4rem] {}", if notification_count.unread.get() > 0 { "" } else { "hidden" })>
+                            {move || notification_count.unread.get()}
+                        </span>
+                    </a>
+                    <a href=move|| link_messages() class="flex gap-1 items-center">
+                        "Messages"
+                        <span class=move||format!("rounded-full bg-base08 text-base00 text-[0.8rem] px-[0.4rem] {}", if conversation_count.unread.get() > 0 { "" } else { "hidden" })>
+                            {move || conversation_count.unread.get()}
+                        </span>
+                    </a>
+                    <a href=move|| link_admin() class=move||format!("{}", if global_state.get_role_tracked().is_staff() { "" } else { "hidden" })>"Admin"</a>
                     <a href=move|| link_settings()>"Settings"</a>
                     <form method="POST" action="" on:submit=on_logout >
                         <input type="submit" value=logout_or_loading class="transition-all duration-300 ease-in hover:font-bold"/>
@@ -513,9 +1155,12 @@ pub mod nav {
 }
 pub mod gallery {
 
+    use crate::api::shared::rating::PostRating;
     use crate::api::{Api, ApiWeb, UserPost, UserPostFile};
     use crate::path::{link_img, link_post, link_post_with_history};
+    use crate::valid::MAX_POST_RELATED_LIMIT;
     // use crate::view::{KILLME, KILLME2};
+    use crate::view::app::GlobalState;
     use crate::view::app::hook::api_gallery::{GalleryApi, GalleryContainerSize};
     use crate::view::app::hook::use_event_listener::EventListener;
     use crate::view::app::hook::use_intersection::Intersection;
@@ -590,7 +1235,11 @@ pub mod gallery {
     pub fn Gallery(
         #[prop(default = 250)] row_height: u32,
         #[prop(optional)] username: Option<RwSignal<Option<String>>>,
+        #[prop(optional)] related_to: Option<Memo<Option<String>>>,
+        #[prop(optional)] collection: Option<Memo<Option<String>>>,
     ) -> impl IntoView {
+        // related posts and collections are not paged by time, they only load downwards
+        let keyed = related_to.or(collection);
         let api_top = ApiWeb::new();
         let api_btm = ApiWeb::new();
         let spawner = Spawner::new();
@@ -640,6 +1289,45 @@ pub mod gallery {
             };
 
             debug_data_push("set_gallery_param_limit", limit.to_string());
+
+            if let Some(collection) = collection {
+                let Some(collection_key) = collection.get_untracked() else {
+                    return;
+                };
+                if !bottom {
+                    return;
+                }
+                let size = GalleryContainerSize {
+                    width,
+                    height,
+                    row_height,
+                };
+                spawner.spawn(async move {
+                    gallery_api
+                        .fetch_collection(limit, size, collection_key)
+                        .await;
+                });
+                return;
+            }
+            if let Some(related_to) = related_to {
+                let Some(post_key) = related_to.get_untracked() else {
+                    return;
+                };
+                // related posts are ranked, not paged by time, so they are fetched only once
+                if !bottom || !gallery_api.is_empty() {
+                    return;
+                }
+                let size = GalleryContainerSize {
+                    width,
+                    height,
+                    row_height,
+                };
+                let limit = limit.min(MAX_POST_RELATED_LIMIT);
+                spawner.spawn(async move {
+                    gallery_api.fetch_related(limit, size, post_key).await;
+                });
+                return;
+            }
             // let width = gallery_elm.client_width() as u32;
             // let height = gallery_elm.client_height() as f64 * 2.0;
             // TODO img count should never be zero
@@ -698,6 +1386,9 @@ pub mod gallery {
         };
 
         let set_scroll_top = move |gallery_elm: &HtmlDivElement| {
+            if keyed.is_some() {
+                return;
+            }
             let scroll_top = gallery_elm.scroll_top();
             let id = gallery_elm.id();
             trace!("scroll top of {} {}", id, scroll_top);
@@ -822,6 +1513,9 @@ pub mod gallery {
 
         Effect::new(move || {
             trace!("running gallery init");
+            if keyed.is_some() {
+                return;
+            }
 
             let Some(gallery_elm) = gallery_ref.get() else {
                 return;
@@ -851,6 +1545,9 @@ pub mod gallery {
         });
 
         Effect::new(move || {
+            if keyed.is_some() {
+                return;
+            }
             let Some(gallery_elm) = gallery_ref.get() else {
                 return;
             };
@@ -899,6 +1596,28 @@ pub mod gallery {
             // set_gallery(true);
         });
 
+        Effect::new(move || {
+            let Some(keyed) = keyed else {
+                return;
+            };
+            let (Some(gallery_elm), Some(_key)) = (gallery_ref.get(), keyed.get()) else {
+                return;
+            };
+            trace!("running gallery related reset");
+
+            gallery_api.reset();
+            scroll_correction.reset();
+            top_intersector_switch.reset();
+            down_intersector_switch.reset();
+
+            let width = gallery_elm.client_width() as u32;
+            let height = gallery_elm.client_height() as f64 * 2.0;
+            let limit = calc_fit_count(width, height, row_height);
+            let time = time_now_ns();
+
+            set_gallery(width, height, true, limit, time);
+        });
+
         gallery_ref.add_mutation_observer(
             move |entries, observer| {
                 debug_data_push("gallery_mutated", "true");
@@ -1042,6 +1761,21 @@ pub mod gallery {
         let post_link = img.get_post_link();
         // let post_link_with_history = img.get_post_link_with_history(9999);
         let img_link = img.get_img_link();
+        let img_rating = img.rating;
+
+        // anything above general stays blurred until clicked, the first click only reveals
+        let global_state = expect_context::<GlobalState>();
+        let revealed = RwSignal::new(false);
+        let is_blurred = move || {
+            !img_rating.is_general() && global_state.rating.with(|v| v.blur) && !revealed.get()
+        };
+        let on_click = move |e: MouseEvent| {
+            if !is_blurred() {
+                return;
+            }
+            e.prevent_default();
+            revealed.set(true);
+        };
 
         let value_left = format!("{view_left}px");
         let value_top = format!("{view_top}px");
@@ -1061,19 +1795,26 @@ pub mod gallery {
             <a
                id=elm_id_img_link(img_key)
                href=post_link
-               class="absolute"
+               class="absolute overflow-hidden"
                style:left=value_left
                style:top=value_top
                style:width=value_width
                style:height=value_height
+               on:click=on_click
             >
                 <img
                     id=elm_id_img_thumbnail(img_key2)
+                    class=move || if is_blurred() { "blur-xl" } else { "" }
                     style:width=value_width2
                     style:height=value_height2
                     // node_ref=img_ref
                     src=img_link
                 />
+                <Show when=is_blurred >
+                    <div class="absolute inset-0 grid place-items-center text-base05 text-[0.9rem]">
+                        {format!("{} - click to reveal", img_rating.label())}
+                    </div>
+                </Show>
             </a>
         }
     }
@@ -1090,6 +1831,7 @@ pub mod gallery {
         pub view_height: f64,
         pub view_pos_x: f64,
         pub view_pos_y: f64,
+        pub rating: PostRating,
         #[serde(serialize_with = "from_u128_custom")]
         pub created_at: u128,
     }
@@ -1120,6 +1862,7 @@ pub mod gallery {
                 view_height: 0.0,
                 view_pos_x: 0.0,
                 view_pos_y: 0.0,
+                rating: user_post.rating,
                 created_at: user_post.created_at,
             }
         }
@@ -1203,6 +1946,7 @@ pub mod gallery {
                 view_height: 0.0,
                 view_pos_x: 0.0,
                 view_pos_y: 0.0,
+                rating: PostRating::General,
                 created_at: 0,
             }
         }
@@ -1222,6 +1966,7 @@ pub mod gallery {
                 view_height: 0.0,
                 view_pos_x: 0.0,
                 view_pos_y: 0.0,
+                rating: PostRating::General,
                 created_at: 0,
             }
         }
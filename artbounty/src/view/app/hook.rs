@@ -3,17 +3,22 @@ pub mod api_post;
 pub mod api_post_comments;
 pub mod api_post_file_upload;
 pub mod api_post_like;
+pub mod use_conversation_count;
 pub mod use_email_change;
 pub mod use_event_listener;
 pub mod use_flag;
+pub mod use_following_feed;
 pub mod use_future;
 pub mod use_infinite_scroll_basic;
 pub mod use_infinite_scroll_fn;
 pub mod use_infinite_scroll_virtual;
 pub mod use_intersection;
 pub mod use_intersection_switch;
+pub mod use_live_post;
 pub mod use_mutation;
+pub mod use_notification_count;
 pub mod use_password_change;
+pub mod use_post_collections;
 pub mod use_post_comment;
 pub mod use_post_comments_baisc;
 pub mod use_post_like;
@@ -21,4 +26,5 @@ pub mod use_register;
 pub mod use_scroll_correction;
 pub mod use_spawner;
 pub mod use_text_length_counter;
+pub mod use_user_follow;
 pub mod use_username_change;
@@ -1,7 +1,12 @@
 use crate::{
     api::{
-        Api, ApiWeb, Order, ServerErr, ServerReqImg, ServerRes, TimeRange, UserPost,
-        shared::post_comment::UserPostComment,
+        Api, ApiWeb, Order, ServerErr, ServerReqImg, ServerRes, UserPost,
+        shared::{
+            cursor::{Cursor, CursorRange},
+            post_comment::UserPostComment,
+            rating::PostRating,
+            visibility::PostVisibility,
+        },
     },
     view::{
         app::{
@@ -72,7 +77,14 @@ impl<API: Api> GalleryApi<API> {
 
         let result = self
             .api_top
-            .add_post(title, description, tags)
+            .add_post(
+                title,
+                description,
+                tags,
+                PostRating::General,
+                PostVisibility::Public,
+                0,
+            )
             .send_native()
             .await;
 
@@ -111,7 +123,7 @@ impl<API: Api> GalleryApi<API> {
         self,
         limit: usize,
         size: GalleryContainerSize,
-        time_range: TimeRange,
+        cursor: CursorRange,
         order: Order,
         reverse: bool,
         tags: impl Into<String>,
@@ -122,13 +134,7 @@ impl<API: Api> GalleryApi<API> {
         let items = self.items;
         let scroll_correction = self.scroll_correction_handle;
 
-        let is_bottom = match time_range {
-            TimeRange::None => true,
-            TimeRange::Less(_) => true,
-            TimeRange::LessOrEqual(_) => true,
-            TimeRange::More(_) => false,
-            TimeRange::MoreOrEqual(_) => false,
-        };
+        let is_bottom = !cursor.is_after();
 
         let api = if is_bottom {
             &self.api_top
@@ -137,12 +143,12 @@ impl<API: Api> GalleryApi<API> {
         };
 
         let result = api
-            .get_posts(limit, time_range, order, tags, username)
+            .get_posts(limit, cursor, order, tags, username)
             .send_native()
             .await;
 
         match result {
-            Ok(ServerRes::Posts(mut posts)) => {
+            Ok(ServerRes::PostPage { mut posts, .. }) => {
                 if reverse {
                     posts.reverse();
                 }
@@ -183,16 +189,15 @@ impl<API: Api> GalleryApi<API> {
         tags: impl Into<String>,
         username: impl Into<String>,
     ) -> f64 {
-        let time_range = self
+        let cursor = self
             .items
-            .with_untracked(|v| v.last().map(|v| v.created_at))
-            .map(TimeRange::Less)
-            .unwrap_or(TimeRange::LessOrEqual(current_time));
+            .with_untracked(|v| v.last().map(|v| Cursor::new(v.created_at, v.key.clone())))
+            .unwrap_or(Cursor::at(current_time + 1));
 
         self.fetch(
             limit,
             size,
-            time_range,
+            CursorRange::Before(cursor),
             Order::ThreeTwoOne,
             false,
             tags,
@@ -209,17 +214,16 @@ impl<API: Api> GalleryApi<API> {
         tags: impl Into<String>,
         username: impl Into<String>,
     ) -> f64 {
-        let time_range = self
+        let cursor = self
             .items
-            .with_untracked(|v| v.first().map(|v| v.created_at))
-            .map(TimeRange::More)
-            .unwrap_or(TimeRange::MoreOrEqual(current_time));
-        debug!("time range picked: {time_range:?}");
+            .with_untracked(|v| v.first().map(|v| Cursor::new(v.created_at, v.key.clone())))
+            .unwrap_or(Cursor::at(current_time));
+        debug!("cursor picked: {cursor:?}");
 
         self.fetch(
             limit,
             size,
-            time_range,
+            CursorRange::After(cursor),
             Order::OneTwoThree,
             true,
             tags,
@@ -246,6 +250,101 @@ impl<API: Api> GalleryApi<API> {
         }
     }
 
+    pub async fn fetch_related(
+        self,
+        limit: usize,
+        size: GalleryContainerSize,
+        post_key: impl Into<String>,
+    ) -> f64 {
+        let items = self.items;
+        let scroll_correction = self.scroll_correction_handle;
+
+        let result = self
+            .api_btm
+            .get_posts_related(post_key, limit)
+            .send_native()
+            .await;
+
+        match result {
+            Ok(ServerRes::Posts(posts)) => {
+                let new_imgs = posts.into_iter().map(Img::from).collect::<Vec<Img>>();
+                let old_imgs = items.get_untracked();
+
+                let (resized_imgs, scroll_by) = add_imgs_to_bottom(
+                    old_imgs,
+                    new_imgs,
+                    size.width,
+                    size.height,
+                    size.row_height,
+                );
+                scroll_correction.update();
+                items.set(resized_imgs);
+
+                return scroll_by;
+            }
+            Ok(err) => {
+                let err = format!("gallery related: unexpected res: {err:?}");
+                error!(err);
+            }
+            Err(err) => {
+                let err = format!("gallery related: {err}");
+                error!(err);
+            }
+        };
+
+        0.0
+    }
+
+    /// collections are ordered by the owner, so they are paged by offset instead of time
+    pub async fn fetch_collection(
+        self,
+        limit: usize,
+        size: GalleryContainerSize,
+        collection_key: impl Into<String>,
+    ) -> f64 {
+        let items = self.items;
+        let scroll_correction = self.scroll_correction_handle;
+        let offset = items.with_untracked(|v| v.len());
+
+        let result = self
+            .api_btm
+            .get_collection_posts(collection_key, limit, offset)
+            .send_native()
+            .await;
+
+        match result {
+            Ok(ServerRes::Posts(posts)) => {
+                if posts.is_empty() {
+                    return 0.0;
+                }
+                let new_imgs = posts.into_iter().map(Img::from).collect::<Vec<Img>>();
+                let old_imgs = items.get_untracked();
+
+                let (resized_imgs, scroll_by) = add_imgs_to_bottom(
+                    old_imgs,
+                    new_imgs,
+                    size.width,
+                    size.height,
+                    size.row_height,
+                );
+                scroll_correction.update();
+                items.set(resized_imgs);
+
+                return scroll_by;
+            }
+            Ok(err) => {
+                let err = format!("gallery collection: unexpected res: {err:?}");
+                error!(err);
+            }
+            Err(err) => {
+                let err = format!("gallery collection: {err}");
+                error!(err);
+            }
+        };
+
+        0.0
+    }
+
     pub fn is_empty(&self) -> bool {
         self.items.with_untracked(|v| v.is_empty())
     }
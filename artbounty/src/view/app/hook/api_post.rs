@@ -1,7 +1,13 @@
 use leptos::prelude::*;
 
 use crate::{
-    api::{Api, Server404Err, ServerErr, ServerUpdatePostDescriptionErr},
+    api::{
+        Api, Server404Err, ServerErr, ServerUpdatePostDescriptionErr, UserPost,
+        shared::{
+            rating::{PostRating, PostRatingErr},
+            visibility::PostVisibility,
+        },
+    },
     path::{link_home, link_img, link_user},
 };
 use tracing::{error, info, trace, warn};
@@ -30,6 +36,13 @@ pub struct PostApi<API: Api> {
     pub description: RwSignal<String, LocalStorage>,
     // pub description_is_empty: RwSignal<bool, LocalStorage>,
     pub favorites: RwSignal<u64, LocalStorage>,
+    pub show: RwSignal<bool, LocalStorage>,
+    pub rating: RwSignal<PostRating, LocalStorage>,
+    pub rating_locked: RwSignal<bool, LocalStorage>,
+    pub visibility: RwSignal<PostVisibility, LocalStorage>,
+    pub publish_at: RwSignal<u128, LocalStorage>,
+    pub embeddable: RwSignal<bool, LocalStorage>,
+    pub edited_at: RwSignal<u128, LocalStorage>,
     pub post_state: RwSignal<PostState, LocalStorage>,
 
     pub api: API,
@@ -77,6 +90,13 @@ impl<API: Api> PostApi<API> {
             description: RwSignal::new_local(String::new()),
             // description_is_empty: RwSignal::new_local(true),
             favorites: RwSignal::new_local(0_u64),
+            show: RwSignal::new_local(true),
+            rating: RwSignal::new_local(PostRating::General),
+            rating_locked: RwSignal::new_local(false),
+            visibility: RwSignal::new_local(PostVisibility::Public),
+            publish_at: RwSignal::new_local(0),
+            embeddable: RwSignal::new_local(true),
+            edited_at: RwSignal::new_local(0),
             post_state: RwSignal::new_local(PostState::Loading),
             api,
         }
@@ -99,6 +119,7 @@ impl<API: Api> PostApi<API> {
             Ok(crate::api::ServerRes::Post(v)) => {
                 self.live_description_length.set(v.description.len());
                 self.description.set(v.description);
+                self.edited_at.set(v.edited_at);
                 self.update_description_mode.set(false);
                 return Some(());
             }
@@ -143,6 +164,7 @@ impl<API: Api> PostApi<API> {
             Ok(crate::api::ServerRes::Post(v)) => {
                 self.live_title_length.set(v.title.len());
                 self.title.set(v.title);
+                self.edited_at.set(v.edited_at);
                 self.update_title_mode.set(false);
                 return Some(());
             }
@@ -182,6 +204,7 @@ impl<API: Api> PostApi<API> {
             Ok(crate::api::ServerRes::Post(v)) => {
                 self.live_tags_length.set(v.tags.len());
                 self.tags.set(v.tags);
+                self.edited_at.set(v.edited_at);
                 self.update_tags_mode.set(false);
                 return Some(());
             }
@@ -227,6 +250,153 @@ impl<API: Api> PostApi<API> {
         None
     }
 
+    pub async fn update_show(self, post_key: impl Into<String>, show: bool) -> Option<()> {
+        let result = self
+            .api
+            .admin_update_post_show(post_key, show)
+            .send_native()
+            .await;
+
+        match result {
+            Ok(crate::api::ServerRes::Post(v)) => {
+                self.show.set(v.show);
+                return Some(());
+            }
+            Ok(res) => {
+                let err = format!("wrong res, expected Post, got {:?}", res);
+                error!(err);
+                self.err_general.set(err);
+            }
+            Err(err) => {
+                let err = format!("unexpected err {:#?}", { err });
+                error!(err);
+                self.err_general.set(err);
+            }
+        }
+
+        None
+    }
+
+    /// moderators go through the override which locks the rating for the author
+    pub async fn update_rating(
+        self,
+        post_key: impl Into<String>,
+        rating: PostRating,
+        moderation: bool,
+    ) -> Option<()> {
+        self.err_general.update(|v| v.clear());
+
+        let req = if moderation {
+            self.api.admin_update_post_rating(post_key, rating)
+        } else {
+            self.api.update_post_rating(post_key, rating)
+        };
+        let result = req.send_native().await;
+
+        match result {
+            Ok(crate::api::ServerRes::Post(v)) => {
+                self.rating.set(v.rating);
+                self.rating_locked.set(v.rating_locked);
+                return Some(());
+            }
+            Ok(res) => {
+                let err = format!("wrong res, expected Post, got {:?}", res);
+                error!(err);
+                self.err_general.set(err);
+            }
+            Err(ServerErr::PostRatingErr(PostRatingErr::Locked)) => {
+                self.rating_locked.set(true);
+                self.err_general
+                    .set("rating was locked by a moderator".to_string());
+            }
+            Err(ServerErr::PostRatingErr(PostRatingErr::NotFound)) => {
+                self.post_state.set(PostState::NotFound);
+                self.err_general.set("post not found".to_string());
+            }
+            Err(err) => {
+                let err = format!("unexpected err {:#?}", { err });
+                error!(err);
+                self.err_general.set(err);
+            }
+        }
+
+        None
+    }
+
+    pub async fn update_visibility(
+        self,
+        post_key: impl Into<String>,
+        visibility: PostVisibility,
+        publish_at: u128,
+    ) -> Option<()> {
+        self.err_general.update(|v| v.clear());
+
+        let result = self
+            .api
+            .update_post_visibility(post_key, visibility, publish_at)
+            .send_native()
+            .await;
+
+        match result {
+            Ok(crate::api::ServerRes::Post(v)) => {
+                self.visibility.set(v.visibility);
+                self.publish_at.set(v.publish_at);
+                return Some(());
+            }
+            Ok(res) => {
+                let err = format!("wrong res, expected Post, got {:?}", res);
+                error!(err);
+                self.err_general.set(err);
+            }
+            Err(ServerErr::PostVisibilityErr(err)) => {
+                self.err_general.set(err.to_string());
+            }
+            Err(err) => {
+                let err = format!("unexpected err {:#?}", { err });
+                error!(err);
+                self.err_general.set(err);
+            }
+        }
+
+        None
+    }
+
+    pub async fn update_embeddable(
+        self,
+        post_key: impl Into<String>,
+        embeddable: bool,
+    ) -> Option<()> {
+        self.err_general.update(|v| v.clear());
+
+        let result = self
+            .api
+            .update_post_embeddable(post_key, embeddable)
+            .send_native()
+            .await;
+
+        match result {
+            Ok(crate::api::ServerRes::Post(v)) => {
+                self.embeddable.set(v.embeddable);
+                return Some(());
+            }
+            Ok(res) => {
+                let err = format!("wrong res, expected Post, got {:?}", res);
+                error!(err);
+                self.err_general.set(err);
+            }
+            Err(ServerErr::PostVisibilityErr(err)) => {
+                self.err_general.set(err.to_string());
+            }
+            Err(err) => {
+                let err = format!("unexpected err {:#?}", { err });
+                error!(err);
+                self.err_general.set(err);
+            }
+        }
+
+        None
+    }
+
     pub async fn get(self, post_id: impl Into<String>) {
         let post_id = post_id.into();
         // let (Some(username), Some(post_id)) = (param_username(), param_post.get()) else {
@@ -236,35 +406,7 @@ impl<API: Api> PostApi<API> {
         let result = self.api.get_post(post_id).send_native().await;
         match result {
             Ok(crate::api::ServerRes::Post(post)) => {
-                self.live_title_length.set(post.title.len());
-                self.title.set(post.title);
-                self.author.set(post.user.username.clone());
-                self.author_link.set(link_user(post.user.username));
-                self.live_tags_length.set(post.tags.len());
-                self.tags.set(post.tags);
-                self.live_description_length.set(post.description.len());
-                self.description.set(post.description);
-                // if post.description.is_empty() {
-                //     self.description.set("No description.".to_string());
-                //     // self.description_is_empty.set(true);
-                // } else {
-                //     self.description.set(post.description);
-                //     // self.description_is_empty.set(false);
-                // }
-
-                self.favorites.set(post.favorites);
-                self.imgs_links.set(
-                    post.file
-                        .into_iter()
-                        .map(|file| {
-                            (
-                                link_img(file.hash, file.extension),
-                                file.width as f64 / file.height as f64,
-                            )
-                        })
-                        .collect(),
-                );
-                self.post_state.set(PostState::Normal);
+                self.set_post(post);
             }
             Ok(res) => {
                 let err = format!("wrong res, expected Post, got {:?}", res);
@@ -282,6 +424,46 @@ impl<API: Api> PostApi<API> {
             }
         }
     }
+
+    /// fills the page from a loaded post, used by the server render too
+    pub fn set_post(self, post: UserPost) {
+        self.live_title_length.set(post.title.len());
+        self.title.set(post.title);
+        self.author.set(post.user.username.clone());
+        self.author_link.set(link_user(post.user.username));
+        self.live_tags_length.set(post.tags.len());
+        self.tags.set(post.tags);
+        self.live_description_length.set(post.description.len());
+        self.description.set(post.description);
+        // if post.description.is_empty() {
+        //     self.description.set("No description.".to_string());
+        //     // self.description_is_empty.set(true);
+        // } else {
+        //     self.description.set(post.description);
+        //     // self.description_is_empty.set(false);
+        // }
+
+        self.favorites.set(post.favorites);
+        self.show.set(post.show);
+        self.rating.set(post.rating);
+        self.rating_locked.set(post.rating_locked);
+        self.visibility.set(post.visibility);
+        self.publish_at.set(post.publish_at);
+        self.embeddable.set(post.embeddable);
+        self.edited_at.set(post.edited_at);
+        self.imgs_links.set(
+            post.file
+                .into_iter()
+                .map(|file| {
+                    (
+                        link_img(file.hash, file.extension),
+                        file.width as f64 / file.height as f64,
+                    )
+                })
+                .collect(),
+        );
+        self.post_state.set(PostState::Normal);
+    }
 }
 
 #[cfg(test)]
@@ -540,7 +722,8 @@ pub mod tests {
         assert!(result.is_some());
 
         let post_all = app.state.db.get_post_all().await.unwrap();
-        assert_eq!(post_all.len(), 0);
+        assert_eq!(post_all.len(), 1);
+        assert!(post_all[0].deleted_at > 0);
     }
 
     #[tokio::test]
@@ -1,6 +1,11 @@
 use crate::{
     api::{
-        Api, ApiWeb, Order, ServerErr, ServerRes, TimeRange, shared::post_comment::UserPostComment,
+        Api, ApiWeb, Order, ServerErr, ServerRes,
+        shared::{
+            cursor::{Cursor, CursorRange},
+            live::LiveEvent,
+            post_comment::UserPostComment,
+        },
     },
     view::{
         app::hook::{
@@ -64,6 +69,8 @@ pub struct CommentsApi2<API: Api> {
     pub finished: RwSignal<bool, LocalStorage>,
     pub replies_count: RwSignal<usize, LocalStorage>,
     pub text: RwSignal<String, LocalStorage>,
+    pub edited_at: RwSignal<u128, LocalStorage>,
+    pub deleted_at: RwSignal<u128, LocalStorage>,
     pub show_editor: RwSignal<bool, LocalStorage>,
     pub edit_mode: RwSignal<bool, LocalStorage>,
     // pub is_last: RwSignal<bool, LocalStorage>,
@@ -87,8 +94,8 @@ where
     API: Api,
 {
     pub fn new(api: API, fetch_count: usize, kind: CommentKind2) -> Self {
-        let (replies_count, text) = match &kind {
-            CommentKind2::Root => (0, String::new()),
+        let (replies_count, text, edited_at, deleted_at) = match &kind {
+            CommentKind2::Root => (0, String::new(), 0, 0),
             CommentKind2::Flat {
                 comment,
                 parent_key,
@@ -103,7 +110,12 @@ where
                 comment,
                 parent_key,
                 ..
-            } => (comment.replies_count, comment.text.clone()),
+            } => (
+                comment.replies_count,
+                comment.text.clone(),
+                comment.edited_at,
+                comment.deleted_at,
+            ),
         };
 
         // let has_reply_bubble = kind.is_none() && com;
@@ -113,6 +125,8 @@ where
             finished: RwSignal::new_local(false),
             replies_count: RwSignal::new_local(replies_count),
             text: RwSignal::new_local(text),
+            edited_at: RwSignal::new_local(edited_at),
+            deleted_at: RwSignal::new_local(deleted_at),
             // has_reply_bubble,
             // is_last: RwSignal::new_local(false),
             show_editor: RwSignal::new_local(false),
@@ -134,7 +148,7 @@ where
         // let finished = self.finished;
 
         match result {
-            Ok(ServerRes::Comments(comments)) => {
+            Ok(ServerRes::CommentPage { comments, .. }) => {
                 let fetch_count = self.fetch_count;
                 let finished = self.finished;
                 let len = comments.len();
@@ -199,11 +213,11 @@ where
         let finished = self.finished;
         let last_item = self.items.with_untracked(|v| v.last().cloned());
         let order = Order::OneTwoThree;
-        let time_range = if let Some(last_item) = last_item {
-            TimeRange::More(last_item.created_at)
+        let cursor = if let Some(last_item) = last_item {
+            CursorRange::After(Cursor::from(&last_item))
         } else {
             let time = time_now_ns();
-            TimeRange::LessOrEqual(time)
+            CursorRange::Before(Cursor::at(time + 1))
         };
 
         let result = self
@@ -212,7 +226,7 @@ where
                 post_key,
                 Some(comment_key),
                 fetch_count,
-                time_range,
+                cursor,
                 order,
                 flatten,
             )
@@ -232,16 +246,16 @@ where
         let finished = self.finished;
         let last_item = self.items.with_untracked(|v| v.last().cloned());
         let order = Order::ThreeTwoOne;
-        let time_range = if let Some(last_item) = last_item {
-            TimeRange::Less(last_item.created_at)
+        let cursor = if let Some(last_item) = last_item {
+            CursorRange::Before(Cursor::from(&last_item))
         } else {
             let time = time_now_ns();
-            TimeRange::LessOrEqual(time)
+            CursorRange::Before(Cursor::at(time + 1))
         };
 
         let result = self
             .api
-            .get_post_comment(post_key, None, fetch_count, time_range, order, false)
+            .get_post_comment(post_key, None, fetch_count, cursor, order, false)
             .send_native()
             .await;
 
@@ -371,6 +385,7 @@ where
             Ok(ServerRes::Comment(comment)) => {
                 self.edit_mode.set(false);
                 self.text.set(comment.text);
+                self.edited_at.set(comment.edited_at);
             }
             Ok(err) => {
                 let err = format!("update comment unexpected res: {err:?}");
@@ -424,6 +439,15 @@ where
     }
 
     pub async fn delete(self) {
+        self.delete_as(false).await
+    }
+
+    /// staff deleting someone else's comment
+    pub async fn delete_moderated(self) {
+        self.delete_as(true).await
+    }
+
+    async fn delete_as(self, moderated: bool) {
         self.err_delete.update(|v| v.clear());
         match self.kind.get_value() {
             CommentKind2::Root => {
@@ -447,13 +471,22 @@ where
                 parent_replies_count,
                 ..
             } => {
-                let result = self
-                    .api
-                    .delete_post_comment(comment.key.clone())
-                    .send_native()
-                    .await;
+                let req = if moderated {
+                    self.api.admin_delete_post_comment(comment.key.clone())
+                } else {
+                    self.api.delete_post_comment(comment.key.clone())
+                };
+                let result = req.send_native().await;
 
                 match result {
+                    // the server keeps comments with replies as placeholders so the thread stays intact
+                    Ok(ServerRes::Ok) if !moderated && self.replies_count.get_untracked() > 0 => {
+                        // only the server knows the exact time, any non zero value marks it as deleted
+                        self.deleted_at.set(1);
+                        self.text.update(|v| v.clear());
+                        self.show_editor.set(false);
+                        self.edit_mode.set(false);
+                    }
                     Ok(ServerRes::Ok) => {
                         // if let Some(parent) = parent {
                         let len_before = parent.with_untracked(|v| v.len());
@@ -585,6 +618,74 @@ where
     pub fn observe_only(&self, post_key: impl Into<String>) {
         self.post_key.set_value(post_key.into());
     }
+
+    pub fn merge_live(&self, event: &LiveEvent) {
+        let kind = self.kind.get_value();
+        match event {
+            LiveEvent::CommentAdded(new_comment) => {
+                let is_reply_of_items = match &kind {
+                    CommentKind2::Root => new_comment.parent_key.is_empty(),
+                    CommentKind2::Reply { comment, .. } => {
+                        new_comment.parent_key.last() == Some(&comment.key)
+                    }
+                    CommentKind2::Flat { comment, .. } => {
+                        new_comment.parent_key.contains(&comment.key)
+                    }
+                    // replies at this depth are merged by the flat parent
+                    CommentKind2::None { .. } => false,
+                };
+                let is_duplicate = self
+                    .items
+                    .with_untracked(|v| v.iter().any(|v| v.key == new_comment.key));
+                if !is_reply_of_items || is_duplicate {
+                    return;
+                }
+
+                if kind.is_root() {
+                    self.items.update(|v| v.insert(0, new_comment.clone()));
+                    return;
+                }
+
+                // pushing before everything is fetched would make the next fetch skip older replies
+                if self.finished.get_untracked() {
+                    self.items.update(|v| v.push(new_comment.clone()));
+                }
+                self.replies_count.update(|v| *v += 1);
+            }
+            LiveEvent::CommentUpdated(updated_comment) => {
+                self.items.update(|v| {
+                    if let Some(v) = v.iter_mut().find(|v| v.key == updated_comment.key) {
+                        v.text = updated_comment.text.clone();
+                        v.modified_at = updated_comment.modified_at;
+                        v.edited_at = updated_comment.edited_at;
+                        v.deleted_at = updated_comment.deleted_at;
+                    }
+                });
+                let is_self = match &kind {
+                    CommentKind2::Root => false,
+                    CommentKind2::Reply { comment, .. }
+                    | CommentKind2::Flat { comment, .. }
+                    | CommentKind2::None { comment, .. } => comment.key == updated_comment.key,
+                };
+                if is_self && !self.edit_mode.get_untracked() {
+                    self.text.set(updated_comment.text.clone());
+                    self.edited_at.set(updated_comment.edited_at);
+                    self.deleted_at.set(updated_comment.deleted_at);
+                }
+            }
+            LiveEvent::CommentDeleted { comment_key, .. } => {
+                let len_before = self.items.with_untracked(|v| v.len());
+                self.items.update(|v| {
+                    v.retain(|v| !(v.key == *comment_key || v.parent_key.contains(comment_key)));
+                });
+                let removed = len_before.saturating_sub(self.items.with_untracked(|v| v.len()));
+                if removed > 0 && !kind.is_root() {
+                    self.replies_count.update(|v| *v = v.saturating_sub(removed));
+                }
+            }
+            LiveEvent::PostLikeCount { .. } => {}
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -696,7 +797,7 @@ impl CommentsApi {
         // });
         let handle_comments_result = move |result, fetch_count| {
             match result {
-                Ok(ServerRes::Comments(comments)) => {
+                Ok(ServerRes::CommentPage { comments, .. }) => {
                     let len = comments.len();
                     trace!(
                         "comments manual (len){len} < (fetch_count){fetch_count} = {}",
@@ -748,15 +849,15 @@ impl CommentsApi {
             let fetch_count = fetch_count.get_value();
             let last_item = comments_local.with_untracked(|v| v.last().cloned());
             let order = Order::ThreeTwoOne;
-            let time_range = if let Some(last_item) = last_item {
-                TimeRange::Less(last_item.created_at)
+            let cursor = if let Some(last_item) = last_item {
+                CursorRange::Before(Cursor::from(&last_item))
             } else {
                 let time = time_now_ns();
-                TimeRange::LessOrEqual(time)
+                CursorRange::Before(Cursor::at(time + 1))
             };
 
             let result = api
-                .get_post_comment(post_id, None, fetch_count, time_range, order, false)
+                .get_post_comment(post_id, None, fetch_count, cursor, order, false)
                 .send_native()
                 .await;
 
@@ -771,15 +872,15 @@ impl CommentsApi {
             let fetch_count = fetch_count.get_value();
             let order = Order::OneTwoThree;
             let last_item = comments_local.with_untracked(|v| v.last().cloned());
-            let time_range = if let Some(last_item) = last_item {
-                TimeRange::More(last_item.created_at)
+            let cursor = if let Some(last_item) = last_item {
+                CursorRange::After(Cursor::from(&last_item))
             } else {
                 let time = time_now_ns();
-                TimeRange::LessOrEqual(time)
+                CursorRange::Before(Cursor::at(time + 1))
             };
 
             let result = api
-                .get_post_comment(post_id, None, fetch_count, time_range, order, flatten)
+                .get_post_comment(post_id, None, fetch_count, cursor, order, flatten)
                 .send_native()
                 .await;
 
@@ -128,7 +128,10 @@ impl FileUpload {
     //     // });
     // }
 
-    pub fn upload(&self, files: &[File]) {
+    /// uploads only the newly selected files, already tracked files are left alone
+    pub fn upload(&self, url: impl Into<String>, files: &[File]) {
+        let url = url.into();
+        let start = self.post_files.with_untracked(|v| v.len());
         // let progress = self.progress;
         // self.add_progress(UploadProgress::Uploading {
         //     file_name: file.name(),
@@ -153,8 +156,9 @@ impl FileUpload {
         // }
         let post_files = self.post_files.clone();
 
-        for (index, post_file) in post_files.get_untracked().iter().enumerate() {
+        for (index, post_file) in post_files.get_untracked().iter().enumerate().skip(start) {
             let file = post_file.file.clone();
+            let url = url.clone();
             spawn_local(async move {
                 let result = JsFuture::from(Promise::new(
                     &mut move |resolve: Function, reject: Function| {
@@ -169,6 +173,7 @@ impl FileUpload {
                                         let Some(file) = v.get_mut(index) else {
                                             return;
                                         };
+                                        file.state = UploadProgressState::Uploading;
                                         file.completed_bytes = event.loaded() as usize;
                                     });
                                     trace!("uploading... {}/{}", event.loaded(), event.total());
@@ -191,9 +196,15 @@ impl FileUpload {
                         .unwrap();
 
                         req.add_event_listener_with_callback(
-                            "loaded",
+                            "load",
                             &Closure::<dyn FnMut()>::new(move || {
                                 trace!("complete");
+                                post_files.update(|v| {
+                                    let Some(file) = v.get_mut(index) else {
+                                        return;
+                                    };
+                                    file.state = UploadProgressState::Completed;
+                                });
                                 resolve.call1(&JsValue::NULL, &"done".into()).unwrap();
                             })
                             .into_js_value()
@@ -205,12 +216,7 @@ impl FileUpload {
                         form.set_with_blob("upload", file.unchecked_ref()).unwrap();
                         // form.set_with_str("what", "nooooooooo").unwrap();
 
-                        req.open_with_async(
-                            "POST",
-                            "http://localhost:3000/api/post/oqmp4g1iiswwp8nixrae/add_file",
-                            true,
-                        )
-                        .unwrap();
+                        req.open_with_async("POST", &url, true).unwrap();
 
                         // req.set_request_header("Content-Type", "application/x-www-form-urlencoded")
                         //     .unwrap();
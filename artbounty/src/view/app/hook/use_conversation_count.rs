@@ -0,0 +1,40 @@
+use leptos::prelude::*;
+use leptos_router::hooks::use_location;
+use tracing::error;
+
+use crate::api::{Api, ApiWeb, ServerRes};
+use crate::view::app::GlobalState;
+
+#[derive(Clone, Copy)]
+pub struct ConversationCount {
+    pub unread: RwSignal<u64>,
+}
+
+pub fn use_conversation_count() -> ConversationCount {
+    let global_state = expect_context::<GlobalState>();
+    let location = use_location();
+    let api = ApiWeb::new();
+    let unread = RwSignal::new(0_u64);
+
+    Effect::new(move || {
+        location.pathname.track();
+        if !global_state.is_logged_in().unwrap_or_default() {
+            unread.set(0);
+            return;
+        }
+        api.get_conversation_unread_count()
+            .send_web(async move |result| match result {
+                Ok(ServerRes::Count(count)) => {
+                    unread.set(count);
+                }
+                Ok(err) => {
+                    error!("use_conversation_count: expected ServerRes::Count, received: {err:?}");
+                }
+                Err(err) => {
+                    error!("use_conversation_count: {err}");
+                }
+            });
+    });
+
+    ConversationCount { unread }
+}
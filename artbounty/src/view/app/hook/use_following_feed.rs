@@ -0,0 +1,99 @@
+use leptos::html::ElementType;
+use leptos::prelude::*;
+use tracing::error;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlElement;
+
+use crate::api::shared::cursor::{Cursor, CursorRange};
+use crate::api::{Api, ApiWeb, Order, ServerRes, UserPost};
+use crate::view::app::hook::use_infinite_scroll_virtual::{
+    InfiniteMerge, InfiniteStage, use_infinite_scroll_virtual,
+};
+use crate::view::toolbox::prelude::*;
+
+#[derive(Clone, Copy)]
+pub struct FollowingFeed {
+    pub err_fetch: RwSignal<String>,
+    pub data: RwSignal<Vec<UserPost>, LocalStorage>,
+}
+
+pub fn use_following_feed<ContainerElm>(
+    fetch_count: usize,
+    feed_container_ref: NodeRef<ContainerElm>,
+) -> FollowingFeed
+where
+    ContainerElm: ElementType,
+    ContainerElm::Output: JsCast + Clone + 'static + Into<HtmlElement>,
+{
+    let api = ApiWeb::new();
+    let err_fetch = RwSignal::new(String::new());
+
+    let infinite_fn = move |stage: InfiniteStage<UserPost>| async move {
+        let (is_top, result) = match stage {
+            InfiniteStage::Init | InfiniteStage::Manual => {
+                let time = time_now_ns();
+                (
+                    false,
+                    api.get_posts_following(
+                        fetch_count,
+                        CursorRange::Before(Cursor::at(time + 1)),
+                        Order::ThreeTwoOne,
+                    )
+                    .send_native()
+                    .await,
+                )
+            }
+            InfiniteStage::Top(data) => (
+                true,
+                api.get_posts_following(
+                    fetch_count,
+                    CursorRange::After(Cursor::from(&data)),
+                    Order::OneTwoThree,
+                )
+                .send_native()
+                .await,
+            ),
+            InfiniteStage::Btm(data) => (
+                false,
+                api.get_posts_following(
+                    fetch_count,
+                    CursorRange::Before(Cursor::from(&data)),
+                    Order::ThreeTwoOne,
+                )
+                .send_native()
+                .await,
+            ),
+        };
+
+        match result {
+            Ok(ServerRes::PostPage { posts, .. }) => {
+                if is_top {
+                    InfiniteMerge::Top {
+                        data: posts.into_iter().rev().collect(),
+                    }
+                } else {
+                    InfiniteMerge::Btm { data: posts }
+                }
+            }
+            Ok(err) => {
+                let err = format!("unexpected server response: {err:?}");
+                error!(err);
+                err_fetch.set(err);
+                InfiniteMerge::None
+            }
+            Err(err) => {
+                let err = format!("use_following_feed: {err}");
+                error!(err);
+                err_fetch.set(err);
+                InfiniteMerge::None
+            }
+        }
+    };
+
+    let infinite = use_infinite_scroll_virtual(feed_container_ref, infinite_fn);
+
+    FollowingFeed {
+        err_fetch,
+        data: infinite.data,
+    }
+}
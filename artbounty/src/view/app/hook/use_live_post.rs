@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use leptos::prelude::*;
+use tracing::{error, trace, warn};
+use wasm_bindgen::{JsCast, prelude::Closure};
+use web_sys::{BinaryType, CloseEvent, MessageEvent, WebSocket, js_sys::Uint8Array};
+
+use crate::api::shared::live::LiveEvent;
+use crate::path::link_live_post;
+use crate::view::app::GlobalState;
+use crate::view::toolbox::prelude::*;
+
+pub const LIVE_POST_RECONNECT_DELAY: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy)]
+pub struct LivePost {
+    pub connected: RwSignal<bool>,
+    listeners: StoredValue<Vec<(usize, Box<dyn Fn(&LiveEvent) + 'static>)>, LocalStorage>,
+    next_listener_id: StoredValue<usize, LocalStorage>,
+}
+
+impl LivePost {
+    /// runs `f` for every event of the observed post until the calling owner is cleaned up
+    pub fn on_event(&self, f: impl Fn(&LiveEvent) + 'static) {
+        let id = self.next_listener_id.get_value();
+        self.next_listener_id.set_value(id + 1);
+        self.listeners.update_value(|v| v.push((id, Box::new(f))));
+
+        let listeners = self.listeners;
+        on_cleanup(move || {
+            listeners.try_update_value(|v| v.retain(|(listener_id, _)| *listener_id != id));
+        });
+    }
+
+    fn dispatch(&self, event: LiveEvent) {
+        trace!("live post recv {event:?}");
+        self.listeners.with_value(|v| {
+            for (_, f) in v {
+                f(&event);
+            }
+        });
+    }
+}
+
+pub fn use_live_post(post_key: Memo<Option<String>>) -> LivePost {
+    let global_state = expect_context::<GlobalState>();
+    let live = LivePost {
+        connected: RwSignal::new(false),
+        listeners: StoredValue::new_local(Vec::new()),
+        next_listener_id: StoredValue::new_local(0),
+    };
+    let socket = StoredValue::new_local(None::<WebSocket>);
+    let reconnect = RwSignal::new(0_usize);
+
+    let close = move || {
+        socket.update_value(|v| {
+            if let Some(socket) = v.take() {
+                socket.set_onclose(None);
+                socket.set_onmessage(None);
+                let _ = socket.close();
+            }
+        });
+        live.connected.set(false);
+    };
+
+    Effect::new(move || {
+        reconnect.track();
+        close();
+        let (Some(post_key), true) = (
+            post_key.get(),
+            global_state.is_logged_in().unwrap_or_default(),
+        ) else {
+            return;
+        };
+        let Ok(origin) = location().origin() else {
+            error!("use_live_post: failed to get origin");
+            return;
+        };
+
+        let ws = match WebSocket::new(&link_live_post(origin, &post_key)) {
+            Ok(ws) => ws,
+            Err(err) => {
+                error!("use_live_post: failed to open socket {err:?}");
+                return;
+            }
+        };
+        ws.set_binary_type(BinaryType::Arraybuffer);
+
+        let on_open = Closure::<dyn Fn()>::new(move || {
+            live.connected.set(true);
+        })
+        .into_js_value();
+        ws.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+
+        let on_message = Closure::<dyn Fn(MessageEvent)>::new(move |e: MessageEvent| {
+            let bytes = Uint8Array::new(&e.data()).to_vec();
+            match LiveEvent::from_bytes(&bytes) {
+                Ok(event) => live.dispatch(event),
+                Err(err) => error!("use_live_post: failed to decode event: {err}"),
+            }
+        })
+        .into_js_value();
+        ws.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        let on_close = Closure::<dyn Fn(CloseEvent)>::new(move |e: CloseEvent| {
+            warn!(
+                "use_live_post: socket closed ({}), reconnecting in {LIVE_POST_RECONNECT_DELAY:?}",
+                e.code()
+            );
+            live.connected.set(false);
+            let result = set_timeout(
+                move || {
+                    reconnect.try_update(|v| *v += 1);
+                },
+                LIVE_POST_RECONNECT_DELAY,
+            );
+            if let Err(err) = result {
+                error!("{err}");
+            }
+        })
+        .into_js_value();
+        ws.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        socket.set_value(Some(ws));
+    });
+
+    on_cleanup(close);
+
+    live
+}
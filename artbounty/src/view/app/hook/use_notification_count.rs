@@ -0,0 +1,41 @@
+use leptos::prelude::*;
+use leptos_router::hooks::use_location;
+use tracing::error;
+
+use crate::api::{Api, ApiWeb, ServerRes};
+use crate::view::app::GlobalState;
+
+#[derive(Clone, Copy)]
+pub struct NotificationCount {
+    pub unread: RwSignal<u64>,
+}
+
+pub fn use_notification_count() -> NotificationCount {
+    let global_state = expect_context::<GlobalState>();
+    let location = use_location();
+    let api = ApiWeb::new();
+    let unread = RwSignal::new(0_u64);
+
+    Effect::new(move || {
+        // refetch on every navigation so the badge catches up after reading notifications
+        location.pathname.track();
+        if !global_state.is_logged_in().unwrap_or_default() {
+            unread.set(0);
+            return;
+        }
+        api.get_notification_unread_count()
+            .send_web(async move |result| match result {
+                Ok(ServerRes::Count(count)) => {
+                    unread.set(count);
+                }
+                Ok(err) => {
+                    error!("use_notification_count: expected ServerRes::Count, received: {err:?}");
+                }
+                Err(err) => {
+                    error!("use_notification_count: {err}");
+                }
+            });
+    });
+
+    NotificationCount { unread }
+}
@@ -0,0 +1,162 @@
+use leptos::prelude::*;
+use tracing::error;
+
+use crate::api::shared::collection::UserCollection;
+use crate::api::{Api, ApiWeb, Order, ServerRes, TimeRange};
+use crate::view::app::GlobalState;
+
+pub const POST_COLLECTIONS_FETCH_COUNT: usize = 100;
+
+#[derive(Clone, Copy)]
+pub struct PostCollections {
+    pub collections: RwSignal<Vec<UserCollection>>,
+    pub saved: RwSignal<Vec<String>>,
+    pub err: RwSignal<String>,
+    pub on_toggle: StoredValue<Box<dyn Fn(String) + Sync + Send + 'static>>,
+    pub on_create: StoredValue<Box<dyn Fn(String, bool) + Sync + Send + 'static>>,
+}
+
+pub fn use_post_collections(post_key: Memo<Option<String>>) -> PostCollections {
+    let global_state = expect_context::<GlobalState>();
+    let api_collections = ApiWeb::new();
+    let api_saved = ApiWeb::new();
+    let api = ApiWeb::new();
+    let collections = RwSignal::new(Vec::<UserCollection>::new());
+    let saved = RwSignal::new(Vec::<String>::new());
+    let err = RwSignal::new(String::new());
+
+    Effect::new(move || {
+        let Some(username) = global_state.get_username_tracked() else {
+            return;
+        };
+        api_collections
+            .get_user_collections(
+                username,
+                POST_COLLECTIONS_FETCH_COUNT,
+                TimeRange::None,
+                Order::ThreeTwoOne,
+            )
+            .send_web(async move |result| match result {
+                Ok(ServerRes::Collections(v)) => {
+                    collections.set(v);
+                }
+                Ok(res) => {
+                    error!("use_post_collections: expected Collections, received: {res:?}");
+                }
+                Err(e) => {
+                    error!("use_post_collections: {e}");
+                }
+            });
+    });
+
+    Effect::new(move || {
+        let (Some(post_key), Some(true)) = (post_key.get(), global_state.is_logged_in()) else {
+            return;
+        };
+        api_saved
+            .get_post_collections(post_key)
+            .send_web(async move |result| match result {
+                Ok(ServerRes::CollectionKeys(keys)) => {
+                    saved.set(keys);
+                }
+                Ok(res) => {
+                    error!("use_post_collections: expected CollectionKeys, received: {res:?}");
+                }
+                Err(e) => {
+                    error!("use_post_collections: {e}");
+                }
+            });
+    });
+
+    let update_collection = move |collection: UserCollection| {
+        collections.update(|v| match v.iter_mut().find(|v| v.key == collection.key) {
+            Some(old) => *old = collection,
+            None => v.insert(0, collection),
+        });
+    };
+
+    let on_toggle = move |collection_key: String| {
+        let Some(post_key) = post_key.get_untracked() else {
+            return;
+        };
+        err.set(String::new());
+        let is_saved = saved.with_untracked(|v| v.contains(&collection_key));
+        if is_saved {
+            api.delete_collection_item(collection_key.clone(), post_key)
+                .send_web(async move |result| match result {
+                    Ok(ServerRes::Ok) => {
+                        saved.update(|v| v.retain(|v| *v != collection_key));
+                        collections.update(|v| {
+                            if let Some(collection) = v.iter_mut().find(|v| v.key == collection_key)
+                            {
+                                collection.items_count = collection.items_count.saturating_sub(1);
+                            }
+                        });
+                    }
+                    Ok(res) => {
+                        error!("use_post_collections: expected Ok, received: {res:?}");
+                    }
+                    Err(e) => {
+                        err.set(e.to_string());
+                    }
+                });
+        } else {
+            api.add_collection_item(collection_key.clone(), post_key)
+                .send_web(async move |result| match result {
+                    Ok(ServerRes::Collection(collection)) => {
+                        saved.update(|v| v.push(collection_key));
+                        update_collection(collection);
+                    }
+                    Ok(res) => {
+                        error!("use_post_collections: expected Collection, received: {res:?}");
+                    }
+                    Err(e) => {
+                        err.set(e.to_string());
+                    }
+                });
+        }
+    };
+
+    // a new collection starts with the current post in it
+    let on_create = move |title: String, private: bool| {
+        let Some(post_key) = post_key.get_untracked() else {
+            return;
+        };
+        err.set(String::new());
+        api.add_collection(title, private)
+            .send_web(async move |result| match result {
+                Ok(ServerRes::Collection(collection)) => {
+                    update_collection(collection.clone());
+                    api.add_collection_item(collection.key.clone(), post_key)
+                        .send_web(async move |result| match result {
+                            Ok(ServerRes::Collection(collection)) => {
+                                saved.update(|v| v.push(collection.key.clone()));
+                                update_collection(collection);
+                            }
+                            Ok(res) => {
+                                error!(
+                                    "use_post_collections: expected Collection, received: {res:?}"
+                                );
+                            }
+                            Err(e) => {
+                                err.set(e.to_string());
+                            }
+                        });
+                }
+                Ok(res) => {
+                    error!("use_post_collections: expected Collection, received: {res:?}");
+                }
+                Err(e) => {
+                    err.set(e.to_string());
+                }
+            });
+    };
+
+    PostCollections {
+        collections,
+        saved,
+        err,
+        on_toggle: StoredValue::new(Box::new(on_toggle)),
+        on_create: StoredValue::new(Box::new(on_create)),
+    }
+}
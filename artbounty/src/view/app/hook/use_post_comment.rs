@@ -4,8 +4,9 @@ use tracing::{error, trace};
 use wasm_bindgen::JsCast;
 use web_sys::{HtmlElement, SubmitEvent};
 
+use crate::api::shared::cursor::{Cursor, CursorRange};
 use crate::api::shared::post_comment::UserPostComment;
-use crate::api::{Api, ApiWeb, Order, ServerRes, TimeRange};
+use crate::api::{Api, ApiWeb, Order, ServerRes};
 use crate::view::app::hook::use_infinite_scroll_virtual::{
     InfiniteMerge, InfiniteStage, use_infinite_scroll_virtual,
 };
@@ -85,7 +86,7 @@ where
                             post_id,
                             comment_key,
                             fetch_count,
-                            TimeRange::LessOrEqual(time),
+                            CursorRange::Before(Cursor::at(time + 1)),
                             Order::ThreeTwoOne,
                             false,
                         )
@@ -99,7 +100,7 @@ where
                         post_id,
                         comment_key,
                         fetch_count,
-                        TimeRange::More(data.created_at),
+                        CursorRange::After(Cursor::from(&data)),
                         Order::OneTwoThree,
                         false,
                     )
@@ -112,7 +113,7 @@ where
                         post_id,
                         comment_key,
                         fetch_count,
-                        TimeRange::Less(data.created_at),
+                        CursorRange::Before(Cursor::from(&data)),
                         Order::ThreeTwoOne,
                         false,
                     )
@@ -133,7 +134,7 @@ where
 
                     InfiniteMerge::Top { data: comments }
                 }
-                Ok(ServerRes::Comments(comments)) => {
+                Ok(ServerRes::CommentPage { comments, .. }) => {
                     // reply_editor_show.set(false);
                     if is_top {
                         InfiniteMerge::Top {
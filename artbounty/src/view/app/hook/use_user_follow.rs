@@ -0,0 +1,161 @@
+use leptos::prelude::*;
+use tracing::error;
+
+use crate::api::{Api, ApiWeb, ServerRes};
+use crate::view::app::GlobalState;
+
+#[derive(Clone, Copy)]
+pub struct UserFollow {
+    pub stage: StoredValue<Box<dyn Fn() -> UserFollowStage + Sync + Send + 'static>>,
+    pub followers: RwSignal<u64>,
+    pub following: RwSignal<u64>,
+    pub on_follow: StoredValue<Box<dyn Fn() + Sync + Send + 'static>>,
+}
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    PartialOrd,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIter,
+    strum::EnumIs,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum UserFollowStage {
+    #[default]
+    Loading,
+    Followed,
+    Unfollowed,
+    Hidden,
+}
+
+pub fn use_user_follow(username: Memo<Option<String>>) -> UserFollow {
+    let global_state = expect_context::<GlobalState>();
+    let api = ApiWeb::new();
+    let api_stats = ApiWeb::new();
+    let stage = RwSignal::new(UserFollowStage::Loading);
+    let followers = RwSignal::new(0_u64);
+    let following = RwSignal::new(0_u64);
+    let stage_view = move || {
+        if api.is_pending_tracked() {
+            UserFollowStage::Loading
+        } else {
+            stage.get()
+        }
+    };
+
+    let update_stats = move |username: String| {
+        api_stats
+            .get_user_follow_stats(username)
+            .send_web(async move |result| match result {
+                Ok(ServerRes::UserFollowStats {
+                    followers: followers_count,
+                    following: following_count,
+                }) => {
+                    followers.set(followers_count);
+                    following.set(following_count);
+                }
+                Ok(err) => {
+                    error!("use_user_follow: expected ServerRes::UserFollowStats, received: {err:?}");
+                }
+                Err(err) => {
+                    error!("use_user_follow: {err}");
+                }
+            });
+    };
+
+    Effect::new(move || {
+        let Some(username) = username.get() else {
+            return;
+        };
+        update_stats(username);
+    });
+
+    Effect::new(move || {
+        let Some(username) = username.get() else {
+            return;
+        };
+        let is_logged_in = global_state.is_logged_in().unwrap_or_default();
+        let is_self = global_state
+            .get_username_tracked()
+            .map(|v| v == username)
+            .unwrap_or_default();
+        if !is_logged_in || is_self {
+            stage.set(UserFollowStage::Hidden);
+            return;
+        }
+        api.check_user_follow(username)
+            .send_web(async move |result| match result {
+                Ok(ServerRes::Condition(condition)) => {
+                    if condition {
+                        stage.set(UserFollowStage::Followed);
+                    } else {
+                        stage.set(UserFollowStage::Unfollowed);
+                    }
+                }
+                Ok(err) => {
+                    error!("use_user_follow: expected ServerRes::Condition, received: {err:?}");
+                    stage.set(UserFollowStage::Unfollowed);
+                }
+                Err(err) => {
+                    error!("use_user_follow: {err}");
+                    stage.set(UserFollowStage::Unfollowed);
+                }
+            });
+    });
+
+    let on_follow = move || {
+        let Some(username) = username.get_untracked() else {
+            return;
+        };
+        match stage.get_untracked() {
+            UserFollowStage::Loading | UserFollowStage::Hidden => {
+                //
+            }
+            UserFollowStage::Followed => {
+                api.delete_user_follow(username.clone())
+                    .send_web(async move |result| {
+                        match result {
+                            Ok(ServerRes::Ok) => {
+                                stage.set(UserFollowStage::Unfollowed);
+                            }
+                            Ok(res) => {
+                                error!("error, expected OK, received: {res:?}");
+                            }
+                            Err(err) => {
+                                error!("use_user_follow: {err}");
+                            }
+                        };
+                        update_stats(username);
+                    });
+            }
+            UserFollowStage::Unfollowed => {
+                api.add_user_follow(username.clone())
+                    .send_web(async move |result| {
+                        match result {
+                            Ok(ServerRes::Ok) => {
+                                stage.set(UserFollowStage::Followed);
+                            }
+                            Ok(res) => {
+                                error!("error, expected OK, received: {res:?}");
+                            }
+                            Err(err) => {
+                                error!("use_user_follow: {err}");
+                            }
+                        };
+                        update_stats(username);
+                    });
+            }
+        }
+    };
+
+    UserFollow {
+        stage: StoredValue::new(Box::new(stage_view)),
+        followers,
+        following,
+        on_follow: StoredValue::new(Box::new(on_follow)),
+    }
+}
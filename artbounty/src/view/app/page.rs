@@ -1,20 +1,31 @@
 pub mod post;
 pub mod settings {
+    use crate::api::shared::account::{
+        AccountDeletionMode, DataExportStatus, UserAccountDeletion, UserDataExport,
+    };
+    use crate::api::shared::api_token::{ApiTokenScope, UserApiToken};
+    use crate::api::shared::audit::UserAuditEvent;
+    use crate::api::shared::rating::PostRating;
+    use crate::api::shared::trash::{TRASH_RETENTION_NS, UserTrash};
+    use crate::api::shared::user_block::{UserBlock, UserBlockKind};
+    use crate::api::shared::webhook::{UserWebhook, UserWebhookDelivery, WebhookEvent};
     use crate::api::{
-        Api, ApiWeb, EmailChangeStage, ServerAddPostErr, ServerErr, ServerReqImg, ServerRes,
+        Api, ApiWeb, EmailChangeStage, Order, ServerAddPostErr, ServerErr, ServerReqImg, ServerRes,
+        TimeRange,
     };
     use crate::path::{
-        link_post, link_reg_finish, link_settings, link_settings_form_email,
+        link_api_data_export, link_post, link_reg_finish, link_settings, link_settings_form_email,
         link_settings_form_email_completed, link_settings_form_email_current_click,
         link_settings_form_email_current_send, link_settings_form_email_final_confirm,
         link_settings_form_email_new_click, link_settings_form_email_new_send,
-        link_settings_form_password_send, link_settings_form_username,
+        link_settings_form_password_send, link_settings_form_username, link_user,
         query_settings_form_password_send,
     };
     use crate::valid::auth::{
         proccess_email, proccess_post_description, proccess_post_title, proccess_username,
     };
     use crate::view::app::GlobalState;
+    use crate::view::app::components::audit_event::AuditEventRow;
     use crate::view::app::components::nav::Nav;
     use crate::view::app::hook::use_email_change::{
         BtnStage, EmailChangeFormStage, use_change_email,
@@ -33,9 +44,16 @@ pub mod settings {
     use leptos_router::{hooks::use_params, params::Params};
 
     use leptos_router::hooks::use_query;
+    use strum::IntoEnumIterator;
     use tracing::{error, trace};
     use web_sys::{HtmlInputElement, HtmlTextAreaElement, MouseEvent, SubmitEvent};
 
+    pub const SETTINGS_AUDIT_FETCH_COUNT: usize = 25;
+    pub const SETTINGS_BLOCKS_FETCH_COUNT: usize = 25;
+    /// 0 is a token that never expires
+    pub const SETTINGS_API_TOKEN_EXPIRY_DAYS: [u128; 4] = [30, 90, 365, 0];
+    pub const SETTINGS_WEBHOOK_DELIVERIES_FETCH_COUNT: usize = 25;
+
     #[component]
     pub fn Page() -> impl IntoView {
         let main_ref = NodeRef::new();
@@ -131,7 +149,14 @@ pub mod settings {
                             <a href=move || query_settings_form_password_send(global_state.get_email_tracked().unwrap_or_default()) class="border-2 border-base0E text-[1.3rem] font-bold px-4 py-1 hover:bg-base02 text-base0E">"Change"</a>
                         </div>
                     </form>
-
+                    <RatingPreferenceForm />
+                    <BlockedUsers />
+                    <Trash />
+                    <DataExport />
+                    <ApiTokens />
+                    <Webhooks />
+                    <SecurityHistory />
+                    <DeleteAccount />
                 </div>
 
                 // username change
@@ -319,126 +344,1028 @@ pub mod settings {
             </main>
         }
     }
-}
-pub mod upload {
 
-    use std::io::Bytes;
-    use std::iter;
+    #[component]
+    pub fn RatingPreferenceForm() -> impl IntoView {
+        let global_state = expect_context::<GlobalState>();
+        let api = ApiWeb::new();
+        let err = RwSignal::new(String::new());
 
-    use crate::api::{Api, ApiWeb, ServerAddPostErr, ServerErr, ServerReqImg};
-    use crate::path::link_post;
-    use crate::valid::auth::{proccess_post_description, proccess_post_tags, proccess_post_title};
-    use crate::view::app::components::nav::Nav;
-    use crate::view::app::hook::api_post_file_upload::FileUpload;
-    use crate::view::toolbox::prelude::*;
-    use leptos::prelude::*;
-    use leptos::{Params, task::spawn_local};
-    use leptos_router::{hooks::use_params, params::Params};
+        let update = move |max_rating: PostRating, blur: bool| {
+            err.set(String::new());
+            api.update_rating_preference(max_rating, blur)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::RatingPreference(v)) => {
+                            global_state.rating.set(v);
+                        }
+                        Ok(res) => {
+                            error!("expected RatingPreference, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
 
-    // use async_stream::stream;
-    use futures_util::pin_mut;
-    use futures_util::stream::StreamExt;
-    use leptos_router::hooks::use_query;
-    use tracing::{error, trace};
-    use wasm_bindgen::prelude::*;
-    // use wasm_bindgen::{JsCast, JsValue, Closure};
-    use wasm_bindgen_futures::JsFuture;
-    use web_sys::js_sys::{Array, Function, JsString, Number, Promise, Reflect};
-    use web_sys::{
-        Blob, File, FormData, HtmlInputElement, HtmlTextAreaElement, ProgressEvent, RequestInit,
-        RequestMode, SubmitEvent, TransformStream, TransformStreamDefaultController, Transformer,
-        XmlHttpRequest,
-    };
+        let on_max_rating = move |e: web_sys::Event| {
+            let max_rating = PostRating::from_db(event_target_value(&e));
+            update(max_rating, global_state.rating.get_untracked().blur);
+        };
+        let on_blur = move |e: web_sys::Event| {
+            let blur = event_target_checked(&e);
+            update(global_state.rating.get_untracked().max_rating, blur);
+        };
 
-    #[derive(Params, PartialEq, Clone)]
-    pub struct UserParams {
-        pub username: Option<String>,
+        view! {
+            <h2 class="text-[1.3rem] text-base0A mt-[4rem]">"Content rating"</h2>
+            <Show when=move || err.with(|v| !v.is_empty()) >
+                <span class="text-base08">{move || err.get()}</span>
+            </Show>
+            <form class="flex flex-col gap-2">
+                <label for="max_rating" class="text-[1.2rem] ">"Show posts up to"</label>
+                <select
+                    id="max_rating"
+                    class="bg-base01 text-base0B px-2"
+                    disabled=move || api.is_pending_tracked()
+                    prop:value=move || global_state.rating.get().max_rating.to_string()
+                    on:change=on_max_rating
+                >
+                    {PostRating::iter().map(|rating| view! {
+                        <option value=rating.to_string()>{rating.label()}</option>
+                    }).collect_view()}
+                </select>
+                <label class="flex gap-2 items-center">
+                    <input
+                        type="checkbox"
+                        disabled=move || api.is_pending_tracked()
+                        prop:checked=move || global_state.rating.get().blur
+                        on:change=on_blur
+                    />
+                    "Blur questionable and explicit posts"
+                </label>
+            </form>
+        }
     }
 
+    /// blocked and muted users, newest first
     #[component]
-    pub fn Page() -> impl IntoView {
-        let main_ref = NodeRef::new();
-        let upload_title = NodeRef::new();
-        let upload_title_err = RwSignal::new(String::new());
-        let upload_image = NodeRef::new();
-        let upload_image_err = RwSignal::new(String::new());
-        let upload_description = NodeRef::new();
-        let upload_description_err = RwSignal::new(String::new());
-        let upload_tags = NodeRef::new();
-        let upload_tags_err = RwSignal::new(String::new());
-        let upload_general_err = RwSignal::new(String::new());
+    pub fn BlockedUsers() -> impl IntoView {
         let api = ApiWeb::new();
-        let navigate = leptos_router::hooks::use_navigate();
+        let api_delete = ApiWeb::new();
+        let users = RwSignal::new(Vec::<UserBlock>::new());
+        let finished = RwSignal::new(false);
+        let err = RwSignal::new(String::new());
 
-        let file_upload = FileUpload::new();
-        let on_file_change = move |_| {
-            let Some(files) = (upload_image.get_untracked() as Option<HtmlInputElement>)
-                .and_then(|f: HtmlInputElement| f.files())
-                .map(|f| f.get_files())
-            else {
+        let fetch = move |time_range: TimeRange| {
+            api.get_user_blocks(SETTINGS_BLOCKS_FETCH_COUNT, time_range, Order::ThreeTwoOne)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::UserBlocks(v)) => {
+                            finished.set(v.len() < SETTINGS_BLOCKS_FETCH_COUNT);
+                            users.update(|users| users.extend(v));
+                        }
+                        Ok(res) => {
+                            error!("expected UserBlocks, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        Effect::new(move || {
+            fetch(TimeRange::None);
+        });
+
+        let on_more = move |_| {
+            let Some(last) = users.with_untracked(|v| v.last().map(|v| v.created_at)) else {
                 return;
             };
-            // file_upload.clear();
-            // file_upload.select(&files);
-            trace!("fle selection changed i think");
+            fetch(TimeRange::Less(last));
         };
 
-        let on_upload = move |e: SubmitEvent| {
+        let on_remove = move |username: String| {
+            api_delete
+                .delete_user_block(username.clone())
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Ok) => {
+                            users.update(|users| users.retain(|v| v.user.username != username));
+                        }
+                        Ok(res) => {
+                            error!("expected Ok, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        view! {
+            <h2 class="text-[1.3rem] text-base0A mt-[4rem]">"Blocked and muted"</h2>
+            <Show when=move || err.with(|v| !v.is_empty()) >
+                <span class="text-base08">{move || err.get()}</span>
+            </Show>
+            <div class="flex flex-col gap-2">
+                <For
+                    each=move || users.get()
+                    key=|block| block.user.username.clone()
+                    let(block)
+                >
+                    {
+                        let username = block.user.username.clone();
+                        let kind = match block.kind {
+                            UserBlockKind::Block => "blocked",
+                            UserBlockKind::Mute => "muted",
+                        };
+                        view! {
+                            <div class="flex gap-2 items-center rounded-xl bg-base01 p-2">
+                                <a href=link_user(&block.user.username) class="text-base0B">{block.user.username.clone()}</a>
+                                <span class="text-base03">{kind}</span>
+                                <button class="ml-auto text-base03 hover:text-base05" on:click=move |_| on_remove(username.clone()) >"Remove"</button>
+                            </div>
+                        }
+                    }
+                </For>
+                <Show when=move || !finished.get() && !api.is_pending_tracked() >
+                    <button class="border-2 border-base05 px-2 hover:bg-base02" on:click=on_more >"More"</button>
+                </Show>
+            </div>
+        }
+    }
+
+    /// deleted posts and comments that can still be restored, newest first
+    #[component]
+    pub fn Trash() -> impl IntoView {
+        let global_state = expect_context::<GlobalState>();
+        let api = ApiWeb::new();
+        let api_restore = ApiWeb::new();
+        let trash = RwSignal::new(UserTrash::default());
+        let err = RwSignal::new(String::new());
+
+        Effect::new(move || {
+            api.get_trash().send_web(move |result| async move {
+                match result {
+                    Ok(ServerRes::Trash(v)) => {
+                        trash.set(v);
+                    }
+                    Ok(res) => {
+                        error!("expected Trash, received {res:?}");
+                    }
+                    Err(e) => {
+                        err.set(e.to_string());
+                    }
+                }
+            });
+        });
+
+        let time_left = move |deleted_at: u128| {
+            ns_to_str((deleted_at + TRASH_RETENTION_NS).saturating_sub(global_state.get_time_ns()))
+        };
+
+        let on_restore_post = move |post_key: String| {
+            api_restore
+                .restore_post(post_key.clone())
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Post(_)) => {
+                            trash.update(|v| v.posts.retain(|v| v.key != post_key));
+                        }
+                        Ok(res) => {
+                            error!("expected Post, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        let on_restore_comment = move |comment_key: String| {
+            api_restore
+                .restore_post_comment(comment_key.clone())
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Comment(_)) => {
+                            trash.update(|v| v.comments.retain(|v| v.key != comment_key));
+                        }
+                        Ok(res) => {
+                            error!("expected Comment, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        view! {
+            <h2 class="text-[1.3rem] text-base0A mt-[4rem]">"Trash"</h2>
+            <Show when=move || err.with(|v| !v.is_empty()) >
+                <span class="text-base08">{move || err.get()}</span>
+            </Show>
+            <Show when=move || trash.with(|v| v.posts.is_empty() && v.comments.is_empty()) && !api.is_pending_tracked() >
+                <span class="text-base03">"Nothing here, deleted posts and comments can be restored for 30 days."</span>
+            </Show>
+            <div class="flex flex-col gap-2">
+                <For
+                    each=move || trash.get().posts
+                    key=|post| post.key.clone()
+                    let(post)
+                >
+                    {
+                        let post_key = post.key.clone();
+                        view! {
+                            <div class="flex gap-2 items-center rounded-xl bg-base01 p-2">
+                                <span class="text-base0B">"Post"</span>
+                                <span class="truncate">{post.title.clone()}</span>
+                                <span class="ml-auto text-base03 shrink-0">{move || time_left(post.deleted_at)}" left"</span>
+                                <button class="text-base03 hover:text-base05 shrink-0" disabled=move || api_restore.is_pending_tracked() on:click=move |_| on_restore_post(post_key.clone()) >"Restore"</button>
+                            </div>
+                        }
+                    }
+                </For>
+                <For
+                    each=move || trash.get().comments
+                    key=|comment| comment.key.clone()
+                    let(comment)
+                >
+                    {
+                        let comment_key = comment.key.clone();
+                        view! {
+                            <div class="flex gap-2 items-center rounded-xl bg-base01 p-2">
+                                <span class="text-base0B">"Comment"</span>
+                                <span class="truncate">{comment.text.clone()}</span>
+                                <span class="ml-auto text-base03 shrink-0">{move || time_left(comment.deleted_at)}" left"</span>
+                                <button class="text-base03 hover:text-base05 shrink-0" disabled=move || api_restore.is_pending_tracked() on:click=move |_| on_restore_comment(comment_key.clone()) >"Restore"</button>
+                            </div>
+                        }
+                    }
+                </For>
+            </div>
+        }
+    }
+
+    /// past exports newest first, a new one can be requested once the previous one finished
+    #[component]
+    pub fn DataExport() -> impl IntoView {
+        let global_state = expect_context::<GlobalState>();
+        let api = ApiWeb::new();
+        let api_add = ApiWeb::new();
+        let exports = RwSignal::new(Vec::<UserDataExport>::new());
+        let err = RwSignal::new(String::new());
+
+        Effect::new(move || {
+            api.get_data_exports().send_web(move |result| async move {
+                match result {
+                    Ok(ServerRes::DataExports(v)) => {
+                        exports.set(v);
+                    }
+                    Ok(res) => {
+                        error!("expected DataExports, received {res:?}");
+                    }
+                    Err(e) => {
+                        err.set(e.to_string());
+                    }
+                }
+            });
+        });
+
+        let on_add = move |_| {
+            err.set(String::new());
+            api_add
+                .add_data_export()
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::DataExport(v)) => {
+                            exports.update(|exports| exports.insert(0, v));
+                        }
+                        Ok(res) => {
+                            error!("expected DataExport, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        let is_pending = move || exports.with(|v| v.iter().any(|v| v.status.is_pending()));
+
+        view! {
+            <h2 id="data_export" class="text-[1.3rem] text-base0A mt-[4rem]">"Your data"</h2>
+            <Show when=move || err.with(|v| !v.is_empty()) >
+                <span class="text-base08">{move || err.get()}</span>
+            </Show>
+            <div class="flex gap-2 items-center">
+                <span class="text-base03">"Download your profile, posts with their original files, comments, likes and emails we sent you as a zip."</span>
+                <button class="border-2 border-base0E text-base0E font-bold px-4 py-1 hover:bg-base02 shrink-0" disabled=move || api_add.is_pending_tracked() || is_pending() on:click=on_add >"Request export"</button>
+            </div>
+            <div class="flex flex-col gap-2">
+                <For
+                    each=move || exports.get()
+                    key=|export| (export.key.clone(), export.status)
+                    let(export)
+                >
+                    <div class="flex gap-2 items-center rounded-xl bg-base01 p-2">
+                        <span class="text-base03">{move || ns_to_str(global_state.get_time_ns().saturating_sub(export.created_at))}" ago"</span>
+                        {match export.status {
+                            DataExportStatus::Pending => view! {
+                                <span class="ml-auto text-base03">"Preparing, we will email you when it is ready"</span>
+                            }.into_any(),
+                            DataExportStatus::Failed => view! {
+                                <span class="ml-auto text-base08">"Failed, request a new one"</span>
+                            }.into_any(),
+                            DataExportStatus::Ready => view! {
+                                <span class="ml-auto text-base03">{format!("{} bytes", export.size_bytes)}</span>
+                                <span class="text-base03">{move || ns_to_str(export.expires.saturating_sub(global_state.get_time_ns()))}" left"</span>
+                                <a href=link_api_data_export(&export.key) rel="external" class="text-base0E hover:text-base05">"Download"</a>
+                            }.into_any(),
+                        }}
+                    </div>
+                </For>
+            </div>
+        }
+    }
+
+    /// personal access tokens for scripts, the secret is only shown right after creating one
+    #[component]
+    pub fn ApiTokens() -> impl IntoView {
+        let global_state = expect_context::<GlobalState>();
+        let api = ApiWeb::new();
+        let api_update = ApiWeb::new();
+        let tokens = RwSignal::new(Vec::<UserApiToken>::new());
+        let scopes = RwSignal::new(vec![ApiTokenScope::Read]);
+        let expiry_days = RwSignal::new(SETTINGS_API_TOKEN_EXPIRY_DAYS[1]);
+        let secret = RwSignal::new(None::<String>);
+        let name = NodeRef::<html::Input>::new();
+        let err = RwSignal::new(String::new());
+
+        Effect::new(move || {
+            api.get_api_tokens().send_web(move |result| async move {
+                match result {
+                    Ok(ServerRes::ApiTokens(v)) => {
+                        tokens.set(v);
+                    }
+                    Ok(res) => {
+                        error!("expected ApiTokens, received {res:?}");
+                    }
+                    Err(e) => {
+                        err.set(e.to_string());
+                    }
+                }
+            });
+        });
+
+        let on_add = move |e: SubmitEvent| {
             e.prevent_default();
-            // let (Some(title), Some(description), Some(tags)) = (
-            //     upload_title.get_untracked() as Option<HtmlInputElement>,
-            //     upload_description.get_untracked() as Option<HtmlTextAreaElement>,
-            //     upload_tags.get_untracked() as Option<HtmlTextAreaElement>,
-            // ) else {
-            //     return;
-            // };
-            //
-            // let title = title.value().trim();
-            // let description = description.value().trim();
-            // let tags = tags.value().trim();
-            //
-            // let title_errs = proccess_post_title(title);
-            // let description_errs = proccess_post_description(description);
-            // let tags_errs = proccess_post_tags(tags);
-            //
-            // upload_title_err.set(title_errs.clone().err().unwrap_or_default());
-            // upload_description_err.set(description_errs.clone().err().unwrap_or_default());
-            // upload_image_err.set(String::new());
-            // upload_general_err.set(String::new());
-            // let (Ok(title), Ok(description)) = (title, description) else {
-            //     return;
-            // };
-            //
-            // file_upload.upload();
+            let Some(name) = name.get_untracked() else {
+                return;
+            };
+            let days = expiry_days.get_untracked();
+            let expires = match days {
+                0 => 0,
+                days => global_state.get_time_ns() + days * 24 * 60 * 60 * 1_000_000_000,
+            };
+            err.set(String::new());
+            api_update
+                .add_api_token(name.value(), scopes.get_untracked(), expires)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::ApiToken { token, secret: v }) => {
+                            tokens.update(|tokens| tokens.insert(0, token));
+                            secret.set(Some(v));
+                        }
+                        Ok(res) => {
+                            error!("expected ApiToken, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
         };
 
-        // let api_post = controller::post::route::add::client.ground();
-        // let on_upload = move |e: SubmitEvent| {
-        //     e.prevent_default();
-        //     trace!("uploading...");
-        //     let navigate = navigate.clone();
-        //     let (Some(files), Some(title), Some(description), Some(tags)) = (
-        //         (upload_image.get_untracked() as Option<HtmlInputElement>)
-        //             .and_then(|f: HtmlInputElement| f.files())
-        //             .map(|f| f.get_files()),
-        //         upload_title.get_untracked() as Option<HtmlInputElement>,
-        //         upload_description.get_untracked() as Option<HtmlTextAreaElement>,
-        //         upload_tags.get_untracked() as Option<HtmlTextAreaElement>,
-        //     ) else {
-        //         return;
-        //     };
-        //
-        //     let title = proccess_post_title(title.value());
-        //     let description = proccess_post_description(description.value());
-        //     let tags = tags.value();
-        //
-        //     upload_title_err.set(title.clone().err().unwrap_or_default());
-        //     upload_description_err.set(description.clone().err().unwrap_or_default());
-        //     upload_image_err.set(String::new());
-        //     upload_general_err.set(String::new());
-        //     let (Ok(title), Ok(description)) = (title, description) else {
-        //         return;
-        //     };
+        let on_delete = move |token_key: String| {
+            err.set(String::new());
+            api_update
+                .delete_api_token(token_key.clone())
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Ok) => {
+                            tokens.update(|tokens| tokens.retain(|v| v.key != token_key));
+                        }
+                        Ok(res) => {
+                            error!("expected Ok, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        let on_scope = move |scope: ApiTokenScope, checked: bool| {
+            scopes.update(|scopes| {
+                scopes.retain(|v| *v != scope);
+                if checked {
+                    scopes.push(scope);
+                }
+            });
+        };
+
+        view! {
+            <h2 id="api_tokens" class="text-[1.3rem] text-base0A mt-[4rem]">"API tokens"</h2>
+            <Show when=move || err.with(|v| !v.is_empty()) >
+                <span class="text-base08">{move || err.get()}</span>
+            </Show>
+            <form class="flex flex-col gap-2" on:submit=on_add>
+                <span class="text-base03">"Tokens let scripts and bots use your account, send them as an \"Authorization: Bearer\" header."</span>
+                {ApiTokenScope::iter()
+                    .filter(|scope| !scope.is_admin() || global_state.get_role_tracked().is_staff())
+                    .map(|scope| view! {
+                        <label class="flex gap-2 items-center">
+                            <input
+                                type="checkbox"
+                                prop:checked=move || scopes.with(|v| v.contains(&scope))
+                                on:change=move |e| on_scope(scope, event_target_checked(&e))
+                            />
+                            <span>{scope.to_string()}</span>
+                            <span class="text-base03">{scope.label()}</span>
+                        </label>
+                    }).collect_view()}
+                <div class="flex gap-2">
+                    <input node_ref=name placeholder="name" type="text" class="bg-base01 text-base0B w-full pl-2" />
+                    <select
+                        class="bg-base01 text-base0B px-2"
+                        on:change=move |e| expiry_days.set(event_target_value(&e).parse().unwrap_or_default())
+                    >
+                        {SETTINGS_API_TOKEN_EXPIRY_DAYS.iter().map(|days| view! {
+                            <option value=days.to_string() selected={*days == expiry_days.get_untracked()}>
+                                {match days {
+                                    0 => "Never expires".to_string(),
+                                    days => format!("{days} days"),
+                                }}
+                            </option>
+                        }).collect_view()}
+                    </select>
+                    <input type="submit" value="Create" disabled=move || api_update.is_pending_tracked() class="border-2 border-base0E text-base0E font-bold px-4 py-1 hover:bg-base02 cursor-pointer" />
+                </div>
+            </form>
+            <Show when=move || secret.with(|v| v.is_some()) >
+                <div class="flex flex-col gap-2 rounded-xl bg-base01 p-2">
+                    <span class="text-base0A">"Copy the token now, it won't be shown again."</span>
+                    <input readonly type="text" prop:value=move || secret.get().unwrap_or_default() class="bg-base02 text-base0B w-full pl-2" />
+                </div>
+            </Show>
+            <div class="flex flex-col gap-2">
+                <For
+                    each=move || tokens.get()
+                    key=|token| token.key.clone()
+                    let(token)
+                >
+                    {
+                        let token_key = token.key.clone();
+                        let last_used_at = token.last_used_at;
+                        let expires = token.expires;
+                        let scopes = token.scopes.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ");
+                        view! {
+                            <div class="flex gap-2 items-center rounded-xl bg-base01 p-2">
+                                <span class="text-base0B">{token.name.clone()}</span>
+                                <span class="text-base03">{scopes}</span>
+                                <span class="ml-auto text-base03">
+                                    {move || match last_used_at {
+                                        0 => "never used".to_string(),
+                                        v => format!("used {} ago", ns_to_str(global_state.get_time_ns().saturating_sub(v))),
+                                    }}
+                                </span>
+                                <span class="text-base03">
+                                    {move || match expires {
+                                        0 => "no expiry".to_string(),
+                                        v if v <= global_state.get_time_ns() => "expired".to_string(),
+                                        v => format!("{} left", ns_to_str(v.saturating_sub(global_state.get_time_ns()))),
+                                    }}
+                                </span>
+                                <button class="text-base03 hover:text-base05" disabled=move || api_update.is_pending_tracked() on:click=move |_| on_delete(token_key.clone()) >"Revoke"</button>
+                            </div>
+                        }
+                    }
+                </For>
+            </div>
+        }
+    }
+
+    /// outgoing webhooks and their delivery log, staff can also subscribe to the whole site
+    #[component]
+    pub fn Webhooks() -> impl IntoView {
+        let global_state = expect_context::<GlobalState>();
+        let api = ApiWeb::new();
+        let api_update = ApiWeb::new();
+        let webhooks = RwSignal::new(Vec::<UserWebhook>::new());
+        let events = RwSignal::new(vec![WebhookEvent::PostCreated]);
+        let admin = RwSignal::new(false);
+        let deliveries = RwSignal::new(None::<(String, Vec<UserWebhookDelivery>)>);
+        let url = NodeRef::<html::Input>::new();
+        let err = RwSignal::new(String::new());
+
+        Effect::new(move || {
+            api.get_webhooks().send_web(move |result| async move {
+                match result {
+                    Ok(ServerRes::Webhooks(v)) => {
+                        webhooks.set(v);
+                    }
+                    Ok(res) => {
+                        error!("expected Webhooks, received {res:?}");
+                    }
+                    Err(e) => {
+                        err.set(e.to_string());
+                    }
+                }
+            });
+        });
+
+        let on_add = move |e: SubmitEvent| {
+            e.prevent_default();
+            let Some(url) = url.get_untracked() else {
+                return;
+            };
+            let admin = admin.get_untracked();
+            let events = events
+                .get_untracked()
+                .into_iter()
+                .filter(|event| admin || !event.is_user_registered())
+                .collect();
+            err.set(String::new());
+            api_update
+                .add_webhook(url.value(), events, admin)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Webhook(webhook)) => {
+                            webhooks.update(|webhooks| webhooks.insert(0, webhook));
+                        }
+                        Ok(res) => {
+                            error!("expected Webhook, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        let on_delete = move |webhook_key: String| {
+            err.set(String::new());
+            api_update
+                .delete_webhook(webhook_key.clone())
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Ok) => {
+                            webhooks.update(|webhooks| webhooks.retain(|v| v.key != webhook_key));
+                            deliveries.update(|v| {
+                                if v.as_ref().is_some_and(|(key, _)| *key == webhook_key) {
+                                    *v = None;
+                                }
+                            });
+                        }
+                        Ok(res) => {
+                            error!("expected Ok, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        let on_deliveries = move |webhook_key: String| {
+            err.set(String::new());
+            api_update
+                .get_webhook_deliveries(
+                    webhook_key.clone(),
+                    SETTINGS_WEBHOOK_DELIVERIES_FETCH_COUNT,
+                    TimeRange::None,
+                )
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::WebhookDeliveries(v)) => {
+                            deliveries.set(Some((webhook_key, v)));
+                        }
+                        Ok(res) => {
+                            error!("expected WebhookDeliveries, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        let on_event = move |event: WebhookEvent, checked: bool| {
+            events.update(|events| {
+                events.retain(|v| *v != event);
+                if checked {
+                    events.push(event);
+                }
+            });
+        };
+
+        view! {
+            <h2 id="webhooks" class="text-[1.3rem] text-base0A mt-[4rem]">"Webhooks"</h2>
+            <Show when=move || err.with(|v| !v.is_empty()) >
+                <span class="text-base08">{move || err.get()}</span>
+            </Show>
+            <form class="flex flex-col gap-2" on:submit=on_add>
+                <span class="text-base03">"Every event is POSTed as json, check the \"X-Artbounty-Signature\" header against an HMAC-SHA256 of the body keyed with the webhook secret."</span>
+                <Show when=move || global_state.get_role_tracked().is_staff() >
+                    <label class="flex gap-2 items-center">
+                        <input
+                            type="checkbox"
+                            prop:checked=move || admin.get()
+                            on:change=move |e| admin.set(event_target_checked(&e))
+                        />
+                        <span>"Admin"</span>
+                        <span class="text-base03">"Get events from the whole site instead of only your posts"</span>
+                    </label>
+                </Show>
+                {WebhookEvent::iter()
+                    .map(|event| view! {
+                        <label class="flex gap-2 items-center" class:hidden=move || event.is_user_registered() && !admin.get()>
+                            <input
+                                type="checkbox"
+                                prop:checked=move || events.with(|v| v.contains(&event))
+                                on:change=move |e| on_event(event, event_target_checked(&e))
+                            />
+                            <span>{event.to_string()}</span>
+                            <span class="text-base03">{event.label()}</span>
+                        </label>
+                    }).collect_view()}
+                <div class="flex gap-2">
+                    <input node_ref=url placeholder="https://" type="text" class="bg-base01 text-base0B w-full pl-2" />
+                    <input type="submit" value="Add" disabled=move || api_update.is_pending_tracked() class="border-2 border-base0E text-base0E font-bold px-4 py-1 hover:bg-base02 cursor-pointer" />
+                </div>
+            </form>
+            <div class="flex flex-col gap-2">
+                <For
+                    each=move || webhooks.get()
+                    key=|webhook| webhook.key.clone()
+                    let(webhook)
+                >
+                    {
+                        let delete_key = webhook.key.clone();
+                        let deliveries_key = webhook.key.clone();
+                        let is_admin = webhook.admin;
+                        let event_names = webhook.events.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ");
+                        view! {
+                            <div class="flex flex-col gap-2 rounded-xl bg-base01 p-2">
+                                <div class="flex gap-2 items-center">
+                                    <span class="text-base0B break-all">{webhook.url.clone()}</span>
+                                    <Show when=move || is_admin >
+                                        <span class="text-base0A">"admin"</span>
+                                    </Show>
+                                    <button class="ml-auto text-base03 hover:text-base05" disabled=move || api_update.is_pending_tracked() on:click=move |_| on_deliveries(deliveries_key.clone()) >"Deliveries"</button>
+                                    <button class="text-base03 hover:text-base05" disabled=move || api_update.is_pending_tracked() on:click=move |_| on_delete(delete_key.clone()) >"Delete"</button>
+                                </div>
+                                <span class="text-base03">{event_names}</span>
+                                <input readonly type="text" prop:value=webhook.secret.clone() class="bg-base02 text-base0B w-full pl-2" />
+                            </div>
+                        }
+                    }
+                </For>
+            </div>
+            <Show when=move || deliveries.with(|v| v.is_some()) >
+                <div class="flex flex-col gap-1 rounded-xl bg-base01 p-2">
+                    <Show when=move || deliveries.with(|v| v.as_ref().is_some_and(|(_, v)| v.is_empty())) >
+                        <span class="text-base03">"No deliveries yet."</span>
+                    </Show>
+                    <For
+                        each=move || deliveries.get().map(|(_, v)| v).unwrap_or_default()
+                        key=|delivery| (delivery.key.clone(), delivery.modified_at)
+                        let(delivery)
+                    >
+                        {
+                            let created_at = delivery.created_at;
+                            view! {
+                                <div class="flex gap-2 items-center">
+                                    <span class="text-base0B">{delivery.event.clone()}</span>
+                                    <span class="text-base03">{delivery.status.to_string()}</span>
+                                    <span class="text-base03">
+                                        {match delivery.response_code {
+                                            0 => "no response".to_string(),
+                                            v => v.to_string(),
+                                        }}
+                                    </span>
+                                    <span class="text-base03">{format!("{} attempts", delivery.attempts)}</span>
+                                    <span class="text-base03 truncate" title=delivery.response.clone()>{delivery.response.clone()}</span>
+                                    <span class="ml-auto text-base03">{move || format!("{} ago", ns_to_str(global_state.get_time_ns().saturating_sub(created_at)))}</span>
+                                </div>
+                            }
+                        }
+                    </For>
+                </div>
+            </Show>
+        }
+    }
+
+    /// asks for the password, the deletion is only scheduled after the emailed link is opened
+    #[component]
+    pub fn DeleteAccount() -> impl IntoView {
+        let global_state = expect_context::<GlobalState>();
+        let navigate = leptos_router::hooks::use_navigate();
+        let query = leptos_router::hooks::use_query_map();
+        let api = ApiWeb::new();
+        let api_update = ApiWeb::new();
+        let deletion = RwSignal::new(None::<UserAccountDeletion>);
+        let mode = RwSignal::new(AccountDeletionMode::default());
+        let password = NodeRef::<html::Input>::new();
+        let err = RwSignal::new(String::new());
+
+        let on_deletion = move |result: Result<ServerRes, ServerErr>| async move {
+            match result {
+                Ok(ServerRes::AccountDeletion(v)) => {
+                    deletion.set(v);
+                }
+                Ok(res) => {
+                    error!("expected AccountDeletion, received {res:?}");
+                }
+                Err(e) => {
+                    err.set(e.to_string());
+                }
+            }
+        };
+
+        Effect::new(move || {
+            let confirm_key = query.with(|v| v.get("delete_account"));
+            match confirm_key {
+                Some(confirm_key) => {
+                    api_update
+                        .confirm_account_deletion(confirm_key)
+                        .send_web(on_deletion);
+                    navigate(
+                        &link_settings(),
+                        NavigateOptions {
+                            replace: true,
+                            ..Default::default()
+                        },
+                    );
+                }
+                None => {
+                    api.get_account_deletion().send_web(on_deletion);
+                }
+            }
+        });
+
+        let on_request = move |e: SubmitEvent| {
+            e.prevent_default();
+            let Some(password) = password.get_untracked() else {
+                return;
+            };
+            err.set(String::new());
+            api_update
+                .request_account_deletion(password.value(), mode.get_untracked())
+                .send_web(on_deletion);
+        };
+
+        let on_cancel = move |_| {
+            err.set(String::new());
+            api_update
+                .cancel_account_deletion()
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Ok) => {
+                            deletion.set(None);
+                        }
+                        Ok(res) => {
+                            error!("expected Ok, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        view! {
+            <h2 class="text-[1.3rem] text-base08 mt-[4rem]">"Delete account"</h2>
+            <Show when=move || err.with(|v| !v.is_empty()) >
+                <span class="text-base08">{move || err.get()}</span>
+            </Show>
+            {move || match deletion.get() {
+                Some(deletion) if deletion.confirmed => view! {
+                    <div class="flex gap-2 items-center rounded-xl bg-base01 p-2">
+                        <span>"Your account will be deleted in "{move || ns_to_str(deletion.delete_at.saturating_sub(global_state.get_time_ns()))}". "{deletion.mode.label()}"."</span>
+                        <button class="ml-auto text-base0E hover:text-base05 shrink-0" disabled=move || api_update.is_pending_tracked() on:click=on_cancel >"Cancel"</button>
+                    </div>
+                }.into_any(),
+                Some(deletion) => view! {
+                    <div class="flex gap-2 items-center rounded-xl bg-base01 p-2">
+                        <span>"We sent a confirmation link to your email. "{deletion.mode.label()}"."</span>
+                        <button class="ml-auto text-base0E hover:text-base05 shrink-0" disabled=move || api_update.is_pending_tracked() on:click=on_cancel >"Cancel"</button>
+                    </div>
+                }.into_any(),
+                None => view! {
+                    <form class="flex flex-col gap-2" on:submit=on_request>
+                        <span class="text-base03">"After confirming by email you have 14 days to change your mind, then the account is erased for good."</span>
+                        {AccountDeletionMode::iter().map(|v| view! {
+                            <label class="flex gap-2 items-center">
+                                <input
+                                    type="radio"
+                                    name="delete_account_mode"
+                                    prop:checked=move || mode.get() == v
+                                    on:change=move |_| mode.set(v)
+                                />
+                                {v.label()}
+                            </label>
+                        }).collect_view()}
+                        <div class="flex gap-2">
+                            <input node_ref=password placeholder="current password" type="password" class="bg-base01 text-base0B w-full pl-2" />
+                            <input type="submit" value="Delete" disabled=move || api_update.is_pending_tracked() class="border-2 border-base08 text-base08 font-bold px-4 py-1 hover:bg-base02 cursor-pointer" />
+                        </div>
+                    </form>
+                }.into_any(),
+            }}
+        }
+    }
+
+    /// logins and account changes, newest first
+    #[component]
+    pub fn SecurityHistory() -> impl IntoView {
+        let api = ApiWeb::new();
+        let events = RwSignal::new(Vec::<UserAuditEvent>::new());
+        let finished = RwSignal::new(false);
+        let err = RwSignal::new(String::new());
+
+        let fetch = move |time_range: TimeRange| {
+            api.get_audit_events(SETTINGS_AUDIT_FETCH_COUNT, time_range, Order::ThreeTwoOne)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::AuditEvents(v)) => {
+                            finished.set(v.len() < SETTINGS_AUDIT_FETCH_COUNT);
+                            events.update(|events| events.extend(v));
+                        }
+                        Ok(res) => {
+                            error!("expected AuditEvents, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        Effect::new(move || {
+            fetch(TimeRange::None);
+        });
+
+        let on_more = move |_| {
+            let Some(last) = events.with_untracked(|v| v.last().map(|v| v.created_at)) else {
+                return;
+            };
+            fetch(TimeRange::Less(last));
+        };
+
+        view! {
+            <h2 class="text-[1.3rem] text-base0A mt-[4rem]">"Security"</h2>
+            <Show when=move || err.with(|v| !v.is_empty()) >
+                <span class="text-base08">{move || err.get()}</span>
+            </Show>
+            <div class="flex flex-col gap-2 mb-[4rem]">
+                <For
+                    each=move || events.get()
+                    key=|event| event.key.clone()
+                    let(event)
+                >
+                    <AuditEventRow event />
+                </For>
+                <Show when=move || !finished.get() && !api.is_pending_tracked() >
+                    <button class="border-2 border-base05 px-2 hover:bg-base02" on:click=on_more >"More"</button>
+                </Show>
+            </div>
+        }
+    }
+}
+pub mod upload {
+
+    use std::io::Bytes;
+    use std::iter;
+
+    use crate::api::{Api, ApiWeb, ServerAddPostErr, ServerErr, ServerReqImg};
+    use crate::path::link_post;
+    use crate::valid::auth::{proccess_post_description, proccess_post_tags, proccess_post_title};
+    use crate::view::app::components::nav::Nav;
+    use crate::view::app::hook::api_post_file_upload::FileUpload;
+    use crate::view::toolbox::prelude::*;
+    use leptos::prelude::*;
+    use leptos::{Params, task::spawn_local};
+    use leptos_router::{hooks::use_params, params::Params};
+
+    // use async_stream::stream;
+    use futures_util::pin_mut;
+    use futures_util::stream::StreamExt;
+    use leptos_router::hooks::use_query;
+    use tracing::{error, trace};
+    use wasm_bindgen::prelude::*;
+    // use wasm_bindgen::{JsCast, JsValue, Closure};
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::js_sys::{Array, Function, JsString, Number, Promise, Reflect};
+    use web_sys::{
+        Blob, File, FormData, HtmlInputElement, HtmlTextAreaElement, ProgressEvent, RequestInit,
+        RequestMode, SubmitEvent, TransformStream, TransformStreamDefaultController, Transformer,
+        XmlHttpRequest,
+    };
+
+    #[derive(Params, PartialEq, Clone)]
+    pub struct UserParams {
+        pub username: Option<String>,
+    }
+
+    #[component]
+    pub fn Page() -> impl IntoView {
+        let main_ref = NodeRef::new();
+        let upload_title = NodeRef::new();
+        let upload_title_err = RwSignal::new(String::new());
+        let upload_image = NodeRef::new();
+        let upload_image_err = RwSignal::new(String::new());
+        let upload_description = NodeRef::new();
+        let upload_description_err = RwSignal::new(String::new());
+        let upload_tags = NodeRef::new();
+        let upload_tags_err = RwSignal::new(String::new());
+        let upload_general_err = RwSignal::new(String::new());
+        let api = ApiWeb::new();
+        let navigate = leptos_router::hooks::use_navigate();
+
+        let file_upload = FileUpload::new();
+        let on_file_change = move |_| {
+            let Some(files) = (upload_image.get_untracked() as Option<HtmlInputElement>)
+                .and_then(|f: HtmlInputElement| f.files())
+                .map(|f| f.get_files())
+            else {
+                return;
+            };
+            // file_upload.clear();
+            // file_upload.select(&files);
+            trace!("fle selection changed i think");
+        };
+
+        let on_upload = move |e: SubmitEvent| {
+            e.prevent_default();
+            // let (Some(title), Some(description), Some(tags)) = (
+            //     upload_title.get_untracked() as Option<HtmlInputElement>,
+            //     upload_description.get_untracked() as Option<HtmlTextAreaElement>,
+            //     upload_tags.get_untracked() as Option<HtmlTextAreaElement>,
+            // ) else {
+            //     return;
+            // };
+            //
+            // let title = title.value().trim();
+            // let description = description.value().trim();
+            // let tags = tags.value().trim();
+            //
+            // let title_errs = proccess_post_title(title);
+            // let description_errs = proccess_post_description(description);
+            // let tags_errs = proccess_post_tags(tags);
+            //
+            // upload_title_err.set(title_errs.clone().err().unwrap_or_default());
+            // upload_description_err.set(description_errs.clone().err().unwrap_or_default());
+            // upload_image_err.set(String::new());
+            // upload_general_err.set(String::new());
+            // let (Ok(title), Ok(description)) = (title, description) else {
+            //     return;
+            // };
+            //
+            // file_upload.upload();
+        };
+
+        // let api_post = controller::post::route::add::client.ground();
+        // let on_upload = move |e: SubmitEvent| {
+        //     e.prevent_default();
+        //     trace!("uploading...");
+        //     let navigate = navigate.clone();
+        //     let (Some(files), Some(title), Some(description), Some(tags)) = (
+        //         (upload_image.get_untracked() as Option<HtmlInputElement>)
+        //             .and_then(|f: HtmlInputElement| f.files())
+        //             .map(|f| f.get_files()),
+        //         upload_title.get_untracked() as Option<HtmlInputElement>,
+        //         upload_description.get_untracked() as Option<HtmlTextAreaElement>,
+        //         upload_tags.get_untracked() as Option<HtmlTextAreaElement>,
+        //     ) else {
+        //         return;
+        //     };
+        //
+        //     let title = proccess_post_title(title.value());
+        //     let description = proccess_post_description(description.value());
+        //     let tags = tags.value();
+        //
+        //     upload_title_err.set(title.clone().err().unwrap_or_default());
+        //     upload_description_err.set(description.clone().err().unwrap_or_default());
+        //     upload_image_err.set(String::new());
+        //     upload_general_err.set(String::new());
+        //     let (Ok(title), Ok(description)) = (title, description) else {
+        //         return;
+        //     };
         //
         //     // let file = files[0].clone().into();
         //
@@ -753,171 +1680,1775 @@ pub mod upload {
         //     });
         // };
 
-        let files_selected = move || {
-            file_upload
-                .post_files
-                .get()
-                .into_iter()
-                .enumerate()
-                .map(|(i, file_progress)| {
-                    let name = file_progress.file_name.clone();
-                    let percent = file_progress.get_upload_percentage().to_string();
-                    // match self {
-                    //     UploadProgress::Selected { file_name, .. } => file_name == name,
-                    //     UploadProgress::Uploading { file_name, .. } => file_name == name,
-                    //     UploadProgress::Completed { file_name, .. } => file_name == name,
-                    // }
-                    view! {
-                        <div class="px-2 py-1 bg-base02">
-                            <span>{ name }</span>
-                            <span> " " { percent }" / 100"</span>
-                            // <img id=move || format!("id{i}") class="" src=url />
-                        </div>
+        let files_selected = move || {
+            file_upload
+                .post_files
+                .get()
+                .into_iter()
+                .enumerate()
+                .map(|(i, file_progress)| {
+                    let name = file_progress.file_name.clone();
+                    let percent = file_progress.get_upload_percentage().to_string();
+                    // match self {
+                    //     UploadProgress::Selected { file_name, .. } => file_name == name,
+                    //     UploadProgress::Uploading { file_name, .. } => file_name == name,
+                    //     UploadProgress::Completed { file_name, .. } => file_name == name,
+                    // }
+                    view! {
+                        <div class="px-2 py-1 bg-base02">
+                            <span>{ name }</span>
+                            <span> " " { percent }" / 100"</span>
+                            // <img id=move || format!("id{i}") class="" src=url />
+                        </div>
+                    }
+                })
+                .collect_view()
+        };
+
+        view! {
+            <main node_ref=main_ref class="grid grid-rows-[auto_1fr] h-screen">
+                <Nav/>
+                <div>
+                    <div class=move||format!("mx-auto text-[1.5rem] {}", if api.is_pending_tracked() {""} else {"hidden"})>
+                        <h1>"LOADING..."</h1>
+                    </div>
+                    <form method="POST" action="" on:submit=on_upload class=move || format!("flex flex-col px-[4rem] max-w-[30rem] mx-auto w-full {}", if !api.is_pending_tracked() {""} else {"hidden"})>
+                        <h1 class="text-[1.5rem]  text-center my-[4rem]">"UPLOAD"</h1>
+                        <div class=move||format!("text-red-600 text-center {}", if upload_general_err.with(|v| v.is_empty()) {"hidden"} else {""})>{move || { upload_general_err.get() }}</div>
+                        <div class="flex flex-col gap-4">
+                            <div class="flex flex-col gap-0">
+                                <label for="title" class="text-[1.2rem] ">"Title"</label>
+                                <div class=move || format!("text-red-600 transition-[font-size] duration-300 ease-in {}", if upload_title_err.with(|err| err.is_empty()) {"text-[0rem]"} else {"text-[1rem]"}) >
+                                    <ul class="list-disc ml-[1rem]">
+                                        {move || upload_title_err.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
+                                    </ul>
+                                </div>
+                                <input placeholder="Funny looking cat" id="title" name="title" node_ref=upload_title type="text" class="border-b-2 border-base05 w-full mt-1 " />
+                            </div>
+                            <div class="flex flex-col gap-0">
+                                <label for="image" class="text-[1.2rem] ">"Images"</label>
+                                <div class=move || format!("text-red-600 transition-[font-size] duration-300 ease-in {}", if upload_image_err.with(|err| err.is_empty()) {"text-[0rem]"} else {"text-[1rem]"}) >
+                                    <ul class="list-disc ml-[1rem]">
+                                        {move || upload_image_err.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
+                                    </ul>
+                                </div>
+                                { files_selected }
+                                <input on:change=on_file_change type="file" id="image" name="image" node_ref=upload_image multiple />
+                            </div>
+                            <div class="flex flex-col gap-0">
+                                <label for="description" class="text-[1.2rem] ">"Description"</label>
+                                <div class=move || format!("text-red-600 transition-[font-size] duration-300 ease-in {}", if upload_description_err.with(|err| err.is_empty()) {"text-[0rem]"} else {"text-[1rem]"}) >
+                                    <ul class="list-disc ml-[1rem]">
+                                        {move || upload_description_err.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
+                                    </ul>
+                                </div>
+                                <textarea class="border-l-2 border-base05 pl-2 bg-base05" node_ref=upload_description id="description" name="description" rows="4" cols="50">""</textarea>
+                            </div>
+                            <div class="flex flex-col gap-0">
+                                <label for="tags" class="text-[1.2rem] ">"Tags"</label>
+                                <div class=move || format!("text-red-600 transition-[font-size] duration-300 ease-in {}", if upload_tags_err.with(|err| err.is_empty()) {"text-[0rem]"} else {"text-[1rem]"}) >
+                                    <ul class="list-disc ml-[1rem]">
+                                        {move || upload_tags_err.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
+                                    </ul>
+                                </div>
+                                <textarea class="border-l-2 border-base05 pl-2 bg-base05" node_ref=upload_tags id="tags" name="tags" rows="1" cols="50">""</textarea>
+                            </div>
+                        </div>
+                        <div class="flex flex-col gap-[1.3rem] mx-auto my-[4rem] text-center">
+                            <input id="upload_btn" type="submit" value="Post" class="border-2 border-base05 text-[1.3rem] font-bold px-4 py-1 hover:bg-base05 hover:text-gray-950"/>
+                        </div>
+                    </form>
+                </div>
+            </main>
+        }
+    }
+}
+pub mod profile {
+
+    use crate::api::Api;
+    use crate::api::ApiWeb;
+    use crate::api::Order;
+    use crate::api::ServerRes;
+    use crate::api::TimeRange;
+    use crate::api::UserFollow;
+    use crate::api::shared::collection::UserCollection;
+    use crate::api::shared::report::ReportTarget;
+    use crate::path::{link_collection, link_conversation, link_user};
+    use crate::view::app::GlobalState;
+    use crate::view::app::components::gallery::Gallery;
+    use crate::view::app::components::nav::Nav;
+    use crate::view::app::components::page_meta::{MetaLoader, PageMetaTags};
+    use crate::view::app::components::report_btn::ReportBtn;
+    use crate::view::app::components::user_block_btn::UserBlockBtn;
+    use crate::view::app::hook::use_user_follow::{UserFollowStage, use_user_follow};
+    use crate::view::toolbox::prelude::*;
+    use leptos::Params;
+    use leptos::prelude::*;
+    use leptos_router::{hooks::use_params, params::Params};
+    use std::rc::Rc;
+    use tracing::error;
+    use tracing::trace;
+
+    pub const PROFILE_FOLLOWS_FETCH_COUNT: usize = 25;
+    pub const PROFILE_COLLECTIONS_FETCH_COUNT: usize = 25;
+
+    #[derive(Params, PartialEq, Clone)]
+    pub struct UserParams {
+        pub username: Option<String>,
+    }
+
+    #[derive(
+        Debug,
+        Default,
+        Clone,
+        Copy,
+        PartialEq,
+        PartialOrd,
+        strum::EnumString,
+        strum::Display,
+        strum::EnumIter,
+        strum::EnumIs,
+    )]
+    #[strum(serialize_all = "lowercase")]
+    pub enum ProfileFollows {
+        #[default]
+        None,
+        Followers,
+        Following,
+    }
+
+    #[component]
+    pub fn Page() -> impl IntoView {
+        let main_ref = NodeRef::new();
+        let param = use_params::<UserParams>();
+        let param_username =
+            Memo::new(move |_| param.read().as_ref().ok().and_then(|v| v.username.clone()));
+        let user_username = RwSignal::new(None::<String>);
+        let user_follow = use_user_follow(param_username);
+        let follows = RwQuery::<ProfileFollows>::new("follows");
+        let meta_loader = MetaLoader::new();
+        let meta = Resource::new_blocking(move || param_username.get().unwrap_or_default(), {
+            let meta_loader = meta_loader.clone();
+            move |username| meta_loader.clone().user(username)
+        });
+        let profile = Resource::new_blocking(
+            move || param_username.get().unwrap_or_default(),
+            move |username| meta_loader.clone().profile(username),
+        );
+        let profile_username = move || {
+            profile
+                .get()
+                .map(|username| username.unwrap_or_else(|| "Not Found".to_string()))
+        };
+
+        // the gallery only loads in the browser
+        Effect::new(move || {
+            let Some(username) = profile_username() else {
+                return;
+            };
+            user_username.set(Some(username));
+        });
+
+        let on_follows = move |kind: ProfileFollows| {
+            if follows.get_or_default_untracked() == kind {
+                follows.clear();
+            } else {
+                follows.set(kind);
+            }
+        };
+
+        let global_state = expect_context::<GlobalState>();
+        let navigate = leptos_router::hooks::use_navigate();
+        let api_message = ApiWeb::new();
+        let is_message_hidden = move || {
+            let is_logged_in = global_state.is_logged_in().unwrap_or_default();
+            let is_self = global_state.get_username_tracked() == param_username.get();
+            !is_logged_in || is_self
+        };
+        let on_message = move |_| {
+            let Some(username) = param_username.get_untracked() else {
+                return;
+            };
+            let navigate = navigate.clone();
+            api_message
+                .add_conversation(username)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Conversation(conversation)) => {
+                            navigate(&link_conversation(conversation.key), Default::default());
+                        }
+                        Ok(res) => {
+                            error!("expected Conversation, received {res:?}");
+                        }
+                        Err(err) => {
+                            error!("profile: {err}");
+                        }
+                    }
+                });
+        };
+
+        let follow_btn_text = move || match user_follow.stage.run() {
+            UserFollowStage::Loading => "...",
+            UserFollowStage::Followed => "Unfollow",
+            UserFollowStage::Unfollowed | UserFollowStage::Hidden => "Follow",
+        };
+
+        view! {
+            <PageMetaTags meta />
+            <main node_ref=main_ref class="grid grid-rows-[auto_auto_1fr] h-screen">
+                <Nav/>
+                <div class="flex flex-col gap-2 px-4">
+                    <div class="flex flex-wrap items-center gap-4">
+                        <Suspense>
+                            <h1>{profile_username}</h1>
+                        </Suspense>
+                        <button class="text-base03 hover:text-base05" on:click=move |_| on_follows(ProfileFollows::Followers) >
+                            {move || user_follow.followers.get()}" followers"
+                        </button>
+                        <button class="text-base03 hover:text-base05" on:click=move |_| on_follows(ProfileFollows::Following) >
+                            {move || user_follow.following.get()}" following"
+                        </button>
+                        <button
+                            class=move || format!("px-2 border-2 border-base05 rounded-lg {}", if user_follow.stage.run().is_hidden() { "hidden" } else { "" })
+                            on:click=move |_| user_follow.on_follow.run()
+                        >
+                            {follow_btn_text}
+                        </button>
+                        <button
+                            class=move || format!("px-2 border-2 border-base05 rounded-lg {}", if is_message_hidden() { "hidden" } else { "" })
+                            on:click=on_message
+                        >
+                            "Message"
+                        </button>
+                        <Show when=move || !is_message_hidden() >
+                            <ReportBtn target=Signal::derive(move || param_username.get().map(|username| ReportTarget::User { username })) />
+                            <UserBlockBtn username=param_username />
+                        </Show>
+                    </div>
+                    <Show when=move || !follows.get_or_default().is_none() >
+                        <FollowList username=param_username kind=Signal::derive(move || follows.get_or_default()) />
+                    </Show>
+                    <CollectionList username=param_username />
+                </div>
+                <Gallery row_height=250 username=user_username />
+            </main>
+        }
+    }
+
+    #[component]
+    pub fn CollectionList(username: Memo<Option<String>>) -> impl IntoView {
+        let api = ApiWeb::new();
+        let collections = RwSignal::new(Vec::<UserCollection>::new());
+        let has_more = RwSignal::new(false);
+
+        let fetch = move |username: String, time_range: TimeRange| {
+            api.get_user_collections(
+                username,
+                PROFILE_COLLECTIONS_FETCH_COUNT,
+                time_range,
+                Order::ThreeTwoOne,
+            )
+            .send_web(move |result| async move {
+                match result {
+                    Ok(ServerRes::Collections(new_collections)) => {
+                        has_more.set(new_collections.len() >= PROFILE_COLLECTIONS_FETCH_COUNT);
+                        collections.update(|v| v.extend(new_collections));
+                    }
+                    Ok(res) => {
+                        error!("expected Collections, received {res:?}");
+                    }
+                    Err(err) => {
+                        error!("profile collections: {err}");
+                    }
+                }
+            });
+        };
+
+        Effect::new(move || {
+            let Some(username) = username.get() else {
+                return;
+            };
+            collections.set(Vec::new());
+            fetch(username, TimeRange::None);
+        });
+
+        let on_more = move |_| {
+            let (Some(username), Some(last)) = (
+                username.get_untracked(),
+                collections.with_untracked(|v| v.last().cloned()),
+            ) else {
+                return;
+            };
+            fetch(username, TimeRange::Less(last.created_at));
+        };
+
+        view! {
+            <Show when=move || collections.with(|v| !v.is_empty()) >
+                <div class="flex flex-wrap gap-2 items-center">
+                    <span class="text-base03">"Collections"</span>
+                    <For
+                        each=move || collections.get()
+                        key=|collection| collection.key.clone()
+                        let(collection)
+                    >
+                        <a href=link_collection(&collection.key) class="rounded-xl bg-base01 px-2 hover:underline">
+                            {collection.title.clone()}
+                            <span class="text-base03">" "{collection.items_count}</span>
+                        </a>
+                    </For>
+                    <Show when=move || has_more.get() >
+                        <button class="text-base03 hover:text-base05" on:click=on_more >"Load more"</button>
+                    </Show>
+                </div>
+            </Show>
+        }
+    }
+
+    #[component]
+    pub fn FollowList(
+        username: Memo<Option<String>>,
+        kind: Signal<ProfileFollows>,
+    ) -> impl IntoView {
+        let api = ApiWeb::new();
+        let users = RwSignal::new(Vec::<UserFollow>::new());
+        let has_more = RwSignal::new(false);
+        let err_fetch = RwSignal::new(String::new());
+
+        let fetch = move |username: String, kind: ProfileFollows, time_range: TimeRange| {
+            let req = match kind {
+                ProfileFollows::Followers => api.get_user_followers(
+                    username,
+                    PROFILE_FOLLOWS_FETCH_COUNT,
+                    time_range,
+                    Order::ThreeTwoOne,
+                ),
+                ProfileFollows::Following => api.get_user_following(
+                    username,
+                    PROFILE_FOLLOWS_FETCH_COUNT,
+                    time_range,
+                    Order::ThreeTwoOne,
+                ),
+                ProfileFollows::None => {
+                    return;
+                }
+            };
+            req.send_web(move |result| async move {
+                match result {
+                    Ok(ServerRes::UserFollows(new_users)) => {
+                        has_more.set(new_users.len() >= PROFILE_FOLLOWS_FETCH_COUNT);
+                        users.update(|users| users.extend(new_users));
+                    }
+                    Ok(res) => {
+                        let err = format!("expected UserFollows, received {res:?}");
+                        error!(err);
+                        err_fetch.set(err);
+                    }
+                    Err(err) => {
+                        let err = format!("follow list: {err}");
+                        error!(err);
+                        err_fetch.set(err);
+                    }
+                }
+            });
+        };
+
+        Effect::new(move || {
+            let (Some(username), kind) = (username.get(), kind.get()) else {
+                return;
+            };
+            users.set(Vec::new());
+            err_fetch.set(String::new());
+            fetch(username, kind, TimeRange::None);
+        });
+
+        let on_more = move |_| {
+            let (Some(username), Some(last)) = (
+                username.get_untracked(),
+                users.with_untracked(|v| v.last().cloned()),
+            ) else {
+                return;
+            };
+            fetch(
+                username,
+                kind.get_untracked(),
+                TimeRange::Less(last.created_at),
+            );
+        };
+
+        view! {
+            <div class="flex flex-col gap-1 max-h-[12rem] overflow-y-auto bg-base01 rounded-xl p-2">
+                <Show when=move || users.with(|v| v.is_empty()) && err_fetch.with(|v| v.is_empty()) >
+                    <span class="text-base03">"Nobody yet"</span>
+                </Show>
+                <For
+                    each=move || users.get()
+                    key=|follow| follow.user.key.clone()
+                    let(follow)
+                >
+                    <a href=link_user(&follow.user.username) class="text-base0C hover:underline">{follow.user.username.clone()}</a>
+                </For>
+                <Show when=move || has_more.get() >
+                    <button class="text-base03 hover:text-base05 text-left" on:click=on_more >"Load more"</button>
+                </Show>
+                <Show when=move || err_fetch.with(|v| !v.is_empty()) >
+                    <span class="text-base08">{move || err_fetch.get()}</span>
+                </Show>
+            </div>
+        }
+    }
+}
+pub mod collection {
+
+    use crate::api::Api;
+    use crate::api::ApiWeb;
+    use crate::api::ServerRes;
+    use crate::api::UserPost;
+    use crate::api::shared::collection::UserCollection;
+    use crate::path::{link_collection, link_post, link_user};
+    use crate::view::app::GlobalState;
+    use crate::view::app::components::gallery::Gallery;
+    use crate::view::app::components::nav::Nav;
+    use crate::view::toolbox::prelude::*;
+    use leptos::{Params, html, prelude::*};
+    use leptos_router::hooks::use_params;
+    use leptos_router::params::Params;
+    use tracing::error;
+    use web_sys::SubmitEvent;
+
+    #[derive(Params, PartialEq, Clone)]
+    pub struct CollectionParams {
+        pub collection: Option<String>,
+    }
+
+    #[component]
+    pub fn Page() -> impl IntoView {
+        let global_state = expect_context::<GlobalState>();
+        let navigate = leptos_router::hooks::use_navigate();
+        let api = ApiWeb::new();
+        let api_update = ApiWeb::new();
+        let api_items = ApiWeb::new();
+        let param = use_params::<CollectionParams>();
+        let param_collection = Memo::new(move |_| {
+            param
+                .read()
+                .as_ref()
+                .ok()
+                .and_then(|v| v.collection.clone())
+        });
+        let collection = RwSignal::new(None::<UserCollection>);
+        let items = RwSignal::new(Vec::<UserPost>::new());
+        let err = RwSignal::new(String::new());
+        let edit_mode = RwSignal::new(false);
+        // bumped after the owner changes the order, so the gallery loads again from the start
+        let gallery_version = RwSignal::new(0_u64);
+        let title_input = NodeRef::<html::Input>::new();
+        let private_input = NodeRef::<html::Input>::new();
+
+        let is_owner = move || {
+            let username = global_state.get_username_tracked();
+            collection.with(|v| {
+                v.as_ref()
+                    .map(|v| Some(&v.user.username) == username.as_ref())
+                    .unwrap_or_default()
+            })
+        };
+
+        Effect::new(move || {
+            let Some(collection_key) = param_collection.get() else {
+                return;
+            };
+            api.get_collection(collection_key)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Collection(v)) => {
+                            collection.set(Some(v));
+                        }
+                        Ok(res) => {
+                            let e = format!("expected Collection, received {res:?}");
+                            error!(e);
+                            err.set(e);
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        });
+
+        let fetch_items = move || {
+            let Some((collection_key, count)) =
+                collection.with_untracked(|v| v.as_ref().map(|v| (v.key.clone(), v.items_count)))
+            else {
+                return;
+            };
+            api_items
+                .get_collection_posts(collection_key, count as usize, 0)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Posts(posts)) => {
+                            items.set(posts);
+                        }
+                        Ok(res) => {
+                            error!("expected Posts, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        let on_edit = move |_| {
+            let enabled = !edit_mode.get_untracked();
+            edit_mode.set(enabled);
+            if enabled {
+                fetch_items();
+            } else {
+                gallery_version.update(|v| *v += 1);
+            }
+        };
+
+        let on_save = move |e: SubmitEvent| {
+            e.prevent_default();
+            let (Some(collection_key), Some(title_input), Some(private_input)) = (
+                param_collection.get_untracked(),
+                title_input.get_untracked(),
+                private_input.get_untracked(),
+            ) else {
+                return;
+            };
+            api_update
+                .update_collection(collection_key, title_input.value(), private_input.checked())
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Collection(v)) => {
+                            err.set(String::new());
+                            collection.set(Some(v));
+                        }
+                        Ok(res) => {
+                            error!("expected Collection, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        let on_delete = move |_| {
+            let Some(collection_key) = param_collection.get_untracked() else {
+                return;
+            };
+            let navigate = navigate.clone();
+            api_update
+                .delete_collection(collection_key)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Ok) => {
+                            let username =
+                                global_state.get_username_untracked().unwrap_or_default();
+                            navigate(&link_user(username), Default::default());
+                        }
+                        Ok(res) => {
+                            error!("expected Ok, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        let on_move = move |post_key: String, new_pos: usize| {
+            let Some(collection_key) = param_collection.get_untracked() else {
+                return;
+            };
+            api_update
+                .order_collection_item(collection_key, post_key, new_pos)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Ok) => {
+                            fetch_items();
+                        }
+                        Ok(res) => {
+                            error!("expected Ok, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        let on_remove = move |post_key: String| {
+            let Some(collection_key) = param_collection.get_untracked() else {
+                return;
+            };
+            api_update
+                .delete_collection_item(collection_key, post_key.clone())
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Ok) => {
+                            items.update(|v| v.retain(|v| v.key != post_key));
+                            collection.update(|v| {
+                                if let Some(v) = v {
+                                    v.items_count = v.items_count.saturating_sub(1);
+                                }
+                            });
+                        }
+                        Ok(res) => {
+                            error!("expected Ok, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
                     }
+                });
+        };
+
+        let title = move || collection.with(|v| v.as_ref().map(|v| v.title.clone()));
+        let author = move || collection.with(|v| v.as_ref().map(|v| v.user.username.clone()));
+        let share_link = move || {
+            collection.with(|v| {
+                v.as_ref().filter(|v| !v.private).map(|v| {
+                    let origin = location().origin().unwrap_or_default();
+                    format!("{origin}{}", link_collection(&v.key))
                 })
-                .collect_view()
+            })
         };
 
         view! {
-            <main node_ref=main_ref class="grid grid-rows-[auto_1fr] h-screen">
+            <main class="grid grid-rows-[auto_auto_1fr] h-screen">
                 <Nav/>
-                <div>
-                    <div class=move||format!("mx-auto text-[1.5rem] {}", if api.is_pending_tracked() {""} else {"hidden"})>
-                        <h1>"LOADING..."</h1>
+                <div class="flex flex-col gap-2 px-4">
+                    <div class="flex flex-wrap items-center gap-4">
+                        <h1 class="text-[1.5rem] text-base0F">{title}</h1>
+                        <a href=move || link_user(author().unwrap_or_default()) class="text-base0B">{author}</a>
+                        <span class="text-base03">{move || collection.with(|v| v.as_ref().map(|v| v.items_count).unwrap_or_default())}" posts"</span>
+                        <span class="text-base03">{move || collection.with(|v| v.as_ref().map(|v| if v.private { "private" } else { "" }))}</span>
+                        <Show when=is_owner >
+                            <button class="px-2 border-2 border-base05 rounded-lg" on:click=on_edit >
+                                {move || if edit_mode.get() { "Done" } else { "Edit" }}
+                            </button>
+                        </Show>
                     </div>
-                    <form method="POST" action="" on:submit=on_upload class=move || format!("flex flex-col px-[4rem] max-w-[30rem] mx-auto w-full {}", if !api.is_pending_tracked() {""} else {"hidden"})>
-                        <h1 class="text-[1.5rem]  text-center my-[4rem]">"UPLOAD"</h1>
-                        <div class=move||format!("text-red-600 text-center {}", if upload_general_err.with(|v| v.is_empty()) {"hidden"} else {""})>{move || { upload_general_err.get() }}</div>
-                        <div class="flex flex-col gap-4">
-                            <div class="flex flex-col gap-0">
-                                <label for="title" class="text-[1.2rem] ">"Title"</label>
-                                <div class=move || format!("text-red-600 transition-[font-size] duration-300 ease-in {}", if upload_title_err.with(|err| err.is_empty()) {"text-[0rem]"} else {"text-[1rem]"}) >
-                                    <ul class="list-disc ml-[1rem]">
-                                        {move || upload_title_err.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
-                                    </ul>
-                                </div>
-                                <input placeholder="Funny looking cat" id="title" name="title" node_ref=upload_title type="text" class="border-b-2 border-base05 w-full mt-1 " />
-                            </div>
-                            <div class="flex flex-col gap-0">
-                                <label for="image" class="text-[1.2rem] ">"Images"</label>
-                                <div class=move || format!("text-red-600 transition-[font-size] duration-300 ease-in {}", if upload_image_err.with(|err| err.is_empty()) {"text-[0rem]"} else {"text-[1rem]"}) >
-                                    <ul class="list-disc ml-[1rem]">
-                                        {move || upload_image_err.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
-                                    </ul>
-                                </div>
-                                { files_selected }
-                                <input on:change=on_file_change type="file" id="image" name="image" node_ref=upload_image multiple />
-                            </div>
-                            <div class="flex flex-col gap-0">
-                                <label for="description" class="text-[1.2rem] ">"Description"</label>
-                                <div class=move || format!("text-red-600 transition-[font-size] duration-300 ease-in {}", if upload_description_err.with(|err| err.is_empty()) {"text-[0rem]"} else {"text-[1rem]"}) >
-                                    <ul class="list-disc ml-[1rem]">
-                                        {move || upload_description_err.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
-                                    </ul>
-                                </div>
-                                <textarea class="border-l-2 border-base05 pl-2 bg-base05" node_ref=upload_description id="description" name="description" rows="4" cols="50">""</textarea>
-                            </div>
-                            <div class="flex flex-col gap-0">
-                                <label for="tags" class="text-[1.2rem] ">"Tags"</label>
-                                <div class=move || format!("text-red-600 transition-[font-size] duration-300 ease-in {}", if upload_tags_err.with(|err| err.is_empty()) {"text-[0rem]"} else {"text-[1rem]"}) >
-                                    <ul class="list-disc ml-[1rem]">
-                                        {move || upload_tags_err.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
-                                    </ul>
-                                </div>
-                                <textarea class="border-l-2 border-base05 pl-2 bg-base05" node_ref=upload_tags id="tags" name="tags" rows="1" cols="50">""</textarea>
+                    <Show when=move || share_link().is_some() >
+                        <div class="flex gap-2 text-base03">
+                            "Share:"
+                            <a href=move || share_link().unwrap_or_default() class="hover:underline">{share_link}</a>
+                        </div>
+                    </Show>
+                    <Show when=move || edit_mode.get() >
+                        <form class="flex flex-wrap gap-2 items-center" on:submit=on_save >
+                            <input node_ref=title_input type="text" prop:value=move || title().unwrap_or_default() class="bg-base01 px-2 rounded-lg" />
+                            <label class="flex gap-1 items-center text-base03">
+                                <input node_ref=private_input type="checkbox" prop:checked=move || collection.with(|v| v.as_ref().map(|v| v.private).unwrap_or_default()) />
+                                "private"
+                            </label>
+                            <input type="submit" value="Save" class="px-2 border-2 border-base05 rounded-lg" />
+                            <button type="button" class="px-2 border-2 border-base08 text-base08 rounded-lg" on:click=on_delete >"Delete collection"</button>
+                        </form>
+                        <div class="flex flex-col gap-1 max-h-[40vh] overflow-y-auto">
+                            <For
+                                each=move || items.get().into_iter().enumerate()
+                                key=|(i, post)| (*i, post.key.clone())
+                                let((i, post))
+                            >
+                                {
+                                    let post_key = post.key.clone();
+                                    let post_key2 = post.key.clone();
+                                    let post_key3 = post.key.clone();
+                                    let len = items.with_untracked(|v| v.len());
+                                    view! {
+                                        <div class="flex gap-2 items-center rounded-xl bg-base01 p-1">
+                                            <span class="text-base03 w-[2rem]">{i + 1}</span>
+                                            <a href=link_post(&post.user.username, &post.key) class="grow hover:underline">{post.title.clone()}</a>
+                                            <button class="px-2 disabled:text-base03" disabled={i == 0} on:click=move |_| on_move(post_key.clone(), i.saturating_sub(1)) >"Up"</button>
+                                            <button class="px-2 disabled:text-base03" disabled={i + 1 >= len} on:click=move |_| on_move(post_key2.clone(), i + 1) >"Down"</button>
+                                            <button class="px-2 text-base08" on:click=move |_| on_remove(post_key3.clone()) >"Remove"</button>
+                                        </div>
+                                    }
+                                }
+                            </For>
+                        </div>
+                    </Show>
+                    <Show when=move || err.with(|v| !v.is_empty()) >
+                        <span class="text-base08">{move || err.get()}</span>
+                    </Show>
+                </div>
+                {move || {
+                    gallery_version.track();
+                    view! { <Gallery row_height=250 collection=param_collection /> }
+                }}
+            </main>
+        }
+    }
+}
+
+pub mod messages {
+
+    use crate::api::Api;
+    use crate::api::ApiWeb;
+    use crate::api::Order;
+    use crate::api::ServerRes;
+    use crate::api::TimeRange;
+    use crate::api::shared::conversation::{UserConversation, UserMessage};
+    use crate::path::{link_api_message_add_file, link_conversation, link_img, link_user};
+    use crate::view::app::components::nav::Nav;
+    use crate::view::app::hook::api_post_file_upload::{FileUpload, UploadProgressState};
+    use crate::view::toolbox::prelude::*;
+    use leptos::{Params, html, prelude::*};
+    use leptos_router::hooks::use_params;
+    use leptos_router::params::Params;
+    use tracing::error;
+    use web_sys::{HtmlInputElement, HtmlTextAreaElement, SubmitEvent};
+
+    pub const CONVERSATIONS_FETCH_COUNT: usize = 25;
+    pub const MESSAGES_FETCH_COUNT: usize = 25;
+
+    #[derive(Params, PartialEq, Clone)]
+    pub struct ConversationParams {
+        pub conversation: Option<String>,
+    }
+
+    #[component]
+    pub fn Page() -> impl IntoView {
+        let api = ApiWeb::new();
+        let conversations = RwSignal::new(Vec::<UserConversation>::new());
+        let has_more = RwSignal::new(false);
+        let err_fetch = RwSignal::new(String::new());
+
+        let fetch = move |time_range: TimeRange| {
+            api.get_conversations(CONVERSATIONS_FETCH_COUNT, time_range, Order::ThreeTwoOne)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Conversations(new_conversations)) => {
+                            has_more.set(new_conversations.len() >= CONVERSATIONS_FETCH_COUNT);
+                            conversations.update(|v| v.extend(new_conversations));
+                        }
+                        Ok(res) => {
+                            let err = format!("expected Conversations, received {res:?}");
+                            error!(err);
+                            err_fetch.set(err);
+                        }
+                        Err(err) => {
+                            let err = format!("messages: {err}");
+                            error!(err);
+                            err_fetch.set(err);
+                        }
+                    }
+                });
+        };
+
+        Effect::new(move || {
+            fetch(TimeRange::None);
+        });
+
+        let on_more = move |_| {
+            let Some(last) = conversations.with_untracked(|v| v.last().cloned()) else {
+                return;
+            };
+            fetch(TimeRange::Less(last.last_message_at));
+        };
+
+        view! {
+            <main class="grid grid-rows-[auto_1fr] h-screen">
+                <Nav/>
+                <div class="flex flex-col gap-2 w-full max-w-[40rem] mx-auto px-2 py-4 overflow-y-auto">
+                    <h1 class="text-[1.5rem] text-base0F">"Messages"</h1>
+                    <Show when=move || conversations.with(|v| v.is_empty()) && err_fetch.with(|v| v.is_empty()) >
+                        <span class="text-base03">"No conversations yet, start one from a user profile"</span>
+                    </Show>
+                    <For
+                        each=move || conversations.get()
+                        key=|conversation| (conversation.key.clone(), conversation.unread, conversation.last_message_at)
+                        let(conversation)
+                    >
+                        <a
+                            href=link_conversation(&conversation.key)
+                            class=format!("flex justify-between rounded-xl p-2 hover:underline {}", if conversation.unread { "bg-base02 text-base05" } else { "bg-base01 text-base03" })
+                        >
+                            <span>{conversation.peer.username.clone()}</span>
+                            <span class="text-base03">{if conversation.blocked { "blocked" } else { "" }}</span>
+                        </a>
+                    </For>
+                    <Show when=move || has_more.get() >
+                        <button class="text-base03 hover:text-base05 text-left" on:click=on_more >"Load more"</button>
+                    </Show>
+                    <Show when=move || err_fetch.with(|v| !v.is_empty()) >
+                        <span class="text-base08">{move || err_fetch.get()}</span>
+                    </Show>
+                </div>
+            </main>
+        }
+    }
+
+    #[component]
+    pub fn ConversationPage() -> impl IntoView {
+        let api_conversation = ApiWeb::new();
+        let api_messages = ApiWeb::new();
+        let api_newer = ApiWeb::new();
+        let api_send = ApiWeb::new();
+        let api_block = ApiWeb::new();
+        let param = use_params::<ConversationParams>();
+        let param_conversation = Memo::new(move |_| {
+            param
+                .read()
+                .as_ref()
+                .ok()
+                .and_then(|v| v.conversation.clone())
+        });
+        let conversation = RwSignal::new(None::<UserConversation>);
+        let messages = RwSignal::new(Vec::<UserMessage>::new());
+        let has_more = RwSignal::new(false);
+        let err_fetch = RwSignal::new(String::new());
+        let err_send = RwSignal::new(String::new());
+        let message_input = NodeRef::<html::Textarea>::new();
+        let file_input = NodeRef::<html::Input>::new();
+        let uploader = FileUpload::new();
+
+        let merge = move |new_messages: Vec<UserMessage>| {
+            messages.update(|messages| {
+                for new_message in new_messages {
+                    match messages.iter_mut().find(|v| v.key == new_message.key) {
+                        Some(message) => *message = new_message,
+                        None => messages.push(new_message),
+                    }
+                }
+                messages.sort_by_key(|v| v.created_at);
+            });
+        };
+
+        let fetch_older = move |conversation_key: String, time_range: TimeRange| {
+            api_messages
+                .get_messages(
+                    conversation_key,
+                    MESSAGES_FETCH_COUNT,
+                    time_range,
+                    Order::ThreeTwoOne,
+                )
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Messages(new_messages)) => {
+                            has_more.set(new_messages.len() >= MESSAGES_FETCH_COUNT);
+                            merge(new_messages);
+                        }
+                        Ok(res) => {
+                            let err = format!("expected Messages, received {res:?}");
+                            error!(err);
+                            err_fetch.set(err);
+                        }
+                        Err(err) => {
+                            let err = format!("messages: {err}");
+                            error!(err);
+                            err_fetch.set(err);
+                        }
+                    }
+                });
+        };
+
+        let fetch_newer = move |conversation_key: String, time: u128| {
+            api_newer
+                .get_messages(
+                    conversation_key,
+                    MESSAGES_FETCH_COUNT,
+                    TimeRange::MoreOrEqual(time),
+                    Order::OneTwoThree,
+                )
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Messages(new_messages)) => {
+                            merge(new_messages);
+                        }
+                        Ok(res) => {
+                            error!("expected Messages, received {res:?}");
+                        }
+                        Err(err) => {
+                            error!("messages: {err}");
+                        }
+                    }
+                });
+        };
+
+        Effect::new(move || {
+            let Some(conversation_key) = param_conversation.get() else {
+                return;
+            };
+            messages.set(Vec::new());
+            api_conversation
+                .get_conversation(conversation_key.clone())
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Conversation(new_conversation)) => {
+                            conversation.set(Some(new_conversation));
+                        }
+                        Ok(res) => {
+                            let err = format!("expected Conversation, received {res:?}");
+                            error!(err);
+                            err_fetch.set(err);
+                        }
+                        Err(err) => {
+                            let err = format!("messages: {err}");
+                            error!(err);
+                            err_fetch.set(err);
+                        }
+                    }
+                });
+            api_conversation
+                .read_conversation(conversation_key.clone())
+                .send_web(async move |result| {
+                    if let Err(err) = result {
+                        error!("messages: {err}");
+                    }
+                });
+            fetch_older(conversation_key, TimeRange::None);
+        });
+
+        // attachments are uploaded after the message is created, refetch it once they land
+        Effect::new(move || {
+            let completed = uploader.post_files.with(|files| {
+                !files.is_empty()
+                    && files
+                        .iter()
+                        .all(|v| matches!(v.state, UploadProgressState::Completed))
+            });
+            if !completed {
+                return;
+            }
+            uploader.clear();
+            let (Some(conversation_key), Some(last)) = (
+                param_conversation.get_untracked(),
+                messages.with_untracked(|v| v.last().cloned()),
+            ) else {
+                return;
+            };
+            fetch_newer(conversation_key, last.created_at);
+        });
+
+        let on_more = move |_| {
+            let (Some(conversation_key), Some(first)) = (
+                param_conversation.get_untracked(),
+                messages.with_untracked(|v| v.first().cloned()),
+            ) else {
+                return;
+            };
+            fetch_older(conversation_key, TimeRange::Less(first.created_at));
+        };
+
+        let on_send = move |e: SubmitEvent| {
+            e.prevent_default();
+            let (Some(conversation_key), Some(message_input), Some(file_input)) = (
+                param_conversation.get_untracked(),
+                message_input.get_untracked() as Option<HtmlTextAreaElement>,
+                file_input.get_untracked() as Option<HtmlInputElement>,
+            ) else {
+                return;
+            };
+            let text = message_input.value();
+            let files = file_input
+                .files()
+                .map(|v| v.get_files())
+                .unwrap_or_default();
+            if text.is_empty() && files.is_empty() {
+                return;
+            }
+            api_send
+                .add_message(conversation_key, text)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Message(message)) => {
+                            err_send.set(String::new());
+                            message_input.set_value("");
+                            file_input.set_value("");
+                            if !files.is_empty() {
+                                uploader
+                                    .upload(link_api_message_add_file(&message.key), &files[..]);
+                            }
+                            merge(vec![message]);
+                        }
+                        Ok(res) => {
+                            let err = format!("expected Message, received {res:?}");
+                            error!(err);
+                            err_send.set(err);
+                        }
+                        Err(err) => {
+                            error!("messages: {err}");
+                            err_send.set(err.to_string());
+                        }
+                    }
+                });
+        };
+
+        let on_block = move |_| {
+            let (Some(conversation_key), Some(blocked)) = (
+                param_conversation.get_untracked(),
+                conversation.with_untracked(|v| v.as_ref().map(|v| v.blocked)),
+            ) else {
+                return;
+            };
+            let req = if blocked {
+                api_block.unblock_conversation(conversation_key)
+            } else {
+                api_block.block_conversation(conversation_key)
+            };
+            req.send_web(move |result| async move {
+                match result {
+                    Ok(ServerRes::Ok) => {
+                        conversation.update(|v| {
+                            if let Some(v) = v {
+                                v.blocked = !blocked;
+                            }
+                        });
+                    }
+                    Ok(res) => {
+                        error!("expected Ok, received {res:?}");
+                    }
+                    Err(err) => {
+                        error!("messages: {err}");
+                    }
+                }
+            });
+        };
+
+        let peer_username = move || {
+            conversation
+                .with(|v| v.as_ref().map(|v| v.peer.username.clone()))
+                .unwrap_or_default()
+        };
+        let block_btn_text = move || {
+            if conversation.with(|v| v.as_ref().map(|v| v.blocked).unwrap_or_default()) {
+                "Unblock"
+            } else {
+                "Block"
+            }
+        };
+
+        view! {
+            <main class="grid grid-rows-[auto_1fr] h-screen">
+                <Nav/>
+                <div class="grid grid-rows-[auto_1fr_auto] gap-2 w-full max-w-[40rem] mx-auto px-2 py-4 overflow-hidden">
+                    <div class="flex justify-between gap-2">
+                        <a href=move || link_user(peer_username()) class="text-[1.5rem] text-base0F hover:underline">{peer_username}</a>
+                        <button class="text-base03 hover:text-base05" on:click=on_block >{block_btn_text}</button>
+                    </div>
+                    <div class="flex flex-col gap-2 overflow-y-auto">
+                        <Show when=move || has_more.get() >
+                            <button class="text-base03 hover:text-base05 text-left" on:click=on_more >"Load older"</button>
+                        </Show>
+                        <For
+                            each=move || messages.get()
+                            key=|message| (message.key.clone(), message.file.len())
+                            let(message)
+                        >
+                            <div class="flex flex-col gap-1 rounded-xl p-2 bg-base01">
+                                <span class="text-base03 text-[0.8rem]">{message.user.username.clone()}</span>
+                                <span class="whitespace-pre-wrap">{message.text.clone()}</span>
+                                {message
+                                    .file
+                                    .iter()
+                                    .map(|file| {
+                                        view! { <img class="max-h-[20rem] object-contain" src=link_img(&file.hash, &file.extension) /> }
+                                    })
+                                    .collect_view()}
                             </div>
+                        </For>
+                        <Show when=move || err_fetch.with(|v| !v.is_empty()) >
+                            <span class="text-base08">{move || err_fetch.get()}</span>
+                        </Show>
+                    </div>
+                    <form class="flex flex-col gap-2" on:submit=on_send >
+                        <textarea node_ref=message_input class="bg-base01 rounded-xl p-2" placeholder="Message" />
+                        <div class="flex justify-between gap-2">
+                            <input node_ref=file_input type="file" accept="image/*" multiple />
+                            <input type="submit" value="Send" class="px-2 border-2 border-base05 rounded-lg"/>
                         </div>
-                        <div class="flex flex-col gap-[1.3rem] mx-auto my-[4rem] text-center">
-                            <input id="upload_btn" type="submit" value="Post" class="border-2 border-base05 text-[1.3rem] font-bold px-4 py-1 hover:bg-base05 hover:text-gray-950"/>
-                        </div>
+                        <Show when=move || err_send.with(|v| !v.is_empty()) >
+                            <span class="text-base08">{move || err_send.get()}</span>
+                        </Show>
                     </form>
                 </div>
             </main>
         }
     }
 }
-pub mod profile {
+pub mod notifications {
 
     use crate::api::Api;
     use crate::api::ApiWeb;
-    use crate::api::Server404Err;
-    use crate::api::ServerErr;
+    use crate::api::Order;
     use crate::api::ServerRes;
-    use crate::view::app::components::gallery::Gallery;
+    use crate::api::TimeRange;
+    use crate::api::shared::notification::UserNotification;
+    use crate::path::link_post;
     use crate::view::app::components::nav::Nav;
     use crate::view::toolbox::prelude::*;
-    use leptos::Params;
     use leptos::prelude::*;
-    use leptos_router::{hooks::use_params, params::Params};
-    use std::rc::Rc;
     use tracing::error;
-    use tracing::trace;
 
-    #[derive(Params, PartialEq, Clone)]
-    pub struct UserParams {
-        pub username: Option<String>,
-    }
+    pub const NOTIFICATIONS_FETCH_COUNT: usize = 25;
 
     #[component]
     pub fn Page() -> impl IntoView {
-        let main_ref = NodeRef::new();
         let api = ApiWeb::new();
-        let param = use_params::<UserParams>();
-        let param_username = move || param.read().as_ref().ok().and_then(|v| v.username.clone());
-        let user_username = RwSignal::new(None::<String>);
+        let api_read = ApiWeb::new();
+        let notifications = RwSignal::new(Vec::<UserNotification>::new());
+        let has_more = RwSignal::new(false);
+        let err_fetch = RwSignal::new(String::new());
+
+        let fetch = move |time_range: TimeRange| {
+            api.get_notifications(NOTIFICATIONS_FETCH_COUNT, time_range, Order::ThreeTwoOne)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Notifications(new_notifications)) => {
+                            has_more.set(new_notifications.len() >= NOTIFICATIONS_FETCH_COUNT);
+                            notifications.update(|v| v.extend(new_notifications));
+                        }
+                        Ok(res) => {
+                            let err = format!("expected Notifications, received {res:?}");
+                            error!(err);
+                            err_fetch.set(err);
+                        }
+                        Err(err) => {
+                            let err = format!("notifications: {err}");
+                            error!(err);
+                            err_fetch.set(err);
+                        }
+                    }
+                });
+        };
 
         Effect::new(move || {
-            let Some(username) = param_username() else {
+            fetch(TimeRange::None);
+        });
+
+        let on_more = move |_| {
+            let Some(last) = notifications.with_untracked(|v| v.last().cloned()) else {
                 return;
             };
-            api.get_user(username).send_web(move |result| async move {
+            fetch(TimeRange::Less(last.modified_at));
+        };
+
+        let on_read_all = move |_| {
+            api_read
+                .read_notification_all()
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Ok) => {
+                            notifications.update(|v| v.iter_mut().for_each(|v| v.read = true));
+                        }
+                        Ok(res) => {
+                            error!("expected Ok, received {res:?}");
+                        }
+                        Err(err) => {
+                            error!("notifications: {err}");
+                        }
+                    }
+                });
+        };
+
+        let on_read = move |key: String| {
+            api_read
+                .read_notification(key.clone())
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Ok) => {
+                            notifications.update(|v| {
+                                if let Some(v) = v.iter_mut().find(|v| v.key == key) {
+                                    v.read = true;
+                                }
+                            });
+                        }
+                        Ok(res) => {
+                            error!("expected Ok, received {res:?}");
+                        }
+                        Err(err) => {
+                            error!("notifications: {err}");
+                        }
+                    }
+                });
+        };
+
+        view! {
+            <main class="grid grid-rows-[auto_1fr] h-screen">
+                <Nav/>
+                <div class="flex flex-col gap-2 w-full max-w-[40rem] mx-auto px-2 py-4 overflow-y-auto">
+                    <div class="flex justify-between gap-2">
+                        <h1 class="text-[1.5rem] text-base0F">"Notifications"</h1>
+                        <button class="text-base03 hover:text-base05" on:click=on_read_all >"Mark all read"</button>
+                    </div>
+                    <Show when=move || notifications.with(|v| v.is_empty()) && err_fetch.with(|v| v.is_empty()) >
+                        <span class="text-base03">"Nothing yet"</span>
+                    </Show>
+                    <For
+                        each=move || notifications.get()
+                        key=|notification| (notification.key.clone(), notification.read, notification.modified_at)
+                        let(notification)
+                    >
+                        {
+                            let key = notification.key.clone();
+                            let read = notification.read;
+                            view! {
+                                <a
+                                    href=link_post(&notification.post_username, &notification.post_key)
+                                    on:click=move |_| {
+                                        if !read {
+                                            on_read(key.clone());
+                                        }
+                                    }
+                                    class=format!("rounded-xl p-2 hover:underline {}", if read { "bg-base01 text-base03" } else { "bg-base02 text-base05" })
+                                >
+                                    {notification.text()}
+                                </a>
+                            }
+                        }
+                    </For>
+                    <Show when=move || has_more.get() >
+                        <button class="text-base03 hover:text-base05 text-left" on:click=on_more >"Load more"</button>
+                    </Show>
+                    <Show when=move || err_fetch.with(|v| !v.is_empty()) >
+                        <span class="text-base08">{move || err_fetch.get()}</span>
+                    </Show>
+                </div>
+            </main>
+        }
+    }
+}
+pub mod admin {
+
+    use crate::api::Api;
+    use crate::api::ApiWeb;
+    use crate::api::Order;
+    use crate::api::ServerRes;
+    use crate::api::TimeRange;
+    use crate::api::UserPost;
+    use crate::api::shared::admin::{AdminUser, Permission, UserRole};
+    use crate::api::shared::audit::{AuditKind, UserAuditEvent};
+    use crate::api::shared::report::{ReportAction, ReportTarget, UserReport};
+    use crate::path::{link_post, link_user};
+    use crate::view::app::GlobalState;
+    use crate::view::app::components::audit_event::AuditEventRow;
+    use crate::view::app::components::nav::Nav;
+    use crate::view::toolbox::prelude::*;
+    use leptos::{html, prelude::*};
+    use strum::IntoEnumIterator;
+    use tracing::error;
+    use web_sys::SubmitEvent;
+
+    pub const ADMIN_USERS_FETCH_COUNT: usize = 50;
+    pub const ADMIN_POSTS_FETCH_COUNT: usize = 100;
+    pub const ADMIN_REPORTS_FETCH_COUNT: usize = 50;
+    pub const ADMIN_AUDIT_FETCH_COUNT: usize = 100;
+
+    #[component]
+    pub fn Page() -> impl IntoView {
+        let global_state = expect_context::<GlobalState>();
+        let api = ApiWeb::new();
+        let api_posts = ApiWeb::new();
+        let api_update = ApiWeb::new();
+        let users = RwSignal::new(Vec::<AdminUser>::new());
+        let posts = RwSignal::new(Vec::<UserPost>::new());
+        let selected_username = RwSignal::new(None::<String>);
+        let err = RwSignal::new(String::new());
+        let search_input = NodeRef::<html::Input>::new();
+
+        let search = move |query: String| {
+            api.admin_get_users(
+                query,
+                ADMIN_USERS_FETCH_COUNT,
+                TimeRange::None,
+                Order::ThreeTwoOne,
+            )
+            .send_web(move |result| async move {
                 match result {
-                    Ok(ServerRes::User { username }) => {
-                        user_username.set(Some(username));
+                    Ok(ServerRes::AdminUsers(v)) => {
+                        err.set(String::new());
+                        users.set(v);
                     }
                     Ok(res) => {
-                        user_username.set(Some(format!("expected Uesr, received {res:?}")));
-                        error!("expected Uesr, received {res:?}");
+                        let e = format!("expected AdminUsers, received {res:?}");
+                        error!(e);
+                        err.set(e);
                     }
-                    Err(ServerErr::NotFoundErr(Server404Err::NotFound)) => {
-                        user_username.set(Some("Not Found".to_string()));
-                    }
-                    Err(err) => {
-                        user_username.set(Some(err.to_string()));
-                        error!("get user err: {err}");
+                    Err(e) => {
+                        err.set(e.to_string());
                     }
                 }
             });
+        };
+
+        Effect::new(move || {
+            search(String::new());
         });
 
+        let on_search = move |e: SubmitEvent| {
+            e.prevent_default();
+            let Some(search_input) = search_input.get_untracked() else {
+                return;
+            };
+            search(search_input.value());
+        };
+
+        let on_user_res = move |result: Result<ServerRes, crate::api::ServerErr>| match result {
+            Ok(ServerRes::AdminUser(user)) => {
+                err.set(String::new());
+                users.update(|v| {
+                    if let Some(v) = v.iter_mut().find(|v| v.key == user.key) {
+                        *v = user;
+                    }
+                });
+            }
+            Ok(res) => {
+                error!("expected AdminUser, received {res:?}");
+            }
+            Err(e) => {
+                err.set(e.to_string());
+            }
+        };
+
+        let on_role = move |user_key: String, role: UserRole| {
+            api_update
+                .admin_update_user_role(user_key, role)
+                .send_web(move |result| async move { on_user_res(result) });
+        };
+
+        let on_ban = move |user_key: String, banned: bool| {
+            api_update
+                .admin_update_user_ban(user_key, banned)
+                .send_web(move |result| async move { on_user_res(result) });
+        };
+
+        let on_storage = move |user_key: String, max_storage: u64, max_storage_per_file: u64| {
+            api_update
+                .admin_update_user_storage(user_key, max_storage, max_storage_per_file)
+                .send_web(move |result| async move { on_user_res(result) });
+        };
+
+        let on_posts = move |username: String| {
+            selected_username.set(Some(username.clone()));
+            api_posts
+                .admin_get_user_posts(
+                    username,
+                    ADMIN_POSTS_FETCH_COUNT,
+                    TimeRange::None,
+                    Order::ThreeTwoOne,
+                )
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Posts(v)) => {
+                            posts.set(v);
+                        }
+                        Ok(res) => {
+                            error!("expected Posts, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        let on_show = move |post_key: String, show: bool| {
+            api_update
+                .admin_update_post_show(post_key, show)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Post(post)) => {
+                            posts.update(|v| {
+                                if let Some(v) = v.iter_mut().find(|v| v.key == post.key) {
+                                    *v = post;
+                                }
+                            });
+                        }
+                        Ok(res) => {
+                            error!("expected Post, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        let can_manage = move || global_state.can(Permission::ManageUser);
+        let can_ban = move || global_state.can(Permission::BanUser);
+        let can_hide = move || global_state.can(Permission::HidePost);
+
         view! {
-            <main node_ref=main_ref class="grid grid-rows-[auto_auto_1fr] h-screen">
+            <main class="grid grid-rows-[auto_1fr] h-screen">
                 <Nav/>
-                <div>
-                    <h1>{move || user_username.get()}</h1>
+                <div class="flex flex-col gap-2 w-full max-w-[60rem] mx-auto px-2 py-4 overflow-y-auto">
+                    <h1 class="text-[1.5rem] text-base0F">"Admin"</h1>
+                    <Show when=move || global_state.can(Permission::ResolveReport) >
+                        <ReportQueue />
+                    </Show>
+                    <h2 class="text-[1.2rem] text-base0F">"Users"</h2>
+                    <form class="flex gap-2" on:submit=on_search >
+                        <input node_ref=search_input type="text" placeholder="username or email" class="grow bg-base01 px-2 rounded-lg" />
+                        <input type="submit" value="Search" class="px-2 border-2 border-base05 rounded-lg" />
+                    </form>
+                    <Show when=move || err.with(|v| !v.is_empty()) >
+                        <span class="text-base08">{move || err.get()}</span>
+                    </Show>
+                    <For
+                        each=move || users.get()
+                        key=|user| (user.key.clone(), user.role, user.banned, user.max_storage_bytes, user.max_storage_per_file_bytes)
+                        let(user)
+                    >
+                        {
+                            let max_storage_input = NodeRef::<html::Input>::new();
+                            let max_storage_per_file_input = NodeRef::<html::Input>::new();
+                            let user_key = user.key.clone();
+                            let user_key2 = user.key.clone();
+                            let user_key3 = user.key.clone();
+                            let username = user.username.clone();
+                            let banned = user.banned;
+                            let on_storage_submit = move |e: SubmitEvent| {
+                                e.prevent_default();
+                                let (Some(max_storage_input), Some(max_storage_per_file_input)) = (
+                                    max_storage_input.get_untracked(),
+                                    max_storage_per_file_input.get_untracked(),
+                                ) else {
+                                    return;
+                                };
+                                let (Ok(max_storage), Ok(max_storage_per_file)) = (
+                                    max_storage_input.value().parse::<u64>(),
+                                    max_storage_per_file_input.value().parse::<u64>(),
+                                ) else {
+                                    err.set("storage has to be a number of bytes".to_string());
+                                    return;
+                                };
+                                on_storage(user_key3.clone(), max_storage, max_storage_per_file);
+                            };
+                            view! {
+                                <div class="flex flex-col gap-1 rounded-xl bg-base01 p-2">
+                                    <div class="flex flex-wrap gap-2 items-center">
+                                        <a href=link_user(&user.username) class="text-base0B hover:underline">{user.username.clone()}</a>
+                                        <span class="text-base03">{user.email.clone().unwrap_or_default()}</span>
+                                        <span class="text-base03">{format!("{} / {} bytes used", user.used_storage_bytes, user.max_storage_bytes)}</span>
+                                        <span class="text-base08">{if banned { "banned" } else { "" }}</span>
+                                        <button class="px-2 border-2 border-base05 rounded-lg" on:click=move |_| on_posts(username.clone()) >"Posts"</button>
+                                    </div>
+                                    <div class="flex flex-wrap gap-2 items-center">
+                                        <select
+                                            class="bg-base02 px-2 rounded-lg disabled:text-base03"
+                                            disabled=move || !can_manage()
+                                            on:change=move |e| on_role(user_key.clone(), UserRole::from_db(event_target_value(&e)))
+                                        >
+                                            {UserRole::iter().map(|role| view! {
+                                                <option value=role.to_string() selected={role == user.role}>{role.to_string()}</option>
+                                            }).collect_view()}
+                                        </select>
+                                        <Show when=can_ban >
+                                            <button
+                                                class=format!("px-2 border-2 rounded-lg {}", if banned { "border-base05" } else { "border-base08 text-base08" })
+                                                on:click={
+                                                    let user_key2 = user_key2.clone();
+                                                    move |_| on_ban(user_key2.clone(), !banned)
+                                                }
+                                            >
+                                                {if banned { "Unban" } else { "Ban" }}
+                                            </button>
+                                        </Show>
+                                        <Show when=can_manage >
+                                            <form class="flex flex-wrap gap-2 items-center" on:submit=on_storage_submit.clone() >
+                                                <label class="text-base03">"max storage"</label>
+                                                <input node_ref=max_storage_input type="number" prop:value=user.max_storage_bytes.to_string() class="bg-base02 px-2 rounded-lg w-[10rem]" />
+                                                <label class="text-base03">"per file"</label>
+                                                <input node_ref=max_storage_per_file_input type="number" prop:value=user.max_storage_per_file_bytes.to_string() class="bg-base02 px-2 rounded-lg w-[10rem]" />
+                                                <input type="submit" value="Save" class="px-2 border-2 border-base05 rounded-lg" />
+                                            </form>
+                                        </Show>
+                                    </div>
+                                </div>
+                            }
+                        }
+                    </For>
+                    <Show when=move || selected_username.with(|v| v.is_some()) && can_hide() >
+                        <h2 class="text-[1.2rem] text-base0F">{move || format!("Posts by {}", selected_username.get().unwrap_or_default())}</h2>
+                        <Show when=move || posts.with(|v| v.is_empty()) >
+                            <span class="text-base03">"No posts"</span>
+                        </Show>
+                        <For
+                            each=move || posts.get()
+                            key=|post| (post.key.clone(), post.show)
+                            let(post)
+                        >
+                            {
+                                let post_key = post.key.clone();
+                                let show = post.show;
+                                view! {
+                                    <div class="flex gap-2 items-center rounded-xl bg-base01 p-1">
+                                        <a href=link_post(&post.user.username, &post.key) class="grow hover:underline">{post.title.clone()}</a>
+                                        <span class="text-base03">{if show { "" } else { "hidden" }}</span>
+                                        <button class="px-2 border-2 border-base05 rounded-lg" on:click=move |_| on_show(post_key.clone(), !show) >
+                                            {if show { "Hide" } else { "Unhide" }}
+                                        </button>
+                                    </div>
+                                }
+                            }
+                        </For>
+                    </Show>
+                    <Show when=move || global_state.can(Permission::ViewAudit) >
+                        <AuditLog />
+                    </Show>
                 </div>
-                <Gallery row_height=250 username=user_username />
             </main>
         }
     }
+
+    #[component]
+    pub fn ReportQueue() -> impl IntoView {
+        let api = ApiWeb::new();
+        let api_resolve = ApiWeb::new();
+        let reports = RwSignal::new(Vec::<UserReport>::new());
+        let err = RwSignal::new(String::new());
+
+        let fetch = move || {
+            api.admin_get_reports(ADMIN_REPORTS_FETCH_COUNT, 0)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Reports(v)) => {
+                            reports.set(v);
+                        }
+                        Ok(res) => {
+                            error!("expected Reports, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        Effect::new(move || {
+            fetch();
+        });
+
+        let on_resolve = move |report_key: String, action: ReportAction, reason: String| {
+            api_resolve
+                .admin_resolve_report(report_key, action, reason)
+                .send_web(move |result| async move {
+                    match result {
+                        Ok(ServerRes::Ok) => {
+                            err.set(String::new());
+                            fetch();
+                        }
+                        Ok(res) => {
+                            error!("expected Ok, received {res:?}");
+                        }
+                        Err(e) => {
+                            err.set(e.to_string());
+                        }
+                    }
+                });
+        };
+
+        view! {
+            <h2 class="text-[1.2rem] text-base0F">"Reports"</h2>
+            <Show when=move || err.with(|v| !v.is_empty()) >
+                <span class="text-base08">{move || err.get()}</span>
+            </Show>
+            <Show when=move || reports.with(|v| v.is_empty()) >
+                <span class="text-base03">"No open reports"</span>
+            </Show>
+            <For
+                each=move || reports.get()
+                key=|report| report.key.clone()
+                let(report)
+            >
+                {
+                    let reason_input = NodeRef::<html::Input>::new();
+                    let days_input = NodeRef::<html::Input>::new();
+                    let resolve = move |report_key: String, action: ReportAction| {
+                        let Some(reason_input) = reason_input.get_untracked() else {
+                            return;
+                        };
+                        on_resolve(report_key, action, reason_input.value());
+                    };
+                    let target_link = match (&report.post_username, &report.post_key) {
+                        (Some(post_username), Some(post_key)) => link_post(post_username, post_key),
+                        _ => link_user(&report.target_user.username),
+                    };
+                    let target_text = match &report.target {
+                        ReportTarget::Post { .. } => format!("post by {}", report.target_user.username),
+                        ReportTarget::Comment { .. } => format!("comment by {}", report.target_user.username),
+                        ReportTarget::User { .. } => format!("user {}", report.target_user.username),
+                    };
+                    let is_user = matches!(report.target, ReportTarget::User { .. });
+                    let key1 = report.key.clone();
+                    let key2 = report.key.clone();
+                    let key3 = report.key.clone();
+                    let key4 = report.key.clone();
+                    view! {
+                        <div class="flex flex-col gap-1 rounded-xl bg-base01 p-2">
+                            <div class="flex flex-wrap gap-2 items-center">
+                                <span class="text-base08">{report.reason.to_string().replace("_", " ")}</span>
+                                <a href=target_link class="text-base0B hover:underline">{target_text}</a>
+                                <span class="text-base03">{format!("reported by {}", report.reporter.username)}</span>
+                            </div>
+                            <Show when={
+                                let empty = report.text.is_empty();
+                                move || !empty
+                            }>
+                                <p class="text-base04 break-all">{report.text.clone()}</p>
+                            </Show>
+                            <div class="flex flex-wrap gap-2 items-center">
+                                <input node_ref=reason_input type="text" placeholder="reason" class="grow bg-base02 px-2 rounded-lg" />
+                                <button class="px-2 border-2 border-base05 rounded-lg" on:click=move |_| resolve(key1.clone(), ReportAction::Dismiss) >"Dismiss"</button>
+                                <Show when=move || !is_user >
+                                    <button class="px-2 border-2 border-base05 rounded-lg" on:click={
+                                        let key2 = key2.clone();
+                                        move |_| resolve(key2.clone(), ReportAction::HideContent)
+                                    } >"Hide"</button>
+                                </Show>
+                                <button class="px-2 border-2 border-base05 rounded-lg" on:click=move |_| resolve(key3.clone(), ReportAction::WarnUser) >"Warn"</button>
+                                <input node_ref=days_input type="number" min="1" value="7" class="bg-base02 px-2 rounded-lg w-[4rem]" />
+                                <button
+                                    class="px-2 border-2 border-base08 text-base08 rounded-lg"
+                                    on:click=move |_| {
+                                        let days = days_input
+                                            .get_untracked()
+                                            .and_then(|v| v.value().parse::<u32>().ok())
+                                            .unwrap_or_default();
+                                        resolve(key4.clone(), ReportAction::SuspendUser { days });
+                                    }
+                                >
+                                    "Suspend days"
+                                </button>
+                            </div>
+                        </div>
+                    }
+                }
+            </For>
+        }
+    }
+
+    #[component]
+    pub fn AuditLog() -> impl IntoView {
+        let api = ApiWeb::new();
+        let events = RwSignal::new(Vec::<UserAuditEvent>::new());
+        let err = RwSignal::new(String::new());
+        let username_input = NodeRef::<html::Input>::new();
+        let kind_input = NodeRef::<html::Select>::new();
+
+        let fetch = move |username: Option<String>, kind: Option<AuditKind>| {
+            api.admin_get_audit_events(
+                username,
+                kind,
+                ADMIN_AUDIT_FETCH_COUNT,
+                TimeRange::None,
+                Order::ThreeTwoOne,
+            )
+            .send_web(move |result| async move {
+                match result {
+                    Ok(ServerRes::AuditEvents(v)) => {
+                        err.set(String::new());
+                        events.set(v);
+                    }
+                    Ok(res) => {
+                        error!("expected AuditEvents, received {res:?}");
+                    }
+                    Err(e) => {
+                        err.set(e.to_string());
+                    }
+                }
+            });
+        };
+
+        Effect::new(move || {
+            fetch(None, None);
+        });
+
+        let on_filter = move |e: SubmitEvent| {
+            e.prevent_default();
+            let (Some(username_input), Some(kind_input)) =
+                (username_input.get_untracked(), kind_input.get_untracked())
+            else {
+                return;
+            };
+            let username = Some(username_input.value()).filter(|v| !v.trim().is_empty());
+            let kind = Some(kind_input.value())
+                .filter(|v| !v.is_empty())
+                .map(AuditKind::from_db);
+            fetch(username, kind);
+        };
+
+        view! {
+            <h2 class="text-[1.2rem] text-base0F">"Audit log"</h2>
+            <form class="flex flex-wrap gap-2" on:submit=on_filter >
+                <input node_ref=username_input type="text" placeholder="username" class="grow bg-base01 px-2 rounded-lg" />
+                <select node_ref=kind_input class="bg-base02 px-2 rounded-lg">
+                    <option value="">"any"</option>
+                    {AuditKind::iter().filter(|v| *v != AuditKind::Other).map(|kind| view! {
+                        <option value=kind.to_string()>{kind.label()}</option>
+                    }).collect_view()}
+                </select>
+                <input type="submit" value="Filter" class="px-2 border-2 border-base05 rounded-lg" />
+            </form>
+            <Show when=move || err.with(|v| !v.is_empty()) >
+                <span class="text-base08">{move || err.get()}</span>
+            </Show>
+            <Show when=move || events.with(|v| v.is_empty()) >
+                <span class="text-base03">"No events"</span>
+            </Show>
+            <For
+                each=move || events.get()
+                key=|event| event.key.clone()
+                let(event)
+            >
+                <AuditEventRow event />
+            </For>
+        }
+    }
 }
 pub mod home {
 
+    use crate::api::UserPost;
+    use crate::path::{link_img, link_post, link_user};
     use crate::view::{
-        app::components::{
-            gallery::{Gallery, Img},
-            nav::Nav,
+        app::{
+            GlobalState,
+            components::{
+                gallery::{Gallery, Img},
+                nav::Nav,
+            },
+            hook::use_following_feed::use_following_feed,
         },
         toolbox::prelude::*,
     };
-    use leptos::prelude::*;
+    use leptos::{html, prelude::*};
 
     use tracing::trace;
 
+    pub const FOLLOWING_FEED_FETCH_COUNT: usize = 10;
+
+    #[derive(
+        Debug,
+        Default,
+        Clone,
+        PartialEq,
+        PartialOrd,
+        strum::EnumString,
+        strum::Display,
+        strum::EnumIter,
+        strum::EnumIs,
+    )]
+    #[strum(serialize_all = "lowercase")]
+    pub enum HomeFeed {
+        #[default]
+        All,
+        Following,
+    }
+
     #[component]
     pub fn Page() -> impl IntoView {
         let main_ref = NodeRef::new();
+        let global_state = expect_context::<GlobalState>();
         let fake_imgs = RwSignal::new(Vec::<Img>::new());
+        let feed = RwQuery::<HomeFeed>::new("feed");
+        let is_following_feed = move || {
+            global_state.is_logged_in().unwrap_or_default() && feed.get_or_default().is_following()
+        };
 
         main_ref.use_file_drop(async |_event, data| {
             for file in data.get_files() {
@@ -937,13 +3468,85 @@ pub mod home {
             let imgs = Img::rand_vec(200);
             fake_imgs.set(imgs);
         });
+
+        let tab_style = move |tab: HomeFeed| {
+            let current = if is_following_feed() {
+                HomeFeed::Following
+            } else {
+                HomeFeed::All
+            };
+            if current == tab {
+                "text-base0F border-b-2 border-base0F"
+            } else {
+                "text-base03 hover:text-base05"
+            }
+        };
+
         view! {
-            <main node_ref=main_ref class="grid grid-rows-[auto_1fr] h-screen">
+            <main node_ref=main_ref class="grid grid-rows-[auto_auto_1fr] h-screen">
                 <Nav/>
-                <Gallery row_height=250 />
+                <div class=move || format!("flex gap-4 px-4 text-[1.1rem] {}", if global_state.is_logged_in().unwrap_or_default() { "" } else { "hidden" })>
+                    <button class=move || tab_style(HomeFeed::All) on:click=move |_| feed.clear() >"All"</button>
+                    <button class=move || tab_style(HomeFeed::Following) on:click=move |_| feed.set(HomeFeed::Following) >"Following"</button>
+                </div>
+                <Show when=is_following_feed fallback=|| view! { <Gallery row_height=250 /> } >
+                    <FollowingFeed />
+                </Show>
             </main>
         }
     }
+
+    #[component]
+    pub fn FollowingFeed() -> impl IntoView {
+        let feed_container_ref = NodeRef::<html::Div>::new();
+        let following_feed = use_following_feed(FOLLOWING_FEED_FETCH_COUNT, feed_container_ref);
+
+        view! {
+            <div class="grid grid-rows-[auto_1fr] overflow-hidden">
+                <Show when=move || following_feed.err_fetch.with(|v| !v.is_empty()) >
+                    <ul class="ml-[1rem] text-base08 list-disc">
+                        {move || following_feed.err_fetch.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
+                    </ul>
+                </Show>
+                <div node_ref=feed_container_ref class="flex flex-col gap-4 items-center px-2 py-4 overflow-y-scroll">
+                    <For
+                        each=move || following_feed.data.get()
+                        key=|post| post.key.clone()
+                        let(post)
+                    >
+                        <FollowingFeedPost post />
+                    </For>
+                </div>
+            </div>
+        }
+    }
+
+    #[component]
+    pub fn FollowingFeedPost(post: UserPost) -> impl IntoView {
+        let post_link = link_post(&post.user.username, &post.key);
+        let user_link = link_user(&post.user.username);
+        let img_link = post
+            .file
+            .first()
+            .map(|file| link_img(&file.hash, &file.extension));
+        let ratio = post
+            .file
+            .first()
+            .map(|file| file.width as f64 / file.height.max(1) as f64)
+            .unwrap_or(1.0);
+
+        view! {
+            <div class="flex flex-col gap-2 w-full max-w-[40rem] bg-base01 rounded-xl p-2">
+                <div class="flex justify-between gap-2">
+                    <a href=post_link.clone() class="text-[1.2rem] text-base0F truncate">{post.title.clone()}</a>
+                    <a href=user_link class="text-base03 hover:text-base05 shrink-0">{post.user.username.clone()}</a>
+                </div>
+                <a href=post_link style:aspect-ratio=ratio.to_string() class="w-full grid place-items-center bg-base02 rounded-lg overflow-hidden">
+                    {img_link.map(|img_link| view! { <img class="w-full h-full object-contain" src=img_link /> })}
+                </a>
+            </div>
+        }
+    }
 }
 
 pub mod register {
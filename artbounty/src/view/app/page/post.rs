@@ -1,8 +1,17 @@
 use std::time::Duration;
 
+use crate::api::shared::admin::Permission;
+use crate::api::shared::live::LiveEvent;
 use crate::api::shared::post_comment::UserPostComment;
+use crate::api::shared::rating::PostRating;
+use crate::api::shared::report::ReportTarget;
+use crate::api::shared::revision::RevisionTarget;
+use crate::api::shared::trash::DELETED_COMMENT_TEXT;
+use crate::api::shared::visibility::PostVisibility;
 use crate::api::{Api, ApiWeb, Server404Err, ServerErr};
-use crate::path::{PATH_LOGIN, link_home, link_img, link_user};
+use crate::path::{
+    PATH_LOGIN, link_api_post_add_file, link_collection, link_home, link_img, link_user,
+};
 use crate::valid::MAX_POST_DESCRIPTION_LENGTH;
 use crate::valid::MAX_POST_TAGS_LENGTH;
 use crate::valid::MAX_POST_TITLE_LENGTH;
@@ -11,7 +20,11 @@ use crate::view::app::components::auto_textarea::AutoTextArea;
 use crate::view::app::components::btn_primary::BtnPrimary;
 use crate::view::app::components::btn_secondary::BtnSecondary;
 use crate::view::app::components::errors::Errors;
+use crate::view::app::components::gallery::Gallery;
 use crate::view::app::components::nav::Nav;
+use crate::view::app::components::page_meta::{MetaLoader, PageMetaTags};
+use crate::view::app::components::report_btn::ReportBtn;
+use crate::view::app::components::revision_history::RevisionHistory;
 use crate::view::app::components::svg_star::Star;
 use crate::view::app::hook::api_post::PostApi;
 use crate::view::app::hook::api_post_comments::{
@@ -24,7 +37,9 @@ use crate::view::app::hook::use_infinite_scroll_fn::InfiniteScrollFn;
 use crate::view::app::hook::use_infinite_scroll_virtual::{
     InfiniteStage, use_infinite_scroll_virtual,
 };
+use crate::view::app::hook::use_live_post::{LivePost, use_live_post};
 use crate::view::app::hook::use_mutation::Mutation;
+use crate::view::app::hook::use_post_collections::use_post_collections;
 use crate::view::app::hook::use_post_comment::use_post_comment;
 use crate::view::app::hook::use_post_comments_baisc::CommentsBaisc;
 use crate::view::app::hook::use_post_like::{self, PostLikeStage, use_post_like};
@@ -35,6 +50,7 @@ use leptos::{Params, task::spawn_local};
 use leptos::{ev, html, prelude::*};
 use leptos_router::hooks::{use_location, use_params};
 use leptos_router::params::Params;
+use strum::IntoEnumIterator;
 use tracing::{debug, error, trace, warn};
 use wasm_bindgen::JsValue;
 use web_sys::{
@@ -61,6 +77,16 @@ pub fn Page() -> impl IntoView {
     let param_username = move || param.read().as_ref().ok().and_then(|v| v.username.clone());
     let param_post = Memo::new(move |_| param.read().as_ref().ok().and_then(|v| v.post.clone()));
 
+    let meta_loader = MetaLoader::new();
+    let meta = Resource::new_blocking(move || param_post.get().unwrap_or_default(), {
+        let meta_loader = meta_loader.clone();
+        move |post_key| meta_loader.clone().post(post_key)
+    });
+    let public_post = Resource::new_blocking(
+        move || param_post.get().unwrap_or_default(),
+        move |post_key| meta_loader.clone().public_post(post_key),
+    );
+
     let location = use_location();
 
     let spawner_post = Spawner::new();
@@ -140,7 +166,17 @@ pub fn Page() -> impl IntoView {
     //     // spawner_comments.spawn(comment_basic.observe_only(comment_container_ref.into(), post_id));
     // });
 
+    let live_post = use_live_post(param_post);
+    provide_context(live_post);
+    merge_live_comments(live_post, comment_basic.comments_manual);
+    live_post.on_event(move |event| {
+        if let LiveEvent::PostLikeCount { count, .. } = event {
+            post_api.favorites.set(*count);
+        }
+    });
+
     let post_like = use_post_like(param_post);
+    let save_mode = RwSignal::new(false);
     let post_like_fn = move || {
         post_like.on_like.with_value(|f| {
             (f)();
@@ -394,6 +430,92 @@ pub fn Page() -> impl IntoView {
         spawner_post.spawn(post_api.delete(post_id));
     };
 
+    let can_report = move || {
+        global_state.is_logged_in().unwrap_or_default()
+            && global_state.get_username_tracked() != param_username()
+    };
+    let report_target = Signal::derive(move || {
+        param_post
+            .get()
+            .map(|post_key| ReportTarget::Post { post_key })
+    });
+
+    let toggle_show = move |_| {
+        let Some(post_id) = param_post.get() else {
+            return;
+        };
+        spawner_post.spawn(post_api.update_show(post_id, !post_api.show.get_untracked()));
+    };
+
+    let is_author = move || {
+        global_state.is_logged_in().unwrap_or_default()
+            && global_state.get_username_tracked() == param_username()
+    };
+    let can_moderate_rating = move || global_state.can(Permission::HidePost);
+    let revision_target = Signal::derive(move || {
+        param_post
+            .get()
+            .map(|post_key| RevisionTarget::Post { post_key })
+    });
+    let can_view_revisions = Signal::derive(move || is_author() || can_moderate_rating());
+    let can_edit_rating =
+        move || (is_author() && !post_api.rating_locked.get()) || can_moderate_rating();
+    let on_rating = move |e: Event| {
+        let Some(post_id) = param_post.get_untracked() else {
+            return;
+        };
+        let rating = PostRating::from_db(event_target_value(&e));
+        let moderation = !is_author() || post_api.rating_locked.get_untracked();
+        spawner_post.spawn(post_api.update_rating(post_id, rating, moderation));
+    };
+
+    // scheduling needs a publish time first, so the select only switches into schedule mode
+    let schedule_mode = RwSignal::new(false);
+    let publish_at_input = NodeRef::<html::Input>::new();
+    let on_visibility = move |e: Event| {
+        let Some(post_id) = param_post.get_untracked() else {
+            return;
+        };
+        let visibility = PostVisibility::from_db(event_target_value(&e));
+        schedule_mode.set(visibility.is_scheduled());
+        if visibility.is_scheduled() {
+            return;
+        }
+        spawner_post.spawn(post_api.update_visibility(post_id, visibility, 0));
+    };
+    let on_schedule = move |_| {
+        let Some(post_id) = param_post.get_untracked() else {
+            return;
+        };
+        let Some(publish_at) = publish_at_input
+            .get_untracked()
+            .and_then(|input: HtmlInputElement| datetime_local_to_ns(input.value()))
+        else {
+            post_api.err_general.set("pick a publish time".to_string());
+            return;
+        };
+        schedule_mode.set(false);
+        spawner_post.spawn(post_api.update_visibility(
+            post_id,
+            PostVisibility::Scheduled,
+            publish_at,
+        ));
+    };
+    let toggle_embeddable = move |_| {
+        let Some(post_id) = param_post.get_untracked() else {
+            return;
+        };
+        spawner_post
+            .spawn(post_api.update_embeddable(post_id, !post_api.embeddable.get_untracked()));
+    };
+    let visibility_label = move || match post_api.visibility.get() {
+        PostVisibility::Scheduled => format!(
+            "publishes in {}",
+            ns_to_str(post_api.publish_at.get().saturating_sub(time_now_ns()))
+        ),
+        visibility => visibility.label().to_string(),
+    };
+
     let uploader = FileUpload::new();
     let upload_image = NodeRef::<html::Input>::new();
     let on_upload = move |_| {
@@ -408,304 +530,389 @@ pub fn Page() -> impl IntoView {
             return;
         };
 
-        uploader.upload(&files[..]);
+        let Some(post_key) = param_post.get_untracked() else {
+            return;
+        };
+        uploader.upload(link_api_post_add_file(post_key), &files[..]);
 
         trace!("files selected: {}", files.len());
     };
 
     view! {
+        <PageMetaTags meta />
         <main node_ref=main_ref class="relative font-hi grid grid-rows-[auto_1fr] h-screen text-base05">
             <Nav/>
 
-            <Show when=move|| post_api.post_state.get().is_not_found() >
-                <div class=move || format!("grid place-items-center text-[1.5rem] ")>
-                    "Not Found"
-                </div>
-            </Show>
+            <Suspense>
+                // fills the post before the views below read it, so the server renders the post and not the loading state
+                {move || public_post.get().flatten().map(|post| post_api.set_post(post))}
+                <Show when=move|| post_api.post_state.get().is_not_found() >
+                    <div class=move || format!("grid place-items-center text-[1.5rem] ")>
+                        "Not Found"
+                    </div>
+                </Show>
 
-            <Show when=move|| post_api.post_state.get().is_deleted() >
-                <div class=move || format!("grid place-items-center text-[1.5rem] ")>
-                    "deleted"
-                </div>
-            </Show>
+                <Show when=move|| post_api.post_state.get().is_deleted() >
+                    <div class=move || format!("grid place-items-center text-[1.5rem] ")>
+                        "deleted"
+                    </div>
+                </Show>
 
-            <Show when=move|| {
-                let state = post_api.post_state.get();
-                state.is_normal() || state.is_loading()
-            } >
-                <div class=move || format!("flex flex-col lg:grid grid-cols-[2fr_1fr] grid-cols-[2fr_1fr] lg:max-h-[calc(100vh-3rem)] gap-2  md:gap-6 flex")>
-                    <div class="col-span-2 flex justify-between px-4 md:px-6 ">
-                        <div></div>
-                        <div>
-                            <button on:click=delete_post>
-                                <SVGTrash class="size-[1.1rem] text-base08 "/>
-                            </button>
+                <Show when=move|| {
+                    let state = post_api.post_state.get();
+                    state.is_normal() || state.is_loading()
+                } >
+                    <div class=move || format!("flex flex-col lg:grid grid-cols-[2fr_1fr] grid-cols-[2fr_1fr] lg:max-h-[calc(100vh-3rem)] gap-2  md:gap-6 flex")>
+                        <div class="col-span-2 flex justify-between px-4 md:px-6 ">
+                            <div class="flex gap-2 items-center">
+                                <Show when=move || !post_api.show.get() >
+                                    <span class="text-base08">"hidden by moderation"</span>
+                                </Show>
+                                <Show
+                                    when=can_edit_rating
+                                    fallback=move || view! { <span class="text-base03">{move || post_api.rating.get().label()}</span> }
+                                >
+                                    <select
+                                        class="bg-base02 px-2 rounded-lg"
+                                        prop:value=move || post_api.rating.get().to_string()
+                                        on:change=on_rating
+                                    >
+                                        {PostRating::iter().map(|rating| view! {
+                                            <option value=rating.to_string()>{rating.label()}</option>
+                                        }).collect_view()}
+                                    </select>
+                                </Show>
+                                <Show when=move || post_api.rating_locked.get() >
+                                    <span class="text-base03">"rating locked by moderation"</span>
+                                </Show>
+                                <Show
+                                    when=is_author
+                                    fallback=move || view! {
+                                        <Show when=move || !post_api.visibility.get().is_public() >
+                                            <span class="text-base03">{visibility_label}</span>
+                                        </Show>
+                                    }
+                                >
+                                    <select
+                                        class="bg-base02 px-2 rounded-lg"
+                                        prop:value=move || if schedule_mode.get() { PostVisibility::Scheduled.to_string() } else { post_api.visibility.get().to_string() }
+                                        on:change=on_visibility
+                                    >
+                                        {PostVisibility::iter().map(|visibility| view! {
+                                            <option value=visibility.to_string()>{visibility.label()}</option>
+                                        }).collect_view()}
+                                    </select>
+                                    <Show when=move || schedule_mode.get() >
+                                        <input node_ref=publish_at_input type="datetime-local" class="bg-base02 px-2 rounded-lg" />
+                                        <button on:click=on_schedule class="text-base03 hover:text-base05">"Schedule"</button>
+                                    </Show>
+                                    <Show when=move || !schedule_mode.get() && post_api.visibility.get().is_scheduled() >
+                                        <span class="text-base03">{visibility_label}</span>
+                                    </Show>
+                                    <button on:click=toggle_embeddable class="text-base03 hover:text-base05">
+                                        {move || if post_api.embeddable.get() { "Disable embedding" } else { "Enable embedding" }}
+                                    </button>
+                                </Show>
+                                <Show when=move || post_api.err_general.with(|v| !v.is_empty()) >
+                                    <span class="text-base08">{move || post_api.err_general.get()}</span>
+                                </Show>
+                            </div>
+                            <div class="flex gap-2 items-center">
+                                <Show when=can_report >
+                                    <ReportBtn target=report_target />
+                                </Show>
+                                <Show when=move || global_state.can(Permission::HidePost) >
+                                    <button on:click=toggle_show class="text-base03 hover:text-base05">
+                                        {move || if post_api.show.get() { "Hide" } else { "Unhide" }}
+                                    </button>
+                                </Show>
+                                <button on:click=delete_post>
+                                    <SVGTrash class="size-[1.1rem] text-base08 "/>
+                                </button>
+                            </div>
                         </div>
-                    </div>
-                    <div class="lg:hidden h-[50vh] flex justify-center place-items-center bg-base02" >
-                        { selected_img }
-                    </div>
-                    <div class="hidden lg:flex flex-col gap-2 lg:overflow-y-scroll" >
-                        { imgs }
-                    </div>
-                    <div class="flex flex-col gap-2 md:gap-6 px-4 md:px-6  lg:overflow-y-scroll">
-                        <div class="flex justify-start gap-2 flex flex-wrap">
-                            { previews }
+                        <div class="lg:hidden h-[50vh] flex justify-center place-items-center bg-base02" >
+                            { selected_img }
                         </div>
-
-                        <div>
-                            <input on:change=on_upload type="file" id="image" name="image" node_ref=upload_image multiple />
-                            // <input on:change=on_file_change type="file" id="image" name="image" node_ref=upload_image multiple />
+                        <div class="hidden lg:flex flex-col gap-2 lg:overflow-y-scroll" >
+                            { imgs }
                         </div>
+                        <div class="flex flex-col gap-2 md:gap-6 px-4 md:px-6  lg:overflow-y-scroll">
+                            <div class="flex justify-start gap-2 flex flex-wrap">
+                                { previews }
+                            </div>
+
+                            <div>
+                                <input on:change=on_upload type="file" id="image" name="image" node_ref=upload_image multiple />
+                                // <input on:change=on_file_change type="file" id="image" name="image" node_ref=upload_image multiple />
+                            </div>
 
 
-                        <div class="flex flex-col gap-2">
-                            <Show when=move || global_state.is_logged_in().unwrap_or_default() >
-                                <div class="flex gap-2 place-items-center">
-                                    <Errors
-                                        error=move||post_api.err_title.get()
-                                    />
-                                    <Show when=move|| post_api.update_title_mode.get()>
-                                        <LengthCounter
-                                            class=move || "ml-auto"
-                                            counter_current=move||post_api.live_title_length.get()
-                                            counter_max=move||MAX_POST_TITLE_LENGTH
+                            <div class="flex flex-col gap-2">
+                                <Show when=move || global_state.is_logged_in().unwrap_or_default() >
+                                    <div class="flex gap-2 place-items-center">
+                                        <Errors
+                                            error=move||post_api.err_title.get()
                                         />
-                                    </Show>
-                                    <EditSaveCancel
-                                        id=move || "title"
-                                        class_edit=move || "ml-auto"
-                                        when=move || post_api.update_title_mode.get()
-                                        on_save=move || edit_title_save()
-                                        on_cancel=move || edit_title_mode_toggle()
-                                        on_edit=move || edit_title_mode_toggle()
-                                    />
-                                </div>
-                            </Show>
-                            <div class="flex justify-between">
-                                <Show when=move || !post_api.update_title_mode.get()>
-                                    <h1 class=move || format!("text-[1.5rem] text-ellipsis {}", if title_is_empty() { "text-base03" } else { "text-base0F" })>{ title }</h1>
+                                        <Show when=move|| post_api.update_title_mode.get()>
+                                            <LengthCounter
+                                                class=move || "ml-auto"
+                                                counter_current=move||post_api.live_title_length.get()
+                                                counter_max=move||MAX_POST_TITLE_LENGTH
+                                            />
+                                        </Show>
+                                        <EditSaveCancel
+                                            id=move || "title"
+                                            class_edit=move || "ml-auto"
+                                            when=move || post_api.update_title_mode.get()
+                                            on_save=move || edit_title_save()
+                                            on_cancel=move || edit_title_mode_toggle()
+                                            on_edit=move || edit_title_mode_toggle()
+                                        />
+                                    </div>
                                 </Show>
-                                <Show when=move || post_api.update_title_mode.get()>
-                                    <AutoTextArea
-                                        id=move||"post_description_editable"
-                                        placeholder=move||"title"
-                                        node_ref=title_input_editor
-                                        on_input=move|v:HtmlTextAreaElement| post_api.live_title_length.set(v.value().len())
-                                        min_height=50.0
-                                        class=move||"w-full bg-base01 text-[1.5rem] text-base05 px-4 py-2 rounded-xl"
-                                    >
-                                        {
-                                            move || if title_is_empty() {
-                                                "".to_string()
-                                            } else {
-                                                title()
+                                <div class="flex justify-between">
+                                    <Show when=move || !post_api.update_title_mode.get()>
+                                        <h1 class=move || format!("text-[1.5rem] text-ellipsis {}", if title_is_empty() { "text-base03" } else { "text-base0F" })>{ title }</h1>
+                                    </Show>
+                                    <Show when=move || post_api.update_title_mode.get()>
+                                        <AutoTextArea
+                                            id=move||"post_description_editable"
+                                            placeholder=move||"title"
+                                            node_ref=title_input_editor
+                                            on_input=move|v:HtmlTextAreaElement| post_api.live_title_length.set(v.value().len())
+                                            min_height=50.0
+                                            class=move||"w-full bg-base01 text-[1.5rem] text-base05 px-4 py-2 rounded-xl"
+                                        >
+                                            {
+                                                move || if title_is_empty() {
+                                                    "".to_string()
+                                                } else {
+                                                    title()
+                                                }
                                             }
-                                        }
-                                    </AutoTextArea>
-                                </Show>
+                                        </AutoTextArea>
+                                    </Show>
 
-                                // <BtnSecondary id=move|| "btn_edit_title" on_click=move|_|edit_title_mode_toggle() >
-                                //     "Edit"
-                                // </BtnSecondary>
+                                    // <BtnSecondary id=move|| "btn_edit_title" on_click=move|_|edit_title_mode_toggle() >
+                                    //     "Edit"
+                                    // </BtnSecondary>
 
-                                // <button on:click=post_like.on_like.to_fn() disabled=move || post_like.stage.run() == PostLikeStage::Loading class=post_like_btn_style >{ post_like_btn_text }</button>
-                            </div>
-                            <div class="flex justify-between place-items-start">
-                                <div class="flex gap-2">
-                                    <p class="text-[1rem] rounded-full h-[3rem] w-[3rem] bg-base05"></p>
-                                    <div class="flex flex-col gap-1">
-                                        <div class="flex gap-1">
-                                            <p class="text-[1rem] text-base03">"by"</p>
-                                            <a href=move || post_api.author_link.get() class="text-[1rem] font-bold text-base0B">{ move || post_api.author.get() }</a>
+                                    // <button on:click=post_like.on_like.to_fn() disabled=move || post_like.stage.run() == PostLikeStage::Loading class=post_like_btn_style >{ post_like_btn_text }</button>
+                                </div>
+                                <div class="flex justify-between place-items-start">
+                                    <div class="flex gap-2">
+                                        <p class="text-[1rem] rounded-full h-[3rem] w-[3rem] bg-base05"></p>
+                                        <div class="flex flex-col gap-1">
+                                            <div class="flex gap-1">
+                                                <p class="text-[1rem] text-base03">"by"</p>
+                                                <a href=move || post_api.author_link.get() class="text-[1rem] font-bold text-base0B">{ move || post_api.author.get() }</a>
+                                                <RevisionHistory target=revision_target edited_at=post_api.edited_at can_view=can_view_revisions />
+                                            </div>
+                                            <p class="text-[1rem]">"9999 followers"</p>
                                         </div>
-                                        <p class="text-[1rem]">"9999 followers"</p>
+                                    </div>
+                                    <div class="flex gap-2">
+                                        <Show when=move || global_state.is_logged_in().unwrap_or_default() >
+                                            <BtnSecondary id=move || "btn_save" on_click=move|_| save_mode.update(|v| *v = !*v)>
+                                                "Save"
+                                            </BtnSecondary>
+                                        </Show>
+                                        <BtnSecondary class=move || format!("flex gap-2 place-items-center ") id=move || "btn_favorite" on_click=move|_|post_like_fn()>
+                                            <span class="mt-[0.1rem]">"Favorite"</span>
+                                            <span class="mt-[0.1rem] text-base03">{move || post_api.favorites.get()}</span>
+                                            <Star class=move||"shrink-0 w-[1.5rem] pb-[0.1rem]" fill=move||is_post_liked_fn() />
+                                        </BtnSecondary>
                                     </div>
                                 </div>
-                                // <div>{move || post_api.favorites.get() }" favorites"</div>
-                                <BtnSecondary class=move || format!("flex gap-2 place-items-center ") id=move || "btn_favorite" on_click=move|_|post_like_fn()>
-                                    <span class="mt-[0.1rem]">"Favorite"</span>
-                                    <Star class=move||"shrink-0 w-[1.5rem] pb-[0.1rem]" fill=move||is_post_liked_fn() />
-                                </BtnSecondary>
+                                <Show when=move || save_mode.get() >
+                                    <SaveToCollection post_key=param_post />
+                                </Show>
                             </div>
-                        </div>
-                        // <div>
-                        //     <AutoTextArea/>
-                        // </div>
-                        <div class="flex flex-col gap-2 md:gap-4 justify-between mt-4">
-                            <div class="flex justify-between">
-                                <h1 class="text-[1.3rem] text-base0F">"Description"</h1>
-                                <div class="flex gap-2 items-center">
-
-                                    <Show when=move || global_state.is_logged_in().unwrap_or_default() >
-                                        <Show when=move|| post_api.update_description_mode.get()>
-                                            <LengthCounter
-                                                counter_current=move||post_api.live_description_length.get()
-                                                counter_max=move||MAX_POST_DESCRIPTION_LENGTH
+                            // <div>
+                            //     <AutoTextArea/>
+                            // </div>
+                            <div class="flex flex-col gap-2 md:gap-4 justify-between mt-4">
+                                <div class="flex justify-between">
+                                    <h1 class="text-[1.3rem] text-base0F">"Description"</h1>
+                                    <div class="flex gap-2 items-center">
+
+                                        <Show when=move || global_state.is_logged_in().unwrap_or_default() >
+                                            <Show when=move|| post_api.update_description_mode.get()>
+                                                <LengthCounter
+                                                    counter_current=move||post_api.live_description_length.get()
+                                                    counter_max=move||MAX_POST_DESCRIPTION_LENGTH
+                                                />
+                                            </Show>
+                                            <EditSaveCancel
+                                                id=move || "description"
+                                                when=move || post_api.update_description_mode.get()
+                                                on_save=move || edit_description_save()
+                                                on_cancel=move || edit_description_mode_toggle()
+                                                on_edit=move || edit_description_mode_toggle()
                                             />
                                         </Show>
-                                        <EditSaveCancel
-                                            id=move || "description"
-                                            when=move || post_api.update_description_mode.get()
-                                            on_save=move || edit_description_save()
-                                            on_cancel=move || edit_description_mode_toggle()
-                                            on_edit=move || edit_description_mode_toggle()
-                                        />
-                                    </Show>
+                                    </div>
                                 </div>
-                            </div>
 
-                            <Show when=move || post_api.err_description.with(|v| !v.is_empty()) >
-                                <ul id="description_errors" class="ml-[1rem] text-base08 list-disc">
-                                    {move || post_api.err_description.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
-                                </ul>
-                            </Show>
+                                <Show when=move || post_api.err_description.with(|v| !v.is_empty()) >
+                                    <ul id="description_errors" class="ml-[1rem] text-base08 list-disc">
+                                        {move || post_api.err_description.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
+                                    </ul>
+                                </Show>
 
-                            <Show when=move || post_api.update_description_mode.get() fallback=move || view!{
-                                <pre
-                                    id="post_description"
-                                    class=move || format!("whitespace-break-spaces break-all text-ellipsis overflow-hidden padding max-w-[calc(100vw-1rem)] rounded {}",
-                                        if post_api.description.with(|v| v.is_empty()) { "text-base03" }  else { "" }
+                                <Show when=move || post_api.update_description_mode.get() fallback=move || view!{
+                                    <pre
+                                        id="post_description"
+                                        class=move || format!("whitespace-break-spaces break-all text-ellipsis overflow-hidden padding max-w-[calc(100vw-1rem)] rounded {}",
+                                            if post_api.description.with(|v| v.is_empty()) { "text-base03" }  else { "" }
 
-                                        )>
-                                    { description }
-                                </pre>
-                            }>
-
-                            <AutoTextArea
-                                id=move||"post_description_editable"
-                                node_ref=description_input_editor
-                                on_input=move|v:HtmlTextAreaElement| post_api.live_description_length.set(v.value().len())
-                                class=move||"bg-base01 text-base05 px-4 py-2 rounded"
-                            >
-                                { description }
-                            </AutoTextArea>
-                                // <pre
-                                //     id="post_description_editable"
-                                //     // on:change=edit_description_keydown
-                                //     node_ref=description_input_editor
-                                //     contenteditable=true
-                                //     class="whitespace-break-spaces break-all text-ellipsis overflow-hidden padding max-w-[calc(100vw-1rem)] bg-base01 text-base05 px-4 py-2 rounded">
-                                //     { move || post_api.description.get() }
-                                // </pre>
-                            </Show>
-                        </div>
-                        <div class="flex flex-col gap-2 md:gap-4 justify-between mt-4">
-                            <div class="flex justify-between">
-                                <h1 class="text-[1.3rem] text-base0F">"Tags"</h1>
-                                <div class="flex gap-2 items-center">
+                                            )>
+                                        { description }
+                                    </pre>
+                                }>
 
-                                    <Show when=move || global_state.is_logged_in().unwrap_or_default() >
-                                        <Show when=move|| post_api.update_tags_mode.get()>
-                                            <LengthCounter
-                                                counter_current=move||post_api.live_tags_length.get()
-                                                counter_max=move||MAX_POST_TAGS_LENGTH
+                                <AutoTextArea
+                                    id=move||"post_description_editable"
+                                    node_ref=description_input_editor
+                                    on_input=move|v:HtmlTextAreaElement| post_api.live_description_length.set(v.value().len())
+                                    class=move||"bg-base01 text-base05 px-4 py-2 rounded"
+                                >
+                                    { description }
+                                </AutoTextArea>
+                                    // <pre
+                                    //     id="post_description_editable"
+                                    //     // on:change=edit_description_keydown
+                                    //     node_ref=description_input_editor
+                                    //     contenteditable=true
+                                    //     class="whitespace-break-spaces break-all text-ellipsis overflow-hidden padding max-w-[calc(100vw-1rem)] bg-base01 text-base05 px-4 py-2 rounded">
+                                    //     { move || post_api.description.get() }
+                                    // </pre>
+                                </Show>
+                            </div>
+                            <div class="flex flex-col gap-2 md:gap-4 justify-between mt-4">
+                                <div class="flex justify-between">
+                                    <h1 class="text-[1.3rem] text-base0F">"Tags"</h1>
+                                    <div class="flex gap-2 items-center">
+
+                                        <Show when=move || global_state.is_logged_in().unwrap_or_default() >
+                                            <Show when=move|| post_api.update_tags_mode.get()>
+                                                <LengthCounter
+                                                    counter_current=move||post_api.live_tags_length.get()
+                                                    counter_max=move||MAX_POST_TAGS_LENGTH
+                                                />
+                                            </Show>
+                                            <EditSaveCancel
+                                                id=move || "tags"
+                                                when=move || post_api.update_tags_mode.get()
+                                                on_save=move || edit_tags_save()
+                                                on_cancel=move || edit_tags()
+                                                on_edit=move || edit_tags()
                                             />
                                         </Show>
-                                        <EditSaveCancel
-                                            id=move || "tags"
-                                            when=move || post_api.update_tags_mode.get()
-                                            on_save=move || edit_tags_save()
-                                            on_cancel=move || edit_tags()
-                                            on_edit=move || edit_tags()
-                                        />
-                                    </Show>
 
-                                </div>
+                                    </div>
 
-                            </div>
-                            <Show when=move || post_api.err_tags.with(|v| !v.is_empty()) >
-                                <ul class="ml-[1rem] text-base08 list-disc">
-                                    {move || post_api.err_tags.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
-                                </ul>
-                            </Show>
-                            <div class=move || format!("text-ellipsis flex flex-wrap gap-1 overflow-hidden padding max-w-[calc(100vw-1rem)] {} ",
-                                    if post_api.tags.with(|v| v.is_empty()) {"text-base03"} else {"text-base05"}
-                                )>
+                                </div>
+                                <Show when=move || post_api.err_tags.with(|v| !v.is_empty()) >
+                                    <ul class="ml-[1rem] text-base08 list-disc">
+                                        {move || post_api.err_tags.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
+                                    </ul>
+                                </Show>
+                                <div class=move || format!("text-ellipsis flex flex-wrap gap-1 overflow-hidden padding max-w-[calc(100vw-1rem)] {} ",
+                                        if post_api.tags.with(|v| v.is_empty()) {"text-base03"} else {"text-base05"}
+                                    )>
 
-                                <Show when={move || post_api.update_tags_mode.get() } fallback={move || view!{
-                                    <Show when={move || post_api.tags.with(|v| !v.is_empty()) } fallback={move || "No tags." }>
-                                        { tags }
+                                    <Show when={move || post_api.update_tags_mode.get() } fallback={move || view!{
+                                        <Show when={move || post_api.tags.with(|v| !v.is_empty()) } fallback={move || "No tags." }>
+                                            { tags }
+                                        </Show>
+                                    } }>
+                                        <AutoTextArea
+                                            id=move||"post_tags_editable"
+                                            node_ref=edit_tags_input
+                                            on_input=move|v:HtmlTextAreaElement| post_api.live_tags_length.set(v.value().len())
+                                            class=move||"text-[1.1rem] break-all focus:outline-none! appearance-none border-none resize w-full rounded bg-base01 px-4 py-2"
+                                            min_height=100.0
+                                        >
+                                             {move || post_api.tags.get()}
+                                        </AutoTextArea>
+                                        // <div contenteditable=true
+                                        //      node_ref=edit_tags_input
+                                        //      class={move || format!("  ")}>
+                                        //      {move || post_api.tags.get()}
+                                        // </div>
                                     </Show>
-                                } }>
-                                    <AutoTextArea
-                                        id=move||"post_tags_editable"
-                                        node_ref=edit_tags_input
-                                        on_input=move|v:HtmlTextAreaElement| post_api.live_tags_length.set(v.value().len())
-                                        class=move||"text-[1.1rem] break-all focus:outline-none! appearance-none border-none resize w-full rounded bg-base01 px-4 py-2"
-                                        min_height=100.0
-                                    >
-                                         {move || post_api.tags.get()}
-                                    </AutoTextArea>
-                                    // <div contenteditable=true
-                                    //      node_ref=edit_tags_input
-                                    //      class={move || format!("  ")}>
-                                    //      {move || post_api.tags.get()}
-                                    // </div>
-                                </Show>
-                             </div>
-                        </div>
-                        <div  class="flex flex-col gap-2 md:gap-4 justify-between mt-4 pb-1">
-                            <h1 class="text-[1.3rem] text-base0F ">"Comments"</h1>
-                            <div class=move || format!( "bg-base01 rounded-xl grid place-items-center py-5 px-2 {}", if  global_state.acc_pending() { "" } else { "hidden" })>
-                                <div class="flex flex-col gap-2">
-                                    <div class="text-base03">"loading..."</div>
-                                </div>
+                                 </div>
                             </div>
-                            <div class=move || format!( "bg-base01 rounded-xl grid place-items-center py-5 px-2 {}", if global_state.is_logged_in().unwrap_or_default() || global_state.acc_pending() { "hidden" } else { "" })>
-                                <div class="flex flex-col gap-2">
-                                    <div class="text-base03">"You must login to comment"</div>
-                                    <a class="mx-auto rounded-full font-semibold text-[1rem] font-medium px-[0.8rem] py-[0.2rem] hover:bg-base05 bg-base0D text-base01" href=PATH_LOGIN >"Login"</a>
+                            <div  class="flex flex-col gap-2 md:gap-4 justify-between mt-4 pb-1">
+                                <h1 class="text-[1.3rem] text-base0F ">"Comments"</h1>
+                                <div class=move || format!( "bg-base01 rounded-xl grid place-items-center py-5 px-2 {}", if  global_state.acc_pending() { "" } else { "hidden" })>
+                                    <div class="flex flex-col gap-2">
+                                        <div class="text-base03">"loading..."</div>
+                                    </div>
                                 </div>
-                            </div>
-                            // <form class=move || format!("flex bg-base01 rounded-xl flex-col gap-2 py-2 px-4 {}", if global_state.is_logged_in().unwrap_or_default()  { "" } else { "hidden" }) on:submit=post_comments.on_comment.to_fn() >
-                            <div class=move || format!("flex bg-base01 rounded-xl flex-col gap-1 py-2 px-4 {}", if global_state.is_logged_in().unwrap_or_default()  { "" } else { "hidden" }) >
-                                <textarea placeholder="Comment" node_ref=comment_input_ref class="focus:outline-none! appearance-none border-none resize text-[1.1rem]" id="story" name="story" rows="3" cols="5" ></textarea>
-                                <ul class="text-base08 list-disc ml-[1rem]">
-                                    {move || comment_basic.err_post.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
-                                </ul>
-                                <div class="flex justify-between place-items-center">
-                                    <p class="text-[1rem]">"0/2000"</p>
-                                    <BtnPrimary id=move|_:()|String::new() on_click=move |_| post_comment() class=move || "ml-auto">
-                                        "Post"
-                                    </BtnPrimary>
-                                    // <BtnPrimary on_click=move |_| post_comment()>
-                                    //     "Post" <Star class="size-5 mb-[0.1rem]"/>
-                                    // </BtnPrimary>
+                                <div class=move || format!( "bg-base01 rounded-xl grid place-items-center py-5 px-2 {}", if global_state.is_logged_in().unwrap_or_default() || global_state.acc_pending() { "hidden" } else { "" })>
+                                    <div class="flex flex-col gap-2">
+                                        <div class="text-base03">"You must login to comment"</div>
+                                        <a class="mx-auto rounded-full font-semibold text-[1rem] font-medium px-[0.8rem] py-[0.2rem] hover:bg-base05 bg-base0D text-base01" href=PATH_LOGIN >"Login"</a>
+                                    </div>
+                                </div>
+                                // <form class=move || format!("flex bg-base01 rounded-xl flex-col gap-2 py-2 px-4 {}", if global_state.is_logged_in().unwrap_or_default()  { "" } else { "hidden" }) on:submit=post_comments.on_comment.to_fn() >
+                                <div class=move || format!("flex bg-base01 rounded-xl flex-col gap-1 py-2 px-4 {}", if global_state.is_logged_in().unwrap_or_default()  { "" } else { "hidden" }) >
+                                    <textarea placeholder="Comment" node_ref=comment_input_ref class="focus:outline-none! appearance-none border-none resize text-[1.1rem]" id="story" name="story" rows="3" cols="5" ></textarea>
+                                    <ul class="text-base08 list-disc ml-[1rem]">
+                                        {move || comment_basic.err_post.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
+                                    </ul>
+                                    <div class="flex justify-between place-items-center">
+                                        <p class="text-[1rem]">"0/2000"</p>
+                                        <BtnPrimary id=move|_:()|String::new() on_click=move |_| post_comment() class=move || "ml-auto">
+                                            "Post"
+                                        </BtnPrimary>
+                                        // <BtnPrimary on_click=move |_| post_comment()>
+                                        //     "Post" <Star class="size-5 mb-[0.1rem]"/>
+                                        // </BtnPrimary>
+                                    </div>
                                 </div>
-                            </div>
 
-                            <div class="flex flex-col gap-2">
-                                <div node_ref=comment_container_ref class=" flex flex-col gap-2 relative 0h-[20rem] 0overflow-y-scroll">
-                                    <For
-                                        each=move || comment_basic.items.get()
-                                        key=|state| state.key.clone()
-                                        let(data)
-                                    >
-                                        {
-                                            view!{
-                                                <PostCommentElm
-                                                    parent_key=String::new()
-                                                    parent_items=comment_basic.items
-                                                    parent_reply_count=comment_basic.replies_count
-                                                    comment=data
-                                                    param_post
-                                                    max_depth=2
-                                                    parent_depth=0 />
-                                            }.into_any()
-                                        }
-                                    </For>
+                                <div class="flex flex-col gap-2">
+                                    <div node_ref=comment_container_ref class=" flex flex-col gap-2 relative 0h-[20rem] 0overflow-y-scroll">
+                                        <For
+                                            each=move || comment_basic.items.get()
+                                            key=|state| state.key.clone()
+                                            let(data)
+                                        >
+                                            {
+                                                view!{
+                                                    <PostCommentElm
+                                                        parent_key=String::new()
+                                                        parent_items=comment_basic.items
+                                                        parent_reply_count=comment_basic.replies_count
+                                                        comment=data
+                                                        param_post
+                                                        max_depth=2
+                                                        parent_depth=0 />
+                                                }.into_any()
+                                            }
+                                        </For>
+                                    </div>
+                                    <Show when=move || comment_basic.comments_manual.err_fetch.with(|v| !v.is_empty()) >
+                                        <ul class="ml-[1rem] text-base08 list-disc">
+                                            {move || comment_basic.comments_manual.err_fetch.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
+                                        </ul>
+                                    </Show>
                                 </div>
-                                <Show when=move || comment_basic.comments_manual.err_fetch.with(|v| !v.is_empty()) >
-                                    <ul class="ml-[1rem] text-base08 list-disc">
-                                        {move || comment_basic.comments_manual.err_fetch.get().trim().split("\n").filter(|v| v.len() > 1).map(|v| v.to_string()).map(move |v: String| view! { <li>{v}</li> }).collect_view() }
-                                    </ul>
-                                </Show>
+                            </div>
+                        </div>
+                        <div class="col-span-2 flex flex-col gap-2 px-2 md:px-6 pb-6">
+                            <h1 class="text-[1.3rem] text-base0F ">"More like this"</h1>
+                            <div class="grid h-[30rem]">
+                                <Gallery row_height=200 related_to=param_post />
                             </div>
                         </div>
                     </div>
-                </div>
-            </Show>
+                </Show>
+            </Suspense>
 
 
             // TODO probably change 1fr to fixed size or auto or minmax bs
@@ -713,6 +920,86 @@ pub fn Page() -> impl IntoView {
     }
 }
 
+fn merge_live_comments<API: Api + Copy + 'static>(
+    live_post: LivePost,
+    comments: CommentsApi2<API>,
+) {
+    let global_state = expect_context::<GlobalState>();
+    live_post.on_event(move |event| {
+        // comments posted from this account are already merged by the post response
+        if let LiveEvent::CommentAdded(comment) = event {
+            if global_state.get_acc_id_untracked().as_ref() == Some(&comment.user.key) {
+                return;
+            }
+        }
+        comments.merge_live(event);
+    });
+}
+
+#[component]
+pub fn SaveToCollection(post_key: Memo<Option<String>>) -> impl IntoView {
+    let post_collections = use_post_collections(post_key);
+    let title_input = NodeRef::<html::Input>::new();
+    let private_input = NodeRef::<html::Input>::new();
+
+    let on_create = move |e: SubmitEvent| {
+        e.prevent_default();
+        let (Some(title_input), Some(private_input)) =
+            (title_input.get_untracked(), private_input.get_untracked())
+        else {
+            return;
+        };
+        let title = title_input.value();
+        if title.trim().is_empty() {
+            return;
+        }
+        let private = private_input.checked();
+        post_collections
+            .on_create
+            .with_value(|f| (f)(title, private));
+        title_input.set_value("");
+    };
+
+    view! {
+        <div class="flex flex-col gap-2 rounded-xl bg-base01 p-2">
+            <For
+                each=move || post_collections.collections.get()
+                key=|collection| (collection.key.clone(), collection.title.clone(), collection.items_count)
+                let(collection)
+            >
+                {
+                    let collection_key = collection.key.clone();
+                    let collection_key2 = collection.key.clone();
+                    let is_saved = move || post_collections.saved.with(|v| v.contains(&collection_key));
+                    view! {
+                        <div class="flex gap-2 items-center">
+                            <input
+                                type="checkbox"
+                                prop:checked=is_saved
+                                on:change=move |_| post_collections.on_toggle.with_value(|f| (f)(collection_key2.clone()))
+                            />
+                            <a href=link_collection(&collection.key) class="hover:underline">{collection.title.clone()}</a>
+                            <span class="text-base03">{collection.items_count}</span>
+                            <span class="text-base03">{if collection.private { "private" } else { "" }}</span>
+                        </div>
+                    }
+                }
+            </For>
+            <form class="flex gap-2 items-center" on:submit=on_create >
+                <input node_ref=title_input type="text" placeholder="New collection" class="bg-base00 px-2 rounded-lg" />
+                <label class="flex gap-1 items-center text-base03">
+                    <input node_ref=private_input type="checkbox" />
+                    "private"
+                </label>
+                <input type="submit" value="Create" class="px-2 border-2 border-base05 rounded-lg" />
+            </form>
+            <Show when=move || post_collections.err.with(|v| !v.is_empty()) >
+                <span class="text-base08">{move || post_collections.err.get()}</span>
+            </Show>
+        </div>
+    }
+}
+
 #[component]
 pub fn LengthCounter(
     #[prop(optional, into)] class: Option<Callback<(), String>>,
@@ -883,6 +1170,9 @@ pub fn PostCommentElm(
     };
 
     let comments_manual = CommentsApi2::new(api, 10, kind.clone());
+    if let Some(live_post) = use_context::<LivePost>() {
+        merge_live_comments(live_post, comments_manual);
+    }
     let post_comment = move |_| {
         let Some(input_elm) = comment_input_ref.get() else {
             return;
@@ -900,6 +1190,37 @@ pub fn PostCommentElm(
     let delete_comment = move |_| {
         spawner.spawn(comments_manual.delete());
     };
+    let delete_comment_moderated = move |_| {
+        spawner.spawn(comments_manual.delete_moderated());
+    };
+    let can_moderate_fn = {
+        let is_owned_fn = is_owned_fn.clone();
+        move || !is_owned_fn() && global_state.can(Permission::DeleteComment)
+    };
+    let can_report_fn = {
+        let is_owned_fn = is_owned_fn.clone();
+        move || !is_owned_fn() && global_state.is_logged_in().unwrap_or_default()
+    };
+    let report_target = {
+        let comment_key = comment_key.clone();
+        Signal::derive(move || {
+            Some(ReportTarget::Comment {
+                comment_key: comment_key.clone(),
+            })
+        })
+    };
+    let revision_target = {
+        let comment_key = comment_key.clone();
+        Signal::derive(move || {
+            Some(RevisionTarget::Comment {
+                comment_key: comment_key.clone(),
+            })
+        })
+    };
+    let can_view_revisions = {
+        let is_owned_fn = is_owned_fn.clone();
+        Signal::derive(move || is_owned_fn() || global_state.can(Permission::DeleteComment))
+    };
     let fetch_comments = move || {
         spawner.spawn(comments_manual.fetch());
     };
@@ -1051,8 +1372,9 @@ pub fn PostCommentElm(
                         <div class="flex gap-2 place-items-center ">
                             <div class="text-[1.2rem]"> {comment.user.username} </div>
                             <div class="text-[1rem] text-base03"> {move || ns_to_str(global_state.get_time_ns().saturating_sub(comment.created_at))}" ago"</div>
+                            <RevisionHistory target=revision_target edited_at=comments_manual.edited_at can_view=can_view_revisions />
 
-                            <Show when={move || is_owned_fn() || comments_manual.edit_mode.get()} >
+                            <Show when={move || (is_owned_fn() && comments_manual.deleted_at.get() == 0) || comments_manual.edit_mode.get()} >
                                 <div class=move || format!(" gap-2 ml-auto place-items-center {}", if comments_manual.edit_mode.get() {"flex"} else {"group-hover:flex hidden"} )>
                                     <button on:click=click_edit class=move || format!("text-center   rounded-full font-semibold text-[0.8rem] font-medium px-[0.8rem] w-[4rem]  {}", if comments_manual.edit_mode.get() { " hover:bg-base05 bg-base0D text-base01" } else { " text-base05 bg-base01 hover:bg-base05 hover:text-base01" })>
                                         <Show when={move || comments_manual.edit_mode.get() } fallback={move || "Edit" }>
@@ -1071,14 +1393,27 @@ pub fn PostCommentElm(
                                     </Show>
                                 </div>
                             </Show>
+                            <Show when={
+                                let can_report_fn = can_report_fn.clone();
+                                move || can_report_fn() && comments_manual.deleted_at.get() == 0
+                            } >
+                                <div class="group-hover:flex hidden gap-2 ml-auto place-items-center text-[0.8rem]">
+                                    <ReportBtn target=report_target />
+                                    <Show when=can_moderate_fn.clone() >
+                                        <button on:click=delete_comment_moderated class="" title="delete as moderator">
+                                            <SVGTrash class="size-[1.1rem] text-base08 "/>
+                                        </button>
+                                    </Show>
+                                </div>
+                            </Show>
                         </div>
 
 
                         <div contenteditable={move || comments_manual.edit_mode.get()}
                              node_ref=comment_edit_ref
-                             class={move || format!(" text-[1.1rem] break-all focus:outline-none! appearance-none border-none resize w-full rounded {}", if comments_manual.edit_mode.get() { "bg-base01 px-4 py-2" } else { "" })} >
+                             class={move || format!(" text-[1.1rem] break-all focus:outline-none! appearance-none border-none resize w-full rounded {} {}", if comments_manual.edit_mode.get() { "bg-base01 px-4 py-2" } else { "" }, if comments_manual.deleted_at.get() > 0 { "text-base03 italic" } else { "" })} >
                             {
-                                move || comments_manual.text.get()
+                                move || if comments_manual.deleted_at.get() > 0 { DELETED_COMMENT_TEXT.to_string() } else { comments_manual.text.get() }
                             }
                         </div>
                         <Show when=move || comments_manual.err_update.with(|v| !v.is_empty()) >
@@ -1115,7 +1450,7 @@ pub fn PostCommentElm(
                                     </button>
                                 </Show>
                             </Show>
-                            <Show when=move || global_state.is_logged_in().unwrap_or_default()>
+                            <Show when=move || global_state.is_logged_in().unwrap_or_default() && comments_manual.deleted_at.get() == 0>
                                 <button on:click=toggle_btn type="submit" class=move || format!("  rounded-full font-semibold text-[0.8rem] font-medium px-[0.8rem] w-[4rem]  {}", if comments_manual.show_editor.get() { "text-base05 bg-base01 hover:bg-base03" } else { "text-base05 bg-base01 hover:bg-base05 hover:text-base01" })>
                                     <Show when=move || comments_manual.show_editor.get() fallback=|| "Reply">
                                         <SVGArrowDown class="size-4 mx-auto"/>
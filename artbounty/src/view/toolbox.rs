@@ -15,7 +15,7 @@ pub mod prelude {
     pub use super::random::{random_u8, random_u32, random_u32_ranged, random_u64};
     pub use super::resize_observer::{self, AddResizeObserver, GetContentBoxSize};
     pub use super::rw_signal_tree::RwSignalTree;
-    pub use super::time::{ns_to_str, time_now_ms, time_now_ns};
+    pub use super::time::{datetime_local_to_ns, ns_to_str, time_now_ms, time_now_ns};
     pub use super::timeout::{SetTimeoutError, set_timeout};
 
     #[cfg(feature = "testing")]
@@ -456,6 +456,15 @@ pub mod time {
         }
     }
 
+    /// parses the value of a `datetime-local` input, which is in the browser's timezone
+    pub fn datetime_local_to_ns(value: impl AsRef<str>) -> Option<u128> {
+        let time = js_sys::Date::new(&wasm_bindgen::JsValue::from_str(value.as_ref())).get_time();
+        if time.is_nan() || time < 0.0 {
+            return None;
+        }
+        Some(time as u128 * 1000000)
+    }
+
     pub fn ns_to_str(ns: u128) -> String {
         let mut output = String::new();
 